@@ -0,0 +1,347 @@
+//! Lump-sum client adjustments spread over the shots
+//!
+//! A negotiated "take $50k off" lowers shot prices so the per-shot numbers
+//! still add up to the bid total. The split is worked out in whole cents
+//! with the largest-remainder method: the shares sum to the amount exactly,
+//! and the same bid always splits the same way, ties going to the earlier
+//! shot. Each adjustment keeps the share it gave every shot, in
+//! `BidMeta.adjustments`, so totals and exports list it as its own line and
+//! `remove_adjustment` can take it back out to the cent.
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::bid::{ShotData, ShotGroup};
+use crate::error::AppError;
+
+/// Shots an adjustment is spread over
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdjustmentScope {
+    AllShots,
+    Group { name: String },
+    ShotIds { ids: Vec<String> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMethod {
+    /// In proportion to each shot's final price
+    ProRata,
+    /// The same amount on every shot
+    Even,
+}
+
+/// What one adjustment added to one shot's final price
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShotShare {
+    pub shot_id: String,
+    /// Whole cents; negative for a discount
+    pub amount: f64,
+}
+
+/// An applied adjustment, as kept in the bid metadata
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LumpAdjustment {
+    pub id: String,
+    /// e.g. "Negotiated discount"
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Negative for a discount
+    pub amount: f64,
+    pub scope: AdjustmentScope,
+    pub method: SplitMethod,
+    pub shares: Vec<ShotShare>,
+    /// RFC 3339
+    pub applied_at: String,
+}
+
+impl LumpAdjustment {
+    /// Name for totals and exports
+    pub fn display_label(&self) -> &str {
+        match &self.label {
+            Some(label) if !label.trim().is_empty() => label,
+            _ if self.amount < 0.0 => "Discount",
+            _ => "Adjustment",
+        }
+    }
+}
+
+/// An adjustment's line in the bid totals
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct AdjustmentLine {
+    pub id: String,
+    pub label: String,
+    pub amount: f64,
+}
+
+impl From<&LumpAdjustment> for AdjustmentLine {
+    fn from(adjustment: &LumpAdjustment) -> Self {
+        Self {
+            id: adjustment.id.clone(),
+            label: adjustment.display_label().to_string(),
+            amount: adjustment.amount,
+        }
+    }
+}
+
+/// Change to one shot made by an adjustment
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShotAdjustmentDelta {
+    pub shot_id: String,
+    pub old_price: f64,
+    pub new_price: f64,
+}
+
+/// Outcome of `apply_lump_adjustment`, returned whether or not it was applied
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdjustmentReport {
+    pub adjustment: LumpAdjustment,
+    pub previous_total: f64,
+    pub new_total: f64,
+    pub deltas: Vec<ShotAdjustmentDelta>,
+    /// Shots in scope that took no share: locked or unpriced
+    pub excluded: Vec<String>,
+    pub dry_run: bool,
+}
+
+pub fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+fn from_cents(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// Split `total` cents in proportion to `weights`, by largest remainder
+///
+/// The shares always sum to `total`. Leftover cents go to the largest
+/// fractional parts, the earliest weight first on ties; a negative total is
+/// split as its magnitude and negated.
+pub fn split_cents(total: i64, weights: &[i64]) -> Result<Vec<i64>, AppError> {
+    let weight_sum: i128 = weights.iter().map(|&w| i128::from(w.max(0))).sum();
+    if weight_sum == 0 {
+        return Err(AppError::validation("Nothing to split the adjustment over: every shot in scope is priced at zero"));
+    }
+
+    let magnitude = i128::from(total).abs();
+    let quotas: Vec<(i128, i128)> = weights
+        .iter()
+        .map(|&w| {
+            let scaled = magnitude * i128::from(w.max(0));
+            (scaled / weight_sum, scaled % weight_sum)
+        })
+        .collect();
+
+    let mut shares: Vec<i128> = quotas.iter().map(|&(whole, _)| whole).collect();
+    let leftover = magnitude - shares.iter().sum::<i128>();
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by(|&a, &b| quotas[b].1.cmp(&quotas[a].1).then(a.cmp(&b)));
+    for &i in order.iter().take(leftover as usize) {
+        shares[i] += 1;
+    }
+
+    let sign = total.signum() as i128;
+    Ok(shares.into_iter().map(|share| (share * sign) as i64).collect())
+}
+
+/// Work out `adjustment`'s shares and the shots they give
+///
+/// `adjustment.shares` is filled in; the rest of it is kept. Locked and
+/// unpriced shots in scope are left alone and the others take their part.
+pub fn plan_adjustment(
+    shots: &[ShotData],
+    groups: &[ShotGroup],
+    mut adjustment: LumpAdjustment,
+) -> Result<(Vec<ShotData>, AdjustmentReport), AppError> {
+    let cents = if adjustment.amount.is_finite() { to_cents(adjustment.amount) } else { 0 };
+    if cents == 0 {
+        return Err(AppError::validation(format!("Adjustment must be at least a cent, got {}", adjustment.amount)));
+    }
+    adjustment.amount = from_cents(cents);
+
+    let in_scope: Vec<usize> = match &adjustment.scope {
+        AdjustmentScope::AllShots => (0..shots.len()).collect(),
+        AdjustmentScope::Group { name } => {
+            let group = groups
+                .iter()
+                .find(|g| g.name == *name)
+                .ok_or_else(|| AppError::not_found(format!("Group '{}' not found", name)))?;
+            shots.iter().enumerate().filter(|(_, s)| group.shot_ids.contains(&s.id)).map(|(i, _)| i).collect()
+        }
+        AdjustmentScope::ShotIds { ids } => {
+            if let Some(missing) = ids.iter().find(|id| !shots.iter().any(|s| s.id == **id)) {
+                return Err(AppError::not_found(format!("Shot {} not found", missing)));
+            }
+            shots.iter().enumerate().filter(|(_, s)| ids.contains(&s.id)).map(|(i, _)| i).collect()
+        }
+    };
+
+    let (adjustable, excluded): (Vec<usize>, Vec<usize>) = in_scope
+        .into_iter()
+        .partition(|&i| !shots[i].price_locked && shots[i].final_price.is_some());
+    if adjustable.is_empty() {
+        return Err(AppError::validation("No unlocked, priced shots in scope to adjust"));
+    }
+
+    let weights: Vec<i64> = match adjustment.method {
+        SplitMethod::ProRata => adjustable.iter().map(|&i| to_cents(shots[i].final_price.unwrap_or(0.0))).collect(),
+        SplitMethod::Even => vec![1; adjustable.len()],
+    };
+    let shares = split_cents(cents, &weights)?;
+
+    let mut adjusted = shots.to_vec();
+    let mut deltas = Vec::with_capacity(adjustable.len());
+    adjustment.shares.clear();
+    for (&i, &share) in adjustable.iter().zip(&shares) {
+        let old_price = shots[i].final_price.unwrap_or(0.0);
+        if to_cents(old_price) + share < 0 {
+            return Err(AppError::validation(format!(
+                "The adjustment would take shot {} below zero ({:.2})",
+                shots[i].id,
+                from_cents(to_cents(old_price) + share)
+            )));
+        }
+        adjusted[i].add_adjustment(from_cents(share));
+        let new_price = adjusted[i].final_price.unwrap_or(0.0);
+        deltas.push(ShotAdjustmentDelta { shot_id: shots[i].id.clone(), old_price, new_price });
+        adjustment.shares.push(ShotShare { shot_id: shots[i].id.clone(), amount: from_cents(share) });
+    }
+
+    let price = |shot: &ShotData| shot.final_price.unwrap_or(0.0);
+    let report = AdjustmentReport {
+        previous_total: shots.iter().map(price).sum(),
+        new_total: adjusted.iter().map(price).sum(),
+        deltas,
+        excluded: excluded.iter().map(|&i| shots[i].id.clone()).collect(),
+        adjustment,
+        dry_run: false,
+    };
+    Ok((adjusted, report))
+}
+
+/// Take `adjustment`'s shares back out of whichever of its shots are in `shots`
+pub fn reverse_adjustment<'a>(shots: impl IntoIterator<Item = &'a mut ShotData>, adjustment: &LumpAdjustment) {
+    for shot in shots {
+        if let Some(share) = adjustment.shares.iter().find(|share| share.shot_id == shot.id) {
+            shot.add_adjustment(-share.amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, price: f64) -> ShotData {
+        ShotData { id: id.to_string(), final_price: Some(price), ..Default::default() }
+    }
+
+    fn adjustment(amount: f64, scope: AdjustmentScope, method: SplitMethod) -> LumpAdjustment {
+        LumpAdjustment {
+            id: "adj-1".to_string(),
+            label: None,
+            amount,
+            scope,
+            method,
+            shares: Vec::new(),
+            applied_at: "2026-10-18T10:00:00Z".to_string(),
+        }
+    }
+
+    fn total_cents(shots: &[ShotData]) -> i64 {
+        shots.iter().filter_map(|s| s.final_price).map(to_cents).sum()
+    }
+
+    #[test]
+    fn test_split_is_penny_exact() {
+        assert_eq!(split_cents(100, &[1, 1, 1]).unwrap(), [34, 33, 33]);
+        assert_eq!(split_cents(-100, &[1, 1, 1]).unwrap(), [-34, -33, -33]);
+        // Largest remainder, not first come: 10 * 2/7 = 2.86 gets the spare cent
+        assert_eq!(split_cents(10, &[1, 2, 4]).unwrap(), [1, 3, 6]);
+        assert_eq!(split_cents(0, &[5, 5]).unwrap(), [0, 0]);
+        assert!(split_cents(100, &[0, 0]).is_err());
+
+        let weights = [123_457, 98_765_432, 1, 55_555, 7, 31_415_926];
+        for total in [1, 2, 5, 99, 5_000_000, -5_000_000, -1, 123_456_789, i64::from(i32::MAX)] {
+            let shares = split_cents(total, &weights).unwrap();
+            assert_eq!(shares.iter().sum::<i64>(), total, "split of {}", total);
+            let weight_sum: i64 = weights.iter().sum();
+            for (share, weight) in shares.iter().zip(weights) {
+                let exact = total as f64 * weight as f64 / weight_sum as f64;
+                assert!((*share as f64 - exact).abs() < 1.0, "{} is off its quota {}", share, exact);
+            }
+            assert_eq!(split_cents(total, &weights).unwrap(), shares, "not deterministic");
+        }
+    }
+
+    #[test]
+    fn test_pro_rata_discount_lands_on_the_cent() {
+        let shots = vec![shot("SH010", 33_333.33), shot("SH020", 66_666.67), shot("SH030", 12_345.68)];
+        let before = total_cents(&shots);
+
+        let (adjusted, report) = plan_adjustment(&shots, &[], adjustment(-50_000.0, AdjustmentScope::AllShots, SplitMethod::ProRata)).unwrap();
+        assert_eq!(total_cents(&adjusted), before - 5_000_000);
+        assert_eq!(report.adjustment.shares.iter().map(|s| to_cents(s.amount)).sum::<i64>(), -5_000_000);
+        assert!((report.new_total - (report.previous_total - 50_000.0)).abs() < 0.005);
+        // Every shot keeps its share of the price, to the cent
+        for (original, after) in shots.iter().zip(&adjusted) {
+            let share = to_cents(after.final_price.unwrap()) - to_cents(original.final_price.unwrap());
+            let exact = -5_000_000.0 * to_cents(original.final_price.unwrap()) as f64 / before as f64;
+            assert!((share as f64 - exact).abs() < 1.0);
+            assert_eq!(to_cents(after.adjustment), share);
+        }
+
+        // Even splits with a remainder stay exact too
+        let (adjusted, report) = plan_adjustment(&shots, &[], adjustment(1000.0, AdjustmentScope::AllShots, SplitMethod::Even)).unwrap();
+        let shares: Vec<f64> = report.adjustment.shares.iter().map(|s| s.amount).collect();
+        assert_eq!(shares, [333.34, 333.33, 333.33]);
+        assert_eq!(total_cents(&adjusted), before + 100_000);
+    }
+
+    #[test]
+    fn test_locked_and_unpriced_shots_are_excluded() {
+        let mut locked = shot("SH020", 5000.0);
+        locked.price_locked = true;
+        let unpriced = ShotData { id: "SH040".to_string(), ..Default::default() };
+        let shots = vec![shot("SH010", 1000.0), locked, shot("SH030", 3000.0), unpriced];
+        let groups = vec![ShotGroup { name: "Lobby".to_string(), shot_ids: vec!["SH010".to_string(), "SH020".to_string()], discount_percent: None }];
+
+        let (adjusted, report) = plan_adjustment(&shots, &groups, adjustment(-400.0, AdjustmentScope::AllShots, SplitMethod::ProRata)).unwrap();
+        assert_eq!(report.excluded, ["SH020", "SH040"]);
+        // The locked shot's part goes to the others
+        assert_eq!(adjusted[0].final_price, Some(900.0));
+        assert_eq!(adjusted[1].final_price, Some(5000.0));
+        assert_eq!(adjusted[2].final_price, Some(2700.0));
+        assert_eq!(adjusted[3].final_price, None);
+
+        let (adjusted, _) = plan_adjustment(&shots, &groups, adjustment(-10.0, AdjustmentScope::Group { name: "Lobby".to_string() }, SplitMethod::Even)).unwrap();
+        assert_eq!(adjusted[0].final_price, Some(990.0));
+        assert_eq!(adjusted[2].final_price, Some(3000.0));
+
+        let only_locked = AdjustmentScope::ShotIds { ids: vec!["SH020".to_string()] };
+        assert!(plan_adjustment(&shots, &groups, adjustment(-10.0, only_locked, SplitMethod::Even)).is_err());
+        let unknown = AdjustmentScope::ShotIds { ids: vec!["SH999".to_string()] };
+        assert!(matches!(plan_adjustment(&shots, &groups, adjustment(-10.0, unknown, SplitMethod::Even)), Err(AppError::NotFound { .. })));
+        assert!(plan_adjustment(&shots, &groups, adjustment(-10.0, AdjustmentScope::Group { name: "Roof".to_string() }, SplitMethod::Even)).is_err());
+        assert!(plan_adjustment(&shots, &groups, adjustment(0.004, AdjustmentScope::AllShots, SplitMethod::Even)).is_err());
+    }
+
+    #[test]
+    fn test_no_shot_goes_below_zero() {
+        let shots = vec![shot("SH010", 100.0), shot("SH020", 10_000.0)];
+        let error = plan_adjustment(&shots, &[], adjustment(-500.0, AdjustmentScope::AllShots, SplitMethod::Even)).unwrap_err();
+        assert!(error.to_string().contains("SH010"));
+        assert!(plan_adjustment(&shots, &[], adjustment(-500.0, AdjustmentScope::AllShots, SplitMethod::ProRata)).is_ok());
+    }
+
+    #[test]
+    fn test_reversal_restores_every_price() {
+        let shots = vec![shot("SH010", 1234.56), shot("SH020", 789.01), shot("SH030", 4321.99)];
+        let (mut adjusted, report) = plan_adjustment(&shots, &[], adjustment(-777.77, AdjustmentScope::AllShots, SplitMethod::ProRata)).unwrap();
+        reverse_adjustment(&mut adjusted, &report.adjustment);
+        for (original, restored) in shots.iter().zip(&adjusted) {
+            assert_eq!(to_cents(restored.final_price.unwrap()), to_cents(original.final_price.unwrap()));
+            assert_eq!(to_cents(restored.adjustment), 0);
+        }
+    }
+}
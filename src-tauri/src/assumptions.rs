@@ -0,0 +1,271 @@
+//! Assumptions and exclusions that go with a bid
+//!
+//! Each bid keeps its own list in `BidMeta`. A library in the config dir
+//! holds boilerplate items ("Plates delivered as EXR") to insert by id.
+//! Items not marked for export stay with the bidder: the Excel export and
+//! the summary leave them out, and the CSV export carries none at all.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::tempfiles;
+
+static LIBRARY_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssumptionCategory {
+    #[default]
+    Assumption,
+    Exclusion,
+    Note,
+}
+
+impl AssumptionCategory {
+    pub const ALL: [AssumptionCategory; 3] = [Self::Assumption, Self::Exclusion, Self::Note];
+
+    /// Heading for a section of these
+    pub fn heading(self) -> &'static str {
+        match self {
+            Self::Assumption => "Assumptions",
+            Self::Exclusion => "Exclusions",
+            Self::Note => "Notes",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AssumptionItem {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub category: AssumptionCategory,
+    /// Shown to the client in the Excel export and the summary
+    #[serde(default = "exported")]
+    pub included_in_export: bool,
+}
+
+fn exported() -> bool {
+    true
+}
+
+impl AssumptionItem {
+    pub fn new(text: impl Into<String>, category: AssumptionCategory) -> Self {
+        Self {
+            id: new_id(),
+            text: text.into(),
+            category,
+            included_in_export: true,
+        }
+    }
+}
+
+fn new_id() -> String {
+    format!("asm-{}", &uuid::Uuid::new_v4().simple().to_string()[..12])
+}
+
+/// Serde `deserialize_with` for `BidMeta::assumptions`
+///
+/// Bids saved before the list kept free text; each of its lines becomes an
+/// assumption.
+pub fn deserialize_assumptions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<AssumptionItem>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Items(Vec<AssumptionItem>),
+        Text(Option<String>),
+    }
+
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Items(items) => items,
+        Stored::Text(text) => from_text(text.as_deref().unwrap_or_default()),
+    })
+}
+
+/// One assumption per non-empty line, without list bullets
+fn from_text(text: &str) -> Vec<AssumptionItem> {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', '•']).trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| AssumptionItem::new(line, AssumptionCategory::Assumption))
+        .collect()
+}
+
+/// Error unless `text` has something in it; returns it trimmed
+pub fn validate_text(text: &str) -> Result<String, String> {
+    match text.trim() {
+        "" => Err("Assumption text is empty".to_string()),
+        text => Ok(text.to_string()),
+    }
+}
+
+/// `items` in the order of `ids`, which must name each of them once
+pub fn reorder(items: &[AssumptionItem], ids: &[String]) -> Result<Vec<AssumptionItem>, String> {
+    if ids.len() != items.len() {
+        return Err(format!("Expected {} assumption ids, got {}", items.len(), ids.len()));
+    }
+    let mut reordered = Vec::with_capacity(items.len());
+    for id in ids {
+        let item = items.iter().find(|item| &item.id == id).ok_or_else(|| format!("Assumption {} not found", id))?;
+        if reordered.iter().any(|placed: &AssumptionItem| &placed.id == id) {
+            return Err(format!("Assumption {} is listed twice", id));
+        }
+        reordered.push(item.clone());
+    }
+    Ok(reordered)
+}
+
+/// The items the client sees, in order
+pub fn exported_items(items: &[AssumptionItem]) -> Vec<AssumptionItem> {
+    items.iter().filter(|item| item.included_in_export).cloned().collect()
+}
+
+/// A boilerplate item in the library
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LibraryItem {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub category: AssumptionCategory,
+}
+
+impl LibraryItem {
+    /// A bid item with this text, under a new id
+    pub fn to_item(&self) -> AssumptionItem {
+        AssumptionItem::new(self.text.clone(), self.category)
+    }
+}
+
+/// Contents of `assumption_library.json`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct AssumptionLibrary {
+    pub items: Vec<LibraryItem>,
+}
+
+impl AssumptionLibrary {
+    /// Starting boilerplate, used until the library is first saved
+    pub fn builtin() -> Self {
+        use AssumptionCategory::*;
+        let item = |id: &str, text: &str, category| LibraryItem { id: id.to_string(), text: text.to_string(), category };
+        Self {
+            items: vec![
+                item("plates-exr", "Plates are delivered as EXR with lens grids and camera reports.", Assumption),
+                item("one-revision-round", "Prices include one round of client revisions per shot.", Assumption),
+                item("locked-cut", "Shot count and frame ranges are based on the locked cut provided.", Assumption),
+                item("no-stereo", "Stereo conversion is excluded.", Exclusion),
+                item("no-plate-prep", "Conform, plate prep and scanning are excluded.", Exclusion),
+                item("no-music-rights", "Licensing of stock footage or third-party assets is excluded.", Exclusion),
+                item("validity", "Prices are valid for the period stated on the bid.", Note),
+            ],
+        }
+    }
+
+    pub fn find(&self, id: &str) -> Option<&LibraryItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    /// Add `item`, replacing the one with its id; an empty id gets a new one
+    pub fn save(&mut self, mut item: LibraryItem) -> Result<LibraryItem, String> {
+        item.text = validate_text(&item.text)?;
+        if item.id.trim().is_empty() {
+            item.id = new_id();
+        }
+        match self.items.iter_mut().find(|existing| existing.id == item.id) {
+            Some(existing) => *existing = item.clone(),
+            None => self.items.push(item.clone()),
+        }
+        Ok(item)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let before = self.items.len();
+        self.items.retain(|item| item.id != id);
+        if self.items.len() == before {
+            return Err(format!("No library item '{}'", id));
+        }
+        Ok(())
+    }
+}
+
+/// Keep the library in `path`; called once at startup
+pub fn init(path: PathBuf) {
+    let _ = LIBRARY_PATH.set(path);
+}
+
+pub fn library_path() -> Option<&'static Path> {
+    LIBRARY_PATH.get().map(PathBuf::as_path)
+}
+
+/// Read the library at `path`; a missing file is the built-in one
+pub fn load_library(path: &Path) -> Result<AssumptionLibrary, String> {
+    if !path.exists() {
+        return Ok(AssumptionLibrary::builtin());
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read the assumption library: {}", e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse the assumption library: {}", e))
+}
+
+pub fn write_library(path: &Path, library: &AssumptionLibrary) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(library)
+        .map_err(|e| format!("Failed to serialize the assumption library: {}", e))?;
+    tempfiles::write_atomic(path, &json).map_err(|e| format!("Failed to save the assumption library: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::BidMeta;
+
+    #[test]
+    fn test_free_text_assumptions_load_as_items() {
+        let meta: BidMeta = serde_json::from_value(serde_json::json!({
+            "default_contingency": 15.0,
+            "default_overhead": 10.0,
+            "currency": "USD",
+            "client_name": null,
+            "project_name": null,
+            "assumptions": "- Plates delivered as EXR.\n\n  * One round of revisions.  ",
+        }))
+        .unwrap();
+        let texts: Vec<&str> = meta.assumptions.iter().map(|a| a.text.as_str()).collect();
+        assert_eq!(texts, vec!["Plates delivered as EXR.", "One round of revisions."]);
+        assert!(meta.assumptions.iter().all(|a| a.category == AssumptionCategory::Assumption && a.included_in_export));
+
+        let saved = serde_json::to_value(&meta).unwrap();
+        let reloaded: BidMeta = serde_json::from_value(saved).unwrap();
+        assert_eq!(reloaded.assumptions, meta.assumptions);
+
+        let empty: BidMeta = serde_json::from_value(serde_json::json!({
+            "default_contingency": 15.0, "default_overhead": 10.0, "currency": "USD",
+            "client_name": null, "project_name": null, "assumptions": null,
+        }))
+        .unwrap();
+        assert!(empty.assumptions.is_empty());
+    }
+
+    #[test]
+    fn test_reorder_and_library() {
+        let items = vec![
+            AssumptionItem::new("A", AssumptionCategory::Assumption),
+            AssumptionItem::new("B", AssumptionCategory::Exclusion),
+        ];
+        let ids = |order: [usize; 2]| order.iter().map(|i| items[*i].id.clone()).collect::<Vec<_>>();
+        let reordered = reorder(&items, &ids([1, 0])).unwrap();
+        assert_eq!(reordered.iter().map(|a| a.text.as_str()).collect::<Vec<_>>(), vec!["B", "A"]);
+        assert!(reorder(&items, &ids([0, 0])).unwrap_err().contains("twice"));
+        assert!(reorder(&items, &ids([0, 1])[..1]).is_err());
+
+        let mut library = AssumptionLibrary::builtin();
+        let inserted = library.find("no-stereo").unwrap().to_item();
+        assert_eq!(inserted.category, AssumptionCategory::Exclusion);
+        assert_ne!(inserted.id, "no-stereo");
+
+        let saved = library.save(LibraryItem { id: String::new(), text: " Roto is outsourced. ".to_string(), category: AssumptionCategory::Note }).unwrap();
+        assert_eq!(library.find(&saved.id).unwrap().text, "Roto is outsourced.");
+        assert!(library.save(LibraryItem { id: "x".to_string(), text: "  ".to_string(), category: AssumptionCategory::Note }).is_err());
+        library.remove("no-stereo").unwrap();
+        assert!(library.remove("no-stereo").is_err());
+    }
+}
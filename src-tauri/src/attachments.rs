@@ -0,0 +1,291 @@
+//! Reference images attached to shots
+//!
+//! Files are copied into `app_data_dir()/attachments/<bid-id>/<shot-id>/` so
+//! the bid keeps working if the originals are moved. Only the metadata lives
+//! on the shot itself. The bid id is the one saved in the project file (and
+//! remembered for a reloaded workbook), so a bid finds its files again when
+//! it is reopened; saving the project prunes files no shot refers to.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use imagesize::ImageType;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::bid::ShotData;
+use crate::error::AppError;
+use crate::tempfiles;
+
+/// Largest image accepted as an attachment
+pub const MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Metadata for an image attached to a shot
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+    pub added_at: String,
+}
+
+impl Attachment {
+    /// Name of the stored copy inside the shot's attachment directory
+    pub fn stored_name(&self) -> String {
+        format!("{}-{}", sanitize(&self.id), sanitize(&self.filename))
+    }
+}
+
+/// Root directory for all attachments, `app_data_dir()/attachments`
+pub fn attachments_root(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    use tauri::Manager;
+
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("attachments"))
+        .map_err(|e| AppError::io(format!("Failed to resolve app data directory: {}", e)))
+}
+
+/// Directory holding the attachments of one shot
+pub fn shot_dir(root: &Path, bid_id: &str, shot_id: &str) -> PathBuf {
+    root.join(sanitize(bid_id)).join(sanitize(shot_id))
+}
+
+/// Location of a stored attachment
+pub fn attachment_path(root: &Path, bid_id: &str, shot_id: &str, attachment: &Attachment) -> PathBuf {
+    shot_dir(root, bid_id, shot_id).join(attachment.stored_name())
+}
+
+/// Validate an image and copy it into the shot's attachment directory
+///
+/// The file type is checked by its magic bytes rather than its extension.
+/// Returns the new attachment and the path of the stored copy.
+pub fn store_attachment(
+    root: &Path,
+    bid_id: &str,
+    shot_id: &str,
+    source: &Path,
+) -> Result<(Attachment, PathBuf), AppError> {
+    let bytes = read_limited(source)?;
+    let (width, height) = image_dimensions(&bytes)?;
+
+    let filename = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("image")
+        .to_string();
+
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        filename,
+        width,
+        height,
+        added_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let dir = shot_dir(root, bid_id, shot_id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create attachment directory: {}", e)))?;
+
+    let path = dir.join(attachment.stored_name());
+    tempfiles::write_atomic(&path, &bytes)
+        .map_err(|e| AppError::io(format!("Failed to store attachment: {}", e)))?;
+
+    Ok((attachment, path))
+}
+
+/// Delete a stored attachment, removing the shot directory once it is empty
+pub fn remove_attachment(root: &Path, bid_id: &str, shot_id: &str, attachment: &Attachment) -> Result<(), AppError> {
+    let path = attachment_path(root, bid_id, shot_id, attachment);
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| AppError::io(format!("Failed to remove attachment: {}", e)))?;
+    }
+
+    // Leaves the directory alone if other attachments remain
+    let _ = std::fs::remove_dir(shot_dir(root, bid_id, shot_id));
+    Ok(())
+}
+
+/// Delete every attachment belonging to a bid
+pub fn remove_bid_attachments(root: &Path, bid_id: &str) -> Result<(), AppError> {
+    let dir = root.join(sanitize(bid_id));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .map_err(|e| AppError::io(format!("Failed to remove attachments: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Delete files stored for bid `bid_id` that none of `shots` refers to
+///
+/// Picks up copies left behind by shots deleted for good, attachments
+/// dropped on import or a save that crashed halfway. Shot directories left
+/// empty go too. Returns how many files were removed.
+pub fn remove_orphans<'a>(root: &Path, bid_id: &str, shots: impl IntoIterator<Item = &'a ShotData>) -> Result<usize, AppError> {
+    let bid_dir = root.join(sanitize(bid_id));
+    let Ok(shot_dirs) = std::fs::read_dir(&bid_dir) else {
+        return Ok(0);
+    };
+
+    let referenced: HashSet<PathBuf> = shots
+        .into_iter()
+        .flat_map(|shot| shot.attachments.iter().map(|a| attachment_path(root, bid_id, &shot.id, a)))
+        .collect();
+
+    let mut removed = 0;
+    for shot_dir in shot_dirs.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()) {
+        let files = std::fs::read_dir(&shot_dir)
+            .map_err(|e| AppError::io(format!("Failed to read {}: {}", shot_dir.display(), e)))?;
+        for file in files.flatten().map(|entry| entry.path()) {
+            if file.is_file() && !referenced.contains(&file) {
+                std::fs::remove_file(&file)
+                    .map_err(|e| AppError::io(format!("Failed to remove orphaned attachment: {}", e)))?;
+                removed += 1;
+            }
+        }
+        // Leaves the directory alone if other attachments remain
+        let _ = std::fs::remove_dir(&shot_dir);
+    }
+    let _ = std::fs::remove_dir(&bid_dir);
+    Ok(removed)
+}
+
+/// Read a file, refusing anything over `MAX_ATTACHMENT_BYTES`
+pub fn read_limited(path: &Path) -> Result<Vec<u8>, AppError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| AppError::not_found(format!("Failed to open {}: {}", path.display(), e)))?;
+
+    let mut bytes = Vec::new();
+    file.take(MAX_ATTACHMENT_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AppError::io(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    if bytes.len() as u64 > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::validation(format!(
+            "Attachments are limited to {} MB",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        )));
+    }
+
+    Ok(bytes)
+}
+
+/// Check the image type and read its dimensions
+///
+/// Only formats the webview can display are accepted.
+pub fn image_dimensions(bytes: &[u8]) -> Result<(u32, u32), AppError> {
+    let unsupported = || AppError::validation("Unsupported image type; use PNG, JPEG, GIF or WebP");
+
+    match imagesize::image_type(bytes).map_err(|_| unsupported())? {
+        ImageType::Png | ImageType::Jpeg | ImageType::Gif | ImageType::Webp => {}
+        _ => return Err(unsupported()),
+    }
+
+    let size = imagesize::blob_size(bytes)
+        .map_err(|e| AppError::validation(format!("Failed to read image dimensions: {}", e)))?;
+
+    Ok((size.width as u32, size.height as u32))
+}
+
+/// Make an id safe to use as a single path component
+pub(crate) fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+
+    match cleaned.trim_matches('.') {
+        "" => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest valid PNG header for a `width` x `height` image
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        bytes.extend_from_slice(&13u32.to_be_bytes());
+        bytes.extend_from_slice(b"IHDR");
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_detects_type_by_magic_bytes() {
+        assert_eq!(image_dimensions(&png(640, 360)).unwrap(), (640, 360));
+        assert!(image_dimensions(b"%PDF-1.7 not an image").is_err());
+        assert!(image_dimensions(b"BM\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_store_and_remove() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let root = dir.join("attachments");
+
+        // Extension doesn't matter, only the contents
+        let source = dir.join("board 01.dat");
+        std::fs::write(&source, png(1920, 1080)).unwrap();
+
+        let (attachment, path) = store_attachment(&root, "bid-1", "SH/010", &source).unwrap();
+        assert_eq!(attachment.filename, "board 01.dat");
+        assert_eq!((attachment.width, attachment.height), (1920, 1080));
+        assert_eq!(path, attachment_path(&root, "bid-1", "SH/010", &attachment));
+        assert!(path.starts_with(root.join("bid-1").join("SH_010")));
+        assert!(path.exists());
+
+        remove_attachment(&root, "bid-1", "SH/010", &attachment).unwrap();
+        assert!(!path.exists());
+        assert!(!shot_dir(&root, "bid-1", "SH/010").exists());
+    }
+
+    #[test]
+    fn test_rejects_oversized_and_non_images() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let root = dir.join("attachments");
+
+        let text = dir.join("notes.png");
+        std::fs::write(&text, "not really a png").unwrap();
+        assert!(store_attachment(&root, "bid", "SH010", &text).is_err());
+
+        let mut large = png(10, 10);
+        large.resize(MAX_ATTACHMENT_BYTES as usize + 1, 0);
+        let big = dir.join("big.png");
+        std::fs::write(&big, large).unwrap();
+        assert!(store_attachment(&root, "bid", "SH010", &big).unwrap_err().message().contains("10 MB"));
+
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_orphans_are_removed() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let root = dir.join("attachments");
+        let source = dir.join("board.png");
+        std::fs::write(&source, png(64, 32)).unwrap();
+
+        let (kept, kept_path) = store_attachment(&root, "bid-1", "SH010", &source).unwrap();
+        let (_, dropped_path) = store_attachment(&root, "bid-1", "SH010", &source).unwrap();
+        let (_, deleted_shot_path) = store_attachment(&root, "bid-1", "SH020", &source).unwrap();
+        let (_, other_bid_path) = store_attachment(&root, "bid-2", "SH010", &source).unwrap();
+
+        let shot = ShotData { id: "SH010".to_string(), attachments: vec![kept], ..Default::default() };
+        assert_eq!(remove_orphans(&root, "bid-1", [&shot]).unwrap(), 2);
+
+        assert!(kept_path.exists());
+        assert!(!dropped_path.exists());
+        assert!(!shot_dir(&root, "bid-1", "SH020").exists());
+        assert!(!deleted_shot_path.exists());
+        assert!(other_bid_path.exists());
+
+        assert_eq!(remove_orphans(&root, "missing", [&shot]).unwrap(), 0);
+    }
+}
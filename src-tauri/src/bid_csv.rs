@@ -0,0 +1,414 @@
+//! CSV export and import of the shot list
+//!
+//! Numbers are written plainly (`1234.5`) unless the locale settings ask for
+//! locale formatting, in which case comma-decimal locales also get `;` as the
+//! delimiter, as Excel expects there. Final prices are written with the
+//! bid's presentation rounding.
+//!
+//! Shots with a department breakdown can be written one row per task; such
+//! files read back into the same tasks.
+//!
+//! Columns follow the active export mapping, so a CSV has the same columns
+//! in the same order as the Excel export.
+
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::commands::bid::{normalize_tags, PriceRounding, ShotData, TaskLine};
+use crate::commands::settings::LocaleSettings;
+use crate::excel::{is_totals_label, split_types, Column};
+use crate::export_mapping::{ExportField, ExportMapping};
+use crate::format::{format_number, parse_locale_number};
+
+/// Extra columns when writing one row per task
+const TASK_HEADERS: [&str; 3] = ["Department", "Task Rate", "Task Note"];
+
+/// Department cell of the row that totals an itemized shot
+const SUBTOTAL_LABEL: &str = "Subtotal";
+
+/// Layout of an exported CSV
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvRows {
+    /// One row per shot
+    #[default]
+    Shots,
+    /// One row per task; shots without tasks keep a single row
+    Tasks,
+    /// As `Tasks`, with a subtotal row after each itemized shot
+    TasksWithSubtotals,
+}
+
+/// Write the shots to `path` as CSV, in the columns `mapping` places
+///
+/// Tags are for the bidding team, so they are only written with
+/// `include_tags`: in their mapped column, or last if the mapping has none.
+pub fn write_bid_csv(
+    path: &Path,
+    shots: &[ShotData],
+    locale: &LocaleSettings,
+    rounding: PriceRounding,
+    rows: CsvRows,
+    mapping: &ExportMapping,
+    include_tags: bool,
+) -> Result<(), String> {
+    let delimiter = if locale.export_locale_numbers && locale.decimal_separator == "," { b';' } else { b',' };
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .map_err(|e| format!("Failed to create CSV file: {}", e))?;
+
+    let number = |value: Option<f64>| match value {
+        Some(value) if locale.export_locale_numbers => format_number(value, 2, locale),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    let frame = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    let mut fields: Vec<(ExportField, &str)> = mapping
+        .ordered_columns()
+        .into_iter()
+        .filter(|c| include_tags || c.field != ExportField::Tags)
+        .map(|c| (c.field, c.header()))
+        .collect();
+    if include_tags && !fields.iter().any(|(field, _)| *field == ExportField::Tags) {
+        fields.push((ExportField::Tags, ExportField::Tags.default_header()));
+    }
+
+    // Shot columns with the given hours, cost and price
+    let shot_row = |shot: &ShotData, hours: Option<f64>, cost: Option<f64>, price: Option<f64>| -> Vec<String> {
+        fields
+            .iter()
+            .map(|(field, _)| match field {
+                ExportField::Id => shot.id.clone(),
+                ExportField::SceneNumber => shot.scene_number.clone(),
+                ExportField::Description => shot.description.clone(),
+                ExportField::VfxTypes => shot.vfx_types.join("; "),
+                ExportField::Complexity => shot.complexity.clone(),
+                ExportField::EstimatedHours => number(hours),
+                ExportField::RatePerHour => number(shot.rate_per_hour),
+                ExportField::EstimatedCost => number(cost),
+                ExportField::ContingencyPercent => number(Some(shot.contingency_percent)),
+                ExportField::OverheadPercent => number(Some(shot.overhead_percent)),
+                ExportField::FinalPrice => number(price.map(|price| rounding.apply(price))),
+                ExportField::FrameIn => frame(shot.frame_in),
+                ExportField::FrameOut => frame(shot.frame_out),
+                ExportField::FrameCount => frame(shot.frame_count),
+                ExportField::Fps => number(shot.fps),
+                ExportField::Tags => shot.tags.join("; "),
+            })
+            .collect()
+    };
+
+    let mut records: Vec<Vec<String>> = Vec::new();
+    for shot in shots {
+        let totals = shot_row(shot, shot.estimated_hours, shot.estimated_cost, shot.final_price);
+        if rows == CsvRows::Shots {
+            records.push(totals);
+            continue;
+        }
+        if shot.tasks.is_empty() {
+            records.push([totals, vec![String::new(); TASK_HEADERS.len()]].concat());
+            continue;
+        }
+
+        let markup = 1.0 + (shot.contingency_percent + shot.overhead_percent) / 100.0;
+        for task in &shot.tasks {
+            let cost = task.cost(shot.rate_per_hour);
+            let row = shot_row(shot, Some(task.hours), cost, cost.map(|c| c * markup));
+            records.push([row, vec![task.department.clone(), number(task.rate_override), task.note.clone()]].concat());
+        }
+        if rows == CsvRows::TasksWithSubtotals {
+            records.push([totals, vec![SUBTOTAL_LABEL.to_string(), String::new(), String::new()]].concat());
+        }
+    }
+
+    let mut headers: Vec<&str> = fields.iter().map(|(_, header)| *header).collect();
+    if rows != CsvRows::Shots {
+        headers.extend(TASK_HEADERS);
+    }
+    writer.write_record(headers).map_err(|e| format!("Failed to write CSV: {}", e))?;
+    for record in records {
+        writer.write_record(record).map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+
+    writer.flush().map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+/// Read shots from a CSV file written by us or edited in a spreadsheet
+///
+/// The delimiter is detected from the header row. `locale` resolves numbers
+/// such as `1.234` whose separator could be either kind.
+pub fn read_bid_csv(path: &Path, locale: &LocaleSettings) -> Result<Vec<ShotData>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read CSV file: {}", e))?;
+    let contents = contents.trim_start_matches('\u{feff}');
+
+    let header_line = contents.lines().next().unwrap_or_default();
+    let delimiter = [b';', b'\t', b',']
+        .into_iter()
+        .max_by_key(|d| header_line.matches(*d as char).count())
+        .unwrap_or(b',');
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let columns: HashMap<Column, usize> = reader
+        .headers()
+        .map_err(|e| format!("Failed to read CSV header: {}", e))?
+        .iter()
+        .enumerate()
+        .filter_map(|(i, header)| Column::from_header(header).map(|column| (column, i)))
+        .collect();
+
+    if !columns.contains_key(&Column::Id) && !columns.contains_key(&Column::Description) {
+        return Err("CSV has no shot ID or description column".to_string());
+    }
+
+    let mut shots: Vec<ShotData> = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read CSV row: {}", e))?;
+
+        let text = |column| {
+            columns
+                .get(&column)
+                .and_then(|&i| record.get(i))
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+        };
+        let number = |column| text(column).and_then(|t| parse_locale_number(&t, locale));
+
+        let id = text(Column::Id);
+        let description = text(Column::Description);
+
+        if id.is_none() && description.is_none() {
+            continue;
+        }
+        if record.iter().any(|t| is_totals_label(t.trim())) {
+            continue;
+        }
+
+        // Task rows after the first add a line to the shot above
+        let task = text(Column::Department).map(|department| TaskLine {
+            department,
+            hours: number(Column::Hours).unwrap_or(0.0),
+            rate_override: number(Column::TaskRate),
+            note: text(Column::TaskNote).unwrap_or_default(),
+        });
+        if let (Some(task), Some(previous)) = (&task, shots.last_mut()) {
+            if id.as_ref() == Some(&previous.id) && !previous.tasks.is_empty() {
+                previous.tasks.push(task.clone());
+                continue;
+            }
+        }
+
+        let id = id.unwrap_or_else(|| format!("shot-{}", shots.len() + 1));
+        let tags = text(Column::Tags)
+            .map(|t| normalize_tags(&split_types(&t)))
+            .transpose()
+            .map_err(|e| format!("Shot {}: {}", id, e))?
+            .unwrap_or_default();
+
+        let mut shot = ShotData {
+            id,
+            scene_number: text(Column::Scene).unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            vfx_types: text(Column::VfxTypes).map(|t| split_types(&t)).unwrap_or_default(),
+            complexity: text(Column::Complexity).unwrap_or_default(),
+            estimated_hours: number(Column::Hours),
+            rate_per_hour: number(Column::Rate),
+            estimated_cost: number(Column::Cost),
+            contingency_percent: number(Column::Contingency).unwrap_or(0.0),
+            overhead_percent: number(Column::Overhead).unwrap_or(0.0),
+            final_price: number(Column::FinalPrice),
+            frame_in: number(Column::FrameIn).map(|n| n as u32),
+            frame_out: number(Column::FrameOut).map(|n| n as u32),
+            frame_count: number(Column::FrameCount).map(|n| n as u32),
+            fps: number(Column::Fps),
+            tasks: task.into_iter().collect(),
+            tags,
+            ..Default::default()
+        };
+        shot.sync_frame_count();
+        shots.push(shot);
+    }
+
+    for shot in shots.iter_mut().filter(|s| !s.tasks.is_empty()) {
+        shot.recalculate_pricing();
+    }
+
+    Ok(shots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::MAX_TAG_LENGTH;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.csv", name, uuid::Uuid::new_v4()))
+    }
+
+    fn sample() -> ShotData {
+        let mut shot = ShotData {
+            id: "SH010".to_string(),
+            scene_number: "1".to_string(),
+            description: "Sky replacement, wide".to_string(),
+            vfx_types: vec!["Compositing".to_string(), "Matte Painting".to_string()],
+            complexity: "Medium".to_string(),
+            estimated_hours: Some(1250.5),
+            rate_per_hour: Some(100.0),
+            contingency_percent: 15.0,
+            overhead_percent: 10.0,
+            frame_in: Some(1001),
+            frame_out: Some(1096),
+            ..Default::default()
+        };
+        shot.recalculate_pricing();
+        shot.sync_frame_count();
+        shot
+    }
+
+    #[test]
+    fn test_machine_readable_round_trip() {
+        let path = temp_path("plain");
+        let mut locale = LocaleSettings::for_locale("de-DE");
+        locale.export_locale_numbers = false;
+
+        write_bid_csv(&path, &[sample()], &locale, PriceRounding::None, CsvRows::Shots, &ExportMapping::default(), false).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains(",1250.5,"));
+
+        // Plain files use `.` decimals, so they are read with the en-US convention
+        let shots = read_bid_csv(&path, &LocaleSettings::default()).unwrap();
+        assert_eq!(shots, vec![sample()]);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_locale_numbers_round_trip() {
+        let path = temp_path("german");
+        let mut locale = LocaleSettings::for_locale("de-DE");
+        locale.export_locale_numbers = true;
+
+        write_bid_csv(&path, &[sample()], &locale, PriceRounding::None, CsvRows::Shots, &ExportMapping::default(), false).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("Shot ID;Scene;"));
+        assert!(written.contains(";1.250,50;"));
+
+        let shots = read_bid_csv(&path, &locale).unwrap();
+        assert_eq!(shots[0].estimated_hours, Some(1250.5));
+        assert_eq!(shots[0].final_price, sample().final_price);
+        assert_eq!(shots[0].frame_count, Some(96));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_export_rounds_final_price_only() {
+        let path = temp_path("rounded");
+        write_bid_csv(&path, &[sample()], &LocaleSettings::default(), PriceRounding::Nearest100, CsvRows::Shots, &ExportMapping::default(), false).unwrap();
+
+        // 125,050 cost with 25% markups is 156,312.50
+        let shots = read_bid_csv(&path, &LocaleSettings::default()).unwrap();
+        assert_eq!(shots[0].final_price, Some(156300.0));
+        assert_eq!(shots[0].estimated_cost, sample().estimated_cost);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_tags_only_with_flag() {
+        let path = temp_path("tags");
+        let tagged = vec![ShotData { tags: vec!["awaiting-plate".to_string(), "phase-2".to_string()], ..sample() }];
+
+        write_bid_csv(&path, &tagged, &LocaleSettings::default(), PriceRounding::None, CsvRows::Shots, &ExportMapping::default(), false).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("awaiting-plate"));
+
+        write_bid_csv(&path, &tagged, &LocaleSettings::default(), PriceRounding::None, CsvRows::Shots, &ExportMapping::default(), true).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().lines().next().unwrap().ends_with(",FPS,Tags"));
+        let shots = read_bid_csv(&path, &LocaleSettings::default()).unwrap();
+        assert_eq!(shots[0].tags, vec!["awaiting-plate", "phase-2"]);
+
+        // One overlong tag fails the import instead of dropping the shot's tags
+        let long = ShotData { tags: vec!["phase-2".to_string(), "x".repeat(MAX_TAG_LENGTH + 1)], ..sample() };
+        write_bid_csv(&path, &[long], &LocaleSettings::default(), PriceRounding::None, CsvRows::Shots, &ExportMapping::default(), true).unwrap();
+        assert!(read_bid_csv(&path, &LocaleSettings::default()).unwrap_err().contains("longer than"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_mapping_picks_and_orders_columns() {
+        let path = temp_path("mapped");
+        let mut mapping = crate::export_mapping::client_summary_preset();
+        // Price moves to the front
+        mapping.columns.iter_mut().for_each(|c| match c.field {
+            ExportField::FinalPrice => c.column = "A".to_string(),
+            _ => c.column = format!("{}", (c.column.as_bytes()[0] + 1) as char),
+        });
+
+        write_bid_csv(&path, &[sample()], &LocaleSettings::default(), PriceRounding::None, CsvRows::Shots, &mapping, false).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().next().unwrap(), "Price,Shot,Scene,Description,Work,Frames");
+        assert!(!written.contains("1250.5"));
+
+        let shots = read_bid_csv(&path, &LocaleSettings::default()).unwrap();
+        assert_eq!(shots[0].final_price, sample().final_price);
+        assert_eq!(shots[0].vfx_types, sample().vfx_types);
+        assert_eq!(shots[0].estimated_hours, None);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_task_rows_round_trip() {
+        let path = temp_path("tasks");
+        let mut itemized = sample();
+        itemized.tasks = vec![
+            TaskLine { department: "roto".to_string(), hours: 8.0, ..Default::default() },
+            TaskLine { department: "comp".to_string(), hours: 16.0, rate_override: Some(150.0), note: "Hero".to_string() },
+        ];
+        itemized.recalculate_pricing();
+        let flat = ShotData { id: "SH020".to_string(), ..sample() };
+
+        let shots = vec![itemized, flat];
+        write_bid_csv(&path, &shots, &LocaleSettings::default(), PriceRounding::None, CsvRows::TasksWithSubtotals, &ExportMapping::default(), false).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.lines().next().unwrap().ends_with(",Department,Task Rate,Task Note"));
+        // Two task rows, the subtotal and the flat shot
+        assert_eq!(written.lines().count(), 5);
+        assert!(written.lines().nth(3).unwrap().contains(",Subtotal,"));
+
+        assert_eq!(read_bid_csv(&path, &LocaleSettings::default()).unwrap(), shots);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_import_accepts_us_numbers_with_german_locale() {
+        let path = temp_path("mixed");
+        std::fs::write(
+            &path,
+            "Shot ID,Description,Est. Hours,Rate/Hr,Final Price\n\
+             SH010,Explosion,\"1,234.56\",95,\"117,283.20\"\n\
+             ,,,,\n\
+             Total,,,,\"117,283.20\"\n",
+        )
+        .unwrap();
+
+        let shots = read_bid_csv(&path, &LocaleSettings::for_locale("de-DE")).unwrap();
+
+        assert_eq!(shots.len(), 1);
+        assert_eq!(shots[0].estimated_hours, Some(1234.56));
+        assert_eq!(shots[0].final_price, Some(117283.2));
+
+        std::fs::remove_file(path).ok();
+    }
+}
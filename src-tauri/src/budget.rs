@@ -0,0 +1,396 @@
+//! Fit a bid to a client's budget
+//!
+//! Works on a copy of the shots so the same plan serves both the preview
+//! (`dry_run`) and the applied change.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::commands::bid::ShotData;
+use crate::error::AppError;
+
+/// Rounds of floor clamping before the plan is accepted as is
+const MAX_FLOOR_PASSES: usize = 32;
+
+/// How the difference to the target is spread over the bid
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BudgetStrategy {
+    /// Scale the hours of every unlocked shot by the same percentage
+    Uniform,
+    /// Scale hours only for shots at or above the given complexity tier
+    AboveComplexity { threshold: String },
+    /// Leave hours alone and scale contingency and overhead
+    MarkupsOnly,
+}
+
+/// Change to one shot made by the fit
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShotBudgetDelta {
+    pub shot_id: String,
+    pub old_hours: Option<f64>,
+    pub new_hours: Option<f64>,
+    pub old_price: f64,
+    pub new_price: f64,
+}
+
+/// Outcome of `fit_to_budget`, returned whether or not it was applied
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BudgetFitReport {
+    pub target: f64,
+    pub previous_total: f64,
+    pub new_total: f64,
+    /// Factor applied to hours (or markups) of the shots that scaled freely
+    pub scale_factor: f64,
+    pub deltas: Vec<ShotBudgetDelta>,
+    /// Shots held at their complexity's minimum hours
+    pub floored: Vec<String>,
+    /// Locked shots and shots the strategy doesn't cover
+    pub excluded: Vec<String>,
+    pub dry_run: bool,
+}
+
+/// Plan the changes that bring the bid's total to `target`
+///
+/// `min_hours` is keyed by complexity tier (case-insensitive). Shots that
+/// hit their floor keep the floor and the rest is spread over the others.
+/// A shot's share of lump-sum adjustments isn't scaled; it stays on top
+/// of the fitted price so removing the adjustment still takes out exactly
+/// what it added.
+pub fn plan_budget_fit(
+    shots: &[ShotData],
+    target: f64,
+    strategy: &BudgetStrategy,
+    min_hours: &HashMap<String, f64>,
+) -> Result<(Vec<ShotData>, BudgetFitReport), AppError> {
+    if !target.is_finite() || target <= 0.0 {
+        return Err(AppError::validation(format!("Budget target must be positive, got {}", target)));
+    }
+
+    let threshold = match strategy {
+        BudgetStrategy::AboveComplexity { threshold } => Some(
+            complexity_rank(threshold)
+                .ok_or_else(|| AppError::validation(format!("Unknown complexity tier '{}'", threshold)))?,
+        ),
+        _ => None,
+    };
+
+    let adjustable: Vec<usize> = shots
+        .iter()
+        .enumerate()
+        .filter(|(_, shot)| {
+            let priced = shot.labor_cost().is_some();
+            let in_scope = threshold.is_none_or(|threshold| {
+                complexity_rank(&shot.complexity).is_some_and(|rank| rank >= threshold)
+            });
+            !shot.price_locked && priced && in_scope
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    if adjustable.is_empty() {
+        return Err(AppError::validation("No unlocked, priced shots for this strategy to adjust"));
+    }
+
+    let price = |shot: &ShotData| shot.final_price.unwrap_or(0.0);
+    let previous_total: f64 = shots.iter().map(price).sum();
+    let fixed_total: f64 = shots
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !adjustable.contains(i))
+        .map(|(_, shot)| price(shot))
+        .sum();
+
+    let available = target - fixed_total;
+    if available <= 0.0 {
+        return Err(AppError::validation(format!(
+            "Locked and excluded shots already total {:.2}, above the target",
+            fixed_total
+        )));
+    }
+
+    // What the adjustable shots' own prices have to come to without their adjustments
+    let adjusted: f64 = adjustable.iter().map(|&i| shots[i].adjustment).sum();
+    let available = available - adjusted;
+    if available <= 0.0 {
+        return Err(AppError::validation(format!(
+            "Lump-sum adjustments on the adjustable shots ({:.2}) leave nothing for the target",
+            adjusted
+        )));
+    }
+
+    let mut fitted = shots.to_vec();
+    let mut floored = Vec::new();
+
+    let scale_factor = match strategy {
+        BudgetStrategy::MarkupsOnly => scale_markups(&mut fitted, &adjustable, available)?,
+        _ => scale_hours(&mut fitted, &adjustable, available, min_hours, &mut floored),
+    };
+
+    let deltas = adjustable
+        .iter()
+        .map(|&i| ShotBudgetDelta {
+            shot_id: shots[i].id.clone(),
+            old_hours: shots[i].estimated_hours,
+            new_hours: fitted[i].estimated_hours,
+            old_price: price(&shots[i]),
+            new_price: price(&fitted[i]),
+        })
+        .collect();
+
+    let excluded = shots
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !adjustable.contains(i))
+        .map(|(_, shot)| shot.id.clone())
+        .collect();
+
+    let report = BudgetFitReport {
+        target,
+        previous_total,
+        new_total: fitted.iter().map(price).sum(),
+        scale_factor,
+        deltas,
+        floored,
+        excluded,
+        dry_run: false,
+    };
+
+    Ok((fitted, report))
+}
+
+/// Scale hours so the adjustable shots total `available` before their
+/// adjustments, honouring floors
+fn scale_hours(
+    shots: &mut [ShotData],
+    adjustable: &[usize],
+    available: f64,
+    min_hours: &HashMap<String, f64>,
+    floored: &mut Vec<String>,
+) -> f64 {
+    let original: Vec<ShotData> = shots.to_vec();
+    let price_at = |i: usize, hours: f64| {
+        let mut shot = original[i].clone();
+        shot.set_hours(hours);
+        shot.final_price.unwrap_or(0.0) - shot.adjustment
+    };
+
+    // Never raise a shot when scaling down, even if it already sits below its floor
+    let floor_of = |i: usize| {
+        let hours = original[i].estimated_hours.unwrap_or(0.0);
+        min_hours
+            .iter()
+            .find(|(tier, _)| tier.eq_ignore_ascii_case(original[i].complexity.trim()))
+            .map(|(_, floor)| floor.min(hours))
+            .unwrap_or(0.0)
+    };
+
+    let mut free: Vec<usize> = adjustable.to_vec();
+    let mut held: Vec<usize> = Vec::new();
+    let mut factor = 1.0;
+
+    for _ in 0..MAX_FLOOR_PASSES {
+        let held_total: f64 = held.iter().map(|&i| price_at(i, floor_of(i))).sum();
+        let free_total: f64 = free.iter().map(|&i| original[i].final_price.unwrap_or(0.0) - original[i].adjustment).sum();
+        if free.is_empty() || free_total <= 0.0 {
+            break;
+        }
+
+        factor = ((available - held_total) / free_total).max(0.0);
+
+        let (hit, rest): (Vec<usize>, Vec<usize>) = free.iter().partition(|&&i| {
+            original[i].estimated_hours.unwrap_or(0.0) * factor < floor_of(i)
+        });
+        if hit.is_empty() {
+            break;
+        }
+        held.extend(hit);
+        free = rest;
+    }
+
+    for &i in &free {
+        match original[i].estimated_hours {
+            Some(hours) => shots[i].set_hours(hours * factor),
+            None => shots[i].recalculate_pricing(),
+        }
+    }
+    for &i in &held {
+        shots[i].set_hours(floor_of(i));
+        floored.push(shots[i].id.clone());
+    }
+
+    factor
+}
+
+/// Scale contingency and overhead so the adjustable shots total `available`
+/// before their adjustments
+fn scale_markups(shots: &mut [ShotData], adjustable: &[usize], available: f64) -> Result<f64, AppError> {
+    let cost = |shot: &ShotData| shot.labor_cost().unwrap_or(0.0);
+
+    let total_cost: f64 = adjustable.iter().map(|&i| cost(&shots[i])).sum();
+    let total_markup: f64 = adjustable
+        .iter()
+        .map(|&i| cost(&shots[i]) * (shots[i].contingency_percent + shots[i].overhead_percent) / 100.0)
+        .sum();
+
+    if total_markup <= 0.0 {
+        return Err(AppError::validation("The adjustable shots have no contingency or overhead to scale"));
+    }
+
+    let factor = (available - total_cost) / total_markup;
+    if factor < 0.0 {
+        return Err(AppError::validation(format!(
+            "The target is below the cost of the adjustable shots ({:.2}) even with no markups",
+            total_cost
+        )));
+    }
+
+    for &i in adjustable {
+        shots[i].contingency_percent *= factor;
+        shots[i].overhead_percent *= factor;
+        shots[i].recalculate_pricing();
+    }
+
+    Ok(factor)
+}
+
+/// Order of the complexity tiers used across the app
+fn complexity_rank(tier: &str) -> Option<u8> {
+    match tier.trim().to_lowercase().as_str() {
+        "low" | "simple" | "easy" => Some(0),
+        "medium" | "moderate" => Some(1),
+        "high" | "complex" | "hard" => Some(2),
+        "very high" | "hero" => Some(3),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, complexity: &str, hours: f64) -> ShotData {
+        let mut shot = ShotData {
+            id: id.to_string(),
+            complexity: complexity.to_string(),
+            estimated_hours: Some(hours),
+            rate_per_hour: Some(100.0),
+            contingency_percent: 10.0,
+            overhead_percent: 10.0,
+            ..Default::default()
+        };
+        shot.recalculate_pricing();
+        shot
+    }
+
+    fn total(shots: &[ShotData]) -> f64 {
+        shots.iter().filter_map(|s| s.final_price).sum()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_uniform_scaling_hits_target() {
+        // 100h + 50h at 100/h with 20% markups = 18,000
+        let shots = vec![shot("a", "High", 100.0), shot("b", "Medium", 50.0)];
+
+        let (fitted, report) = plan_budget_fit(&shots, 9000.0, &BudgetStrategy::Uniform, &HashMap::new()).unwrap();
+
+        assert!(close(report.previous_total, 18000.0));
+        assert!(close(report.new_total, 9000.0));
+        assert!(close(total(&fitted), 9000.0));
+        assert!(close(report.scale_factor, 0.5));
+        assert_eq!(fitted[0].estimated_hours, Some(50.0));
+        assert_eq!(report.deltas.len(), 2);
+    }
+
+    #[test]
+    fn test_locked_shots_excluded_and_remainder_redistributed() {
+        let mut locked = shot("locked", "High", 100.0);
+        locked.price_locked = true;
+        let shots = vec![locked, shot("b", "Medium", 50.0), shot("c", "Medium", 50.0)];
+
+        // Locked shot keeps its 12,000; the other two share the remaining 3,000
+        let (fitted, report) = plan_budget_fit(&shots, 15000.0, &BudgetStrategy::Uniform, &HashMap::new()).unwrap();
+
+        assert_eq!(fitted[0].estimated_hours, Some(100.0));
+        assert!(close(fitted[1].final_price.unwrap(), 1500.0));
+        assert!(close(report.new_total, 15000.0));
+        assert_eq!(report.excluded, vec!["locked"]);
+    }
+
+    #[test]
+    fn test_floors_hold_and_shift_the_cut() {
+        let floors = HashMap::from([("Low".to_string(), 40.0)]);
+        let shots = vec![shot("low", "Low", 50.0), shot("high", "High", 150.0)];
+
+        // Halving would put "low" at 25h; it holds at 40h and "high" absorbs the rest
+        let (fitted, report) = plan_budget_fit(&shots, 12000.0, &BudgetStrategy::Uniform, &floors).unwrap();
+
+        assert_eq!(report.floored, vec!["low"]);
+        assert_eq!(fitted[0].estimated_hours, Some(40.0));
+        assert!(close(fitted[1].estimated_hours.unwrap(), 60.0));
+        assert!(close(report.new_total, 12000.0));
+    }
+
+    #[test]
+    fn test_above_complexity_scales_only_matching_shots() {
+        let shots = vec![shot("low", "Low", 50.0), shot("high", "High", 100.0)];
+        let strategy = BudgetStrategy::AboveComplexity { threshold: "medium".to_string() };
+
+        let (fitted, report) = plan_budget_fit(&shots, 12000.0, &strategy, &HashMap::new()).unwrap();
+
+        assert_eq!(fitted[0].estimated_hours, Some(50.0));
+        assert!(close(fitted[1].estimated_hours.unwrap(), 50.0));
+        assert_eq!(report.excluded, vec!["low"]);
+
+        let unknown = BudgetStrategy::AboveComplexity { threshold: "epic".to_string() };
+        assert!(plan_budget_fit(&shots, 12000.0, &unknown, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_markups_only_keeps_hours() {
+        let shots = vec![shot("a", "Medium", 100.0)];
+
+        // Cost 10,000; 11,000 needs the 20% markup halved to 10%
+        let (fitted, report) = plan_budget_fit(&shots, 11000.0, &BudgetStrategy::MarkupsOnly, &HashMap::new()).unwrap();
+
+        assert_eq!(fitted[0].estimated_hours, Some(100.0));
+        assert!(close(fitted[0].contingency_percent, 5.0));
+        assert!(close(fitted[0].overhead_percent, 5.0));
+        assert!(close(report.new_total, 11000.0));
+
+        assert!(plan_budget_fit(&shots, 9000.0, &BudgetStrategy::MarkupsOnly, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_adjustment_shares_are_kept_apart() {
+        let mut discounted = shot("a", "High", 100.0);
+        discounted.add_adjustment(-2000.0);
+        let shots = vec![discounted, shot("b", "Medium", 50.0)];
+
+        // 12,000 + 6,000 of own price less the 2,000 share; the own prices halve
+        let (fitted, report) = plan_budget_fit(&shots, 7000.0, &BudgetStrategy::Uniform, &HashMap::new()).unwrap();
+        assert!(close(report.new_total, 7000.0));
+        assert!(close(report.scale_factor, 0.5));
+        assert_eq!(fitted[0].adjustment, -2000.0);
+        assert!(close(fitted[0].final_price.unwrap(), 4000.0));
+
+        // Cost 15,000 less 2,000 needs 1,000 of markup instead of 3,000
+        let (fitted, report) = plan_budget_fit(&shots, 14000.0, &BudgetStrategy::MarkupsOnly, &HashMap::new()).unwrap();
+        assert!(close(report.new_total, 14000.0));
+        assert!(close(fitted[1].contingency_percent, 10.0 / 3.0));
+    }
+
+    #[test]
+    fn test_invalid_targets_rejected() {
+        let mut locked = shot("a", "High", 100.0);
+        locked.price_locked = true;
+        let shots = vec![locked.clone(), shot("b", "Low", 10.0)];
+
+        assert!(plan_budget_fit(&shots, 0.0, &BudgetStrategy::Uniform, &HashMap::new()).is_err());
+        assert!(plan_budget_fit(&shots, 5000.0, &BudgetStrategy::Uniform, &HashMap::new()).is_err());
+        assert!(plan_budget_fit(&[locked], 5000.0, &BudgetStrategy::Uniform, &HashMap::new()).is_err());
+    }
+}
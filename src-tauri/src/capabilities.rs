@@ -0,0 +1,105 @@
+//! Which features work with the current setup
+//!
+//! Without a model (setup skipped, or the file moved) the app still opens,
+//! edits and exports existing bids; only the LLM features are switched off.
+
+use serde::{Deserialize, Serialize};
+use crate::commands::model::resolve_model_path;
+use crate::commands::settings::Settings;
+use crate::error::AppError;
+use crate::model_health::{check_model_path, model_expected};
+
+/// Feature flags for the UI
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Capabilities {
+    /// The model file the settings point at exists
+    pub model_configured: bool,
+    pub sidecar_running: bool,
+    /// Open Excel and CSV bids; falls back to the Rust reader without the
+    /// sidecar
+    pub load_bids: bool,
+    /// Shot edits, totals, CSV import and export
+    pub edit_bids: bool,
+    /// Filling an Excel template, done by the sidecar
+    pub export_excel: bool,
+    pub process_script: bool,
+    /// LLM chat; slash commands and simple edits work regardless
+    pub chat: bool,
+    /// Why LLM features are off, if they are
+    pub reason: Option<String>,
+}
+
+/// Capabilities given whether a model is configured and the sidecar runs
+pub fn capabilities_for(model_configured: bool, sidecar_running: bool) -> Capabilities {
+    let llm = model_configured && sidecar_running;
+    let reason = if !model_configured {
+        Some(AppError::no_model().message().to_string())
+    } else if !sidecar_running {
+        Some(AppError::sidecar_unavailable().message().to_string())
+    } else {
+        None
+    };
+
+    Capabilities {
+        model_configured,
+        sidecar_running,
+        load_bids: true,
+        edit_bids: true,
+        export_excel: sidecar_running,
+        process_script: llm,
+        chat: llm,
+        reason,
+    }
+}
+
+/// Whether the model file the settings point at exists
+pub fn model_configured(settings: &Settings) -> bool {
+    resolve_model_path(settings).is_file()
+}
+
+/// Error for LLM commands when there is no model to run them with
+///
+/// A model that has loaded before and is now missing gets
+/// `model_unavailable` with the reason, rather than `no_model`.
+pub fn require_model(settings: &Settings) -> Result<(), AppError> {
+    if model_configured(settings) {
+        return Ok(());
+    }
+    if model_expected(settings) {
+        check_model_path(&resolve_model_path(settings)).map_err(AppError::model_unavailable)?;
+    }
+    Err(AppError::no_model())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_llm_features_follow_model_presence() {
+        let dir = std::env::temp_dir().join(format!("capabilities-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = dir.join("model.gguf");
+
+        let mut settings = Settings::default();
+        settings.llm.model_path = Some(model.to_string_lossy().to_string());
+        assert!(!model_configured(&settings));
+        assert_eq!(require_model(&settings), Err(AppError::no_model()));
+
+        let degraded = capabilities_for(model_configured(&settings), true);
+        assert!(degraded.load_bids && degraded.edit_bids && degraded.export_excel);
+        assert!(!degraded.process_script && !degraded.chat);
+        assert_eq!(degraded.reason.as_deref(), Some(AppError::no_model().message()));
+
+        std::fs::write(&model, b"GGUF").unwrap();
+        assert!(require_model(&settings).is_ok());
+
+        let full = capabilities_for(model_configured(&settings), true);
+        assert!(full.process_script && full.chat && full.reason.is_none());
+
+        let stopped = capabilities_for(true, false);
+        assert!(!stopped.chat && !stopped.export_excel && stopped.edit_bids);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,10 +1,36 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use serde_json::{json, Value};
-use tauri::State;
-use crate::state::{BidState, SidecarState};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tauri::{AppHandle, State};
+use crate::adjustments::{AdjustmentLine, AdjustmentReport, AdjustmentScope, LumpAdjustment, SplitMethod};
+use crate::assumptions::{self, AssumptionCategory, AssumptionItem, LibraryItem};
+use crate::attachments::{self, Attachment};
+use crate::budget::{BudgetFitReport, BudgetStrategy};
+use crate::compare::{self, BidComparison};
+use crate::error::AppError;
+use crate::events::{emit_event, AppEvent};
+use crate::estimation::{self, EstimateShare, EstimateStrategy, EstimatedBy, MissingHoursEstimate};
+use crate::format::{format_currency, format_number};
+use crate::review::{self, ShotReview};
+use crate::state::audit::{current_user, AuditEntry};
+use crate::state::bid::ReadOnlyStatus;
+use crate::state::journal::JournalStats;
+use crate::sidecar::{query_cache, RpcMethod};
+use crate::state::last_error::tracked_command;
+use crate::state::{workspaces, BidState, SessionState, SidecarState, WindowBid};
+use crate::scene_order::compare_scene_numbers;
+
+/// Frame rate assumed when a shot has a frame count but no fps
+pub const DEFAULT_FPS: f64 = 24.0;
+
+/// Department for flat hours folded into a task breakdown, e.g. on merge
+pub const UNASSIGNED_DEPARTMENT: &str = "Unassigned";
 
 /// Shot data with pricing
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
 pub struct ShotData {
     pub id: String,
     pub scene_number: String,
@@ -17,16 +43,519 @@ pub struct ShotData {
     pub contingency_percent: f64,
     pub overhead_percent: f64,
     pub final_price: Option<f64>,
+    #[serde(default, alias = "cut_in")]
+    pub frame_in: Option<u32>,
+    #[serde(default, alias = "cut_out")]
+    pub frame_out: Option<u32>,
+    #[serde(default, alias = "frames")]
+    pub frame_count: Option<u32>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+    /// Reference images, managed through `attach_reference`/`remove_reference`
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Delivered mid-run by `shot_extracted`; cleared when the final shot
+    /// list from `process_script` arrives
+    #[serde(default)]
+    pub provisional: bool,
+    /// Keep this shot's price fixed when fitting the bid to a budget
+    #[serde(default)]
+    pub price_locked: bool,
+    /// Position in the bid, with gaps so a move only renumbers one shot;
+    /// managed by `BidState`
+    #[serde(default)]
+    pub sort_index: u32,
+    /// Department breakdown; when present, hours and cost are derived from
+    /// it and the flat fields are read-only
+    #[serde(default)]
+    pub tasks: Vec<TaskLine>,
+    /// Workflow labels such as "awaiting-plate", set by the bidder and never
+    /// by the LLM; managed through `add_tags`/`remove_tags`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// How sure the sidecar was of this shot, 0 to 1; None when it didn't say
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Reasons to double-check the shot, e.g. "ambiguous_scene" from the
+    /// sidecar or "duplicate_description" from `review`
+    #[serde(default)]
+    pub extraction_flags: Vec<String>,
+    /// Set by `mark_reviewed`; takes the shot out of the review queue
+    #[serde(default)]
+    pub review: Option<ShotReview>,
+    /// Set when `estimate_missing_hours` filled the hours; cleared once
+    /// they are edited by hand
+    #[serde(default)]
+    pub estimated_by: Option<EstimatedBy>,
+    /// This shot's share of the bid's lump-sum adjustments, already in
+    /// `final_price`; managed through `apply_lump_adjustment`
+    #[serde(default)]
+    pub adjustment: f64,
 }
 
-/// Shot grouping for batch operations
+/// One department's share of the work on a shot
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct TaskLine {
+    /// e.g. "roto", "prep", "comp", "CG", "FX"
+    pub department: String,
+    pub hours: f64,
+    /// Rate for this line instead of the shot's `rate_per_hour`
+    #[serde(default)]
+    pub rate_override: Option<f64>,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl TaskLine {
+    /// Hourly rate for this line, falling back to the shot's
+    pub fn rate(&self, shot_rate: Option<f64>) -> Option<f64> {
+        self.rate_override.or(shot_rate)
+    }
+
+    /// Cost of this line, if it has a rate
+    pub fn cost(&self, shot_rate: Option<f64>) -> Option<f64> {
+        self.rate(shot_rate).map(|rate| self.hours * rate)
+    }
+}
+
+/// Longest tag accepted, in characters
+pub const MAX_TAG_LENGTH: usize = 40;
+
+/// Trim, lowercase and dedupe tags, keeping their order
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, AppError> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(AppError::validation(format!("Tag '{}' is longer than {} characters", tag, MAX_TAG_LENGTH)));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    Ok(normalized)
+}
+
+/// A tag and how many shots carry it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Which shots `query_shots` returns; empty lists match everything
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ShotFilter {
+    /// Shots with at least one of these tags
+    #[serde(default)]
+    pub tags_any: Vec<String>,
+    /// Shots with every one of these tags
+    #[serde(default)]
+    pub tags_all: Vec<String>,
+}
+
+impl ShotFilter {
+    pub fn matches(&self, shot: &ShotData) -> bool {
+        let has = |tag: &String| shot.tags.contains(tag);
+        (self.tags_any.is_empty() || self.tags_any.iter().any(has)) && self.tags_all.iter().all(has)
+    }
+}
+
+/// Check task lines before they replace a shot's breakdown
+pub fn validate_tasks(tasks: &[TaskLine]) -> Result<(), AppError> {
+    for task in tasks {
+        if task.department.trim().is_empty() {
+            return Err(AppError::validation("Every task needs a department"));
+        }
+        if !task.hours.is_finite() || task.hours < 0.0 {
+            return Err(AppError::validation(format!("Hours for {} must be zero or more", task.department)));
+        }
+        if task.rate_override.is_some_and(|rate| !rate.is_finite() || rate < 0.0) {
+            return Err(AppError::validation(format!("Rate for {} must be zero or more", task.department)));
+        }
+    }
+    Ok(())
+}
+
+impl ShotData {
+    /// Recompute `frame_count` from the in/out points when both are set
+    pub fn sync_frame_count(&mut self) {
+        if let (Some(frame_in), Some(frame_out)) = (self.frame_in, self.frame_out) {
+            if frame_out >= frame_in {
+                self.frame_count = Some(frame_out - frame_in + 1);
+            }
+        }
+    }
+
+    /// Shot duration in seconds, if the frame count is known
+    pub fn duration_seconds(&self) -> Option<f64> {
+        let fps = self.fps.filter(|f| *f > 0.0).unwrap_or(DEFAULT_FPS);
+        self.frame_count.map(|frames| frames as f64 / fps)
+    }
+
+    /// Recompute cost and final price from hours, rate and markups
+    ///
+    /// With tasks, hours are first set to the sum of the task hours. Any
+    /// lump-sum adjustment is kept on top of the marked-up cost.
+    pub fn recalculate_pricing(&mut self) {
+        if !self.tasks.is_empty() {
+            self.estimated_hours = Some(self.tasks.iter().map(|t| t.hours).sum());
+        }
+
+        if let Some(cost) = self.labor_cost() {
+            let markup = 1.0 + (self.contingency_percent + self.overhead_percent) / 100.0;
+            self.estimated_cost = Some(cost);
+            self.final_price = Some(cost * markup + self.adjustment);
+        }
+    }
+
+    /// Add `amount` to the shot's adjustment and final price
+    pub fn add_adjustment(&mut self, amount: f64) {
+        self.adjustment = ((self.adjustment + amount) * 100.0).round() / 100.0;
+        self.final_price = self.final_price.map(|price| price + amount);
+    }
+
+    /// Cost before markups: hours times rate, or the sum of the task lines
+    /// when every line has a rate
+    pub fn labor_cost(&self) -> Option<f64> {
+        if self.tasks.is_empty() {
+            return Some(self.estimated_hours? * self.rate_per_hour?);
+        }
+        self.tasks.iter().map(|t| t.cost(self.rate_per_hour)).sum()
+    }
+
+    /// Set the shot's hours and reprice; with tasks, every line is scaled
+    /// by the same factor. The hours are no longer an estimate.
+    pub fn set_hours(&mut self, hours: f64) {
+        self.estimated_by = None;
+        let current: f64 = self.tasks.iter().map(|t| t.hours).sum();
+        if current > 0.0 {
+            let factor = hours / current;
+            for task in &mut self.tasks {
+                task.hours *= factor;
+            }
+        } else {
+            self.estimated_hours = Some(hours);
+        }
+        self.recalculate_pricing();
+    }
+}
+
+/// Hours and cost before markups of one department across a set of shots
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct DepartmentTotal {
+    pub department: String,
+    pub hours: f64,
+    pub cost: f64,
+}
+
+/// Sum the frame counts of all shots that have one
+pub fn total_frame_count<'a>(shots: impl IntoIterator<Item = &'a ShotData>) -> u64 {
+    shots
+        .into_iter()
+        .filter_map(|s| s.frame_count)
+        .map(u64::from)
+        .sum()
+}
+
+/// Task hours and cost by department, in order of first appearance
+///
+/// Departments match case-insensitively. Shots without tasks add nothing.
+pub fn department_totals<'a>(shots: impl IntoIterator<Item = &'a ShotData>) -> Vec<DepartmentTotal> {
+    let mut totals: Vec<DepartmentTotal> = Vec::new();
+
+    for shot in shots {
+        for task in &shot.tasks {
+            let department = task.department.trim();
+            let index = match totals.iter().position(|t| t.department.eq_ignore_ascii_case(department)) {
+                Some(index) => index,
+                None => {
+                    totals.push(DepartmentTotal { department: department.to_string(), hours: 0.0, cost: 0.0 });
+                    totals.len() - 1
+                }
+            };
+            totals[index].hours += task.hours;
+            totals[index].cost += task.cost(shot.rate_per_hour).unwrap_or(0.0);
+        }
+    }
+
+    totals
+}
+
+/// Rounding applied to client-facing prices
+///
+/// Precise prices are kept on the shots; rounding happens only in totals and
+/// exports.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceRounding {
+    #[default]
+    None,
+    NearestDollar,
+    Nearest10,
+    Nearest100,
+    Nearest500,
+}
+
+impl PriceRounding {
+    fn step(self) -> Option<f64> {
+        match self {
+            PriceRounding::None => None,
+            PriceRounding::NearestDollar => Some(1.0),
+            PriceRounding::Nearest10 => Some(10.0),
+            PriceRounding::Nearest100 => Some(100.0),
+            PriceRounding::Nearest500 => Some(500.0),
+        }
+    }
+
+    /// Round `price` for presentation
+    ///
+    /// Halves round up (away from zero), not to even: 1,250 to the nearest
+    /// 100 is 1,300, as a client would do it by hand. The price is first
+    /// taken to whole cents so float noise like 1,249.9999999 doesn't decide
+    /// the direction.
+    pub fn apply(self, price: f64) -> f64 {
+        match self.step() {
+            Some(step) => {
+                let cents = (price * 100.0).round() / 100.0;
+                (cents / step).round() * step
+            }
+            None => price,
+        }
+    }
+}
+
+/// Sum of the shots' final prices as the client sees them
+///
+/// With rounding active this is the sum of the rounded prices, so the total
+/// matches the column it sits under.
+pub fn total_final_price(shots: &[ShotData], rounding: PriceRounding) -> f64 {
+    shots.iter()
+        .filter_map(|s| s.final_price)
+        .map(|price| rounding.apply(price))
+        .sum()
+}
+
+/// Where a bid is in its lifecycle
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BidStatus {
+    #[default]
+    Draft,
+    Sent,
+    Revised,
+    Approved,
+    Expired,
+}
+
+impl BidStatus {
+    /// Whether `set_bid_status` may move a bid from `self` to `next`
+    ///
+    /// An approved bid only reopens through `revise_bid`, which keeps a copy
+    /// of what the client approved.
+    pub fn can_become(self, next: BidStatus) -> bool {
+        use BidStatus::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Draft, Sent) | (Sent, Revised) | (Sent, Approved) | (Sent, Expired) | (Revised, Sent) | (Expired, Revised)
+            )
+    }
+}
+
+/// Bid-level metadata and pricing defaults
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BidMeta {
+    pub default_contingency: f64,
+    pub default_overhead: f64,
+    pub currency: String,
+    pub client_name: Option<String>,
+    pub project_name: Option<String>,
+    /// Rounding for final prices in totals and exports
+    #[serde(default)]
+    pub rounding: PriceRounding,
+    /// Changed only through `set_bid_status` and `revise_bid`
+    #[serde(default)]
+    pub status: BidStatus,
+    /// Last day the quote is valid
+    #[serde(default)]
+    pub valid_until: Option<NaiveDate>,
+    /// Bumped by `revise_bid`
+    #[serde(default = "first_version")]
+    pub version: u32,
+    /// Assumptions, exclusions and notes, in the order they are listed
+    #[serde(default, deserialize_with = "assumptions::deserialize_assumptions")]
+    pub assumptions: Vec<AssumptionItem>,
+    /// Lump-sum adjustments, oldest first; changed only through
+    /// `apply_lump_adjustment` and `remove_adjustment`
+    #[serde(default)]
+    pub adjustments: Vec<LumpAdjustment>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+impl BidMeta {
+    /// Whether the quote lapsed before `today` without being approved
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        let open = matches!(self.status, BidStatus::Draft | BidStatus::Sent | BidStatus::Revised);
+        open && self.valid_until.is_some_and(|date| date < today)
+    }
+}
+
+impl Default for BidMeta {
+    fn default() -> Self {
+        Self {
+            default_contingency: 15.0,
+            default_overhead: 10.0,
+            currency: "USD".to_string(),
+            client_name: None,
+            project_name: None,
+            rounding: PriceRounding::None,
+            status: BidStatus::Draft,
+            valid_until: None,
+            version: 1,
+            assumptions: Vec::new(),
+            adjustments: Vec::new(),
+        }
+    }
+}
+
+/// The bid as it stood when `revise_bid` reopened it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BidRevision {
+    pub revised_at: String,
+    /// Carries the revision's version and status
+    pub meta: BidMeta,
+    pub shots: Vec<ShotData>,
+    pub groups: Vec<ShotGroup>,
+}
+
+/// Result of changing the bid metadata
 #[derive(Debug, Serialize, Deserialize)]
+pub struct BidMetaUpdate {
+    pub meta: BidMeta,
+    pub affected_shots: usize,
+    pub grand_total: f64,
+}
+
+/// Shot grouping for batch operations
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ShotGroup {
     pub name: String,
     pub shot_ids: Vec<String>,
     pub discount_percent: Option<f64>,
 }
 
+/// A deleted shot kept so it can be restored
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedShot {
+    pub shot: ShotData,
+    /// RFC 3339 time it was deleted
+    pub deleted_at: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Groups it was in when deleted
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+/// A shot brought back from the archive
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoredShot {
+    pub shot: ShotData,
+    /// Set when the shot had to take a new id
+    pub warning: Option<String>,
+}
+
+/// Order to list shots in; `Manual` is the bid's own order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ShotOrder {
+    #[default]
+    Manual,
+    /// Scene number, then shot id, in natural order (2 before 10)
+    Scene,
+    Id,
+}
+
+impl ShotOrder {
+    pub fn sort(self, shots: &mut [ShotData]) {
+        match self {
+            ShotOrder::Manual => shots.sort_by_key(|s| s.sort_index),
+            ShotOrder::Scene => shots.sort_by(|a, b| {
+                compare_scene_numbers(&a.scene_number, &b.scene_number).then_with(|| compare_scene_numbers(&a.id, &b.id))
+            }),
+            ShotOrder::Id => shots.sort_by(|a, b| compare_scene_numbers(&a.id, &b.id)),
+        }
+    }
+}
+
+/// A page of shots for virtualized listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShotPage {
+    pub shots: Vec<ShotData>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+/// Similarity score between two shots flagged as likely duplicates
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicatePair {
+    pub first_id: String,
+    pub second_id: String,
+    pub similarity: f64,
+}
+
+/// Cluster of shots that are likely duplicates of each other
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateGroup {
+    pub scene_number: String,
+    pub shot_ids: Vec<String>,
+    pub pairs: Vec<DuplicatePair>,
+}
+
+/// Bid totals with display strings in the user's locale
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct BidTotals {
+    pub shot_count: usize,
+    pub total_hours: f64,
+    pub total_cost: f64,
+    /// Sum of the rounded final prices when `rounding` is active
+    pub grand_total: f64,
+    /// Sum of the unrounded final prices
+    pub precise_grand_total: f64,
+    pub rounding: PriceRounding,
+    pub currency: String,
+    pub status: BidStatus,
+    pub valid_until: Option<NaiveDate>,
+    pub version: u32,
+    /// `valid_until` has passed and the bid isn't approved
+    pub expired: bool,
+    /// Task hours by department; empty when no shot has tasks
+    pub departments: Vec<DepartmentTotal>,
+    /// Sum of the frame counts of shots that have one
+    #[serde(default)]
+    pub total_frames: u64,
+    /// How much of the hours and grand total come from table estimates
+    pub estimate_share: EstimateShare,
+    /// Lump-sum adjustments, already in the grand total
+    pub adjustments: Vec<AdjustmentLine>,
+    pub display: BidTotalsDisplay,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct BidTotalsDisplay {
+    pub total_hours: String,
+    pub total_cost: String,
+    pub grand_total: String,
+}
+
 /// Query parameters for bid queries
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BidQueryParams {
@@ -34,41 +563,811 @@ pub struct BidQueryParams {
     pub params: Option<Value>,
 }
 
+tracked_command! { Bid;
 /// Get a single shot by ID
 #[tauri::command]
-pub fn get_shot(id: String, state: State<'_, BidState>) -> Result<ShotData, String> {
+pub fn get_shot(id: String, state: WindowBid<'_>) -> Result<ShotData, AppError> {
     let shots = state.get_shots();
 
     shots
         .iter()
         .find(|s| s.id == id)
         .cloned()
-        .ok_or_else(|| format!("Shot {} not found", id))
+        .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id)))
+}
 }
 
+tracked_command! { Bid;
 /// Update shot data
 #[tauri::command]
 pub fn update_shot(
     id: String,
     updates: ShotData,
-    state: State<'_, BidState>,
-) -> Result<ShotData, String> {
+    state: WindowBid<'_>,
+) -> Result<ShotData, AppError> {
     state.update_shot(id, updates)
 }
+}
+
+tracked_command! { Bid;
+/// Who changed shot `id` and when: one entry per changed field, oldest first
+#[tauri::command]
+pub fn get_shot_history(id: String, state: WindowBid<'_>) -> Result<Vec<AuditEntry>, AppError> {
+    state.shot_history(&id).map_err(AppError::io)
+}
+}
 
+tracked_command! { Bid;
 /// Group multiple shots for batch operations
 #[tauri::command]
-pub fn group_shots(group: ShotGroup) -> Result<String, String> {
-    // TODO: Implement shot grouping logic
-    Ok(format!("Created group '{}' with {} shots", group.name, group.shot_ids.len()))
+pub fn group_shots(group: ShotGroup, state: WindowBid<'_>) -> Result<String, AppError> {
+    let message = format!("Created group '{}' with {} shots", group.name, group.shot_ids.len());
+    state.add_group(group)?;
+    Ok(message)
+}
+}
+
+tracked_command! { Bid;
+/// Merge several shots into a primary shot
+///
+/// VFX types are combined, hours are summed and pricing is recalculated.
+/// The merged shots are removed and any groups that referenced them point
+/// at the primary shot instead.
+#[tauri::command]
+pub fn merge_shots(
+    primary_id: String,
+    merged_ids: Vec<String>,
+    state: WindowBid<'_>,
+) -> Result<ShotData, AppError> {
+    state.merge_shots(&primary_id, &merged_ids)
+}
+}
+
+/// Get all shots in the current bid, in bid order unless `order` says
+/// otherwise
+#[tauri::command]
+pub fn get_all_shots(order: Option<ShotOrder>, state: WindowBid<'_>) -> Vec<ShotData> {
+    let mut shots = state.get_shots();
+    if let Some(order) = order.filter(|o| *o != ShotOrder::Manual) {
+        order.sort(&mut shots);
+    }
+    shots
+}
+
+/// Get a page of shots in the current bid
+///
+/// Ordering matches `get_all_shots`. Offsets past the end return an empty page.
+#[tauri::command]
+pub fn get_shots_page(
+    offset: usize,
+    limit: usize,
+    order: Option<ShotOrder>,
+    state: WindowBid<'_>,
+) -> ShotPage {
+    let (shots, total) = match order.unwrap_or_default() {
+        ShotOrder::Manual => state.get_shots_page(offset, limit),
+        order => {
+            let mut shots = state.get_shots();
+            order.sort(&mut shots);
+            let total = shots.len();
+            (shots.into_iter().skip(offset).take(limit).collect(), total)
+        }
+    };
+
+    ShotPage {
+        shots,
+        offset: offset.min(total),
+        total,
+    }
+}
+
+tracked_command! { Bid;
+/// Shots matching `filter`, in bid order
+#[tauri::command]
+pub fn query_shots(filter: ShotFilter, state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    let filter = ShotFilter {
+        tags_any: normalize_tags(&filter.tags_any)?,
+        tags_all: normalize_tags(&filter.tags_all)?,
+    };
+    Ok(state.get_shots().into_iter().filter(|shot| filter.matches(shot)).collect())
+}
+}
+
+tracked_command! { Bid;
+/// Add tags to shots; returns the updated shots
+#[tauri::command]
+pub fn add_tags(shot_ids: Vec<String>, tags: Vec<String>, state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.add_tags(&shot_ids, &tags)
+}
+}
+
+tracked_command! { Bid;
+/// Take tags off shots; returns the updated shots
+#[tauri::command]
+pub fn remove_tags(shot_ids: Vec<String>, tags: Vec<String>, state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.remove_tags(&shot_ids, &tags)
+}
+}
+
+/// Every tag in the bid with the number of shots carrying it, most used first
+#[tauri::command]
+pub fn get_all_tags(state: WindowBid<'_>) -> Vec<TagCount> {
+    state.all_tags()
+}
+
+tracked_command! { Bid;
+/// Reorder the bid's shots; `ordered_ids` must list each shot once
+///
+/// Returns the shots in their new order. Undo with `undo_shot_order`.
+#[tauri::command]
+pub fn reorder_shots(ordered_ids: Vec<String>, state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.reorder_shots(&ordered_ids)
+}
+}
+
+tracked_command! { Bid;
+/// Move a shot in front of `before_id`, or to the end when it is omitted
+#[tauri::command]
+pub fn move_shot(
+    id: String,
+    before_id: Option<String>,
+    state: WindowBid<'_>,
+) -> Result<Vec<ShotData>, AppError> {
+    state.move_shot(&id, before_id.as_deref())
+}
+}
+
+tracked_command! { Bid;
+/// Replace a shot's department breakdown
+///
+/// The shot's hours and cost become the sum of the lines. An empty list
+/// returns it to flat hours.
+#[tauri::command]
+pub fn set_shot_tasks(id: String, tasks: Vec<TaskLine>, state: WindowBid<'_>) -> Result<ShotData, AppError> {
+    state.set_shot_tasks(&id, tasks)
+}
+}
+
+tracked_command! { Bid;
+/// Give shots the breakdown of a task template from the pricing settings
+///
+/// Replaces any tasks the shots had. Returns the updated shots.
+#[tauri::command]
+pub fn apply_task_template(
+    shot_ids: Vec<String>,
+    template_name: String,
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<Vec<ShotData>, AppError> {
+    let pricing = session_state.get_settings().unwrap_or_default().pricing;
+    let template = pricing.task_template(&template_name)
+        .ok_or_else(|| AppError::not_found(format!("Task template '{}' not found", template_name)))?;
+
+    state.set_tasks(&shot_ids, &template.tasks)
+}
+}
+
+tracked_command! { Bid;
+/// Restore the shot order from before the last reorder or move
+#[tauri::command]
+pub fn undo_shot_order(state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.undo_shot_order()
+}
+}
+
+tracked_command! { Bid;
+/// Move a shot to the archive, out of totals and exports
+#[tauri::command]
+pub fn delete_shot(id: String, reason: Option<String>, state: WindowBid<'_>) -> Result<ArchivedShot, AppError> {
+    state.delete_shot(&id, reason)
+}
+}
+
+/// Deleted shots that can still be restored, oldest first
+#[tauri::command]
+pub fn get_archived_shots(state: WindowBid<'_>) -> Vec<ArchivedShot> {
+    state.get_archived_shots()
+}
+
+tracked_command! { Bid;
+/// Bring an archived shot back where it was
+///
+/// If its id has been reused since, it comes back under a suffixed id and
+/// the result carries a warning.
+#[tauri::command]
+pub fn restore_shot(id: String, state: WindowBid<'_>) -> Result<RestoredShot, AppError> {
+    state.restore_shot(&id)
+}
+}
+
+tracked_command! { Bid;
+/// Permanently drop archived shots deleted more than `older_than_days` ago
+///
+/// Returns how many were dropped.
+#[tauri::command]
+pub fn purge_archived(older_than_days: u32, state: WindowBid<'_>) -> Result<usize, AppError> {
+    state.purge_archived(older_than_days)
+}
 }
 
-/// Get all shots in the current bid
+tracked_command! { Bid;
+/// Reverse the last shot delete or restore
 #[tauri::command]
-pub fn get_all_shots(state: State<'_, BidState>) -> Vec<ShotData> {
-    state.get_shots()
+pub fn undo_archive_change(state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.undo_archive_change()
+}
 }
 
+tracked_command! { Bid;
+/// Repeat the last shot delete or restore that was undone
+#[tauri::command]
+pub fn redo_archive_change(state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.redo_archive_change()
+}
+}
+
+/// Get the bid-level metadata and pricing defaults
+#[tauri::command]
+pub fn get_bid_meta(state: WindowBid<'_>) -> BidMeta {
+    state.get_meta()
+}
+
+tracked_command! { Bid;
+/// Update the bid-level metadata
+///
+/// Shots still on the previous contingency/overhead defaults are moved to the
+/// new ones and repriced; shots with their own values are left alone.
+#[tauri::command]
+pub fn update_bid_meta(meta: BidMeta, state: WindowBid<'_>) -> Result<BidMetaUpdate, AppError> {
+    state.ensure_writable()?;
+    let affected_shots = state.set_meta(meta.clone());
+
+    Ok(BidMetaUpdate {
+        meta,
+        affected_shots,
+        grand_total: state.grand_total(),
+    })
+}
+}
+
+/// Whether the open bid can be edited
+#[tauri::command]
+pub fn get_bid_readonly(state: WindowBid<'_>) -> ReadOnlyStatus {
+    state.read_only_status()
+}
+
+/// Shots to double-check, least confident and most flagged first
+///
+/// Flags worked out from the bid are refreshed on every call; reviewed
+/// shots are left out.
+#[tauri::command]
+pub fn get_review_queue(state: WindowBid<'_>) -> Vec<ShotData> {
+    review::review_queue(state.get_shots())
+}
+
+tracked_command! { Bid;
+/// Record that `shot_id` was checked, by whom and when, taking it out of
+/// the review queue
+#[tauri::command]
+pub fn mark_reviewed(shot_id: String, state: WindowBid<'_>) -> Result<ShotData, AppError> {
+    state.modify_shot(&shot_id, |shot| {
+        shot.review = Some(ShotReview {
+            reviewed_by: current_user(),
+            reviewed_at: chrono::Utc::now().to_rfc3339(),
+        });
+        Ok(shot.clone())
+    })
+}
+}
+
+tracked_command! { Bid;
+/// Lock or unlock editing of the open bid
+///
+/// While locked every edit fails with a `read_only` error; exports, totals
+/// and queries still work. With `persist` the project is also saved to open
+/// read-only. A project saved that way needs `unlock_bid`. Emits
+/// `bid-readonly-changed`.
+#[tauri::command]
+pub fn set_bid_readonly(
+    enabled: bool,
+    persist: Option<bool>,
+    app: AppHandle,
+    state: WindowBid<'_>,
+) -> Result<ReadOnlyStatus, AppError> {
+    let status = state.set_read_only(enabled, persist.unwrap_or(false))?;
+    emit_event(&app, AppEvent::BidReadonlyChanged(status))?;
+    Ok(status)
+}
+}
+
+tracked_command! { Bid;
+/// Unlock the bid and clear the project's read-only flag
+///
+/// The bid is marked as changed so the cleared flag gets saved. Emits
+/// `bid-readonly-changed`.
+#[tauri::command]
+pub fn unlock_bid(app: AppHandle, state: WindowBid<'_>) -> Result<ReadOnlyStatus, AppError> {
+    let status = state.unlock();
+    emit_event(&app, AppEvent::BidReadonlyChanged(status))?;
+    Ok(status)
+}
+}
+
+tracked_command! { Bid;
+/// Empty the loaded bid to start a new one; `confirm` must be true
+///
+/// Earlier versions are dropped too unless `keep_revisions` is set. Emits
+/// `bid-changed` with the (zero) totals.
+#[tauri::command]
+pub fn clear_bid(
+    confirm: bool,
+    keep_revisions: Option<bool>,
+    app: AppHandle,
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<BidTotals, AppError> {
+    if !confirm {
+        return Err(AppError::validation("Clearing the bid removes every shot; pass confirm to go ahead"));
+    }
+
+    state.clear(keep_revisions.unwrap_or(false))?;
+
+    let totals = get_bid_totals_internal(&state, &session_state);
+    emit_event(&app, AppEvent::BidChanged(totals.clone()))?;
+    Ok(totals)
+}
+}
+
+tracked_command! { Bid;
+/// Restore the bid autosaved before the last crash or restart
+///
+/// Returns the restored shots, or an error if there is nothing to restore.
+#[tauri::command]
+pub fn restore_autosaved_bid(state: WindowBid<'_>) -> Result<Vec<ShotData>, AppError> {
+    state.restore_autosave()
+        .map_err(AppError::io)?
+        .map(|autosave| autosave.shots)
+        .ok_or_else(|| AppError::not_found("No autosaved bid to restore"))
+}
+}
+
+tracked_command! { Bid;
+/// Move the bid to `status`, refusing transitions the workflow doesn't allow
+#[tauri::command]
+pub fn set_bid_status(status: BidStatus, state: WindowBid<'_>) -> Result<BidMeta, AppError> {
+    state.set_status(status)
+}
+}
+
+tracked_command! { Bid;
+/// Reopen a sent, approved or expired bid as a new draft version
+///
+/// The bid as it stood is kept in the project so `compare_bids` can diff
+/// the versions later.
+#[tauri::command]
+pub fn revise_bid(state: WindowBid<'_>) -> Result<BidMeta, AppError> {
+    state.revise()
+}
+}
+
+tracked_command! { Bid;
+/// Add an assumption, exclusion or note to the end of the bid's list
+///
+/// Items are shown to the client unless `included_in_export` is false.
+/// Returns the whole list.
+#[tauri::command]
+pub fn add_assumption(
+    text: String,
+    category: AssumptionCategory,
+    included_in_export: Option<bool>,
+    state: WindowBid<'_>,
+) -> Result<Vec<AssumptionItem>, AppError> {
+    let mut item = AssumptionItem::new(text, category);
+    item.included_in_export = included_in_export.unwrap_or(true);
+    state.add_assumptions(vec![item])
+}
+}
+
+tracked_command! { Bid;
+/// Replace the text, category or export flag of the item with `item.id`
+#[tauri::command]
+pub fn update_assumption(item: AssumptionItem, state: WindowBid<'_>) -> Result<Vec<AssumptionItem>, AppError> {
+    state.update_assumption(item)
+}
+}
+
+tracked_command! { Bid;
+#[tauri::command]
+pub fn remove_assumption(id: String, state: WindowBid<'_>) -> Result<Vec<AssumptionItem>, AppError> {
+    state.remove_assumption(&id)
+}
+}
+
+tracked_command! { Bid;
+/// Put the bid's assumptions in the order of `ids`, which must list each once
+#[tauri::command]
+pub fn reorder_assumptions(ids: Vec<String>, state: WindowBid<'_>) -> Result<Vec<AssumptionItem>, AppError> {
+    state.reorder_assumptions(&ids)
+}
+}
+
+tracked_command! { Bid;
+/// Boilerplate assumptions to insert into bids
+#[tauri::command]
+pub fn get_assumption_library() -> Result<Vec<LibraryItem>, AppError> {
+    Ok(load_assumption_library()?.items)
+}
+}
+
+tracked_command! { Bid;
+/// Add a boilerplate item, or replace the one with its id; an empty id
+/// gets a new one
+#[tauri::command]
+pub fn save_library_assumption(item: LibraryItem) -> Result<Vec<LibraryItem>, AppError> {
+    let mut library = load_assumption_library()?;
+    library.save(item).map_err(AppError::validation)?;
+    write_assumption_library(&library)?;
+    Ok(library.items)
+}
+}
+
+tracked_command! { Bid;
+#[tauri::command]
+pub fn delete_library_assumption(id: String) -> Result<Vec<LibraryItem>, AppError> {
+    let mut library = load_assumption_library()?;
+    library.remove(&id).map_err(AppError::not_found)?;
+    write_assumption_library(&library)?;
+    Ok(library.items)
+}
+}
+
+tracked_command! { Bid;
+/// Add the library items `ids` to the end of the bid's list, in that order
+#[tauri::command]
+pub fn insert_library_assumptions(ids: Vec<String>, state: WindowBid<'_>) -> Result<Vec<AssumptionItem>, AppError> {
+    let library = load_assumption_library()?;
+    let items = ids
+        .iter()
+        .map(|id| {
+            library.find(id)
+                .map(LibraryItem::to_item)
+                .ok_or_else(|| AppError::not_found(format!("No library item '{}'", id)))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    state.add_assumptions(items)
+}
+}
+
+fn load_assumption_library() -> Result<assumptions::AssumptionLibrary, AppError> {
+    match assumptions::library_path() {
+        Some(path) => assumptions::load_library(path).map_err(AppError::io),
+        None => Ok(assumptions::AssumptionLibrary::builtin()),
+    }
+}
+
+fn write_assumption_library(library: &assumptions::AssumptionLibrary) -> Result<(), AppError> {
+    let path = assumptions::library_path()
+        .ok_or_else(|| AppError::io("Config directory is not available"))?;
+    assumptions::write_library(path, library).map_err(AppError::io)
+}
+
+tracked_command! { Bid;
+/// Diff two versions of a bid, or the bids of two windows
+///
+/// `base_context` and `target_context` are window context ids from
+/// `list_bid_windows` and default to the calling window. Versions default to
+/// each bid's current one.
+#[tauri::command]
+pub fn compare_bids(
+    base_version: Option<u32>,
+    target_version: Option<u32>,
+    base_context: Option<String>,
+    target_context: Option<String>,
+    app: AppHandle,
+    state: WindowBid<'_>,
+) -> Result<BidComparison, AppError> {
+    let context = |id: Option<String>| id.map(|id| workspaces::context_bid(&app, &id)).transpose();
+    let (base_bid, target_bid) = (context(base_context)?, context(target_context)?);
+
+    let version = |bid: &BidState, v: Option<u32>| {
+        let v = v.unwrap_or_else(|| bid.get_meta().version);
+        bid.version(v).ok_or_else(|| AppError::not_found(format!("Bid version {} not found", v)))
+    };
+
+    let base = version(base_bid.as_deref().unwrap_or(&state), base_version)?;
+    let target = version(target_bid.as_deref().unwrap_or(&state), target_version)?;
+
+    Ok(compare::compare_versions(&base, &target))
+}
+}
+
+/// Size, revision and compaction history of the bid's write-ahead journal
+#[tauri::command]
+pub fn get_journal_stats(state: WindowBid<'_>) -> JournalStats {
+    state.journal_stats()
+}
+
+tracked_command! { Bid;
+/// Fill in estimated hours from shot duration and recalculate pricing
+///
+/// Applies an hours-per-second factor for each complexity tier to shots that
+/// have a frame count but no estimated hours. When `factors` is omitted the
+/// ones saved in the pricing settings are used. Returns the number of shots
+/// that were estimated.
+#[tauri::command]
+pub fn estimate_hours_from_duration(
+    factors: Option<HashMap<String, f64>>,
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<usize, AppError> {
+    estimate_hours_from_duration_internal(factors, &state, &session_state)
+}
+}
+
+pub(crate) fn estimate_hours_from_duration_internal(
+    factors: Option<HashMap<String, f64>>,
+    state: &BidState,
+    session_state: &SessionState,
+) -> Result<usize, AppError> {
+    state.ensure_writable()?;
+    let factors = factors.unwrap_or_else(|| session_state.get_settings().unwrap_or_default().pricing.hours_per_second);
+    Ok(state.estimate_hours_from_duration(&factors))
+}
+
+tracked_command! { Bid;
+/// Fill in blank hours from the hour ranges in the pricing settings
+///
+/// Each shot without hours or tasks gets the range for its complexity, and
+/// VFX type where one is configured, picked by `strategy`, and is marked
+/// `estimated_by: table`. Shots whose tier has no range are left blank.
+#[tauri::command]
+pub fn estimate_missing_hours(
+    strategy: Option<EstimateStrategy>,
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<MissingHoursEstimate, AppError> {
+    estimate_missing_hours_internal(strategy.unwrap_or_default(), &state, &session_state)
+}
+}
+
+pub(crate) fn estimate_missing_hours_internal(
+    strategy: EstimateStrategy,
+    state: &BidState,
+    session_state: &SessionState,
+) -> Result<MissingHoursEstimate, AppError> {
+    state.ensure_writable()?;
+    let ranges = session_state.get_settings().unwrap_or_default().pricing.hour_ranges;
+    let filled = state.estimate_missing_hours(&ranges, strategy);
+
+    Ok(MissingHoursEstimate {
+        filled,
+        grand_total: state.grand_total(),
+    })
+}
+
+/// Get the bid's hour and price totals, formatted for display
+#[tauri::command]
+pub fn get_bid_totals(
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> BidTotals {
+    get_bid_totals_internal(&state, &session_state)
+}
+
+pub(crate) fn get_bid_totals_internal(state: &BidState, session_state: &SessionState) -> BidTotals {
+    let locale = session_state.get_settings().unwrap_or_default().locale;
+    let meta = state.get_meta();
+    let expired = meta.is_expired(chrono::Local::now().date_naive());
+    let BidMeta { currency, rounding, status, valid_until, version, adjustments, .. } = meta;
+    let shots = state.get_shots();
+
+    let total_hours: f64 = shots.iter().filter_map(|s| s.estimated_hours).sum();
+    let total_cost: f64 = shots.iter().filter_map(|s| s.estimated_cost).sum();
+    let grand_total = total_final_price(&shots, rounding);
+
+    BidTotals {
+        shot_count: shots.len(),
+        departments: department_totals(&shots),
+        total_frames: total_frame_count(&shots),
+        estimate_share: estimation::estimate_share(&shots, rounding),
+        adjustments: adjustments.iter().map(AdjustmentLine::from).collect(),
+        total_hours,
+        total_cost,
+        grand_total,
+        precise_grand_total: total_final_price(&shots, PriceRounding::None),
+        rounding,
+        display: BidTotalsDisplay {
+            total_hours: format_number(total_hours, 1, &locale),
+            total_cost: format_currency(total_cost, &currency, &locale),
+            grand_total: format_currency(grand_total, &currency, &locale),
+        },
+        currency,
+        status,
+        valid_until,
+        version,
+        expired,
+    }
+}
+
+tracked_command! { Bid;
+/// Scale the bid so its grand total lands on a client's budget
+///
+/// Shots with `price_locked` keep their price and the difference is spread
+/// over the rest. Hours never drop below the per-complexity minimums in the
+/// pricing settings. With `dry_run` the report is returned without changing
+/// the bid, so the UI can preview it.
+#[tauri::command]
+pub fn fit_to_budget(
+    target: f64,
+    strategy: BudgetStrategy,
+    dry_run: Option<bool>,
+    state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<BudgetFitReport, AppError> {
+    fit_to_budget_internal(target, &strategy, dry_run.unwrap_or(false), &state, &session_state)
+}
+}
+
+pub(crate) fn fit_to_budget_internal(
+    target: f64,
+    strategy: &BudgetStrategy,
+    dry_run: bool,
+    state: &BidState,
+    session_state: &SessionState,
+) -> Result<BudgetFitReport, AppError> {
+    let min_hours = session_state.get_settings().unwrap_or_default().pricing.min_hours;
+    state.fit_to_budget(target, strategy, &min_hours, dry_run)
+}
+
+tracked_command! { Bid;
+/// Spread a lump sum, e.g. a negotiated discount, over the shots in `scope`
+///
+/// Shares are whole cents and add up to `amount` exactly. Locked and
+/// unpriced shots are left alone. The adjustment is kept in the bid
+/// metadata as its own line until `remove_adjustment` takes it back out.
+/// With `dry_run` the report is returned without changing the bid.
+#[tauri::command]
+pub fn apply_lump_adjustment(
+    amount: f64,
+    scope: AdjustmentScope,
+    method: SplitMethod,
+    label: Option<String>,
+    dry_run: Option<bool>,
+    state: WindowBid<'_>,
+) -> Result<AdjustmentReport, AppError> {
+    state.apply_adjustment(amount, scope, method, label, dry_run.unwrap_or(false))
+}
+}
+
+tracked_command! { Bid;
+/// Undo a lump-sum adjustment, restoring each shot's price to the cent
+#[tauri::command]
+pub fn remove_adjustment(
+    id: String,
+    state: WindowBid<'_>,
+) -> Result<LumpAdjustment, AppError> {
+    state.remove_adjustment(&id)
+}
+}
+
+tracked_command! { Bid;
+/// Find clusters of likely duplicate shots in the current bid
+///
+/// Shots are compared within the same scene by token Jaccard similarity of
+/// their normalized descriptions. Pairs scoring at or above `threshold`
+/// (0.0 - 1.0) are linked into groups. Nothing is removed; the groups are
+/// returned for the user to review and merge.
+#[tauri::command]
+pub fn find_duplicate_shots(
+    threshold: f64,
+    state: WindowBid<'_>,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    find_duplicate_shots_internal(threshold, &state)
+}
+}
+
+pub(crate) fn find_duplicate_shots_internal(
+    threshold: f64,
+    state: &BidState,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(AppError::validation(format!("Threshold must be between 0 and 1, got {}", threshold)));
+    }
+
+    Ok(find_duplicates(&state.get_shots(), threshold))
+}
+
+tracked_command! { Bid;
+/// Attach a reference image (storyboard frame, still) to a shot
+///
+/// The image is copied into the app data directory so the bid no longer
+/// depends on the original file. Returns the path of the stored copy.
+#[tauri::command]
+pub fn attach_reference(
+    shot_id: String,
+    file_path: String,
+    app: AppHandle,
+    state: WindowBid<'_>,
+) -> Result<String, AppError> {
+    state.ensure_writable()?;
+    let root = attachments::attachments_root(&app)?;
+    let bid_id = state.bid_id();
+
+    // Fail before copying anything if the shot doesn't exist
+    if !state.get_shots().iter().any(|s| s.id == shot_id) {
+        return Err(AppError::not_found(format!("Shot {} not found", shot_id)));
+    }
+
+    let (attachment, path) = attachments::store_attachment(&root, &bid_id, &shot_id, Path::new(&file_path))?;
+
+    let stored = attachment.clone();
+    if let Err(e) = state.modify_shot(&shot_id, |shot| {
+        shot.attachments.push(stored);
+        Ok(())
+    }) {
+        let _ = attachments::remove_attachment(&root, &bid_id, &shot_id, &attachment);
+        return Err(e);
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+}
+
+tracked_command! { Bid;
+/// Remove a reference image from a shot and delete the stored copy
+#[tauri::command]
+pub fn remove_reference(
+    shot_id: String,
+    attachment_id: String,
+    app: AppHandle,
+    state: WindowBid<'_>,
+) -> Result<(), AppError> {
+    let root = attachments::attachments_root(&app)?;
+
+    let removed = state.modify_shot(&shot_id, |shot| {
+        let index = shot.attachments
+            .iter()
+            .position(|a| a.id == attachment_id)
+            .ok_or_else(|| AppError::not_found(format!("Attachment {} not found on shot {}", attachment_id, shot_id)))?;
+        Ok(shot.attachments.remove(index))
+    })?;
+
+    attachments::remove_attachment(&root, &state.bid_id(), &shot_id, &removed)
+}
+}
+
+tracked_command! { Bid;
+/// Get the path of a stored reference image
+///
+/// The webview loads it through the asset protocol (`convertFileSrc`).
+#[tauri::command]
+pub fn get_reference_path(
+    shot_id: String,
+    attachment_id: String,
+    app: AppHandle,
+    state: WindowBid<'_>,
+) -> Result<String, AppError> {
+    let root = attachments::attachments_root(&app)?;
+    let shot = state.get_shots()
+        .into_iter()
+        .find(|s| s.id == shot_id)
+        .ok_or_else(|| AppError::not_found(format!("Shot {} not found", shot_id)))?;
+
+    let attachment = shot.attachments
+        .iter()
+        .find(|a| a.id == attachment_id)
+        .ok_or_else(|| AppError::not_found(format!("Attachment {} not found on shot {}", attachment_id, shot_id)))?;
+
+    let path = attachments::attachment_path(&root, &state.bid_id(), &shot_id, attachment);
+    if !path.exists() {
+        return Err(AppError::not_found(format!("Attachment file {} is missing", attachment.filename)));
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+}
+
+tracked_command! { Bid;
 /// Query bid data from Python sidecar
 ///
 /// This allows querying the loaded bid for various information:
@@ -78,21 +1377,23 @@ pub fn get_all_shots(state: State<'_, BidState>) -> Vec<ShotData> {
 /// - most_expensive_shot: Get the most expensive shot
 /// - complexity_breakdown: Get shot counts by complexity
 /// - summary: Get complete bid summary
+///
+/// Identical queries made at once share one call; with `query_cache_ms`
+/// set, answers are reused until a bid is edited, loaded or cleared.
 #[tauri::command]
 pub async fn bid_query(
     query: BidQueryParams,
     sidecar_state: State<'_, SidecarState>,
-) -> Result<Value, String> {
+) -> Result<Value, AppError> {
     log::info!("Bid query: {}", query.query_type);
 
     // Check if sidecar is running
     if !sidecar_state.is_running() {
-        return Err("Python sidecar is not running. Please restart the application.".to_string());
+        return Err(AppError::sidecar_unavailable());
     }
 
     // Get RPC client
-    let rpc_client = sidecar_state.rpc_client()
-        .ok_or_else(|| "Failed to get RPC client".to_string())?;
+    let rpc_client = sidecar_state.client_for(RpcMethod::BidQuery)?;
 
     // Call Python RPC to query bid
     let params = json!({
@@ -100,7 +1401,271 @@ pub async fn bid_query(
         "params": query.params.unwrap_or(json!({}))
     });
 
-    let result = rpc_client.call("bid_query".to_string(), params).await?;
+    // Identical queries share one call, and its answer while the cache lasts
+    let key = query_cache::query_key(&query.query_type, &params["params"]);
+    let timeout = sidecar_state.rpc_timeout(RpcMethod::BidQuery);
+    let result = sidecar_state.queries()
+        .get_or_query(key, || rpc_client.call_detailed(RpcMethod::BidQuery.to_string(), params, timeout))
+        .await?;
 
     Ok(result)
 }
+}
+
+/// Group shots whose descriptions are similar within the same scene
+pub fn find_duplicates(shots: &[ShotData], threshold: f64) -> Vec<DuplicateGroup> {
+    let tokens: Vec<HashSet<String>> = shots
+        .iter()
+        .map(|s| description_tokens(&s.description))
+        .collect();
+
+    // Union-find over shot indices, linking each pair above the threshold
+    let mut parent: Vec<usize> = (0..shots.len()).collect();
+    let mut pairs = Vec::new();
+
+    for i in 0..shots.len() {
+        for j in (i + 1)..shots.len() {
+            if shots[i].scene_number.trim() != shots[j].scene_number.trim() {
+                continue;
+            }
+
+            let similarity = jaccard_similarity(&tokens[i], &tokens[j]);
+            if similarity >= threshold {
+                let (root_i, root_j) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                parent[root_j] = root_i;
+                pairs.push((i, j, similarity));
+            }
+        }
+    }
+
+    // Collect clusters in bid order, keyed by their root
+    let mut groups: Vec<(usize, DuplicateGroup)> = Vec::new();
+
+    for (i, j, similarity) in pairs {
+        let root = find_root(&mut parent, i);
+        let index = match groups.iter().position(|(r, _)| *r == root) {
+            Some(index) => index,
+            None => {
+                groups.push((root, DuplicateGroup {
+                    scene_number: shots[i].scene_number.clone(),
+                    shot_ids: vec![],
+                    pairs: vec![],
+                }));
+                groups.len() - 1
+            }
+        };
+
+        let group = &mut groups[index].1;
+        for k in [i, j] {
+            if !group.shot_ids.contains(&shots[k].id) {
+                group.shot_ids.push(shots[k].id.clone());
+            }
+        }
+        group.pairs.push(DuplicatePair {
+            first_id: shots[i].id.clone(),
+            second_id: shots[j].id.clone(),
+            similarity,
+        });
+    }
+
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Lowercase, strip punctuation, and split a description into a token set
+pub(crate) fn description_tokens(description: &str) -> HashSet<String> {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+pub(crate) fn jaccard_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let intersection = a.intersection(b).count() as f64;
+    let union = a.union(b).count() as f64;
+    intersection / union
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, scene: &str, description: &str) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            scene_number: scene.to_string(),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn task(department: &str, hours: f64, rate_override: Option<f64>) -> TaskLine {
+        TaskLine { department: department.to_string(), hours, rate_override, note: String::new() }
+    }
+
+    #[test]
+    fn test_tasks_drive_hours_and_cost() {
+        let mut itemized = ShotData {
+            estimated_hours: Some(99.0),
+            rate_per_hour: Some(100.0),
+            contingency_percent: 10.0,
+            tasks: vec![task("roto", 8.0, None), task("comp", 16.0, Some(150.0))],
+            ..shot("SH010", "1", "Greenscreen")
+        };
+        itemized.recalculate_pricing();
+
+        assert_eq!(itemized.estimated_hours, Some(24.0));
+        assert_eq!(itemized.estimated_cost, Some(800.0 + 2400.0));
+        assert!((itemized.final_price.unwrap() - 3520.0).abs() < 1e-9);
+
+        // Scaling the shot scales every line
+        itemized.estimated_by = Some(EstimatedBy::Table);
+        itemized.set_hours(12.0);
+        assert_eq!(itemized.tasks[0].hours, 4.0);
+        assert_eq!(itemized.estimated_by, None);
+        assert_eq!(itemized.estimated_cost, Some(400.0 + 1200.0));
+
+        let flat = ShotData { estimated_hours: Some(10.0), rate_per_hour: Some(100.0), ..shot("SH020", "1", "Sky") };
+        let other = ShotData { tasks: vec![task("Roto ", 2.0, Some(50.0))], ..shot("SH030", "2", "Wire") };
+        let totals = department_totals(&[itemized, flat, other]);
+        assert_eq!(totals, vec![
+            DepartmentTotal { department: "roto".to_string(), hours: 6.0, cost: 500.0 },
+            DepartmentTotal { department: "comp".to_string(), hours: 8.0, cost: 1200.0 },
+        ]);
+
+        assert!(validate_tasks(&[task(" ", 1.0, None)]).is_err());
+        assert!(validate_tasks(&[task("comp", -1.0, None)]).is_err());
+        assert!(validate_tasks(&[task("comp", 1.0, Some(f64::NAN))]).is_err());
+    }
+
+    #[test]
+    fn test_duration_estimate_uses_saved_factors() {
+        let state = BidState::default();
+        let timed = |id: &str, frames: u32| ShotData {
+            complexity: "Medium".to_string(),
+            frame_count: Some(frames),
+            fps: Some(24.0),
+            ..shot(id, "1", "Sky")
+        };
+        state.set_shots(vec![timed("SH010", 48), timed("SH020", 72), shot("SH030", "2", "Wire")]).unwrap();
+
+        let session_state = SessionState::default();
+        let mut settings = crate::commands::settings::Settings::default();
+        settings.pricing.hours_per_second = HashMap::from([("medium".to_string(), 1.0)]);
+        session_state.set_settings(settings);
+
+        assert_eq!(estimate_hours_from_duration_internal(None, &state, &session_state).unwrap(), 2);
+        let hours: Vec<_> = state.get_shots().iter().map(|s| s.estimated_hours).collect();
+        assert_eq!(hours, vec![Some(2.0), Some(3.0), None]);
+
+        let totals = get_bid_totals_internal(&state, &session_state);
+        assert_eq!(totals.total_frames, 120);
+    }
+
+    #[test]
+    fn test_duplicates_are_clustered() {
+        let shots = vec![
+            shot("a", "12", "Car explodes on the bridge, debris everywhere"),
+            shot("b", "12", "The car explodes on the bridge; debris everywhere!"),
+            shot("c", "12", "Sky replacement over the city skyline"),
+        ];
+
+        let groups = find_duplicates(&shots, 0.6);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].shot_ids, vec!["a", "b"]);
+        assert_eq!(groups[0].pairs.len(), 1);
+        assert!(groups[0].pairs[0].similarity >= 0.6);
+    }
+
+    #[test]
+    fn test_distinct_descriptions_are_not_clustered() {
+        let shots = vec![
+            shot("a", "3", "Muzzle flash on rifle"),
+            shot("b", "3", "Green screen window composite at night"),
+        ];
+
+        assert!(find_duplicates(&shots, 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_require_same_scene() {
+        let shots = vec![
+            shot("a", "1", "Wire removal on stunt performer"),
+            shot("b", "2", "Wire removal on stunt performer"),
+        ];
+
+        assert!(find_duplicates(&shots, 0.9).is_empty());
+    }
+
+    #[test]
+    fn test_transitive_duplicates_form_one_group() {
+        let shots = vec![
+            shot("a", "5", "dragon flies over castle"),
+            shot("b", "5", "dragon flies over the castle"),
+            shot("c", "5", "dragon flies over the castle walls"),
+        ];
+
+        let groups = find_duplicates(&shots, 0.7);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].shot_ids, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use BidStatus::*;
+
+        for (from, to) in [(Draft, Sent), (Sent, Approved), (Sent, Revised), (Revised, Sent), (Sent, Expired), (Expired, Revised)] {
+            assert!(from.can_become(to), "{:?} -> {:?}", from, to);
+        }
+        for (from, to) in [(Approved, Draft), (Approved, Revised), (Draft, Approved), (Expired, Approved)] {
+            assert!(!from.can_become(to), "{:?} -> {:?}", from, to);
+        }
+
+        let today = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let meta = BidMeta { status: Sent, valid_until: NaiveDate::from_ymd_opt(2026, 3, 9), ..Default::default() };
+        assert!(meta.is_expired(today));
+        assert!(!BidMeta { status: Approved, ..meta.clone() }.is_expired(today));
+        assert!(!BidMeta { valid_until: Some(today), ..meta }.is_expired(today));
+    }
+
+    #[test]
+    fn test_rounding_is_half_up_not_bankers() {
+        // Banker's rounding would give the even neighbour in each case
+        assert_eq!(PriceRounding::NearestDollar.apply(12486.5), 12487.0);
+        assert_eq!(PriceRounding::Nearest10.apply(12485.0), 12490.0);
+        assert_eq!(PriceRounding::Nearest100.apply(1250.0), 1300.0);
+        assert_eq!(PriceRounding::Nearest500.apply(1250.0), 1500.0);
+
+        assert_eq!(PriceRounding::NearestDollar.apply(12487.3326), 12487.0);
+        assert_eq!(PriceRounding::Nearest100.apply(12449.999999999), 12500.0);
+        assert_eq!(PriceRounding::Nearest500.apply(12487.33), 12500.0);
+        assert_eq!(PriceRounding::None.apply(12487.3326), 12487.3326);
+    }
+
+    #[test]
+    fn test_rounded_total_sums_rounded_prices() {
+        let shots: Vec<ShotData> = [1049.0, 1049.0, 1049.0]
+            .into_iter()
+            .map(|price| ShotData { final_price: Some(price), ..Default::default() })
+            .collect();
+
+        assert_eq!(total_final_price(&shots, PriceRounding::None), 3147.0);
+        // Rounding the precise total instead would give 3,100
+        assert_eq!(total_final_price(&shots, PriceRounding::Nearest100), 3000.0);
+    }
+}
@@ -1,24 +1,183 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use serde_json::json;
-use tauri::{Window, State, Emitter};
-use std::time::{SystemTime, UNIX_EPOCH};
-use crate::state::SidecarState;
+use tauri::{Window, State, Manager};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, MutexGuard};
+use crate::assumptions::{AssumptionCategory, AssumptionItem};
+use crate::bid_csv::CsvRows;
+use crate::capabilities::require_model;
+use crate::error::AppError;
+use crate::events::{emit_event, AppEvent};
+use crate::format::{format_currency, format_number};
+use crate::sidecar::RpcMethod;
+use crate::state::last_error::tracked_command;
+use crate::state::{BidState, ErrorDomain, LastErrorState, SessionState, SidecarState, WindowBid};
+use super::bid::{get_bid_totals_internal, total_final_price, ShotData};
+use super::script::export_bid_csv_internal;
+use super::model::ensure_model_ready;
+use super::settings::{ChatOverlapMode, LocaleSettings};
+use super::slash::{self, ExportFormat, ShotEdit, SlashCommand};
+
+/// Error returned when a message overlaps an in-flight one in reject mode
+pub const CHAT_BUSY_ERROR: &str = "Please wait for the current response before sending another message";
+
+/// How long a chat edit waits for confirmation before it lapses
+pub const PENDING_ACTION_TTL: Duration = Duration::from_secs(10 * 60);
 
 /// Chat message from user
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
     pub timestamp: i64,
+    /// Structured action payload returned with the reply, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<serde_json::Value>,
 }
 
 /// Command execution request
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct CommandRequest {
     pub command: String,
     pub args: Vec<String>,
 }
 
+/// Which path produced a chat reply
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatHandler {
+    /// Applied directly to the bid by the Rust pre-parser
+    Local,
+    /// Routed to the Python sidecar
+    Sidecar,
+}
+
+/// Reply to a chat message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatReply {
+    pub content: String,
+    pub handled_by: ChatHandler,
+}
+
+/// The loaded bid as described to the sidecar with each chat message, so
+/// answers are about the data the user sees rather than whatever bid the
+/// sidecar last loaded
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct BidContext {
+    pub bid_id: String,
+    /// File name of the script the bid came from
+    pub script_name: Option<String>,
+    pub project_name: Option<String>,
+    pub client_name: Option<String>,
+    pub currency: String,
+    pub shot_count: usize,
+    pub grand_total: f64,
+    /// The shots the message is about, when it was scoped to some
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<ShotScope>,
+}
+
+/// A subset of shots a chat message is limited to, e.g. the selection
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ShotScope {
+    pub shot_ids: Vec<String>,
+    /// Sum of the scoped shots' final prices with the bid's rounding
+    pub grand_total: f64,
+    pub shots: Vec<ShotData>,
+}
+
+/// Bid edit recognised by the local pre-parser
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalAction {
+    /// Scale prices by a signed percentage
+    AdjustPrice { scene: Option<String>, percent: f64 },
+    /// Set the complexity tier
+    SetComplexity { scene: Option<String>, complexity: String },
+}
+
+/// One shot as a pending chat edit would leave it
+#[derive(Debug, Serialize, Clone, PartialEq, JsonSchema)]
+pub struct ShotChange {
+    pub shot_id: String,
+    pub scene_number: String,
+    pub complexity_before: String,
+    pub complexity_after: String,
+    pub price_before: Option<f64>,
+    pub price_after: Option<f64>,
+}
+
+/// Before and after numbers for a pending chat edit
+#[derive(Debug, Serialize, Clone, PartialEq, JsonSchema)]
+pub struct PreviewDiff {
+    pub shots: Vec<ShotChange>,
+    /// Bid totals with the bid's rounding
+    pub total_before: f64,
+    pub total_after: f64,
+}
+
+/// A chat edit held until the user confirms it, sent as the
+/// `chat-action-pending` event
+#[derive(Debug, Serialize, Clone, JsonSchema)]
+pub struct PendingAction {
+    pub id: String,
+    /// What the edit does, e.g. "Increase prices by 20% for 4 shot(s) in scene 12"
+    pub description: String,
+    pub affected_shot_ids: Vec<String>,
+    pub preview_diff: PreviewDiff,
+    /// RFC 3339
+    pub expires_at: String,
+    #[serde(skip)]
+    action: LocalAction,
+    /// Bid the preview was made for; each window has its own
+    #[serde(skip)]
+    bid_id: String,
+    /// `BidState::edit_count` when the preview was made
+    #[serde(skip)]
+    bid_edits: u64,
+    /// Shots the message was scoped to; the edit leaves the rest alone
+    #[serde(skip)]
+    shot_ids: Option<Vec<String>>,
+    #[serde(skip)]
+    created: Instant,
+}
+
+impl PendingAction {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created) >= PENDING_ACTION_TTL
+    }
+
+    /// Whether the edit is low-risk enough to apply without asking: one
+    /// shot's complexity
+    pub fn is_safe(&self) -> bool {
+        matches!(self.action, LocalAction::SetComplexity { .. }) && self.affected_shot_ids.len() == 1
+    }
+}
+
+/// Guard that lets one chat message be processed at a time
+///
+/// The sidecar handles requests one after another, so overlapping messages
+/// (double submits, key repeat) are either refused or queued in arrival
+/// order depending on `ui.chat_overlap`.
+#[derive(Default)]
+pub struct ChatGate {
+    in_flight: Mutex<()>,
+}
+
+impl ChatGate {
+    /// Wait for (or refuse) the turn to process a message
+    ///
+    /// The returned guard must be held until the reply has been produced.
+    pub async fn acquire(&self, mode: ChatOverlapMode) -> Result<MutexGuard<'_, ()>, AppError> {
+        match mode {
+            ChatOverlapMode::Reject => self.in_flight
+                .try_lock()
+                .map_err(|_| AppError::not_ready(CHAT_BUSY_ERROR)),
+            ChatOverlapMode::Queue => Ok(self.in_flight.lock().await),
+        }
+    }
+}
+
 /// Get current timestamp as Unix seconds
 fn current_timestamp() -> i64 {
     SystemTime::now()
@@ -29,125 +188,303 @@ fn current_timestamp() -> i64 {
 
 /// Send a chat message and get response from LLM
 ///
-/// This calls the Python sidecar which processes the message through:
+/// Slash commands (`/total`, `/help`, ...) and simple numeric edits ("make
+/// scene 12 20% more expensive") are handled locally. Everything else goes
+/// to the Python sidecar which processes the message through:
 /// 1. Chat command processor (pattern matching for queries)
 /// 2. LLM for complex intent parsing
 /// 3. Returns structured action or query result
+///
+/// Edits from either path aren't applied straight away: they're previewed
+/// and held as a `PendingAction` until `confirm_chat_action`, unless
+/// `ui.chat_auto_apply_safe_actions` lets a low-risk one through.
+///
+/// `shot_ids` limits the message to those shots, e.g. "what's the total
+/// for these shots" with a selection: `/total` and chat edits cover only
+/// them, and the sidecar is told which they are.
 #[tauri::command]
 pub async fn send_message(
     message: String,
+    shot_ids: Option<Vec<String>>,
     window: Window,
+    bid_state: WindowBid<'_>,
     sidecar_state: State<'_, SidecarState>,
-) -> Result<String, String> {
-    let timestamp = current_timestamp();
+    session_state: State<'_, SessionState>,
+    chat_gate: State<'_, ChatGate>,
+) -> Result<ChatReply, AppError> {
+    let last_errors = window.state::<LastErrorState>();
+    last_errors.track_async(ErrorDomain::Chat, "send_message", async {
+        let shot_ids = shot_ids.filter(|ids| !ids.is_empty());
+        let overlap = session_state.get_settings().unwrap_or_default().ui.chat_overlap;
+        let _turn = chat_gate.acquire(overlap).await?;
 
-    log::info!("Chat message: {}", message);
+        let timestamp = current_timestamp();
 
-    // Emit user message
-    window.emit("chat-message", ChatMessage {
-        role: "user".to_string(),
-        content: message.clone(),
-        timestamp,
-    }).map_err(|e| e.to_string())?;
+        log::info!("Chat message: {}", message);
 
-    // Check if sidecar is running
-    if !sidecar_state.is_running() {
-        let error_msg = "Python sidecar is not running. Please restart the application.".to_string();
+        // Emit user message
+        let user_message = ChatMessage {
+            role: "user".to_string(),
+            content: message.clone(),
+            timestamp,
+            action: None,
+        };
+        session_state.push_chat_message(user_message.clone());
+        emit_event(&window, AppEvent::ChatMessage(user_message))?;
 
-        window.emit("chat-message", ChatMessage {
-            role: "assistant".to_string(),
-            content: error_msg.clone(),
-            timestamp: current_timestamp(),
-        }).map_err(|e| e.to_string())?;
+        // Slash commands never reach the sidecar, even when they fail to parse
+        let local = match slash::parse(&message) {
+            Some(Ok(command)) => Some(run_slash_command(command, shot_ids.as_deref(), &bid_state, &session_state)),
+            Some(Err(e)) => Some(e.to_string()),
+            // Try the local pre-parser next; fall back to the sidecar if it
+            // doesn't recognise the message or nothing in the Rust-side bid matched
+            None => match parse_local_action(&message) {
+                Some(action) => stage_action(action, shot_ids.as_deref(), &window, &bid_state, &session_state)?,
+                None => None,
+            },
+        };
 
-        return Err(error_msg);
-    }
+        if let Some(content) = local {
+            let assistant_message = ChatMessage {
+                role: "assistant".to_string(),
+                content: content.clone(),
+                timestamp: current_timestamp(),
+                action: None,
+            };
+            session_state.push_chat_message(assistant_message.clone());
+            emit_event(&window, AppEvent::ChatMessage(assistant_message))?;
 
-    // Get RPC client
-    let rpc_client = sidecar_state.rpc_client()
-        .ok_or_else(|| "Failed to get RPC client".to_string())?;
+            return Ok(ChatReply {
+                content,
+                handled_by: ChatHandler::Local,
+            });
+        }
 
-    // Call Python RPC to process chat command
-    let params = json!({
-        "message": message,
-        "bid_context": null  // Python will use loaded bid if available
-    });
+        // The rest needs the LLM: check there is a model and a running sidecar
+        let settings = session_state.get_settings().unwrap_or_default();
+        let unavailable = require_model(&settings)
+            .err()
+            .or_else(|| (!sidecar_state.is_running()).then(AppError::sidecar_unavailable));
+        if let Some(error) = unavailable {
+            emit_event(&window, AppEvent::ChatMessage(ChatMessage {
+                role: "assistant".to_string(),
+                content: error.to_string(),
+                timestamp: current_timestamp(),
+                action: None,
+            }))?;
 
-    let result = rpc_client.call("chat_command".to_string(), params).await?;
+            return Err(error);
+        }
 
-    // Parse response
-    let explanation = result.get("explanation")
-        .and_then(|e| e.as_str())
-        .unwrap_or("Processed")
-        .to_string();
+        ensure_model_ready(&window, &sidecar_state, &settings)?;
 
-    let action_type = result.get("action_type")
-        .and_then(|a| a.as_str())
-        .unwrap_or("unknown")
-        .to_string();
+        // Get RPC client
+        let rpc_client = sidecar_state.client_for(RpcMethod::ChatCommand)?;
 
-    // If there's a query result, format it nicely
-    let response_content = if let Some(query_result) = result.get("query_result") {
-        format_query_response(action_type, query_result)
-    } else {
-        explanation
-    };
+        // Call Python RPC to process chat command
+        let context = bid_context(&bid_state, session_state.get_current_script(), shot_ids.as_deref())?;
+        let mut params = chat_params(&message, context.as_ref(), &bid_state, &sidecar_state);
+        // Edits come back for the user to confirm
+        params["apply_changes"] = json!(false);
+
+        let result = rpc_client.call_detailed(RpcMethod::ChatCommand.to_string(), params, sidecar_state.rpc_timeout(RpcMethod::ChatCommand)).await?;
+
+        // Parse response
+        let explanation = result.get("explanation")
+            .and_then(|e| e.as_str())
+            .unwrap_or("Processed")
+            .to_string();
 
-    // Emit assistant response
-    window.emit("chat-message", ChatMessage {
+        let action_type = result.get("action_type")
+            .and_then(|a| a.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        // Keep the structured payload for the transcript
+        let action = (action_type != "unknown").then(|| result.clone());
+
+        // If there's a query result, format it nicely
+        let response_content = if let Some(query_result) = result.get("query_result") {
+            let locale = session_state.get_settings().unwrap_or_default().locale;
+            format_query_response(action_type, query_result, &locale, &bid_state.get_meta().currency)
+        } else {
+            match sidecar_action(&result) {
+                Some(edit) => match stage_action(edit, shot_ids.as_deref(), &window, &bid_state, &session_state)? {
+                    Some(staged) => format!("{}\n\n{}", explanation, staged),
+                    None => explanation,
+                },
+                None => explanation,
+            }
+        };
+
+        // Emit assistant response
+        let assistant_message = ChatMessage {
+            role: "assistant".to_string(),
+            content: response_content.clone(),
+            timestamp: current_timestamp(),
+            action,
+        };
+        session_state.push_chat_message(assistant_message.clone());
+        emit_event(&window, AppEvent::ChatMessage(assistant_message))?;
+
+        Ok(ChatReply {
+            content: response_content,
+            handled_by: ChatHandler::Sidecar,
+        })
+    }).await
+}
+
+tracked_command! { Chat;
+/// Apply a pending chat edit
+///
+/// Fails with a stale-action error once it has expired or the bid has
+/// changed since it was previewed.
+#[tauri::command]
+pub fn confirm_chat_action(
+    id: String,
+    window: Window,
+    bid_state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
+    let pending = session_state.take_pending_action(&id)
+        .ok_or_else(|| AppError::not_found(format!("No pending chat action '{}'", id)))?;
+    let content = confirm_pending(&pending, &bid_state, Instant::now())?;
+
+    let assistant_message = ChatMessage {
         role: "assistant".to_string(),
-        content: response_content.clone(),
+        content: content.clone(),
         timestamp: current_timestamp(),
-    }).map_err(|e| e.to_string())?;
+        action: None,
+    };
+    session_state.push_chat_message(assistant_message.clone());
+    emit_event(&window, AppEvent::ChatMessage(assistant_message))?;
 
-    Ok(response_content)
+    Ok(content)
+}
 }
 
+tracked_command! { Chat;
+/// Discard a pending chat edit
+#[tauri::command]
+pub fn reject_chat_action(
+    id: String,
+    session_state: State<'_, SessionState>,
+) -> Result<(), AppError> {
+    session_state.take_pending_action(&id)
+        .map(|_| ())
+        .ok_or_else(|| AppError::not_found(format!("No pending chat action '{}'", id)))
+}
+}
+
+tracked_command! { Chat;
 /// Execute a natural language command
 #[tauri::command]
 pub async fn execute_command(
     request: CommandRequest,
     window: Window,
+    bid_state: WindowBid<'_>,
     sidecar_state: State<'_, SidecarState>,
-) -> Result<String, String> {
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
     log::info!("Executing command: {}", request.command);
 
-    window.emit("command-executing", &request)
-        .map_err(|e| e.to_string())?;
+    emit_event(&window, AppEvent::CommandExecuting(request.clone()))?;
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    require_model(&settings)?;
 
     // Check if sidecar is running
     if !sidecar_state.is_running() {
-        return Err("Python sidecar is not running. Please restart the application.".to_string());
+        return Err(AppError::sidecar_unavailable());
     }
 
+    ensure_model_ready(&window, &sidecar_state, &settings)?;
+
     // Get RPC client
-    let rpc_client = sidecar_state.rpc_client()
-        .ok_or_else(|| "Failed to get RPC client".to_string())?;
+    let rpc_client = sidecar_state.client_for(RpcMethod::ChatCommand)?;
 
     // For now, we just route to chat_command
     // In the future, we might have separate command handlers
     let message = format!("{} {}", request.command, request.args.join(" "));
 
-    let params = json!({
-        "message": message,
-        "bid_context": null
-    });
+    let context = bid_context(&bid_state, session_state.get_current_script(), None)?;
+    let params = chat_params(&message, context.as_ref(), &bid_state, &sidecar_state);
 
-    let result = rpc_client.call("chat_command".to_string(), params).await?;
+    let result = rpc_client.call_detailed(RpcMethod::ChatCommand.to_string(), params, sidecar_state.rpc_timeout(RpcMethod::ChatCommand)).await?;
 
     let response = result.get("explanation")
         .and_then(|e| e.as_str())
         .unwrap_or("Command executed")
         .to_string();
 
-    window.emit("command-complete", &response)
-        .map_err(|e| e.to_string())?;
+    emit_event(&window, AppEvent::CommandComplete(response.clone()))?;
 
     Ok(response)
 }
+}
+
+tracked_command! { Chat;
+/// Export the stored chat history as a Markdown transcript
+///
+/// Returns the path written.
+#[tauri::command]
+pub fn export_chat_transcript(
+    output_path: String,
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
+    let history = session_state.snapshot().chat_history;
+    let markdown = render_transcript(&history);
+
+    std::fs::write(&output_path, markdown)
+        .map_err(|e| AppError::io(format!("Failed to write transcript: {}", e)))?;
+
+    Ok(output_path)
+}
+}
+
+/// Render chat messages as Markdown with role headers and local timestamps
+pub fn render_transcript(messages: &[ChatMessage]) -> String {
+    let mut markdown = String::from("# Chat Transcript\n");
+
+    for message in messages {
+        let role = match message.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            other => other,
+        };
+
+        markdown.push_str(&format!("\n## {} ({})\n\n", role, format_local_timestamp(message.timestamp)));
+        markdown.push_str(message.content.trim_end());
+        markdown.push('\n');
+
+        if let Some(ref action) = message.action {
+            let json = serde_json::to_string_pretty(action).unwrap_or_default();
+            markdown.push_str(&format!("\n```json\n{}\n```\n", json));
+        }
+    }
+
+    markdown
+}
+
+/// Format Unix seconds in the local timezone
+fn format_local_timestamp(timestamp: i64) -> String {
+    use chrono::TimeZone;
+
+    chrono::Local.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S %:z").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
 
 /// Format query result for display to user
-fn format_query_response(action_type: String, result: &serde_json::Value) -> String {
+///
+/// Amounts use the user's locale and the bid's currency.
+fn format_query_response(
+    action_type: String,
+    result: &serde_json::Value,
+    locale: &LocaleSettings,
+    currency: &str,
+) -> String {
     match action_type.as_str() {
         "query" => {
             // Try to format various query types
@@ -156,8 +493,10 @@ fn format_query_response(action_type: String, result: &serde_json::Value) -> Str
                 let avg = result.get("average_cost").and_then(|v| v.as_f64()).unwrap_or(0.0);
 
                 return format!(
-                    "Total Budget: ${:.2}\nShots: {}\nAverage: ${:.2}",
-                    total, shot_count, avg
+                    "Total Budget: {}\nShots: {}\nAverage: {}",
+                    format_currency(total, currency, locale),
+                    shot_count,
+                    format_currency(avg, currency, locale)
                 );
             }
 
@@ -178,3 +517,749 @@ fn format_query_response(action_type: String, result: &serde_json::Value) -> Str
         }
     }
 }
+
+/// Recognise simple bid edits without an LLM round trip
+///
+/// Only returns an action when the intent is unambiguous: a price change
+/// needs a percentage, a direction and a price word; a complexity change
+/// needs the word "complexity" and a tier.
+pub fn parse_local_action(message: &str) -> Option<LocalAction> {
+    let lower = message.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '!' | '?'))
+        .map(|t| t.trim_end_matches('.'))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let has = |words: &[&str]| tokens.iter().any(|t| words.contains(t));
+    let scene = parse_scene(&tokens);
+
+    if has(&["complexity"]) {
+        let complexity = tokens.iter().find_map(|t| match *t {
+            "low" | "simple" | "easy" => Some("Low"),
+            "medium" | "moderate" => Some("Medium"),
+            "high" | "complex" | "hard" | "hero" => Some("High"),
+            _ => None,
+        })?;
+
+        return Some(LocalAction::SetComplexity {
+            scene,
+            complexity: complexity.to_string(),
+        });
+    }
+
+    // Percentages that refer to markups rather than prices go to the sidecar
+    if has(&["contingency", "overhead", "hours", "discount"]) {
+        return None;
+    }
+
+    let percent = parse_percent(&tokens)?;
+
+    if !has(&["expensive", "cheaper", "price", "prices", "cost", "costs", "budget"]) {
+        return None;
+    }
+
+    let increase = has(&["more", "increase", "raise", "up", "higher"]);
+    let decrease = has(&["less", "cheaper", "decrease", "reduce", "lower", "down", "cut"]);
+
+    let percent = match (increase, decrease) {
+        (true, false) => percent,
+        (false, true) => -percent,
+        _ => return None,
+    };
+
+    Some(LocalAction::AdjustPrice { scene, percent })
+}
+
+/// Find "scene 12" / "sc 12A" and return the scene number
+fn parse_scene(tokens: &[&str]) -> Option<String> {
+    tokens
+        .windows(2)
+        .find(|pair| matches!(pair[0], "scene" | "scenes" | "sc"))
+        .map(|pair| pair[1].trim_start_matches('#').to_uppercase())
+        .filter(|scene| scene.chars().next().is_some_and(|c| c.is_ascii_digit()))
+}
+
+/// Find "20%" or "20 percent" and return the number
+fn parse_percent(tokens: &[&str]) -> Option<f64> {
+    tokens.iter().enumerate().find_map(|(i, token)| {
+        if let Some(number) = token.strip_suffix('%') {
+            return number.parse().ok();
+        }
+
+        match tokens.get(i + 1) {
+            Some(&"percent") | Some(&"pct") => token.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Run a slash command against the bid and return the reply
+///
+/// Failures (unknown shot, unwritable path) are reported in the reply rather
+/// than as errors, like any other chat answer.
+fn run_slash_command(command: SlashCommand, shot_ids: Option<&[String]>, bid_state: &BidState, session_state: &SessionState) -> String {
+    let locale = session_state.get_settings().unwrap_or_default().locale;
+    let currency = bid_state.get_meta().currency;
+    let money = |value: Option<f64>| value.map_or("—".to_string(), |v| format_currency(v, &currency, &locale));
+
+    // IDs are matched exactly first, then ignoring case ("sh042a")
+    let find_shot = |id: &str| {
+        let shots = bid_state.get_shots();
+        let exact = shots.iter().position(|s| s.id == id);
+        exact
+            .or_else(|| shots.iter().position(|s| s.id.eq_ignore_ascii_case(id)))
+            .map(|i| shots[i].clone())
+    };
+
+    match command {
+        SlashCommand::Help(topic) => slash::help_text(topic.as_deref()),
+        SlashCommand::Total if shot_ids.is_some() => {
+            let scope = match bid_context(bid_state, None, shot_ids) {
+                Ok(context) => context.and_then(|context| context.scope).unwrap_or_default(),
+                Err(e) => return e.to_string(),
+            };
+            let hours: f64 = scope.shots.iter().filter_map(|s| s.estimated_hours).sum();
+            let cost: f64 = scope.shots.iter().filter_map(|s| s.estimated_cost).sum();
+            format!(
+                "{} selected shots · {} hours\nCost: {}\nGrand total: {}",
+                scope.shots.len(), format_number(hours, 1, &locale), money(Some(cost)), money(Some(scope.grand_total)),
+            )
+        }
+        SlashCommand::Total => {
+            let totals = get_bid_totals_internal(bid_state, session_state);
+            let mut reply = format!(
+                "{} shots · {} hours\nCost: {}\nGrand total: {}",
+                totals.shot_count, totals.display.total_hours, totals.display.total_cost, totals.display.grand_total,
+            );
+            if totals.expired {
+                reply.push_str("\nThis quote has expired.");
+            }
+            reply
+        }
+        SlashCommand::Shot(id) => match find_shot(&id) {
+            Some(shot) => format_shot(&shot, &money, &locale),
+            None => format!("Shot {} not found.", id),
+        },
+        SlashCommand::Set { shot_id, edit } => {
+            let Some(shot) = find_shot(&shot_id) else {
+                return format!("Shot {} not found.", shot_id);
+            };
+
+            let result = bid_state.modify_shot(&shot.id, |shot| {
+                match &edit {
+                    ShotEdit::Complexity(tier) => shot.complexity = tier.clone(),
+                    ShotEdit::Hours(hours) => shot.set_hours(*hours),
+                    ShotEdit::Rate(rate) => shot.rate_per_hour = Some(*rate),
+                    ShotEdit::Contingency(percent) => shot.contingency_percent = *percent,
+                    ShotEdit::Overhead(percent) => shot.overhead_percent = *percent,
+                    ShotEdit::Description(text) => shot.description = text.clone(),
+                    ShotEdit::Scene(scene) => shot.scene_number = scene.clone(),
+                }
+                shot.recalculate_pricing();
+                Ok(shot.final_price)
+            });
+
+            match result {
+                Ok(price) => format!("Updated {}. Final price: {}", shot.id, money(price)),
+                Err(e) => e.to_string(),
+            }
+        }
+        SlashCommand::Tag { shot_id, tags } => match find_shot(&shot_id) {
+            Some(shot) => tags_reply(&shot.id, bid_state.add_tags(std::slice::from_ref(&shot.id), &tags)),
+            None => format!("Shot {} not found.", shot_id),
+        },
+        SlashCommand::Untag { shot_id, tags } => match find_shot(&shot_id) {
+            Some(shot) => tags_reply(&shot.id, bid_state.remove_tags(std::slice::from_ref(&shot.id), &tags)),
+            None => format!("Shot {} not found.", shot_id),
+        },
+        SlashCommand::Export { format: ExportFormat::Csv, path } => {
+            let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+                _ => path,
+            };
+            match export_bid_csv_internal(path, CsvRows::default(), false, false, bid_state, session_state) {
+                Ok(path) => format!("Exported {} shots to {}", bid_state.get_shots().len(), path),
+                Err(e) => format!("Export failed: {}", e),
+            }
+        }
+        SlashCommand::Assumptions(category) => assumptions_reply(&bid_state.get_meta().assumptions, category),
+    }
+}
+
+/// The bid's items in `category`, or in every category, for `/assumptions`
+fn assumptions_reply(items: &[AssumptionItem], category: Option<AssumptionCategory>) -> String {
+    let categories = category.map_or(AssumptionCategory::ALL.to_vec(), |c| vec![c]);
+    let sections: Vec<String> = categories
+        .into_iter()
+        .filter_map(|category| {
+            let lines: Vec<String> = items
+                .iter()
+                .filter(|item| item.category == category)
+                .map(|item| {
+                    let internal = if item.included_in_export { "" } else { " (internal)" };
+                    format!("- {}{}", item.text, internal)
+                })
+                .collect();
+            (!lines.is_empty()).then(|| format!("{}:\n{}", category.heading(), lines.join("\n")))
+        })
+        .collect();
+
+    if !sections.is_empty() {
+        return sections.join("\n\n");
+    }
+    match category {
+        Some(category) => format!("No {} recorded for this bid.", category.heading().to_lowercase()),
+        None => "No assumptions or exclusions recorded for this bid.".to_string(),
+    }
+}
+
+/// The shot's tags after `/tag` or `/untag`
+fn tags_reply(id: &str, result: Result<Vec<ShotData>, AppError>) -> String {
+    match result {
+        Ok(updated) => match updated.first().map(|shot| shot.tags.as_slice()) {
+            Some([]) | None => format!("{} has no tags.", id),
+            Some(tags) => format!("{} tags: {}", id, tags.join(", ")),
+        },
+        Err(e) => e.to_string(),
+    }
+}
+
+/// One shot as a short multi-line summary
+fn format_shot(shot: &ShotData, money: &dyn Fn(Option<f64>) -> String, locale: &LocaleSettings) -> String {
+    let hours = shot.estimated_hours.map_or("—".to_string(), |h| format_number(h, 1, locale));
+    let mut lines = vec![format!("Shot {} · Scene {} · {}", shot.id, shot.scene_number, shot.complexity)];
+    if !shot.description.is_empty() {
+        lines.push(shot.description.clone());
+    }
+    if !shot.vfx_types.is_empty() {
+        lines.push(format!("VFX: {}", shot.vfx_types.join(", ")));
+    }
+    if !shot.tags.is_empty() {
+        lines.push(format!("Tags: {}", shot.tags.join(", ")));
+    }
+    lines.push(format!("Hours: {} · Rate: {}", hours, money(shot.rate_per_hour)));
+    lines.push(format!(
+        "Contingency: {}% · Overhead: {}%",
+        shot.contingency_percent, shot.overhead_percent,
+    ));
+    lines.push(format!("Final price: {}", money(shot.final_price)));
+    lines.join("\n")
+}
+
+/// Describe the loaded bid for the sidecar, scoped to `shot_ids` if given;
+/// None when no bid is loaded, leaving the sidecar to its own
+pub fn bid_context(bid_state: &BidState, script_path: Option<String>, shot_ids: Option<&[String]>) -> Result<Option<BidContext>, AppError> {
+    let shots = bid_state.get_shots();
+    if shots.is_empty() && shot_ids.is_none() {
+        return Ok(None);
+    }
+    let meta = bid_state.get_meta();
+
+    let scope = match shot_ids {
+        Some(ids) => {
+            let scoped = ids
+                .iter()
+                .map(|id| shots.iter().find(|s| &s.id == id).cloned()
+                    .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id))))
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(ShotScope {
+                shot_ids: ids.to_vec(),
+                grand_total: total_final_price(&scoped, meta.rounding),
+                shots: scoped,
+            })
+        }
+        None => None,
+    };
+
+    Ok(Some(BidContext {
+        bid_id: bid_state.bid_id(),
+        script_name: script_path.map(|path| {
+            std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(path)
+        }),
+        project_name: meta.project_name,
+        client_name: meta.client_name,
+        currency: meta.currency,
+        shot_count: shots.len(),
+        grand_total: bid_state.grand_total(),
+        scope,
+    }))
+}
+
+/// Params for a `chat_command` call
+fn chat_params(message: &str, context: Option<&BidContext>, bid_state: &BidState, sidecar_state: &SidecarState) -> serde_json::Value {
+    json!({
+        "message": message,
+        "bid_context": context,
+        // Answer questions but don't change a locked bid
+        "read_only": bid_state.read_only_status().read_only,
+        "workspace_dir": sidecar_state.use_workspace(&bid_state.bid_id()),
+    })
+}
+
+/// The bid edit a sidecar reply proposes, for the action types the app applies
+fn sidecar_action(result: &serde_json::Value) -> Option<LocalAction> {
+    let params = result.get("action_params")?;
+    let scene = params.get("scene").and_then(|s| s.as_str()).map(str::to_string);
+
+    match result.get("action_type")?.as_str()? {
+        "adjust_price" => Some(LocalAction::AdjustPrice {
+            scene,
+            percent: params.get("percent")?.as_f64()?,
+        }),
+        "set_complexity" => Some(LocalAction::SetComplexity {
+            scene,
+            complexity: params.get("complexity")?.as_str()?.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Hold a chat edit for confirmation, or apply it if it's low-risk and the
+/// settings allow; returns the reply, or None if no shots matched
+fn stage_action(
+    action: LocalAction,
+    shot_ids: Option<&[String]>,
+    window: &Window,
+    bid_state: &BidState,
+    session_state: &SessionState,
+) -> Result<Option<String>, AppError> {
+    if let Err(e) = bid_state.ensure_writable() {
+        return Ok(Some(e.to_string()));
+    }
+    let Some(pending) = preview_action(action, shot_ids, bid_state) else {
+        return Ok(None);
+    };
+
+    let auto_apply = session_state.get_settings().unwrap_or_default().ui.chat_auto_apply_safe_actions;
+    if auto_apply && pending.is_safe() {
+        return Ok(apply_local_action(&pending.action, shot_ids, bid_state));
+    }
+
+    let reply = format!("{}? Confirm to apply it or reject to leave the bid as is.", pending.description);
+    session_state.add_pending_action(pending.clone());
+    emit_event(window, AppEvent::ChatActionPending(pending))?;
+    Ok(Some(reply))
+}
+
+/// Work out what `action` would change, among `shot_ids` if given, without
+/// touching the bid; None if no shots match
+pub fn preview_action(action: LocalAction, shot_ids: Option<&[String]>, bid_state: &BidState) -> Option<PendingAction> {
+    // Read first: anything that changes the bid from here on makes the preview stale
+    let bid_edits = bid_state.edit_count();
+    let changes = match &action {
+        LocalAction::AdjustPrice { scene, percent } => bid_state.preview_adjust_prices(scene.as_deref(), shot_ids, *percent),
+        LocalAction::SetComplexity { scene, complexity } => bid_state.preview_set_complexity(scene.as_deref(), shot_ids, complexity),
+    };
+    if changes.is_empty() {
+        return None;
+    }
+
+    let rounding = bid_state.get_meta().rounding;
+    let mut shots = bid_state.get_shots();
+    let total_before = total_final_price(&shots, rounding);
+    for (_, after) in &changes {
+        if let Some(shot) = shots.iter_mut().find(|s| s.id == after.id) {
+            *shot = after.clone();
+        }
+    }
+    let total_after = total_final_price(&shots, rounding);
+
+    let created = Instant::now();
+    let expires_at = chrono::Utc::now() + chrono::Duration::from_std(PENDING_ACTION_TTL).unwrap_or_default();
+
+    Some(PendingAction {
+        id: uuid::Uuid::new_v4().to_string(),
+        description: describe_action(&action, changes.len(), false),
+        affected_shot_ids: changes.iter().map(|(before, _)| before.id.clone()).collect(),
+        preview_diff: PreviewDiff {
+            shots: changes
+                .into_iter()
+                .map(|(before, after)| ShotChange {
+                    shot_id: before.id,
+                    scene_number: before.scene_number,
+                    complexity_before: before.complexity,
+                    complexity_after: after.complexity,
+                    price_before: before.final_price,
+                    price_after: after.final_price,
+                })
+                .collect(),
+            total_before,
+            total_after,
+        },
+        expires_at: expires_at.to_rfc3339(),
+        action,
+        bid_id: bid_state.bid_id(),
+        bid_edits,
+        shot_ids: shot_ids.map(<[String]>::to_vec),
+        created,
+    })
+}
+
+/// Apply a pending edit unless it has expired or the bid moved on since
+/// the preview; returns the reply
+pub fn confirm_pending(pending: &PendingAction, bid_state: &BidState, now: Instant) -> Result<String, AppError> {
+    if pending.is_expired(now) {
+        return Err(AppError::stale_action("This change expired; ask again to get a fresh preview"));
+    }
+    if pending.bid_id != bid_state.bid_id() {
+        return Err(AppError::stale_action("This change was proposed for a bid in another window"));
+    }
+    if pending.bid_edits != bid_state.edit_count() {
+        return Err(AppError::stale_action("The bid changed since this was proposed; ask again to get a fresh preview"));
+    }
+    bid_state.ensure_writable()?;
+
+    Ok(apply_local_action(&pending.action, pending.shot_ids.as_deref(), bid_state)
+        .unwrap_or_else(|| "No shots matched, so nothing changed".to_string()))
+}
+
+/// Apply a parsed action to the bid, returning the reply if any shots matched
+fn apply_local_action(action: &LocalAction, shot_ids: Option<&[String]>, bid_state: &BidState) -> Option<String> {
+    let count = match action {
+        LocalAction::AdjustPrice { scene, percent } => bid_state.adjust_prices(scene.as_deref(), shot_ids, *percent),
+        LocalAction::SetComplexity { scene, complexity } => bid_state.set_complexity(scene.as_deref(), shot_ids, complexity),
+    };
+
+    (count > 0).then(|| describe_action(action, count, true))
+}
+
+/// "Increased prices by 20% for 4 shot(s) in scene 12", or "Increase ..."
+/// for an edit not applied yet
+fn describe_action(action: &LocalAction, count: usize, applied: bool) -> String {
+    let scene = match action {
+        LocalAction::AdjustPrice { scene, .. } | LocalAction::SetComplexity { scene, .. } => scene,
+    };
+    let target = match scene {
+        Some(scene) => format!("{} shot(s) in scene {}", count, scene),
+        None => format!("{} shot(s)", count),
+    };
+
+    match action {
+        LocalAction::AdjustPrice { percent, .. } if *percent >= 0.0 => {
+            let verb = if applied { "Increased" } else { "Increase" };
+            format!("{} prices by {}% for {}", verb, percent, target)
+        }
+        LocalAction::AdjustPrice { percent, .. } => {
+            let verb = if applied { "Reduced" } else { "Reduce" };
+            format!("{} prices by {}% for {}", verb, -percent, target)
+        }
+        LocalAction::SetComplexity { complexity, .. } => {
+            format!("Set complexity to {} for {}", complexity, target)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::commands::bid::TaskLine;
+
+    #[tokio::test]
+    async fn test_overlapping_message_rejected() {
+        let gate = ChatGate::default();
+
+        let first = gate.acquire(ChatOverlapMode::Reject).await.unwrap();
+        let second = gate.acquire(ChatOverlapMode::Reject).await;
+        assert_eq!(second.unwrap_err(), AppError::not_ready(CHAT_BUSY_ERROR));
+
+        // Once the first reply is done the next message goes through
+        drop(first);
+        assert!(gate.acquire(ChatOverlapMode::Reject).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_message_queued() {
+        let gate = Arc::new(ChatGate::default());
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let first = gate.acquire(ChatOverlapMode::Queue).await.unwrap();
+
+        let queued = {
+            let gate = gate.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _turn = gate.acquire(ChatOverlapMode::Queue).await.unwrap();
+                order.lock().unwrap().push("second");
+            })
+        };
+
+        // The second message waits while the first is processing
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!queued.is_finished());
+        order.lock().unwrap().push("first");
+        drop(first);
+
+        queued.await.unwrap();
+        assert_eq!(*order.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_parses_percentage_increase_for_scene() {
+        let action = parse_local_action("Make scene 12 shots 20% more expensive");
+
+        assert_eq!(action, Some(LocalAction::AdjustPrice {
+            scene: Some("12".to_string()),
+            percent: 20.0,
+        }));
+    }
+
+    #[test]
+    fn test_parses_percentage_decrease_without_scene() {
+        let action = parse_local_action("reduce the price of everything by 12.5 percent");
+
+        assert_eq!(action, Some(LocalAction::AdjustPrice {
+            scene: None,
+            percent: -12.5,
+        }));
+    }
+
+    #[test]
+    fn test_parses_scene_filter() {
+        let action = parse_local_action("set scene 4A complexity to high");
+
+        assert_eq!(action, Some(LocalAction::SetComplexity {
+            scene: Some("4A".to_string()),
+            complexity: "High".to_string(),
+        }));
+        assert_eq!(parse_scene(&["in", "sc", "#7"]), Some("7".to_string()));
+        assert_eq!(parse_scene(&["the", "scene", "with", "cars"]), None);
+    }
+
+    #[test]
+    fn test_query_totals_use_locale() {
+        let result = json!({ "total_budget": 1234567.5, "shot_count": 3, "average_cost": 411522.5 });
+
+        let us = format_query_response("query".to_string(), &result, &LocaleSettings::default(), "USD");
+        assert_eq!(us, "Total Budget: $1,234,567.50\nShots: 3\nAverage: $411,522.50");
+
+        let de = format_query_response("query".to_string(), &result, &LocaleSettings::for_locale("de-DE"), "EUR");
+        assert_eq!(de, "Total Budget: 1.234.567,50\u{a0}€\nShots: 3\nAverage: 411.522,50\u{a0}€");
+    }
+
+    #[test]
+    fn test_transcript_markdown_structure() {
+        use chrono::TimeZone;
+
+        let messages = vec![
+            ChatMessage {
+                role: "user".to_string(),
+                content: "What's the total?".to_string(),
+                timestamp: 1_760_000_000,
+                action: None,
+            },
+            ChatMessage {
+                role: "assistant".to_string(),
+                content: "Total Budget: $1000.00".to_string(),
+                timestamp: 1_760_000_005,
+                action: Some(json!({ "action_type": "query" })),
+            },
+        ];
+
+        let markdown = render_transcript(&messages);
+        let expected_time = chrono::Local.timestamp_opt(1_760_000_000, 0).unwrap()
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string();
+
+        assert!(markdown.starts_with("# Chat Transcript\n"));
+        assert!(markdown.contains(&format!("## User ({})\n\nWhat's the total?\n", expected_time)));
+        assert!(markdown.contains("## Assistant ("));
+        assert!(markdown.contains("```json\n{\n  \"action_type\": \"query\"\n}\n```"));
+        assert_eq!(markdown.matches("## ").count(), 2);
+    }
+
+    #[test]
+    fn test_ambiguous_messages_fall_back() {
+        assert_eq!(parse_local_action("increase contingency by 5%"), None);
+        assert_eq!(parse_local_action("what is 20% of the budget"), None);
+        assert_eq!(parse_local_action("make scene 3 more expensive"), None);
+        assert_eq!(parse_local_action("What's the most expensive shot?"), None);
+    }
+
+    #[test]
+    fn test_slash_commands_edit_the_bid() {
+        let bid_state = BidState::default();
+        let session_state = SessionState::default();
+        bid_state.set_shots(vec![ShotData {
+            id: "SH042A".to_string(),
+            scene_number: "12".to_string(),
+            complexity: "Low".to_string(),
+            estimated_hours: Some(10.0),
+            rate_per_hour: Some(100.0),
+            ..Default::default()
+        }]).unwrap();
+        let run = |message: &str| run_slash_command(slash::parse(message).unwrap().unwrap(), None, &bid_state, &session_state);
+
+        assert_eq!(run("/set sh042a hours 12"), "Updated SH042A. Final price: $1,200.00");
+        run("/set SH042A complexity high");
+        assert_eq!(bid_state.get_shots()[0].complexity, "High");
+
+        let shot = run("/shot sh042a");
+        assert!(shot.starts_with("Shot SH042A · Scene 12 · High"), "{}", shot);
+        assert!(shot.contains("Hours: 12.0"), "{}", shot);
+        assert_eq!(run("/total"), "1 shots · 12.0 hours\nCost: $1,200.00\nGrand total: $1,200.00");
+        assert_eq!(run("/shot SH999"), "Shot SH999 not found.");
+
+        assert_eq!(run("/exclusions"), "No exclusions recorded for this bid.");
+        let mut internal = AssumptionItem::new("Pad the crane shots.", AssumptionCategory::Note);
+        internal.included_in_export = false;
+        bid_state.add_assumptions(vec![
+            AssumptionItem::new("Plates delivered as EXR.", AssumptionCategory::Assumption),
+            AssumptionItem::new("Stereo conversion.", AssumptionCategory::Exclusion),
+            internal,
+        ]).unwrap();
+        assert_eq!(run("/exclusions"), "Exclusions:\n- Stereo conversion.");
+        assert_eq!(
+            run("/assumptions"),
+            "Assumptions:\n- Plates delivered as EXR.\n\nExclusions:\n- Stereo conversion.\n\nNotes:\n- Pad the crane shots. (internal)"
+        );
+    }
+
+    #[test]
+    fn test_set_hours_scales_task_lines() {
+        let bid_state = BidState::default();
+        let session_state = SessionState::default();
+        let task = |department: &str, hours: f64| TaskLine { department: department.to_string(), hours, rate_override: None, note: String::new() };
+        bid_state.set_shots(vec![ShotData {
+            id: "SH010".to_string(),
+            rate_per_hour: Some(100.0),
+            tasks: vec![task("roto", 4.0), task("comp", 6.0)],
+            ..Default::default()
+        }]).unwrap();
+
+        let reply = run_slash_command(slash::parse("/set SH010 hours 20").unwrap().unwrap(), None, &bid_state, &session_state);
+        assert_eq!(reply, "Updated SH010. Final price: $2,000.00");
+        let shot = &bid_state.get_shots()[0];
+        assert_eq!(shot.estimated_hours, Some(20.0));
+        assert_eq!(shot.tasks.iter().map(|t| t.hours).collect::<Vec<_>>(), [8.0, 12.0]);
+    }
+
+    fn priced_shot(id: &str, scene: &str) -> ShotData {
+        let mut shot = ShotData {
+            id: id.to_string(),
+            scene_number: scene.to_string(),
+            complexity: "Low".to_string(),
+            estimated_hours: Some(10.0),
+            rate_per_hour: Some(100.0),
+            ..Default::default()
+        };
+        shot.recalculate_pricing();
+        shot
+    }
+
+    #[test]
+    fn test_pending_action_previews_without_applying() {
+        let bid_state = BidState::default();
+        bid_state.set_shots(vec![priced_shot("SH010", "12"), priced_shot("SH020", "12"), priced_shot("SH030", "13")]).unwrap();
+
+        let action = LocalAction::AdjustPrice { scene: Some("12".to_string()), percent: 20.0 };
+        let pending = preview_action(action.clone(), None, &bid_state).unwrap();
+        assert_eq!(pending.description, "Increase prices by 20% for 2 shot(s) in scene 12");
+        assert_eq!(pending.affected_shot_ids, vec!["SH010", "SH020"]);
+        assert_eq!(pending.preview_diff.total_before, 3000.0);
+        assert_eq!(pending.preview_diff.total_after, 3400.0);
+        assert_eq!(pending.preview_diff.shots[0].price_after, Some(1200.0));
+        assert!(!pending.is_safe());
+        // Nothing changes until it's confirmed
+        assert_eq!(bid_state.get_shots()[0].final_price, Some(1000.0));
+
+        let reply = confirm_pending(&pending, &bid_state, Instant::now()).unwrap();
+        assert_eq!(reply, "Increased prices by 20% for 2 shot(s) in scene 12");
+        assert_eq!(bid_state.get_shots()[0].final_price, Some(1200.0));
+
+        assert!(preview_action(LocalAction::SetComplexity { scene: Some("99".to_string()), complexity: "High".to_string() }, None, &bid_state).is_none());
+        let single = preview_action(LocalAction::SetComplexity { scene: Some("13".to_string()), complexity: "High".to_string() }, None, &bid_state).unwrap();
+        assert!(single.is_safe());
+    }
+
+    #[test]
+    fn test_pending_action_goes_stale() {
+        let bid_state = BidState::default();
+        bid_state.set_shots(vec![priced_shot("SH010", "12")]).unwrap();
+        let action = LocalAction::SetComplexity { scene: None, complexity: "High".to_string() };
+
+        let pending = preview_action(action.clone(), None, &bid_state).unwrap();
+        let late = Instant::now() + PENDING_ACTION_TTL;
+        assert!(matches!(confirm_pending(&pending, &bid_state, late), Err(AppError::StaleAction { .. })));
+
+        bid_state.adjust_prices(None, None, 10.0);
+        assert!(matches!(confirm_pending(&pending, &bid_state, Instant::now()), Err(AppError::StaleAction { .. })));
+        assert_eq!(bid_state.get_shots()[0].complexity, "Low");
+
+        // A preview from one window can't be confirmed against another's bid
+        let other_window = BidState::default();
+        other_window.set_shots(vec![priced_shot("SH010", "12")]).unwrap();
+        let pending = preview_action(action.clone(), None, &bid_state).unwrap();
+        assert!(matches!(confirm_pending(&pending, &other_window, Instant::now()), Err(AppError::StaleAction { .. })));
+
+        let session_state = SessionState::default();
+        let fresh = preview_action(action, None, &bid_state).unwrap();
+        session_state.add_pending_action(fresh.clone());
+        assert!(session_state.take_pending_action(&fresh.id).is_some());
+        assert!(session_state.take_pending_action(&fresh.id).is_none());
+    }
+
+    #[test]
+    fn test_scoped_messages_leave_other_shots_alone() {
+        let bid_state = BidState::default();
+        let session_state = SessionState::default();
+        bid_state.set_shots(vec![priced_shot("SH010", "12"), priced_shot("SH020", "12"), priced_shot("SH030", "13")]).unwrap();
+        let selection = ["SH010".to_string(), "SH030".to_string()];
+
+        let total = run_slash_command(SlashCommand::Total, Some(&selection), &bid_state, &session_state);
+        assert_eq!(total, "2 selected shots · 20.0 hours\nCost: $2,000.00\nGrand total: $2,000.00");
+        let missing = run_slash_command(SlashCommand::Total, Some(&["SH999".to_string()]), &bid_state, &session_state);
+        assert!(missing.contains("SH999"), "{}", missing);
+
+        let action = LocalAction::AdjustPrice { scene: Some("12".to_string()), percent: 20.0 };
+        let pending = preview_action(action, Some(&selection), &bid_state).unwrap();
+        assert_eq!(pending.affected_shot_ids, vec!["SH010"]);
+        assert_eq!(pending.preview_diff.total_after, 3200.0);
+
+        confirm_pending(&pending, &bid_state, Instant::now()).unwrap();
+        let prices: Vec<Option<f64>> = bid_state.get_shots().iter().map(|s| s.final_price).collect();
+        assert_eq!(prices, [Some(1200.0), Some(1000.0), Some(1000.0)]);
+    }
+
+    #[test]
+    fn test_chat_params_carry_bid_context() {
+        let bid_state = BidState::default();
+        let sidecar_state = SidecarState::default();
+        assert_eq!(bid_context(&bid_state, None, None).unwrap(), None);
+
+        bid_state.set_shots(vec![priced_shot("SH010", "12"), priced_shot("SH020", "12"), priced_shot("SH030", "13")]).unwrap();
+        let context = bid_context(&bid_state, Some("/scripts/heist_v3.pdf".to_string()), None).unwrap();
+        let params = chat_params("what's the total?", context.as_ref(), &bid_state, &sidecar_state);
+
+        assert_eq!(params["message"], "what's the total?");
+        assert_eq!(params["bid_context"]["bid_id"], json!(bid_state.bid_id()));
+        assert_eq!(params["bid_context"]["script_name"], "heist_v3.pdf");
+        assert_eq!(params["bid_context"]["shot_count"], 3);
+        assert_eq!(params["bid_context"]["grand_total"], 3000.0);
+        assert!(params["bid_context"].get("scope").is_none());
+
+        let ids = vec!["SH030".to_string(), "SH010".to_string()];
+        let scoped = bid_context(&bid_state, None, Some(&ids)).unwrap();
+        let params = chat_params("what's the total for these shots?", scoped.as_ref(), &bid_state, &sidecar_state);
+        assert_eq!(params["bid_context"]["scope"]["shot_ids"], json!(["SH030", "SH010"]));
+        assert_eq!(params["bid_context"]["scope"]["grand_total"], 2000.0);
+        assert_eq!(params["bid_context"]["scope"]["shots"][0]["id"], "SH030");
+
+        let missing = bid_context(&bid_state, None, Some(&["SH999".to_string()]));
+        assert!(matches!(missing, Err(AppError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_sidecar_edits_are_recognised() {
+        let adjust = json!({ "action_type": "adjust_price", "action_params": { "scene": "4", "percent": -10 } });
+        assert_eq!(sidecar_action(&adjust), Some(LocalAction::AdjustPrice { scene: Some("4".to_string()), percent: -10.0 }));
+
+        let complexity = json!({ "action_type": "set_complexity", "action_params": { "complexity": "Hero" } });
+        assert_eq!(sidecar_action(&complexity), Some(LocalAction::SetComplexity { scene: None, complexity: "Hero".to_string() }));
+
+        assert_eq!(sidecar_action(&json!({ "action_type": "query", "action_params": {} })), None);
+        assert_eq!(sidecar_action(&json!({ "action_type": "adjust_price" })), None);
+    }
+}
@@ -1,6 +1,11 @@
 // Command modules
 pub mod bid;
 pub mod chat;
+pub mod model;
+pub mod palette;
+pub mod project;
 pub mod script;
+pub mod session;
 pub mod settings;
 pub mod setup;
+pub mod slash;
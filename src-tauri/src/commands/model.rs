@@ -0,0 +1,638 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use crate::capabilities::{capabilities_for, model_configured, require_model, Capabilities};
+use crate::commands::settings::Settings;
+use crate::error::AppError;
+use crate::events::{emit_event, AppEvent, InsufficientMemory, ModelCorrupt, ModelLoading};
+use crate::gguf::{self, ModelInfo, ModelInfoSource, GGUF_MAGIC};
+use crate::memory::{memory_status, models_that_fit, MemoryStatus};
+use crate::model_health::{self, check_model_path, model_expected, remember_known_good, FoundModel, ModelUnavailable};
+use crate::setup_wizard::{check_model_integrity, get_default_model_path, ModelIntegrity};
+use crate::sidecar::{MethodMetrics, PoolStats, ProtocolInfo, RpcMethod};
+use crate::sidecar::rpc::RPC_METHOD_NOT_FOUND_CODE;
+use crate::state::last_error::tracked_command;
+use crate::state::{ModelState, SessionState, SidecarState};
+
+/// Environment variable naming the `rpc_server.py` to run, ahead of settings
+pub const SIDECAR_PATH_ENV: &str = "VFX_SIDECAR_PATH";
+
+/// Sidecar process and model status
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SidecarStatus {
+    pub running: bool,
+    /// Running with the model loaded
+    #[serde(default)]
+    pub ready: bool,
+    pub model_state: ModelState,
+    /// What the sidecar said it speaks; None until it has been asked
+    #[serde(default)]
+    pub protocol: Option<ProtocolInfo>,
+    /// Why the model can't be opened, until it can again
+    #[serde(default)]
+    pub model_unavailable: Option<ModelUnavailable>,
+}
+
+/// Get the sidecar process and model status
+#[tauri::command]
+pub fn get_sidecar_status(sidecar_state: State<'_, SidecarState>) -> SidecarStatus {
+    SidecarStatus {
+        running: sidecar_state.is_running(),
+        ready: sidecar_state.is_ready(),
+        model_state: sidecar_state.model_state(),
+        protocol: sidecar_state.protocol_info(),
+        model_unavailable: sidecar_state.model_unavailable(),
+    }
+}
+
+/// Which features work right now; without a model the LLM ones are off
+#[tauri::command]
+pub fn get_capabilities(
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Capabilities {
+    let settings = session_state.get_settings().unwrap_or_default();
+    capabilities_for(model_configured(&settings), sidecar_state.is_running())
+}
+
+/// RPC latency and worker load for the debug panel
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RpcMetricsReport {
+    /// Slowest p95 first
+    pub methods: Vec<MethodMetrics>,
+    pub pool: PoolStats,
+}
+
+/// Per-method RPC latency and the call queue, for the debug panel
+#[tauri::command]
+pub fn get_rpc_metrics(sidecar_state: State<'_, SidecarState>) -> RpcMetricsReport {
+    RpcMetricsReport {
+        methods: sidecar_state.rpc_metrics(),
+        pool: sidecar_state.rpc_pool_stats(),
+    }
+}
+
+/// Clear the RPC latency counters
+#[tauri::command]
+pub fn reset_rpc_metrics(sidecar_state: State<'_, SidecarState>) {
+    sidecar_state.reset_rpc_metrics();
+}
+
+tracked_command! { Sidecar;
+/// Load the configured model into the sidecar ahead of the first LLM call
+///
+/// Emits `model-loading` when the load starts (the sidecar reports further
+/// progress under the same event) and `model-ready` once it is loaded. When
+/// too little memory is available, fails with `insufficient_memory` and emits
+/// `insufficient-memory` instead, unless `llm.skip_memory_check` is set.
+#[tauri::command]
+pub async fn preload_model(app: AppHandle) -> Result<ModelState, AppError> {
+    preload_model_internal(&app).await
+}
+}
+
+/// Available memory and what the configured model needs, for the RAM
+/// indicator
+#[tauri::command]
+pub fn get_memory_status(session_state: State<'_, SessionState>) -> MemoryStatus {
+    memory_status(&session_state.get_settings().unwrap_or_default())
+}
+
+tracked_command! { Sidecar;
+/// Architecture, size, quantization and context length of the model
+///
+/// Asked of the sidecar, or read from the GGUF header when the sidecar isn't
+/// running or doesn't advertise `model_info`. Cached until the sidecar restarts.
+#[tauri::command]
+pub async fn get_model_info(
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<ModelInfo, AppError> {
+    if let Some(info) = sidecar_state.model_info() {
+        return Ok(info);
+    }
+
+    let from_sidecar = match sidecar_state.client_for(RpcMethod::ModelInfo).ok() {
+        Some(rpc_client) => {
+            match rpc_client.call_detailed(RpcMethod::ModelInfo.to_string(), json!({}), sidecar_state.rpc_timeout(RpcMethod::ModelInfo)).await {
+                Ok(result) => Some(model_info_from_result(&result)),
+                Err(e) if e.code == RPC_METHOD_NOT_FOUND_CODE => None,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        None => None,
+    };
+
+    let info = match from_sidecar {
+        Some(info) => info,
+        None => {
+            let settings = session_state.get_settings().unwrap_or_default();
+            require_model(&settings)?;
+            let model_path = resolve_model_path(&settings);
+            tauri::async_runtime::spawn_blocking(move || gguf::read_model_info(&model_path))
+                .await
+                .map_err(|e| AppError::io(format!("Reading the model header failed: {}", e)))?
+                .map_err(AppError::io)?
+        }
+    };
+
+    sidecar_state.set_model_info(Some(info.clone()));
+    Ok(info)
+}
+}
+
+fn model_info_from_result(result: &serde_json::Value) -> ModelInfo {
+    let text = |key: &str| result.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let number = |key: &str| result.get(key).and_then(|v| v.as_u64());
+
+    ModelInfo {
+        architecture: text("architecture"),
+        name: text("name"),
+        parameter_count: number("parameter_count"),
+        quantization: text("quantization"),
+        context_length: number("context_length"),
+        source: ModelInfoSource::Sidecar,
+    }
+}
+
+tracked_command! { Sidecar;
+/// Unload the model to free its memory while doing non-LLM work
+#[tauri::command]
+pub async fn unload_model(sidecar_state: State<'_, SidecarState>) -> Result<(), String> {
+    unload_model_internal(&sidecar_state).await
+}
+}
+
+pub(crate) async fn unload_model_internal(sidecar_state: &SidecarState) -> Result<(), String> {
+    if !sidecar_state.is_running() {
+        return Err("Python sidecar is not running. Please restart the application.".to_string());
+    }
+
+    let rpc_client = sidecar_state.client_for(RpcMethod::UnloadModel)?;
+
+    rpc_client.call(RpcMethod::UnloadModel.to_string(), json!({}), sidecar_state.rpc_timeout(RpcMethod::UnloadModel)).await?;
+    sidecar_state.set_model_state(ModelState::NotLoaded);
+
+    Ok(())
+}
+
+tracked_command! { Sidecar;
+/// Point the app at a GGUF model without re-running setup
+///
+/// Saves the path in the settings, where the sidecar's environment picks it
+/// up, and restarts the sidecar so the new model is loaded. Returns the applied (canonical) path.
+#[tauri::command]
+pub async fn set_model_path(path: String, app: AppHandle) -> Result<String, String> {
+    set_model_path_internal(&app, &path).await
+}
+}
+
+tracked_command! { Sidecar;
+/// Switch to a copy of the model found by `rescan_for_model`
+///
+/// As `set_model_path`; also restarts a sidecar left stopped while the model
+/// was unavailable.
+#[tauri::command]
+pub async fn set_active_model(path: String, app: AppHandle) -> Result<String, String> {
+    set_model_path_internal(&app, &path).await
+}
+}
+
+async fn set_model_path_internal(app: &AppHandle, path: &str) -> Result<String, String> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
+    let model_path = validate_model_file(Path::new(path))?;
+    let applied = model_path.to_string_lossy().to_string();
+
+    let mut settings = session_state.get_settings().unwrap_or_default();
+    apply_model_path(&mut settings, &model_path);
+    sidecar_state.set_llm_settings(settings.llm.clone());
+    session_state.set_settings(settings);
+
+    sidecar_state.set_model_unavailable(None);
+    restart_sidecar(app).await
+        .map_err(|e| format!("Model path saved, but the sidecar failed to restart: {}", e))?;
+    sidecar_state.negotiate_protocol().await;
+
+    if sidecar_state.is_running() {
+        preload_model_internal(app).await?;
+    }
+
+    Ok(applied)
+}
+
+tracked_command! { Sidecar;
+/// Look for the model after `model-unavailable`: known-good paths, the model
+/// directory and mounted drives
+///
+/// Copies are listed known-good first; pass one to `set_active_model`.
+#[tauri::command]
+pub async fn rescan_for_model(
+    session_state: State<'_, SessionState>,
+) -> Result<Vec<FoundModel>, String> {
+    let settings = session_state.get_settings().unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || model_health::rescan_for_model(&settings))
+        .await
+        .map_err(|e| format!("Model rescan failed: {}", e))
+}
+}
+
+/// `require_model`, reporting a model that has gone missing
+///
+/// The reason is kept on the sidecar state, which holds off restarts, and
+/// `model-unavailable` is emitted. Once the model is back, a sidecar left
+/// stopped meanwhile is started again.
+pub(crate) async fn require_available_model(app: &AppHandle, settings: &Settings) -> Result<(), AppError> {
+    let sidecar_state: State<SidecarState> = app.state();
+    if model_expected(settings) {
+        let model_path = resolve_model_path(settings);
+        if let Err(unavailable) = check_model_path(&model_path) {
+            report_model_unavailable(app, &unavailable);
+            return Err(AppError::model_unavailable(unavailable));
+        }
+        if sidecar_state.set_model_unavailable(None).is_some() && !sidecar_state.is_running() {
+            log::info!("Model is available again at {}; restarting the sidecar", model_path.display());
+            restart_sidecar(app).await.map_err(|e| AppError::SidecarUnavailable { message: e })?;
+            sidecar_state.negotiate_protocol().await;
+        }
+    }
+    require_model(settings)
+}
+
+fn report_model_unavailable(app: &AppHandle, unavailable: &ModelUnavailable) {
+    log::warn!("{}", unavailable.message);
+    app.state::<SidecarState>().set_model_unavailable(Some(unavailable.clone()));
+    emit_event(app, AppEvent::ModelUnavailable(unavailable.clone())).ok();
+}
+
+/// Check that `path` is a readable GGUF model file
+pub(crate) fn validate_model_file(path: &Path) -> Result<PathBuf, String> {
+    if path.as_os_str().is_empty() {
+        return Err("Model path is empty".to_string());
+    }
+    if !path.is_file() {
+        return Err(format!("Model file not found: {}", path.display()));
+    }
+
+    let is_gguf = path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"));
+    if !is_gguf {
+        return Err("Model file must have a .gguf extension".to_string());
+    }
+
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|e| format!("Failed to read model file: {}", e))?;
+
+    if &magic != GGUF_MAGIC {
+        return Err("File is not a GGUF model (bad header)".to_string());
+    }
+
+    path.canonicalize()
+        .map_err(|e| format!("Failed to resolve model path: {}", e))
+}
+
+/// Record `model_path` as the model to load
+///
+/// The recorded checksum belongs to the previous file, so it is dropped
+/// when the model changes.
+fn apply_model_path(settings: &mut Settings, model_path: &Path) {
+    if resolve_model_path(settings) != model_path {
+        settings.llm.model_sha256 = None;
+    }
+    settings.llm.model_path = Some(model_path.to_string_lossy().to_string());
+    if let Some(name) = model_path.file_name() {
+        settings.llm.model_name = name.to_string_lossy().to_string();
+    }
+}
+
+/// Model chosen with `set_model_path`, falling back to the setup default
+/// in the model directory
+pub(crate) fn resolve_model_path(settings: &Settings) -> PathBuf {
+    settings.llm.model_path
+        .as_deref()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_default_model_path(&settings.paths.model_dir))
+}
+
+/// Drop a checksum left over from `previous` once `settings` points at
+/// another model file, unless a new checksum came with it
+pub(crate) fn forget_stale_model_hash(previous: &Settings, settings: &mut Settings) {
+    if resolve_model_path(previous) != resolve_model_path(settings)
+        && previous.llm.model_sha256 == settings.llm.model_sha256
+    {
+        settings.llm.model_sha256 = None;
+    }
+}
+
+/// Error unless there is memory free for the model in `settings`, emitting
+/// `insufficient-memory` when there isn't
+///
+/// Checked before every load, as free memory changes when other apps open.
+fn check_memory<R: Runtime>(emitter: &impl Emitter<R>, settings: &Settings) -> Result<(), AppError> {
+    let memory = memory_status(settings);
+    if let Err(e) = memory.check() {
+        let available_gb = memory.available_gb.unwrap_or_default();
+        emit_event(emitter, AppEvent::InsufficientMemory(InsufficientMemory {
+            smaller_models: models_that_fit(&memory.model, available_gb),
+            model: memory.model,
+            needed_gb: memory.needed_gb,
+            available_gb,
+            suggestion: "Close other apps to free memory, or choose a smaller model.".to_string(),
+        })).ok();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Error unless the model is loaded for an LLM-bound operation
+///
+/// With the model not loaded yet, too little free memory to load it is
+/// reported first, as `preload_model` would, rather than only asking for a
+/// preload that can't succeed.
+pub(crate) fn ensure_model_ready<R: Runtime>(emitter: &impl Emitter<R>, sidecar_state: &SidecarState, settings: &Settings) -> Result<(), AppError> {
+    if sidecar_state.model_state() == ModelState::NotLoaded {
+        check_memory(emitter, settings)?;
+    }
+    sidecar_state.ensure_model_loaded()
+}
+
+/// Shared by `preload_model` and the preload-on-start hook
+///
+/// A model that loads is remembered as known-good for rescans.
+pub(crate) async fn preload_model_internal(app: &AppHandle) -> Result<ModelState, AppError> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
+    if let state @ (ModelState::Loading | ModelState::Loaded { .. }) = sidecar_state.model_state() {
+        return Ok(state);
+    }
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    require_available_model(app, &settings).await?;
+
+    if !sidecar_state.is_running() {
+        return Err(AppError::sidecar_unavailable());
+    }
+
+    let rpc_client = sidecar_state.client_for(RpcMethod::LoadModel)?;
+    check_memory(app, &settings)?;
+
+    let model_path = resolve_model_path(&settings);
+    let name = model_path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| settings.llm.model_name.clone());
+
+    sidecar_state.set_model_state(ModelState::Loading);
+    emit_event(app, AppEvent::ModelLoading(ModelLoading { model: name.clone(), percent: 0 })).ok();
+
+    let params = json!({
+        "model_path": model_path.to_string_lossy().to_string(),
+        "context_size": settings.llm.context_size,
+    });
+
+    if let Err(e) = rpc_client.call_detailed(RpcMethod::LoadModel.to_string(), params, sidecar_state.rpc_timeout(RpcMethod::LoadModel)).await {
+        sidecar_state.set_model_state(ModelState::NotLoaded);
+        return Err(AppError::RpcFailed { code: e.code, message: format!("Failed to load model: {}", e.message) });
+    }
+
+    let state = ModelState::Loaded {
+        name,
+        ctx: settings.llm.context_size,
+    };
+    sidecar_state.set_model_state(state.clone());
+    emit_event(app, AppEvent::ModelReady(state.clone())).ok();
+
+    let mut latest = session_state.get_settings().unwrap_or_default();
+    if remember_known_good(&mut latest.llm, &model_path) {
+        session_state.set_settings(latest);
+    }
+
+    Ok(state)
+}
+
+/// Places `rpc_server.py` may be, in the order they are tried
+pub(crate) fn sidecar_script_candidates(app: &AppHandle, settings: &Settings) -> Vec<PathBuf> {
+    script_candidates(
+        std::env::var(SIDECAR_PATH_ENV).ok().as_deref(),
+        settings.sidecar.script_path.as_deref(),
+        &app.path().resource_dir().unwrap_or_else(|_| PathBuf::from(".")),
+    )
+}
+
+/// The first of these that exists is run:
+///
+/// 1. `VFX_SIDECAR_PATH`
+/// 2. `sidecar.script_path` in settings
+/// 3. The copy bundled in the app's resources (production)
+/// 4. `python_sidecar/` relative to the working directory, as in
+///    `cargo tauri dev` from `src-tauri/` or the project root
+///
+/// Blank overrides are ignored.
+fn script_candidates(env_path: Option<&str>, settings_path: Option<&str>, resource_dir: &Path) -> Vec<PathBuf> {
+    let overrides = [env_path, settings_path]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+
+    overrides
+        .chain([
+            resource_dir.join("python_sidecar/rpc_server.py"),
+            PathBuf::from("../../python_sidecar/rpc_server.py"),
+            PathBuf::from("../python_sidecar/rpc_server.py"),
+        ])
+        .collect()
+}
+
+/// Start the sidecar, first verifying the model against its recorded
+/// checksum
+///
+/// Hashing runs on a blocking thread. On a mismatch `model-corrupt` is
+/// emitted and the sidecar is left stopped, so Python never tries to load a
+/// damaged file. A model that loaded before and can't be opened now gets
+/// `model-unavailable`; the sidecar still starts for the non-LLM features.
+/// Returns whether the sidecar was started.
+pub async fn start_sidecar_checked(app: &AppHandle, script_path: PathBuf) -> Result<bool, String> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    let model_path = resolve_model_path(&settings);
+    let expected = settings.llm.model_sha256.clone();
+
+    if model_expected(&settings) {
+        if let Err(unavailable) = check_model_path(&model_path) {
+            report_model_unavailable(app, &unavailable);
+        }
+    }
+
+    let check_path = model_path.clone();
+    let integrity = tauri::async_runtime::spawn_blocking(move || {
+        check_model_integrity(&check_path, expected.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Model check failed: {}", e))??;
+
+    if let ModelIntegrity::Corrupt { expected, actual } = integrity {
+        let message = format!(
+            "The model file {} is corrupt (checksum mismatch). Re-download it or choose another model in Settings.",
+            model_path.display()
+        );
+        log::error!("{} Expected {}, got {}", message, expected, actual);
+        emit_event(app, AppEvent::ModelCorrupt(ModelCorrupt {
+            path: model_path.to_string_lossy().to_string(),
+            expected,
+            actual,
+            message,
+        })).ok();
+        return Ok(false);
+    }
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || handle.state::<SidecarState>().start(script_path, &settings.llm))
+        .await
+        .map_err(|e| format!("Sidecar start failed: {}", e))??;
+    sidecar_state.negotiate_protocol().await;
+    Ok(true)
+}
+
+/// Restart the sidecar off the async runtime; starting waits on the new
+/// process
+pub(crate) async fn restart_sidecar(app: &AppHandle) -> Result<(), String> {
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || handle.state::<SidecarState>().restart())
+        .await
+        .map_err(|e| format!("Sidecar restart failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("model-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_validate_model_file_rejects_bad_files() {
+        assert!(validate_model_file(Path::new("")).is_err());
+        assert!(validate_model_file(Path::new("/nonexistent/model.gguf")).unwrap_err().contains("not found"));
+
+        let wrong_ext = temp_file("model.bin", b"GGUF\x03\x00\x00\x00");
+        assert!(validate_model_file(&wrong_ext).unwrap_err().contains(".gguf"));
+
+        let bad_magic = temp_file("model.gguf", b"PK\x03\x04 not a model");
+        assert!(validate_model_file(&bad_magic).unwrap_err().contains("bad header"));
+
+        let truncated = temp_file("tiny.gguf", b"GG");
+        assert!(validate_model_file(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_validate_model_file_accepts_gguf() {
+        let path = temp_file("Custom.Q4_K_M.GGUF", b"GGUF\x03\x00\x00\x00");
+
+        let validated = validate_model_file(&path).unwrap();
+        assert_eq!(validated, path.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_model_path_override_updates_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(resolve_model_path(&settings), get_default_model_path(""));
+
+        // An empty path counts as unset
+        settings.llm.model_path = Some(String::new());
+        assert_eq!(resolve_model_path(&settings), get_default_model_path(""));
+
+        // The default model moves with the model directory
+        settings.paths.model_dir = "/mnt/models".to_string();
+        assert_eq!(resolve_model_path(&settings), get_default_model_path("/mnt/models"));
+        assert!(resolve_model_path(&settings).starts_with("/mnt/models"));
+
+        let path = PathBuf::from("/models/custom.gguf");
+        apply_model_path(&mut settings, &path);
+
+        assert_eq!(settings.llm.model_path.as_deref(), Some("/models/custom.gguf"));
+        assert_eq!(settings.llm.model_name, "custom.gguf");
+        assert_eq!(resolve_model_path(&settings), path);
+    }
+
+    #[test]
+    fn test_changing_the_model_drops_its_checksum() {
+        let mut settings = Settings::default();
+        settings.llm.model_sha256 = Some("abc".to_string());
+
+        // Choosing the same file again keeps it
+        apply_model_path(&mut settings, &get_default_model_path(""));
+        assert_eq!(settings.llm.model_sha256.as_deref(), Some("abc"));
+        apply_model_path(&mut settings, Path::new("/models/custom.gguf"));
+        assert_eq!(settings.llm.model_sha256, None);
+
+        // Saving settings that move the default model does the same
+        let mut previous = Settings::default();
+        previous.llm.model_sha256 = Some("abc".to_string());
+        let mut moved = previous.clone();
+        moved.paths.model_dir = "/mnt/models".to_string();
+        forget_stale_model_hash(&previous, &mut moved);
+        assert_eq!(moved.llm.model_sha256, None);
+
+        // A checksum saved along with the new path is the new file's
+        let mut chosen = previous.clone();
+        chosen.llm.model_path = Some("/models/other.gguf".to_string());
+        chosen.llm.model_sha256 = Some("def".to_string());
+        forget_stale_model_hash(&previous, &mut chosen);
+        assert_eq!(chosen.llm.model_sha256.as_deref(), Some("def"));
+    }
+
+    #[test]
+    fn test_script_resolution_order() {
+        let resources = Path::new("/app/resources");
+        let bundled = resources.join("python_sidecar/rpc_server.py");
+        let fallbacks = [
+            PathBuf::from("../../python_sidecar/rpc_server.py"),
+            PathBuf::from("../python_sidecar/rpc_server.py"),
+        ];
+
+        let candidates = script_candidates(Some("/env/rpc_server.py"), Some("/settings/rpc_server.py"), resources);
+        assert_eq!(candidates[..3], [
+            PathBuf::from("/env/rpc_server.py"),
+            PathBuf::from("/settings/rpc_server.py"),
+            bundled.clone(),
+        ]);
+        assert_eq!(candidates[3..], fallbacks);
+
+        let candidates = script_candidates(Some(" "), Some("/settings/rpc_server.py"), resources);
+        assert_eq!(candidates[0], PathBuf::from("/settings/rpc_server.py"));
+        assert_eq!(script_candidates(None, None, resources)[0], bundled);
+        assert_eq!(script_candidates(None, Some(""), resources).len(), 3);
+    }
+
+    #[test]
+    fn test_model_info_from_sidecar_result() {
+        let info = model_info_from_result(&json!({
+            "architecture": "llama",
+            "parameter_count": 8_030_261_248u64,
+            "quantization": "Q5_K_M",
+            "context_length": 8192,
+        }));
+
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+        assert_eq!(info.name, None);
+        assert_eq!(info.parameter_count, Some(8_030_261_248));
+        assert_eq!(info.quantization.as_deref(), Some("Q5_K_M"));
+        assert_eq!(info.context_length, Some(8192));
+        assert_eq!(info.source, ModelInfoSource::Sidecar);
+    }
+}
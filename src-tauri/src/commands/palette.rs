@@ -0,0 +1,513 @@
+//! Command palette backend
+//!
+//! `get_command_catalog` lists the actions the Cmd+K palette can run, with
+//! their arguments and whether they can run right now;
+//! `invoke_palette_command` validates the arguments and routes to the same
+//! code the regular commands use.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Manager, State, Window};
+use crate::bid_csv::CsvRows;
+use crate::budget::BudgetStrategy;
+use crate::capabilities::model_configured;
+use crate::estimation::EstimateStrategy;
+use crate::state::{workspaces, BidState, ErrorDomain, LastErrorState, ModelState, SessionState, SidecarState, WindowBid};
+use super::settings::toggle_theme;
+use super::{bid, model, script};
+
+/// Threshold `find_duplicate_shots` uses when the palette doesn't give one
+const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.8;
+
+/// JSON type an argument must have
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgKind {
+    String,
+    /// A file path; the UI can offer a file picker
+    Path,
+    Number,
+    Boolean,
+    Object,
+}
+
+impl ArgKind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            ArgKind::String => value.is_string(),
+            ArgKind::Path => value.as_str().is_some_and(|s| !s.trim().is_empty()),
+            ArgKind::Number => value.is_number(),
+            ArgKind::Boolean => value.is_boolean(),
+            ArgKind::Object => value.is_object(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ArgKind::String | ArgKind::Path => "string",
+            ArgKind::Number => "number",
+            ArgKind::Boolean => "boolean",
+            ArgKind::Object => "object",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PaletteArg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+/// What must be true for an action to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Requirement {
+    Nothing,
+    Shots,
+    Sidecar,
+    Model,
+    SidecarAndShots,
+}
+
+/// Live state the availability of actions depends on
+#[derive(Debug, Clone, Copy, Default)]
+struct Availability {
+    has_shots: bool,
+    sidecar_running: bool,
+    model_configured: bool,
+    model_loaded: bool,
+}
+
+impl Availability {
+    fn current(bid_state: &BidState, sidecar_state: &SidecarState, session_state: &SessionState) -> Self {
+        Self {
+            has_shots: !bid_state.get_shots().is_empty(),
+            sidecar_running: sidecar_state.is_running(),
+            model_configured: model_configured(&session_state.get_settings().unwrap_or_default()),
+            model_loaded: matches!(sidecar_state.model_state(), ModelState::Loaded { .. }),
+        }
+    }
+
+    /// Why an action with `requirement` can't run, if it can't
+    fn blocker(&self, requirement: Requirement) -> Option<&'static str> {
+        let needs_shots = matches!(requirement, Requirement::Shots | Requirement::SidecarAndShots);
+        let needs_sidecar = matches!(requirement, Requirement::Sidecar | Requirement::Model | Requirement::SidecarAndShots);
+
+        if requirement == Requirement::Model && !self.model_configured {
+            Some("No model is configured")
+        } else if needs_sidecar && !self.sidecar_running {
+            Some("The Python sidecar is not running")
+        } else if requirement == Requirement::Model && !self.model_loaded {
+            Some("The model is not loaded")
+        } else if needs_shots && !self.has_shots {
+            Some("No bid is loaded")
+        } else {
+            None
+        }
+    }
+}
+
+struct CommandSpec {
+    id: &'static str,
+    title: &'static str,
+    description: &'static str,
+    args: &'static [PaletteArg],
+    requires: Requirement,
+}
+
+const fn arg(name: &'static str, kind: ArgKind, required: bool, description: &'static str) -> PaletteArg {
+    PaletteArg { name, kind, required, description }
+}
+
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        id: "process_script",
+        title: "Process Script",
+        description: "Extract VFX shots from a script and build a bid",
+        args: &[
+            arg("file_path", ArgKind::Path, true, "Script file (PDF, TXT or MD)"),
+            arg("output_dir", ArgKind::Path, false, "Folder for the bid; defaults to the output folder setting"),
+            arg("force", ArgKind::Boolean, false, "Process again even if this script was processed before"),
+        ],
+        requires: Requirement::Model,
+    },
+    CommandSpec {
+        id: "load_bid",
+        title: "Open Bid",
+        description: "Load an existing bid workbook",
+        args: &[arg("file_path", ArgKind::Path, true, "Bid workbook (.xlsx)")],
+        requires: Requirement::Nothing,
+    },
+    CommandSpec {
+        id: "export_bid_with_template",
+        title: "Export to Excel Template",
+        description: "Fill a studio Excel template with the current bid",
+        args: &[
+            arg("template_path", ArgKind::Path, true, "Template workbook (.xlsx)"),
+            arg("output_path", ArgKind::Path, true, "Where to save the bid"),
+            arg("include_tags", ArgKind::Boolean, false, "Include internal shot tags"),
+        ],
+        requires: Requirement::SidecarAndShots,
+    },
+    CommandSpec {
+        id: "export_bid_csv",
+        title: "Export CSV",
+        description: "Save the shot list as CSV",
+        args: &[
+            arg("output_path", ArgKind::Path, true, "Where to save the CSV"),
+            arg("include_tags", ArgKind::Boolean, false, "Include internal shot tags"),
+            arg("include_archived", ArgKind::Boolean, false, "Include deleted shots from the archive"),
+        ],
+        requires: Requirement::Shots,
+    },
+    CommandSpec {
+        id: "import_bid_csv",
+        title: "Import CSV",
+        description: "Replace the shots with those in a CSV file",
+        args: &[
+            arg("file_path", ArgKind::Path, true, "CSV file"),
+            arg("locale", ArgKind::String, false, "Number format of the file, e.g. de-DE"),
+        ],
+        requires: Requirement::Nothing,
+    },
+    CommandSpec {
+        id: "fit_to_budget",
+        title: "Fit to Budget",
+        description: "Scale the bid so its total matches a target",
+        args: &[
+            arg("target", ArgKind::Number, true, "Target grand total"),
+            arg("strategy", ArgKind::Object, false, "How to scale; uniform by default"),
+            arg("dry_run", ArgKind::Boolean, false, "Preview without changing the bid"),
+        ],
+        requires: Requirement::Shots,
+    },
+    CommandSpec {
+        id: "estimate_hours_from_duration",
+        title: "Estimate Hours from Duration",
+        description: "Fill in missing hours from each shot's frame count",
+        args: &[],
+        requires: Requirement::Shots,
+    },
+    CommandSpec {
+        id: "estimate_missing_hours",
+        title: "Estimate Missing Hours",
+        description: "Fill in blank hours from the typical hours per complexity",
+        args: &[arg("strategy", ArgKind::String, false, "midpoint, low or high; midpoint by default")],
+        requires: Requirement::Shots,
+    },
+    CommandSpec {
+        id: "find_duplicate_shots",
+        title: "Find Duplicate Shots",
+        description: "List shots that look like duplicates within a scene",
+        args: &[arg("threshold", ArgKind::Number, false, "Similarity from 0 to 1; 0.8 by default")],
+        requires: Requirement::Shots,
+    },
+    CommandSpec {
+        id: "restore_autosaved_bid",
+        title: "Restore Autosaved Bid",
+        description: "Reopen the bid saved before the last crash",
+        args: &[],
+        requires: Requirement::Nothing,
+    },
+    CommandSpec {
+        id: "preload_model",
+        title: "Load Model",
+        description: "Load the model now instead of on first use",
+        args: &[],
+        requires: Requirement::Sidecar,
+    },
+    CommandSpec {
+        id: "unload_model",
+        title: "Unload Model",
+        description: "Free the model's memory",
+        args: &[],
+        requires: Requirement::Model,
+    },
+    CommandSpec {
+        id: "restart_sidecar",
+        title: "Restart Sidecar",
+        description: "Restart the Python process",
+        args: &[],
+        requires: Requirement::Nothing,
+    },
+    CommandSpec {
+        id: "toggle_theme",
+        title: "Toggle Theme",
+        description: "Switch between light and dark",
+        args: &[],
+        requires: Requirement::Nothing,
+    },
+];
+
+/// An action as listed in the palette
+#[derive(Debug, Serialize, Clone)]
+pub struct PaletteCommand {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub args: &'static [PaletteArg],
+    pub available: bool,
+    /// Why the action is greyed out
+    pub unavailable_reason: Option<&'static str>,
+}
+
+/// Outcome of a palette action; failures are reported here rather than as
+/// a rejected promise
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PaletteResult {
+    pub ok: bool,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl PaletteResult {
+    fn failed(message: impl Into<String>) -> Self {
+        Self { ok: false, message: message.into(), data: None }
+    }
+}
+
+/// Every palette action, with availability computed from the current state
+#[tauri::command]
+pub fn get_command_catalog(
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Vec<PaletteCommand> {
+    catalog(Availability::current(&bid_state, &sidecar_state, &session_state))
+}
+
+/// Run a palette action by id
+///
+/// `args` is an object keyed by argument name (or null for none). Unknown or
+/// unavailable actions and invalid arguments come back with `ok: false`, and
+/// are kept for the error panel like any other failed command.
+/// Bid actions work on the bid of the calling window.
+#[tauri::command]
+pub async fn invoke_palette_command(id: String, args: Value, app: AppHandle, window: Window) -> PaletteResult {
+    match run(&id, &args, &app, &window).await {
+        Ok((message, data)) => PaletteResult { ok: true, message, data },
+        Err(e) => {
+            app.state::<LastErrorState>().record(ErrorDomain::Bid, "invoke_palette_command", &e);
+            PaletteResult::failed(e)
+        }
+    }
+}
+
+/// Look up, validate and dispatch a palette action
+async fn run(id: &str, args: &Value, app: &AppHandle, window: &Window) -> Result<(String, Option<Value>), String> {
+    let spec = COMMANDS
+        .iter()
+        .find(|spec| spec.id == id)
+        .ok_or_else(|| format!("Unknown command '{}'", id))?;
+
+    let args = validate_args(spec.args, args)?;
+
+    let bid_state = workspaces::window_bid(app, window.label())
+        .ok_or_else(|| "No bid is open in this window".to_string())?;
+    let availability = Availability::current(
+        &bid_state,
+        &app.state::<SidecarState>(),
+        &app.state::<SessionState>(),
+    );
+    if let Some(reason) = availability.blocker(spec.requires) {
+        return Err(format!("{}: {}", spec.title, reason));
+    }
+
+    dispatch(spec.id, &args, app, &bid_state).await
+}
+
+fn catalog(availability: Availability) -> Vec<PaletteCommand> {
+    COMMANDS
+        .iter()
+        .map(|spec| {
+            let blocker = availability.blocker(spec.requires);
+            PaletteCommand {
+                id: spec.id,
+                title: spec.title,
+                description: spec.description,
+                args: spec.args,
+                available: blocker.is_none(),
+                unavailable_reason: blocker,
+            }
+        })
+        .collect()
+}
+
+/// Check `args` against `schema`, returning them as an object
+fn validate_args(schema: &[PaletteArg], args: &Value) -> Result<Map<String, Value>, String> {
+    let args = match args {
+        Value::Null => Map::new(),
+        Value::Object(map) => map.clone(),
+        _ => return Err("Arguments must be an object".to_string()),
+    };
+
+    if let Some(unknown) = args.keys().find(|key| !schema.iter().any(|a| a.name == key.as_str())) {
+        return Err(format!("Unknown argument '{}'", unknown));
+    }
+
+    for spec in schema {
+        match args.get(spec.name).filter(|value| !value.is_null()) {
+            None if spec.required => return Err(format!("Missing argument '{}'", spec.name)),
+            Some(value) if !spec.kind.matches(value) => {
+                return Err(format!("Argument '{}' must be a {}", spec.name, spec.kind.name()));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(args)
+}
+
+/// Run a validated action; returns the message and any data for the UI
+async fn dispatch(id: &str, args: &Map<String, Value>, app: &AppHandle, bid_state: &BidState) -> Result<(String, Option<Value>), String> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
+    let text = |name: &str| args.get(name).and_then(Value::as_str).map(str::to_string);
+    let number = |name: &str| args.get(name).and_then(Value::as_f64);
+    let flag = |name: &str| args.get(name).and_then(Value::as_bool).unwrap_or(false);
+
+    match id {
+        "process_script" => {
+            let analysis = script::process_script_internal(text("file_path").unwrap_or_default(), text("output_dir"), flag("force"), app, bid_state).await?;
+            Ok((format!("Extracted {} shots", analysis.shots.len()), Some(json!(&analysis))))
+        }
+        "load_bid" => {
+            let analysis = script::open_bid(text("file_path").unwrap_or_default(), bid_state, &sidecar_state, &session_state).await?;
+            Ok((format!("Loaded {} shots", analysis.shots.len()), Some(json!(&analysis))))
+        }
+        "export_bid_with_template" => {
+            let path = script::export_bid_with_template_internal(
+                text("template_path").unwrap_or_default(),
+                text("output_path").unwrap_or_default(),
+                flag("include_tags"),
+                bid_state,
+                &sidecar_state,
+            ).await?;
+            Ok((format!("Exported to {}", path), Some(json!({ "path": path }))))
+        }
+        "export_bid_csv" => {
+            let path = script::export_bid_csv_internal(
+                text("output_path").unwrap_or_default(),
+                CsvRows::default(),
+                flag("include_tags"),
+                flag("include_archived"),
+                bid_state,
+                &session_state,
+            )?;
+            Ok((format!("Exported to {}", path), Some(json!({ "path": path }))))
+        }
+        "import_bid_csv" => {
+            let shots = script::import_bid_csv_internal(
+                text("file_path").unwrap_or_default(),
+                text("locale"),
+                bid_state,
+                &session_state,
+            )?;
+            Ok((format!("Imported {} shots", shots.len()), Some(json!(&shots))))
+        }
+        "fit_to_budget" => {
+            let strategy: BudgetStrategy = match args.get("strategy").filter(|v| !v.is_null()) {
+                Some(value) => serde_json::from_value(value.clone())
+                    .map_err(|e| format!("Invalid strategy: {}", e))?,
+                None => BudgetStrategy::Uniform,
+            };
+            let dry_run = args.get("dry_run").and_then(Value::as_bool).unwrap_or(false);
+            let report = bid::fit_to_budget_internal(number("target").unwrap_or_default(), &strategy, dry_run, bid_state, &session_state)?;
+            let verb = if dry_run { "Would scale" } else { "Scaled" };
+            Ok((format!("{} the bid by {:.3}", verb, report.scale_factor), Some(json!(&report))))
+        }
+        "estimate_hours_from_duration" => {
+            let estimated = bid::estimate_hours_from_duration_internal(None, bid_state, &session_state)?;
+            Ok((format!("Estimated hours for {} shots", estimated), Some(json!({ "estimated": estimated }))))
+        }
+        "estimate_missing_hours" => {
+            let strategy = match args.get("strategy") {
+                Some(value) => serde_json::from_value(value.clone())
+                    .map_err(|_| "Argument 'strategy' must be midpoint, low or high".to_string())?,
+                None => EstimateStrategy::default(),
+            };
+            let estimate = bid::estimate_missing_hours_internal(strategy, bid_state, &session_state)?;
+            Ok((format!("Estimated hours for {} shots", estimate.filled), Some(json!(&estimate))))
+        }
+        "find_duplicate_shots" => {
+            let threshold = number("threshold").unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+            let groups = bid::find_duplicate_shots_internal(threshold, bid_state)?;
+            Ok((format!("Found {} groups of likely duplicates", groups.len()), Some(json!(&groups))))
+        }
+        "restore_autosaved_bid" => {
+            let autosave = bid_state.restore_autosave()?
+                .ok_or_else(|| "No autosaved bid to restore".to_string())?;
+            Ok((format!("Restored {} shots", autosave.shots.len()), Some(json!(&autosave.shots))))
+        }
+        "preload_model" => {
+            let state = model::preload_model_internal(app).await?;
+            Ok(("Model loaded".to_string(), Some(json!(&state))))
+        }
+        "unload_model" => {
+            model::unload_model_internal(&sidecar_state).await?;
+            Ok(("Model unloaded".to_string(), None))
+        }
+        "restart_sidecar" => {
+            model::restart_sidecar(app).await?;
+            sidecar_state.negotiate_protocol().await;
+            Ok(("Sidecar restarted".to_string(), None))
+        }
+        "toggle_theme" => {
+            let theme = toggle_theme(&session_state);
+            Ok((format!("Switched to the {} theme", theme), Some(json!({ "theme": theme }))))
+        }
+        _ => Err(format!("Unknown command '{}'", id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command<'a>(catalog: &'a [PaletteCommand], id: &str) -> &'a PaletteCommand {
+        catalog.iter().find(|c| c.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_availability_follows_state() {
+        let empty = catalog(Availability::default());
+        assert!(!command(&empty, "export_bid_csv").available);
+        assert_eq!(command(&empty, "export_bid_csv").unavailable_reason, Some("No bid is loaded"));
+        assert_eq!(command(&empty, "process_script").unavailable_reason, Some("No model is configured"));
+        assert!(command(&empty, "load_bid").available);
+        assert!(command(&empty, "toggle_theme").available);
+
+        let no_model = catalog(Availability { has_shots: true, sidecar_running: true, ..Default::default() });
+        assert_eq!(command(&no_model, "process_script").unavailable_reason, Some("No model is configured"));
+        assert!(command(&no_model, "load_bid").available && command(&no_model, "export_bid_csv").available);
+
+        let running = catalog(Availability { has_shots: true, sidecar_running: true, model_configured: true, model_loaded: false });
+        assert!(command(&running, "export_bid_csv").available);
+        assert!(command(&running, "export_bid_with_template").available);
+        assert_eq!(command(&running, "process_script").unavailable_reason, Some("The model is not loaded"));
+
+        let ready = catalog(Availability { has_shots: false, sidecar_running: true, model_configured: true, model_loaded: true });
+        assert!(command(&ready, "process_script").available);
+        assert!(!command(&ready, "export_bid_with_template").available);
+    }
+
+    #[test]
+    fn test_args_are_validated_against_schema() {
+        let schema = COMMANDS.iter().find(|c| c.id == "fit_to_budget").unwrap().args;
+
+        assert!(validate_args(schema, &json!({ "target": 50000 })).is_ok());
+        assert!(validate_args(schema, &json!({ "target": 50000, "dry_run": true, "strategy": null })).is_ok());
+        assert_eq!(validate_args(schema, &json!({})).unwrap_err(), "Missing argument 'target'");
+        assert_eq!(validate_args(schema, &json!({ "target": "lots" })).unwrap_err(), "Argument 'target' must be a number");
+        assert_eq!(validate_args(schema, &json!({ "target": 1, "rush": true })).unwrap_err(), "Unknown argument 'rush'");
+        assert!(validate_args(schema, &json!([50000])).is_err());
+
+        let no_args = COMMANDS.iter().find(|c| c.id == "toggle_theme").unwrap().args;
+        assert!(validate_args(no_args, &Value::Null).is_ok());
+
+        let path_args = COMMANDS.iter().find(|c| c.id == "export_bid_csv").unwrap().args;
+        assert!(validate_args(path_args, &json!({ "output_path": "  " })).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+use std::path::Path;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder, Window, WindowEvent};
+use crate::attachments;
+use crate::events::{emit_event, emit_event_to, AppEvent, BidExpired, WindowCloseRequested};
+use crate::project::{self, ProjectFile, PROJECT_VERSION};
+use crate::state::bid::ReadOnlyStatus;
+use crate::state::last_error::tracked_command;
+use crate::state::workspaces::{self, MAIN_WINDOW_LABEL, WINDOW_LABEL_PREFIX};
+use crate::state::{BidState, SessionState, SidecarState, WindowBid, WorkspaceRegistry};
+use crate::workspace;
+
+/// Title of the main window in `tauri.conf.json`; bid windows prefix the project name
+const BID_WINDOW_TITLE: &str = "VFX Bidding AI Assistant";
+
+tracked_command! { Bid;
+/// Save the current bid as a project file
+///
+/// With `embed_attachments` the project is written as a zip that carries the
+/// reference images too, so it can be opened on another machine. Stored
+/// images no longer used by the bid are deleted once it is saved.
+#[tauri::command]
+pub fn save_project(
+    path: String,
+    embed_attachments: Option<bool>,
+    app: AppHandle,
+    bid_state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<String, String> {
+    let project = ProjectFile {
+        version: PROJECT_VERSION,
+        bid_id: bid_state.bid_id(),
+        saved_at: chrono::Utc::now().to_rfc3339(),
+        meta: bid_state.get_meta(),
+        shots: bid_state.get_shots(),
+        groups: bid_state.get_groups(),
+        revisions: bid_state.get_revisions(),
+        archived: bid_state.get_archived_shots(),
+        read_only: bid_state.read_only_status().project_read_only,
+        audit: bid_state.audit_trail()?,
+    };
+
+    let root = attachments::attachments_root(&app)?;
+    let embed_from = embed_attachments.unwrap_or(false).then_some(root.as_path());
+
+    project::write_project(Path::new(&path), &project, embed_from)?;
+    bid_state.mark_saved();
+
+    match attachments::remove_orphans(&root, &project.bid_id, project.attachment_shots()) {
+        Ok(0) => {}
+        Ok(removed) => log::info!("Removed {} orphaned attachment(s) of bid {}", removed, project.bid_id),
+        Err(e) => log::warn!("Failed to clean up orphaned attachments: {}", e),
+    }
+
+    if bid_state.is_main() {
+        session_state.update(|s| s.last_bid_path = Some(path.clone()));
+    }
+    Ok(path)
+}
+}
+
+tracked_command! { Bid;
+/// Open a project file, replacing the current bid
+///
+/// Embedded attachments are restored into the app data directory. A quote
+/// past its `valid_until` raises a `bid-expired` warning event. A project
+/// saved read-only opens read-only.
+#[tauri::command]
+pub fn load_project(
+    path: String,
+    app: AppHandle,
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<ProjectFile, String> {
+    let session_state = bid_state.is_main().then_some(session_state.inner());
+    open_project(path, false, &app, &bid_state, &sidecar_state, session_state)
+}
+}
+
+tracked_command! { Bid;
+/// Open a project file with editing locked, whatever the file says
+#[tauri::command]
+pub fn open_project_readonly(
+    path: String,
+    app: AppHandle,
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<ProjectFile, String> {
+    let session_state = bid_state.is_main().then_some(session_state.inner());
+    open_project(path, true, &app, &bid_state, &sidecar_state, session_state)
+}
+}
+
+/// Load `path` into `bid_state`; the session remembers the path only when
+/// given, which is for the main window's bid
+fn open_project(
+    path: String,
+    read_only: bool,
+    app: &AppHandle,
+    bid_state: &BidState,
+    sidecar_state: &SidecarState,
+    session_state: Option<&SessionState>,
+) -> Result<ProjectFile, String> {
+    let root = attachments::attachments_root(app)?;
+    let project = project::read_project(Path::new(&path), &root)?;
+
+    bid_state.load_archive(project.archived.clone());
+    bid_state.replace_bid(
+        project.bid_id.clone(),
+        project.shots.clone(),
+        project.groups.clone(),
+        project.meta.clone(),
+        project.revisions.clone(),
+        ReadOnlyStatus {
+            read_only: read_only || project.read_only,
+            project_read_only: project.read_only,
+            dirty: false,
+        },
+    );
+    if let Err(e) = bid_state.import_audit(project.audit.clone()) {
+        log::warn!("Failed to restore the project's audit trail: {}", e);
+    }
+    emit_event(app, AppEvent::BidReadonlyChanged(bid_state.read_only_status())).ok();
+    // Frees the previous bid's workspace and readies this one's
+    sidecar_state.use_workspace(&project.bid_id);
+
+    if project.meta.is_expired(chrono::Local::now().date_naive()) {
+        emit_event(app, AppEvent::BidExpired(BidExpired {
+            project_name: project.meta.project_name.clone(),
+            status: project.meta.status,
+            valid_until: project.meta.valid_until,
+        })).ok();
+    }
+
+    if let Some(session_state) = session_state {
+        session_state.update(|s| s.last_bid_path = Some(path));
+    }
+    Ok(project)
+}
+
+tracked_command! { Bid;
+/// Delete a project file along with its stored attachments
+#[tauri::command]
+pub fn delete_project(
+    path: String,
+    app: AppHandle,
+) -> Result<(), String> {
+    let bid_id = project::read_bid_id(Path::new(&path))?;
+
+    if workspaces::window_with_bid(&app, &bid_id).is_some() {
+        return Err("This project is currently open. Open another bid or close its window before deleting it.".to_string());
+    }
+
+    std::fs::remove_file(&path)
+        .map_err(|e| format!("Failed to delete project file: {}", e))?;
+
+    attachments::remove_bid_attachments(&attachments::attachments_root(&app)?, &bid_id)?;
+    Ok(())
+}
+}
+
+/// A window and the bid open in it, as listed by `list_bid_windows`
+#[derive(Debug, Serialize, Clone)]
+pub struct BidWindow {
+    /// Window label; what `compare_bids` takes as a context id
+    pub context_id: String,
+    pub bid_id: String,
+    pub project_name: Option<String>,
+    pub shot_count: usize,
+    pub dirty: bool,
+}
+
+tracked_command! { Bid;
+/// Open a project file in a new window, next to the bid already open
+///
+/// The window gets its own bid; settings and the sidecar are shared. A
+/// project already open in some window isn't opened twice. Returns the new
+/// window's context id.
+#[tauri::command]
+pub async fn open_bid_in_new_window(
+    path: String,
+    app: AppHandle,
+    registry: State<'_, WorkspaceRegistry>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<String, String> {
+    let bid_id = project::read_bid_id(Path::new(&path))?;
+    if let Some(label) = workspaces::window_with_bid(&app, &bid_id) {
+        return Err(format!("This project is already open in window '{}'", label));
+    }
+
+    let (context_id, bid_state) = registry.create();
+    let opened = open_project(path, false, &app, &bid_state, &sidecar_state, None).and_then(|project| {
+        let title = match &project.meta.project_name {
+            Some(name) => format!("{} - {}", name, BID_WINDOW_TITLE),
+            None => BID_WINDOW_TITLE.to_string(),
+        };
+        WebviewWindowBuilder::new(&app, &context_id, WebviewUrl::App("index.html".into()))
+            .title(title)
+            .inner_size(1200.0, 800.0)
+            .resizable(true)
+            .build()
+            .map_err(|e| format!("Failed to open window: {}", e))
+    });
+
+    match opened {
+        Ok(_) => Ok(context_id),
+        Err(e) => {
+            registry.remove(&context_id);
+            Err(e)
+        }
+    }
+}
+}
+
+/// Every window with its bid, the main one first
+#[tauri::command]
+pub fn list_bid_windows(
+    main_bid: State<'_, BidState>,
+    registry: State<'_, WorkspaceRegistry>,
+) -> Vec<BidWindow> {
+    let describe = |context_id: String, bid_state: &BidState| BidWindow {
+        context_id,
+        bid_id: bid_state.bid_id(),
+        project_name: bid_state.get_meta().project_name,
+        shot_count: bid_state.get_shots().len(),
+        dirty: bid_state.read_only_status().dirty,
+    };
+
+    let windows = registry.contexts().into_iter().filter(|(id, _)| id.starts_with(WINDOW_LABEL_PREFIX));
+    std::iter::once(describe(MAIN_WINDOW_LABEL.to_string(), &main_bid))
+        .chain(windows.map(|(id, bid_state)| describe(id, &bid_state)))
+        .collect()
+}
+
+/// A project tab, as listed by `list_projects`
+#[derive(Debug, Serialize, Clone)]
+pub struct ProjectTab {
+    /// What bid commands take as `projectId`; the window label for the
+    /// window's own bid
+    pub project_id: String,
+    pub bid_id: String,
+    pub project_name: Option<String>,
+    pub shot_count: usize,
+    pub dirty: bool,
+    pub active: bool,
+}
+
+/// Open an empty project tab in the calling window and switch to it
+///
+/// Load a project or process a script into it as usual. Returns its id.
+#[tauri::command]
+pub fn create_project(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+) -> String {
+    let (project_id, _) = registry.create_tab(window.label());
+    emit_event_to(&window, window.label(), AppEvent::ActiveProjectChanged(project_id.clone())).ok();
+    project_id
+}
+
+tracked_command! { Bid;
+/// Show project `project_id` in the calling window
+///
+/// The window's own label switches back to the bid it had before any tabs.
+/// Bid commands without a `projectId` act on the project shown.
+#[tauri::command]
+pub fn switch_project(
+    project_id: String,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+) -> Result<(), String> {
+    registry.set_active(window.label(), &project_id)?;
+    emit_event_to(&window, window.label(), AppEvent::ActiveProjectChanged(project_id.clone())).ok();
+    Ok(())
+}
+}
+
+tracked_command! { Bid;
+/// Close project tab `project_id` of the calling window
+///
+/// Refuses while it has unsaved changes unless `discard_changes` is set.
+/// Closing the project shown switches back to the window's own bid.
+#[tauri::command]
+pub fn close_project(
+    project_id: String,
+    discard_changes: bool,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<(), String> {
+    if !registry.tabs(window.label()).contains(&project_id) {
+        return Err(format!("No project '{}' in this window", project_id));
+    }
+    let was_active = registry.active(window.label()).as_deref() == Some(project_id.as_str());
+    let dirty = registry.get(&project_id).is_some_and(|bid_state| bid_state.read_only_status().dirty);
+    if dirty && !discard_changes {
+        return Err("This project has unsaved changes. Save it or discard the changes to close it.".to_string());
+    }
+
+    if let Some(bid_state) = registry.remove(&project_id) {
+        release_workspace(&sidecar_state, &bid_state);
+    }
+    if was_active {
+        emit_event_to(&window, window.label(), AppEvent::ActiveProjectChanged(window.label().to_string())).ok();
+    }
+    Ok(())
+}
+}
+
+/// The calling window's own bid and its project tabs
+#[tauri::command]
+pub fn list_projects(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+) -> Vec<ProjectTab> {
+    let label = window.label();
+    let active = registry.active(label).unwrap_or_else(|| label.to_string());
+    let describe = |project_id: String, bid_state: &BidState| ProjectTab {
+        active: project_id == active,
+        bid_id: bid_state.bid_id(),
+        project_name: bid_state.get_meta().project_name,
+        shot_count: bid_state.get_shots().len(),
+        dirty: bid_state.read_only_status().dirty,
+        project_id,
+    };
+
+    let own = match registry.get(label) {
+        Some(bid_state) => describe(label.to_string(), &bid_state),
+        None => describe(label.to_string(), &window.state::<BidState>()),
+    };
+    std::iter::once(own)
+        .chain(registry.tabs(label).into_iter().filter_map(|id| {
+            let bid_state = registry.get(&id)?;
+            Some(describe(id, &bid_state))
+        }))
+        .collect()
+}
+
+fn release_workspace(sidecar_state: &SidecarState, bid_state: &BidState) {
+    if let Some(root) = workspace::root() {
+        sidecar_state.release_workspace(&workspace::workspace_dir(root, &bid_state.bid_id()));
+    }
+}
+
+tracked_command! { Bid;
+/// Close the calling bid window
+///
+/// Refuses while its bid or one of its project tabs has unsaved changes
+/// unless `discard_changes` is set. The main window isn't closed this way.
+#[tauri::command]
+pub fn close_bid_window(
+    discard_changes: bool,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+) -> Result<(), String> {
+    let bid_state = registry.get(window.label())
+        .ok_or_else(|| "Only windows opened for a second bid can be closed here".to_string())?;
+    if bid_state.read_only_status().dirty && !discard_changes {
+        return Err("This bid has unsaved changes. Save it or discard the changes to close the window.".to_string());
+    }
+    let dirty_projects = dirty_tabs(&registry, window.label());
+    if !dirty_projects.is_empty() && !discard_changes {
+        return Err(format!(
+            "{} project tab(s) in this window have unsaved changes. Save them or discard the changes to close the window.",
+            dirty_projects.len()
+        ));
+    }
+    // Skips `CloseRequested`; the context goes when the window is destroyed
+    window.destroy().map_err(|e| format!("Failed to close window: {}", e))
+}
+}
+
+/// Project tabs of `window` with unsaved changes
+fn dirty_tabs(registry: &WorkspaceRegistry, window: &str) -> Vec<String> {
+    registry.tabs(window)
+        .into_iter()
+        .filter(|id| registry.get(id).is_some_and(|bid_state| bid_state.read_only_status().dirty))
+        .collect()
+}
+
+/// Guard and clean up bid windows
+///
+/// Closing a window whose bid or any project tab has unsaved changes is
+/// held back and `bid-window-close-requested` is sent to it so it can ask
+/// the user; a destroyed window's bid and project tabs are dropped and
+/// their sidecar workspaces released.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    let registry: State<WorkspaceRegistry> = window.state();
+    match event {
+        WindowEvent::CloseRequested { api, .. } => {
+            let own = registry.get(window.label());
+            let dirty_projects = dirty_tabs(&registry, window.label());
+            // The main window's own bid isn't asked about; its autosave
+            // offers it back on the next launch
+            let status = match &own {
+                Some(bid_state) => bid_state.read_only_status(),
+                None => window.state::<BidState>().read_only_status(),
+            };
+            let own_dirty = own.is_some() && status.dirty;
+            if own_dirty || !dirty_projects.is_empty() {
+                api.prevent_close();
+                let request = WindowCloseRequested { status, dirty_projects };
+                emit_event_to(window, window.label(), AppEvent::BidWindowCloseRequested(request)).ok();
+            }
+        }
+        WindowEvent::Destroyed => {
+            let sidecar_state: State<SidecarState> = window.state();
+            let contexts = registry.tabs(window.label()).into_iter().chain(std::iter::once(window.label().to_string()));
+            for id in contexts {
+                if let Some(bid_state) = registry.remove(&id) {
+                    release_workspace(&sidecar_state, &bid_state);
+                }
+            }
+        }
+        _ => {}
+    }
+}
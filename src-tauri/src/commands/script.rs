@@ -1,24 +1,109 @@
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use tauri::{State, Window, Emitter};
-use crate::state::{BidState, SidecarState};
-use super::bid::ShotData;
-use std::path::PathBuf;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State, Window};
+use crate::state::last_error::tracked_command;
+use crate::state::{BidState, SessionState, SidecarState, WindowBid, WorkspaceRegistry};
+use crate::state::workspaces::MAIN_WINDOW_LABEL;
+use crate::error::AppError;
+use crate::assumptions::exported_items;
+use crate::bid_csv::{self, CsvRows};
+use crate::events::{emit_event, emit_event_to, AppEvent, ScriptHashProgress};
+use crate::excel;
+use crate::export_mapping::{self, ExportMapping, ExportMappingList, ExportMappingStore};
+use crate::scene_detect;
+use crate::scene_order::compare_scene_numbers;
+use crate::script_cache::{self, CachedResult};
+use crate::shot_list::{self, ShotListFormat, ShotListReport};
+use crate::history::{self, ProcessingEstimate, ProcessingRun, DEFAULT_HISTORY_LIMIT};
+use crate::sidecar::{AsyncRpcClient, RpcMethod};
+use crate::sidecar::rpc::RPC_CANCELLED_CODE;
+use crate::review;
+use crate::source_sync::{self, SourceStatus};
+use crate::summary::{self, SummaryFormat, SummaryOptions};
+use crate::workspace;
+use super::model::{ensure_model_ready, require_available_model};
+use super::bid::{department_totals, get_bid_totals_internal, total_frame_count, total_final_price, DepartmentTotal, PriceRounding, ShotData};
+use super::settings::{LocaleSettings, Settings};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Placeholders a bid template must define for the sidecar to fill it
+pub const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "shots",
+    "total_cost",
+    "project_name",
+    "client_name",
+];
+
+/// `ShotData` fields for the bidder's eyes only, left out of client exports
+const INTERNAL_FIELDS: [&str; 4] = ["confidence", "extraction_flags", "review", "estimated_by"];
 
 /// Script processing result
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ScriptAnalysis {
     pub shots: Vec<ShotData>,
     pub metadata: ScriptMetadata,
+    /// Per-scene breakdown, in natural scene order
+    #[serde(default)]
+    pub scenes: Vec<SceneSummary>,
+    #[serde(default)]
+    pub source: AnalysisSource,
+    /// Excel bid written by `process_script`
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// Scenes found in a text script before extraction; None for PDFs
+    #[serde(default)]
+    pub detected_scenes: Option<usize>,
+    /// Shots the sidecar extracted but couldn't price; they are in `shots`
+    /// too, flagged `unpriced`, for the bidder to finish by hand
+    #[serde(default)]
+    pub unpriced_shots: Vec<ShotData>,
+    /// Problems that didn't stop the run, e.g. an unknown VFX type
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Reopened from an earlier run of the same script rather than processed
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SceneSummary {
+    pub scene_number: String,
+    pub shot_count: usize,
+    /// Sum of the scene's final prices, unrounded
+    pub subtotal: f64,
+    /// Task hours by department
+    #[serde(default)]
+    pub departments: Vec<DepartmentTotal>,
+    /// Sum of the frame counts of the scene's shots that have one
+    #[serde(default)]
+    pub total_frames: u64,
+}
+
+/// Where a loaded bid was read by
+///
+/// `RustFallback` bids were read without the sidecar, so re-exporting them
+/// still needs a working Python environment.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnalysisSource {
+    #[default]
+    Sidecar,
+    RustFallback,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ScriptMetadata {
     pub title: Option<String>,
     pub total_shots: usize,
     pub vfx_categories: Vec<String>,
+    #[serde(default)]
+    pub total_frames: u64,
 }
 
+tracked_command! { Script;
 /// Process a script file and generate VFX bid Excel
 ///
 /// This calls the Python sidecar via JSON-RPC to run the VFX bidding pipeline.
@@ -27,54 +112,197 @@ pub struct ScriptMetadata {
 /// 2. Extract VFX shots using LLM analysis
 /// 3. Calculate pricing based on industry standards
 /// 4. Generate Excel bid document
+///
+/// The bid is written to `output_dir`, else `Settings.paths.output_dir`;
+/// with neither the sidecar picks the location.
 #[tauri::command]
 pub async fn process_script(
     file_path: String,
+    output_dir: Option<String>,
+    force: Option<bool>,
     window: Window,
-    bid_state: State<'_, BidState>,
-    sidecar_state: State<'_, SidecarState>,
-) -> Result<ScriptAnalysis, String> {
+    bid_state: WindowBid<'_>,
+) -> Result<ScriptAnalysis, AppError> {
+    process_script_internal(file_path, output_dir, force.unwrap_or(false), window.app_handle(), &bid_state).await
+}
+}
+
+/// Shared by `process_script` and the command palette; the shots go into
+/// `bid_state`, the bid of the calling window
+///
+/// A script processed before is reopened from its workbook, and reported
+/// with `script-result-cached`, unless `force` is set.
+pub(crate) async fn process_script_internal(
+    file_path: String,
+    output_dir: Option<String>,
+    force: bool,
+    app: &AppHandle,
+    bid_state: &BidState,
+) -> Result<ScriptAnalysis, AppError> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
     log::info!("Processing script: {}", file_path);
 
     // Emit progress event
-    window.emit("script-processing-start", &file_path)
-        .map_err(|e| e.to_string())?;
+    emit_event(app, AppEvent::ScriptProcessingStart(file_path.clone()))?;
+
+    let settings = session_state.get_settings().unwrap_or_default();
+
+    // Resolve file path
+    let path = PathBuf::from(&file_path);
+    let absolute_path = path.canonicalize()
+        .map_err(|e| AppError::not_found(format!("Invalid file path: {}", e)))?;
+
+    if !absolute_path.exists() {
+        return Err(AppError::not_found(format!("File not found: {}", file_path)));
+    }
+
+    // Reopening an earlier result doesn't need the model
+    let content_hash = hash_script(app, &absolute_path).await;
+    if let Some(cached) = content_hash.as_deref().filter(|_| !force).and_then(|hash| cached_result(app, hash)) {
+        match open_cached_result(app, &cached, &absolute_path, bid_state).await {
+            Ok(analysis) => return Ok(analysis),
+            Err(e) => log::warn!("Re-processing {}, its earlier bid didn't open: {}", file_path, e),
+        }
+    }
+
+    require_available_model(app, &settings).await?;
 
     // Check if sidecar is running
     if !sidecar_state.is_running() {
-        return Err("Python sidecar is not running. Please restart the application.".to_string());
+        return Err(AppError::sidecar_unavailable());
     }
 
+    ensure_model_ready(app, &sidecar_state, &settings)?;
+
     // Get RPC client
-    let rpc_client = sidecar_state.rpc_client()
-        .ok_or_else(|| "Failed to get RPC client".to_string())?;
+    let rpc_client = sidecar_state.client_for(RpcMethod::ProcessScript)?;
 
-    // Resolve file path
-    let path = PathBuf::from(&file_path);
-    let absolute_path = path.canonicalize()
-        .map_err(|e| format!("Invalid file path: {}", e))?;
+    // The bid gets its id now so the sidecar's files for it share a workspace
+    let bid_id = uuid::Uuid::new_v4().to_string();
+    let workspace = sidecar_state.use_workspace(&bid_id);
 
-    if !absolute_path.exists() {
-        return Err(format!("File not found: {}", file_path));
+    // Without an output directory the bid goes in the workspace
+    let output_path = match resolve_output_dir(output_dir.as_deref(), &settings) {
+        Some(dir) => {
+            ensure_writable_dir(&dir)?;
+            Some(bid_output_path(&dir, &absolute_path).to_string_lossy().to_string())
+        }
+        None => workspace.as_ref().map(|dir| bid_output_path(dir, &absolute_path).to_string_lossy().to_string()),
+    };
+
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let started = Instant::now();
+    let size = history::script_size(&absolute_path);
+    let mut page_count = size.page_count;
+    let mut stages = BTreeMap::new();
+
+    // Text scripts are split into scenes here; the sidecar takes them as a hint
+    let stage = Instant::now();
+    let scene_hints = scene_detect::preprocess(&absolute_path);
+    if let Some(scenes) = &scene_hints {
+        log::info!("Detected {} scenes in {}", scenes.len(), file_path);
+        stages.insert("preprocess".to_string(), stage.elapsed().as_millis() as u64);
     }
 
     // Call Python RPC to process script
     let params = json!({
         "path": absolute_path.to_string_lossy().to_string(),
-        "output_path": null  // Use default output path
+        "output_path": output_path,
+        "mapping": export_mapping::active_mapping(),
+        "workspace_dir": workspace,
+        "scene_hints": scene_hints.as_ref().filter(|scenes| !scenes.is_empty()),
     });
+    let mut excel_output = None;
+    let mut unpriced_shots = Vec::new();
+    let mut warnings = Vec::new();
 
-    let result = rpc_client.call("process_script".to_string(), params).await?;
+    // Shots streamed by `shot_extracted` fill the table until the final
+    // list from the generated bid replaces them
+    bid_state.begin_extraction();
 
-    // Parse response
-    let excel_path = result.get("excel_path")
-        .and_then(|p| p.as_str())
-        .ok_or_else(|| "No excel_path in response".to_string())?;
+    let request_id = sidecar_state.track_call(RpcMethod::ProcessScript);
+    let outcome: Result<(), AppError> = async {
+        let stage = Instant::now();
+        let result = rpc_client.call_with_id(
+            RpcMethod::ProcessScript.to_string(),
+            params,
+            sidecar_state.rpc_timeout(RpcMethod::ProcessScript),
+            request_id.clone(),
+        ).await;
+        sidecar_state.untrack_call(RpcMethod::ProcessScript, &request_id);
+        let result = result?;
+        stages.insert("process_script".to_string(), stage.elapsed().as_millis() as u64);
 
-    log::info!("Generated bid: {}", excel_path);
+        if let Some(pages) = result.get("page_count").and_then(Value::as_u64) {
+            page_count = Some(pages);
+        }
+
+        // Parse response
+        let excel_path = result.get("excel_path")
+            .and_then(|p| p.as_str())
+            .ok_or_else(|| AppError::validation("No excel_path in response"))?;
+
+        log::info!("Generated bid: {}", excel_path);
+        excel_output = Some(excel_path.to_string());
+
+        session_state.update(|s| {
+            s.current_script = Some(absolute_path.to_string_lossy().to_string());
+            s.last_bid_path = Some(excel_path.to_string());
+            s.last_generated_bid = Some(excel_path.to_string());
+            s.last_output_dir = PathBuf::from(excel_path).parent()
+                .map(|p| p.to_string_lossy().to_string());
+        });
+
+        // Load the generated bid into memory
+        // The workbook doesn't keep the sidecar's confidence and flags, so
+        // they come from the shots it sent along the way
+        let stage = Instant::now();
+        let extracted = bid_state.get_shots();
+        load_bid_as(excel_path.to_string(), bid_id.clone(), Some(&extracted), bid_state, &sidecar_state).await?;
+        stages.insert("load_bid".to_string(), stage.elapsed().as_millis() as u64);
+
+        // Shots the sidecar couldn't price aren't in the workbook
+        let (unpriced, run_warnings) = partial_pricing(&result);
+        for warning in &run_warnings {
+            log::warn!("{}: {}", file_path, warning);
+        }
+        unpriced_shots = add_unpriced_shots(bid_state, unpriced);
+        warnings = run_warnings;
+        Ok(())
+    }.await;
 
-    // Load the generated bid into memory
-    load_bid_internal(excel_path.to_string(), &bid_state, &sidecar_state).await?;
+    // On failure or cancellation every provisional shot goes and the bid
+    // that was open before comes back, so no partial bid is left behind
+    let reconciled = match &outcome {
+        Ok(()) => bid_state.finish_extraction(),
+        Err(_) => bid_state.abort_extraction(),
+    };
+    if !reconciled.is_empty() {
+        emit_event(app, AppEvent::ShotsReconciled(reconciled))?;
+    }
+    if outcome.is_err() {
+        emit_event(app, AppEvent::BidChanged(get_bid_totals_internal(bid_state, &session_state)))?;
+    }
+    if let Err(AppError::RpcFailed { code: RPC_CANCELLED_CODE, .. }) = &outcome {
+        emit_event(app, AppEvent::ScriptProcessingCancelled(file_path.clone()))?;
+    }
+
+    record_processing_run(app, &settings, ProcessingRun {
+        started_at,
+        script_hash: history::script_hash(&absolute_path),
+        page_count,
+        word_count: size.word_count,
+        shot_count: if outcome.is_ok() { bid_state.get_shots().len() } else { 0 },
+        stages,
+        total_millis: started.elapsed().as_millis() as u64,
+        model: history::model_name(&settings),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        success: outcome.is_ok(),
+        error: outcome.as_ref().err().map(|e| e.message().to_string()),
+    });
+    outcome?;
 
     // Get loaded shots
     let shots = bid_state.get_shots();
@@ -86,98 +314,584 @@ pub async fn process_script(
             .to_string()),
         total_shots: shots.len(),
         vfx_categories: extract_vfx_categories(&shots),
+        total_frames: total_frame_count(&shots),
     };
 
     let analysis = ScriptAnalysis {
+        scenes: scene_summaries(&shots),
         shots,
         metadata,
+        source: AnalysisSource::Sidecar,
+        output_path: excel_output,
+        detected_scenes: scene_hints.map(|scenes| scenes.len()),
+        unpriced_shots,
+        warnings,
+        cached: false,
     };
 
-    window.emit("script-processing-complete", &analysis)
-        .map_err(|e| e.to_string())?;
+    if let (Some(hash), Some(excel_path)) = (content_hash, &analysis.output_path) {
+        remember_result(app, CachedResult {
+            hash,
+            script_path: absolute_path.to_string_lossy().to_string(),
+            excel_path: excel_path.clone(),
+            shot_count: analysis.shots.len(),
+            processed_at: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+
+    emit_event(app, AppEvent::ScriptProcessingComplete(analysis.clone()))?;
 
     Ok(analysis)
 }
 
+/// SHA-256 of the script's contents, read off the async runtime with
+/// `script-hash-progress` for large files; None when it can't be read,
+/// which only skips the cache
+async fn hash_script(app: &AppHandle, path: &Path) -> Option<String> {
+    let handle = app.clone();
+    let path = path.to_path_buf();
+    let hashed = tauri::async_runtime::spawn_blocking(move || {
+        let file_path = path.to_string_lossy().to_string();
+        script_cache::hash_file(&path, |bytes_hashed, total_bytes| {
+            emit_event(&handle, AppEvent::ScriptHashProgress(ScriptHashProgress {
+                file_path: file_path.clone(),
+                bytes_hashed,
+                total_bytes,
+            })).ok();
+        })
+    }).await;
+
+    match hashed {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(e)) => {
+            log::warn!("Not checking the script cache: {}", e);
+            None
+        }
+        Err(e) => {
+            log::warn!("Script hashing failed: {}", e);
+            None
+        }
+    }
+}
+
+/// The earlier result for a script with `hash`, pruning deleted workbooks
+fn cached_result(app: &AppHandle, hash: &str) -> Option<CachedResult> {
+    let path = script_cache::cache_path(app).ok()?;
+    let mut cache = script_cache::load(&path);
+    let (found, pruned) = cache.lookup(hash);
+    if pruned {
+        if let Err(e) = script_cache::save(&path, &cache) {
+            log::warn!("Failed to prune the script cache: {}", e);
+        }
+    }
+    found
+}
+
+fn remember_result(app: &AppHandle, result: CachedResult) {
+    let saved = script_cache::cache_path(app).and_then(|path| {
+        let mut cache = script_cache::load(&path);
+        cache.prune();
+        cache.insert(result);
+        script_cache::save(&path, &cache)
+    });
+    if let Err(e) = saved {
+        log::warn!("Failed to remember the script result: {}", e);
+    }
+}
+
+/// Load the workbook of an earlier run of `script` in place of processing it
+async fn open_cached_result(
+    app: &AppHandle,
+    cached: &CachedResult,
+    script: &Path,
+    bid_state: &BidState,
+) -> Result<ScriptAnalysis, AppError> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+
+    log::info!("{} was processed before, reopening {}", script.display(), cached.excel_path);
+    let mut analysis = open_bid(cached.excel_path.clone(), bid_state, &sidecar_state, &session_state).await?;
+    analysis.cached = true;
+    analysis.output_path = Some(cached.excel_path.clone());
+
+    session_state.update(|s| {
+        s.current_script = Some(script.to_string_lossy().to_string());
+        s.last_generated_bid = Some(cached.excel_path.clone());
+    });
+    emit_event(app, AppEvent::ScriptResultCached(cached.clone()))?;
+    Ok(analysis)
+}
+
+/// Directory a new bid goes in: `explicit`, else the configured output
+/// directory; None leaves it to the sidecar
+pub(crate) fn resolve_output_dir(explicit: Option<&str>, settings: &Settings) -> Option<PathBuf> {
+    explicit
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .or_else(|| Some(settings.paths.output_dir.trim()).filter(|dir| !dir.is_empty()))
+        .map(PathBuf::from)
+}
+
+/// Create `dir` if needed and check a file can be written in it
+pub(crate) fn ensure_writable_dir(dir: &Path) -> Result<(), AppError> {
+    let not_writable = |e: std::io::Error| {
+        AppError::io(format!("Output directory {} is not writable: {}", dir.display(), e))
+    };
+
+    std::fs::create_dir_all(dir).map_err(not_writable)?;
+    let probe = dir.join(format!(".write-test-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&probe, b"").map_err(not_writable)?;
+    std::fs::remove_file(&probe).ok();
+    Ok(())
+}
+
+/// `<dir>/<script name>_bid.xlsx`
+fn bid_output_path(dir: &Path, script: &Path) -> PathBuf {
+    let stem = script.file_stem().and_then(|s| s.to_str()).unwrap_or("script");
+    dir.join(format!("{}_bid.xlsx", stem))
+}
+
+/// Append a run to the processing history unless the user opted out
+fn record_processing_run(app: &AppHandle, settings: &Settings, run: ProcessingRun) {
+    if !settings.ui.record_processing_history {
+        return;
+    }
+
+    if let Err(e) = history::history_path(app).and_then(|path| history::append_run(&path, &run)) {
+        log::warn!("Failed to record processing run: {}", e);
+    }
+}
+
+tracked_command! { Script;
+/// Past `process_script` runs, newest first
+#[tauri::command]
+pub fn get_processing_history(limit: Option<usize>, app: AppHandle) -> Result<Vec<ProcessingRun>, AppError> {
+    let path = history::history_path(&app)?;
+    Ok(history::recent_runs(&path, limit.unwrap_or(DEFAULT_HISTORY_LIMIT)))
+}
+}
+
+tracked_command! { Script;
+/// How long `process_script` is likely to take on a script
+///
+/// Extrapolated from the throughput of past runs; the confidence is
+/// `unknown` when there is no history or the script's size can't be read.
+#[tauri::command]
+pub fn estimate_processing_time(
+    script_path: String,
+    app: AppHandle,
+    session_state: State<'_, SessionState>,
+) -> Result<ProcessingEstimate, AppError> {
+    let path = Path::new(&script_path);
+    if !path.is_file() {
+        return Err(AppError::not_found(format!("File not found: {}", script_path)));
+    }
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    let runs = history::read_runs(&history::history_path(&app)?);
+    Ok(history::estimate(history::script_size(path), &runs, &history::model_name(&settings)))
+}
+}
+
+/// Quick look at a script before a full `process_script` run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScriptScan {
+    /// Candidate VFX shots; the full run may find a few more or fewer
+    pub shot_count: usize,
+    /// VFX categories among the candidates
+    pub categories: Vec<String>,
+    pub page_count: Option<u64>,
+    /// How long the full run is likely to take
+    pub estimate: ProcessingEstimate,
+}
+
+/// Result of `scan_script` as the sidecar sends it
+#[derive(Debug, Deserialize)]
+struct ScanReply {
+    shot_count: usize,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    page_count: Option<u64>,
+}
+
+tracked_command! { Script;
+/// Count a script's candidate VFX shots without pricing them
+///
+/// Much faster than `process_script`: nothing is priced, no workbook is
+/// written and the bid is left alone. Comes with the expected time of a
+/// full run, as `estimate_processing_time` gives it.
+#[tauri::command]
+pub async fn scan_script(
+    path: String,
+    app: AppHandle,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<ScriptScan, AppError> {
+    let absolute_path = Path::new(&path).canonicalize()
+        .map_err(|e| AppError::not_found(format!("Invalid file path: {}", e)))?;
+    let rpc_client = sidecar_state.client_for(RpcMethod::ScanScript)?;
+    let timeout = sidecar_state.rpc_timeout(RpcMethod::ScanScript);
+    let reply = request_scan(&rpc_client, &absolute_path, timeout).await?;
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    let runs = history::read_runs(&history::history_path(&app)?);
+    let estimate = history::estimate(history::script_size(&absolute_path), &runs, &history::model_name(&settings));
+    Ok(ScriptScan {
+        shot_count: reply.shot_count,
+        categories: reply.categories,
+        page_count: reply.page_count.or(estimate.size.page_count),
+        estimate,
+    })
+}
+}
+
+async fn request_scan(rpc_client: &AsyncRpcClient, path: &Path, timeout: Duration) -> Result<ScanReply, AppError> {
+    let params = json!({ "path": path.to_string_lossy().to_string() });
+    let result = rpc_client.call_detailed(RpcMethod::ScanScript.to_string(), params, timeout).await?;
+    serde_json::from_value(result)
+        .map_err(|e| AppError::validation(format!("Unexpected scan_script response: {}", e)))
+}
+
+tracked_command! { Script;
+/// Stop a running `process_script`
+///
+/// The pending call fails at once with a cancelled `rpc_failed` error, the
+/// sidecar is sent a `cancel` notification and `script-processing-cancelled`
+/// is emitted. Returns false when no script was being processed.
+#[tauri::command]
+pub fn cancel_script_processing(sidecar_state: State<'_, SidecarState>) -> Result<bool, AppError> {
+    sidecar_state.cancel_call(RpcMethod::ProcessScript)
+}
+}
+
+/// Add a shot from a `shot_extracted` sidecar event to the bid
+///
+/// The shot goes to whichever bid is processing a script. Emits
+/// `shot-extracted` to the window showing it with the provisional shot so the table can
+/// show it before the pipeline finishes. Repeated and late events are dropped.
+pub fn handle_shot_extracted(app: &AppHandle, data: &Value) {
+    let Some(shot) = parse_extracted_shot(data) else {
+        log::warn!("Ignoring shot_extracted event without a valid shot: {}", data);
+        return;
+    };
+
+    let main: State<BidState> = app.state();
+    let registry = app.state::<WorkspaceRegistry>();
+    let windows = registry.contexts();
+    let contexts = std::iter::once((MAIN_WINDOW_LABEL, main.inner()))
+        .chain(windows.iter().map(|(label, bid)| (label.as_str(), bid.as_ref())));
+    for (label, bid_state) in contexts {
+        if bid_state.is_extracting() {
+            if let Some(shot) = bid_state.add_provisional_shot(shot) {
+                emit_event_to(app, registry.window_of(label).as_str(), AppEvent::ShotExtracted(shot)).ok();
+            }
+            return;
+        }
+    }
+}
+
+/// Parse the shot fields of a `shot_extracted` event
+///
+/// The sidecar may send a shot before pricing is known, so missing fields
+/// take their defaults; only the id is required.
+fn parse_extracted_shot(data: &Value) -> Option<ShotData> {
+    let fields = data.as_object()?;
+    fields.get("id")
+        .and_then(|id| id.as_str())
+        .filter(|id| !id.trim().is_empty())?;
+
+    // Drop unset defaults so aliased fields (cut_in, frames) don't collide
+    let mut shot = serde_json::to_value(ShotData::default()).ok()?;
+    let merged = shot.as_object_mut()?;
+    merged.retain(|_, value| !value.is_null());
+    merged.extend(fields.clone());
+
+    serde_json::from_value(shot).ok()
+}
+
+/// Shots a `process_script` result lists as unpriced, and its warnings
+fn partial_pricing(result: &Value) -> (Vec<ShotData>, Vec<String>) {
+    let unpriced = result.get("unpriced_shots")
+        .and_then(Value::as_array)
+        .map(|shots| shots.iter().filter_map(parse_extracted_shot).collect())
+        .unwrap_or_default();
+    let warnings = result.get("warnings")
+        .and_then(Value::as_array)
+        .map(|warnings| warnings.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    (unpriced, warnings)
+}
+
+/// Add unpriced shots to the bid flagged for review, skipping any the
+/// workbook already has; returns them as stored
+fn add_unpriced_shots(bid_state: &BidState, shots: Vec<ShotData>) -> Vec<ShotData> {
+    let mut added = Vec::new();
+    for mut shot in shots {
+        if bid_state.get_shots().iter().any(|existing| existing.id == shot.id) {
+            continue;
+        }
+        if !shot.extraction_flags.iter().any(|flag| flag == review::UNPRICED) {
+            shot.extraction_flags.push(review::UNPRICED.to_string());
+        }
+        shot.sync_frame_count();
+        added.push(shot.id.clone());
+        bid_state.add_shot(shot);
+    }
+
+    bid_state.get_shots()
+        .into_iter()
+        .filter(|shot| added.contains(&shot.id))
+        .collect()
+}
+
+tracked_command! { Script;
 /// Load an existing bid from Excel file
 ///
 /// This calls the Python sidecar to load and parse an Excel bid into memory.
+/// When the sidecar is not running the workbook is read in Rust instead and
+/// the result is flagged with `source: "rust-fallback"`.
 #[tauri::command]
 pub async fn load_bid(
     file_path: String,
-    bid_state: State<'_, BidState>,
+    bid_state: WindowBid<'_>,
     sidecar_state: State<'_, SidecarState>,
-) -> Result<ScriptAnalysis, String> {
+    session_state: State<'_, SessionState>,
+) -> Result<ScriptAnalysis, AppError> {
+    open_bid(file_path, &bid_state, &sidecar_state, &session_state).await
+}
+}
+
+/// Load a bid through the sidecar, or in Rust when it isn't running, and
+/// remember it in the session
+pub(crate) async fn open_bid(
+    file_path: String,
+    bid_state: &BidState,
+    sidecar_state: &SidecarState,
+    session_state: &SessionState,
+) -> Result<ScriptAnalysis, AppError> {
     log::info!("Loading bid: {}", file_path);
 
-    // Check if sidecar is running
-    if !sidecar_state.is_running() {
-        return Err("Python sidecar is not running. Please restart the application.".to_string());
-    }
+    let analysis = if sidecar_state.is_running() {
+        load_bid_internal(file_path.clone(), bid_state, sidecar_state).await?
+    } else {
+        log::warn!("Python sidecar is not running, reading bid without it");
+        load_bid_fallback(&file_path, &session_state.get_settings().unwrap_or_default().locale, bid_state)?
+    };
 
-    let analysis = load_bid_internal(file_path, &bid_state, &sidecar_state).await?;
+    session_state.update(|s| s.last_bid_path = Some(file_path));
 
     Ok(analysis)
 }
 
-/// Internal function to load bid (shared by process_script and load_bid)
-async fn load_bid_internal(
+tracked_command! { Script;
+/// Open a bid workbook in the system's default app
+///
+/// Opens `path`, or the workbook `process_script` last generated when it is
+/// omitted. Only `.xlsx` and `.xls` files are opened. Returns the path opened.
+#[tauri::command]
+pub fn open_bid_file(path: Option<String>, session_state: State<'_, SessionState>) -> Result<String, AppError> {
+    open_bid_file_internal(path, &session_state, open_in_default_app)
+}
+}
+
+pub(crate) fn open_bid_file_internal(
+    path: Option<String>,
+    session_state: &SessionState,
+    open: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> Result<String, AppError> {
+    let path = path
+        .or_else(|| session_state.snapshot().last_generated_bid)
+        .ok_or_else(|| AppError::not_found("No bid has been generated yet"))?;
+    if !Path::new(&path).is_file() {
+        return Err(AppError::not_found(format!("File not found: {}", path)));
+    }
+    let is_workbook = Path::new(&path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| OPENABLE_EXTENSIONS.iter().any(|allowed| ext.eq_ignore_ascii_case(allowed)));
+    if !is_workbook {
+        return Err(AppError::validation(format!("Only Excel workbooks can be opened: {}", path)));
+    }
+
+    open(Path::new(&path)).map_err(|e| AppError::io(format!("Failed to open {}: {}", path, e)))?;
+    Ok(path)
+}
+
+/// Extensions `open_bid_file` will hand to the default app
+const OPENABLE_EXTENSIONS: [&str; 2] = ["xlsx", "xls"];
+
+/// Hand `path` to the platform opener without waiting for the app it starts
+///
+/// Goes through the opener plugin (ShellExecute on Windows) rather than a
+/// shell, so nothing in the path is interpreted.
+fn open_in_default_app(path: &Path) -> std::io::Result<()> {
+    tauri_plugin_opener::open_path(path, None::<&str>).map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Internal function to load bid (shared by load_bid and session restore)
+///
+/// A workbook loaded before keeps its bid id and workspace; any other gets
+/// a fresh one.
+pub(crate) async fn load_bid_internal(
     file_path: String,
-    _bid_state: &BidState,
+    bid_state: &BidState,
     sidecar_state: &SidecarState,
-) -> Result<ScriptAnalysis, String> {
-    let rpc_client = sidecar_state.rpc_client()
-        .ok_or_else(|| "Failed to get RPC client".to_string())?;
+) -> Result<ScriptAnalysis, AppError> {
+    let bid_id = workbook_bid_id(Path::new(&file_path));
+    load_bid_as(file_path, bid_id, None, bid_state, sidecar_state).await
+}
+
+/// Bid id `path` was last loaded as, else a new one
+fn workbook_bid_id(path: &Path) -> String {
+    path.canonicalize()
+        .ok()
+        .and_then(|path| workspace::bid_for_source(workspace::root()?, &path))
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Remember that `bid_id` was loaded from `path`, for `workbook_bid_id`
+fn remember_workbook(bid_id: &str, path: &Path) {
+    let Some(root) = workspace::root() else {
+        return;
+    };
+    let recorded = path.canonicalize()
+        .map_err(|e| e.to_string())
+        .and_then(|path| workspace::record_source(&workspace::prepare(root, bid_id)?, &path));
+    if let Err(e) = recorded {
+        log::warn!("Not remembering the bid id of {}: {}", path.display(), e);
+    }
+}
+
+/// Load a bid through the sidecar under `bid_id`, in that bid's workspace
+///
+/// `extracted` is set for the bid a script run just produced: its shots
+/// take their scores from it and get the review heuristics' flags.
+async fn load_bid_as(
+    file_path: String,
+    bid_id: String,
+    extracted: Option<&[ShotData]>,
+    bid_state: &BidState,
+    sidecar_state: &SidecarState,
+) -> Result<ScriptAnalysis, AppError> {
+    let rpc_client = sidecar_state.client_for(RpcMethod::LoadBid)?;
 
     // Resolve file path
     let path = PathBuf::from(&file_path);
     let absolute_path = path.canonicalize()
-        .map_err(|e| format!("Invalid file path: {}", e))?;
+        .map_err(|e| AppError::not_found(format!("Invalid file path: {}", e)))?;
 
     if !absolute_path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(AppError::not_found(format!("File not found: {}", file_path)));
     }
 
     // Call Python RPC to load bid
     let params = json!({
-        "path": absolute_path.to_string_lossy().to_string()
+        "path": absolute_path.to_string_lossy().to_string(),
+        "workspace_dir": sidecar_state.use_workspace(&bid_id),
     });
 
-    let result = rpc_client.call("load_bid".to_string(), params).await?;
+    let result = rpc_client.call_detailed(RpcMethod::LoadBid.to_string(), params, sidecar_state.rpc_timeout(RpcMethod::LoadBid)).await?;
 
     // Parse summary
     let summary = result.get("summary")
         .and_then(|s| s.as_object())
-        .ok_or_else(|| "No summary in response".to_string())?;
+        .ok_or_else(|| AppError::validation("No summary in response"))?;
 
     let total_shots = summary.get("total_shots")
         .and_then(|v| v.as_u64())
         .unwrap_or(0) as usize;
 
-    // For now, we don't get detailed shots from load_bid
-    // The caller can call bid_query to get specific data
-    // This is a limitation of the current Python RPC implementation
+    // Older sidecars don't return detailed shots from load_bid; the caller
+    // can use bid_query instead. When shots are present, frame columns
+    // (frame_in/cut_in, frame_out/cut_out, frame_count/frames) map directly.
+    let mut shots: Vec<ShotData> = result.get("shots")
+        .and_then(|s| serde_json::from_value(s.clone()).ok())
+        .unwrap_or_default();
+
+    for shot in &mut shots {
+        shot.sync_frame_count();
+    }
+    if let Some(extracted) = extracted {
+        review::carry_scores(extracted, &mut shots);
+        review::apply_heuristics(&mut shots);
+    }
+
+    if !shots.is_empty() {
+        bid_state.set_shots(shots.clone())?;
+        remember_workbook(&bid_id, &absolute_path);
+        bid_state.assign_bid_id(bid_id);
+    }
+    track_source(bid_state, &absolute_path);
 
     Ok(ScriptAnalysis {
-        shots: vec![],
         metadata: ScriptMetadata {
             title: summary.get("script_name")
                 .and_then(|s| s.as_str())
                 .map(|s| s.to_string()),
             total_shots,
-            vfx_categories: vec![],
+            vfx_categories: extract_vfx_categories(&shots),
+            total_frames: total_frame_count(&shots),
+        },
+        scenes: scene_summaries(&shots),
+        shots,
+        source: AnalysisSource::Sidecar,
+        output_path: None,
+        detected_scenes: None,
+        unpriced_shots: Vec::new(),
+        warnings: Vec::new(),
+        cached: false,
+    })
+}
+
+/// Read a bid workbook without the sidecar, taking numbers typed as text in
+/// `locale`'s style
+pub(crate) fn load_bid_fallback(
+    file_path: &str,
+    locale: &LocaleSettings,
+    bid_state: &BidState,
+) -> Result<ScriptAnalysis, AppError> {
+    let path = PathBuf::from(file_path);
+    if !path.exists() {
+        return Err(AppError::not_found(format!("File not found: {}", file_path)));
+    }
+
+    let shots = excel::read_bid_workbook(&path, locale).map_err(AppError::validation)?;
+    let bid_id = workbook_bid_id(&path);
+    bid_state.set_shots(shots.clone())?;
+    remember_workbook(&bid_id, &path);
+    bid_state.assign_bid_id(bid_id);
+    track_source(bid_state, &path);
+
+    Ok(ScriptAnalysis {
+        metadata: ScriptMetadata {
+            title: path.file_stem()
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string()),
+            total_shots: shots.len(),
+            vfx_categories: extract_vfx_categories(&shots),
+            total_frames: total_frame_count(&shots),
         },
+        scenes: scene_summaries(&shots),
+        shots,
+        source: AnalysisSource::RustFallback,
+        output_path: None,
+        detected_scenes: None,
+        unpriced_shots: Vec::new(),
+        warnings: Vec::new(),
+        cached: false,
     })
 }
 
+tracked_command! { Script;
 /// Export bid to Excel format
 ///
 /// Currently this is a placeholder. The Excel is generated during process_script.
 #[tauri::command]
 pub async fn export_bid(
     output_path: String,
-    _bid_state: State<'_, BidState>,
-) -> Result<String, String> {
+    _bid_state: WindowBid<'_>,
+) -> Result<String, AppError> {
     log::info!("Exporting bid to: {}", output_path);
 
     // TODO: Implement Excel export via Python RPC
@@ -185,6 +899,429 @@ pub async fn export_bid(
 
     Ok(format!("Export not yet implemented. Use process_script to generate Excel."))
 }
+}
+
+tracked_command! { Script;
+/// Built-in and saved export mappings, and which is active
+#[tauri::command]
+pub fn list_export_mappings() -> Result<ExportMappingList, AppError> {
+    let store = load_mapping_store()?;
+    Ok(ExportMappingList::from(&store))
+}
+}
+
+tracked_command! { Script;
+/// Save a studio's sheet layout, replacing a saved mapping of the same name
+///
+/// Built-in presets can't be overwritten.
+#[tauri::command]
+pub fn save_export_mapping(mapping: ExportMapping) -> Result<ExportMappingList, AppError> {
+    let mut store = load_mapping_store()?;
+    store.save(mapping).map_err(AppError::validation)?;
+    write_mapping_store(&store)?;
+    Ok(ExportMappingList::from(&store))
+}
+}
+
+tracked_command! { Script;
+/// Use the mapping called `name` for Excel and CSV exports
+#[tauri::command]
+pub fn set_active_export_mapping(name: String) -> Result<ExportMapping, AppError> {
+    let mut store = load_mapping_store()?;
+    let mapping = store.set_active(&name).map_err(AppError::not_found)?;
+    write_mapping_store(&store)?;
+    Ok(mapping)
+}
+}
+
+fn load_mapping_store() -> Result<ExportMappingStore, AppError> {
+    match export_mapping::store_path() {
+        Some(path) => export_mapping::load_store(path).map_err(AppError::io),
+        None => Ok(ExportMappingStore::default()),
+    }
+}
+
+fn write_mapping_store(store: &ExportMappingStore) -> Result<(), AppError> {
+    let path = export_mapping::store_path()
+        .ok_or_else(|| AppError::io("Config directory is not available"))?;
+    export_mapping::write_store(path, store).map_err(AppError::io)
+}
+
+tracked_command! { Script;
+/// Export the shot list as CSV
+///
+/// Numbers follow the locale settings when `export_locale_numbers` is on and
+/// are written plainly otherwise. Final prices use the bid's rounding.
+/// Columns follow the active export mapping. `rows` chooses one row per shot
+/// (the default) or per task. Shot tags are internal and left out unless
+/// `include_tags` is set. Archived shots are left out unless
+/// `include_archived` is set, when they follow the others. Returns the path
+/// written.
+#[tauri::command]
+pub fn export_bid_csv(
+    output_path: String,
+    rows: Option<CsvRows>,
+    include_tags: Option<bool>,
+    include_archived: Option<bool>,
+    bid_state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
+    let include_archived = include_archived.unwrap_or(false);
+    export_bid_csv_internal(output_path, rows.unwrap_or_default(), include_tags.unwrap_or(false), include_archived, &bid_state, &session_state)
+}
+}
+
+pub(crate) fn export_bid_csv_internal(
+    output_path: String,
+    rows: CsvRows,
+    include_tags: bool,
+    include_archived: bool,
+    bid_state: &BidState,
+    session_state: &SessionState,
+) -> Result<String, AppError> {
+    let locale = session_state.get_settings().unwrap_or_default().locale;
+    let rounding = bid_state.get_meta().rounding;
+
+    let mapping = export_mapping::active_mapping();
+
+    bid_state.ensure_source_not_overwritten(Path::new(&output_path))?;
+    let mut shots = bid_state.get_shots();
+    if include_archived {
+        shots.extend(bid_state.get_archived_shots().into_iter().map(|a| a.shot));
+    }
+
+    bid_csv::write_bid_csv(Path::new(&output_path), &shots, &locale, rounding, rows, &mapping, include_tags)
+        .map_err(AppError::io)?;
+    track_source(bid_state, Path::new(&output_path));
+
+    Ok(output_path)
+}
+
+tracked_command! { Script;
+/// Export the shot list for editorial or ShotGrid
+///
+/// `mapping` names an export mapping whose headers replace the format's own
+/// for the fields it places. Shots missing a field the format needs are
+/// listed in the report instead of written with blanks.
+#[tauri::command]
+pub fn export_shot_list(
+    format: ShotListFormat,
+    output_path: String,
+    mapping: Option<String>,
+    bid_state: WindowBid<'_>,
+) -> Result<ShotListReport, AppError> {
+    let names = match mapping {
+        Some(name) => Some(
+            load_mapping_store()?
+                .find(&name)
+                .ok_or_else(|| AppError::not_found(format!("No export mapping named '{}'", name)))?,
+        ),
+        None => None,
+    };
+
+    bid_state.ensure_source_not_overwritten(Path::new(&output_path))?;
+    shot_list::write_shot_list(
+        Path::new(&output_path),
+        &bid_state.get_shots(),
+        &bid_state.get_groups(),
+        format,
+        names.as_ref(),
+    )
+    .map_err(AppError::io)
+}
+}
+
+tracked_command! { Script;
+/// Render a quick-look summary of the bid for pasting into an email
+///
+/// `options` picks the sections, all of them by default. Amounts follow the
+/// locale settings; tags and task notes are never included. When
+/// `output_path` is given the summary is also written there.
+#[tauri::command]
+pub fn generate_bid_summary(
+    format: SummaryFormat,
+    options: Option<SummaryOptions>,
+    output_path: Option<String>,
+    bid_state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
+    let locale = session_state.get_settings().unwrap_or_default().locale;
+    let rendered = summary::render_summary(
+        &bid_state.get_shots(),
+        &bid_state.get_meta(),
+        &locale,
+        format,
+        &options.unwrap_or_default(),
+    );
+
+    if let Some(path) = output_path {
+        bid_state.ensure_source_not_overwritten(Path::new(&path))?;
+        std::fs::write(&path, &rendered)
+            .map_err(|e| AppError::io(format!("Failed to write summary: {}", e)))?;
+    }
+    Ok(rendered)
+}
+}
+
+tracked_command! { Script;
+/// Replace the shots with those in a CSV file
+///
+/// `locale` (e.g. "de-DE") declares how the file's numbers are written. When
+/// omitted, files are assumed to match our own CSV export settings.
+#[tauri::command]
+pub fn import_bid_csv(
+    file_path: String,
+    locale: Option<String>,
+    bid_state: WindowBid<'_>,
+    session_state: State<'_, SessionState>,
+) -> Result<Vec<ShotData>, AppError> {
+    import_bid_csv_internal(file_path, locale, &bid_state, &session_state)
+}
+}
+
+pub(crate) fn import_bid_csv_internal(
+    file_path: String,
+    locale: Option<String>,
+    bid_state: &BidState,
+    session_state: &SessionState,
+) -> Result<Vec<ShotData>, AppError> {
+    bid_state.ensure_writable()?;
+    let path = PathBuf::from(&file_path);
+    if !path.exists() {
+        return Err(AppError::not_found(format!("File not found: {}", file_path)));
+    }
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    let locale = match locale {
+        Some(tag) => LocaleSettings::for_locale(&tag),
+        None if settings.locale.export_locale_numbers => settings.locale,
+        None => LocaleSettings::default(),
+    };
+
+    let shots = bid_csv::read_bid_csv(&path, &locale).map_err(AppError::validation)?;
+    bid_state.set_shots(shots.clone())?;
+    track_source(bid_state, &path);
+
+    Ok(shots)
+}
+
+tracked_command! { Script;
+/// Export the bid using a studio's own Excel template
+///
+/// The sidecar fills the template's named ranges/placeholders with shot data
+/// and totals instead of generating the default layout, in the columns of
+/// the active export mapping. Shot tags are sent only with `include_tags`.
+/// Returns the path of the written file.
+#[tauri::command]
+pub async fn export_bid_with_template(
+    template_path: String,
+    output_path: String,
+    include_tags: Option<bool>,
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<String, AppError> {
+    export_bid_with_template_internal(template_path, output_path, include_tags.unwrap_or(false), &bid_state, &sidecar_state).await
+}
+}
+
+pub(crate) async fn export_bid_with_template_internal(
+    template_path: String,
+    output_path: String,
+    include_tags: bool,
+    bid_state: &BidState,
+    sidecar_state: &SidecarState,
+) -> Result<String, AppError> {
+    log::info!("Exporting bid with template {} to: {}", template_path, output_path);
+
+    // Check if sidecar is running
+    if !sidecar_state.is_running() {
+        return Err(AppError::sidecar_unavailable());
+    }
+
+    let rpc_client = sidecar_state.client_for(RpcMethod::ExportBidWithTemplate)?;
+
+    let timeout = sidecar_state.rpc_timeout(RpcMethod::ExportBidWithTemplate);
+    export_with_template(&rpc_client, timeout, Path::new(&template_path), &output_path, include_tags, bid_state).await
+}
+
+/// Validate the template and ask the sidecar to fill it
+async fn export_with_template(
+    rpc_client: &AsyncRpcClient,
+    timeout: Duration,
+    template_path: &Path,
+    output_path: &str,
+    include_tags: bool,
+    bid_state: &BidState,
+) -> Result<String, AppError> {
+    if !template_path.is_file() {
+        return Err(AppError::not_found(format!("Template not found: {}", template_path.display())));
+    }
+
+    let is_xlsx = template_path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("xlsx"))
+        .unwrap_or(false);
+
+    if !is_xlsx {
+        return Err(AppError::validation(format!("Template must be an .xlsx file: {}", template_path.display())));
+    }
+
+    bid_state.ensure_source_not_overwritten(Path::new(output_path))?;
+
+    // Internal assumptions stay with the bidder
+    let mut meta = bid_state.get_meta();
+    meta.assumptions = exported_items(&meta.assumptions);
+    let shots = bid_state.get_shots();
+    let params = json!({
+        "template_path": template_path.to_string_lossy().to_string(),
+        "output_path": output_path,
+        "placeholders": TEMPLATE_PLACEHOLDERS,
+        "shots": export_shots(&shots, meta.rounding, include_tags),
+        "grand_total": total_final_price(&shots, meta.rounding),
+        "departments": department_totals(&shots),
+        "assumptions": meta.assumptions,
+        "meta": meta,
+        "mapping": export_mapping::active_mapping(),
+    });
+
+    let result = rpc_client.call_detailed(RpcMethod::ExportBidWithTemplate.to_string(), params, timeout).await
+        .map_err(|e| {
+            let missing: Vec<String> = e.data.as_ref()
+                .and_then(|d| d.get("missing_placeholders"))
+                .and_then(|m| serde_json::from_value(m.clone()).ok())
+                .unwrap_or_default();
+
+            if missing.is_empty() {
+                AppError::from(e)
+            } else {
+                AppError::validation(format!("Template is missing required placeholders: {}", missing.join(", ")))
+            }
+        })?;
+
+    let excel_path = result.get("excel_path")
+        .and_then(|p| p.as_str())
+        .unwrap_or(output_path);
+
+    log::info!("Exported bid: {}", excel_path);
+    track_source(bid_state, Path::new(excel_path));
+
+    // The export now holds the latest edits, so the autosave is redundant
+    if let Err(e) = bid_state.clear_autosave() {
+        log::warn!("{}", e);
+    }
+
+    Ok(excel_path.to_string())
+}
+
+/// Remember the file the bid was loaded from or exported to, so edits
+/// made to it outside the app are noticed
+fn track_source(bid_state: &BidState, path: &Path) {
+    match source_sync::fingerprint(path) {
+        Ok(fingerprint) => bid_state.track_source(fingerprint),
+        Err(e) => log::warn!("Not tracking changes to the bid file: {}", e),
+    }
+}
+
+tracked_command! { Script;
+/// Compare the bid's file with what's on disk
+///
+/// Called when the window regains focus. A change or deletion not reported
+/// before is also sent as the `source-file-diverged` event.
+#[tauri::command]
+pub fn check_source_sync(app: AppHandle, bid_state: WindowBid<'_>) -> Result<SourceStatus, AppError> {
+    let (status, newly_diverged) = bid_state.check_source();
+    if newly_diverged {
+        emit_event(&app, AppEvent::SourceFileDiverged(status.clone()))?;
+    }
+    Ok(status)
+}
+}
+
+tracked_command! { Script;
+/// Drop the in-app bid and load its file again, with the outside edits
+#[tauri::command]
+pub async fn reload_from_disk(
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<Vec<ShotData>, AppError> {
+    let source = bid_state.source_file()
+        .ok_or_else(|| AppError::validation("The bid wasn't loaded from a file"))?;
+    if !Path::new(&source.path).exists() {
+        return Err(AppError::not_found(format!("{} was moved or deleted", source.path)));
+    }
+
+    if is_csv(Path::new(&source.path)) {
+        return import_bid_csv_internal(source.path, None, &bid_state, &session_state);
+    }
+    Ok(open_bid(source.path, &bid_state, &sidecar_state, &session_state).await?.shots)
+}
+}
+
+tracked_command! { Script;
+/// Let the next export write over the bid's file despite the outside edits
+#[tauri::command]
+pub fn overwrite_on_export(bid_state: WindowBid<'_>) -> Result<(), AppError> {
+    bid_state.confirm_overwrite()
+}
+}
+
+tracked_command! { Script;
+/// Export next to the bid's file as `<name>_v<N>`, leaving the changed
+/// file alone
+///
+/// CSV bids are exported as CSV; Excel bids need `template_path`. Returns
+/// the path written, which becomes the bid's file from then on.
+#[tauri::command]
+pub async fn export_as_new_version(
+    template_path: Option<String>,
+    include_tags: Option<bool>,
+    bid_state: WindowBid<'_>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<String, AppError> {
+    let source = bid_state.source_file()
+        .ok_or_else(|| AppError::validation("The bid wasn't loaded from a file"))?;
+    let source_path = PathBuf::from(&source.path);
+    let output_path = source_sync::next_version_path(&source_path).to_string_lossy().to_string();
+    let include_tags = include_tags.unwrap_or(false);
+
+    if is_csv(&source_path) {
+        return export_bid_csv_internal(output_path, CsvRows::default(), include_tags, false, &bid_state, &session_state);
+    }
+    let template_path = template_path
+        .ok_or_else(|| AppError::validation("Choose a template to export a new Excel version"))?;
+    export_bid_with_template_internal(template_path, output_path, include_tags, &bid_state, &sidecar_state).await
+}
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+/// Shots as sent for export, each with the client-facing
+/// `final_price_rounded` next to the precise `final_price`
+///
+/// Review state and hour provenance are internal and never sent.
+fn export_shots(shots: &[ShotData], rounding: PriceRounding, include_tags: bool) -> Vec<Value> {
+    shots.iter()
+        .map(|shot| {
+            let mut value = json!(shot);
+            value["final_price_rounded"] = json!(shot.final_price.map(|price| rounding.apply(price)));
+            if let Some(fields) = value.as_object_mut() {
+                for field in INTERNAL_FIELDS {
+                    fields.remove(field);
+                }
+                if !include_tags {
+                    fields.remove("tags");
+                }
+            }
+            value
+        })
+        .collect()
+}
 
 /// Extract unique VFX categories from shots
 fn extract_vfx_categories(shots: &[ShotData]) -> Vec<String> {
@@ -202,3 +1339,368 @@ fn extract_vfx_categories(shots: &[ShotData]) -> Vec<String> {
     sorted.sort();
     sorted
 }
+
+/// Shot count, price subtotal and frame total for each scene
+pub(crate) fn scene_summaries(shots: &[ShotData]) -> Vec<SceneSummary> {
+    let mut scenes: Vec<SceneSummary> = Vec::new();
+
+    for shot in shots {
+        let scene_number = shot.scene_number.trim();
+        let index = match scenes.iter().position(|s| s.scene_number == scene_number) {
+            Some(index) => index,
+            None => {
+                scenes.push(SceneSummary {
+                    scene_number: scene_number.to_string(),
+                    shot_count: 0,
+                    subtotal: 0.0,
+                    departments: Vec::new(),
+                    total_frames: 0,
+                });
+                scenes.len() - 1
+            }
+        };
+        scenes[index].shot_count += 1;
+        scenes[index].subtotal += shot.final_price.unwrap_or(0.0);
+        scenes[index].total_frames += shot.frame_count.map(u64::from).unwrap_or(0);
+    }
+
+    for scene in &mut scenes {
+        scene.departments = department_totals(shots.iter().filter(|s| s.scene_number.trim() == scene.scene_number));
+    }
+
+    scenes.sort_by(|a, b| compare_scene_numbers(&a.scene_number, &b.scene_number));
+    scenes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::rpc::testing::MockSidecar;
+    use crate::assumptions::{AssumptionCategory, AssumptionItem};
+    use crate::sidecar::RpcError;
+    use super::super::bid::{BidMeta, BidStatus};
+
+    fn write_template(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.xlsx", name, uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"template").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_open_bid_file_resolves_the_last_generated_bid() {
+        let session_state = SessionState::default();
+        let opened = std::cell::RefCell::new(None);
+        let record = |path: &Path| {
+            *opened.borrow_mut() = Some(path.to_path_buf());
+            Ok(())
+        };
+
+        let error = open_bid_file_internal(None, &session_state, record).unwrap_err();
+        assert_eq!(error, AppError::not_found("No bid has been generated yet"));
+
+        let workbook = write_template("generated");
+        session_state.update(|s| s.last_generated_bid = Some(workbook.to_string_lossy().to_string()));
+        let path = open_bid_file_internal(None, &session_state, record).unwrap();
+        assert_eq!(PathBuf::from(path), workbook);
+        assert_eq!(opened.borrow_mut().take(), Some(workbook.clone()));
+
+        // An explicit path wins, and must exist before anything is opened
+        let missing = std::env::temp_dir().join("missing-bid.xlsx");
+        let error = open_bid_file_internal(Some(missing.to_string_lossy().to_string()), &session_state, record).unwrap_err();
+        assert!(matches!(error, AppError::NotFound { .. }));
+        assert_eq!(*opened.borrow(), None);
+        std::fs::remove_file(workbook).unwrap();
+
+        // Anything but a workbook is refused, whatever its name holds
+        for name in ["notes.bat", "bid.xlsx & calc.exe", "bid.xlsx.exe"] {
+            let other = std::env::temp_dir().join(format!("{}-{}", uuid::Uuid::new_v4(), name));
+            std::fs::write(&other, b"x").unwrap();
+            let error = open_bid_file_internal(Some(other.to_string_lossy().to_string()), &session_state, record).unwrap_err();
+            assert!(matches!(error, AppError::Validation { .. }), "{} was accepted", name);
+            std::fs::remove_file(other).unwrap();
+        }
+        let upper = std::env::temp_dir().join(format!("{}-BID.XLS", uuid::Uuid::new_v4()));
+        std::fs::write(&upper, b"x").unwrap();
+        assert!(open_bid_file_internal(Some(upper.to_string_lossy().to_string()), &session_state, record).is_ok());
+        std::fs::remove_file(&upper).unwrap();
+        assert_eq!(*opened.borrow(), Some(upper));
+    }
+
+    #[test]
+    fn test_parse_extracted_shot_fills_defaults() {
+        let shot = parse_extracted_shot(&json!({
+            "id": "SH010",
+            "description": "Muzzle flash",
+            "cut_in": 1001,
+            "cut_out": 1024,
+        }))
+        .unwrap();
+
+        assert_eq!(shot.id, "SH010");
+        assert_eq!(shot.description, "Muzzle flash");
+        assert_eq!(shot.frame_in, Some(1001));
+        assert_eq!(shot.frame_out, Some(1024));
+        assert_eq!(shot.estimated_hours, None);
+        assert!(shot.vfx_types.is_empty());
+
+        assert!(parse_extracted_shot(&json!({ "description": "No id" })).is_none());
+        assert!(parse_extracted_shot(&json!({ "id": "  " })).is_none());
+        assert!(parse_extracted_shot(&json!("SH010")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_template_path_is_forwarded() {
+        let template = write_template("forwarded");
+        let (sidecar, client) = MockSidecar::new(|request| {
+            Ok(json!({ "excel_path": request.params["output_path"] }))
+        });
+
+        let exported = export_with_template(&client, Duration::from_secs(5), &template, "/tmp/out.xlsx", false, &BidState::default())
+            .await
+            .unwrap();
+
+        let requests = sidecar.requests();
+        assert_eq!(exported, "/tmp/out.xlsx");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "export_bid_with_template");
+        assert_eq!(requests[0].params["template_path"], template.to_string_lossy().to_string());
+
+        std::fs::remove_file(template).ok();
+    }
+
+    #[tokio::test]
+    async fn test_export_sends_rounded_prices() {
+        let template = write_template("rounded");
+        let (sidecar, client) = MockSidecar::new(|request| {
+            Ok(json!({ "excel_path": request.params["output_path"] }))
+        });
+
+        let bid_state = BidState::default();
+        let mut internal = AssumptionItem::new("Pad the harbor shots", AssumptionCategory::Note);
+        internal.included_in_export = false;
+        bid_state.set_meta(BidMeta {
+            rounding: PriceRounding::Nearest100,
+            assumptions: vec![AssumptionItem::new("Stereo conversion", AssumptionCategory::Exclusion), internal],
+            ..Default::default()
+        });
+        bid_state.set_shots(vec![ShotData {
+            id: "SH010".to_string(),
+            final_price: Some(1249.99),
+            confidence: Some(0.4),
+            extraction_flags: vec!["ambiguous_scene".to_string()],
+            ..Default::default()
+        }]).unwrap();
+        bid_state.set_status(BidStatus::Sent).unwrap();
+
+        export_with_template(&client, Duration::from_secs(5), &template, "/tmp/out.xlsx", false, &bid_state)
+            .await
+            .unwrap();
+
+        let params = &sidecar.requests()[0].params;
+        assert_eq!(params["shots"][0]["final_price"], 1249.99);
+        assert_eq!(params["shots"][0]["final_price_rounded"], 1200.0);
+        // Review state stays with the bidder
+        assert!(params["shots"][0].get("confidence").is_none());
+        assert!(params["shots"][0].get("extraction_flags").is_none());
+        assert_eq!(params["grand_total"], 1200.0);
+        assert_eq!(params["meta"]["rounding"], "nearest100");
+        assert_eq!(params["meta"]["status"], "sent");
+        assert_eq!(params["meta"]["version"], 1);
+        assert_eq!(params["assumptions"].as_array().unwrap().len(), 1);
+        assert_eq!(params["assumptions"][0]["category"], "exclusion");
+        assert_eq!(params["meta"]["assumptions"], params["assumptions"]);
+
+        std::fs::remove_file(template).ok();
+    }
+
+    #[tokio::test]
+    async fn test_missing_placeholders_are_listed() {
+        let template = write_template("missing");
+        let (_sidecar, client) = MockSidecar::new(|_| {
+            Err(RpcError {
+                code: -32602,
+                message: "Template is missing placeholders".to_string(),
+                data: Some(json!({ "missing_placeholders": ["total_cost", "client_name"] })),
+            })
+        });
+
+        let error = export_with_template(&client, Duration::from_secs(5), &template, "/tmp/out.xlsx", false, &BidState::default())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error, AppError::validation("Template is missing required placeholders: total_cost, client_name"));
+
+        std::fs::remove_file(template).ok();
+    }
+
+    #[tokio::test]
+    async fn test_missing_template_is_rejected() {
+        let (sidecar, client) = MockSidecar::new(|_| Ok(json!({})));
+        let missing = std::env::temp_dir().join("does-not-exist.xlsx");
+
+        let error = export_with_template(&client, Duration::from_secs(5), &missing, "/tmp/out.xlsx", false, &BidState::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, AppError::NotFound { .. }));
+        assert!(error.message().starts_with("Template not found"));
+        assert!(sidecar.requests().is_empty());
+    }
+
+    #[test]
+    fn test_scene_summaries_group_and_subtotal() {
+        let shot = |id: &str, scene: &str, price: Option<f64>| ShotData {
+            id: id.to_string(),
+            scene_number: scene.to_string(),
+            final_price: price,
+            ..Default::default()
+        };
+        let mut shots = vec![
+            shot("1", "10", Some(1_000.0)),
+            shot("2", "2", Some(250.0)),
+            shot("3", "10A", Some(400.0)),
+            shot("4", "10", Some(500.5)),
+            shot("5", "", None),
+            shot("6", "2", None),
+            shot("7", "9", Some(75.0)),
+        ];
+        shots[0].frame_count = Some(120);
+        shots[3].frame_count = Some(48);
+
+        let scenes = scene_summaries(&shots);
+        let order: Vec<_> = scenes.iter().map(|s| s.scene_number.as_str()).collect();
+        assert_eq!(order, vec!["2", "9", "10", "10A", ""]);
+
+        assert_eq!(scenes[0], SceneSummary { scene_number: "2".to_string(), shot_count: 2, subtotal: 250.0, departments: Vec::new(), total_frames: 0 });
+        assert_eq!(scenes[2], SceneSummary { scene_number: "10".to_string(), shot_count: 2, subtotal: 1_500.5, departments: Vec::new(), total_frames: 168 });
+        assert_eq!(scenes[4].shot_count, 1);
+        assert_eq!(scenes.iter().map(|s| s.shot_count).sum::<usize>(), shots.len());
+    }
+
+    #[test]
+    fn test_output_dir_resolution() {
+        let mut settings = Settings::default();
+        assert_eq!(resolve_output_dir(None, &settings), None);
+        assert_eq!(resolve_output_dir(Some("  "), &settings), None);
+
+        settings.paths.output_dir = "/bids".to_string();
+        assert_eq!(resolve_output_dir(None, &settings), Some(PathBuf::from("/bids")));
+        assert_eq!(resolve_output_dir(Some("/tmp/run"), &settings), Some(PathBuf::from("/tmp/run")));
+
+        assert_eq!(
+            bid_output_path(Path::new("/bids"), Path::new("/scripts/Pilot v2.pdf")),
+            PathBuf::from("/bids/Pilot v2_bid.xlsx")
+        );
+    }
+
+    #[test]
+    fn test_output_dir_is_created_or_rejected() {
+        let dir = std::env::temp_dir().join(format!("output-dir-{}", uuid::Uuid::new_v4()));
+        let nested = dir.join("bids").join("2026");
+        ensure_writable_dir(&nested).unwrap();
+        assert!(nested.is_dir());
+        assert_eq!(std::fs::read_dir(&nested).unwrap().count(), 0);
+
+        // A file where the directory should be can't be written into
+        let blocked = dir.join("taken");
+        std::fs::write(&blocked, "").unwrap();
+        let error = ensure_writable_dir(&blocked.join("bids")).unwrap_err();
+        assert!(matches!(error, AppError::Io { .. }));
+        assert!(error.message().contains("is not writable"), "{}", error.message());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_unpriced_shots_are_kept_alongside_priced() {
+        let result = json!({
+            "excel_path": "/tmp/bid.xlsx",
+            "unpriced_shots": [
+                { "id": "SH030", "scene_number": "3", "vfx_types": ["hologram"], "cut_in": 1001, "cut_out": 1048 },
+                { "id": "SH010", "description": "Also in the workbook" },
+                { "description": "No id" },
+            ],
+            "warnings": ["SH030: unknown VFX type 'hologram'"],
+        });
+        let bid_state = BidState::default();
+        bid_state.set_shots(vec![
+            ShotData { id: "SH010".to_string(), scene_number: "1".to_string(), final_price: Some(1200.0), ..Default::default() },
+            ShotData { id: "SH020".to_string(), scene_number: "2".to_string(), final_price: Some(800.0), ..Default::default() },
+        ]).unwrap();
+
+        let (unpriced, warnings) = partial_pricing(&result);
+        assert_eq!(unpriced.len(), 2);
+        let stored = add_unpriced_shots(&bid_state, unpriced);
+
+        assert_eq!(warnings, ["SH030: unknown VFX type 'hologram'"]);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, "SH030");
+        assert_eq!(stored[0].final_price, None);
+        assert_eq!(stored[0].frame_count, Some(48));
+        assert_eq!(stored[0].extraction_flags, [review::UNPRICED]);
+
+        let shots = bid_state.get_shots();
+        let ids: Vec<&str> = shots.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["SH010", "SH020", "SH030"]);
+        assert_eq!(shots[0].description, "");
+        assert_eq!(partial_pricing(&json!({ "excel_path": "/tmp/bid.xlsx" })), (Vec::new(), Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_process_call() {
+        // The sidecar never answers process_script
+        let sidecar = MockSidecar::scripted(|_| vec![]);
+        let client = AsyncRpcClient::new(sidecar.rpc_client());
+        let bid_state = BidState::default();
+
+        bid_state.begin_extraction();
+        bid_state.add_provisional_shot(ShotData { id: "SH010".to_string(), ..Default::default() });
+
+        let call = tokio::spawn(async move {
+            client.call_with_id("process_script".to_string(), json!({}), Duration::from_secs(60), "run-1".to_string()).await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(sidecar.rpc_client().cancel("run-1").unwrap());
+        let error = tokio::time::timeout(Duration::from_secs(2), call).await
+            .expect("cancelled call returns promptly")
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.code, RPC_CANCELLED_CODE);
+
+        let reconciled = bid_state.finish_extraction();
+        assert_eq!(reconciled.removed, vec!["SH010"]);
+        assert!(bid_state.get_shots().is_empty());
+
+        let cancel = sidecar.requests().into_iter().find(|r| r.method == "cancel").unwrap();
+        assert_eq!((cancel.id, cancel.params), (None, json!({ "id": "run-1" })));
+
+        // Nothing left to cancel
+        assert!(!sidecar.rpc_client().cancel("run-1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scan_reads_the_sidecar_summary() {
+        let (sidecar, client) = MockSidecar::new(|request| match request.params["path"].as_str() {
+            Some("/scripts/pilot.pdf") => Ok(json!({
+                "shot_count": 48,
+                "categories": ["compositing", "cg_creature"],
+                "page_count": 112,
+            })),
+            _ => Ok(json!({ "shots": [] })),
+        });
+        let timeout = Duration::from_secs(5);
+
+        let reply = request_scan(&client, Path::new("/scripts/pilot.pdf"), timeout).await.unwrap();
+        assert_eq!(reply.shot_count, 48);
+        assert_eq!(reply.categories, ["compositing", "cg_creature"]);
+        assert_eq!(reply.page_count, Some(112));
+
+        let requests = sidecar.requests();
+        assert_eq!(requests[0].method, "scan_script");
+        assert_eq!(requests[0].params, json!({ "path": "/scripts/pilot.pdf" }));
+
+        let error = request_scan(&client, Path::new("/scripts/other.pdf"), timeout).await.unwrap_err();
+        assert!(matches!(error, AppError::Validation { .. }), "{:?}", error);
+    }
+}
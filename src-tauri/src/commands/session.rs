@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use std::path::Path;
+use crate::events::{event_schemas, EventSchema};
+use crate::state::last_error::{tracked_command, LastError};
+use crate::state::session::SessionSnapshot;
+use crate::state::{BidState, ErrorDomain, LastErrorState, SessionState, SidecarState};
+use super::chat::ChatMessage;
+use super::script::{load_bid_fallback, load_bid_internal, ScriptAnalysis};
+use super::settings::LocaleSettings;
+
+/// UI context reported by the frontend as it changes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionUpdate {
+    pub chat_panel_open: Option<bool>,
+    pub selected_shot_id: Option<String>,
+    pub last_output_dir: Option<String>,
+}
+
+/// Result of restoring the previous session
+///
+/// `notices` explains anything that could not be restored, such as a bid
+/// file that has been moved or deleted since the last launch.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionRestore {
+    pub bid: Option<ScriptAnalysis>,
+    pub selected_shot_id: Option<String>,
+    pub chat_panel_open: bool,
+    pub last_output_dir: Option<String>,
+    pub chat_history: Vec<ChatMessage>,
+    pub notices: Vec<String>,
+}
+
+/// Record UI context so it can be restored on the next launch
+#[tauri::command]
+pub fn update_session(update: SessionUpdate, session_state: State<'_, SessionState>) {
+    session_state.update(|s| {
+        if let Some(open) = update.chat_panel_open {
+            s.chat_panel_open = open;
+        }
+        if update.selected_shot_id.is_some() {
+            s.selected_shot_id = update.selected_shot_id;
+        }
+        if update.last_output_dir.is_some() {
+            s.last_output_dir = update.last_output_dir;
+        }
+    });
+}
+
+/// The last error in each area of the app, newest first, for the error panel
+#[tauri::command]
+pub fn get_last_errors(last_errors: State<'_, LastErrorState>) -> Vec<LastError> {
+    last_errors.get()
+}
+
+/// Clear the error panel, or just `domain`'s entry
+#[tauri::command]
+pub fn clear_last_errors(domain: Option<ErrorDomain>, last_errors: State<'_, LastErrorState>) {
+    last_errors.clear(domain);
+}
+
+/// Every event the backend sends, with a JSON Schema of its payload
+#[tauri::command]
+pub fn get_event_schema() -> Vec<EventSchema> {
+    event_schemas()
+}
+
+/// The previous session, if it is waiting to be restored
+///
+/// Same payload as `session-restore-available`, which is sent at launch
+/// before the frontend may be listening.
+#[tauri::command]
+pub fn get_pending_session_restore(session_state: State<'_, SessionState>) -> Option<SessionSnapshot> {
+    session_state.pending_restore()
+}
+
+/// Decline the previous session so it isn't offered again this run
+#[tauri::command]
+pub fn dismiss_session_restore(session_state: State<'_, SessionState>) {
+    session_state.dismiss_restore();
+}
+
+tracked_command! { Bid;
+/// Restore the last session: reload the bid, selection and chat history
+///
+/// Requires `ui.restore_session` to be enabled. Missing files are reported
+/// as notices rather than failing the restore, as is a bid left unloaded
+/// because an autosave is still waiting to be restored or discarded.
+#[tauri::command]
+pub async fn restore_last_session(
+    session_state: State<'_, SessionState>,
+    bid_state: State<'_, BidState>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<SessionRestore, String> {
+    let snapshot = session_state.snapshot();
+
+    if !snapshot.restore_enabled() {
+        return Err("Session restore is disabled in settings".to_string());
+    }
+    session_state.dismiss_restore();
+
+    let mut notices = Vec::new();
+    let bid = match snapshot.last_bid_path.clone() {
+        Some(path) => {
+            let locale = session_state.get_settings().unwrap_or_default().locale;
+            reload_last_bid(path, &locale, &bid_state, &sidecar_state, &mut notices).await
+        }
+        None => None,
+    };
+
+    // Only restore the selection if the shot is still part of the bid
+    let shots = bid_state.get_shots();
+    let selected_shot_id = snapshot.selected_shot_id.filter(|id| {
+        shots.is_empty() || shots.iter().any(|s| &s.id == id)
+    });
+
+    let last_output_dir = snapshot.last_output_dir.filter(|dir| {
+        let exists = Path::new(dir).is_dir();
+        if !exists {
+            notices.push(format!("The last output folder {} no longer exists.", dir));
+        }
+        exists
+    });
+
+    Ok(SessionRestore {
+        bid,
+        selected_shot_id,
+        chat_panel_open: snapshot.chat_panel_open,
+        last_output_dir,
+        chat_history: snapshot.chat_history,
+        notices,
+    })
+}
+}
+
+/// Reopen the session's bid from `path`, explaining in `notices` why not
+///
+/// Loading replaces the bid and overwrites the autosave, so an autosave the
+/// user hasn't restored or discarded yet holds the reload back.
+async fn reload_last_bid(
+    path: String,
+    locale: &LocaleSettings,
+    bid_state: &BidState,
+    sidecar_state: &SidecarState,
+    notices: &mut Vec<String>,
+) -> Option<ScriptAnalysis> {
+    match bid_state.read_autosave() {
+        Ok(Some(_)) => {
+            notices.push(
+                "Unsaved changes from the last run were autosaved. Restore or discard them before reopening the last bid."
+                    .to_string(),
+            );
+            return None;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            notices.push(format!("The last bid was not reopened, as the autosave couldn't be checked: {}", e));
+            return None;
+        }
+    }
+
+    if !Path::new(&path).exists() {
+        notices.push(format!(
+            "The last bid could not be found at {}. It may have been moved or deleted.",
+            path
+        ));
+        return None;
+    }
+
+    let loaded = if sidecar_state.is_running() {
+        load_bid_internal(path, bid_state, sidecar_state).await
+    } else {
+        load_bid_fallback(&path, locale, bid_state)
+    };
+    match loaded {
+        Ok(analysis) => Some(analysis),
+        Err(e) => {
+            notices.push(format!("Failed to reload the last bid: {}", e));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::ShotData;
+
+    #[tokio::test]
+    async fn test_autosave_holds_back_the_session_bid() {
+        let path = std::env::temp_dir().join(format!("bid_autosave-{}.json", uuid::Uuid::new_v4()));
+        let crashed = BidState::default();
+        crashed.enable_autosave(path.clone());
+        crashed.set_shots(vec![ShotData { id: "SH010".to_string(), ..Default::default() }]).unwrap();
+        tokio::time::sleep(crate::state::bid::AUTOSAVE_DEBOUNCE * 3).await;
+
+        let bid_state = BidState::default();
+        bid_state.enable_autosave(path.clone());
+        let before = std::fs::read(&path).unwrap();
+
+        let mut notices = Vec::new();
+        let reloaded = reload_last_bid("/bids/missing.xlsx".to_string(), &LocaleSettings::default(), &bid_state, &SidecarState::default(), &mut notices).await;
+
+        assert!(reloaded.is_none());
+        assert!(notices[0].contains("autosaved"), "{:?}", notices);
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        // With the autosave dealt with, the bid is looked for as usual
+        bid_state.clear_autosave().unwrap();
+        let mut notices = Vec::new();
+        reload_last_bid("/bids/missing.xlsx".to_string(), &LocaleSettings::default(), &bid_state, &SidecarState::default(), &mut notices).await;
+        assert!(notices[0].contains("could not be found"), "{:?}", notices);
+    }
+}
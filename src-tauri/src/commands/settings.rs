@@ -1,7 +1,29 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use crate::commands::bid::TaskLine;
+use crate::commands::model::forget_stale_model_hash;
+use crate::estimation::{default_hour_ranges, validate_hour_ranges, HourRange};
+use crate::setup_wizard::{check_dir_writable, setup_manifest};
+use crate::sidecar::blob::DEFAULT_BLOB_THRESHOLD;
+use crate::sidecar::metrics::DEFAULT_WARN_AFTER;
+use crate::sidecar::pool::DEFAULT_MAX_IN_FLIGHT;
+use crate::sidecar::rpc::DEFAULT_HEARTBEAT_GAP;
+use crate::sidecar::RpcMethod;
+use crate::state::last_error::tracked_command;
+use crate::state::{BidState, SessionState, SidecarState};
+use crate::storage::{self, StorageCategory, StorageUsage};
+use crate::tempfiles;
+use crate::workspace::{self, WorkspaceInfo};
+
+/// Longest timeout any RPC method may be given
+pub const MAX_RPC_TIMEOUT_SECS: u64 = 4 * 60 * 60;
 
 /// Application settings
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct Settings {
     /// LLM server configuration
     pub llm: LlmSettings,
@@ -9,30 +31,399 @@ pub struct Settings {
     pub paths: PathSettings,
     /// UI preferences
     pub ui: UiSettings,
+    /// Pricing factors used when estimating shots
+    #[serde(default)]
+    pub pricing: PricingSettings,
+    /// Python sidecar behaviour
+    #[serde(default)]
+    pub sidecar: SidecarSettings,
+    /// Number and currency formatting
+    #[serde(default)]
+    pub locale: LocaleSettings,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct LlmSettings {
     pub server_url: String,
     pub model_name: String,
     pub context_size: usize,
     pub temperature: f32,
     pub max_tokens: usize,
+    /// Model file chosen after setup; the setup default is used when unset
+    #[serde(default)]
+    pub model_path: Option<String>,
+    /// SHA-256 of the model file, checked before the sidecar starts
+    #[serde(default)]
+    pub model_sha256: Option<String>,
+    /// Load the model even when too little memory looks free
+    #[serde(default)]
+    pub skip_memory_check: bool,
+    /// Paths the model last loaded from, newest first; rescans look here first
+    #[serde(default)]
+    pub known_model_paths: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct PathSettings {
     pub python_path: String,
     pub scripts_dir: String,
     pub templates_dir: String,
     pub output_dir: String,
+    /// Where setup puts models; empty for `~/VFX-BIDDING/Models`
+    #[serde(default)]
+    pub model_dir: String,
+}
+
+impl PathSettings {
+    /// Directories that are set must exist; the output and model
+    /// directories are created if missing, and the model one must be
+    /// writable. Empty fields are unset and pass.
+    pub fn validate(&self) -> Result<(), String> {
+        existing_dir("Scripts directory", &self.scripts_dir, false)?;
+        existing_dir("Templates directory", &self.templates_dir, false)?;
+        existing_dir("Output directory", &self.output_dir, true)?;
+        match self.model_dir.trim() {
+            "" => Ok(()),
+            dir => check_dir_writable(Path::new(dir)).map_err(|e| format!("Model directory: {}", e)),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Check `value` names a directory, creating it first with `create`
+fn existing_dir(field: &str, value: &str, create: bool) -> Result<(), String> {
+    let dir = match value.trim() {
+        "" => return Ok(()),
+        dir => Path::new(dir),
+    };
+
+    if create && !dir.exists() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("{}: Can't create {}: {}", field, dir.display(), e))?;
+    }
+    match std::fs::metadata(dir) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(format!("{}: {} is a file, not a directory", field, dir.display())),
+        Err(_) => Err(format!("{}: {} doesn't exist", field, dir.display())),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct UiSettings {
     pub theme: String,
     pub auto_save: bool,
     pub show_console: bool,
+    /// Offer to reopen the last bid and window context on launch
+    #[serde(default)]
+    pub restore_session: bool,
+    /// Load the model as soon as the sidecar starts
+    #[serde(default = "default_true")]
+    pub preload_on_start: bool,
+    /// What to do with a chat message sent while another is still processing
+    #[serde(default)]
+    pub chat_overlap: ChatOverlapMode,
+    /// Apply low-risk chat edits (one shot's complexity) without asking;
+    /// anything larger always waits for confirmation
+    #[serde(default)]
+    pub chat_auto_apply_safe_actions: bool,
+    /// Keep a local history of script processing runs for time estimates
+    #[serde(default = "default_true")]
+    pub record_processing_history: bool,
+}
+
+/// Handling of chat messages that overlap an in-flight one
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatOverlapMode {
+    /// Refuse the new message until the current response arrives
+    #[default]
+    Reject,
+    /// Hold the new message and send it once the current one finishes
+    Queue,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_heartbeat_gap() -> u64 {
+    DEFAULT_HEARTBEAT_GAP.as_secs()
+}
+
+fn default_blob_threshold() -> usize {
+    DEFAULT_BLOB_THRESHOLD
+}
+
+/// Smallest blob threshold accepted; below it every request would go
+/// through files
+pub const MIN_BLOB_THRESHOLD_BYTES: usize = 4 * 1024;
+
+fn default_slow_call_warn_ms() -> u64 {
+    DEFAULT_WARN_AFTER.as_millis() as u64
+}
+
+fn default_max_in_flight_rpcs() -> usize {
+    DEFAULT_MAX_IN_FLIGHT
+}
+
+fn default_workspace_retention_days() -> u32 {
+    90
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PricingSettings {
+    /// Artist hours per second of screen time, keyed by complexity tier
+    pub hours_per_second: HashMap<String, f64>,
+    /// Fewest hours a shot of each complexity tier may be cut to when
+    /// fitting a bid to a budget
+    #[serde(default = "default_min_hours")]
+    pub min_hours: HashMap<String, f64>,
+    /// Named department breakdowns for `apply_task_template`
+    #[serde(default = "default_task_templates")]
+    pub task_templates: Vec<TaskTemplate>,
+    /// Typical hours per complexity tier, optionally per VFX type, for
+    /// `estimate_missing_hours`
+    #[serde(default = "default_hour_ranges")]
+    pub hour_ranges: Vec<HourRange>,
+}
+
+/// Reusable department breakdown, e.g. "Greenscreen comp"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TaskTemplate {
+    pub name: String,
+    pub tasks: Vec<TaskLine>,
+}
+
+impl PricingSettings {
+    /// Template called `name`, ignoring case
+    pub fn task_template(&self, name: &str) -> Option<&TaskTemplate> {
+        self.task_templates.iter().find(|t| t.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_hour_ranges(&self.hour_ranges)
+    }
+}
+
+fn default_min_hours() -> HashMap<String, f64> {
+    HashMap::from([
+        ("Low".to_string(), 2.0),
+        ("Medium".to_string(), 8.0),
+        ("High".to_string(), 20.0),
+    ])
+}
+
+fn default_task_templates() -> Vec<TaskTemplate> {
+    let template = |name: &str, tasks: &[(&str, f64)]| TaskTemplate {
+        name: name.to_string(),
+        tasks: tasks
+            .iter()
+            .map(|(department, hours)| TaskLine { department: department.to_string(), hours: *hours, ..Default::default() })
+            .collect(),
+    };
+
+    vec![
+        template("Greenscreen comp", &[("roto", 8.0), ("prep", 4.0), ("comp", 16.0)]),
+        template("Wire removal", &[("roto", 4.0), ("prep", 12.0), ("comp", 4.0)]),
+        template("CG element", &[("CG", 40.0), ("FX", 24.0), ("comp", 16.0)]),
+    ]
+}
+
+impl Default for PricingSettings {
+    fn default() -> Self {
+        Self {
+            hours_per_second: HashMap::from([
+                ("Low".to_string(), 2.0),
+                ("Medium".to_string(), 6.0),
+                ("High".to_string(), 15.0),
+            ]),
+            min_hours: default_min_hours(),
+            task_templates: default_task_templates(),
+            hour_ranges: default_hour_ranges(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SidecarSettings {
+    /// RPC timeout in seconds, keyed by method name
+    pub rpc_timeouts: HashMap<String, u64>,
+    /// Timeout in seconds for methods not listed in `rpc_timeouts`
+    pub default_rpc_timeout: u64,
+    /// Seconds a call that has sent heartbeats may go without another
+    /// before it counts as hung; replaces the timeout for that call
+    #[serde(default = "default_heartbeat_gap")]
+    pub heartbeat_gap_secs: u64,
+    /// Extra variables for the sidecar process, e.g. proxies or `LLAMA_METAL=1`
+    #[serde(default)]
+    pub extra_env: HashMap<String, String>,
+    /// Pass the app's whole environment to the sidecar instead of the
+    /// allowlist; for debugging only
+    #[serde(default)]
+    pub inherit_env: bool,
+    /// Days a bid's sidecar workspace may go unused before pruning is
+    /// offered; 0 keeps them forever
+    #[serde(default = "default_workspace_retention_days")]
+    pub workspace_retention_days: u32,
+    /// RPC values larger than this many bytes of JSON are passed through
+    /// files rather than inline, to a sidecar that supports it
+    #[serde(default = "default_blob_threshold")]
+    pub blob_threshold_bytes: usize,
+    /// `rpc_server.py` to run instead of the bundled one, for developers
+    /// working on the sidecar; `VFX_SIDECAR_PATH` overrides it
+    #[serde(default)]
+    pub script_path: Option<String>,
+    /// Calls slower than this many milliseconds are logged with where their
+    /// time went; 0 logs none
+    #[serde(default = "default_slow_call_warn_ms")]
+    pub slow_call_warn_ms: u64,
+    /// Clear the RPC latency counters whenever the sidecar restarts
+    #[serde(default)]
+    pub reset_metrics_on_restart: bool,
+    /// Reuse `bid_query` answers for this many milliseconds, until the bid
+    /// changes; 0 only shares identical queries running at once
+    #[serde(default)]
+    pub query_cache_ms: u64,
+    /// RPC calls running at once; more wait their turn
+    #[serde(default = "default_max_in_flight_rpcs")]
+    pub max_in_flight_rpcs: usize,
+}
+
+impl SidecarSettings {
+    /// Timeout to apply to a call of `method`
+    pub fn timeout_for(&self, method: &str) -> Duration {
+        let secs = self.rpc_timeouts
+            .get(method)
+            .copied()
+            .unwrap_or(self.default_rpc_timeout);
+        Duration::from_secs(secs)
+    }
+
+    pub fn heartbeat_gap(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_gap_secs)
+    }
+
+    pub fn query_cache_ttl(&self) -> Duration {
+        Duration::from_millis(self.query_cache_ms)
+    }
+
+    pub fn slow_call_warn(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.slow_call_warn_ms)).filter(|limit| !limit.is_zero())
+    }
+
+    /// Every timeout must be positive and no more than `MAX_RPC_TIMEOUT_SECS`,
+    /// and name a method the sidecar has
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(method) = self.rpc_timeouts.keys().find(|m| RpcMethod::from_name(m).is_none()) {
+            return Err(format!("Unknown RPC method '{}'", method));
+        }
+
+        let entries = self.rpc_timeouts
+            .iter()
+            .map(|(method, secs)| (method.as_str(), *secs))
+            .chain(std::iter::once(("default", self.default_rpc_timeout)))
+            .chain(std::iter::once(("heartbeat gap", self.heartbeat_gap_secs)));
+
+        for (method, secs) in entries {
+            if secs == 0 || secs > MAX_RPC_TIMEOUT_SECS {
+                return Err(format!(
+                    "Timeout for '{}' must be between 1 and {} seconds, got {}",
+                    method, MAX_RPC_TIMEOUT_SECS, secs
+                ));
+            }
+        }
+
+        if self.blob_threshold_bytes < MIN_BLOB_THRESHOLD_BYTES {
+            return Err(format!(
+                "Blob threshold must be at least {} bytes, got {}",
+                MIN_BLOB_THRESHOLD_BYTES, self.blob_threshold_bytes
+            ));
+        }
+
+        if self.max_in_flight_rpcs == 0 {
+            return Err("At least one RPC call must be allowed at a time".to_string());
+        }
+
+        for name in self.extra_env.keys() {
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(format!("Invalid environment variable name '{}'", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SidecarSettings {
+    fn default() -> Self {
+        Self {
+            rpc_timeouts: HashMap::from([
+                (RpcMethod::ProcessScript.to_string(), 1800),
+                (RpcMethod::ChatCommand.to_string(), 180),
+                (RpcMethod::BidQuery.to_string(), 15),
+                (RpcMethod::Health.to_string(), 5),
+                (RpcMethod::LoadModel.to_string(), 600),
+            ]),
+            default_rpc_timeout: 120,
+            heartbeat_gap_secs: default_heartbeat_gap(),
+            extra_env: HashMap::new(),
+            inherit_env: false,
+            workspace_retention_days: default_workspace_retention_days(),
+            blob_threshold_bytes: default_blob_threshold(),
+            script_path: None,
+            slow_call_warn_ms: default_slow_call_warn_ms(),
+            reset_metrics_on_restart: false,
+            query_cache_ms: 0,
+            max_in_flight_rpcs: DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
+}
+
+/// Where the currency symbol goes relative to the amount
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyPosition {
+    /// `$1,234.56`
+    Before,
+    /// `1.234,56 €`
+    After,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct LocaleSettings {
+    /// BCP 47 tag such as "en-US" or "de-DE"
+    pub locale: String,
+    pub currency_symbol_position: CurrencyPosition,
+    pub thousands_separator: String,
+    pub decimal_separator: String,
+    /// Write CSV numbers with these separators; off keeps CSV machine-readable
+    #[serde(default)]
+    pub export_locale_numbers: bool,
+}
+
+impl LocaleSettings {
+    /// Conventions for a known locale, falling back to en-US
+    pub fn for_locale(locale: &str) -> Self {
+        let (thousands, decimal, position) = match locale.to_ascii_lowercase().as_str() {
+            "de-de" | "de-at" | "de" | "es-es" | "it-it" | "nl-nl" => (".", ",", CurrencyPosition::After),
+            "fr-fr" | "fr-ca" | "fr" => ("\u{a0}", ",", CurrencyPosition::After),
+            "de-ch" => ("'", ".", CurrencyPosition::Before),
+            _ => (",", ".", CurrencyPosition::Before),
+        };
+
+        Self {
+            locale: locale.to_string(),
+            currency_symbol_position: position,
+            thousands_separator: thousands.to_string(),
+            decimal_separator: decimal.to_string(),
+            export_locale_numbers: false,
+        }
+    }
+}
+
+impl Default for LocaleSettings {
+    fn default() -> Self {
+        Self::for_locale("en-US")
+    }
 }
 
 impl Default for Settings {
@@ -40,40 +431,236 @@ impl Default for Settings {
         Self {
             llm: LlmSettings {
                 server_url: "http://localhost:8080".to_string(),
-                model_name: "Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf".to_string(),
+                model_name: setup_manifest().default_model.clone(),
                 context_size: 8192,
                 temperature: 0.1,
                 max_tokens: 4096,
+                model_path: None,
+                model_sha256: None,
+                skip_memory_check: false,
+                known_model_paths: Vec::new(),
             },
             paths: PathSettings {
                 python_path: "python3".to_string(),
                 scripts_dir: "".to_string(),
                 templates_dir: "".to_string(),
                 output_dir: "".to_string(),
+                model_dir: "".to_string(),
             },
             ui: UiSettings {
                 theme: "dark".to_string(),
                 auto_save: true,
                 show_console: false,
+                restore_session: false,
+                preload_on_start: true,
+                chat_overlap: ChatOverlapMode::Reject,
+                chat_auto_apply_safe_actions: false,
+                record_processing_history: true,
             },
+            pricing: PricingSettings::default(),
+            sidecar: SidecarSettings::default(),
+            locale: LocaleSettings::default(),
         }
     }
 }
 
 /// Get current settings
 #[tauri::command]
-pub fn get_settings() -> Settings {
-    // TODO: Load from persistent storage
-    Settings::default()
+pub fn get_settings(session_state: State<'_, SessionState>) -> Settings {
+    session_state.get_settings().unwrap_or_default()
 }
 
+tracked_command! { Setup;
 /// Update settings
+///
+/// The active settings are saved with the session. Changed LLM parameters
+/// are sent to a running sidecar with a `configure` call unless
+/// `configure_sidecar` is false; either way they are exported to it on the
+/// next start.
 #[tauri::command]
-pub fn update_settings(_settings: Settings) -> Result<(), String> {
-    // TODO: Save to persistent storage
+pub async fn update_settings(
+    settings: Settings,
+    configure_sidecar: Option<bool>,
+    session_state: State<'_, SessionState>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<(), String> {
+    settings.sidecar.validate()?;
+    settings.paths.validate()?;
+    settings.pricing.validate()?;
+
+    let previous = session_state.get_settings().unwrap_or_default();
+    let mut settings = settings;
+    forget_stale_model_hash(&previous, &mut settings);
+    let llm_changed = previous.llm != settings.llm;
+
+    sidecar_state.set_rpc_timeouts(settings.sidecar.clone());
+    sidecar_state.set_llm_settings(settings.llm.clone());
+    let llm = settings.llm.clone();
+    session_state.set_settings(settings);
+
+    if llm_changed && configure_sidecar.unwrap_or(true) && sidecar_state.is_running() {
+        send_llm_settings(&sidecar_state, &llm)
+            .await
+            .map_err(|e| format!("Settings saved, but the sidecar did not accept them: {}", e))?;
+    }
+    Ok(())
+}
+}
+
+/// Apply `llm` to the running sidecar without restarting it
+pub(crate) async fn send_llm_settings(sidecar_state: &SidecarState, llm: &LlmSettings) -> Result<(), String> {
+    let rpc_client = sidecar_state.client_for(RpcMethod::Configure)?;
+
+    rpc_client.call(RpcMethod::Configure.to_string(), configure_params(llm), sidecar_state.rpc_timeout(RpcMethod::Configure)).await?;
     Ok(())
 }
 
+/// `configure` parameters, named like the settings fields
+fn configure_params(llm: &LlmSettings) -> serde_json::Value {
+    serde_json::json!({
+        "context_size": llm.context_size,
+        "temperature": llm.temperature,
+        "max_tokens": llm.max_tokens,
+        "model_name": llm.model_name,
+    })
+}
+
+/// Switch between the light and dark theme; returns the new theme
+pub(crate) fn toggle_theme(session_state: &SessionState) -> String {
+    let mut settings = session_state.get_settings().unwrap_or_default();
+    settings.ui.theme = if settings.ui.theme == "light" { "dark" } else { "light" }.to_string();
+
+    let theme = settings.ui.theme.clone();
+    session_state.set_settings(settings);
+    theme
+}
+
+/// Get the sidecar settings, including per-method RPC timeouts
+#[tauri::command]
+pub fn get_sidecar_settings(session_state: State<'_, SessionState>) -> SidecarSettings {
+    session_state.get_settings().unwrap_or_default().sidecar
+}
+
+tracked_command! { Setup;
+/// Update the sidecar settings
+///
+/// Timeouts take effect for the next RPC call.
+#[tauri::command]
+pub fn update_sidecar_settings(
+    sidecar: SidecarSettings,
+    session_state: State<'_, SessionState>,
+    sidecar_state: State<'_, SidecarState>,
+) -> Result<SidecarSettings, String> {
+    sidecar.validate()?;
+
+    let mut settings = session_state.get_settings().unwrap_or_default();
+    settings.sidecar = sidecar.clone();
+
+    sidecar_state.set_rpc_timeouts(sidecar.clone());
+    session_state.set_settings(settings);
+    Ok(sidecar)
+}
+}
+
+/// Bytes used by the cache, attachments, autosaves, models and logs
+#[tauri::command]
+pub fn get_storage_usage(app: AppHandle, session_state: State<'_, SessionState>) -> StorageUsage {
+    storage::storage_usage(&app, &session_state.get_settings().unwrap_or_default())
+}
+
+tracked_command! { Setup;
+/// Free the space used by `categories`, returning the usage afterwards
+///
+/// Temp files written in the last hour are kept so a running download
+/// isn't cut off.
+#[tauri::command]
+pub fn clear_cache(
+    categories: Vec<StorageCategory>,
+    app: AppHandle,
+    session_state: State<'_, SessionState>,
+    bid_state: State<'_, BidState>,
+) -> Result<StorageUsage, String> {
+    if let Some(category) = categories.iter().find(|c| !c.clearable()) {
+        return Err(format!("{:?} can't be cleared from here", category));
+    }
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    for category in categories {
+        match category {
+            StorageCategory::Autosaves => bid_state.clear_autosave()?,
+            StorageCategory::Cache => {
+                let temp_root = tempfiles::temp_root();
+                for path in storage::category_paths(&app, &settings, category) {
+                    for entry in std::fs::read_dir(&path).into_iter().flatten().flatten() {
+                        let child = entry.path();
+                        if Some(child.as_path()) == temp_root {
+                            tempfiles::clean_orphans(&child, Duration::from_secs(60 * 60));
+                            continue;
+                        }
+                        storage::clear_path(&child);
+                        let _ = std::fs::remove_dir(&child);
+                    }
+                }
+            }
+            _ => {
+                for path in storage::category_paths(&app, &settings, category) {
+                    storage::clear_path(&path);
+                }
+            }
+        }
+    }
+
+    Ok(storage::storage_usage(&app, &settings))
+}
+}
+
+/// The sidecar's per-bid workspaces, most recently used first
+#[tauri::command]
+pub fn list_workspaces() -> Vec<WorkspaceInfo> {
+    workspace::root().map(workspace::list).unwrap_or_default()
+}
+
+tracked_command! { Setup;
+/// Delete a bid's sidecar workspace, returning the bytes freed
+///
+/// The sidecar is told to release it first if it's the one in use; it is
+/// recreated the next time that bid needs the sidecar.
+#[tauri::command]
+pub fn delete_workspace(id: String, sidecar_state: State<'_, SidecarState>) -> Result<u64, String> {
+    let root = workspace::root().ok_or("Workspaces are not available")?;
+    if let Some(active) = sidecar_state.active_workspace().filter(|dir| *dir == root.join(&id)) {
+        sidecar_state.release_workspace(&active);
+    }
+    workspace::delete(root, &id)
+}
+}
+
+tracked_command! { Setup;
+/// Delete the workspaces the user confirmed from a `workspaces-prune-pending`
+/// offer, returning the bytes freed
+///
+/// Ids used since the offer, or in use now, are skipped.
+#[tauri::command]
+pub fn prune_workspaces(
+    ids: Vec<String>,
+    sidecar_state: State<'_, SidecarState>,
+    session_state: State<'_, SessionState>,
+) -> Result<u64, String> {
+    let root = workspace::root().ok_or("Workspaces are not available")?;
+    let retention_days = session_state.get_settings().unwrap_or_default().sidecar.workspace_retention_days;
+    let active = sidecar_state.active_workspace();
+
+    let mut freed = 0;
+    for stale in workspace::stale(root, retention_days, chrono::Utc::now(), active.as_deref()) {
+        if ids.contains(&stale.id) {
+            freed += workspace::delete(root, &stale.id)?;
+        }
+    }
+    Ok(freed)
+}
+}
+
+tracked_command! { Setup;
 /// Test LLM connection
 #[tauri::command]
 pub async fn test_llm_connection(settings: Settings) -> Result<String, String> {
@@ -97,3 +684,85 @@ pub async fn test_llm_connection(settings: Settings) -> Result<String, String> {
         }
     }
 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_validation() {
+        let root = std::env::temp_dir().join(format!("paths-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("notes.txt"), "").unwrap();
+        let dir = |name: &str| root.join(name).to_string_lossy().to_string();
+        let paths = |scripts_dir: String, templates_dir: String, output_dir: String| PathSettings {
+            scripts_dir,
+            templates_dir,
+            output_dir,
+            ..Settings::default().paths
+        };
+
+        // Unset paths are fine
+        assert!(paths(String::new(), " ".to_string(), String::new()).validate().is_ok());
+
+        let error = paths(dir("missing"), String::new(), String::new()).validate().unwrap_err();
+        assert!(error.starts_with("Scripts directory:") && error.contains("doesn't exist"), "{}", error);
+        let error = paths(String::new(), dir("notes.txt"), String::new()).validate().unwrap_err();
+        assert!(error.starts_with("Templates directory:") && error.contains("not a directory"), "{}", error);
+        let error = paths(String::new(), String::new(), dir("notes.txt")).validate().unwrap_err();
+        assert!(error.starts_with("Output directory:"), "{}", error);
+
+        // A missing output directory is created
+        assert!(paths(dir(""), String::new(), dir("out/bids")).validate().is_ok());
+        assert!(root.join("out/bids").is_dir());
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_timeout_policy_defaults() {
+        let sidecar = SidecarSettings::default();
+
+        assert_eq!(sidecar.timeout_for("process_script"), Duration::from_secs(1800));
+        assert_eq!(sidecar.timeout_for("bid_query"), Duration::from_secs(15));
+        assert_eq!(sidecar.timeout_for("unknown_method"), Duration::from_secs(120));
+        assert!(sidecar.validate().is_ok());
+    }
+
+    #[test]
+    fn test_timeout_validation() {
+        let mut sidecar = SidecarSettings::default();
+        sidecar.rpc_timeouts.insert("bid_query".to_string(), 0);
+        assert!(sidecar.validate().unwrap_err().contains("'bid_query'"));
+
+        let mut sidecar = SidecarSettings::default();
+        sidecar.rpc_timeouts.insert("bid_querry".to_string(), 30);
+        assert_eq!(sidecar.validate().unwrap_err(), "Unknown RPC method 'bid_querry'");
+
+        let sidecar = SidecarSettings {
+            default_rpc_timeout: MAX_RPC_TIMEOUT_SECS + 1,
+            ..Default::default()
+        };
+        assert!(sidecar.validate().unwrap_err().contains("'default'"));
+
+        let sidecar = SidecarSettings { heartbeat_gap_secs: 0, ..Default::default() };
+        assert!(sidecar.validate().unwrap_err().contains("'heartbeat gap'"));
+
+        let sidecar = SidecarSettings { blob_threshold_bytes: 100, ..Default::default() };
+        assert!(sidecar.validate().unwrap_err().starts_with("Blob threshold"));
+
+        let mut sidecar = SidecarSettings::default();
+        sidecar.extra_env.insert("A=B".to_string(), "1".to_string());
+        assert!(sidecar.validate().unwrap_err().contains("'A=B'"));
+    }
+
+    #[test]
+    fn test_settings_without_sidecar_section_use_defaults() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("sidecar");
+
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.sidecar, SidecarSettings::default());
+    }
+}
@@ -2,11 +2,27 @@
 //!
 //! Frontend commands for the setup wizard
 
+use crate::commands::model::resolve_model_path;
+use crate::diagnostics::{DiagnosticsReport, ModelDiagnostics, SidecarDiagnostics};
+use crate::events::{emit_event, AppEvent};
+use crate::history::{history_path, recent_runs, DEFAULT_HISTORY_LIMIT};
+use crate::offline_bundle::{default_bundle_dir, find_bundle, install_model, open_bundle, pip_install_args, run_pip};
+use crate::self_test::{SelfTestInputs, SelfTestReport};
+use crate::sidecar::process::default_python;
+use crate::sidecar::smoke::run_smoke_test;
+use crate::sidecar::SmokeTestResult;
 use crate::setup_wizard::*;
-use tauri::{Window, State, Manager, Emitter};
+use crate::state::last_error::tracked_command;
+use crate::state::{SessionState, SidecarState};
+use tauri::{Window, State, Manager};
 use std::sync::Mutex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long a Python check is reused by repeated status checks
+const PYTHON_STATUS_TTL: Duration = Duration::from_secs(30);
 
 /// Setup state managed during the wizard process
 #[derive(Debug, Default)]
@@ -14,7 +30,89 @@ pub struct SetupWizardState {
     pub config_dir: Option<PathBuf>,
     pub model_path: Option<PathBuf>,
     pub python_path: Option<String>,
+    /// rpc_server.py, found at startup
+    pub script_path: Option<PathBuf>,
     pub completed_steps: Vec<SetupStep>,
+    /// Latest `run_setup_smoke_test` result
+    pub smoke_test: Option<SmokeTestResult>,
+    /// Packages the last `install_python_dependencies` failed to install
+    pub install_errors: Vec<InstallError>,
+    /// Latest `check_python` result and when it ran
+    pub python_status: Option<(Instant, PythonStatus)>,
+    /// Time spent on each step so far
+    pub timings: SetupTimings,
+    /// Bundle the dependencies and model were installed from, if any
+    pub offline_bundle: Option<PathBuf>,
+}
+
+impl SetupWizardState {
+    /// The last Python check, if it's recent enough to reuse
+    pub fn cached_python_status(&self, now: Instant) -> Option<PythonStatus> {
+        self.python_status
+            .as_ref()
+            .filter(|(checked, _)| now.saturating_duration_since(*checked) < PYTHON_STATUS_TTL)
+            .map(|(_, status)| status.clone())
+    }
+
+    /// Mark `step` done or not; false when it already was
+    pub fn set_step_done(&mut self, step: SetupStep, done: bool) -> bool {
+        let present = self.completed_steps.contains(&step);
+        if done && !present {
+            self.completed_steps.push(step);
+        } else if !done && present {
+            self.completed_steps.retain(|completed| *completed != step);
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// Record whether `step` is done, keeping it for a wizard reopened later
+fn record_step(state: &Mutex<SetupWizardState>, step: SetupStep, done: bool) {
+    let (config_dir, completed) = {
+        let mut state_guard = state.lock().unwrap();
+        if !state_guard.set_step_done(step, done) {
+            return;
+        }
+        (state_guard.config_dir.clone(), state_guard.completed_steps.clone())
+    };
+    if let Some(config_dir) = config_dir {
+        if let Err(e) = write_setup_progress(&config_dir, &completed) {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Setup may only finish once every required step is done
+fn steps_gate(completed: &[SetupStep]) -> Result<(), String> {
+    let missing = missing_setup_steps(completed);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = missing
+        .iter()
+        .map(|step| match step {
+            SetupStep::SystemCheck => "the system check",
+            SetupStep::InstallDependencies => "installing the Python packages",
+            _ => "setting up the model",
+        })
+        .collect();
+    Err(format!("Setup isn't finished: {} still to do.", names.join(", ")))
+}
+
+/// Check Python, reusing a recent result unless `force_refresh` is set
+async fn python_status(state: &Mutex<SetupWizardState>, force_refresh: bool) -> Result<PythonStatus, String> {
+    if !force_refresh {
+        let cached = state.lock().unwrap().cached_python_status(Instant::now());
+        if let Some(status) = cached {
+            return Ok(status);
+        }
+    }
+
+    let status = check_python().await?;
+    state.lock().unwrap().python_status = Some((Instant::now(), status.clone()));
+    Ok(status)
 }
 
 /// Detailed status response for frontend
@@ -26,6 +124,21 @@ pub struct SetupStatusResponse {
     pub system: Option<SystemRequirements>,
     pub model_configured: bool,
     pub model_path: Option<String>,
+    /// Offline bundle found in `~/VFX-BIDDING/offline_bundle/`
+    pub offline_bundle: Option<String>,
+    /// Steps done so far, including in earlier runs of the wizard
+    pub completed_steps: Vec<SetupStep>,
+    /// Where the wizard should resume; `Complete` once every step is done
+    pub current_step: SetupStep,
+}
+
+/// Result of `install_from_offline_bundle`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OfflineInstall {
+    pub bundle_path: String,
+    /// `name==version` of each package installed
+    pub packages: Vec<String>,
+    pub model_path: String,
 }
 
 /// Dependency check response
@@ -35,12 +148,165 @@ pub struct DependencyStatus {
     pub packages_ok: bool,
     pub model_ok: bool,
     pub missing_packages: Vec<String>,
+    pub outdated_packages: Vec<OutdatedPackage>,
+    /// None until `run_setup_smoke_test` has run
+    pub smoke_test: Option<SmokeTestResult>,
+    /// Why the last dependency install failed, one entry per package
+    pub install_errors: Vec<InstallError>,
     pub can_start: bool,
 }
 
+/// Result of picking a local model file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalModelSelection {
+    pub path: String,
+    /// Set when the model probably won't fit in this machine's RAM; the
+    /// selection still stands
+    pub ram_warning: Option<String>,
+}
+
+tracked_command! { Setup;
+/// Every Python found on this machine, for the interpreter chooser
+#[tauri::command]
+pub async fn list_python_interpreters() -> Result<Vec<PythonInterpreter>, String> {
+    crate::setup_wizard::list_python_interpreters().await
+}
+}
+
+tracked_command! { Setup;
+/// Bundle environment details for a support request
+///
+/// Covers system requirements, Python and package versions, the model and its
+/// checksum, and the sidecar's status and recent output, with secrets
+/// redacted. `include_self_test` adds the `run_self_test` checklist. When
+/// `output_path` is given the report is also written there.
+#[tauri::command]
+pub async fn generate_diagnostics_report(
+    output_path: Option<String>,
+    include_self_test: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<serde_json::Value, String> {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+    let mut errors = Vec::new();
+
+    let model_dir = session_state.get_settings().unwrap_or_default().paths.model_dir;
+    let system = check_system_requirements(&model_dir)
+        .map_err(|e| errors.push(format!("system: {}", e)))
+        .ok();
+    let python = check_python().await
+        .map_err(|e| errors.push(format!("python: {}", e)))
+        .ok();
+
+    let mut packages = BTreeMap::new();
+    if let Some(executable) = python.as_ref().and_then(|p| p.executable_path.clone()) {
+        match python_package_versions(Path::new(&executable)).await {
+            Some(versions) => packages = versions,
+            None => errors.push(format!("packages: could not query {}", executable)),
+        }
+    }
+
+    let settings = session_state.get_settings().unwrap_or_default();
+    let model_path = resolve_model_path(&settings);
+    let recorded_sha256 = settings.llm.model_sha256.clone();
+
+    let check_path = model_path.clone();
+    let expected = recorded_sha256.clone();
+    let checksum = tauri::async_runtime::spawn_blocking(move || {
+        check_model_integrity(&check_path, expected.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|result| result)
+    .map_err(|e| errors.push(format!("model checksum: {}", e)))
+    .ok();
+
+    let processing_history = history_path(&app)
+        .map(|path| recent_runs(&path, DEFAULT_HISTORY_LIMIT))
+        .map_err(|e| errors.push(format!("processing history: {}", e)))
+        .unwrap_or_default();
+
+    let self_test = if include_self_test.unwrap_or(false) {
+        Some(crate::self_test::run_self_test(self_test_inputs(&app)).await)
+    } else {
+        None
+    };
+
+    let report = DiagnosticsReport {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        system,
+        python,
+        packages,
+        model: ModelDiagnostics {
+            path: model_path.to_string_lossy().to_string(),
+            size_bytes: std::fs::metadata(&model_path).ok().map(|m| m.len()),
+            recorded_sha256,
+            checksum,
+        },
+        sidecar: SidecarDiagnostics {
+            running: sidecar_state.is_running(),
+            model_state: sidecar_state.model_state(),
+            rpc_metrics: sidecar_state.rpc_metrics(),
+            recent_log: sidecar_state.recent_log(),
+        },
+        processing_history,
+        self_test,
+        errors,
+    };
+
+    let json = report.to_redacted_json();
+
+    if let Some(output_path) = output_path {
+        let contents = serde_json::to_string_pretty(&json)
+            .map_err(|e| format!("Failed to serialize report: {}", e))?;
+        std::fs::write(&output_path, contents)
+            .map_err(|e| format!("Failed to write report: {}", e))?;
+    }
+
+    Ok(json)
+}
+}
+
+tracked_command! { Setup;
+/// Run the diagnostics checklist, from the config directory to an RPC
+/// health call
+///
+/// Each item passes, fails or is skipped on its own time limit; failures
+/// carry a hint naming the settings screen that fixes them.
+#[tauri::command]
+pub async fn run_self_test(app: tauri::AppHandle) -> Result<SelfTestReport, String> {
+    Ok(crate::self_test::run_self_test(self_test_inputs(&app)).await)
+}
+}
+
+fn self_test_inputs(app: &tauri::AppHandle) -> SelfTestInputs {
+    let sidecar_state: State<SidecarState> = app.state();
+    let session_state: State<SessionState> = app.state();
+    let settings = session_state.get_settings().unwrap_or_default();
+
+    SelfTestInputs {
+        config_dir: app.path().app_config_dir().ok(),
+        script_candidates: crate::commands::model::sidecar_script_candidates(app, &settings),
+        python: default_python(),
+        packages: setup_manifest().packages.clone(),
+        model_path: resolve_model_path(&settings),
+        llm_server_url: settings.llm.server_url.clone(),
+        output_dir: Some(settings.paths.output_dir.trim())
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+        data_dir: app.path().app_data_dir().ok(),
+        rpc: sidecar_state.rpc_client(),
+    }
+}
+
+tracked_command! { Setup;
 /// Check if this is the first run and get overall setup status
+///
+/// The Python check is reused for a short while; `force_refresh` runs it again.
 #[tauri::command]
 pub async fn check_setup_status(
+    force_refresh: Option<bool>,
     state: State<'_, Mutex<SetupWizardState>>,
     app: tauri::AppHandle,
 ) -> Result<SetupStatusResponse, String> {
@@ -59,14 +325,17 @@ pub async fn check_setup_status(
             system: None,
             model_configured: true,
             model_path: None,
+            offline_bundle: None,
+            completed_steps: REQUIRED_SETUP_STEPS.to_vec(),
+            current_step: SetupStep::Complete,
         });
     }
 
     // First run - check everything
-    let python_status = check_python().await?;
-    let system_reqs = check_system_requirements()?;
-
-    let model_path = get_default_model_path();
+    let python_status = python_status(&state, force_refresh.unwrap_or(false)).await?;
+    let model_dir = app.state::<SessionState>().get_settings().unwrap_or_default().paths.model_dir;
+    let system_reqs = check_system_requirements(&model_dir)?;
+    let model_path = get_default_model_path(&model_dir);
     let model_ok = model_path.exists();
 
     // Update state
@@ -77,6 +346,13 @@ pub async fn check_setup_status(
         state_guard.python_path = Some(path.clone());
     }
 
+    // Steps done before the wizard was last closed, unless undone since
+    state_guard.completed_steps = read_setup_progress(&config_dir);
+    if !python_status.missing_packages.is_empty() {
+        state_guard.set_step_done(SetupStep::InstallDependencies, false);
+    }
+    let completed_steps = state_guard.completed_steps.clone();
+
     let can_proceed = python_status.installed
         && system_reqs.ram_sufficient
         && system_reqs.disk_sufficient;
@@ -88,96 +364,144 @@ pub async fn check_setup_status(
         system: Some(system_reqs),
         model_configured: model_ok,
         model_path: Some(model_path.to_string_lossy().to_string()),
+        offline_bundle: find_bundle(&default_bundle_dir()).map(|path| path.to_string_lossy().to_string()),
+        current_step: next_setup_step(&completed_steps),
+        completed_steps,
     })
 }
+}
 
+tracked_command! { Setup;
 /// Start the setup process
 #[tauri::command]
 pub async fn start_setup(
     _state: State<'_, Mutex<SetupWizardState>>,
     window: Window,
 ) -> Result<String, String> {
-    window.emit("setup-started", ()).ok();
+    emit_event(&window, AppEvent::SetupStarted(())).ok();
 
     // Emit initial progress
-    window.emit("setup-progress", serde_json::json!({
-        "step": "Welcome",
-        "message": "Welcome to VFX Bidding AI Setup",
-        "percent": 0
-    })).ok();
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::Welcome, "Welcome to VFX Bidding AI Setup", 0))).ok();
 
     Ok("Setup started".to_string())
 }
+}
 
+tracked_command! { Setup;
 /// Verify system requirements
 #[tauri::command]
 pub async fn verify_system_requirements(
     window: Window,
+    state: State<'_, Mutex<SetupWizardState>>,
 ) -> Result<SystemRequirements, String> {
-    window.emit("setup-progress", serde_json::json!({
-        "step": "SystemCheck",
-        "message": "Checking system requirements...",
-        "percent": 10
-    })).ok();
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::SystemCheck, "Checking system requirements...", 10))).ok();
 
-    let reqs = check_system_requirements()?;
+    let started = Instant::now();
+    let model_dir = window.state::<SessionState>().get_settings().unwrap_or_default().paths.model_dir;
+    let reqs = check_system_requirements(&model_dir);
+    state.lock().unwrap().timings.record(SetupStep::SystemCheck, started.elapsed(), reqs.is_ok());
+    let reqs = reqs?;
+    record_step(&state, SetupStep::SystemCheck, reqs.ram_sufficient && reqs.disk_sufficient);
 
-    window.emit("setup-progress", serde_json::json!({
-        "step": "SystemCheck",
-        "message": "System check complete",
-        "percent": 20
-    })).ok();
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::SystemCheck, "System check complete", 20))).ok();
 
     Ok(reqs)
 }
+}
 
+tracked_command! { Setup;
 /// Install Python dependencies
+///
+/// `concurrency` sets how many packages pip installs at once; it defaults to
+/// `DEFAULT_INSTALL_CONCURRENCY`. Use 1 if parallel installs misbehave.
 #[tauri::command]
 pub async fn install_python_dependencies(
     python_path: String,
+    concurrency: Option<usize>,
     window: Window,
-    _state: State<'_, Mutex<SetupWizardState>>,
+    state: State<'_, Mutex<SetupWizardState>>,
 ) -> Result<(), String> {
-    window.emit("setup-progress", serde_json::json!({
-        "step": "InstallDependencies",
-        "message": "Installing Python packages...",
-        "percent": 30
-    })).ok();
-
-    install_packages(&python_path, |message| {
-        window.emit("setup-progress", serde_json::json!({
-            "step": "InstallDependencies",
-            "message": message,
-            "percent": 30
+    {
+        // A smoke test run against another interpreter no longer counts
+        let mut state_guard = state.lock().unwrap();
+        state_guard.python_path = Some(python_path.clone());
+        state_guard.smoke_test = None;
+        // Packages are about to change
+        state_guard.python_status = None;
+    }
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "Installing Python packages...", 30))).ok();
+
+    let concurrency = concurrency.unwrap_or(DEFAULT_INSTALL_CONCURRENCY);
+    let started = Instant::now();
+    let installed = install_packages(&python_path, concurrency, |message| {
+        emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, message, 30))).ok();
+    }).await;
+    {
+        let mut state_guard = state.lock().unwrap();
+        state_guard.python_status = None;
+        state_guard.timings.record(SetupStep::InstallDependencies, started.elapsed(), installed.is_ok());
+        state_guard.install_errors = installed.as_ref().err().cloned().unwrap_or_default();
+    }
+    record_step(&state, SetupStep::InstallDependencies, installed.is_ok());
+    if let Err(errors) = installed {
+        emit_event(&window, AppEvent::SetupProgress(SetupProgress {
+            errors: errors.clone(),
+            ..SetupProgress::new(SetupStep::InstallDependencies, "Some packages could not be installed", 30)
         })).ok();
-    }).await?;
+        return Err(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"));
+    }
 
-    window.emit("setup-progress", serde_json::json!({
-        "step": "InstallDependencies",
-        "message": "Dependencies installed successfully",
-        "percent": 50
-    })).ok();
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "Dependencies installed successfully", 50))).ok();
 
     Ok(())
 }
+}
 
+tracked_command! { Setup;
 /// Download or locate the model file
+///
+/// Downloads are retried from where they stopped after a network error, up
+/// to `download_attempts` requests in all (5 by default).
 #[tauri::command]
 pub async fn setup_model_file(
     source_type: String,
     source_path: String,
+    download_attempts: Option<u32>,
     state: State<'_, Mutex<SetupWizardState>>,
     window: Window,
 ) -> Result<String, String> {
-    window.emit("setup-progress", serde_json::json!({
-        "step": "DownloadModel",
-        "message": "Setting up model file...",
-        "percent": 55
-    })).ok();
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "Setting up model file...", 55))).ok();
+
+    // Copies and downloads go to the configured model directory
+    let model_dir = window.state::<SessionState>().get_settings().unwrap_or_default().paths.model_dir;
+    let model_path = get_default_model_path(&model_dir);
+
+    let started = Instant::now();
+    let placed = place_model_file(&source_type, source_path, download_attempts, &model_path, &window).await;
+    state.lock().unwrap().timings.record(SetupStep::DownloadModel, started.elapsed(), placed.is_ok());
+    record_step(&state, SetupStep::DownloadModel, placed.is_ok());
+    placed?;
 
-    let model_path = get_default_model_path();
+    // Update state
+    let mut state_guard = state.lock().unwrap();
+    state_guard.model_path = Some(model_path.clone());
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "Model setup complete", 100))).ok();
+
+    Ok(model_path.to_string_lossy().to_string())
+}
+}
 
-    let source = match source_type.as_str() {
+/// Copy or download the model to `model_path` and verify it
+async fn place_model_file(
+    source_type: &str,
+    source_path: String,
+    download_attempts: Option<u32>,
+    model_path: &Path,
+    window: &Window,
+) -> Result<(), String> {
+    let source = match source_type {
         "local" => {
             // Verify local file exists
             let path = PathBuf::from(&source_path);
@@ -191,15 +515,20 @@ pub async fn setup_model_file(
                     .map_err(|e| format!("Failed to create model directory: {}", e))?;
             }
 
-            std::fs::copy(&path, &model_path)
+            let size = std::fs::metadata(&path)
+                .map_err(|e| format!("Failed to read model file: {}", e))?
+                .len();
+            ensure_disk_space(model_path, size)?;
+
+            std::fs::copy(&path, model_path)
                 .map_err(|e| format!("Failed to copy model file: {}", e))?;
 
-            ModelSource::LocalFile(model_path.clone())
+            ModelSource::LocalFile(model_path.to_path_buf())
         }
         "url" => {
             ModelSource::DirectUrl {
                 url: source_path,
-                filename: "Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf".to_string(),
+                filename: setup_manifest().default_model.clone(),
             }
         }
         _ => {
@@ -209,106 +538,277 @@ pub async fn setup_model_file(
 
     // If it's a URL, download it
     if matches!(source, ModelSource::DirectUrl { .. }) {
-        download_model(window.clone(), source, model_path.clone()).await?;
+        let mut retry = DownloadRetry::default();
+        if let Some(attempts) = download_attempts {
+            retry.max_attempts = attempts.max(1);
+        }
+        download_model(window.clone(), source, model_path.to_path_buf(), retry).await?;
     }
 
     // Verify the model
-    window.emit("setup-progress", serde_json::json!({
-        "step": "DownloadModel",
-        "message": "Verifying model file...",
-        "percent": 95
-    })).ok();
+    emit_event(window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "Verifying model file...", 95))).ok();
 
-    let verified = verify_model(&model_path).await?;
+    let verified = verify_model(model_path).await?;
 
     if !verified {
         return Err("Model file verification failed".to_string());
     }
-
-    // Update state
-    let mut state_guard = state.lock().unwrap();
-    state_guard.model_path = Some(model_path.clone());
-
-    window.emit("setup-progress", serde_json::json!({
-        "step": "DownloadModel",
-        "message": "Model setup complete",
-        "percent": 100
-    })).ok();
-
-    Ok(model_path.to_string_lossy().to_string())
+    Ok(())
 }
 
+tracked_command! { Setup;
 /// Skip model download for advanced users who will configure later
 #[tauri::command]
 pub async fn skip_model_setup(
     state: State<'_, Mutex<SetupWizardState>>,
 ) -> Result<(), String> {
-    let mut state_guard = state.lock().unwrap();
-    state_guard.model_path = Some(PathBuf::from("")); // Empty path = will configure later
+    state.lock().unwrap().model_path = Some(PathBuf::from("")); // Empty path = will configure later
+    record_step(&state, SetupStep::DownloadModel, true);
     Ok(())
 }
+}
+
+tracked_command! { Setup;
+/// Install the Python packages and the model from an offline bundle
+///
+/// `path` is a bundle directory or zip; without one, the bundle in
+/// `~/VFX-BIDDING/offline_bundle/` is used. The bundle is checked against
+/// the setup manifest before anything is installed, and pip never reaches
+/// for the network; its output comes through as `setup-progress`.
+#[tauri::command]
+pub async fn install_from_offline_bundle(
+    path: Option<String>,
+    state: State<'_, Mutex<SetupWizardState>>,
+    window: Window,
+) -> Result<OfflineInstall, String> {
+    let bundle_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => find_bundle(&default_bundle_dir())
+            .ok_or_else(|| format!("No offline bundle found in {}", default_bundle_dir().display()))?,
+    };
+    let python_path = state.lock().unwrap().python_path.clone()
+        .ok_or_else(|| "Python not found".to_string())?;
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "Verifying offline bundle...", 30).offline())).ok();
+
+    let started = Instant::now();
+    let opened = {
+        let bundle_path = bundle_path.clone();
+        tokio::task::spawn_blocking(move || open_bundle(&bundle_path, setup_manifest()))
+            .await
+            .map_err(|e| format!("Bundle check failed: {}", e))?
+    };
+    let bundle = match opened {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            state.lock().unwrap().timings.record(SetupStep::InstallDependencies, started.elapsed(), false);
+            return Err(e);
+        }
+    };
+
+    {
+        let mut state_guard = state.lock().unwrap();
+        state_guard.smoke_test = None;
+        state_guard.python_status = None;
+    }
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "Installing Python packages from the offline bundle...", 35).offline())).ok();
+
+    let args = pip_install_args(&bundle, setup_manifest());
+    let installed = run_pip(&python_path, &args, |line| {
+        emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, line, 35).offline())).ok();
+    }).await;
+    state.lock().unwrap().timings.record(SetupStep::InstallDependencies, started.elapsed(), installed.is_ok());
+    record_step(&state, SetupStep::InstallDependencies, installed.is_ok());
+    installed?;
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "Copying model from the offline bundle...", 55).offline())).ok();
+
+    let model_dir = window.state::<SessionState>().get_settings().unwrap_or_default().paths.model_dir;
+    let model_path = get_default_model_path(&model_dir);
+    let started = Instant::now();
+    let packages: Vec<String> = args.iter().filter(|arg| arg.contains("==")).cloned().collect();
+    let copied = {
+        let model_path = model_path.clone();
+        tokio::task::spawn_blocking(move || install_model(&bundle, &model_path))
+            .await
+            .map_err(|e| format!("Model copy failed: {}", e))?
+    };
+    state.lock().unwrap().timings.record(SetupStep::DownloadModel, started.elapsed(), copied.is_ok());
+    record_step(&state, SetupStep::DownloadModel, copied.is_ok());
+    copied?;
+
+    {
+        let mut state_guard = state.lock().unwrap();
+        state_guard.model_path = Some(model_path.clone());
+        state_guard.offline_bundle = Some(bundle_path.clone());
+    }
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "Installed from the offline bundle", 100).offline())).ok();
+
+    Ok(OfflineInstall {
+        bundle_path: bundle_path.to_string_lossy().to_string(),
+        packages,
+        model_path: model_path.to_string_lossy().to_string(),
+    })
+}
+}
 
+tracked_command! { Setup;
+/// Launch the sidecar once and check it answers before setup finishes
+///
+/// Uses the interpreter chosen in the wizard. Failures come back in the
+/// result, naming the phase that failed, rather than as an error.
+#[tauri::command]
+pub async fn run_setup_smoke_test(
+    state: State<'_, Mutex<SetupWizardState>>,
+    sidecar_state: State<'_, SidecarState>,
+    window: Window,
+) -> Result<SmokeTestResult, String> {
+    let (python, script_path) = {
+        let state_guard = state.lock().unwrap();
+        let script_path = state_guard.script_path.clone()
+            .ok_or_else(|| "Sidecar script not found".to_string())?;
+        (state_guard.python_path.clone().unwrap_or_else(|| "python3".to_string()), script_path)
+    };
+
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::Verify, "Starting the Python sidecar...", 90))).ok();
+
+    let env = sidecar_state.env();
+    let result = tokio::task::spawn_blocking(move || run_smoke_test(&python, &script_path, env))
+        .await
+        .map_err(|e| format!("Smoke test failed to run: {}", e))?;
+
+    let message = match (&result.failed_phase, &result.error) {
+        (Some(phase), Some(error)) => format!("Sidecar check failed during {:?}: {}", phase, error),
+        _ => "Sidecar check passed".to_string(),
+    };
+    emit_event(&window, AppEvent::SetupProgress(SetupProgress::new(SetupStep::Verify, message, 95))).ok();
+
+    state.lock().unwrap().smoke_test = Some(result.clone());
+    Ok(result)
+}
+}
+
+tracked_command! { Setup;
 /// Complete the setup process
+///
+/// Refused until every required step is done and the smoke test has
+/// passed, unless `skip_smoke_test` records that the user chose to finish
+/// without it.
 #[tauri::command]
 pub async fn complete_setup_process(
+    skip_smoke_test: Option<bool>,
     state: State<'_, Mutex<SetupWizardState>>,
     window: Window,
 ) -> Result<(), String> {
-    let config_dir = {
+    let (config_dir, timings, offline_bundle) = {
         let state_guard = state.lock().unwrap();
-        state_guard.config_dir.clone()
-            .ok_or_else(|| "Config directory not set".to_string())?
+        steps_gate(&state_guard.completed_steps)?;
+        smoke_test_gate(state_guard.smoke_test.as_ref(), skip_smoke_test.unwrap_or(false))?;
+        let config_dir = state_guard.config_dir.clone()
+            .ok_or_else(|| "Config directory not set".to_string())?;
+        (config_dir, state_guard.timings.clone(), state_guard.offline_bundle.clone())
     };
 
-    complete_setup(&config_dir).await?;
+    complete_setup(&config_dir, &timings, offline_bundle.as_deref()).await?;
 
-    window.emit("setup-complete", ()).ok();
+    emit_event(&window, AppEvent::SetupComplete(())).ok();
 
     Ok(())
 }
+}
+
+tracked_command! { Setup;
+/// How long each setup step took
+///
+/// During the wizard these are this run's timings; once setup has finished
+/// in an earlier run, the ones saved when it completed.
+#[tauri::command]
+pub async fn get_setup_timings(
+    state: State<'_, Mutex<SetupWizardState>>,
+    app: tauri::AppHandle,
+) -> Result<SetupTimings, String> {
+    let timings = state.lock().unwrap().timings.clone();
+    if !timings.steps.is_empty() {
+        return Ok(timings);
+    }
+
+    let config_dir = app.path().app_config_dir()
+        .map_err(|e| format!("Failed to get config dir: {}", e))?;
+    Ok(read_setup_timings(&config_dir).unwrap_or_default())
+}
+}
 
+/// Setup may finish once the smoke test passed or the user skipped it
+fn smoke_test_gate(result: Option<&SmokeTestResult>, skip: bool) -> Result<(), String> {
+    match result {
+        _ if skip => Ok(()),
+        Some(result) if result.passed => Ok(()),
+        Some(result) => Err(format!(
+            "The sidecar check failed during {:?}{}. Fix the problem and run it again, or skip it to finish anyway.",
+            result.failed_phase.unwrap_or(crate::sidecar::SmokePhase::Launch),
+            result.error.as_deref().map(|e| format!(": {}", e)).unwrap_or_default(),
+        )),
+        None => Err("Run the sidecar check before finishing setup, or skip it to finish anyway.".to_string()),
+    }
+}
+
+tracked_command! { Setup;
 /// Verify all dependencies are ready
+///
+/// Outdated packages count as not ready. `force_refresh` skips the cached
+/// Python check.
 #[tauri::command]
 pub async fn verify_dependencies(
+    force_refresh: Option<bool>,
     state: State<'_, Mutex<SetupWizardState>>,
 ) -> Result<DependencyStatus, String> {
     // Extract needed data from state before await
-    let (model_path_exists, python_path) = {
+    let (model_path_exists, python_path, smoke_test) = {
         let state_guard = state.lock().unwrap();
         let model_ok = state_guard.model_path
             .as_ref()
             .map(|p| p.exists() || p.as_os_str().is_empty())
             .unwrap_or(false);
         let py_path = state_guard.python_path.clone();
-        (model_ok, py_path)
+        (model_ok, py_path, state_guard.smoke_test.clone())
     };
+    let install_errors = state.lock().unwrap().install_errors.clone();
 
     // Check Python (this is async, so must be done outside the lock)
-    let python_status = check_python().await?;
+    let python_status = python_status(&state, force_refresh.unwrap_or(false)).await?;
 
     let python_ok = python_status.installed && python_status.pip_available;
-    let packages_ok = python_status.missing_packages.is_empty();
+    let packages_ok = python_status.missing_packages.is_empty() && python_status.outdated_packages.is_empty();
     let model_ok = model_path_exists;
 
     let missing_packages = python_status.missing_packages;
+    let outdated_packages = python_status.outdated_packages;
 
-    let can_start = python_ok && packages_ok && model_ok;
+    let smoke_ok = smoke_test.as_ref().is_some_and(|result| result.passed);
+    let can_start = python_ok && packages_ok && model_ok && smoke_ok;
 
     Ok(DependencyStatus {
         python_ok,
         packages_ok,
         model_ok,
         missing_packages,
+        outdated_packages,
+        smoke_test,
+        install_errors,
         can_start,
     })
 }
+}
 
+tracked_command! { Setup;
 /// Select a local model file
 #[tauri::command]
 pub async fn select_local_model(
     path: String,
     state: State<'_, Mutex<SetupWizardState>>,
-) -> Result<String, String> {
+) -> Result<LocalModelSelection, String> {
     let model_path = PathBuf::from(&path);
 
     if !model_path.exists() {
@@ -323,28 +823,42 @@ pub async fn select_local_model(
         return Err("File too small to be a valid model (should be > 1GB)".to_string());
     }
 
+    let model_dir = model_path.parent().and_then(|dir| dir.to_str()).unwrap_or_default();
+    let ram_warning = check_system_requirements(model_dir)
+        .ok()
+        .and_then(|system| model_ram_warning(metadata.len(), system.ram_total_gb));
+
     // Update state
     let mut state_guard = state.lock().unwrap();
     state_guard.model_path = Some(model_path.clone());
 
-    Ok(model_path.to_string_lossy().to_string())
+    Ok(LocalModelSelection {
+        path: model_path.to_string_lossy().to_string(),
+        ram_warning,
+    })
+}
 }
 
+tracked_command! { Setup;
 /// Get recommended model download instructions
 #[tauri::command]
 pub async fn get_model_download_instructions() -> Result<ModelDownloadInstructions, String> {
+    let manifest = setup_manifest();
+    let model = manifest.default_model();
+    let size = format_bytes(manifest.default_model_size());
+
     Ok(ModelDownloadInstructions {
         methods: vec![
             ModelDownloadMethod {
                 name: "Manual Download from Hugging Face".to_string(),
                 description: "Download manually and select the file".to_string(),
-                url: "https://huggingface.co/bartowski/Floppa-12B-Gemma3-Uncensored-GGUF".to_string(),
+                url: model.url.clone().unwrap_or_default(),
                 requires_auth: true,
                 instructions: vec![
                     "1. Visit the Hugging Face repository".to_string(),
                     "2. Sign in or create a Hugging Face account".to_string(),
                     "3. Navigate to the Files tab".to_string(),
-                    "4. Download Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf (~6.5GB)".to_string(),
+                    format!("4. Download {} (~{})", model.filename, size),
                     "5. Click 'Select Local File' and choose the downloaded file".to_string(),
                 ].join("\n"),
             },
@@ -363,10 +877,11 @@ pub async fn get_model_download_instructions() -> Result<ModelDownloadInstructio
                 instructions: "Click 'Select Local File' and navigate to your existing model".to_string(),
             },
         ],
-        filename: "Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf".to_string(),
-        expected_size: "Approximately 6.5 GB".to_string(),
+        filename: model.filename.clone(),
+        expected_size: format!("Approximately {}", size),
     })
 }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModelDownloadInstructions {
@@ -384,6 +899,7 @@ pub struct ModelDownloadMethod {
     pub instructions: String,
 }
 
+tracked_command! { Setup;
 /// Reset setup (for testing or reconfiguration)
 #[tauri::command]
 pub async fn reset_setup(app: tauri::AppHandle) -> Result<(), String> {
@@ -399,3 +915,71 @@ pub async fn reset_setup(app: tauri::AppHandle) -> Result<(), String> {
 
     Ok(())
 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::SmokePhase;
+
+    #[test]
+    fn test_completion_needs_passing_smoke_test_or_skip() {
+        let failed = SmokeTestResult {
+            passed: false,
+            timings: Vec::new(),
+            failed_phase: Some(SmokePhase::Handshake),
+            error: Some("Sidecar closed its output before responding".to_string()),
+            exit_code: Some(1),
+            stderr_tail: vec!["ModuleNotFoundError: No module named 'llama_cpp'".to_string()],
+        };
+
+        assert!(smoke_test_gate(None, false).is_err());
+        let error = smoke_test_gate(Some(&failed), false).unwrap_err();
+        assert!(error.contains("Handshake") && error.contains("closed its output"));
+
+        assert!(smoke_test_gate(Some(&failed), true).is_ok());
+        assert!(smoke_test_gate(None, true).is_ok());
+        let passed = SmokeTestResult { passed: true, failed_phase: None, error: None, ..failed };
+        assert!(smoke_test_gate(Some(&passed), false).is_ok());
+    }
+
+    #[test]
+    fn test_completion_needs_every_required_step() {
+        let mut state = SetupWizardState::default();
+        assert!(state.set_step_done(SetupStep::SystemCheck, true));
+        assert!(!state.set_step_done(SetupStep::SystemCheck, true));
+        assert!(state.set_step_done(SetupStep::InstallDependencies, true));
+        assert_eq!(
+            steps_gate(&state.completed_steps).unwrap_err(),
+            "Setup isn't finished: setting up the model still to do."
+        );
+
+        // A failed reinstall undoes the step
+        assert!(state.set_step_done(SetupStep::InstallDependencies, false));
+        assert!(state.set_step_done(SetupStep::DownloadModel, true));
+        assert!(steps_gate(&state.completed_steps).unwrap_err().contains("installing the Python packages"));
+
+        state.set_step_done(SetupStep::InstallDependencies, true);
+        assert_eq!(steps_gate(&state.completed_steps), Ok(()));
+    }
+
+    #[test]
+    fn test_python_status_cached_briefly() {
+        let checked = Instant::now();
+        let status = PythonStatus {
+            installed: true,
+            version: Some("Python 3.11.4".to_string()),
+            executable_path: Some("python3".to_string()),
+            pip_available: true,
+            packages_installed: Vec::new(),
+            missing_packages: vec!["pandas".to_string()],
+            package_versions: BTreeMap::new(),
+            outdated_packages: Vec::new(),
+        };
+        let state = SetupWizardState { python_status: Some((checked, status)), ..Default::default() };
+
+        assert!(state.cached_python_status(checked + Duration::from_secs(5)).is_some());
+        assert!(state.cached_python_status(checked + PYTHON_STATUS_TTL).is_none());
+        assert!(SetupWizardState::default().cached_python_status(checked).is_none());
+    }
+}
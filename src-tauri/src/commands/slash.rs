@@ -0,0 +1,443 @@
+//! Chat slash commands
+//!
+//! Messages starting with `/` (`/total`, `/shot SH042`, ...) are parsed here
+//! and run straight against the bid by `send_message`, with no LLM round
+//! trip. The chat UI relies on the exact syntax and replies below.
+
+use std::fmt;
+use crate::assumptions::AssumptionCategory;
+
+/// A parsed slash command
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlashCommand {
+    /// List every command, or show one command's syntax
+    Help(Option<String>),
+    Total,
+    Shot(String),
+    Set { shot_id: String, edit: ShotEdit },
+    Tag { shot_id: String, tags: Vec<String> },
+    Untag { shot_id: String, tags: Vec<String> },
+    Export { format: ExportFormat, path: String },
+    /// List the bid's assumptions, or only one category of them
+    Assumptions(Option<AssumptionCategory>),
+}
+
+/// Field change made by `/set`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShotEdit {
+    Complexity(String),
+    Hours(f64),
+    Rate(f64),
+    Contingency(f64),
+    Overhead(f64),
+    Description(String),
+    Scene(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+}
+
+/// Why a message starting with `/` couldn't be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlashError {
+    /// Not a command we know; answered with the help listing
+    Unknown(String),
+    /// A known command used wrongly; answered with its syntax
+    Usage { command: &'static str, problem: String },
+}
+
+impl fmt::Display for SlashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlashError::Unknown(name) => write!(f, "Unknown command /{}.\n\n{}", name, help_text(None)),
+            SlashError::Usage { command, problem } => {
+                let spec = find_command(command).expect("usage errors name a known command");
+                write!(f, "{}\nUsage: {}\nExample: {}", problem, spec.usage, spec.example)
+            }
+        }
+    }
+}
+
+/// Syntax and description of one command, for `/help`
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub example: &'static str,
+}
+
+pub const COMMANDS: &[CommandHelp] = &[
+    CommandHelp {
+        name: "help",
+        usage: "/help [command]",
+        summary: "List the commands, or show how to use one",
+        example: "/help set",
+    },
+    CommandHelp {
+        name: "total",
+        usage: "/total",
+        summary: "Shot count, hours and grand total",
+        example: "/total",
+    },
+    CommandHelp {
+        name: "shot",
+        usage: "/shot <shot-id>",
+        summary: "Show one shot",
+        example: "/shot SH042",
+    },
+    CommandHelp {
+        name: "set",
+        usage: "/set <shot-id> <field> <value>",
+        summary: "Change complexity, hours, rate, contingency, overhead, description or scene",
+        example: "/set SH042 complexity high",
+    },
+    CommandHelp {
+        name: "tag",
+        usage: "/tag <shot-id> <tag> [tag...]",
+        summary: "Add workflow tags to a shot",
+        example: "/tag SH010 awaiting-plate",
+    },
+    CommandHelp {
+        name: "untag",
+        usage: "/untag <shot-id> <tag> [tag...]",
+        summary: "Remove tags from a shot",
+        example: "/untag SH010 awaiting-plate",
+    },
+    CommandHelp {
+        name: "export",
+        usage: "/export csv <path>",
+        summary: "Export the shot list as CSV",
+        example: "/export csv ~/Desktop/bid.csv",
+    },
+    CommandHelp {
+        name: "assumptions",
+        usage: "/assumptions [assumptions|exclusions|notes]",
+        summary: "List the bid's assumptions, exclusions and notes",
+        example: "/assumptions exclusions",
+    },
+    CommandHelp {
+        name: "exclusions",
+        usage: "/exclusions",
+        summary: "List what the bid excludes",
+        example: "/exclusions",
+    },
+];
+
+/// Fields `/set` can change
+pub const SET_FIELDS: &[&str] = &["complexity", "hours", "rate", "contingency", "overhead", "description", "scene"];
+
+fn find_command(name: &str) -> Option<&'static CommandHelp> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// Parse `message` if it is a slash command; `None` for ordinary chat
+pub fn parse(message: &str) -> Option<Result<SlashCommand, SlashError>> {
+    let body = message.trim().strip_prefix('/')?;
+    Some(parse_body(body))
+}
+
+fn parse_body(body: &str) -> Result<SlashCommand, SlashError> {
+    let (name, rest) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+    let name = name.to_lowercase();
+    let spec = find_command(&name).ok_or(SlashError::Unknown(name))?;
+    let usage = |problem: String| SlashError::Usage { command: spec.name, problem };
+
+    let args = tokenize(rest).map_err(usage)?;
+
+    match spec.name {
+        "help" => match args.as_slice() {
+            [] => Ok(SlashCommand::Help(None)),
+            [topic] => Ok(SlashCommand::Help(Some(topic.trim_start_matches('/').to_lowercase()))),
+            _ => Err(usage("Expected at most one command name.".to_string())),
+        },
+        "total" if args.is_empty() => Ok(SlashCommand::Total),
+        "total" => Err(usage("/total takes no arguments.".to_string())),
+        "shot" => match args.as_slice() {
+            [id] => Ok(SlashCommand::Shot(id.clone())),
+            [] => Err(usage("Missing the shot ID.".to_string())),
+            _ => Err(usage("Expected one shot ID; quote IDs that contain spaces.".to_string())),
+        },
+        "set" => parse_set(&args).map_err(usage),
+        "tag" | "untag" => match args.as_slice() {
+            [shot_id, tags @ ..] if !tags.is_empty() => {
+                let (shot_id, tags) = (shot_id.clone(), tags.to_vec());
+                Ok(if spec.name == "tag" { SlashCommand::Tag { shot_id, tags } } else { SlashCommand::Untag { shot_id, tags } })
+            }
+            [_] => Err(usage("Missing the tag.".to_string())),
+            _ => Err(usage("Expected a shot ID and at least one tag.".to_string())),
+        },
+        "export" => parse_export(&args).map_err(usage),
+        "assumptions" => match args.as_slice() {
+            [] => Ok(SlashCommand::Assumptions(None)),
+            [kind] => parse_category(kind).map(|c| SlashCommand::Assumptions(Some(c))).map_err(usage),
+            _ => Err(usage("Expected at most one category.".to_string())),
+        },
+        "exclusions" if args.is_empty() => Ok(SlashCommand::Assumptions(Some(AssumptionCategory::Exclusion))),
+        "exclusions" => Err(usage("/exclusions takes no arguments.".to_string())),
+        _ => unreachable!("every command in COMMANDS is parsed"),
+    }
+}
+
+fn parse_set(args: &[String]) -> Result<SlashCommand, String> {
+    let [shot_id, field, value @ ..] = args else {
+        return Err("Expected a shot ID, a field and a value.".to_string());
+    };
+    if value.is_empty() {
+        return Err(format!("Missing the new value for {}.", field));
+    }
+
+    let text = value.join(" ");
+    let number = |name: &str, text: &str| -> Result<f64, String> {
+        let bare = text.trim_start_matches('$').trim_end_matches('%').replace(',', "");
+        match bare.parse::<f64>() {
+            Ok(n) if n.is_finite() && n >= 0.0 && value.len() == 1 => Ok(n),
+            _ => Err(format!("{} must be a number of zero or more, not '{}'.", name, text)),
+        }
+    };
+
+    let edit = match field.to_lowercase().as_str() {
+        "complexity" => ShotEdit::Complexity(parse_complexity(&text)?),
+        "hours" => ShotEdit::Hours(number("Hours", &text)?),
+        "rate" => ShotEdit::Rate(number("Rate", &text)?),
+        "contingency" => ShotEdit::Contingency(number("Contingency", &text)?),
+        "overhead" => ShotEdit::Overhead(number("Overhead", &text)?),
+        "description" => ShotEdit::Description(text),
+        "scene" => ShotEdit::Scene(text),
+        _ => {
+            return Err(format!("Unknown field '{}'. Fields: {}.", field, SET_FIELDS.join(", ")));
+        }
+    };
+
+    Ok(SlashCommand::Set { shot_id: shot_id.clone(), edit })
+}
+
+fn parse_category(text: &str) -> Result<AssumptionCategory, String> {
+    match text.to_lowercase().as_str() {
+        "assumption" | "assumptions" => Ok(AssumptionCategory::Assumption),
+        "exclusion" | "exclusions" => Ok(AssumptionCategory::Exclusion),
+        "note" | "notes" => Ok(AssumptionCategory::Note),
+        _ => Err(format!("Unknown category '{}'. Use assumptions, exclusions or notes.", text)),
+    }
+}
+
+/// Complexity tier names as the rest of the app spells them
+fn parse_complexity(text: &str) -> Result<String, String> {
+    let tier = match text.trim().to_lowercase().as_str() {
+        "low" | "simple" | "easy" => "Low",
+        "medium" | "moderate" => "Medium",
+        "high" | "complex" | "hard" | "hero" => "High",
+        _ => return Err(format!("Unknown complexity '{}'. Use low, medium or high.", text)),
+    };
+    Ok(tier.to_string())
+}
+
+fn parse_export(args: &[String]) -> Result<SlashCommand, String> {
+    let [format, path @ ..] = args else {
+        return Err("Expected a format and a path.".to_string());
+    };
+
+    let format = match format.to_lowercase().as_str() {
+        "csv" => ExportFormat::Csv,
+        other => return Err(format!("Can't export '{}'; only csv is supported here.", other)),
+    };
+    if path.is_empty() {
+        return Err("Missing the file path.".to_string());
+    }
+
+    Ok(SlashCommand::Export { format, path: path.join(" ") })
+}
+
+/// Split arguments on whitespace, keeping "double" or 'single' quoted text
+/// together
+///
+/// A quote only opens at the start of an argument, so apostrophes (Hero's)
+/// need no escaping. Inside double quotes `\"` and `\\` are escapes.
+pub fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('"') if c == '\\' => match chars.next() {
+                Some(escaped) => current.push(escaped),
+                None => break,
+            },
+            Some(_) => current.push(c),
+            None if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            None if !in_token && matches!(c, '"' | '\'') => {
+                quote = Some(c);
+                in_token = true;
+            }
+            None => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Unclosed {} quote.", q));
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// The reply to `/help`, for one command or all of them
+pub fn help_text(topic: Option<&str>) -> String {
+    if let Some(spec) = topic.and_then(find_command) {
+        return format!("{}\n{}\nExample: {}", spec.usage, spec.summary, spec.example);
+    }
+
+    let mut text = String::new();
+    if let Some(topic) = topic {
+        text.push_str(&format!("Unknown command /{}.\n\n", topic));
+    }
+    text.push_str("Slash commands:");
+    for spec in COMMANDS {
+        text.push_str(&format!("\n{} — {}\n    e.g. {}", spec.usage, spec.summary, spec.example));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(message: &str) -> Result<SlashCommand, SlashError> {
+        parse(message).expect("is a slash command")
+    }
+
+    fn problem(message: &str) -> String {
+        match parsed(message) {
+            Err(SlashError::Usage { problem, .. }) => problem,
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ordinary_chat_is_not_a_command() {
+        assert_eq!(parse("what's the total?"), None);
+        assert_eq!(parse("  make scene 4 cheaper"), None);
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(tokenize(r#"SH042 description "Sky replacement, wide""#).unwrap(), vec!["SH042", "description", "Sky replacement, wide"]);
+        assert_eq!(tokenize("'scene 4 plate'  x").unwrap(), vec!["scene 4 plate", "x"]);
+        assert_eq!(tokenize(r#""say \"hi\"" Hero's"#).unwrap(), vec![r#"say "hi""#, "Hero's"]);
+        assert_eq!(tokenize(r#""" b"#).unwrap(), vec!["", "b"]);
+        assert_eq!(tokenize("   ").unwrap(), Vec::<String>::new());
+        assert_eq!(tokenize(r#"a "open"#).unwrap_err(), "Unclosed \" quote.");
+    }
+
+    #[test]
+    fn test_simple_commands() {
+        assert_eq!(parsed("/total"), Ok(SlashCommand::Total));
+        assert_eq!(parsed("  /TOTAL  "), Ok(SlashCommand::Total));
+        assert_eq!(parsed("/help"), Ok(SlashCommand::Help(None)));
+        assert_eq!(parsed("/help /Set"), Ok(SlashCommand::Help(Some("set".to_string()))));
+        assert_eq!(problem("/total now"), "/total takes no arguments.");
+    }
+
+    #[test]
+    fn test_shot_ids_keep_letters_and_case() {
+        assert_eq!(parsed("/shot SH042A"), Ok(SlashCommand::Shot("SH042A".to_string())));
+        assert_eq!(parsed("/shot 12b_v2"), Ok(SlashCommand::Shot("12b_v2".to_string())));
+        assert_eq!(parsed("/shot \"SH 042\""), Ok(SlashCommand::Shot("SH 042".to_string())));
+        assert_eq!(problem("/shot"), "Missing the shot ID.");
+        assert!(problem("/shot SH 042").contains("quote IDs"));
+    }
+
+    #[test]
+    fn test_set_fields() {
+        let set = |id: &str, edit| Ok(SlashCommand::Set { shot_id: id.to_string(), edit });
+
+        assert_eq!(parsed("/set SH042 complexity high"), set("SH042", ShotEdit::Complexity("High".to_string())));
+        assert_eq!(parsed("/set SH042 Complexity \"Hero\""), set("SH042", ShotEdit::Complexity("High".to_string())));
+        assert_eq!(parsed("/set SH042 hours 12.5"), set("SH042", ShotEdit::Hours(12.5)));
+        assert_eq!(parsed("/set SH042 rate $1,250"), set("SH042", ShotEdit::Rate(1250.0)));
+        assert_eq!(parsed("/set SH042 contingency 15%"), set("SH042", ShotEdit::Contingency(15.0)));
+        assert_eq!(parsed("/set 7A description Hero's jump, wide"), set("7A", ShotEdit::Description("Hero's jump, wide".to_string())));
+        assert_eq!(parsed("/set 7A scene '12 B'"), set("7A", ShotEdit::Scene("12 B".to_string())));
+    }
+
+    #[test]
+    fn test_set_errors_show_the_syntax() {
+        assert_eq!(problem("/set SH042 colour red"), "Unknown field 'colour'. Fields: complexity, hours, rate, contingency, overhead, description, scene.");
+        assert_eq!(problem("/set SH042 hours twelve"), "Hours must be a number of zero or more, not 'twelve'.");
+        assert_eq!(problem("/set SH042 hours -3"), "Hours must be a number of zero or more, not '-3'.");
+        assert_eq!(problem("/set SH042 hours 1 2"), "Hours must be a number of zero or more, not '1 2'.");
+        assert!(problem("/set SH042 complexity extreme").contains("Use low, medium or high"));
+        assert_eq!(problem("/set SH042 hours"), "Missing the new value for hours.");
+        assert_eq!(problem("/set SH042"), "Expected a shot ID, a field and a value.");
+        assert_eq!(problem("/set SH042 description \"unclosed"), "Unclosed \" quote.");
+
+        let reply = parsed("/set SH042 colour red").unwrap_err().to_string();
+        assert!(reply.ends_with("Usage: /set <shot-id> <field> <value>\nExample: /set SH042 complexity high"), "{}", reply);
+    }
+
+    #[test]
+    fn test_tag() {
+        assert_eq!(
+            parsed("/tag SH010 awaiting-plate \"Phase 2\""),
+            Ok(SlashCommand::Tag { shot_id: "SH010".to_string(), tags: vec!["awaiting-plate".to_string(), "Phase 2".to_string()] })
+        );
+        assert_eq!(
+            parsed("/untag SH010 awaiting-plate"),
+            Ok(SlashCommand::Untag { shot_id: "SH010".to_string(), tags: vec!["awaiting-plate".to_string()] })
+        );
+        assert_eq!(problem("/tag SH010"), "Missing the tag.");
+        assert_eq!(problem("/untag"), "Expected a shot ID and at least one tag.");
+    }
+
+    #[test]
+    fn test_export() {
+        assert_eq!(
+            parsed("/export CSV ~/Desktop/bid.csv"),
+            Ok(SlashCommand::Export { format: ExportFormat::Csv, path: "~/Desktop/bid.csv".to_string() })
+        );
+        assert_eq!(
+            parsed("/export csv \"/tmp/My Bids/bid.csv\""),
+            Ok(SlashCommand::Export { format: ExportFormat::Csv, path: "/tmp/My Bids/bid.csv".to_string() })
+        );
+        assert_eq!(problem("/export xlsx out.xlsx"), "Can't export 'xlsx'; only csv is supported here.");
+        assert_eq!(problem("/export csv"), "Missing the file path.");
+    }
+
+    #[test]
+    fn test_assumptions() {
+        assert_eq!(parsed("/assumptions"), Ok(SlashCommand::Assumptions(None)));
+        assert_eq!(parsed("/assumptions Notes"), Ok(SlashCommand::Assumptions(Some(AssumptionCategory::Note))));
+        assert_eq!(parsed("/exclusions"), Ok(SlashCommand::Assumptions(Some(AssumptionCategory::Exclusion))));
+        assert!(problem("/assumptions risks").starts_with("Unknown category 'risks'"));
+        assert_eq!(problem("/exclusions all"), "/exclusions takes no arguments.");
+    }
+
+    #[test]
+    fn test_unknown_commands_list_help() {
+        assert_eq!(parsed("/totals"), Err(SlashError::Unknown("totals".to_string())));
+        assert_eq!(parsed("/"), Err(SlashError::Unknown(String::new())));
+
+        let reply = parsed("/totals").unwrap_err().to_string();
+        assert!(reply.starts_with("Unknown command /totals."));
+        for spec in COMMANDS {
+            assert!(reply.contains(spec.usage) && reply.contains(spec.example), "{} missing", spec.name);
+        }
+
+        assert!(help_text(Some("set")).starts_with("/set <shot-id> <field> <value>"));
+        assert!(help_text(Some("nope")).starts_with("Unknown command /nope."));
+    }
+}
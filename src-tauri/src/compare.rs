@@ -0,0 +1,142 @@
+//! Differences between two versions of a bid
+//!
+//! Shows what changed between the version a client approved and the
+//! revision being prepared.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::commands::bid::{total_final_price, BidRevision, ShotData};
+
+/// Change to a shot present in both versions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShotChange {
+    pub shot_id: String,
+    /// Fields that differ, e.g. "estimated_hours"
+    pub fields: Vec<String>,
+    pub old_price: Option<f64>,
+    pub new_price: Option<f64>,
+}
+
+/// Result of `compare_bids`; shot ids are in bid order
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BidComparison {
+    pub base_version: u32,
+    pub target_version: u32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ShotChange>,
+    /// Grand totals with each version's own rounding
+    pub base_total: f64,
+    pub target_total: f64,
+    pub difference: f64,
+}
+
+pub fn compare_versions(base: &BidRevision, target: &BidRevision) -> BidComparison {
+    let base_shots: HashMap<&str, &ShotData> = base.shots.iter().map(|s| (s.id.as_str(), s)).collect();
+    let target_ids: Vec<&str> = target.shots.iter().map(|s| s.id.as_str()).collect();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+
+    for shot in &target.shots {
+        let Some(old) = base_shots.get(shot.id.as_str()) else {
+            added.push(shot.id.clone());
+            continue;
+        };
+
+        let fields = changed_fields(old, shot);
+        if !fields.is_empty() {
+            changed.push(ShotChange {
+                shot_id: shot.id.clone(),
+                fields,
+                old_price: old.final_price,
+                new_price: shot.final_price,
+            });
+        }
+    }
+
+    let removed = base.shots
+        .iter()
+        .filter(|s| !target_ids.contains(&s.id.as_str()))
+        .map(|s| s.id.clone())
+        .collect();
+
+    let base_total = total_final_price(&base.shots, base.meta.rounding);
+    let target_total = total_final_price(&target.shots, target.meta.rounding);
+
+    BidComparison {
+        base_version: base.meta.version,
+        target_version: target.meta.version,
+        added,
+        removed,
+        changed,
+        base_total,
+        target_total,
+        difference: target_total - base_total,
+    }
+}
+
+/// Names of the serialized fields whose values differ
+fn changed_fields(old: &ShotData, new: &ShotData) -> Vec<String> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+
+    new.iter()
+        .filter(|(field, value)| old.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::{BidMeta, BidStatus};
+
+    fn shot(id: &str, hours: f64) -> ShotData {
+        let mut shot = ShotData {
+            id: id.to_string(),
+            scene_number: "1".to_string(),
+            complexity: "Simple".to_string(),
+            estimated_hours: Some(hours),
+            rate_per_hour: Some(100.0),
+            ..Default::default()
+        };
+        shot.recalculate_pricing();
+        shot
+    }
+
+    fn version(version: u32, shots: Vec<ShotData>) -> BidRevision {
+        BidRevision {
+            revised_at: String::new(),
+            meta: BidMeta { version, status: BidStatus::Approved, ..Default::default() },
+            shots,
+            groups: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let approved = version(2, vec![shot("1", 10.0), shot("2", 20.0), shot("3", 5.0)]);
+        let mut third = shot("3", 5.0);
+        third.description = "Wider plate".to_string();
+        let draft = version(3, vec![shot("1", 10.0), shot("2", 25.0), third, shot("4", 8.0)]);
+
+        let comparison = compare_versions(&approved, &draft);
+
+        assert_eq!((comparison.base_version, comparison.target_version), (2, 3));
+        assert_eq!(comparison.added, vec!["4"]);
+        assert!(comparison.removed.is_empty());
+
+        let changed: Vec<_> = comparison.changed.iter().map(|c| c.shot_id.as_str()).collect();
+        assert_eq!(changed, vec!["2", "3"]);
+        assert!(comparison.changed[0].fields.contains(&"estimated_hours".to_string()));
+        assert_eq!(comparison.changed[1].fields, vec!["description"]);
+        assert!(comparison.changed[0].new_price > comparison.changed[0].old_price);
+
+        let back = compare_versions(&draft, &approved);
+        assert_eq!(back.removed, vec!["4"]);
+        assert!((back.difference + comparison.difference).abs() < 1e-9);
+    }
+}
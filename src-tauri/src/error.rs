@@ -0,0 +1,313 @@
+//! Error type returned by Tauri commands
+//!
+//! Serializes as `{ "kind": "...", "message": "..." }` so the frontend can
+//! tell a stopped sidecar from a bad input or a missing shot.
+
+use serde::Serialize;
+use crate::model_health::{ModelUnavailable, UnavailableReason};
+use crate::sidecar::pool::RPC_BACKPRESSURE_CODE;
+use crate::sidecar::RpcError;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppError {
+    /// The Python sidecar is not running
+    SidecarUnavailable { message: String },
+    /// Something the command depends on (such as the model) isn't ready yet
+    NotReady { message: String },
+    /// No model file is configured, so LLM features are off
+    NoModel { message: String },
+    /// The model that used to load can't be opened; `reason` says why
+    ModelUnavailable { reason: UnavailableReason, path: String, message: String },
+    /// The sidecar answered with a JSON-RPC error
+    RpcFailed { code: i32, message: String },
+    /// A shot, attachment or file doesn't exist
+    NotFound { message: String },
+    /// The request itself is invalid
+    Validation { message: String },
+    /// Reading or writing a file failed
+    Io { message: String },
+    /// Too little free RAM to load the model safely
+    InsufficientMemory { needed_gb: f64, available_gb: f64, message: String },
+    /// The bid is open read-only
+    ReadOnly { message: String },
+    /// The bid's file was changed outside the app and would be overwritten
+    SourceDiverged { message: String },
+    /// A staged chat edit expired or the bid changed since it was previewed
+    StaleAction { message: String },
+    /// The running sidecar doesn't advertise `method`, which needs protocol
+    /// `required`
+    UnsupportedBySidecar { method: String, sidecar_version: Option<String>, required: u32, message: String },
+    /// The sidecar speaks another major protocol version; no calls are made
+    SidecarIncompatible { message: String },
+    /// Too many sidecar calls are already waiting; try again shortly
+    Backpressure { message: String },
+}
+
+impl AppError {
+    pub fn sidecar_unavailable() -> Self {
+        AppError::SidecarUnavailable {
+            message: "Python sidecar is not running. Please restart the application.".to_string(),
+        }
+    }
+
+    pub fn not_ready(message: impl Into<String>) -> Self {
+        AppError::NotReady { message: message.into() }
+    }
+
+    pub fn no_model() -> Self {
+        AppError::NoModel {
+            message: "No model configured. Choose a model file in Settings to process scripts and chat; \
+                      existing bids can still be opened and edited."
+                .to_string(),
+        }
+    }
+
+    pub fn model_unavailable(unavailable: ModelUnavailable) -> Self {
+        AppError::ModelUnavailable {
+            reason: unavailable.reason,
+            path: unavailable.path,
+            message: unavailable.message,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound { message: message.into() }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation { message: message.into() }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        AppError::Io { message: message.into() }
+    }
+
+    pub fn insufficient_memory(needed_gb: f64, available_gb: f64) -> Self {
+        AppError::InsufficientMemory {
+            needed_gb,
+            available_gb,
+            message: format!(
+                "Loading the model needs about {:.1} GB of free memory, but only {:.1} GB is available. \
+                 Close other apps or choose a smaller model.",
+                needed_gb, available_gb
+            ),
+        }
+    }
+
+    pub fn read_only(message: impl Into<String>) -> Self {
+        AppError::ReadOnly { message: message.into() }
+    }
+
+    pub fn source_diverged(message: impl Into<String>) -> Self {
+        AppError::SourceDiverged { message: message.into() }
+    }
+
+    pub fn stale_action(message: impl Into<String>) -> Self {
+        AppError::StaleAction { message: message.into() }
+    }
+
+    pub fn unsupported_by_sidecar(method: &str, sidecar_version: Option<String>, required: u32) -> Self {
+        AppError::UnsupportedBySidecar {
+            message: format!(
+                "The Python sidecar ({}) doesn't support {}, which needs protocol {}. Update the sidecar to use this feature.",
+                sidecar_version.as_deref().map_or("an older version".to_string(), |v| format!("version {}", v)),
+                method,
+                required,
+            ),
+            method: method.to_string(),
+            sidecar_version,
+            required,
+        }
+    }
+
+    pub fn sidecar_incompatible(message: impl Into<String>) -> Self {
+        AppError::SidecarIncompatible { message: message.into() }
+    }
+
+    /// Human-readable description, without the kind
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::SidecarUnavailable { message }
+            | AppError::NotReady { message }
+            | AppError::NoModel { message }
+            | AppError::ModelUnavailable { message, .. }
+            | AppError::RpcFailed { message, .. }
+            | AppError::NotFound { message }
+            | AppError::Validation { message }
+            | AppError::Io { message }
+            | AppError::InsufficientMemory { message, .. }
+            | AppError::ReadOnly { message }
+            | AppError::SourceDiverged { message }
+            | AppError::StaleAction { message }
+            | AppError::UnsupportedBySidecar { message, .. }
+            | AppError::SidecarIncompatible { message }
+            | AppError::Backpressure { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::RpcFailed { code, message } => write!(f, "RPC error ({}): {}", code, message),
+            other => f.write_str(other.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Keeps callers that still work with `String` errors compiling
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<RpcError> for AppError {
+    fn from(error: RpcError) -> Self {
+        if error.code == RPC_BACKPRESSURE_CODE {
+            return AppError::Backpressure { message: error.message };
+        }
+        AppError::RpcFailed {
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// Tauri errors (failed emits, path resolution) are I/O from the caller's view
+impl From<tauri::Error> for AppError {
+    fn from(error: tauri::Error) -> Self {
+        AppError::Io { message: error.to_string() }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io { message: error.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shape(error: AppError) -> serde_json::Value {
+        serde_json::to_value(error).unwrap()
+    }
+
+    #[test]
+    fn test_sidecar_unavailable_shape() {
+        assert_eq!(shape(AppError::sidecar_unavailable()), json!({
+            "kind": "sidecar_unavailable",
+            "message": "Python sidecar is not running. Please restart the application.",
+        }));
+    }
+
+    #[test]
+    fn test_not_ready_shape() {
+        assert_eq!(shape(AppError::not_ready("Model loading")), json!({
+            "kind": "not_ready",
+            "message": "Model loading",
+        }));
+    }
+
+    #[test]
+    fn test_rpc_failed_shape() {
+        let error = AppError::from(RpcError {
+            code: -32602,
+            message: "Invalid params".to_string(),
+            data: Some(json!({"field": "path"})),
+        });
+
+        assert_eq!(shape(error.clone()), json!({
+            "kind": "rpc_failed",
+            "code": -32602,
+            "message": "Invalid params",
+        }));
+        assert_eq!(String::from(error), "RPC error (-32602): Invalid params");
+    }
+
+    #[test]
+    fn test_backpressure_shape() {
+        let error = AppError::from(RpcError {
+            code: RPC_BACKPRESSURE_CODE,
+            message: "RPC method 'bid_query' was turned away".to_string(),
+            data: None,
+        });
+
+        assert_eq!(shape(error), json!({
+            "kind": "backpressure",
+            "message": "RPC method 'bid_query' was turned away",
+        }));
+    }
+
+    #[test]
+    fn test_not_found_shape() {
+        assert_eq!(shape(AppError::not_found("Shot SH010 not found")), json!({
+            "kind": "not_found",
+            "message": "Shot SH010 not found",
+        }));
+    }
+
+    #[test]
+    fn test_validation_shape() {
+        assert_eq!(shape(AppError::validation("Threshold must be between 0 and 1")), json!({
+            "kind": "validation",
+            "message": "Threshold must be between 0 and 1",
+        }));
+    }
+
+    #[test]
+    fn test_io_shape() {
+        let error = AppError::from(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied"));
+
+        assert_eq!(shape(error.clone()), json!({
+            "kind": "io",
+            "message": "denied",
+        }));
+        assert_eq!(String::from(error), "denied");
+    }
+
+    #[test]
+    fn test_insufficient_memory_shape() {
+        assert_eq!(shape(AppError::insufficient_memory(8.1, 3.04)), json!({
+            "kind": "insufficient_memory",
+            "needed_gb": 8.1,
+            "available_gb": 3.04,
+            "message": "Loading the model needs about 8.1 GB of free memory, but only 3.0 GB is available. \
+                        Close other apps or choose a smaller model.",
+        }));
+    }
+
+    #[test]
+    fn test_model_unavailable_shape() {
+        let unavailable = ModelUnavailable {
+            path: "/Volumes/FastSSD/model.gguf".to_string(),
+            reason: UnavailableReason::VolumeUnmounted,
+            volume: Some("/Volumes/FastSSD".to_string()),
+            message: "The model is on /Volumes/FastSSD, which isn't mounted.".to_string(),
+        };
+        assert_eq!(shape(AppError::model_unavailable(unavailable)), json!({
+            "kind": "model_unavailable",
+            "reason": "volume_unmounted",
+            "path": "/Volumes/FastSSD/model.gguf",
+            "message": "The model is on /Volumes/FastSSD, which isn't mounted.",
+        }));
+    }
+
+    #[test]
+    fn test_unsupported_by_sidecar_shape() {
+        assert_eq!(shape(AppError::unsupported_by_sidecar("model_info", None, 1)), json!({
+            "kind": "unsupported_by_sidecar",
+            "method": "model_info",
+            "sidecar_version": null,
+            "required": 1,
+            "message": "The Python sidecar (an older version) doesn't support model_info, which needs protocol 1. \
+                        Update the sidecar to use this feature.",
+        }));
+    }
+}
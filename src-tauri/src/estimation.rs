@@ -0,0 +1,182 @@
+//! Default hours for shots left blank
+//!
+//! New bidders leave hours empty when unsure, and a bid with blank hours has
+//! meaningless totals. The pricing settings carry a typical hour range per
+//! complexity tier, optionally narrowed to a VFX type ("Medium" greenscreen
+//! comps run 24–40 hours). `estimate_missing_hours` fills blanks from it and
+//! marks the shot `estimated_by: table` until someone enters hours by hand,
+//! so the totals can say how much of the bid rests on the table.
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::bid::{total_final_price, PriceRounding, ShotData};
+
+/// Where in the range a filled-in estimate lands
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateStrategy {
+    #[default]
+    Midpoint,
+    Low,
+    High,
+}
+
+/// Where a shot's hours came from when they weren't entered by hand
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimatedBy {
+    /// Filled from the hour ranges in the pricing settings
+    Table,
+}
+
+/// Typical hours for shots of one complexity tier, and of one VFX type if set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct HourRange {
+    pub complexity: String,
+    /// Narrows the range to shots with this VFX type, e.g. "greenscreen"
+    #[serde(default)]
+    pub vfx_type: Option<String>,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl HourRange {
+    pub fn hours(&self, strategy: EstimateStrategy) -> f64 {
+        match strategy {
+            EstimateStrategy::Midpoint => (self.low + self.high) / 2.0,
+            EstimateStrategy::Low => self.low,
+            EstimateStrategy::High => self.high,
+        }
+    }
+}
+
+pub fn default_hour_ranges() -> Vec<HourRange> {
+    let range = |complexity: &str, vfx_type: Option<&str>, low: f64, high: f64| HourRange {
+        complexity: complexity.to_string(),
+        vfx_type: vfx_type.map(str::to_string),
+        low,
+        high,
+    };
+
+    vec![
+        range("Low", None, 4.0, 12.0),
+        range("Medium", None, 16.0, 40.0),
+        range("Medium", Some("greenscreen"), 24.0, 40.0),
+        range("High", None, 40.0, 120.0),
+    ]
+}
+
+/// Ranges must be finite, non-negative and low to high, and name a tier
+pub fn validate_hour_ranges(ranges: &[HourRange]) -> Result<(), String> {
+    for range in ranges {
+        if range.complexity.trim().is_empty() {
+            return Err("Every hour range needs a complexity tier".to_string());
+        }
+        if !(range.low.is_finite() && range.high.is_finite()) || range.low < 0.0 || range.high < range.low {
+            return Err(format!(
+                "Hour range for {} must run from zero or more up to at least its low end, got {}–{}",
+                range.complexity, range.low, range.high
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Range for `shot`: one for its tier and one of its VFX types if there is
+/// one, else the tier's own. Matching ignores case.
+pub fn find_range<'a>(ranges: &'a [HourRange], shot: &ShotData) -> Option<&'a HourRange> {
+    let tier = |range: &&HourRange| range.complexity.trim().eq_ignore_ascii_case(shot.complexity.trim());
+    let has_type = |vfx_type: &str| shot.vfx_types.iter().any(|t| t.trim().eq_ignore_ascii_case(vfx_type.trim()));
+
+    ranges.iter()
+        .filter(tier)
+        .find(|range| range.vfx_type.as_deref().is_some_and(has_type))
+        .or_else(|| ranges.iter().filter(tier).find(|range| range.vfx_type.is_none()))
+}
+
+/// How much of a bid rests on table estimates rather than entered hours
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct EstimateShare {
+    pub table_hours: f64,
+    pub manual_hours: f64,
+    /// Final prices of table-estimated shots, rounded like the grand total
+    pub table_total: f64,
+    pub manual_total: f64,
+}
+
+pub fn estimate_share(shots: &[ShotData], rounding: PriceRounding) -> EstimateShare {
+    let (table, manual): (Vec<ShotData>, Vec<ShotData>) = shots.iter()
+        .filter(|shot| shot.estimated_hours.is_some())
+        .cloned()
+        .partition(|shot| shot.estimated_by == Some(EstimatedBy::Table));
+    let hours = |shots: &[ShotData]| shots.iter().filter_map(|s| s.estimated_hours).sum();
+
+    EstimateShare {
+        table_hours: hours(&table),
+        manual_hours: hours(&manual),
+        table_total: total_final_price(&table, rounding),
+        manual_total: total_final_price(&manual, rounding),
+    }
+}
+
+/// Result of `estimate_missing_hours`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MissingHoursEstimate {
+    /// Shots that got hours from the table
+    pub filled: usize,
+    pub grand_total: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(complexity: &str, vfx_types: &[&str]) -> ShotData {
+        ShotData {
+            complexity: complexity.to_string(),
+            vfx_types: vfx_types.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_range_lookup_prefers_vfx_type() {
+        let ranges = default_hour_ranges();
+        let range = find_range(&ranges, &shot("medium", &["Greenscreen", "roto"])).unwrap();
+        assert_eq!((range.low, range.high), (24.0, 40.0));
+        assert_eq!(find_range(&ranges, &shot("Medium", &["cleanup"])).unwrap().low, 16.0);
+        assert!(find_range(&ranges, &shot("Extreme", &[])).is_none());
+
+        assert_eq!(range.hours(EstimateStrategy::Midpoint), 32.0);
+        assert_eq!(range.hours(EstimateStrategy::Low), 24.0);
+        assert_eq!(range.hours(EstimateStrategy::High), 40.0);
+    }
+
+    #[test]
+    fn test_invalid_ranges_are_rejected() {
+        assert!(validate_hour_ranges(&default_hour_ranges()).is_ok());
+        let backwards = HourRange { complexity: "Low".to_string(), vfx_type: None, low: 10.0, high: 5.0 };
+        assert!(validate_hour_ranges(&[backwards]).is_err());
+        let unnamed = HourRange { complexity: " ".to_string(), vfx_type: None, low: 1.0, high: 5.0 };
+        assert!(validate_hour_ranges(&[unnamed]).is_err());
+    }
+
+    #[test]
+    fn test_share_splits_table_and_manual() {
+        let priced = |hours: f64, price: f64, estimated_by| ShotData {
+            estimated_hours: Some(hours),
+            final_price: Some(price),
+            estimated_by,
+            ..Default::default()
+        };
+        let shots = vec![
+            priced(32.0, 3200.0, Some(EstimatedBy::Table)),
+            priced(10.0, 1000.0, None),
+            priced(8.0, 800.5, None),
+            ShotData::default(),
+        ];
+
+        let share = estimate_share(&shots, PriceRounding::None);
+        assert_eq!(share, EstimateShare { table_hours: 32.0, manual_hours: 18.0, table_total: 3200.0, manual_total: 1800.5 });
+    }
+}
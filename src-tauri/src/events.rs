@@ -0,0 +1,476 @@
+//! Events sent to the frontend, with their payloads
+//!
+//! Every event goes out through `emit_event` as an `AppEvent`, which knows
+//! its name, so names and payload shapes live in this one table.
+//! `get_event_schema` describes each event's payload as JSON Schema for the
+//! frontend's generated types. Sidecar notifications are forwarded under
+//! their kebab-case names; the ones raised in Rust are typed here too.
+
+use std::borrow::Cow;
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Runtime};
+use crate::commands::bid::{BidStatus, BidTotals, ShotData};
+use crate::commands::chat::{ChatMessage, CommandRequest, PendingAction};
+use crate::commands::script::ScriptAnalysis;
+use crate::model_health::ModelUnavailable;
+use crate::script_cache::CachedResult;
+use crate::setup_wizard::SetupProgress;
+use crate::sidecar::metrics::SlowCall;
+use crate::sidecar::rpc::ProgressEvent;
+use crate::source_sync::SourceStatus;
+use crate::state::bid::{ReadOnlyStatus, ShotsReconciled};
+use crate::state::session::SessionSnapshot;
+use crate::state::sidecar::ModelState;
+use crate::workspace::PrunePlan;
+
+/// Payload of `bid-autosave-available`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct AutosaveAvailable {
+    pub saved_at: String,
+    pub shot_count: usize,
+}
+
+/// Payload of `bid-expired`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct BidExpired {
+    pub project_name: Option<String>,
+    pub status: BidStatus,
+    pub valid_until: Option<NaiveDate>,
+}
+
+/// Payload of `bid-window-close-requested`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct WindowCloseRequested {
+    /// The window's own bid
+    #[serde(flatten)]
+    pub status: ReadOnlyStatus,
+    /// Project tabs of the window with unsaved changes
+    pub dirty_projects: Vec<String>,
+}
+
+/// Payload of `insufficient-memory`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct InsufficientMemory {
+    pub model: String,
+    pub needed_gb: f64,
+    pub available_gb: f64,
+    /// Catalog models that would fit, smallest first
+    pub smaller_models: Vec<String>,
+    pub suggestion: String,
+}
+
+/// Payload of `model-loading` as the app sends it; the sidecar reports
+/// further progress under the same name
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ModelLoading {
+    pub model: String,
+    pub percent: u8,
+}
+
+/// Payload of `model-corrupt`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ModelCorrupt {
+    pub path: String,
+    /// SHA-256 recorded in the settings
+    pub expected: String,
+    pub actual: String,
+    pub message: String,
+}
+
+/// Payload of `script-hash-progress`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ScriptHashProgress {
+    pub file_path: String,
+    pub bytes_hashed: u64,
+    pub total_bytes: u64,
+}
+
+/// Data of the sidecar's `model_locked` notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ModelLocked {
+    pub message: String,
+    /// The load error
+    pub detail: String,
+}
+
+/// Data of the sidecar's `sidecar_incompatible` notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SidecarIncompatible {
+    pub protocol_version: u32,
+    pub app_protocol_version: u32,
+    pub sidecar_version: Option<String>,
+    pub message: String,
+}
+
+/// Data of the sidecar's `sidecar_protocol_degraded` notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ProtocolDegraded {
+    /// Unparseable messages in a row
+    pub unparseable: usize,
+    /// Whether the sidecar marks its messages
+    pub framed: bool,
+}
+
+/// Name, description and payload schema of one event
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EventSchema {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema (draft 7) of the payload
+    pub payload: Value,
+    /// Raised by the sidecar and forwarded
+    pub forwarded: bool,
+}
+
+macro_rules! app_events {
+    ($($(#[doc = $doc:literal])+ $variant:ident($payload:ty) => $name:literal,)+) => {
+        /// An event for the frontend, carrying its payload
+        // Built and sent straight away, never stored
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Debug, Clone)]
+        pub enum AppEvent {
+            $($(#[doc = $doc])+ $variant($payload),)+
+            /// A sidecar notification, forwarded with its name in kebab-case
+            Sidecar(ProgressEvent),
+        }
+
+        impl AppEvent {
+            pub fn name(&self) -> Cow<'static, str> {
+                match self {
+                    $(Self::$variant(_) => Cow::Borrowed($name),)+
+                    Self::Sidecar(event) => Cow::Owned(event.event.replace('_', "-")),
+                }
+            }
+        }
+
+        impl Serialize for AppEvent {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    $(Self::$variant(payload) => payload.serialize(serializer),)+
+                    Self::Sidecar(event) => event.data.serialize(serializer),
+                }
+            }
+        }
+
+        fn app_event_schemas() -> Vec<EventSchema> {
+            vec![$(schema_of::<$payload>($name, &[$($doc),+], false),)+]
+        }
+    };
+}
+
+app_events! {
+    /// The setup wizard started
+    SetupStarted(()) => "setup-started",
+    /// A setup step moved on
+    SetupProgress(SetupProgress) => "setup-progress",
+    /// Setup finished
+    SetupComplete(()) => "setup-complete",
+    /// At launch: the previous session can be restored
+    SessionRestoreAvailable(SessionSnapshot) => "session-restore-available",
+    /// At launch: a crash left an autosaved bid
+    BidAutosaveAvailable(AutosaveAvailable) => "bid-autosave-available",
+    /// Workspaces past the retention period, to confirm before deleting
+    WorkspacesPrunePending(PrunePlan) => "workspaces-prune-pending",
+    /// The bid became editable or read-only
+    BidReadonlyChanged(ReadOnlyStatus) => "bid-readonly-changed",
+    /// The bid changed; carries its new totals
+    BidChanged(BidTotals) => "bid-changed",
+    /// An opened project's quote is past its validity date
+    BidExpired(BidExpired) => "bid-expired",
+    /// The window now shows another project; carries its id
+    ActiveProjectChanged(String) => "active-project-changed",
+    /// A window with unsaved changes, in its own bid or a project tab, was
+    /// asked to close
+    BidWindowCloseRequested(WindowCloseRequested) => "bid-window-close-requested",
+    /// The model file can't be opened
+    ModelUnavailable(ModelUnavailable) => "model-unavailable",
+    /// The model won't fit in free memory
+    InsufficientMemory(InsufficientMemory) => "insufficient-memory",
+    /// The model started loading
+    ModelLoading(ModelLoading) => "model-loading",
+    /// The model is loaded
+    ModelReady(ModelState) => "model-ready",
+    /// The model file doesn't match its recorded checksum
+    ModelCorrupt(ModelCorrupt) => "model-corrupt",
+    /// A message was added to the chat
+    ChatMessage(ChatMessage) => "chat-message",
+    /// A chat edit waits for the user to confirm it
+    ChatActionPending(PendingAction) => "chat-action-pending",
+    /// A command went to the sidecar
+    CommandExecuting(CommandRequest) => "command-executing",
+    /// A command finished; carries its explanation
+    CommandComplete(String) => "command-complete",
+    /// Script processing started; carries the script path
+    ScriptProcessingStart(String) => "script-processing-start",
+    /// Hashing a large script moved on
+    ScriptHashProgress(ScriptHashProgress) => "script-hash-progress",
+    /// The script was processed before and its earlier bid was reopened
+    ScriptResultCached(CachedResult) => "script-result-cached",
+    /// Script processing finished
+    ScriptProcessingComplete(ScriptAnalysis) => "script-processing-complete",
+    /// Script processing was cancelled; carries the script path
+    ScriptProcessingCancelled(String) => "script-processing-cancelled",
+    /// A provisional shot arrived while a script is processed
+    ShotExtracted(ShotData) => "shot-extracted",
+    /// The final shot list replaced the provisional shots
+    ShotsReconciled(ShotsReconciled) => "shots-reconciled",
+    /// The bid's file changed on disk or was deleted
+    SourceFileDiverged(SourceStatus) => "source-file-diverged",
+}
+
+/// Send `event` to every window
+pub fn emit_event<R: Runtime>(emitter: &impl Emitter<R>, event: AppEvent) -> tauri::Result<()> {
+    emitter.emit(&event.name(), &event)
+}
+
+/// Send `event` to the window labelled `target` only
+pub fn emit_event_to<R: Runtime>(emitter: &impl Emitter<R>, target: &str, event: AppEvent) -> tauri::Result<()> {
+    emitter.emit_to(target, &event.name(), &event)
+}
+
+/// Every event the app sends, with its payload schema
+///
+/// Sidecar notifications the app doesn't raise itself, like the sidecar's
+/// own `model-loading` progress, aren't listed.
+pub fn event_schemas() -> Vec<EventSchema> {
+    let mut schemas = app_event_schemas();
+    schemas.extend([
+        schema_of::<ModelLocked>("model-locked", &[" The model file is held by another process"], true),
+        schema_of::<SidecarIncompatible>("sidecar-incompatible", &[" The sidecar speaks another protocol version"], true),
+        schema_of::<ProtocolDegraded>("sidecar-protocol-degraded", &[" The sidecar's output stopped parsing"], true),
+        schema_of::<SlowCall>("slow-operation", &[" A sidecar call is running much longer than usual"], true),
+    ]);
+    schemas
+}
+
+fn schema_of<T: JsonSchema>(name: &str, doc: &[&str], forwarded: bool) -> EventSchema {
+    let schema = schemars::gen::SchemaSettings::draft07().into_generator().into_root_schema_for::<T>();
+    EventSchema {
+        name: name.to_string(),
+        description: doc.iter().map(|line| line.trim()).collect::<Vec<_>>().join(" "),
+        payload: serde_json::to_value(schema).unwrap_or_default(),
+        forwarded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::setup_wizard::SetupStep;
+
+    fn keys(event: &AppEvent) -> Vec<String> {
+        let mut keys: Vec<String> = serde_json::to_value(event).unwrap().as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Sorted field names of an object payload, or the payload itself
+    fn shape(event: &AppEvent) -> Value {
+        match serde_json::to_value(event).unwrap() {
+            Value::Object(_) => json!(keys(event)),
+            other => other,
+        }
+    }
+
+    #[test]
+    fn test_payloads_keep_the_field_names_the_frontend_reads() {
+        let progress = AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "Installing...", 30));
+        assert_eq!(progress.name(), "setup-progress");
+        assert_eq!(serde_json::to_value(&progress).unwrap(), json!({
+            "step": "InstallDependencies", "message": "Installing...", "percent": 30,
+        }));
+        let download = SetupProgress { bytes_per_sec: Some(1e6), eta_seconds: Some(20), ..SetupProgress::new(SetupStep::DownloadModel, "", 40) };
+        assert_eq!(keys(&AppEvent::SetupProgress(download)), ["bytes_per_sec", "eta_seconds", "message", "percent", "step"]);
+        assert_eq!(
+            keys(&AppEvent::SetupProgress(SetupProgress::new(SetupStep::DownloadModel, "", 55).offline())),
+            ["message", "offline", "percent", "step"]
+        );
+        assert_eq!(serde_json::to_value(AppEvent::SetupComplete(())).unwrap(), Value::Null);
+
+        let expired = AppEvent::BidExpired(BidExpired {
+            project_name: Some("Pilot".to_string()),
+            status: BidStatus::Sent,
+            valid_until: NaiveDate::from_ymd_opt(2026, 9, 30),
+        });
+        assert_eq!(serde_json::to_value(&expired).unwrap(), json!({
+            "project_name": "Pilot", "status": "sent", "valid_until": "2026-09-30",
+        }));
+        assert_eq!(keys(&AppEvent::InsufficientMemory(InsufficientMemory {
+            model: "m.gguf".to_string(), needed_gb: 9.0, available_gb: 4.0, smaller_models: vec![], suggestion: String::new(),
+        })), ["available_gb", "model", "needed_gb", "smaller_models", "suggestion"]);
+        assert_eq!(keys(&AppEvent::ModelCorrupt(ModelCorrupt {
+            path: String::new(), expected: String::new(), actual: String::new(), message: String::new(),
+        })), ["actual", "expected", "message", "path"]);
+        assert_eq!(keys(&AppEvent::BidReadonlyChanged(ReadOnlyStatus::default())), ["dirty", "project_read_only", "read_only"]);
+        assert_eq!(keys(&AppEvent::ShotsReconciled(ShotsReconciled::default())), ["added", "confirmed", "removed"]);
+        assert_eq!(
+            serde_json::to_value(AppEvent::ModelReady(ModelState::Loaded { name: "m.gguf".to_string(), ctx: 4096 })).unwrap(),
+            json!({ "state": "Loaded", "name": "m.gguf", "ctx": 4096 })
+        );
+        assert_eq!(serde_json::to_value(AppEvent::ActiveProjectChanged("project-1".to_string())).unwrap(), json!("project-1"));
+
+        let forwarded = AppEvent::Sidecar(ProgressEvent { event: "slow_operation".to_string(), data: json!({ "method": "chat" }) });
+        assert_eq!(forwarded.name(), "slow-operation");
+        assert_eq!(serde_json::to_value(&forwarded).unwrap(), json!({ "method": "chat" }));
+    }
+
+    #[test]
+    fn test_every_event_payload_keeps_its_shape() {
+        use crate::commands::chat::{preview_action, LocalAction};
+        use crate::commands::script::ScriptMetadata;
+        use crate::model_health::UnavailableReason;
+        use crate::state::{BidState, SessionState};
+        use crate::workspace::WorkspaceInfo;
+
+        let bid_state = BidState::default();
+        bid_state.set_shots(vec![ShotData {
+            id: "SH010".to_string(),
+            scene_number: "12".to_string(),
+            estimated_hours: Some(10.0),
+            rate_per_hour: Some(100.0),
+            final_price: Some(1000.0),
+            ..Default::default()
+        }]).unwrap();
+        let pending = preview_action(LocalAction::AdjustPrice { scene: None, percent: 10.0 }, None, &bid_state).unwrap();
+
+        let events = vec![
+            AppEvent::SetupStarted(()),
+            AppEvent::SetupProgress(SetupProgress::new(SetupStep::InstallDependencies, "", 30)),
+            AppEvent::SetupComplete(()),
+            AppEvent::SessionRestoreAvailable(SessionSnapshot::default()),
+            AppEvent::BidAutosaveAvailable(AutosaveAvailable { saved_at: String::new(), shot_count: 3 }),
+            AppEvent::WorkspacesPrunePending(PrunePlan {
+                retention_days: 90,
+                workspaces: vec![WorkspaceInfo { id: String::new(), path: String::new(), bytes: 0, last_used: None }],
+                total_bytes: 0,
+            }),
+            AppEvent::BidReadonlyChanged(ReadOnlyStatus::default()),
+            AppEvent::BidChanged(crate::commands::bid::get_bid_totals_internal(&bid_state, &SessionState::default())),
+            AppEvent::BidExpired(BidExpired { project_name: None, status: BidStatus::Sent, valid_until: None }),
+            AppEvent::ActiveProjectChanged("project-1".to_string()),
+            AppEvent::BidWindowCloseRequested(WindowCloseRequested { status: ReadOnlyStatus::default(), dirty_projects: vec![] }),
+            AppEvent::ModelUnavailable(ModelUnavailable {
+                path: String::new(), reason: UnavailableReason::VolumeUnmounted, volume: None, message: String::new(),
+            }),
+            AppEvent::InsufficientMemory(InsufficientMemory {
+                model: String::new(), needed_gb: 9.0, available_gb: 4.0, smaller_models: vec![], suggestion: String::new(),
+            }),
+            AppEvent::ModelLoading(ModelLoading { model: String::new(), percent: 40 }),
+            AppEvent::ModelReady(ModelState::Loaded { name: String::new(), ctx: 4096 }),
+            AppEvent::ModelCorrupt(ModelCorrupt {
+                path: String::new(), expected: String::new(), actual: String::new(), message: String::new(),
+            }),
+            AppEvent::ChatMessage(ChatMessage { role: "assistant".to_string(), content: String::new(), timestamp: 0, action: None }),
+            AppEvent::ChatActionPending(pending),
+            AppEvent::CommandExecuting(CommandRequest { command: String::new(), args: vec![] }),
+            AppEvent::CommandComplete("Done".to_string()),
+            AppEvent::ScriptProcessingStart("script.pdf".to_string()),
+            AppEvent::ScriptHashProgress(ScriptHashProgress { file_path: String::new(), bytes_hashed: 0, total_bytes: 0 }),
+            AppEvent::ScriptResultCached(CachedResult {
+                hash: String::new(), script_path: String::new(), excel_path: String::new(), shot_count: 0, processed_at: String::new(),
+            }),
+            AppEvent::ScriptProcessingComplete(ScriptAnalysis {
+                shots: vec![],
+                metadata: ScriptMetadata { title: None, total_shots: 0, vfx_categories: vec![], total_frames: 0 },
+                scenes: vec![],
+                source: Default::default(),
+                output_path: None,
+                detected_scenes: None,
+                unpriced_shots: vec![],
+                warnings: vec![],
+                cached: false,
+            }),
+            AppEvent::ScriptProcessingCancelled("script.pdf".to_string()),
+            AppEvent::ShotExtracted(ShotData::default()),
+            AppEvent::ShotsReconciled(ShotsReconciled::default()),
+            AppEvent::SourceFileDiverged(SourceStatus::Missing { path: String::new(), recorded_modified_at: None }),
+        ];
+        let expected = [
+            ("setup-started", json!(null)),
+            ("setup-progress", json!(["message", "percent", "step"])),
+            ("setup-complete", json!(null)),
+            ("session-restore-available", json!([
+                "chat_history", "chat_panel_open", "current_script", "last_bid_path",
+                "last_generated_bid", "last_output_dir", "selected_shot_id", "settings"
+            ])),
+            ("bid-autosave-available", json!(["saved_at", "shot_count"])),
+            ("workspaces-prune-pending", json!(["retention_days", "total_bytes", "workspaces"])),
+            ("bid-readonly-changed", json!(["dirty", "project_read_only", "read_only"])),
+            ("bid-changed", json!([
+                "adjustments", "currency", "departments", "display", "estimate_share", "expired",
+                "grand_total", "precise_grand_total", "rounding", "shot_count", "status", "total_cost",
+                "total_frames", "total_hours", "valid_until", "version"
+            ])),
+            ("bid-expired", json!(["project_name", "status", "valid_until"])),
+            ("active-project-changed", json!("project-1")),
+            ("bid-window-close-requested", json!(["dirty", "dirty_projects", "project_read_only", "read_only"])),
+            ("model-unavailable", json!(["message", "path", "reason", "volume"])),
+            ("insufficient-memory", json!(["available_gb", "model", "needed_gb", "smaller_models", "suggestion"])),
+            ("model-loading", json!(["model", "percent"])),
+            ("model-ready", json!(["ctx", "name", "state"])),
+            ("model-corrupt", json!(["actual", "expected", "message", "path"])),
+            ("chat-message", json!(["content", "role", "timestamp"])),
+            ("chat-action-pending", json!(["affected_shot_ids", "description", "expires_at", "id", "preview_diff"])),
+            ("command-executing", json!(["args", "command"])),
+            ("command-complete", json!("Done")),
+            ("script-processing-start", json!("script.pdf")),
+            ("script-hash-progress", json!(["bytes_hashed", "file_path", "total_bytes"])),
+            ("script-result-cached", json!(["excel_path", "hash", "processed_at", "script_path", "shot_count"])),
+            ("script-processing-complete", json!([
+                "cached", "detected_scenes", "metadata", "output_path", "scenes", "shots", "source",
+                "unpriced_shots", "warnings"
+            ])),
+            ("script-processing-cancelled", json!("script.pdf")),
+            ("shot-extracted", json!([
+                "adjustment", "attachments", "complexity", "confidence", "contingency_percent",
+                "description", "estimated_by", "estimated_cost", "estimated_hours", "extraction_flags",
+                "final_price", "fps", "frame_count", "frame_in", "frame_out", "id", "overhead_percent",
+                "price_locked", "provisional", "rate_per_hour", "review", "scene_number", "sort_index",
+                "tags", "tasks", "vfx_types"
+            ])),
+            ("shots-reconciled", json!(["added", "confirmed", "removed"])),
+            ("source-file-diverged", json!(["path", "recorded_modified_at", "status"])),
+        ];
+        let shapes: Vec<(String, Value)> = events.iter().map(|event| (event.name().into_owned(), shape(event))).collect();
+        assert_eq!(shapes, expected.map(|(name, shape)| (name.to_string(), shape)));
+
+        // Every variant is pinned above, in table order
+        let names: Vec<String> = app_event_schemas().into_iter().map(|schema| schema.name).collect();
+        assert_eq!(names, shapes.into_iter().map(|(name, _)| name).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_every_event_has_a_schema() {
+        let schemas = event_schemas();
+        let mut names: Vec<&str> = schemas.iter().map(|s| s.name.as_str()).collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count, "duplicate event names");
+        assert!(names.iter().all(|name| name.chars().all(|c| c.is_ascii_lowercase() || c == '-')));
+
+        let progress = schemas.iter().find(|s| s.name == "setup-progress").unwrap();
+        assert_eq!(progress.description, "A setup step moved on");
+        let properties = progress.payload["properties"].as_object().unwrap();
+        for field in ["step", "message", "percent", "bytes_per_sec", "eta_seconds", "retry_attempt", "max_attempts", "offline", "errors"] {
+            assert!(properties.contains_key(field), "setup-progress schema lacks {}", field);
+        }
+        let required: Vec<&str> = progress.payload["required"].as_array().unwrap().iter().filter_map(Value::as_str).collect();
+        assert_eq!(required, ["message", "percent", "step"]);
+
+        let analysis = schemas.iter().find(|s| s.name == "script-processing-complete").unwrap();
+        assert!(analysis.payload["definitions"]["ShotData"].is_object());
+
+        let forwarded: Vec<String> = schemas.iter().filter(|s| s.forwarded).map(|s| s.name.clone()).collect();
+        assert_eq!(forwarded, [
+            crate::sidecar::process::MODEL_LOCKED_EVENT.replace('_', "-"),
+            crate::sidecar::protocol::INCOMPATIBLE_EVENT.replace('_', "-"),
+            crate::sidecar::rpc::PROTOCOL_DEGRADED_EVENT.replace('_', "-"),
+            "slow-operation".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,339 @@
+//! Pure-Rust reader for bid workbooks
+//!
+//! Used when the Python sidecar is unavailable so an existing bid can still be
+//! opened and inspected. Writing bids still goes through the sidecar.
+
+use std::collections::HashMap;
+use std::path::Path;
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use crate::commands::bid::{normalize_tags, ShotData};
+use crate::commands::settings::LocaleSettings;
+use crate::format::parse_locale_number;
+
+/// Sheet the generator writes shots to
+const SHOTS_SHEET: &str = "Shots";
+
+/// Number of rows searched for the header before giving up
+const HEADER_SEARCH_ROWS: usize = 20;
+
+/// Columns of the bid sheet that map onto `ShotData`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Column {
+    Id,
+    Scene,
+    Description,
+    VfxTypes,
+    Complexity,
+    Hours,
+    Rate,
+    Cost,
+    Contingency,
+    Overhead,
+    FinalPrice,
+    FrameIn,
+    FrameOut,
+    FrameCount,
+    Fps,
+    Department,
+    TaskRate,
+    TaskNote,
+    Tags,
+}
+
+impl Column {
+    /// Match a header cell, ignoring case, spacing and punctuation
+    pub(crate) fn from_header(header: &str) -> Option<Self> {
+        let key: String = header
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+
+        let column = match key.as_str() {
+            "shotid" | "shot" | "shotno" | "shotnumber" | "id" => Column::Id,
+            "scene" | "sceneno" | "scenenumber" => Column::Scene,
+            "description" | "shotdescription" | "desc" => Column::Description,
+            "vfxtypes" | "vfxtype" | "vfx" | "types" | "work" => Column::VfxTypes,
+            "complexity" => Column::Complexity,
+            "estimatedhours" | "esthours" | "hours" => Column::Hours,
+            "rateperhour" | "rate" | "hourlyrate" | "ratehr" => Column::Rate,
+            "estimatedcost" | "estcost" | "cost" => Column::Cost,
+            "contingencypercent" | "contingency" => Column::Contingency,
+            "overheadpercent" | "overhead" => Column::Overhead,
+            "finalprice" | "price" | "shottotal" => Column::FinalPrice,
+            "framein" | "cutin" => Column::FrameIn,
+            "frameout" | "cutout" => Column::FrameOut,
+            "framecount" | "frames" => Column::FrameCount,
+            "fps" | "framerate" => Column::Fps,
+            "department" | "dept" => Column::Department,
+            "taskrate" => Column::TaskRate,
+            "tasknote" => Column::TaskNote,
+            "tags" | "tag" => Column::Tags,
+            _ => return None,
+        };
+        Some(column)
+    }
+}
+
+/// Read the shots from a bid workbook without the sidecar
+///
+/// Uses the "Shots" sheet when present, otherwise the first sheet. The header
+/// row is found by its column names, so title rows above it and extra columns
+/// are ignored. Numbers typed as text are read with `locale`'s separators.
+pub fn read_bid_workbook(path: &Path, locale: &LocaleSettings) -> Result<Vec<ShotData>, String> {
+    let mut workbook = open_workbook_auto(path)
+        .map_err(|e| format!("Failed to open workbook: {}", e))?;
+
+    let sheet_names = workbook.sheet_names();
+    let sheet = sheet_names
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(SHOTS_SHEET))
+        .or_else(|| sheet_names.first())
+        .cloned()
+        .ok_or_else(|| "Workbook has no sheets".to_string())?;
+
+    let range = workbook
+        .worksheet_range(&sheet)
+        .map_err(|e| format!("Failed to read sheet '{}': {}", sheet, e))?;
+
+    read_shots(&range, locale)
+}
+
+fn read_shots(range: &Range<Data>, locale: &LocaleSettings) -> Result<Vec<ShotData>, String> {
+    let (header_row, columns) = find_header(range)
+        .ok_or_else(|| "Could not find the shot table header row".to_string())?;
+
+    let mut shots = Vec::new();
+
+    for row in range.rows().skip(header_row + 1) {
+        let text = |column| columns.get(&column).and_then(|&i| cell_text(row.get(i)?));
+        let number = |column| columns.get(&column).and_then(|&i| cell_number(row.get(i)?, locale));
+
+        let id = text(Column::Id);
+        let description = text(Column::Description);
+
+        // Blank separator rows and the totals row at the bottom
+        if id.is_none() && description.is_none() {
+            continue;
+        }
+        if row.iter().filter_map(cell_text).any(|t| is_totals_label(&t)) {
+            continue;
+        }
+
+        let id = id.unwrap_or_else(|| format!("shot-{}", shots.len() + 1));
+        let tags = text(Column::Tags)
+            .map(|t| normalize_tags(&split_types(&t)))
+            .transpose()
+            .map_err(|e| format!("Shot {}: {}", id, e))?
+            .unwrap_or_default();
+
+        let mut shot = ShotData {
+            id,
+            scene_number: text(Column::Scene).unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            vfx_types: text(Column::VfxTypes)
+                .map(|t| split_types(&t))
+                .unwrap_or_default(),
+            complexity: text(Column::Complexity).unwrap_or_default(),
+            estimated_hours: number(Column::Hours),
+            rate_per_hour: number(Column::Rate),
+            estimated_cost: number(Column::Cost),
+            contingency_percent: columns
+                .get(&Column::Contingency)
+                .and_then(|&i| cell_percent(row.get(i)?, locale))
+                .unwrap_or(0.0),
+            overhead_percent: columns
+                .get(&Column::Overhead)
+                .and_then(|&i| cell_percent(row.get(i)?, locale))
+                .unwrap_or(0.0),
+            final_price: number(Column::FinalPrice),
+            frame_in: number(Column::FrameIn).map(|n| n as u32),
+            frame_out: number(Column::FrameOut).map(|n| n as u32),
+            frame_count: number(Column::FrameCount).map(|n| n as u32),
+            fps: number(Column::Fps),
+            attachments: Vec::new(),
+            provisional: false,
+            price_locked: false,
+            sort_index: 0,
+            tasks: Vec::new(),
+            tags,
+            confidence: None,
+            extraction_flags: Vec::new(),
+            review: None,
+            estimated_by: None,
+            adjustment: 0.0,
+        };
+        shot.sync_frame_count();
+        shots.push(shot);
+    }
+
+    Ok(shots)
+}
+
+/// Find the first row that names enough known columns to be the header
+fn find_header(range: &Range<Data>) -> Option<(usize, HashMap<Column, usize>)> {
+    range.rows().take(HEADER_SEARCH_ROWS).enumerate().find_map(|(index, row)| {
+        let mut columns = HashMap::new();
+        for (i, cell) in row.iter().enumerate() {
+            if let Some(column) = cell_text(cell).and_then(|t| Column::from_header(&t)) {
+                columns.entry(column).or_insert(i);
+            }
+        }
+
+        let has_key = columns.contains_key(&Column::Id) || columns.contains_key(&Column::Description);
+        (has_key && columns.len() >= 3).then_some((index, columns))
+    })
+}
+
+fn cell_text(cell: &Data) -> Option<String> {
+    let text = match cell {
+        Data::Empty | Data::Error(_) => return None,
+        Data::String(s) => s.trim().to_string(),
+        Data::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+        other => other.to_string().trim().to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+fn cell_number(cell: &Data, locale: &LocaleSettings) -> Option<f64> {
+    match cell {
+        Data::Float(f) => Some(*f),
+        Data::Int(i) => Some(*i as f64),
+        Data::String(s) => parse_locale_number(s, locale),
+        _ => None,
+    }
+}
+
+/// Percent cells may hold 15, "15%" or 0.15 formatted as a percentage
+///
+/// Only numbers below 1 are read as fractions, so a plain 1 stays 1%.
+fn cell_percent(cell: &Data, locale: &LocaleSettings) -> Option<f64> {
+    match cell {
+        Data::Float(f) if f.abs() < 1.0 => Some(f * 100.0),
+        other => cell_number(other, locale),
+    }
+}
+
+pub(crate) fn split_types(text: &str) -> Vec<String> {
+    text.split([',', ';', '/', '\n'])
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+pub(crate) fn is_totals_label(text: &str) -> bool {
+    matches!(
+        text.to_lowercase().as_str(),
+        "total" | "totals" | "grand total" | "subtotal"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    fn text_number(text: &str, locale: &LocaleSettings) -> Option<f64> {
+        cell_number(&Data::String(text.to_string()), locale)
+    }
+
+    #[test]
+    fn test_parse_number_formats() {
+        let locale = LocaleSettings::default();
+        assert_eq!(text_number("1,250.50", &locale), Some(1250.5));
+        assert_eq!(text_number("$ 4,000", &locale), Some(4000.0));
+        assert_eq!(text_number("€95", &locale), Some(95.0));
+        assert_eq!(text_number("(120.00)", &locale), Some(-120.0));
+        assert_eq!(text_number("15%", &locale), Some(15.0));
+        assert_eq!(text_number("TBD", &locale), None);
+        assert_eq!(text_number("  ", &locale), None);
+
+        // Comma decimals, as typed in a German workbook
+        assert_eq!(text_number("1.234,56", &locale), Some(1234.56));
+        let german = LocaleSettings::for_locale("de-DE");
+        assert_eq!(text_number("1.234,56", &german), Some(1234.56));
+        assert_eq!(text_number("12,5", &german), Some(12.5));
+        assert_eq!(text_number("12,5 %", &german), Some(12.5));
+    }
+
+    #[test]
+    fn test_percent_cells() {
+        let locale = LocaleSettings::default();
+        assert_eq!(cell_percent(&Data::Float(0.15), &locale), Some(15.0));
+        assert_eq!(cell_percent(&Data::Float(1.0), &locale), Some(1.0));
+        assert_eq!(cell_percent(&Data::Int(1), &locale), Some(1.0));
+        assert_eq!(cell_percent(&Data::Float(12.5), &locale), Some(12.5));
+        assert_eq!(cell_percent(&Data::String("15%".to_string()), &locale), Some(15.0));
+    }
+
+    #[test]
+    fn test_header_aliases() {
+        assert_eq!(Column::from_header("Shot ID"), Some(Column::Id));
+        assert_eq!(Column::from_header("Est. Hours"), Some(Column::Hours));
+        assert_eq!(Column::from_header("Contingency %"), Some(Column::Contingency));
+        assert_eq!(Column::from_header("Rate/Hr"), Some(Column::Rate));
+        assert_eq!(Column::from_header("Notes"), None);
+    }
+
+    #[test]
+    fn test_read_generated_bid() {
+        let shots = read_bid_workbook(&fixture("generated_bid.xlsx"), &LocaleSettings::default()).unwrap();
+
+        assert_eq!(shots.len(), 3);
+        let first = &shots[0];
+        assert_eq!(first.id, "SH010");
+        assert_eq!(first.scene_number, "1");
+        assert_eq!(first.vfx_types, vec!["Compositing", "Roto"]);
+        assert_eq!(first.complexity, "Medium");
+        assert_eq!(first.estimated_hours, Some(40.0));
+        assert_eq!(first.rate_per_hour, Some(100.0));
+        assert_eq!(first.estimated_cost, Some(4000.0));
+        assert_eq!(first.contingency_percent, 15.0);
+        assert_eq!(first.overhead_percent, 10.0);
+        assert_eq!(first.final_price, Some(5000.0));
+        assert_eq!(first.frame_count, Some(96));
+    }
+
+    #[test]
+    fn test_read_hand_edited_bid() {
+        let shots = read_bid_workbook(&fixture("hand_edited_bid.xlsx"), &LocaleSettings::default()).unwrap();
+
+        // Title rows, blank rows and the totals row are skipped
+        assert_eq!(shots.len(), 3);
+
+        // Numbers typed as text and currency formatting
+        let first = &shots[0];
+        assert_eq!(first.id, "SH010");
+        assert_eq!(first.estimated_hours, Some(40.0));
+        assert_eq!(first.rate_per_hour, Some(100.0));
+        assert_eq!(first.estimated_cost, Some(4000.0));
+        assert_eq!(first.final_price, Some(5000.0));
+        assert_eq!(first.contingency_percent, 15.0);
+
+        // Blank pricing cells stay unset rather than becoming zero
+        let pending = &shots[1];
+        assert_eq!(pending.estimated_hours, None);
+        assert_eq!(pending.rate_per_hour, Some(95.0));
+        assert_eq!(pending.estimated_cost, None);
+        assert_eq!(pending.final_price, None);
+
+        // Percent-formatted cells stored as fractions
+        let third = &shots[2];
+        assert_eq!(third.contingency_percent, 20.0);
+        assert_eq!(third.overhead_percent, 10.0);
+        assert_eq!(third.final_price, Some(1950.0));
+    }
+
+    #[test]
+    fn test_missing_header_is_an_error() {
+        let range = Range::from_sparse(vec![calamine::Cell::new(
+            (0, 0),
+            Data::String("Just a note".to_string()),
+        )]);
+        assert!(read_shots(&range, &LocaleSettings::default()).is_err());
+    }
+}
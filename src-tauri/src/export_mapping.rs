@@ -0,0 +1,458 @@
+//! Column layouts for exported bid sheets
+//!
+//! Each client studio has its own bid sheet. A mapping says which shot
+//! fields go in which column; the sidecar gets the active one with every
+//! Excel export, and the CSV export writes the same columns in the same
+//! order so the two agree. Mappings the user saves live in
+//! `app_config_dir()/export_mappings.json` next to the built-in presets.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::tempfiles;
+
+/// Preset used until another is chosen; matches the original CSV layout
+pub const DETAILED_PRESET: &str = "Detailed (internal)";
+
+pub const CLIENT_SUMMARY_PRESET: &str = "Client summary";
+
+/// Column names of a ShotGrid shot import, for `export_shot_list`
+pub const SHOTGRID_PRESET: &str = "ShotGrid import";
+
+/// Excel's last column, XFD
+const MAX_COLUMN_INDEX: usize = 16_383;
+
+static STORE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// A `ShotData` field that can be exported, named as in `ShotData`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportField {
+    Id,
+    SceneNumber,
+    Description,
+    VfxTypes,
+    Complexity,
+    EstimatedHours,
+    RatePerHour,
+    EstimatedCost,
+    ContingencyPercent,
+    OverheadPercent,
+    FinalPrice,
+    FrameIn,
+    FrameOut,
+    FrameCount,
+    Fps,
+    Tags,
+}
+
+impl ExportField {
+    /// Fields every mapping has to place
+    pub const REQUIRED: [ExportField; 3] = [ExportField::Id, ExportField::Description, ExportField::FinalPrice];
+
+    /// Header written when the mapping doesn't name one
+    pub fn default_header(self) -> &'static str {
+        match self {
+            ExportField::Id => "Shot ID",
+            ExportField::SceneNumber => "Scene",
+            ExportField::Description => "Description",
+            ExportField::VfxTypes => "VFX Types",
+            ExportField::Complexity => "Complexity",
+            ExportField::EstimatedHours => "Est. Hours",
+            ExportField::RatePerHour => "Rate/Hr",
+            ExportField::EstimatedCost => "Est. Cost",
+            ExportField::ContingencyPercent => "Contingency %",
+            ExportField::OverheadPercent => "Overhead %",
+            ExportField::FinalPrice => "Final Price",
+            ExportField::FrameIn => "Frame In",
+            ExportField::FrameOut => "Frame Out",
+            ExportField::FrameCount => "Frame Count",
+            ExportField::Fps => "FPS",
+            ExportField::Tags => "Tags",
+        }
+    }
+}
+
+/// Where group subtotal rows go in the Excel export
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtotalPlacement {
+    #[default]
+    None,
+    AboveGroup,
+    BelowGroup,
+}
+
+/// One exported column
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ColumnMapping {
+    pub field: ExportField,
+    /// Spreadsheet column, e.g. "C"
+    pub column: String,
+    /// Header text; the field's default header when None
+    #[serde(default)]
+    pub header: Option<String>,
+}
+
+impl ColumnMapping {
+    pub fn header(&self) -> &str {
+        self.header.as_deref().unwrap_or(self.field.default_header())
+    }
+}
+
+/// A studio's bid sheet layout
+///
+/// Fields without a column are left out of the export.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportMapping {
+    pub name: String,
+    pub sheet_name: String,
+    /// 1-based row holding the headers; shots start on the row below
+    pub header_row: u32,
+    pub columns: Vec<ColumnMapping>,
+    #[serde(default)]
+    pub subtotals: SubtotalPlacement,
+}
+
+impl ExportMapping {
+    /// Check the required fields are placed and no column is used twice
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Export mapping needs a name".to_string());
+        }
+        if self.sheet_name.trim().is_empty() {
+            return Err("Export mapping needs a sheet name".to_string());
+        }
+        if self.header_row == 0 {
+            return Err("Header row starts at 1".to_string());
+        }
+
+        let mut columns = HashSet::new();
+        let mut fields = HashSet::new();
+        for mapping in &self.columns {
+            let index = column_index(&mapping.column)
+                .ok_or_else(|| format!("'{}' is not a column letter", mapping.column))?;
+            if !columns.insert(index) {
+                return Err(format!("Column {} is mapped more than once", mapping.column.to_ascii_uppercase()));
+            }
+            if !fields.insert(mapping.field) {
+                return Err(format!("'{}' is mapped more than once", mapping.field.default_header()));
+            }
+        }
+
+        let missing: Vec<&str> = ExportField::REQUIRED
+            .iter()
+            .filter(|field| !fields.contains(*field))
+            .map(|field| field.default_header())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("Export mapping must include {}", missing.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Columns left to right; invalid letters sort last
+    pub fn ordered_columns(&self) -> Vec<&ColumnMapping> {
+        let mut columns: Vec<&ColumnMapping> = self.columns.iter().collect();
+        columns.sort_by_key(|c| column_index(&c.column).unwrap_or(usize::MAX));
+        columns
+    }
+}
+
+impl Default for ExportMapping {
+    fn default() -> Self {
+        detailed_preset()
+    }
+}
+
+/// 0-based index of a column letter such as "A" or "AB"
+pub fn column_index(letters: &str) -> Option<usize> {
+    if letters.is_empty() || letters.len() > 3 || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let index = letters
+        .to_ascii_uppercase()
+        .bytes()
+        .fold(0usize, |index, b| index * 26 + (b - b'A' + 1) as usize)
+        - 1;
+    (index <= MAX_COLUMN_INDEX).then_some(index)
+}
+
+/// Column letter for a 0-based index
+pub fn column_letter(mut index: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+fn columns_in_order(fields: &[(ExportField, Option<&str>)]) -> Vec<ColumnMapping> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, (field, header))| ColumnMapping {
+            field: *field,
+            column: column_letter(i),
+            header: header.map(str::to_string),
+        })
+        .collect()
+}
+
+/// Every costing column, as the bidding team works with it
+pub fn detailed_preset() -> ExportMapping {
+    use ExportField::*;
+
+    ExportMapping {
+        name: DETAILED_PRESET.to_string(),
+        sheet_name: "Shots".to_string(),
+        header_row: 1,
+        columns: columns_in_order(&[
+            (Id, None),
+            (SceneNumber, None),
+            (Description, None),
+            (VfxTypes, None),
+            (Complexity, None),
+            (EstimatedHours, None),
+            (RatePerHour, None),
+            (EstimatedCost, None),
+            (ContingencyPercent, None),
+            (OverheadPercent, None),
+            (FinalPrice, None),
+            (FrameIn, None),
+            (FrameOut, None),
+            (FrameCount, None),
+            (Fps, None),
+        ]),
+        subtotals: SubtotalPlacement::None,
+    }
+}
+
+/// What a client sees: the work and its price, no hours or markups
+pub fn client_summary_preset() -> ExportMapping {
+    use ExportField::*;
+
+    ExportMapping {
+        name: CLIENT_SUMMARY_PRESET.to_string(),
+        sheet_name: "Bid Summary".to_string(),
+        header_row: 1,
+        columns: columns_in_order(&[
+            (Id, Some("Shot")),
+            (SceneNumber, None),
+            (Description, None),
+            (VfxTypes, Some("Work")),
+            (FrameCount, Some("Frames")),
+            (FinalPrice, Some("Price")),
+        ]),
+        subtotals: SubtotalPlacement::BelowGroup,
+    }
+}
+
+/// ShotGrid's shot import columns; a shot list, not a bid sheet, so it has
+/// the bid hours and cost but no final price, and isn't offered for Excel
+/// exports
+pub fn shotgrid_preset() -> ExportMapping {
+    use ExportField::*;
+
+    ExportMapping {
+        name: SHOTGRID_PRESET.to_string(),
+        sheet_name: "Shots".to_string(),
+        header_row: 1,
+        columns: columns_in_order(&[
+            (Id, Some("Shot Code")),
+            (Description, None),
+            (SceneNumber, Some("Sequence")),
+            (EstimatedHours, Some("Bid Hours")),
+            (EstimatedCost, Some("Bid Cost")),
+            (FrameIn, Some("Cut In")),
+            (FrameOut, Some("Cut Out")),
+            (FrameCount, Some("Cut Duration")),
+        ]),
+        subtotals: SubtotalPlacement::None,
+    }
+}
+
+pub fn builtin_mappings() -> Vec<ExportMapping> {
+    vec![detailed_preset(), client_summary_preset()]
+}
+
+/// Contents of `export_mappings.json`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ExportMappingStore {
+    /// Name of the mapping in use; the detailed preset when None
+    pub active: Option<String>,
+    pub saved: Vec<ExportMapping>,
+}
+
+impl ExportMappingStore {
+    pub fn find(&self, name: &str) -> Option<ExportMapping> {
+        builtin_mappings()
+            .into_iter()
+            .chain(self.saved.iter().cloned())
+            .find(|mapping| mapping.name == name)
+    }
+
+    /// The active mapping, falling back to the detailed preset if it was
+    /// deleted from the file
+    pub fn active_mapping(&self) -> ExportMapping {
+        self.active.as_deref().and_then(|name| self.find(name)).unwrap_or_default()
+    }
+
+    /// Add `mapping`, replacing a saved one with the same name
+    pub fn save(&mut self, mapping: ExportMapping) -> Result<(), String> {
+        mapping.validate()?;
+        if builtin_mappings().iter().any(|m| m.name == mapping.name) {
+            return Err(format!("'{}' is a built-in preset; save it under another name", mapping.name));
+        }
+
+        match self.saved.iter_mut().find(|m| m.name == mapping.name) {
+            Some(existing) => *existing = mapping,
+            None => self.saved.push(mapping),
+        }
+        Ok(())
+    }
+
+    pub fn set_active(&mut self, name: &str) -> Result<ExportMapping, String> {
+        let mapping = self.find(name).ok_or_else(|| format!("No export mapping named '{}'", name))?;
+        self.active = Some(mapping.name.clone());
+        Ok(mapping)
+    }
+}
+
+/// Built-in and saved mappings, for the export settings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportMappingList {
+    pub active: String,
+    pub builtin: Vec<ExportMapping>,
+    pub saved: Vec<ExportMapping>,
+}
+
+impl From<&ExportMappingStore> for ExportMappingList {
+    fn from(store: &ExportMappingStore) -> Self {
+        ExportMappingList {
+            active: store.active_mapping().name,
+            builtin: builtin_mappings(),
+            saved: store.saved.clone(),
+        }
+    }
+}
+
+/// Keep mappings in `path`; called once at startup
+pub fn init(path: PathBuf) {
+    let _ = STORE_PATH.set(path);
+}
+
+pub fn store_path() -> Option<&'static Path> {
+    STORE_PATH.get().map(PathBuf::as_path)
+}
+
+/// Read the store at `path`; a missing file is an empty store
+pub fn load_store(path: &Path) -> Result<ExportMappingStore, String> {
+    if !path.exists() {
+        return Ok(ExportMappingStore::default());
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read export mappings: {}", e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse export mappings: {}", e))
+}
+
+pub fn write_store(path: &Path, store: &ExportMappingStore) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(store)
+        .map_err(|e| format!("Failed to serialize export mappings: {}", e))?;
+    tempfiles::write_atomic(path, &json).map_err(|e| format!("Failed to save export mappings: {}", e))
+}
+
+/// Mapping exports use right now
+///
+/// An unreadable store falls back to the detailed preset so exports keep
+/// working; the error is logged.
+pub fn active_mapping() -> ExportMapping {
+    let Some(path) = store_path() else {
+        return ExportMapping::default();
+    };
+    match load_store(path) {
+        Ok(store) => store.active_mapping(),
+        Err(e) => {
+            log::warn!("{}; exporting with '{}'", e, DETAILED_PRESET);
+            ExportMapping::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_letters() {
+        assert_eq!(column_index("A"), Some(0));
+        assert_eq!(column_index("z"), Some(25));
+        assert_eq!(column_index("AA"), Some(26));
+        assert_eq!(column_index("XFD"), Some(MAX_COLUMN_INDEX));
+        assert_eq!(column_index("XFE"), None);
+        assert_eq!(column_index("A1"), None);
+        assert_eq!(column_index(""), None);
+
+        for index in [0, 25, 26, 701, 702, MAX_COLUMN_INDEX] {
+            assert_eq!(column_index(&column_letter(index)), Some(index));
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        for preset in builtin_mappings() {
+            assert!(preset.validate().is_ok(), "{} is invalid", preset.name);
+        }
+
+        let mut mapping = client_summary_preset();
+        mapping.columns.retain(|c| c.field != ExportField::FinalPrice);
+        assert_eq!(mapping.validate().unwrap_err(), "Export mapping must include Final Price");
+
+        let mut mapping = client_summary_preset();
+        mapping.columns[1].column = "a".to_string();
+        assert_eq!(mapping.validate().unwrap_err(), "Column A is mapped more than once");
+
+        let mut mapping = client_summary_preset();
+        mapping.columns[1].field = ExportField::Id;
+        assert!(mapping.validate().unwrap_err().contains("'Shot ID'"));
+
+        let mut mapping = client_summary_preset();
+        mapping.columns[0].column = "1".to_string();
+        assert!(mapping.validate().is_err());
+    }
+
+    #[test]
+    fn test_store_save_and_activate() {
+        let path = std::env::temp_dir().join(format!("export-mappings-{}.json", uuid::Uuid::new_v4()));
+        let mut store = load_store(&path).unwrap();
+        assert_eq!(store.active_mapping().name, DETAILED_PRESET);
+
+        let mut studio = client_summary_preset();
+        studio.name = "Northlight".to_string();
+        studio.header_row = 4;
+        studio.columns.swap(0, 1);
+        store.save(studio.clone()).unwrap();
+        assert!(store.save(detailed_preset()).unwrap_err().contains("built-in"));
+        assert_eq!(store.set_active("Northlight").unwrap().header_row, 4);
+        assert!(store.set_active("Missing").is_err());
+
+        write_store(&path, &store).unwrap();
+        let reloaded = load_store(&path).unwrap();
+        assert_eq!(reloaded.active_mapping(), studio);
+        let list = ExportMappingList::from(&reloaded);
+        assert_eq!((list.active.as_str(), list.builtin.len(), list.saved.len()), ("Northlight", 2, 1));
+
+        // Order follows the letters, not the list
+        let order: Vec<_> = studio.ordered_columns().iter().map(|c| c.field).collect();
+        assert_eq!(&order[..2], &[ExportField::Id, ExportField::SceneNumber]);
+
+        std::fs::remove_file(path).ok();
+    }
+}
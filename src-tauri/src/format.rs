@@ -0,0 +1,176 @@
+//! Locale-aware number and currency formatting
+//!
+//! Used for chat replies, bid totals and CSV files so clients see the
+//! separators their Excel expects.
+
+use crate::commands::settings::{CurrencyPosition, LocaleSettings};
+
+/// Format `value` with `decimals` fraction digits and the locale's separators
+pub fn format_number(value: f64, decimals: usize, locale: &LocaleSettings) -> String {
+    let plain = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = match plain.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (plain.as_str(), None),
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push_str(&locale.thousands_separator);
+        }
+        grouped.push(digit);
+    }
+
+    if let Some(fraction) = fraction {
+        grouped.push_str(&locale.decimal_separator);
+        grouped.push_str(fraction);
+    }
+
+    // "-0.00" reads as a bug in a bid
+    let negative = value < 0.0 && plain.chars().any(|c| c.is_ascii_digit() && c != '0');
+    if negative {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+/// Format a money amount with the currency's symbol (or code) in the
+/// locale's position
+pub fn format_currency(value: f64, currency: &str, locale: &LocaleSettings) -> String {
+    let amount = format_number(value.abs(), 2, locale);
+    let symbol = currency_symbol(currency);
+    let sign = if value < 0.0 && amount.chars().any(|c| c.is_ascii_digit() && c != '0') { "-" } else { "" };
+
+    match locale.currency_symbol_position {
+        CurrencyPosition::Before if symbol.chars().count() == 1 => format!("{}{}{}", sign, symbol, amount),
+        CurrencyPosition::Before => format!("{}{} {}", sign, symbol, amount),
+        CurrencyPosition::After => format!("{}{}\u{a0}{}", sign, amount, symbol),
+    }
+}
+
+/// Parse a number written in either `1,234.56` or `1.234,56` style
+///
+/// When both separators appear, the last one is the decimal separator. A
+/// lone separator is ambiguous ("1.234"), so the locale decides: it is the
+/// decimal separator if it matches the locale's. Currency symbols, spaces
+/// and a trailing percent sign are ignored.
+pub fn parse_locale_number(text: &str, locale: &LocaleSettings) -> Option<f64> {
+    let trimmed = text.trim();
+    let negative = trimmed.starts_with('-') || (trimmed.starts_with('(') && trimmed.ends_with(')'));
+
+    let kept: String = trimmed
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '\''))
+        .collect();
+    if !kept.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let last_dot = kept.rfind('.');
+    let last_comma = kept.rfind(',');
+
+    let decimal = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(_), None) => lone_separator('.', &kept, locale),
+        (None, Some(_)) => lone_separator(',', &kept, locale),
+        (None, None) => None,
+    };
+
+    let normalized: String = kept
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_ascii_digit() => Some(c),
+            c if Some(c) == decimal => Some('.'),
+            _ => None,
+        })
+        .collect();
+
+    let value: f64 = normalized.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Whether a separator that is the only kind in the number is the decimal one
+fn lone_separator(separator: char, number: &str, locale: &LocaleSettings) -> Option<char> {
+    let repeated = number.matches(separator).count() > 1;
+    let is_locale_decimal = locale.decimal_separator.starts_with(separator);
+    (!repeated && is_locale_decimal).then_some(separator)
+}
+
+/// Symbol for common ISO currency codes; other codes are shown as-is
+pub fn currency_symbol(currency: &str) -> &str {
+    match currency.to_ascii_uppercase().as_str() {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" => "¥",
+        "INR" => "₹",
+        _ => currency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn us() -> LocaleSettings {
+        LocaleSettings::for_locale("en-US")
+    }
+
+    fn de() -> LocaleSettings {
+        LocaleSettings::for_locale("de-DE")
+    }
+
+    fn fr() -> LocaleSettings {
+        LocaleSettings::for_locale("fr-FR")
+    }
+
+    #[test]
+    fn test_format_number_conventions() {
+        assert_eq!(format_number(1234567.891, 2, &us()), "1,234,567.89");
+        assert_eq!(format_number(1234567.891, 2, &de()), "1.234.567,89");
+        assert_eq!(format_number(1234567.891, 2, &fr()), "1\u{a0}234\u{a0}567,89");
+        assert_eq!(format_number(999.0, 0, &us()), "999");
+        assert_eq!(format_number(-1500.5, 1, &de()), "-1.500,5");
+        assert_eq!(format_number(-0.001, 2, &us()), "0.00");
+    }
+
+    #[test]
+    fn test_format_currency_conventions() {
+        assert_eq!(format_currency(1234.5, "USD", &us()), "$1,234.50");
+        assert_eq!(format_currency(1234.5, "EUR", &de()), "1.234,50\u{a0}€");
+        assert_eq!(format_currency(1234.5, "EUR", &fr()), "1\u{a0}234,50\u{a0}€");
+        assert_eq!(format_currency(-20.0, "GBP", &us()), "-£20.00");
+        assert_eq!(format_currency(1000.0, "CHF", &us()), "CHF 1,000.00");
+    }
+
+    #[test]
+    fn test_parse_accepts_both_conventions() {
+        for locale in [us(), de(), fr()] {
+            assert_eq!(parse_locale_number("1.234,56", &locale), Some(1234.56));
+            assert_eq!(parse_locale_number("1,234.56", &locale), Some(1234.56));
+            assert_eq!(parse_locale_number("1.234.567", &locale), Some(1234567.0));
+            assert_eq!(parse_locale_number("€ 95", &locale), Some(95.0));
+        }
+
+        assert_eq!(parse_locale_number("1\u{a0}234,56 €", &fr()), Some(1234.56));
+        assert_eq!(parse_locale_number("(120.00)", &us()), Some(-120.0));
+        assert_eq!(parse_locale_number("n/a", &us()), None);
+    }
+
+    #[test]
+    fn test_parse_lone_separator_uses_locale() {
+        assert_eq!(parse_locale_number("1.234", &us()), Some(1.234));
+        assert_eq!(parse_locale_number("1.234", &de()), Some(1234.0));
+        assert_eq!(parse_locale_number("12,5", &de()), Some(12.5));
+        assert_eq!(parse_locale_number("12,500", &us()), Some(12500.0));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for locale in [us(), de(), fr()] {
+            let text = format_number(98765.43, 2, &locale);
+            assert_eq!(parse_locale_number(&text, &locale), Some(98765.43));
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Reading model metadata from a GGUF file header
+//!
+//! Used when the sidecar can't describe the loaded model itself. Only the
+//! key/value section at the start of the file is read; tensor data is never
+//! touched, so this stays fast on multi-gigabyte models.
+
+use std::io::{BufReader, Read};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// Magic bytes at the start of every GGUF file
+pub const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Caps that stop a corrupt header from allocating or looping forever
+const MAX_KV_COUNT: u64 = 1_000_000;
+const MAX_STRING_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Where a `ModelInfo` came from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelInfoSource {
+    Sidecar,
+    GgufHeader,
+}
+
+/// What the model is, for confirming the right quant and context are in use
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelInfo {
+    /// e.g. "llama", "gemma3"
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+    pub parameter_count: Option<u64>,
+    /// e.g. "Q4_K_M"
+    pub quantization: Option<String>,
+    /// Tokens the model was trained for
+    pub context_length: Option<u64>,
+    pub source: ModelInfoSource,
+}
+
+/// Value types in the GGUF key/value section
+mod value_type {
+    pub const UINT8: u32 = 0;
+    pub const INT8: u32 = 1;
+    pub const UINT16: u32 = 2;
+    pub const INT16: u32 = 3;
+    pub const UINT32: u32 = 4;
+    pub const INT32: u32 = 5;
+    pub const FLOAT32: u32 = 6;
+    pub const BOOL: u32 = 7;
+    pub const STRING: u32 = 8;
+    pub const ARRAY: u32 = 9;
+    pub const UINT64: u32 = 10;
+    pub const INT64: u32 = 11;
+    pub const FLOAT64: u32 = 12;
+}
+
+/// A metadata value we care about; everything else is skipped
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(u64),
+    String(String),
+    Other,
+}
+
+/// Read the architecture, name, size, quantization and context length from
+/// the header of the GGUF file at `path`
+pub fn read_model_info(path: &Path) -> Result<ModelInfo, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open model file: {}", e))?;
+    parse_header(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to read GGUF header of {}: {}", path.display(), e))
+}
+
+fn parse_header(reader: &mut impl Read) -> Result<ModelInfo, String> {
+    let mut magic = [0u8; 4];
+    read_exact(reader, &mut magic)?;
+    if &magic != GGUF_MAGIC {
+        return Err("not a GGUF file".to_string());
+    }
+
+    // Version 1 used 32-bit counts and is no longer produced by any tool
+    let version = read_u32(reader)?;
+    if version < 2 {
+        return Err(format!("unsupported GGUF version {}", version));
+    }
+
+    let _tensor_count = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+    if kv_count > MAX_KV_COUNT {
+        return Err(format!("implausible metadata count {}", kv_count));
+    }
+
+    let mut info = ModelInfo {
+        architecture: None,
+        name: None,
+        parameter_count: None,
+        quantization: None,
+        context_length: None,
+        source: ModelInfoSource::GgufHeader,
+    };
+    let mut size_label = None;
+    let mut context_lengths = Vec::new();
+
+    for _ in 0..kv_count {
+        let key = read_string(reader)?;
+        let kind = read_u32(reader)?;
+        let value = read_value(reader, kind)?;
+
+        match (key.as_str(), value) {
+            ("general.architecture", Value::String(arch)) => info.architecture = Some(arch),
+            ("general.name", Value::String(name)) => info.name = Some(name),
+            ("general.parameter_count", Value::Int(count)) => info.parameter_count = Some(count),
+            ("general.size_label", Value::String(label)) => size_label = Some(label),
+            ("general.file_type", Value::Int(file_type)) => info.quantization = quantization_name(file_type).map(str::to_string),
+            (key, Value::Int(length)) if key.ends_with(".context_length") => {
+                context_lengths.push((key.trim_end_matches(".context_length").to_string(), length));
+            }
+            _ => {}
+        }
+    }
+
+    // The context length key is namespaced by architecture
+    info.context_length = context_lengths.iter()
+        .find(|(arch, _)| Some(arch) == info.architecture.as_ref())
+        .or(context_lengths.first())
+        .map(|(_, length)| *length);
+    if info.parameter_count.is_none() {
+        info.parameter_count = size_label.as_deref().and_then(parse_size_label);
+    }
+
+    Ok(info)
+}
+
+fn read_value(reader: &mut impl Read, kind: u32) -> Result<Value, String> {
+    let value = match kind {
+        value_type::UINT8 | value_type::INT8 | value_type::BOOL => Value::Int(read_bytes::<1>(reader)?[0] as u64),
+        value_type::UINT16 | value_type::INT16 => Value::Int(u16::from_le_bytes(read_bytes(reader)?) as u64),
+        value_type::UINT32 | value_type::INT32 => Value::Int(read_u32(reader)? as u64),
+        value_type::UINT64 | value_type::INT64 => Value::Int(read_u64(reader)?),
+        value_type::FLOAT32 => {
+            read_bytes::<4>(reader)?;
+            Value::Other
+        }
+        value_type::FLOAT64 => {
+            read_bytes::<8>(reader)?;
+            Value::Other
+        }
+        value_type::STRING => Value::String(read_string(reader)?),
+        value_type::ARRAY => {
+            // Tokenizer vocabularies are large arrays; read past them
+            let item_kind = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            for _ in 0..len {
+                read_value(reader, item_kind)?;
+            }
+            Value::Other
+        }
+        other => return Err(format!("unknown metadata type {}", other)),
+    };
+    Ok(value)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING_BYTES {
+        return Err(format!("implausible string length {}", len));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    read_exact(reader, &mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, String> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    read_exact(reader, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), String> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => "header is truncated".to_string(),
+        _ => e.to_string(),
+    })
+}
+
+/// llama.cpp's name for a `general.file_type`
+fn quantization_name(file_type: u64) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        _ => return None,
+    })
+}
+
+/// Parameters from a label such as "12B" or "500M"; mixture labels like
+/// "8x7B" aren't a single count and give None
+fn parse_size_label(label: &str) -> Option<u64> {
+    let label = label.trim();
+    let (number, unit) = label.split_at(label.len().checked_sub(1)?);
+    let scale = match unit.to_ascii_uppercase().as_str() {
+        "K" => 1e3,
+        "M" => 1e6,
+        "B" => 1e9,
+        "T" => 1e12,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * scale).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal GGUF v3 header with the given key/value entries
+    struct Fixture(Vec<u8>, u64);
+
+    impl Fixture {
+        fn new() -> Self {
+            Fixture(Vec::new(), 0)
+        }
+
+        fn key(&mut self, key: &str, kind: u32) -> &mut Vec<u8> {
+            self.1 += 1;
+            push_string(&mut self.0, key);
+            self.0.extend_from_slice(&kind.to_le_bytes());
+            &mut self.0
+        }
+
+        fn string(mut self, key: &str, value: &str) -> Self {
+            push_string(self.key(key, value_type::STRING), value);
+            self
+        }
+
+        fn u32(mut self, key: &str, value: u32) -> Self {
+            self.key(key, value_type::UINT32).extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        fn bytes(self) -> Vec<u8> {
+            let mut bytes = GGUF_MAGIC.to_vec();
+            bytes.extend_from_slice(&3u32.to_le_bytes());
+            bytes.extend_from_slice(&291u64.to_le_bytes());
+            bytes.extend_from_slice(&self.1.to_le_bytes());
+            bytes.extend_from_slice(&self.0);
+            bytes
+        }
+    }
+
+    fn push_string(bytes: &mut Vec<u8>, value: &str) {
+        bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn fixture() -> Fixture {
+        let mut fixture = Fixture::new()
+            .string("general.architecture", "gemma3")
+            .string("general.name", "Floppa 12B")
+            .string("general.size_label", "12B")
+            .u32("general.file_type", 14)
+            .u32("gemma3.context_length", 131_072);
+
+        // A tokenizer vocabulary and a float, both skipped
+        let vocab = fixture.key("tokenizer.ggml.tokens", value_type::ARRAY);
+        vocab.extend_from_slice(&value_type::STRING.to_le_bytes());
+        vocab.extend_from_slice(&3u64.to_le_bytes());
+        for token in ["<pad>", "<eos>", "shot"] {
+            push_string(vocab, token);
+        }
+        fixture.key("gemma3.rope.freq_base", value_type::FLOAT32).extend_from_slice(&1e6f32.to_le_bytes());
+        fixture
+    }
+
+    #[test]
+    fn test_reads_header_fields() {
+        let bytes = fixture().u32("general.quantization_version", 2).bytes();
+        let info = parse_header(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(info, ModelInfo {
+            architecture: Some("gemma3".to_string()),
+            name: Some("Floppa 12B".to_string()),
+            parameter_count: Some(12_000_000_000),
+            quantization: Some("Q4_K_S".to_string()),
+            context_length: Some(131_072),
+            source: ModelInfoSource::GgufHeader,
+        });
+
+        let dir = std::env::temp_dir().join(format!("gguf-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gguf");
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_model_info(&path).unwrap(), info);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_rejects_bad_headers() {
+        let bytes = fixture().bytes();
+        assert!(parse_header(&mut &bytes[..bytes.len() - 3]).unwrap_err().contains("truncated"));
+        assert!(parse_header(&mut &b"PK\x03\x04"[..]).unwrap_err().contains("not a GGUF"));
+
+        let mut v1 = bytes.clone();
+        v1[4..8].copy_from_slice(&1u32.to_le_bytes());
+        assert!(parse_header(&mut v1.as_slice()).unwrap_err().contains("version 1"));
+
+        let unknown = Fixture::new().u32("general.file_type", 99).bytes();
+        let info = parse_header(&mut unknown.as_slice()).unwrap();
+        assert_eq!(info.quantization, None);
+        assert_eq!(info.architecture, None);
+    }
+
+    #[test]
+    fn test_parse_size_label() {
+        assert_eq!(parse_size_label("7.5B"), Some(7_500_000_000));
+        assert_eq!(parse_size_label("500M"), Some(500_000_000));
+        assert_eq!(parse_size_label("8x7B"), None);
+        assert_eq!(parse_size_label(""), None);
+    }
+}
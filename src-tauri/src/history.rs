@@ -0,0 +1,339 @@
+//! Local history of script processing runs
+//!
+//! Each `process_script` appends one line to
+//! `app_data_dir()/history/processing_runs.jsonl`, which is what turnaround
+//! estimates for new scripts are extrapolated from. Nothing here leaves the
+//! machine, and the script path is only stored as a hash.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::commands::model::resolve_model_path;
+use crate::commands::settings::Settings;
+use crate::error::AppError;
+
+/// Words on a typical screenplay page, for PDFs whose text can't be read
+pub const WORDS_PER_SCRIPT_PAGE: u64 = 200;
+
+/// Runs shown when no limit is given
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// One `process_script` call
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProcessingRun {
+    /// RFC 3339 start time
+    pub started_at: String,
+    /// SHA-256 of the absolute script path
+    pub script_hash: String,
+    pub page_count: Option<u64>,
+    pub word_count: Option<u64>,
+    pub shot_count: usize,
+    /// Milliseconds spent in each stage, e.g. `process_script` and `load_bid`
+    pub stages: BTreeMap<String, u64>,
+    pub total_millis: u64,
+    pub model: String,
+    pub app_version: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl ProcessingRun {
+    /// Words processed per minute, for successful runs with a word count
+    fn words_per_minute(&self) -> Option<f64> {
+        let words = self.word_count.filter(|w| *w > 0)?;
+        (self.success && self.total_millis > 0).then(|| words as f64 / (self.total_millis as f64 / 60_000.0))
+    }
+}
+
+/// Size of a script, as far as it can be read without the sidecar
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct ScriptSize {
+    pub page_count: Option<u64>,
+    pub word_count: Option<u64>,
+    /// The word count comes from the page count, not the text
+    pub words_estimated: bool,
+}
+
+/// How much history an estimate rests on
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateConfidence {
+    /// No usable history or no word count
+    Unknown,
+    Low,
+    Medium,
+    High,
+}
+
+/// Expected processing time for a script
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProcessingEstimate {
+    pub size: ScriptSize,
+    /// Successful runs the estimate is based on
+    pub runs_used: usize,
+    pub expected_secs: Option<f64>,
+    pub low_secs: Option<f64>,
+    pub high_secs: Option<f64>,
+    pub confidence: EstimateConfidence,
+}
+
+/// `app_data_dir()/history/processing_runs.jsonl`
+pub fn history_path(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    use tauri::Manager;
+
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("history").join("processing_runs.jsonl"))
+        .map_err(|e| AppError::io(format!("Failed to resolve app data directory: {}", e)))
+}
+
+/// Add a run to the end of the history file
+pub fn append_run(path: &Path, run: &ProcessingRun) -> Result<(), AppError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(format!("Failed to create {}: {}", parent.display(), e)))?;
+    }
+
+    let line = serde_json::to_string(run)
+        .map_err(|e| AppError::io(format!("Failed to serialize processing run: {}", e)))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| AppError::io(format!("Failed to open {}: {}", path.display(), e)))?;
+    writeln!(file, "{}", line)
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Every recorded run, oldest first; unreadable lines are skipped
+pub fn read_runs(path: &Path) -> Vec<ProcessingRun> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(run) => Some(run),
+            Err(e) => {
+                log::warn!("Skipping unreadable processing history line: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The newest `limit` runs, newest first
+pub fn recent_runs(path: &Path, limit: usize) -> Vec<ProcessingRun> {
+    let mut runs = read_runs(path);
+    runs.reverse();
+    runs.truncate(limit);
+    runs
+}
+
+/// File name of the model the settings point at, as runs record it
+pub fn model_name(settings: &Settings) -> String {
+    resolve_model_path(settings)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| settings.llm.model_name.clone())
+}
+
+/// Lowercase hex SHA-256 of a script path
+pub fn script_hash(path: &Path) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(path.to_string_lossy().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Count the words of a text script, or the pages of a PDF
+///
+/// PDF text is usually compressed, so only `/Type /Page` objects are
+/// counted and the words are taken as `WORDS_PER_SCRIPT_PAGE` a page.
+pub fn script_size(path: &Path) -> ScriptSize {
+    let Ok(bytes) = std::fs::read(path) else {
+        return ScriptSize::default();
+    };
+
+    if bytes.starts_with(b"%PDF") {
+        let pages = count_pdf_pages(&bytes);
+        return ScriptSize {
+            page_count: (pages > 0).then_some(pages),
+            word_count: (pages > 0).then_some(pages * WORDS_PER_SCRIPT_PAGE),
+            words_estimated: true,
+        };
+    }
+
+    let words = String::from_utf8_lossy(&bytes).split_whitespace().count() as u64;
+    ScriptSize {
+        page_count: None,
+        word_count: (words > 0).then_some(words),
+        words_estimated: false,
+    }
+}
+
+/// `/Type /Page` dictionaries, not counting the `/Pages` tree nodes
+fn count_pdf_pages(bytes: &[u8]) -> u64 {
+    let mut count = 0;
+    let mut rest = bytes;
+
+    while let Some(pos) = rest.windows(5).position(|w| w == b"/Type") {
+        rest = &rest[pos + 5..];
+        let after = rest.iter().position(|b| !b.is_ascii_whitespace()).map_or(rest, |skip| &rest[skip..]);
+        if after.starts_with(b"/Page") && !after[5..].first().is_some_and(|b| b.is_ascii_alphanumeric()) {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+/// Extrapolate processing time for a script of `size` from `runs`
+///
+/// Runs with the same model are preferred when there are any. The range is
+/// the interquartile spread of past throughput, or the full spread with
+/// fewer than four runs.
+pub fn estimate(size: ScriptSize, runs: &[ProcessingRun], model: &str) -> ProcessingEstimate {
+    let same_model: Vec<f64> = runs.iter().filter(|r| r.model == model).filter_map(ProcessingRun::words_per_minute).collect();
+    let mut rates = if same_model.is_empty() {
+        runs.iter().filter_map(ProcessingRun::words_per_minute).collect()
+    } else {
+        same_model
+    };
+    rates.sort_by(f64::total_cmp);
+
+    let unknown = ProcessingEstimate {
+        size,
+        runs_used: rates.len(),
+        expected_secs: None,
+        low_secs: None,
+        high_secs: None,
+        confidence: EstimateConfidence::Unknown,
+    };
+    let Some(words) = size.word_count else {
+        return unknown;
+    };
+    if rates.is_empty() {
+        return unknown;
+    }
+
+    let secs = |rate: f64| words as f64 / rate * 60.0;
+    let (slow, fast) = if rates.len() >= 4 {
+        (percentile(&rates, 0.25), percentile(&rates, 0.75))
+    } else {
+        (rates[0], rates[rates.len() - 1])
+    };
+
+    ProcessingEstimate {
+        size,
+        runs_used: rates.len(),
+        expected_secs: Some(secs(percentile(&rates, 0.5))),
+        low_secs: Some(secs(fast)),
+        high_secs: Some(secs(slow)),
+        confidence: match rates.len() {
+            0..=2 => EstimateConfidence::Low,
+            3..=9 => EstimateConfidence::Medium,
+            _ => EstimateConfidence::High,
+        },
+    }
+}
+
+/// Linearly interpolated percentile of sorted values
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let position = fraction * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(words: u64, millis: u64, model: &str, success: bool) -> ProcessingRun {
+        ProcessingRun {
+            started_at: "2026-01-01T00:00:00Z".to_string(),
+            script_hash: script_hash(Path::new("/scripts/pilot.pdf")),
+            page_count: None,
+            word_count: Some(words),
+            shot_count: 12,
+            stages: BTreeMap::from([("process_script".to_string(), millis)]),
+            total_millis: millis,
+            model: model.to_string(),
+            app_version: "1.0.0".to_string(),
+            success,
+            error: None,
+        }
+    }
+
+    fn words(count: u64) -> ScriptSize {
+        ScriptSize { word_count: Some(count), ..Default::default() }
+    }
+
+    #[test]
+    fn test_history_round_trips_newest_first() {
+        let dir = std::env::temp_dir().join(format!("history-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("history").join("processing_runs.jsonl");
+        assert!(read_runs(&path).is_empty());
+
+        append_run(&path, &run(1_000, 60_000, "a.gguf", true)).unwrap();
+        append_run(&path, &run(2_000, 60_000, "a.gguf", false)).unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "not json").unwrap();
+        append_run(&path, &run(3_000, 60_000, "a.gguf", true)).unwrap();
+
+        assert_eq!(read_runs(&path).len(), 3);
+        let recent = recent_runs(&path, 2);
+        assert_eq!(recent.iter().map(|r| r.word_count).collect::<Vec<_>>(), vec![Some(3_000), Some(2_000)]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_estimate_extrapolates_throughput() {
+        assert_eq!(estimate(words(9_000), &[], "a.gguf").confidence, EstimateConfidence::Unknown);
+        assert_eq!(estimate(ScriptSize::default(), &[run(1_000, 60_000, "a.gguf", true)], "a.gguf").expected_secs, None);
+
+        // 1000 and 3000 words a minute; failures don't count
+        let runs = vec![
+            run(1_000, 60_000, "a.gguf", true),
+            run(3_000, 60_000, "a.gguf", true),
+            run(50, 60_000, "a.gguf", false),
+        ];
+        let estimate_a = estimate(words(6_000), &runs, "a.gguf");
+        assert_eq!(estimate_a.runs_used, 2);
+        assert_eq!(estimate_a.confidence, EstimateConfidence::Low);
+        assert_eq!(estimate_a.expected_secs, Some(180.0));
+        assert_eq!((estimate_a.low_secs, estimate_a.high_secs), (Some(120.0), Some(360.0)));
+
+        // Runs with the model in use win over others
+        let mut mixed = runs.clone();
+        mixed.push(run(6_000, 60_000, "b.gguf", true));
+        assert_eq!(estimate(words(6_000), &mixed, "b.gguf").expected_secs, Some(60.0));
+        assert_eq!(estimate(words(6_000), &mixed, "c.gguf").runs_used, 3);
+    }
+
+    #[test]
+    fn test_script_size_counts_words_or_pdf_pages() {
+        let dir = std::env::temp_dir().join(format!("script-size-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let text = dir.join("pilot.txt");
+        std::fs::write(&text, "INT. LAB - NIGHT\n\nSparks fly.\n").unwrap();
+        assert_eq!(script_size(&text), ScriptSize { page_count: None, word_count: Some(6), words_estimated: false });
+
+        let pdf = dir.join("pilot.pdf");
+        std::fs::write(&pdf, b"%PDF-1.7\n1 0 obj << /Type /Pages /Count 2 >>\n2 0 obj << /Type /Page >>\n3 0 obj <</Type/Page/Parent 1 0 R>>\n").unwrap();
+        let size = script_size(&pdf);
+        assert_eq!(size.page_count, Some(2));
+        assert_eq!(size.word_count, Some(2 * WORDS_PER_SCRIPT_PAGE));
+        assert!(size.words_estimated);
+
+        assert_eq!(script_size(&dir.join("missing.txt")), ScriptSize::default());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,8 +1,39 @@
 // Library exports for testing
+pub mod adjustments;
+pub mod assumptions;
+pub mod attachments;
+pub mod bid_csv;
+pub mod budget;
+pub mod capabilities;
 pub mod commands;
+pub mod compare;
+pub mod diagnostics;
+pub mod error;
+pub mod estimation;
+pub mod events;
+pub mod excel;
+pub mod export_mapping;
+pub mod format;
+pub mod gguf;
+pub mod history;
+pub mod memory;
+pub mod model_health;
+pub mod offline_bundle;
+pub mod project;
+pub mod review;
+pub mod scene_detect;
+pub mod scene_order;
+pub mod script_cache;
+pub mod self_test;
 pub mod sidecar;
 pub mod state;
 pub mod setup_wizard;
+pub mod shot_list;
+pub mod source_sync;
+pub mod storage;
+pub mod summary;
+pub mod tempfiles;
+pub mod workspace;
 
-pub use commands::{bid, chat, script, settings};
+pub use commands::{bid, chat, script, session, settings};
 pub use state::{bid::BidState, session::SessionState};
@@ -1,15 +1,48 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod adjustments;
+mod assumptions;
+mod attachments;
+mod bid_csv;
+mod budget;
+mod capabilities;
 mod commands;
+mod compare;
+mod diagnostics;
+mod error;
+mod estimation;
+mod events;
+mod excel;
+mod export_mapping;
+mod format;
+mod gguf;
+mod history;
+mod memory;
+mod model_health;
+mod offline_bundle;
+mod project;
+mod review;
+mod scene_detect;
+mod scene_order;
+mod script_cache;
+mod self_test;
 mod sidecar;
 mod state;
 mod setup_wizard;
+mod shot_list;
+mod source_sync;
+mod storage;
+mod summary;
+mod tempfiles;
+mod workspace;
 
-use commands::{bid, chat, script, settings, setup};
-use state::{bid::BidState, sidecar::SidecarState};
+use commands::{bid, chat, model, palette, script, session, settings, setup};
+use events::{emit_event, AppEvent, AutosaveAvailable};
+use state::{bid::{BidState, ChangeListener}, last_error::LastErrorState, session::SessionState, sidecar::{SidecarState, HEALTH_CHECK_INTERVAL}, workspaces::{WorkspaceRegistry, MAIN_WINDOW_LABEL}};
 use tauri::{Manager, State};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::Mutex;
 
 #[tokio::main]
@@ -18,10 +51,15 @@ async fn main() {
     env_logger::init();
 
     tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
         // Initialize global state
         .manage(BidState::default())
         .manage(SidecarState::default())
-        .manage(setup::SetupWizardState::default())
+        .manage(SessionState::default())
+        .manage(LastErrorState::default())
+        .manage(WorkspaceRegistry::default())
+        .manage(chat::ChatGate::default())
+        .manage(Mutex::new(setup::SetupWizardState::default()))
         // Register all Tauri commands
         .invoke_handler(tauri::generate_handler![
             // Setup wizard commands
@@ -30,29 +68,146 @@ async fn main() {
             setup::verify_system_requirements,
             setup::install_python_dependencies,
             setup::setup_model_file,
+            setup::install_from_offline_bundle,
             setup::skip_model_setup,
             setup::complete_setup_process,
+            setup::get_setup_timings,
+            setup::run_setup_smoke_test,
             setup::verify_dependencies,
+            setup::list_python_interpreters,
+            setup::generate_diagnostics_report,
+            setup::run_self_test,
             setup::select_local_model,
             setup::get_model_download_instructions,
             setup::reset_setup,
             // Script commands
             script::process_script,
+            script::cancel_script_processing,
+            script::get_processing_history,
+            script::estimate_processing_time,
+            script::scan_script,
             script::load_bid,
+            script::open_bid_file,
             script::export_bid,
+            script::export_bid_with_template,
+            script::export_bid_csv,
+            script::export_shot_list,
+            script::generate_bid_summary,
+            script::list_export_mappings,
+            script::save_export_mapping,
+            script::set_active_export_mapping,
+            script::import_bid_csv,
+            script::check_source_sync,
+            script::reload_from_disk,
+            script::overwrite_on_export,
+            script::export_as_new_version,
             // Chat commands
             chat::send_message,
+            chat::confirm_chat_action,
+            chat::reject_chat_action,
             chat::execute_command,
+            chat::export_chat_transcript,
             // Bid commands
             bid::get_shot,
             bid::update_shot,
+            bid::get_shot_history,
+            bid::get_review_queue,
+            bid::mark_reviewed,
             bid::group_shots,
+            bid::merge_shots,
             bid::get_all_shots,
+            bid::query_shots,
+            bid::add_tags,
+            bid::remove_tags,
+            bid::get_all_tags,
+            bid::reorder_shots,
+            bid::move_shot,
+            bid::undo_shot_order,
+            bid::delete_shot,
+            bid::get_archived_shots,
+            bid::restore_shot,
+            bid::purge_archived,
+            bid::undo_archive_change,
+            bid::redo_archive_change,
+            bid::set_shot_tasks,
+            bid::apply_task_template,
+            bid::get_shots_page,
+            bid::find_duplicate_shots,
+            bid::fit_to_budget,
+            bid::apply_lump_adjustment,
+            bid::remove_adjustment,
+            bid::estimate_hours_from_duration,
+            bid::estimate_missing_hours,
+            bid::get_bid_meta,
+            bid::get_bid_totals,
+            bid::update_bid_meta,
+            bid::clear_bid,
+            bid::restore_autosaved_bid,
+            bid::get_bid_readonly,
+            bid::set_bid_readonly,
+            bid::unlock_bid,
+            bid::get_journal_stats,
+            bid::set_bid_status,
+            bid::revise_bid,
+            bid::add_assumption,
+            bid::update_assumption,
+            bid::remove_assumption,
+            bid::reorder_assumptions,
+            bid::get_assumption_library,
+            bid::save_library_assumption,
+            bid::delete_library_assumption,
+            bid::insert_library_assumptions,
+            bid::compare_bids,
+            bid::attach_reference,
+            bid::remove_reference,
+            bid::get_reference_path,
             bid::bid_query,
+            // Project commands
+            commands::project::save_project,
+            commands::project::load_project,
+            commands::project::open_project_readonly,
+            commands::project::delete_project,
+            commands::project::open_bid_in_new_window,
+            commands::project::list_bid_windows,
+            commands::project::close_bid_window,
+            commands::project::create_project,
+            commands::project::switch_project,
+            commands::project::close_project,
+            commands::project::list_projects,
             // Settings commands
             settings::get_settings,
             settings::update_settings,
             settings::test_llm_connection,
+            settings::get_sidecar_settings,
+            settings::update_sidecar_settings,
+            settings::get_storage_usage,
+            settings::clear_cache,
+            settings::list_workspaces,
+            settings::delete_workspace,
+            settings::prune_workspaces,
+            // Model commands
+            model::get_sidecar_status,
+            model::get_capabilities,
+            model::preload_model,
+            model::get_memory_status,
+            model::get_model_info,
+            model::unload_model,
+            model::get_rpc_metrics,
+            model::reset_rpc_metrics,
+            model::set_model_path,
+            model::set_active_model,
+            model::rescan_for_model,
+            // Command palette
+            palette::get_command_catalog,
+            palette::invoke_palette_command,
+            // Session commands
+            session::update_session,
+            session::restore_last_session,
+            session::get_pending_session_restore,
+            session::dismiss_session_restore,
+            session::get_last_errors,
+            session::clear_last_errors,
+            session::get_event_schema,
         ])
         // Setup application
         .setup(|app| {
@@ -63,41 +218,188 @@ async fn main() {
             //     window.open_devtools();
             // }
 
-            // Start Python sidecar on application startup
-            let sidecar_state: State<SidecarState> = app.state();
-
-            // Find the Python RPC server script
-            // Try multiple possible locations
-            let possible_paths = vec![
-                // Absolute path to parent directory (works in dev)
-                PathBuf::from("/Volumes/MacWork/VFX-BIDDING/python_sidecar/rpc_server.py"),
-                // App bundle resources (production)
+            // Packages and models for the setup wizard; the built-in lists
+            // apply when no manifest is bundled
+            let manifest_path = [
                 app.path().resource_dir()
                     .unwrap_or_else(|_| PathBuf::from("."))
-                    .join("python_sidecar/rpc_server.py"),
-                // Relative to project root (development)
-                PathBuf::from("../../python_sidecar/rpc_server.py"),
-                // Relative to project root (alternative)
-                PathBuf::from("../python_sidecar/rpc_server.py"),
-            ];
-
-            let resource_path = possible_paths.into_iter()
+                    .join("setup_manifest.json"),
+                PathBuf::from("resources/setup_manifest.json"),
+            ]
+            .into_iter()
+            .find(|p| p.exists());
+
+            if let Some(path) = manifest_path {
+                if let Err(e) = setup_wizard::init_setup_manifest(&path) {
+                    eprintln!("Invalid setup manifest, using built-in defaults: {}", e);
+                }
+            }
+
+            // Scratch space for downloads and atomic writes
+            match app.path().app_cache_dir() {
+                Ok(cache_dir) => match tempfiles::init(cache_dir.join("tmp")) {
+                    Ok(0) => {}
+                    Ok(removed) => log::info!("Removed {} stale temp files", removed),
+                    Err(e) => eprintln!("Failed to prepare temp directory: {}", e),
+                },
+                Err(e) => eprintln!("Failed to locate app cache directory: {}", e),
+            }
+
+            // Per-bid working directories for the sidecar
+            match app.path().app_data_dir() {
+                Ok(data_dir) => workspace::init(data_dir.join("workspaces")),
+                Err(e) => eprintln!("Failed to locate app data directory: {}", e),
+            }
+
+            // Python sidecar, started below once settings are loaded
+            let sidecar_state: State<SidecarState> = app.state();
+
+            // Forward sidecar events (e.g. model_loading) to the frontend as
+            // kebab-case Tauri events; extracted shots also go into the bid
+            let handle = app.handle().clone();
+            sidecar_state.set_event_sink(Arc::new(move |event| {
+                if event.event == "shot_extracted" {
+                    script::handle_shot_extracted(&handle, &event.data);
+                    return;
+                }
+                emit_event(&handle, AppEvent::Sidecar(event)).ok();
+            }));
+
+            // Keep the cached running state in step with the process
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(HEALTH_CHECK_INTERVAL);
+                let sidecar_state = handle.state::<SidecarState>();
+                sidecar_state.check_health();
+                // A sidecar restarted by the check takes calls again once it has said which protocol it speaks
+                if sidecar_state.handshake_pending() && sidecar_state.is_running() {
+                    tauri::async_runtime::block_on(sidecar_state.negotiate_protocol());
+                }
+            });
+
+            // Cached bid_query answers are stale once any bid changes
+            let handle = app.handle().clone();
+            let invalidate: ChangeListener = Arc::new(move || handle.state::<SidecarState>().queries().invalidate());
+            app.state::<BidState>().on_change(invalidate.clone());
+            app.state::<WorkspaceRegistry>().on_change(invalidate);
+
+            // Offer to restore the previous session
+            let session_state: State<SessionState> = app.state();
+            if let Ok(config_dir) = app.path().app_config_dir() {
+                export_mapping::init(config_dir.join("export_mappings.json"));
+                assumptions::init(config_dir.join("assumption_library.json"));
+                match session_state.load(&config_dir) {
+                    // Also kept for `get_pending_session_restore`, as the
+                    // frontend may not be listening yet
+                    Ok(_) => {
+                        if let Some(snapshot) = session_state.pending_restore() {
+                            emit_event(app.handle(), AppEvent::SessionRestoreAvailable(snapshot)).ok();
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to load previous session: {}", e),
+                }
+            }
+
+            // Autosave the bid and offer to restore a copy left by a crash
+            let bid_state: State<BidState> = app.state();
+            if let Ok(config_dir) = app.path().app_config_dir() {
+                bid_state.enable_autosave(config_dir.join("bid_autosave.json"));
+                match app.path().app_data_dir() {
+                    Ok(data_dir) => {
+                        if let Err(e) = bid_state.enable_journal(data_dir.join("journal")) {
+                            eprintln!("Failed to start bid journal: {}", e);
+                        }
+                        bid_state.enable_audit(data_dir.join("audit"));
+                        let registry = app.state::<WorkspaceRegistry>();
+                        registry.enable_audit(data_dir.join("audit"));
+                        registry.enable_recovery(data_dir.join("contexts"));
+                        let recovered = registry.recover_tabs(MAIN_WINDOW_LABEL);
+                        if !recovered.is_empty() {
+                            log::info!("Reopened {} unsaved project tab(s) left by a crash", recovered.len());
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to locate app data directory: {}", e),
+                }
+                match bid_state.read_autosave() {
+                    Ok(Some(autosave)) => {
+                        emit_event(app.handle(), AppEvent::BidAutosaveAvailable(AutosaveAvailable {
+                            saved_at: autosave.saved_at,
+                            shot_count: autosave.shots.len(),
+                        })).ok();
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to read autosaved bid: {}", e),
+                }
+            }
+
+            // Apply saved RPC timeouts before any command calls the sidecar
+            let settings = session_state.get_settings().unwrap_or_default();
+            sidecar_state.set_rpc_timeouts(settings.sidecar.clone());
+
+            // Find the Python RPC server script, honouring developer overrides
+            let resource_path = model::sidecar_script_candidates(app.handle(), &settings).into_iter()
                 .find(|p| p.exists())
                 .unwrap_or_else(|| PathBuf::from("python_sidecar/rpc_server.py"));
 
             println!("Starting Python sidecar from: {:?}", resource_path);
+            let wizard_state: State<Mutex<setup::SetupWizardState>> = app.state();
+            wizard_state.lock().unwrap().script_path = Some(resource_path.clone());
 
-            // Start the sidecar - this will spawn the Python process
-            match sidecar_state.start(resource_path) {
-                Ok(_) => println!("Python sidecar started successfully"),
-                Err(e) => {
-                    eprintln!("Failed to start Python sidecar: {}", e);
-                    eprintln!("Application will continue but RPC calls will fail");
-                }
+            // Offer to delete workspaces unused for longer than the retention
+            // period; sizing them can take a while, so off the main thread
+            if let Some(root) = workspace::root() {
+                let handle = app.handle().clone();
+                let retention_days = settings.sidecar.workspace_retention_days;
+                tauri::async_runtime::spawn_blocking(move || {
+                    let plan = workspace::prune_plan(root, retention_days, None);
+                    if !plan.workspaces.is_empty() {
+                        emit_event(&handle, AppEvent::WorkspacesPrunePending(plan)).ok();
+                    }
+                });
             }
 
+            // Start the sidecar once the model checksum (if recorded) has been
+            // verified off the main thread, then warm up the model so the
+            // first chat/script run doesn't stall
+            let handle = app.handle().clone();
+            // Without a model the app runs in bid-editing mode only
+            let preload = settings.ui.preload_on_start && capabilities::model_configured(&settings);
+            tauri::async_runtime::spawn(async move {
+                match model::start_sidecar_checked(&handle, resource_path).await {
+                    Ok(true) => println!("Python sidecar started successfully"),
+                    Ok(false) => return,
+                    Err(e) => {
+                        eprintln!("Failed to start Python sidecar: {}", e);
+                        eprintln!("Application will continue but RPC calls will fail");
+                        return;
+                    }
+                }
+
+                if preload {
+                    if let Err(e) = model::preload_model_internal(&handle).await {
+                        eprintln!("Model preload failed: {}", e);
+                    }
+                }
+            });
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(commands::project::handle_window_event)
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Persist the session and pending journal entries on exit
+            if let tauri::RunEvent::Exit = event {
+                let sidecar_state: State<SidecarState> = app.state();
+                sidecar_state.shutdown_rpc_pool();
+
+                let bid_state: State<BidState> = app.state();
+                bid_state.flush_journal();
+
+                let session_state: State<SessionState> = app.state();
+                if let Err(e) = session_state.save() {
+                    eprintln!("Failed to save session: {}", e);
+                }
+            }
+        });
 }
@@ -0,0 +1,159 @@
+//! Free-memory guard for loading the model
+//!
+//! Passing the setup wizard's total-RAM minimum doesn't mean there is room
+//! for the weights once the webview and other apps are running, so the
+//! memory actually available is checked right before each load.
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+use crate::commands::model::resolve_model_path;
+use crate::commands::settings::Settings;
+use crate::error::AppError;
+use crate::setup_wizard::{setup_manifest, ModelEntry, MODEL_RAM_HEADROOM};
+
+const GB: f64 = 1_000_000_000.0;
+
+/// Memory readings for the RAM indicator and the load guard
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MemoryStatus {
+    /// File name of the model that would be loaded
+    pub model: String,
+    /// Weights plus the catalog's working margin
+    pub needed_gb: f64,
+    /// None when this platform's memory can't be read
+    pub available_gb: Option<f64>,
+    pub total_gb: Option<f64>,
+    /// Whether the model fits; also true when memory is unknown
+    pub sufficient: bool,
+    /// False when `skip_memory_check` is set
+    pub guard_enabled: bool,
+}
+
+impl MemoryStatus {
+    /// Error unless the model fits or the guard is off
+    pub fn check(&self) -> Result<(), AppError> {
+        match self.available_gb {
+            Some(available) if self.guard_enabled && !self.sufficient => {
+                Err(AppError::insufficient_memory(self.needed_gb, available))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Total and available bytes, where the platform reports them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MemoryReading {
+    pub total: Option<u64>,
+    pub available: Option<u64>,
+}
+
+/// Check the model chosen in `settings` against the memory available now
+pub fn memory_status(settings: &Settings) -> MemoryStatus {
+    let model_path = resolve_model_path(settings);
+    let model = model_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| settings.llm.model_name.clone());
+
+    let manifest = setup_manifest();
+    let entry = manifest.models.iter().find(|m| m.filename == model);
+    let needed = match std::fs::metadata(&model_path) {
+        Ok(metadata) => memory_needed(metadata.len(), entry.and_then(|e| e.ram_margin_bytes)),
+        Err(_) => entry_memory_needed(entry.unwrap_or(manifest.default_model()))
+            .unwrap_or_else(|| memory_needed(manifest.default_model_size(), None)),
+    };
+
+    status_for(model, needed, read_memory(), !settings.llm.skip_memory_check)
+}
+
+fn status_for(model: String, needed: u64, reading: MemoryReading, guard_enabled: bool) -> MemoryStatus {
+    MemoryStatus {
+        model,
+        needed_gb: needed as f64 / GB,
+        available_gb: reading.available.map(|b| b as f64 / GB),
+        total_gb: reading.total.map(|b| b as f64 / GB),
+        sufficient: reading.available.is_none_or(|available| available >= needed),
+        guard_enabled,
+    }
+}
+
+/// Bytes needed to load `model_bytes` of weights
+///
+/// Without a margin in the catalog the usual headroom for the context cache
+/// and runtime is assumed.
+pub fn memory_needed(model_bytes: u64, margin_bytes: Option<u64>) -> u64 {
+    let margin = margin_bytes.unwrap_or((model_bytes as f64 * (MODEL_RAM_HEADROOM - 1.0)) as u64);
+    model_bytes.saturating_add(margin)
+}
+
+fn entry_memory_needed(entry: &ModelEntry) -> Option<u64> {
+    entry.size_bytes.map(|size| memory_needed(size, entry.ram_margin_bytes))
+}
+
+/// Catalog models other than `current` that fit in `available_gb`, smallest
+/// first
+pub fn models_that_fit(current: &str, available_gb: f64) -> Vec<String> {
+    let mut fitting: Vec<(u64, &ModelEntry)> = setup_manifest()
+        .models
+        .iter()
+        .filter(|m| m.filename != current)
+        .filter_map(|m| entry_memory_needed(m).map(|needed| (needed, m)))
+        .filter(|(needed, _)| *needed as f64 / GB <= available_gb)
+        .collect();
+    fitting.sort_by_key(|(needed, _)| *needed);
+    fitting.into_iter().map(|(_, m)| m.filename.clone()).collect()
+}
+
+/// Current total and available memory, through sysinfo
+///
+/// Available is what a new process can get without swapping, not the free
+/// figure: `MemAvailable` on Linux (free plus reclaimable cache), and on
+/// macOS free plus inactive and purgeable pages less what the compressor
+/// holds, since macOS keeps "free" near zero by caching and compressing.
+/// A platform reporting zero counts as unknown.
+pub fn read_memory() -> MemoryReading {
+    let system = System::new_with_specifics(RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()));
+    let known = |bytes: u64| (bytes > 0).then_some(bytes);
+
+    MemoryReading {
+        total: known(system.total_memory()),
+        available: known(system.available_memory()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_memory_from_the_host() {
+        let reading = read_memory();
+        let (total, available) = (reading.total.unwrap(), reading.available.unwrap());
+        assert!(available <= total, "{} available of {}", available, total);
+    }
+
+    #[test]
+    fn test_guard_compares_available_with_needed() {
+        let needed = memory_needed(6_500_000_000, None);
+        assert_eq!(needed, 8_125_000_000);
+        assert_eq!(memory_needed(6_500_000_000, Some(1_000_000_000)), 7_500_000_000);
+
+        let reading = |available| MemoryReading { total: Some(8_000_000_000), available };
+
+        let low = status_for("model.gguf".to_string(), needed, reading(Some(3_000_000_000)), true);
+        assert!(!low.sufficient);
+        assert_eq!(low.check(), Err(AppError::insufficient_memory(8.125, 3.0)));
+
+        // The bypass setting lets the load go ahead
+        let bypassed = MemoryStatus { guard_enabled: false, ..low };
+        assert!(bypassed.check().is_ok());
+
+        let plenty = status_for("model.gguf".to_string(), needed, reading(Some(12_000_000_000)), true);
+        assert!(plenty.sufficient && plenty.check().is_ok());
+
+        // Unknown memory never blocks
+        let unknown = status_for("model.gguf".to_string(), needed, MemoryReading::default(), true);
+        assert!(unknown.sufficient && unknown.check().is_ok());
+    }
+}
@@ -0,0 +1,269 @@
+//! Finding out why a model that used to load no longer does
+//!
+//! Models are often kept on external drives. When the file is gone the
+//! sidecar only reports a generic load failure, so the path is checked
+//! first and the cause classified: a drive that isn't mounted, a file that
+//! was moved or deleted, or one that can't be read. Settings keep the last
+//! few paths a model loaded from, which a rescan looks at first.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::model::resolve_model_path;
+use crate::commands::settings::{LlmSettings, Settings};
+use crate::setup_wizard::{model_dir, setup_manifest};
+
+/// Known-good model paths kept in the settings
+pub const KNOWN_MODEL_PATHS_LIMIT: usize = 5;
+
+/// Where removable drives are mounted, with how many path components below
+/// the root name a drive (`/media/<user>/<drive>` on Linux)
+const VOLUME_ROOTS: [(&str, usize); 3] = [("/Volumes", 1), ("/media", 2), ("/run/media", 2)];
+
+/// Directory levels searched below a drive or the model directory
+const SCAN_DEPTH: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableReason {
+    /// On a drive that isn't mounted
+    VolumeUnmounted,
+    /// Moved or deleted
+    Deleted,
+    PermissionDenied,
+    /// Any other error reading it
+    Unreadable,
+}
+
+/// Payload of `model-unavailable`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ModelUnavailable {
+    pub path: String,
+    pub reason: UnavailableReason,
+    /// Mount point of the missing drive, for `VolumeUnmounted`
+    pub volume: Option<String>,
+    pub message: String,
+}
+
+/// A copy of a model found by `rescan_for_model`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FoundModel {
+    pub path: String,
+    pub size_bytes: u64,
+    /// A model has loaded from this path before
+    pub known_good: bool,
+}
+
+/// Whether a model should be there: one has loaded before
+///
+/// Without that a missing file just means no model was set up.
+pub fn model_expected(settings: &Settings) -> bool {
+    !settings.llm.known_model_paths.is_empty()
+}
+
+/// Check the model at `path` can be opened, and say why not
+pub fn check_model_path(path: &Path) -> Result<(), ModelUnavailable> {
+    check_with_roots(path, &volume_roots())
+}
+
+fn volume_roots() -> Vec<(PathBuf, usize)> {
+    VOLUME_ROOTS.iter().map(|(root, depth)| (PathBuf::from(root), *depth)).collect()
+}
+
+fn check_with_roots(path: &Path, roots: &[(PathBuf, usize)]) -> Result<(), ModelUnavailable> {
+    let error = match std::fs::metadata(path) {
+        Ok(meta) if meta.is_file() => match std::fs::File::open(path) {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        },
+        Ok(_) => std::io::Error::new(std::io::ErrorKind::NotFound, "not a file"),
+        Err(e) => e,
+    };
+
+    let shown = path.display();
+    let (reason, volume, message) = if error.kind() == std::io::ErrorKind::PermissionDenied {
+        (
+            UnavailableReason::PermissionDenied,
+            None,
+            format!("The model file {} can't be read: permission denied.", shown),
+        )
+    } else if let Some(volume) = unmounted_volume(path, roots) {
+        let message = format!(
+            "The model is on {}, which isn't mounted. Connect the drive, or rescan for another copy of the model.",
+            volume.display()
+        );
+        (UnavailableReason::VolumeUnmounted, Some(volume.to_string_lossy().to_string()), message)
+    } else if error.kind() == std::io::ErrorKind::NotFound {
+        (
+            UnavailableReason::Deleted,
+            None,
+            format!("The model file {} was moved or deleted. Rescan to find it, or choose another model.", shown),
+        )
+    } else {
+        (UnavailableReason::Unreadable, None, format!("The model file {} can't be read: {}", shown, error))
+    };
+
+    Err(ModelUnavailable { path: path.to_string_lossy().to_string(), reason, volume, message })
+}
+
+/// Mount point of the drive `path` is on, when that drive is missing
+fn unmounted_volume(path: &Path, roots: &[(PathBuf, usize)]) -> Option<PathBuf> {
+    // Ancestors run from the file up, so the last missing one is the highest
+    let missing = path.ancestors().filter(|a| !a.as_os_str().is_empty() && !a.exists()).last()?;
+    roots.iter().find_map(|(root, depth)| {
+        let below = missing.strip_prefix(root).ok()?.components().count();
+        if below == 0 || below > *depth {
+            return None;
+        }
+        let drive: PathBuf = path.strip_prefix(root).ok()?.components().take(*depth).collect();
+        Some(root.join(drive))
+    })
+}
+
+/// Put `path` first among the known-good model paths; false when it
+/// already was
+pub fn remember_known_good(llm: &mut LlmSettings, path: &Path) -> bool {
+    let path = path.to_string_lossy().to_string();
+    if llm.known_model_paths.first() == Some(&path) {
+        return false;
+    }
+    llm.known_model_paths.retain(|known| *known != path);
+    llm.known_model_paths.insert(0, path);
+    llm.known_model_paths.truncate(KNOWN_MODEL_PATHS_LIMIT);
+    true
+}
+
+/// Look for the model: known-good paths first, then its file name and the
+/// catalog's in the model directory, next to known-good paths, and on
+/// mounted drives
+///
+/// Reads directories, so call it off the main thread.
+pub fn rescan_for_model(settings: &Settings) -> Vec<FoundModel> {
+    rescan_with_roots(settings, &volume_roots())
+}
+
+fn rescan_with_roots(settings: &Settings, roots: &[(PathBuf, usize)]) -> Vec<FoundModel> {
+    let mut names: HashSet<String> = setup_manifest().models.iter().map(|m| m.filename.clone()).collect();
+    names.insert(settings.llm.model_name.clone());
+    if let Some(name) = resolve_model_path(settings).file_name() {
+        names.insert(name.to_string_lossy().to_string());
+    }
+    let known: Vec<PathBuf> = settings.llm.known_model_paths.iter().map(PathBuf::from).collect();
+
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |path: &Path, known_good: bool| {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return;
+        };
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if meta.is_file() && seen.insert(key) {
+            found.push(FoundModel { path: path.to_string_lossy().to_string(), size_bytes: meta.len(), known_good });
+        }
+    };
+
+    for path in &known {
+        add(path, true);
+    }
+
+    let mut dirs: Vec<(PathBuf, usize)> = vec![(model_dir(&settings.paths.model_dir), SCAN_DEPTH)];
+    dirs.extend(known.iter().filter_map(|path| Some((path.parent()?.to_path_buf(), 0))));
+    dirs.extend(roots.iter().map(|(root, depth)| (root.clone(), depth + SCAN_DEPTH)));
+    for (dir, depth) in dirs {
+        let mut matches = Vec::new();
+        find_named(&dir, &names, depth, &mut matches);
+        for path in matches {
+            add(&path, known.contains(&path));
+        }
+    }
+    found
+}
+
+/// Files in `dir` named one of `names`, down `depth` more levels; hidden
+/// directories are skipped
+fn find_named(dir: &Path, names: &HashSet<String>, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(kind) = entry.file_type() else {
+            continue;
+        };
+        if kind.is_file() && names.contains(&name) {
+            out.push(entry.path());
+        } else if kind.is_dir() && depth > 0 && !name.starts_with('.') {
+            find_named(&entry.path(), names, depth - 1, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_model_is_classified() {
+        let temp = tempfile::tempdir().unwrap();
+        let volumes = temp.path();
+        let roots = [(volumes.to_path_buf(), 1)];
+        std::fs::create_dir_all(volumes.join("Studio/models")).unwrap();
+        let present = volumes.join("Studio/models/model.gguf");
+        std::fs::write(&present, b"GGUF").unwrap();
+        assert!(check_with_roots(&present, &roots).is_ok());
+
+        let unplugged = check_with_roots(&volumes.join("FastSSD/models/model.gguf"), &roots).unwrap_err();
+        assert_eq!(unplugged.reason, UnavailableReason::VolumeUnmounted);
+        assert_eq!(unplugged.volume, Some(volumes.join("FastSSD").to_string_lossy().to_string()));
+
+        // The drive is there, the file isn't
+        let deleted = check_with_roots(&volumes.join("Studio/models/gone.gguf"), &roots).unwrap_err();
+        assert_eq!((deleted.reason, deleted.volume), (UnavailableReason::Deleted, None));
+        let moved_dir = check_with_roots(&volumes.join("Studio/old/model.gguf"), &roots).unwrap_err();
+        assert_eq!(moved_dir.reason, UnavailableReason::Deleted);
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        let outside = check_with_roots(&elsewhere.path().join("model.gguf"), &roots).unwrap_err();
+        assert_eq!(outside.reason, UnavailableReason::Deleted);
+    }
+
+    #[test]
+    fn test_rescan_finds_known_and_catalog_copies() {
+        let temp = tempfile::tempdir().unwrap();
+        let volumes = temp.path();
+        let name = setup_manifest().default_model.clone();
+        std::fs::create_dir_all(volumes.join("Backup/llm/models")).unwrap();
+        let on_drive = volumes.join("Backup/llm/models").join(&name);
+        std::fs::write(&on_drive, b"GGUF").unwrap();
+        std::fs::create_dir_all(volumes.join("Backup/.Trashes")).unwrap();
+        std::fs::write(volumes.join("Backup/.Trashes").join(&name), b"GGUF").unwrap();
+
+        let known_dir = tempfile::tempdir().unwrap();
+        let known = known_dir.path().join("custom.gguf");
+        std::fs::write(&known, b"GGUF").unwrap();
+        let model_dir = tempfile::tempdir().unwrap();
+        let mut settings = Settings::default();
+        settings.paths.model_dir = model_dir.path().to_string_lossy().to_string();
+        settings.llm.model_path = Some("/Volumes/Gone/custom.gguf".to_string());
+        assert!(remember_known_good(&mut settings.llm, Path::new("/Volumes/Gone/custom.gguf")));
+        assert!(remember_known_good(&mut settings.llm, &known));
+        assert!(!remember_known_good(&mut settings.llm, &known));
+
+        let found = rescan_with_roots(&settings, &[(volumes.to_path_buf(), 1)]);
+        let paths: Vec<(&str, bool)> = found.iter().map(|f| (f.path.as_str(), f.known_good)).collect();
+        assert_eq!(paths, vec![
+            (known.to_str().unwrap(), true),
+            (on_drive.to_str().unwrap(), false),
+        ]);
+
+        for n in 0..KNOWN_MODEL_PATHS_LIMIT + 2 {
+            remember_known_good(&mut settings.llm, Path::new(&format!("/models/{}.gguf", n)));
+        }
+        assert_eq!(settings.llm.known_model_paths.len(), KNOWN_MODEL_PATHS_LIMIT);
+        assert_eq!(settings.llm.known_model_paths[0], format!("/models/{}.gguf", KNOWN_MODEL_PATHS_LIMIT + 1));
+    }
+}
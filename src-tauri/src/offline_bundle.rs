@@ -0,0 +1,443 @@
+//! Installing from an offline bundle on machines without internet
+//!
+//! Studio machines in secure facilities can't reach PyPI or Hugging Face, so
+//! the wizard can install from a bundle instead: a directory, or a zip of
+//! one, holding
+//!
+//! ```text
+//! bundle_manifest.json
+//! wheels/<package wheels and their dependencies>
+//! models/<the GGUF model>
+//! ```
+//!
+//! The manifest lists each wheel with its package, version and sha256, and
+//! the model with its sha256. Everything is checked against the setup
+//! manifest and the files before pip runs with `--no-index`. A bundle left in
+//! `~/VFX-BIDDING/offline_bundle/` is found without asking.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::setup_wizard::{compare_package_versions, sha256_file, SetupManifest};
+
+pub const BUNDLE_MANIFEST: &str = "bundle_manifest.json";
+const WHEELS_DIR: &str = "wheels";
+const MODELS_DIR: &str = "models";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundleManifest {
+    pub packages: Vec<BundlePackage>,
+    pub model: BundleModel,
+}
+
+/// One wheel in `wheels/`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundlePackage {
+    pub name: String,
+    pub version: String,
+    /// File name inside `wheels/`
+    pub wheel: String,
+    pub sha256: String,
+}
+
+/// The model in `models/`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundleModel {
+    pub filename: String,
+    pub sha256: String,
+}
+
+/// A bundle ready to install from; a zipped one is extracted to a temp
+/// directory that goes away when this is dropped
+#[derive(Debug)]
+pub struct OpenBundle {
+    /// Directory holding `bundle_manifest.json`
+    pub dir: PathBuf,
+    pub manifest: BundleManifest,
+    extracted: Option<PathBuf>,
+}
+
+impl Drop for OpenBundle {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.extracted {
+            fs::remove_dir_all(dir).ok();
+        }
+    }
+}
+
+impl OpenBundle {
+    pub fn wheels_dir(&self) -> PathBuf {
+        self.dir.join(WHEELS_DIR)
+    }
+
+    pub fn model_file(&self) -> PathBuf {
+        self.dir.join(MODELS_DIR).join(&self.manifest.model.filename)
+    }
+}
+
+/// `~/VFX-BIDDING/offline_bundle`
+pub fn default_bundle_dir() -> PathBuf {
+    let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("VFX-BIDDING");
+    path.push("offline_bundle");
+    path
+}
+
+/// The bundle in `dir`: the directory itself when it has a manifest, else
+/// the first zip in it
+pub fn find_bundle(dir: &Path) -> Option<PathBuf> {
+    if dir.join(BUNDLE_MANIFEST).is_file() {
+        return Some(dir.to_path_buf());
+    }
+    let mut zips: Vec<PathBuf> = fs::read_dir(dir).ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip")))
+        .collect();
+    zips.sort();
+    zips.into_iter().next()
+}
+
+/// Open the bundle at `path` and check it against `setup`
+pub fn open_bundle(path: &Path, setup: &SetupManifest) -> Result<OpenBundle, String> {
+    let (dir, extracted) = if path.is_dir() {
+        (path.to_path_buf(), None)
+    } else if path.is_file() {
+        let temp = crate::tempfiles::temp_root()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("offline_bundle-{}", uuid::Uuid::new_v4()));
+        let root = extract_zip(path, &temp).inspect_err(|_| {
+            fs::remove_dir_all(&temp).ok();
+        })?;
+        (root, Some(temp))
+    } else {
+        return Err(format!("Offline bundle {} not found", path.display()));
+    };
+
+    // Built first so an extracted bundle is cleaned up on any error below
+    let mut bundle = OpenBundle {
+        dir,
+        manifest: BundleManifest { packages: Vec::new(), model: BundleModel { filename: String::new(), sha256: String::new() } },
+        extracted,
+    };
+    bundle.manifest = read_manifest(&bundle.dir)?;
+    verify_bundle(&bundle, setup)?;
+    Ok(bundle)
+}
+
+fn read_manifest(dir: &Path) -> Result<BundleManifest, String> {
+    let path = dir.join(BUNDLE_MANIFEST);
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Offline bundle has no readable {}: {}", BUNDLE_MANIFEST, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse {}: {}", BUNDLE_MANIFEST, e))
+}
+
+/// Extract `zip` into `dest`; returns the directory holding the manifest,
+/// which may be a single folder the bundle was zipped in
+fn extract_zip(zip: &Path, dest: &Path) -> Result<PathBuf, String> {
+    let file = fs::File::open(zip).map_err(|e| format!("Failed to open {}: {}", zip.display(), e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("{} is not a readable zip: {}", zip.display(), e))?;
+    crate::setup_wizard::ensure_disk_space(dest, unpacked_size(&mut archive))?;
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| format!("Failed to read {}: {}", zip.display(), e))?;
+        // Entries that would land outside `dest` are skipped
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let target = dest.join(name);
+        if entry.is_dir() {
+            fs::create_dir_all(&target).map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+        }
+        let mut out = fs::File::create(&target).map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+        std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+    }
+
+    if dest.join(BUNDLE_MANIFEST).is_file() {
+        return Ok(dest.to_path_buf());
+    }
+    fs::read_dir(dest).ok()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.join(BUNDLE_MANIFEST).is_file())
+        .ok_or_else(|| format!("{} has no {}", zip.display(), BUNDLE_MANIFEST))
+}
+
+/// Bytes the archive's files take once extracted, as its entries declare
+fn unpacked_size<R: std::io::Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> u64 {
+    (0..archive.len())
+        .filter_map(|index| archive.by_index(index).ok().filter(|entry| !entry.is_dir()).map(|entry| entry.size()))
+        .sum()
+}
+
+/// pip names compare case-insensitively with `-`, `_` and `.` alike
+fn same_package(a: &str, b: &str) -> bool {
+    let normalize = |name: &str| name.trim().to_lowercase().replace(['_', '.'], "-");
+    normalize(a) == normalize(b)
+}
+
+/// Check the manifest covers what setup installs, and every file it lists
+/// is there with its checksum. Errors name the entry that doesn't match.
+pub fn verify_bundle(bundle: &OpenBundle, setup: &SetupManifest) -> Result<(), String> {
+    let manifest = &bundle.manifest;
+
+    for required in &setup.packages {
+        let Some(package) = manifest.packages.iter().find(|p| same_package(&p.name, &required.name)) else {
+            return Err(format!("Package '{}' is missing from the bundle manifest", required.name));
+        };
+        if let Some(minimum) = required.minimum_version() {
+            if compare_package_versions(&package.version, minimum).is_lt() {
+                return Err(format!(
+                    "Package '{}' in the bundle is version {}, but at least {} is required",
+                    package.name, package.version, minimum
+                ));
+            }
+        }
+    }
+
+    for package in &manifest.packages {
+        let wheel = bundle.wheels_dir().join(&package.wheel);
+        check_file(&wheel, &package.sha256)
+            .map_err(|e| format!("Wheel '{}' for package '{}': {}", package.wheel, package.name, e))?;
+    }
+
+    if manifest.model.filename != setup.default_model {
+        return Err(format!(
+            "Model '{}' in the bundle isn't the model setup installs ('{}')",
+            manifest.model.filename, setup.default_model
+        ));
+    }
+    check_file(&bundle.model_file(), &manifest.model.sha256)
+        .map_err(|e| format!("Model '{}': {}", manifest.model.filename, e))
+}
+
+fn check_file(path: &Path, expected: &str) -> Result<(), String> {
+    if !path.is_file() {
+        return Err(format!("{} is missing", path.display()));
+    }
+    let actual = sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!("sha256 is {}, expected {}", actual, expected.trim()));
+    }
+    Ok(())
+}
+
+/// Arguments to `python` for installing the setup packages from the
+/// bundle's wheels alone
+pub fn pip_install_args(bundle: &OpenBundle, setup: &SetupManifest) -> Vec<String> {
+    let mut args: Vec<String> = ["-m", "pip", "install", "--no-index", "--find-links"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    args.push(bundle.wheels_dir().to_string_lossy().to_string());
+    args.extend(setup.packages.iter().filter_map(|required| {
+        bundle.manifest.packages.iter()
+            .find(|p| same_package(&p.name, &required.name))
+            .map(|p| format!("{}=={}", p.name, p.version))
+    }));
+    args
+}
+
+/// Run pip with `args`, passing each line it prints to `progress`
+///
+/// Fails with what pip printed to stderr.
+pub async fn run_pip(python_path: &str, args: &[String], progress: impl Fn(String)) -> Result<(), String> {
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+    let mut child = tokio::process::Command::new(python_path)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pip: {}", e))?;
+
+    // Drained alongside stdout so pip can't stall on a full stderr pipe
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = tokio::spawn(async move {
+        let mut errors = String::new();
+        stderr.read_to_string(&mut errors).await.ok();
+        errors
+    });
+
+    let mut lines = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if !line.is_empty() {
+            progress(line.to_string());
+        }
+    }
+
+    let status = child.wait().await.map_err(|e| format!("Failed to run pip: {}", e))?;
+    let errors = errors.await.unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Failed to install from offline bundle: {}", errors.trim()))
+    }
+}
+
+/// Copy the bundle's model to `destination` and check the copy
+pub fn install_model(bundle: &OpenBundle, destination: &Path) -> Result<(), String> {
+    let source = bundle.model_file();
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create model directory: {}", e))?;
+    }
+    let size = fs::metadata(&source).map_err(|e| format!("Failed to read model file: {}", e))?.len();
+    crate::setup_wizard::ensure_disk_space(destination, size)?;
+
+    fs::copy(&source, destination).map_err(|e| format!("Failed to copy model file: {}", e))?;
+    check_file(destination, &bundle.manifest.model.sha256)
+        .map_err(|e| format!("Copied model '{}': {}", bundle.manifest.model.filename, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::setup_wizard::{ModelEntry, PackageSpec};
+
+    fn sha256(bytes: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn setup() -> SetupManifest {
+        SetupManifest {
+            packages: vec![
+                PackageSpec { name: "llama-cpp-python".to_string(), version: Some(">=0.2.90".to_string()), import_name: None },
+                PackageSpec { name: "pandas".to_string(), version: None, import_name: None },
+            ],
+            models: vec![ModelEntry { filename: "model.gguf".to_string(), url: None, size_bytes: None, ram_margin_bytes: None }],
+            default_model: "model.gguf".to_string(),
+        }
+    }
+
+    /// A valid bundle under a fresh directory
+    fn write_bundle() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bundle-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join(WHEELS_DIR)).unwrap();
+        fs::create_dir_all(dir.join(MODELS_DIR)).unwrap();
+
+        let mut packages = Vec::new();
+        for (name, version, wheel) in [
+            ("llama_cpp_python", "0.2.90", "llama_cpp_python-0.2.90-cp311-linux.whl"),
+            ("pandas", "2.2.0", "pandas-2.2.0-cp311-linux.whl"),
+            ("numpy", "1.26.4", "numpy-1.26.4-cp311-linux.whl"),
+        ] {
+            fs::write(dir.join(WHEELS_DIR).join(wheel), wheel).unwrap();
+            packages.push(BundlePackage { name: name.to_string(), version: version.to_string(), wheel: wheel.to_string(), sha256: sha256(wheel.as_bytes()) });
+        }
+        fs::write(dir.join(MODELS_DIR).join("model.gguf"), b"GGUF weights").unwrap();
+
+        let manifest = BundleManifest {
+            packages,
+            model: BundleModel { filename: "model.gguf".to_string(), sha256: sha256(b"GGUF weights") },
+        };
+        fs::write(dir.join(BUNDLE_MANIFEST), serde_json::to_string(&manifest).unwrap()).unwrap();
+        dir
+    }
+
+    fn edit_manifest(dir: &Path, edit: impl FnOnce(&mut BundleManifest)) {
+        let mut manifest = read_manifest(dir).unwrap();
+        edit(&mut manifest);
+        fs::write(dir.join(BUNDLE_MANIFEST), serde_json::to_string(&manifest).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_valid_bundle_installs_from_wheels_only() {
+        let dir = write_bundle();
+        let bundle = open_bundle(&dir, &setup()).unwrap();
+
+        let args = pip_install_args(&bundle, &setup());
+        assert_eq!(args[..5], ["-m", "pip", "install", "--no-index", "--find-links"]);
+        assert_eq!(args[5], dir.join(WHEELS_DIR).to_string_lossy());
+        assert_eq!(args[6..], ["llama_cpp_python==0.2.90", "pandas==2.2.0"]);
+
+        let destination = dir.join("installed/model.gguf");
+        install_model(&bundle, &destination).unwrap();
+        assert_eq!(fs::read(destination).unwrap(), b"GGUF weights");
+
+        assert_eq!(find_bundle(&dir), Some(dir.clone()));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_mismatches_name_the_entry() {
+        let error = |edit: fn(&mut BundleManifest)| {
+            let dir = write_bundle();
+            edit_manifest(&dir, edit);
+            let error = open_bundle(&dir, &setup()).unwrap_err();
+            fs::remove_dir_all(dir).ok();
+            error
+        };
+
+        let missing = error(|m| m.packages.retain(|p| p.name != "pandas"));
+        assert_eq!(missing, "Package 'pandas' is missing from the bundle manifest");
+        let old = error(|m| m.packages[0].version = "0.2.1".to_string());
+        assert!(old.starts_with("Package 'llama_cpp_python' in the bundle is version 0.2.1"), "{}", old);
+        let checksum = error(|m| m.packages[2].sha256 = "00".repeat(32));
+        assert!(checksum.starts_with("Wheel 'numpy-1.26.4-cp311-linux.whl' for package 'numpy': sha256 is"), "{}", checksum);
+        let model = error(|m| m.model.sha256 = "00".repeat(32));
+        assert!(model.starts_with("Model 'model.gguf': sha256 is"), "{}", model);
+        let other_model = error(|m| m.model.filename = "other.gguf".to_string());
+        assert!(other_model.starts_with("Model 'other.gguf' in the bundle isn't"), "{}", other_model);
+    }
+
+    #[test]
+    fn test_zipped_bundle_is_extracted_and_cleaned_up() {
+        let dir = write_bundle();
+        let zip_path = dir.with_extension("zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for entry in walk(&dir) {
+            let name = format!("bundle/{}", entry.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"));
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&fs::read(&entry).unwrap()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let files: u64 = walk(&dir).iter().map(|file| fs::metadata(file).unwrap().len()).sum();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(unpacked_size(&mut archive), files);
+
+        let bundle = open_bundle(&zip_path, &setup()).unwrap();
+        let extracted = bundle.dir.clone();
+        assert!(extracted.join(BUNDLE_MANIFEST).is_file());
+        drop(bundle);
+        assert!(!extracted.exists());
+
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_file(zip_path).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pip_output_is_streamed() {
+        let script = |body: &str| vec!["-c".to_string(), body.to_string()];
+        let lines = std::sync::Mutex::new(Vec::new());
+
+        run_pip("sh", &script("echo Processing a.whl; echo; echo Successfully installed a-1.0"), |line| {
+            lines.lock().unwrap().push(line)
+        }).await.unwrap();
+        assert_eq!(*lines.lock().unwrap(), ["Processing a.whl", "Successfully installed a-1.0"]);
+
+        let failed = run_pip("sh", &script("echo ERROR: No matching distribution >&2; exit 1"), |_| {}).await;
+        assert_eq!(failed.unwrap_err(), "Failed to install from offline bundle: ERROR: No matching distribution");
+    }
+
+    fn walk(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir).unwrap()
+            .flatten()
+            .flat_map(|entry| if entry.path().is_dir() { walk(&entry.path()) } else { vec![entry.path()] })
+            .collect()
+    }
+}
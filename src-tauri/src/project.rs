@@ -0,0 +1,324 @@
+//! Project files: the full bid saved by the desktop app
+//!
+//! A project is written as plain JSON, or as a zip holding `project.json`
+//! plus the shot attachments when they are embedded for sharing.
+
+use std::io::{Read, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use crate::attachments::{self, MAX_ATTACHMENT_BYTES};
+use crate::commands::bid::{ArchivedShot, BidMeta, BidRevision, ShotData, ShotGroup};
+use crate::state::audit::AuditEntry;
+use crate::tempfiles;
+
+/// Current project file format version
+pub const PROJECT_VERSION: u32 = 1;
+
+/// Name of the bid JSON inside a zipped project
+const PROJECT_ENTRY: &str = "project.json";
+
+/// Folder holding embedded attachments inside a zipped project
+const ATTACHMENTS_PREFIX: &str = "attachments";
+
+/// Saved bid with everything needed to reopen it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectFile {
+    pub version: u32,
+    pub bid_id: String,
+    pub saved_at: String,
+    #[serde(default)]
+    pub meta: BidMeta,
+    pub shots: Vec<ShotData>,
+    #[serde(default)]
+    pub groups: Vec<ShotGroup>,
+    /// Earlier versions kept by `revise_bid`, for `compare_bids`
+    #[serde(default)]
+    pub revisions: Vec<BidRevision>,
+    /// Deleted shots that can still be restored
+    #[serde(default)]
+    pub archived: Vec<ArchivedShot>,
+    /// Open read-only by default, e.g. for an approved version
+    #[serde(default)]
+    pub read_only: bool,
+    /// Per-shot audit trail, so the history goes wherever the file does
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<AuditEntry>,
+}
+
+impl ProjectFile {
+    /// Every shot whose attachments the project keeps: current, archived and
+    /// in earlier revisions
+    pub fn attachment_shots(&self) -> impl Iterator<Item = &ShotData> {
+        self.shots
+            .iter()
+            .chain(self.archived.iter().map(|archived| &archived.shot))
+            .chain(self.revisions.iter().flat_map(|revision| &revision.shots))
+    }
+}
+
+/// Write a project to `path`
+///
+/// When `attachments_root` is given the file is a zip with every attachment
+/// the shots reference embedded, otherwise it is plain JSON and attachments
+/// stay in the app data directory.
+pub fn write_project(path: &Path, project: &ProjectFile, attachments_root: Option<&Path>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(project)
+        .map_err(|e| format!("Failed to serialize project: {}", e))?;
+
+    let Some(root) = attachments_root else {
+        return std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write project file: {}", e));
+    };
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create project file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+    let zip_err = |e: zip::result::ZipError| format!("Failed to write project file: {}", e);
+
+    zip.start_file(PROJECT_ENTRY, options).map_err(zip_err)?;
+    zip.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write project file: {}", e))?;
+
+    for shot in &project.shots {
+        for attachment in &shot.attachments {
+            let source = attachments::attachment_path(root, &project.bid_id, &shot.id, attachment);
+            let bytes = match std::fs::read(&source) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    log::warn!("Skipping missing attachment {}: {}", source.display(), e);
+                    continue;
+                }
+            };
+
+            zip.start_file(embedded_name(&shot.id, attachment), options).map_err(zip_err)?;
+            zip.write_all(&bytes)
+                .map_err(|e| format!("Failed to write project file: {}", e))?;
+        }
+    }
+
+    zip.finish().map_err(zip_err)?;
+    Ok(())
+}
+
+/// Read a project file, restoring embedded attachments into `attachments_root`
+///
+/// Embedded files are checked as when attaching them; one that is too large
+/// or isn't a supported image is dropped from its shot.
+pub fn read_project(path: &Path, attachments_root: &Path) -> Result<ProjectFile, String> {
+    let (mut project, zip) = open_project(path)?;
+    let Some(mut zip) = zip else {
+        return Ok(project);
+    };
+
+    for shot in &mut project.shots {
+        let mut rejected = Vec::new();
+        for attachment in &shot.attachments {
+            let Ok(entry) = zip.by_name(&embedded_name(&shot.id, attachment)) else {
+                log::warn!("Project is missing embedded attachment {}", attachment.filename);
+                continue;
+            };
+
+            // The header's size can't be trusted, so read at most one byte past the limit
+            let mut contents = Vec::new();
+            entry.take(MAX_ATTACHMENT_BYTES + 1).read_to_end(&mut contents)
+                .map_err(|e| format!("Failed to read embedded attachment: {}", e))?;
+            if contents.len() as u64 > MAX_ATTACHMENT_BYTES {
+                log::warn!("Dropping oversized embedded attachment {}", attachment.filename);
+                rejected.push(attachment.id.clone());
+                continue;
+            }
+            if let Err(e) = attachments::image_dimensions(&contents) {
+                log::warn!("Dropping embedded attachment {}: {}", attachment.filename, e);
+                rejected.push(attachment.id.clone());
+                continue;
+            }
+
+            let target = attachments::attachment_path(attachments_root, &project.bid_id, &shot.id, attachment);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create attachment directory: {}", e))?;
+            }
+            tempfiles::write_atomic(&target, &contents)
+                .map_err(|e| format!("Failed to restore attachment: {}", e))?;
+        }
+        shot.attachments.retain(|attachment| !rejected.contains(&attachment.id));
+    }
+
+    Ok(project)
+}
+
+/// Read just the bid id of a project, without restoring attachments
+pub fn read_bid_id(path: &Path) -> Result<String, String> {
+    open_project(path).map(|(project, _)| project.bid_id)
+}
+
+type ProjectArchive = zip::ZipArchive<std::io::Cursor<Vec<u8>>>;
+
+/// Parse the project JSON, keeping the archive open if the file is zipped
+fn open_project(path: &Path) -> Result<(ProjectFile, Option<ProjectArchive>), String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read project file: {}", e))?;
+
+    if !bytes.starts_with(b"PK\x03\x04") {
+        return Ok((parse_project(&bytes)?, None));
+    }
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+        .map_err(|e| format!("Failed to open project file: {}", e))?;
+
+    let mut json = Vec::new();
+    zip.by_name(PROJECT_ENTRY)
+        .map_err(|_| format!("Project file has no {}", PROJECT_ENTRY))?
+        .read_to_end(&mut json)
+        .map_err(|e| format!("Failed to read project file: {}", e))?;
+
+    Ok((parse_project(&json)?, Some(zip)))
+}
+
+fn parse_project(json: &[u8]) -> Result<ProjectFile, String> {
+    let project: ProjectFile = serde_json::from_slice(json)
+        .map_err(|e| format!("Failed to parse project file: {}", e))?;
+
+    if project.version > PROJECT_VERSION {
+        return Err(format!(
+            "Project was saved by a newer version of the app (format {})",
+            project.version
+        ));
+    }
+
+    Ok(project)
+}
+
+/// Zip entry name for an embedded attachment
+///
+/// Uses the same sanitized names as the on-disk layout, so entries can't
+/// escape the attachments folder when extracted.
+fn embedded_name(shot_id: &str, attachment: &attachments::Attachment) -> String {
+    format!(
+        "{}/{}/{}",
+        ATTACHMENTS_PREFIX,
+        attachments::sanitize(shot_id),
+        attachment.stored_name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::Attachment;
+
+    /// PNG header for a 64 x 32 image
+    fn png() -> Vec<u8> {
+        let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        bytes.extend_from_slice(b"IHDR");
+        bytes.extend_from_slice(&64u32.to_be_bytes());
+        bytes.extend_from_slice(&32u32.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    fn project_with_attachment() -> (ProjectFile, Attachment) {
+        let attachment = Attachment {
+            id: "a1".to_string(),
+            filename: "board.png".to_string(),
+            width: 64,
+            height: 32,
+            added_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+
+        let shot = ShotData {
+            id: "SH010".to_string(),
+            scene_number: "1".to_string(),
+            description: "Hero shot".to_string(),
+            attachments: vec![attachment.clone()],
+            ..Default::default()
+        };
+
+        let project = ProjectFile {
+            version: PROJECT_VERSION,
+            bid_id: "bid-1".to_string(),
+            saved_at: "2024-01-01T00:00:00Z".to_string(),
+            meta: BidMeta::default(),
+            shots: vec![shot],
+            groups: Vec::new(),
+            revisions: Vec::new(),
+            archived: Vec::new(),
+            read_only: false,
+            audit: Vec::new(),
+        };
+
+        (project, attachment)
+    }
+
+    #[test]
+    fn test_json_round_trip_keeps_attachment_metadata() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("bid.vfxbid");
+        let (project, attachment) = project_with_attachment();
+
+        write_project(&path, &project, None).unwrap();
+        let loaded = read_project(&path, &dir.join("attachments")).unwrap();
+
+        assert_eq!(loaded.bid_id, "bid-1");
+        assert_eq!(loaded.shots[0].attachments, vec![attachment]);
+        assert_eq!(read_bid_id(&path).unwrap(), "bid-1");
+    }
+
+    #[test]
+    fn test_zip_embeds_and_restores_attachments() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let saved_root = dir.join("saved");
+        let restored_root = dir.join("restored");
+        let path = dir.join("bid.vfxbid");
+        let (project, attachment) = project_with_attachment();
+
+        let stored = attachments::attachment_path(&saved_root, "bid-1", "SH010", &attachment);
+        std::fs::create_dir_all(stored.parent().unwrap()).unwrap();
+        std::fs::write(&stored, png()).unwrap();
+
+        write_project(&path, &project, Some(&saved_root)).unwrap();
+        let loaded = read_project(&path, &restored_root).unwrap();
+
+        let restored = attachments::attachment_path(&restored_root, "bid-1", "SH010", &attachment);
+        assert_eq!(loaded.shots[0].attachments.len(), 1);
+        assert_eq!(std::fs::read(restored).unwrap(), png());
+        assert_eq!(read_bid_id(&path).unwrap(), "bid-1");
+    }
+
+    #[test]
+    fn test_embedded_attachments_are_revalidated() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let saved_root = dir.join("saved");
+        let restored_root = dir.join("restored");
+        let path = dir.join("bid.vfxbid");
+        let (project, attachment) = project_with_attachment();
+
+        // Swapped for something else inside the zip
+        let stored = attachments::attachment_path(&saved_root, "bid-1", "SH010", &attachment);
+        std::fs::create_dir_all(stored.parent().unwrap()).unwrap();
+        std::fs::write(&stored, b"#!/bin/sh not an image").unwrap();
+
+        write_project(&path, &project, Some(&saved_root)).unwrap();
+        let loaded = read_project(&path, &restored_root).unwrap();
+
+        assert!(loaded.shots[0].attachments.is_empty());
+        assert!(!attachments::attachment_path(&restored_root, "bid-1", "SH010", &attachment).exists());
+    }
+
+    #[test]
+    fn test_rejects_newer_format() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("bid.vfxbid");
+        let (mut project, _) = project_with_attachment();
+        project.version = PROJECT_VERSION + 1;
+
+        write_project(&path, &project, None).unwrap();
+        assert!(read_project(&path, dir).unwrap_err().contains("newer version"));
+    }
+}
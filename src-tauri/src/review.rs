@@ -0,0 +1,243 @@
+//! Which extracted shots a bidder should double-check
+//!
+//! The LLM sometimes invents shots or gets the complexity wrong. The sidecar
+//! sends a `confidence` and `extraction_flags` with each shot it extracts;
+//! on top of those the flags below are worked out here from the bid itself.
+//! Shots with a low confidence or any flag make up the review queue until
+//! someone marks them reviewed.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::bid::{description_tokens, jaccard_similarity, ShotData};
+use crate::scene_order::compare_scene_numbers;
+
+/// Shots below this confidence are queued even without a flag
+pub const REVIEW_CONFIDENCE: f32 = 0.6;
+
+/// Description similarity above which two shots look like the same one
+pub const DUPLICATE_SIMILARITY: f64 = 0.9;
+
+/// How far a price may sit from its scene's median before it's flagged
+pub const PRICE_OUTLIER_FACTOR: f64 = 3.0;
+
+pub const DUPLICATE_DESCRIPTION: &str = "duplicate_description";
+pub const HIGH_COMPLEXITY_NO_HOURS: &str = "high_complexity_no_hours";
+pub const PRICE_OUTLIER: &str = "price_outlier";
+/// Extracted by the sidecar but not priced by it
+pub const UNPRICED: &str = "unpriced";
+
+/// Flags set here rather than by the sidecar; recomputed on every check
+const HEURISTIC_FLAGS: [&str; 3] = [DUPLICATE_DESCRIPTION, HIGH_COMPLEXITY_NO_HOURS, PRICE_OUTLIER];
+
+/// Who marked a shot as checked, and when
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct ShotReview {
+    /// OS user the app ran as
+    pub reviewed_by: Option<String>,
+    /// RFC 3339
+    pub reviewed_at: String,
+}
+
+/// How much a flag should push a shot up the queue
+pub fn flag_severity(flag: &str) -> u32 {
+    match flag {
+        DUPLICATE_DESCRIPTION | HIGH_COMPLEXITY_NO_HOURS | UNPRICED => 3,
+        PRICE_OUTLIER | "ambiguous_scene" => 2,
+        _ => 1,
+    }
+}
+
+/// Replace the heuristic flags on `shots` with fresh ones, keeping the
+/// sidecar's
+pub fn apply_heuristics(shots: &mut [ShotData]) {
+    let flags = heuristic_flags(shots);
+    for shot in shots.iter_mut() {
+        shot.extraction_flags.retain(|flag| !HEURISTIC_FLAGS.contains(&flag.as_str()));
+        for flag in flags.get(&shot.id).into_iter().flatten() {
+            if !shot.extraction_flags.iter().any(|f| f == flag) {
+                shot.extraction_flags.push(flag.to_string());
+            }
+        }
+    }
+}
+
+/// Heuristic flags by shot id
+fn heuristic_flags(shots: &[ShotData]) -> HashMap<String, Vec<&'static str>> {
+    let mut flags: HashMap<String, Vec<&'static str>> = HashMap::new();
+    let mut flag = |id: &str, flag: &'static str| {
+        let entry = flags.entry(id.to_string()).or_default();
+        if !entry.contains(&flag) {
+            entry.push(flag);
+        }
+    };
+
+    let tokens: Vec<_> = shots.iter().map(|s| description_tokens(&s.description)).collect();
+    for i in 0..shots.len() {
+        for j in (i + 1)..shots.len() {
+            if tokens[i].is_empty() || tokens[j].is_empty() {
+                continue;
+            }
+            if jaccard_similarity(&tokens[i], &tokens[j]) > DUPLICATE_SIMILARITY {
+                flag(&shots[i].id, DUPLICATE_DESCRIPTION);
+                flag(&shots[j].id, DUPLICATE_DESCRIPTION);
+            }
+        }
+    }
+
+    for shot in shots {
+        if shot.complexity.trim().eq_ignore_ascii_case("high") && shot.estimated_hours.unwrap_or(0.0) <= 0.0 {
+            flag(&shot.id, HIGH_COMPLEXITY_NO_HOURS);
+        }
+    }
+
+    let mut scenes: HashMap<&str, Vec<f64>> = HashMap::new();
+    for shot in shots {
+        if let Some(price) = shot.final_price.filter(|p| *p > 0.0) {
+            scenes.entry(shot.scene_number.trim()).or_default().push(price);
+        }
+    }
+    for shot in shots {
+        let (Some(price), Some(prices)) = (shot.final_price.filter(|p| *p > 0.0), scenes.get(shot.scene_number.trim())) else {
+            continue;
+        };
+        // A median of two is just their average; too few to call an outlier
+        if prices.len() < 3 {
+            continue;
+        }
+        let median = median(prices);
+        if price > median * PRICE_OUTLIER_FACTOR || price * PRICE_OUTLIER_FACTOR < median {
+            flag(&shot.id, PRICE_OUTLIER);
+        }
+    }
+
+    flags
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Copy the sidecar's confidence and flags from `extracted` to the shots
+/// of the same id that came back without them, as the final list read from
+/// the generated workbook does
+pub fn carry_scores(extracted: &[ShotData], shots: &mut [ShotData]) {
+    let scores: HashMap<&str, &ShotData> = extracted.iter().map(|s| (s.id.as_str(), s)).collect();
+    for shot in shots.iter_mut() {
+        let Some(source) = scores.get(shot.id.as_str()) else {
+            continue;
+        };
+        if shot.confidence.is_none() {
+            shot.confidence = source.confidence;
+        }
+        if shot.extraction_flags.is_empty() {
+            shot.extraction_flags = source.extraction_flags.clone();
+        }
+    }
+}
+
+/// Whether a shot needs checking: not yet reviewed, and unsure or flagged
+pub fn needs_review(shot: &ShotData) -> bool {
+    shot.review.is_none()
+        && (shot.confidence.is_some_and(|c| c < REVIEW_CONFIDENCE) || !shot.extraction_flags.is_empty())
+}
+
+/// Shots to check with fresh heuristic flags, least confident first, then
+/// by flag severity and scene
+pub fn review_queue(mut shots: Vec<ShotData>) -> Vec<ShotData> {
+    apply_heuristics(&mut shots);
+    let severity = |shot: &ShotData| shot.extraction_flags.iter().map(|f| flag_severity(f)).sum::<u32>();
+
+    let mut queue: Vec<ShotData> = shots.into_iter().filter(needs_review).collect();
+    queue.sort_by(|a, b| {
+        // Shots the sidecar didn't score count as sure
+        let confidence = |shot: &ShotData| shot.confidence.unwrap_or(1.0);
+        confidence(a).total_cmp(&confidence(b))
+            .then_with(|| severity(b).cmp(&severity(a)))
+            .then_with(|| compare_scene_numbers(&a.scene_number, &b.scene_number))
+            .then_with(|| a.sort_index.cmp(&b.sort_index))
+    });
+    queue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, scene: &str, description: &str, price: f64) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            scene_number: scene.to_string(),
+            description: description.to_string(),
+            complexity: "Medium".to_string(),
+            estimated_hours: Some(10.0),
+            final_price: Some(price),
+            ..Default::default()
+        }
+    }
+
+    fn flags_of<'a>(shots: &'a [ShotData], id: &str) -> &'a [String] {
+        &shots.iter().find(|s| s.id == id).unwrap().extraction_flags
+    }
+
+    #[test]
+    fn test_heuristic_flags() {
+        let mut shots = vec![
+            shot("SH010", "1", "Sky replacement over the harbour", 1000.0),
+            shot("SH020", "2", "sky replacement over the Harbour!", 1100.0),
+            shot("SH030", "3", "Muzzle flash", 900.0),
+            shot("SH040", "3", "Blood hit", 1000.0),
+            shot("SH050", "3", "Full CG creature", 5000.0),
+            ShotData { complexity: "High".to_string(), estimated_hours: None, ..shot("SH060", "4", "Crowd", 0.0) },
+        ];
+        shots[0].extraction_flags = vec!["ambiguous_scene".to_string(), PRICE_OUTLIER.to_string()];
+
+        apply_heuristics(&mut shots);
+        // The sidecar's flag stays, the stale heuristic one goes
+        assert_eq!(flags_of(&shots, "SH010"), ["ambiguous_scene", DUPLICATE_DESCRIPTION]);
+        assert_eq!(flags_of(&shots, "SH020"), [DUPLICATE_DESCRIPTION]);
+        assert_eq!(flags_of(&shots, "SH050"), [PRICE_OUTLIER]);
+        assert!(flags_of(&shots, "SH030").is_empty());
+        assert_eq!(flags_of(&shots, "SH060"), [HIGH_COMPLEXITY_NO_HOURS]);
+    }
+
+    #[test]
+    fn test_queue_orders_by_confidence_then_severity() {
+        let shots = vec![
+            ShotData { confidence: Some(0.9), extraction_flags: vec!["no_vfx_keywords".to_string()], ..shot("SH010", "1", "Rain", 100.0) },
+            ShotData { confidence: Some(0.3), ..shot("SH020", "2", "Snow", 100.0) },
+            ShotData { confidence: Some(0.95), ..shot("SH030", "3", "Fog", 100.0) },
+            ShotData { complexity: "High".to_string(), estimated_hours: Some(0.0), ..shot("SH040", "4", "Fire", 100.0) },
+            ShotData {
+                confidence: Some(0.1),
+                review: Some(ShotReview { reviewed_by: None, reviewed_at: "2024-01-01T00:00:00Z".to_string() }),
+                ..shot("SH050", "5", "Smoke", 100.0)
+            },
+        ];
+
+        let ids: Vec<String> = review_queue(shots).into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["SH020", "SH010", "SH040"]);
+    }
+
+    #[test]
+    fn test_scores_carry_over_by_id() {
+        let extracted = vec![ShotData {
+            confidence: Some(0.4),
+            extraction_flags: vec!["ambiguous_scene".to_string()],
+            ..shot("SH010", "1", "", 0.0)
+        }];
+        let mut shots = vec![shot("SH010", "1", "", 0.0), shot("SH020", "1", "", 0.0)];
+
+        carry_scores(&extracted, &mut shots);
+        assert_eq!(shots[0].confidence, Some(0.4));
+        assert_eq!(shots[0].extraction_flags, ["ambiguous_scene"]);
+        assert_eq!(shots[1].confidence, None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Splitting plain-text scripts into scenes before extraction
+//!
+//! For `.txt` and `.md` scripts the scene breaks are plain to see, so they
+//! are found here rather than left to the LLM: sluglines ("INT. HARBOUR -
+//! NIGHT", optionally numbered) or, in Markdown without sluglines, headers.
+//! The boundaries go to the sidecar as `scene_hints` so it can chunk the
+//! script by scene. PDFs are still parsed by the sidecar alone.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// Extensions the preprocessor reads, lowercase
+const TEXT_EXTENSIONS: [&str; 3] = ["txt", "md", "markdown"];
+
+/// Slugline openings, checked against the uppercased line
+const SLUGLINE_PREFIXES: [&str; 8] = ["INT./EXT.", "EXT./INT.", "INT/EXT.", "EXT/INT.", "I/E.", "INT.", "EXT.", "EST."];
+
+/// One scene found in the text
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SceneBoundary {
+    /// Number printed with the heading, if the script numbers its scenes
+    pub scene_number: Option<String>,
+    /// The slugline or header text, without numbers or `#`s
+    pub heading: String,
+    /// 1-based line of the heading
+    pub start_line: usize,
+    /// 1-based last line of the scene
+    pub end_line: usize,
+}
+
+/// Scenes of the script at `path`; None for files the preprocessor doesn't
+/// handle or can't read
+pub fn preprocess(path: &Path) -> Option<Vec<SceneBoundary>> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !TEXT_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    Some(detect_scenes(&String::from_utf8_lossy(&bytes), extension != "txt"))
+}
+
+/// Scene boundaries in `text`
+///
+/// Sluglines win; Markdown headers only count when there are none, at the
+/// shallowest level used more than once so a lone title isn't a scene.
+pub fn detect_scenes(text: &str, markdown: bool) -> Vec<SceneBoundary> {
+    let lines: Vec<&str> = text.lines().collect();
+    let sluglines: Vec<(usize, Option<String>, String)> = lines.iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = if markdown { line.trim_start_matches('#') } else { line };
+            parse_slugline(line).map(|(number, heading)| (index, number, heading))
+        })
+        .collect();
+
+    let headings = if !sluglines.is_empty() || !markdown {
+        sluglines
+    } else {
+        markdown_headings(&lines)
+    };
+
+    headings.iter()
+        .enumerate()
+        .map(|(i, (index, scene_number, heading))| SceneBoundary {
+            scene_number: scene_number.clone(),
+            heading: heading.clone(),
+            start_line: index + 1,
+            end_line: headings.get(i + 1).map_or(lines.len(), |next| next.0),
+        })
+        .collect()
+}
+
+/// `(scene number, heading)` when `line` is a slugline such as
+/// "12A INT. HARBOUR - NIGHT 12A"
+fn parse_slugline(line: &str) -> Option<(Option<String>, String)> {
+    let line = line.trim();
+    let (number, rest) = match line.split_once(char::is_whitespace) {
+        Some((first, rest)) if is_scene_number(first.trim_end_matches('.')) => {
+            (Some(first.trim_end_matches('.').to_string()), rest.trim_start())
+        }
+        _ => (None, line),
+    };
+
+    let upper = rest.to_uppercase();
+    if !SLUGLINE_PREFIXES.iter().any(|prefix| upper.starts_with(prefix)) {
+        return None;
+    }
+
+    // Shooting scripts repeat the number at the end of the line
+    let heading = match (&number, rest.rsplit_once(char::is_whitespace)) {
+        (Some(number), Some((heading, last))) if last == number => heading.trim_end(),
+        _ => rest,
+    };
+    Some((number, heading.to_string()))
+}
+
+/// "12", "12A", "A12" but not words
+fn is_scene_number(token: &str) -> bool {
+    token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().all(|c| c.is_ascii_alphanumeric())
+        && token.chars().filter(|c| c.is_ascii_alphabetic()).count() <= 2
+}
+
+/// Headers at the shallowest level used more than once, else every header
+fn markdown_headings(lines: &[&str]) -> Vec<(usize, Option<String>, String)> {
+    let headers: Vec<(usize, usize, &str)> = lines.iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let level = line.chars().take_while(|c| *c == '#').count();
+            let text = &line[level..];
+            (1..=6).contains(&level)
+                .then_some(text)
+                .filter(|text| text.starts_with(' ') && !text.trim().is_empty())
+                .map(|text| (index, level, text.trim().trim_end_matches('#').trim_end()))
+        })
+        .collect();
+
+    let count = |level: usize| headers.iter().filter(|h| h.1 == level).count();
+    let level = (1..=6).find(|level| count(*level) > 1);
+
+    headers.into_iter()
+        .filter(|(_, l, _)| level.is_none_or(|level| *l == level))
+        .map(|(index, _, text)| {
+            // "Scene 12: Harbour" or "12. Harbour" carry their number
+            let text = text.strip_prefix("Scene ").or_else(|| text.strip_prefix("SCENE ")).unwrap_or(text);
+            let first = text.split(|c: char| c.is_whitespace() || c == ':').next().unwrap_or_default();
+            let first = first.trim_end_matches('.');
+            if is_scene_number(first) {
+                let heading = text[first.len()..].trim_start_matches(['.', ':']).trim();
+                (index, Some(first.to_string()), heading.to_string())
+            } else {
+                (index, None, text.to_string())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(scenes: &[SceneBoundary]) -> Vec<(Option<&str>, &str, usize, usize)> {
+        scenes.iter()
+            .map(|s| (s.scene_number.as_deref(), s.heading.as_str(), s.start_line, s.end_line))
+            .collect()
+    }
+
+    #[test]
+    fn test_sluglines() {
+        let text = "\
+FADE IN:
+
+INT. HARBOUR - NIGHT
+
+Rain lashes the docks.
+12A EXT. LIGHTHOUSE - DAY 12A
+The lamp explodes.
+int./ext. car - moving
+INTERIOR DESIGN is not a slugline.
+";
+        let scenes = detect_scenes(text, false);
+        assert_eq!(summary(&scenes), vec![
+            (None, "INT. HARBOUR - NIGHT", 3, 5),
+            (Some("12A"), "EXT. LIGHTHOUSE - DAY", 6, 7),
+            (None, "int./ext. car - moving", 8, 9),
+        ]);
+        assert!(detect_scenes("Just some notes.\nNo scenes here.", false).is_empty());
+    }
+
+    #[test]
+    fn test_markdown_headers() {
+        let text = "\
+# The Harbour
+## Scene 1: Docks at night
+Rain.
+## 2. Lighthouse
+The lamp explodes.
+### Notes
+## Escape
+";
+        let scenes = detect_scenes(text, true);
+        assert_eq!(summary(&scenes), vec![
+            (Some("1"), "Docks at night", 2, 3),
+            (Some("2"), "Lighthouse", 4, 6),
+            (None, "Escape", 7, 7),
+        ]);
+
+        // Sluglines under headers take precedence over the headers
+        let scenes = detect_scenes("# Title\n## INT. HARBOUR - NIGHT\nRain.\n## Notes\n", true);
+        assert_eq!(summary(&scenes), vec![(None, "INT. HARBOUR - NIGHT", 2, 4)]);
+    }
+
+    #[test]
+    fn test_only_text_scripts_are_preprocessed() {
+        let dir = std::env::temp_dir().join(format!("scenes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("script.TXT"), "INT. HARBOUR - NIGHT\nRain.\n").unwrap();
+        std::fs::write(dir.join("script.pdf"), "INT. HARBOUR - NIGHT\n").unwrap();
+
+        assert_eq!(preprocess(&dir.join("script.TXT")).unwrap().len(), 1);
+        assert_eq!(preprocess(&dir.join("script.pdf")), None);
+        assert_eq!(preprocess(&dir.join("missing.md")), None);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,97 @@
+//! Ordering scene numbers the way they read
+//!
+//! Scripts number scenes "1", "1A", "2", "10", "12B"; compared as plain
+//! strings "10" lands before "2". Everything that lists shots by scene goes
+//! through `compare_scene_numbers` so the order is the same everywhere.
+
+use std::cmp::Ordering;
+
+/// Compare scene numbers the way they read: 2 < 10 < 10A < 10b < 11
+///
+/// Runs of digits compare by value, everything else case-insensitively.
+/// Numbered scenes sort before unnumbered ones. Shot ids ("SH010") follow the
+/// same rules. Numbers that read the same ("010" and "10") fall back to their
+/// text so the order is total.
+pub fn compare_scene_numbers(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.trim(), b.trim());
+    natural_order(a, b).then_with(|| a.cmp(b))
+}
+
+fn natural_order(a: &str, b: &str) -> Ordering {
+    let starts_numeric = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+    match (starts_numeric(a), starts_numeric(b)) {
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        _ => {}
+    }
+
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let split = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+                let (da, ra) = a.split_at(split(a));
+                let (db, rb) = b.split_at(split(b));
+                let (da, db) = (da.trim_start_matches('0'), db.trim_start_matches('0'));
+                let order = da.len().cmp(&db.len()).then_with(|| da.cmp(db));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a, b) = (ra, rb);
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(scenes: &[&'static str]) -> Vec<&'static str> {
+        let mut scenes = scenes.to_vec();
+        scenes.sort_by(|a, b| compare_scene_numbers(a, b));
+        scenes
+    }
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        assert_eq!(compare_scene_numbers("2", "10"), Ordering::Less);
+        assert_eq!(compare_scene_numbers("100", "99"), Ordering::Greater);
+        assert_eq!(compare_scene_numbers(" 7", "7 "), Ordering::Equal);
+        assert_eq!(sorted(&["11", "10b", "INT", "2", "010A", "10", "1", "100"]), vec!["1", "2", "10", "010A", "10b", "11", "100", "INT"]);
+    }
+
+    #[test]
+    fn test_letter_suffixes_sort_within_their_number() {
+        assert_eq!(compare_scene_numbers("1A", "1B"), Ordering::Less);
+        assert_eq!(compare_scene_numbers("1B", "2"), Ordering::Less);
+        assert_eq!(compare_scene_numbers("1", "1A"), Ordering::Less);
+        assert_eq!(sorted(&["12B", "1A", "12", "1", "12a", "2"]), vec!["1", "1A", "2", "12", "12a", "12B"]);
+        // Mixed runs keep going past the first letter
+        assert_eq!(sorted(&["4A10", "4A9", "4B1"]), vec!["4A9", "4A10", "4B1"]);
+    }
+
+    #[test]
+    fn test_unnumbered_scenes_sort_alphabetically_after_numbers() {
+        assert_eq!(compare_scene_numbers("99Z", "EXT"), Ordering::Less);
+        assert_eq!(sorted(&["pickup", "INT", "", "Ext", "3"]), vec!["3", "", "Ext", "INT", "pickup"]);
+        assert_eq!(sorted(&["SH020", "SH3", "sh10"]), vec!["SH3", "sh10", "SH020"]);
+    }
+
+    #[test]
+    fn test_equal_readings_still_have_an_order() {
+        assert_eq!(compare_scene_numbers("010", "10"), "010".cmp("10"));
+        assert_eq!(compare_scene_numbers("1a", "1A"), Ordering::Greater);
+        assert_eq!(compare_scene_numbers("1A", "1A"), Ordering::Equal);
+    }
+}
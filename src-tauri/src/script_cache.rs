@@ -0,0 +1,202 @@
+//! Results of earlier `process_script` runs, by script content
+//!
+//! Re-dropping a script that was already processed would otherwise re-run
+//! the whole pipeline for the same bid. Scripts are hashed (SHA-256, read
+//! in chunks off the async runtime) and each successful run is remembered
+//! in `app_data_dir()/script_cache.json`. An entry whose workbook is gone
+//! is a miss and is dropped.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::error::AppError;
+use crate::tempfiles;
+
+/// Entries kept, newest first
+pub const CACHE_LIMIT: usize = 50;
+
+/// Scripts at least this large report hashing progress
+pub const HASH_PROGRESS_MIN_BYTES: u64 = 64 << 20;
+
+const HASH_CHUNK_BYTES: usize = 1 << 20;
+
+/// Chunks between progress reports
+const PROGRESS_EVERY_CHUNKS: u64 = 8;
+
+/// One earlier run, as `script-result-cached` reports it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct CachedResult {
+    /// Lowercase hex SHA-256 of the script's contents
+    pub hash: String,
+    /// Where the script was when it was processed
+    pub script_path: String,
+    pub excel_path: String,
+    pub shot_count: usize,
+    /// RFC 3339
+    pub processed_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ScriptCache {
+    pub entries: Vec<CachedResult>,
+}
+
+impl ScriptCache {
+    /// The entry for `hash` whose workbook still exists
+    ///
+    /// Entries whose workbook is gone are removed; returns whether any were.
+    pub fn lookup(&mut self, hash: &str) -> (Option<CachedResult>, bool) {
+        let pruned = self.prune();
+        (self.entries.iter().find(|entry| entry.hash == hash).cloned(), pruned)
+    }
+
+    /// Drop entries whose workbook was deleted; true when any were
+    pub fn prune(&mut self) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| Path::new(&entry.excel_path).is_file());
+        self.entries.len() != before
+    }
+
+    /// Put `entry` first, replacing any for the same script contents
+    pub fn insert(&mut self, entry: CachedResult) {
+        self.entries.retain(|existing| existing.hash != entry.hash);
+        self.entries.insert(0, entry);
+        self.entries.truncate(CACHE_LIMIT);
+    }
+}
+
+/// `app_data_dir()/script_cache.json`
+pub fn cache_path(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    use tauri::Manager;
+
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("script_cache.json"))
+        .map_err(|e| AppError::io(format!("Failed to resolve app data directory: {}", e)))
+}
+
+/// Read the cache; a missing or unreadable file is an empty one
+pub fn load(path: &Path) -> ScriptCache {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return ScriptCache::default();
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable script cache {}: {}", path.display(), e);
+        ScriptCache::default()
+    })
+}
+
+pub fn save(path: &Path, cache: &ScriptCache) -> Result<(), AppError> {
+    let json = serde_json::to_vec_pretty(cache)
+        .map_err(|e| AppError::io(format!("Failed to serialize the script cache: {}", e)))?;
+    tempfiles::write_atomic(path, &json)
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Lowercase hex SHA-256 of the file at `path`
+///
+/// Blocks; `progress` gets the bytes hashed so far and the file size,
+/// every few megabytes, for files of at least `HASH_PROGRESS_MIN_BYTES`.
+pub fn hash_file(path: &Path, progress: impl Fn(u64, u64)) -> Result<String, AppError> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| AppError::io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let total = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let report = total >= HASH_PROGRESS_MIN_BYTES;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_BYTES];
+    let mut hashed = 0u64;
+    let mut chunks = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| AppError::io(format!("Failed to read {}: {}", path.display(), e)))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        hashed += read as u64;
+        chunks += 1;
+        if report && chunks.is_multiple_of(PROGRESS_EVERY_CHUNKS) {
+            progress(hashed, total);
+        }
+    }
+    if report {
+        progress(hashed, total);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn entry(hash: &str, excel_path: &Path) -> CachedResult {
+        CachedResult {
+            hash: hash.to_string(),
+            script_path: "/scripts/pilot.pdf".to_string(),
+            excel_path: excel_path.to_string_lossy().to_string(),
+            shot_count: 12,
+            processed_at: "2026-10-01T09:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let small = dir.join("pilot.txt");
+        std::fs::write(&small, b"abc").unwrap();
+        let reports = RefCell::new(Vec::new());
+        let hash = hash_file(&small, |done, total| reports.borrow_mut().push((done, total))).unwrap();
+        assert_eq!(hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(reports.borrow().is_empty());
+
+        let large = dir.join("feature.pdf");
+        let size = HASH_PROGRESS_MIN_BYTES + 3;
+        std::fs::File::create(&large).unwrap().set_len(size).unwrap();
+        hash_file(&large, |done, total| reports.borrow_mut().push((done, total))).unwrap();
+        let reports = reports.into_inner();
+        assert_eq!(reports.last(), Some(&(size, size)));
+        assert!(reports.len() > 1 && reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        assert!(hash_file(&dir.join("missing.pdf"), |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn test_lookup_prunes_deleted_workbooks() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let kept = dir.join("pilot_bid.xlsx");
+        std::fs::write(&kept, b"xlsx").unwrap();
+
+        let mut cache = ScriptCache::default();
+        cache.insert(entry("aaa", &kept));
+        cache.insert(entry("bbb", &dir.join("deleted_bid.xlsx")));
+        assert_eq!(cache.entries[0].hash, "bbb");
+
+        let (miss, pruned) = cache.lookup("bbb");
+        assert!(miss.is_none() && pruned);
+        let (hit, pruned) = cache.lookup("aaa");
+        assert_eq!(hit.map(|e| e.shot_count), Some(12));
+        assert!(!pruned);
+
+        // Re-processing replaces the entry rather than adding one
+        cache.insert(CachedResult { shot_count: 14, ..entry("aaa", &kept) });
+        assert_eq!(cache.entries.len(), 1);
+        for n in 0..CACHE_LIMIT + 1 {
+            cache.insert(entry(&n.to_string(), &kept));
+        }
+        assert_eq!(cache.entries.len(), CACHE_LIMIT);
+
+        let path = dir.join("script_cache.json");
+        save(&path, &cache).unwrap();
+        assert_eq!(load(&path), cache);
+        assert_eq!(load(&dir.join("none.json")), ScriptCache::default());
+    }
+}
@@ -0,0 +1,478 @@
+//! Self-test behind the "run diagnostics" button
+//!
+//! Walks every integration point from the config directory to an RPC round
+//! trip and reports each one on its own, so support can see the first thing
+//! that broke. Every check has its own time limit; a hung probe fails by
+//! itself instead of stalling the report.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::commands::model::validate_model_file;
+use crate::setup_wizard::{format_bytes, free_space_bytes, parse_python_version, PackageSpec};
+use crate::sidecar::{AsyncRpcClient, RpcMethod};
+use crate::state::session::SessionSnapshot;
+
+/// Limit for file system checks
+const FS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Limit for launching Python and importing the packages; importing
+/// llama_cpp or chromadb cold can take a while
+const PYTHON_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Limit for the LLM endpoint and the RPC health call
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Free space below this fails the disk check
+pub const MIN_FREE_BYTES: u64 = 1_000_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Not applicable, or blocked by an earlier failure
+    Skip,
+}
+
+/// Settings screen that can fix a failed check
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsScreen {
+    Llm,
+    Paths,
+    /// The setup wizard: Python, packages and the model
+    Setup,
+    Storage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Remediation {
+    pub screen: SettingsScreen,
+    pub hint: String,
+}
+
+/// One item of the checklist
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CheckResult {
+    /// Stable id, e.g. "python_launch"
+    pub id: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// Extra lines such as the paths searched
+    pub details: Vec<String>,
+    pub duration_ms: u64,
+    pub remediation: Option<Remediation>,
+}
+
+/// Result of `run_self_test`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SelfTestReport {
+    pub started_at: String,
+    pub duration_ms: u64,
+    /// No check failed; skipped checks don't count
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+/// What a probe found, before it is timed
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    pub status: CheckStatus,
+    pub message: String,
+    pub details: Vec<String>,
+    pub remediation: Option<Remediation>,
+}
+
+impl CheckOutcome {
+    pub fn pass(message: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Pass, message: message.into(), details: Vec::new(), remediation: None }
+    }
+
+    pub fn skip(message: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Skip, message: message.into(), details: Vec::new(), remediation: None }
+    }
+
+    pub fn fail(message: impl Into<String>, screen: SettingsScreen, hint: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            message: message.into(),
+            details: Vec::new(),
+            remediation: Some(Remediation { screen, hint: hint.into() }),
+        }
+    }
+
+    pub fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Everything the checks look at, gathered by the command
+pub struct SelfTestInputs {
+    pub config_dir: Option<PathBuf>,
+    pub script_candidates: Vec<PathBuf>,
+    /// Interpreter the sidecar is started with
+    pub python: String,
+    pub packages: Vec<PackageSpec>,
+    pub model_path: PathBuf,
+    pub llm_server_url: String,
+    pub output_dir: Option<PathBuf>,
+    /// Where models, attachments and the journal are written
+    pub data_dir: Option<PathBuf>,
+    /// Client for the sidecar, if it is running
+    pub rpc: Option<AsyncRpcClient>,
+}
+
+/// Run every check in order
+pub async fn run_self_test(inputs: SelfTestInputs) -> SelfTestReport {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let started = Instant::now();
+    let mut checks = Vec::new();
+
+    let config_dir = inputs.config_dir.clone();
+    checks.push(run_check("config_dir_writable", FS_TIMEOUT, blocking(move || match config_dir {
+        Some(dir) => probe_writable(&dir, true, SettingsScreen::Storage),
+        None => CheckOutcome::fail("Couldn't locate the config directory", SettingsScreen::Storage, "Check the app can read your home directory"),
+    })).await);
+
+    let settings_file = inputs.config_dir.as_ref().map(|dir| dir.join("session.json"));
+    checks.push(run_check("settings_parse", FS_TIMEOUT, blocking(move || match settings_file {
+        Some(path) => probe_settings(&path),
+        None => CheckOutcome::skip("No config directory to read settings from"),
+    })).await);
+
+    let candidates = inputs.script_candidates.clone();
+    checks.push(run_check("sidecar_script", FS_TIMEOUT, blocking(move || probe_script(&candidates))).await);
+
+    let python_check = run_check("python_launch", PYTHON_TIMEOUT, probe_python(&inputs.python)).await;
+    let python_ok = python_check.status == CheckStatus::Pass;
+    checks.push(python_check);
+
+    checks.push(if python_ok {
+        run_check("python_packages", PYTHON_TIMEOUT, probe_packages(&inputs.python, &inputs.packages)).await
+    } else {
+        skipped("python_packages", "Python didn't start")
+    });
+
+    let model_path = inputs.model_path.clone();
+    checks.push(run_check("model_file", FS_TIMEOUT, blocking(move || probe_model(&model_path))).await);
+
+    checks.push(run_check("llm_endpoint", NETWORK_TIMEOUT, probe_endpoint(&inputs.llm_server_url)).await);
+
+    let output_dir = inputs.output_dir.clone();
+    checks.push(run_check("output_dir_writable", FS_TIMEOUT, blocking(move || match output_dir {
+        Some(dir) => probe_writable(&dir, false, SettingsScreen::Paths),
+        None => CheckOutcome::skip("No output directory set; exports ask where to save"),
+    })).await);
+
+    let data_dir = inputs.data_dir.clone();
+    checks.push(run_check("disk_space", FS_TIMEOUT, blocking(move || match data_dir {
+        Some(dir) => probe_disk_space(&dir, free_space_bytes(&dir)),
+        None => CheckOutcome::skip("Couldn't locate the data directory"),
+    })).await);
+
+    checks.push(match inputs.rpc {
+        Some(rpc) => run_check("rpc_health", NETWORK_TIMEOUT, probe_rpc(rpc)).await,
+        None => skipped("rpc_health", "Sidecar isn't running"),
+    });
+
+    SelfTestReport {
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        passed: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+    }
+}
+
+/// Time `probe`, failing it if it runs past `limit`
+pub async fn run_check(id: &str, limit: Duration, probe: impl Future<Output = CheckOutcome>) -> CheckResult {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(limit, probe).await.unwrap_or_else(|_| CheckOutcome {
+        status: CheckStatus::Fail,
+        message: format!("Timed out after {}s", limit.as_secs()),
+        details: Vec::new(),
+        remediation: None,
+    });
+
+    CheckResult {
+        id: id.to_string(),
+        status: outcome.status,
+        message: outcome.message,
+        details: outcome.details,
+        duration_ms: started.elapsed().as_millis() as u64,
+        remediation: outcome.remediation,
+    }
+}
+
+fn skipped(id: &str, message: &str) -> CheckResult {
+    CheckResult {
+        id: id.to_string(),
+        status: CheckStatus::Skip,
+        message: message.to_string(),
+        details: Vec::new(),
+        duration_ms: 0,
+        remediation: None,
+    }
+}
+
+/// Run a file system probe off the async runtime
+async fn blocking(probe: impl FnOnce() -> CheckOutcome + Send + 'static) -> CheckOutcome {
+    tokio::task::spawn_blocking(probe)
+        .await
+        .unwrap_or_else(|e| CheckOutcome::skip(format!("Check panicked: {}", e)))
+}
+
+/// Write and remove a scratch file in `dir`, creating `dir` if allowed
+fn probe_writable(dir: &Path, create: bool, screen: SettingsScreen) -> CheckOutcome {
+    if create {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return CheckOutcome::fail(format!("Can't create {}: {}", dir.display(), e), screen, "Check the folder's permissions");
+        }
+    } else if !dir.is_dir() {
+        return CheckOutcome::fail(format!("{} does not exist", dir.display()), SettingsScreen::Paths, "Choose an existing output folder");
+    }
+
+    let probe = dir.join(format!(".self-test-{}.tmp", uuid::Uuid::new_v4().simple()));
+    match std::fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            CheckOutcome::pass(format!("{} is writable", dir.display()))
+        }
+        Err(e) => CheckOutcome::fail(format!("Can't write to {}: {}", dir.display(), e), screen, "Check the folder's permissions"),
+    }
+}
+
+/// Parse the saved session the settings live in
+fn probe_settings(path: &Path) -> CheckOutcome {
+    if !path.exists() {
+        return CheckOutcome::pass("No saved settings; defaults in use");
+    }
+
+    let hint = "Review the settings and save them to rewrite the file";
+    let snapshot = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_str::<SessionSnapshot>(&contents).map_err(|e| e.to_string()))
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => return CheckOutcome::fail(format!("Can't read {}: {}", path.display(), e), SettingsScreen::Llm, hint),
+    };
+
+    match snapshot.settings.map(|s| s.sidecar.validate()) {
+        None => CheckOutcome::pass("No saved settings; defaults in use"),
+        Some(Ok(())) => CheckOutcome::pass("Settings parsed"),
+        Some(Err(e)) => CheckOutcome::fail(format!("Saved settings are invalid: {}", e), SettingsScreen::Llm, hint),
+    }
+}
+
+fn probe_script(candidates: &[PathBuf]) -> CheckOutcome {
+    let details: Vec<String> = candidates
+        .iter()
+        .map(|path| format!("{} ({})", path.display(), if path.is_file() { "found" } else { "missing" }))
+        .collect();
+
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => CheckOutcome::pass(format!("Found {}", path.display())),
+        None => CheckOutcome::fail(
+            "rpc_server.py not found",
+            SettingsScreen::Setup,
+            "Reinstall the app to restore the bundled python_sidecar folder",
+        ),
+    }
+    .with_details(details)
+}
+
+async fn probe_python(python: &str) -> CheckOutcome {
+    let hint = "Pick a Python interpreter in setup, or set VFX_PYTHON_PATH";
+    let output = match tokio::process::Command::new(python).arg("--version").kill_on_drop(true).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return CheckOutcome::fail(format!("{} --version exited with {}", python, output.status), SettingsScreen::Setup, hint),
+        Err(e) => return CheckOutcome::fail(format!("Can't run {}: {}", python, e), SettingsScreen::Setup, hint),
+    };
+
+    // Python 2 prints its version to stderr
+    let text = format!("{} {}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    match parse_python_version(&text) {
+        Some(version) => CheckOutcome::pass(format!("Python {} ({})", version, python)),
+        None => CheckOutcome::fail(format!("{} didn't report a version", python), SettingsScreen::Setup, hint),
+    }
+}
+
+/// Import each package in its own interpreter, all at once
+async fn probe_packages(python: &str, packages: &[PackageSpec]) -> CheckOutcome {
+    let probes = packages.iter().map(|package| {
+        let module = package.module_name();
+        async move {
+            let output = tokio::process::Command::new(python)
+                .args(["-c", &format!("import {}", module)])
+                .kill_on_drop(true)
+                .output()
+                .await;
+            let error = match output {
+                Ok(output) if output.status.success() => None,
+                Ok(output) => Some(last_line(&String::from_utf8_lossy(&output.stderr))),
+                Err(e) => Some(e.to_string()),
+            };
+            (package.name.clone(), module, error)
+        }
+    });
+    let results = futures_util::future::join_all(probes).await;
+
+    let missing: Vec<&str> = results.iter().filter(|(_, _, error)| error.is_some()).map(|(name, _, _)| name.as_str()).collect();
+    let details = results
+        .iter()
+        .map(|(name, module, error)| match error {
+            None => format!("{} (import {}): ok", name, module),
+            Some(error) => format!("{} (import {}): {}", name, module, error),
+        })
+        .collect();
+
+    let outcome = if missing.is_empty() {
+        CheckOutcome::pass(format!("{} packages import", results.len()))
+    } else {
+        CheckOutcome::fail(
+            format!("Can't import {}", missing.join(", ")),
+            SettingsScreen::Setup,
+            "Install the missing packages from setup",
+        )
+    };
+    outcome.with_details(details)
+}
+
+fn last_line(text: &str) -> String {
+    text.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("").trim().to_string()
+}
+
+fn probe_model(path: &Path) -> CheckOutcome {
+    match validate_model_file(path) {
+        Ok(path) => {
+            let size = std::fs::metadata(&path).map(|m| format_bytes(m.len())).unwrap_or_default();
+            CheckOutcome::pass(format!("GGUF model at {} ({})", path.display(), size))
+        }
+        Err(e) => CheckOutcome::fail(e, SettingsScreen::Setup, "Choose a model file in setup"),
+    }
+}
+
+async fn probe_endpoint(server_url: &str) -> CheckOutcome {
+    let server_url = server_url.trim_end_matches('/');
+    if server_url.is_empty() {
+        return CheckOutcome::skip("No LLM server URL set");
+    }
+
+    let hint = "Check the server URL and that the server is running";
+    let url = format!("{}/health", server_url);
+    match reqwest::Client::new().get(&url).timeout(NETWORK_TIMEOUT).send().await {
+        Ok(response) if response.status().is_success() => CheckOutcome::pass(format!("{} answered", url)),
+        Ok(response) => CheckOutcome::fail(format!("{} returned {}", url, response.status()), SettingsScreen::Llm, hint),
+        Err(e) => CheckOutcome::fail(format!("Can't reach {}: {}", url, e), SettingsScreen::Llm, hint),
+    }
+}
+
+/// Compare free space at `dir` with `MIN_FREE_BYTES`
+fn probe_disk_space(dir: &Path, available: Option<u64>) -> CheckOutcome {
+    match available {
+        None => CheckOutcome::skip(format!("Couldn't read free space at {}", dir.display())),
+        Some(bytes) if bytes >= MIN_FREE_BYTES => CheckOutcome::pass(format!("{} free at {}", format_bytes(bytes), dir.display())),
+        Some(bytes) => CheckOutcome::fail(
+            format!("Only {} free at {}; {} needed", format_bytes(bytes), dir.display(), format_bytes(MIN_FREE_BYTES)),
+            SettingsScreen::Storage,
+            "Clear the cache or free up space on this disk",
+        ),
+    }
+}
+
+async fn probe_rpc(rpc: AsyncRpcClient) -> CheckOutcome {
+    match rpc.call(RpcMethod::Health.to_string(), json!({}), NETWORK_TIMEOUT).await {
+        Ok(result) => {
+            let status = result.get("status").and_then(|s| s.as_str()).unwrap_or("ok");
+            CheckOutcome::pass(format!("Sidecar answered health ({})", status))
+        }
+        Err(e) => CheckOutcome::fail(e, SettingsScreen::Setup, "Restart the app; if it keeps failing, run the setup smoke test"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hung_probe_times_out() {
+        let result = run_check("slow", Duration::from_millis(50), async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            CheckOutcome::pass("never")
+        })
+        .await;
+
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.message.starts_with("Timed out"));
+        assert!(result.duration_ms < 5000);
+    }
+
+    #[tokio::test]
+    async fn test_report_with_nothing_installed() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let inputs = SelfTestInputs {
+            config_dir: Some(dir.join("config")),
+            script_candidates: vec![dir.join("a/rpc_server.py"), dir.join("b/rpc_server.py")],
+            python: dir.join("no-python").to_string_lossy().to_string(),
+            packages: vec![PackageSpec { name: "pandas".to_string(), version: None, import_name: None }],
+            model_path: dir.join("model.gguf"),
+            llm_server_url: String::new(),
+            output_dir: None,
+            data_dir: None,
+            rpc: None,
+        };
+        let report = run_self_test(inputs).await;
+
+        let ids: Vec<_> = report.checks.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec![
+            "config_dir_writable",
+            "settings_parse",
+            "sidecar_script",
+            "python_launch",
+            "python_packages",
+            "model_file",
+            "llm_endpoint",
+            "output_dir_writable",
+            "disk_space",
+            "rpc_health",
+        ]);
+
+        let status = |id: &str| report.checks.iter().find(|c| c.id == id).unwrap();
+        assert_eq!(status("config_dir_writable").status, CheckStatus::Pass);
+        assert_eq!(status("settings_parse").status, CheckStatus::Pass);
+        assert_eq!(status("sidecar_script").status, CheckStatus::Fail);
+        assert_eq!(status("sidecar_script").details.len(), 2);
+        assert_eq!(status("python_launch").remediation.as_ref().unwrap().screen, SettingsScreen::Setup);
+        assert_eq!(status("python_packages").status, CheckStatus::Skip);
+        assert_eq!(status("model_file").status, CheckStatus::Fail);
+        assert_eq!(status("rpc_health").status, CheckStatus::Skip);
+        assert!(!report.passed);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][3]["remediation"]["screen"], "setup");
+    }
+
+    #[test]
+    fn test_settings_and_disk_probes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let session = dir.join("session.json");
+        std::fs::write(&session, "{ not json").unwrap();
+        assert_eq!(probe_settings(&session).status, CheckStatus::Fail);
+        std::fs::write(&session, r#"{ "last_bid_path": "/bids/a.json" }"#).unwrap();
+        assert_eq!(probe_settings(&session).status, CheckStatus::Pass);
+
+        assert_eq!(probe_writable(&dir.join("missing"), false, SettingsScreen::Paths).status, CheckStatus::Fail);
+        assert_eq!(probe_writable(dir, false, SettingsScreen::Paths).status, CheckStatus::Pass);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert_eq!(probe_disk_space(dir, None).status, CheckStatus::Skip);
+        assert_eq!(probe_disk_space(dir, Some(MIN_FREE_BYTES)).status, CheckStatus::Pass);
+        let low = probe_disk_space(dir, Some(MIN_FREE_BYTES / 2));
+        assert_eq!(low.remediation.unwrap().screen, SettingsScreen::Storage);
+    }
+}
@@ -6,19 +6,22 @@
 //! - Model download/setup
 //! - Configuration initialization
 
+use std::collections::{BTreeMap, VecDeque};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::fs;
-use tauri::Emitter;
+use std::sync::OnceLock;
 use serde::{Deserialize, Serialize};
-
-#[cfg(target_os = "macos")]
-use std::os::unix::process::ExitStatusExt;
+use schemars::JsonSchema;
+use sysinfo::{Disks, MemoryRefreshKind, RefreshKind, System};
+use crate::events::{emit_event, AppEvent};
+use crate::tempfiles;
 
 /// Setup configuration constants
 const MODEL_SIZE_BYTES: u64 = 6_500_000_000; // ~6.5GB
 const REQUIRED_DISK_SPACE: u64 = 15_000_000_000; // 15GB free space needed
 const MIN_RAM_GB: u64 = 8; // Minimum 8GB RAM
+pub(crate) const MODEL_RAM_HEADROOM: f64 = 1.25; // Context cache and runtime on top of the weights
+const MODEL_DISK_HEADROOM: u64 = 500_000_000; // Left free after writing the model
 
 /// Python installation status
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +32,31 @@ pub struct PythonStatus {
     pub pip_available: bool,
     pub packages_installed: Vec<String>,
     pub missing_packages: Vec<String>,
+    /// Installed version of each package in `packages_installed`
+    #[serde(default)]
+    pub package_versions: BTreeMap<String, String>,
+    /// Installed packages older than the manifest's minimum version
+    #[serde(default)]
+    pub outdated_packages: Vec<OutdatedPackage>,
+}
+
+/// A required package installed at a version below the minimum
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed: String,
+    pub minimum: String,
+}
+
+/// A Python interpreter found on this machine
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PythonInterpreter {
+    pub path: String,
+    /// e.g. "3.11.4"; None if `--version` failed
+    pub version: Option<String>,
+    /// All required packages are importable by this interpreter
+    pub packages_ok: bool,
+    pub missing_packages: Vec<String>,
 }
 
 /// System requirements check
@@ -42,25 +70,98 @@ pub struct SystemRequirements {
     pub architecture: String,
 }
 
-/// Setup progress update
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Setup progress update, sent as `setup-progress`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct SetupProgress {
     pub step: SetupStep,
     pub message: String,
     pub percent: u8,
-    pub completed: bool,
+    /// Set during a model download once the speed is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_per_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eta_seconds: Option<u64>,
+    /// Set while a dropped download waits to resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    /// Installing from an offline bundle
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub offline: bool,
+    /// Packages that failed to install
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<InstallError>,
+}
+
+impl SetupProgress {
+    pub fn new(step: SetupStep, message: impl Into<String>, percent: u8) -> Self {
+        Self {
+            step,
+            message: message.into(),
+            percent,
+            bytes_per_sec: None,
+            eta_seconds: None,
+            retry_attempt: None,
+            max_attempts: None,
+            offline: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The same, for an offline bundle install
+    pub fn offline(self) -> Self {
+        Self { offline: true, ..self }
+    }
 }
 
 /// Setup wizard steps
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
 pub enum SetupStep {
     Welcome,
     SystemCheck,
     InstallDependencies,
     DownloadModel,
+    /// The sidecar smoke test
+    Verify,
     Complete,
 }
 
+/// Time spent on one wizard step, over every attempt at it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StepTiming {
+    pub step: SetupStep,
+    pub millis: u64,
+    pub attempts: u32,
+    /// Whether the latest attempt succeeded
+    pub succeeded: bool,
+}
+
+/// How long first-run setup took, step by step; saved in
+/// `setup_complete.json` so the next install can be estimated
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SetupTimings {
+    /// In the order the steps were first run
+    pub steps: Vec<StepTiming>,
+    pub total_millis: u64,
+}
+
+impl SetupTimings {
+    /// Add an attempt at `step` that took `elapsed`
+    pub fn record(&mut self, step: SetupStep, elapsed: std::time::Duration, succeeded: bool) {
+        let millis = elapsed.as_millis() as u64;
+        match self.steps.iter_mut().find(|timing| timing.step == step) {
+            Some(timing) => {
+                timing.millis += millis;
+                timing.attempts += 1;
+                timing.succeeded = succeeded;
+            }
+            None => self.steps.push(StepTiming { step, millis, attempts: 1, succeeded }),
+        }
+        self.total_millis += millis;
+    }
+}
+
 /// Overall setup status
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SetupStatus {
@@ -70,7 +171,7 @@ pub struct SetupStatus {
     pub can_proceed: bool,
 }
 
-/// Required Python packages
+/// Required Python packages when no setup manifest is bundled
 const REQUIRED_PACKAGES: &[&str] = &[
     "openpyxl",
     "pandas",
@@ -80,6 +181,167 @@ const REQUIRED_PACKAGES: &[&str] = &[
     "PyPDF2",
 ];
 
+/// Oldest version of each built-in package the sidecar works with
+const MIN_PACKAGE_VERSIONS: &[(&str, &str)] = &[
+    ("openpyxl", "3.1.0"),
+    ("pandas", "2.0.0"),
+    ("chromadb", "0.4.0"),
+    ("llama-cpp-python", "0.2.90"),
+    ("pdfplumber", "0.10.0"),
+    ("PyPDF2", "3.0.0"),
+];
+
+/// Model installed when no setup manifest is bundled
+const DEFAULT_MODEL_FILENAME: &str = "Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf";
+const DEFAULT_MODEL_URL: &str = "https://huggingface.co/bartowski/Floppa-12B-Gemma3-Uncensored-GGUF";
+
+/// Manifest loaded at startup by `init_setup_manifest`
+static SETUP_MANIFEST: OnceLock<SetupManifest> = OnceLock::new();
+
+/// Packages and models the wizard installs, read from the bundled
+/// `setup_manifest.json` so forks can change them without a rebuild
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SetupManifest {
+    pub packages: Vec<PackageSpec>,
+    pub models: Vec<ModelEntry>,
+    /// `filename` of the model in `models` installed by default
+    pub default_model: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PackageSpec {
+    /// Distribution name as pip knows it, e.g. "llama-cpp-python"
+    pub name: String,
+    /// pip version specifier, e.g. ">=0.2.90"
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Module to import when it isn't the name with `-` as `_`, e.g.
+    /// "llama_cpp"
+    #[serde(default)]
+    pub import_name: Option<String>,
+}
+
+impl PackageSpec {
+    /// Argument for `pip install`
+    pub fn requirement(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Lowest version `version` allows, from its `>=`, `~=`, `==` or `>`
+    /// clause
+    pub fn minimum_version(&self) -> Option<&str> {
+        self.version.as_deref()?.split(',').find_map(|clause| {
+            let clause = clause.trim();
+            [">=", "~=", "==", ">"]
+                .iter()
+                .find_map(|op| clause.strip_prefix(op))
+                .map(str::trim)
+                .filter(|version| !version.is_empty())
+        })
+    }
+
+    /// Module `import` loads; a `-python` suffix is dropped, as in
+    /// "llama-cpp-python"
+    pub fn module_name(&self) -> String {
+        match &self.import_name {
+            Some(module) => module.clone(),
+            None => self.name.trim_end_matches("-python").replace('-', "_"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelEntry {
+    pub filename: String,
+    /// Page the model can be downloaded from
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    /// RAM needed on top of the weights for the context cache and runtime;
+    /// a quarter of `size_bytes` when unset
+    #[serde(default)]
+    pub ram_margin_bytes: Option<u64>,
+}
+
+impl Default for SetupManifest {
+    fn default() -> Self {
+        Self {
+            packages: REQUIRED_PACKAGES
+                .iter()
+                .map(|name| PackageSpec {
+                    name: name.to_string(),
+                    version: MIN_PACKAGE_VERSIONS
+                        .iter()
+                        .find(|(package, _)| package == name)
+                        .map(|(_, minimum)| format!(">={}", minimum)),
+                    import_name: None,
+                })
+                .collect(),
+            models: vec![ModelEntry {
+                filename: DEFAULT_MODEL_FILENAME.to_string(),
+                url: Some(DEFAULT_MODEL_URL.to_string()),
+                size_bytes: Some(MODEL_SIZE_BYTES),
+                ram_margin_bytes: Some(MODEL_SIZE_BYTES / 4),
+            }],
+            default_model: DEFAULT_MODEL_FILENAME.to_string(),
+        }
+    }
+}
+
+impl SetupManifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let manifest: Self = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        if manifest.packages.iter().any(|p| p.name.trim().is_empty()) {
+            return Err("Setup manifest lists a package without a name".to_string());
+        }
+        if !manifest.models.iter().any(|m| m.filename == manifest.default_model) {
+            return Err(format!("Default model {} is not in the model catalog", manifest.default_model));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn package_names(&self) -> Vec<String> {
+        self.packages.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn default_model(&self) -> &ModelEntry {
+        self.models
+            .iter()
+            .find(|m| m.filename == self.default_model)
+            .expect("default model is validated on load")
+    }
+
+    /// Expected size of the default model, for progress and verification
+    pub fn default_model_size(&self) -> u64 {
+        self.default_model().size_bytes.unwrap_or(MODEL_SIZE_BYTES)
+    }
+}
+
+/// Use the manifest at `path` instead of the built-in lists; call once at
+/// startup, before the wizard runs
+pub fn init_setup_manifest(path: &Path) -> Result<(), String> {
+    SETUP_MANIFEST
+        .set(SetupManifest::load(path)?)
+        .map_err(|_| "Setup manifest is already loaded".to_string())
+}
+
+/// The manifest loaded at startup, or the built-in one
+pub fn setup_manifest() -> &'static SetupManifest {
+    static BUILT_IN: OnceLock<SetupManifest> = OnceLock::new();
+    SETUP_MANIFEST
+        .get()
+        .unwrap_or_else(|| BUILT_IN.get_or_init(SetupManifest::default))
+}
+
 /// Model download sources
 #[derive(Debug, Clone)]
 pub enum ModelSource {
@@ -106,6 +368,43 @@ impl Default for SetupStatus {
     }
 }
 
+/// Steps that must be done before setup can complete, in wizard order
+pub const REQUIRED_SETUP_STEPS: [SetupStep; 3] = [
+    SetupStep::SystemCheck,
+    SetupStep::InstallDependencies,
+    SetupStep::DownloadModel,
+];
+
+/// Required steps not in `completed`, in wizard order
+pub fn missing_setup_steps(completed: &[SetupStep]) -> Vec<SetupStep> {
+    REQUIRED_SETUP_STEPS.into_iter().filter(|step| !completed.contains(step)).collect()
+}
+
+/// The step to resume the wizard at: the first one not done, or `Complete`
+pub fn next_setup_step(completed: &[SetupStep]) -> SetupStep {
+    missing_setup_steps(completed).first().copied().unwrap_or(SetupStep::Complete)
+}
+
+/// Steps done in earlier runs of an unfinished setup
+pub fn read_setup_progress(config_dir: &Path) -> Vec<SetupStep> {
+    let Ok(contents) = fs::read_to_string(config_dir.join("setup_progress.json")) else {
+        return Vec::new();
+    };
+    serde_json::from_str::<serde_json::Value>(&contents)
+        .ok()
+        .and_then(|data| serde_json::from_value(data.get("completed_steps")?.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Keep the steps done so far, for a wizard closed before it finished
+pub fn write_setup_progress(config_dir: &Path, completed: &[SetupStep]) -> Result<(), String> {
+    fs::create_dir_all(config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    let data = serde_json::json!({ "completed_steps": completed });
+    fs::write(config_dir.join("setup_progress.json"), serde_json::to_string_pretty(&data).unwrap())
+        .map_err(|e| format!("Failed to save setup progress: {}", e))
+}
+
 /// Check if this is the first run
 pub async fn is_first_run(config_dir: &Path) -> Result<bool, String> {
     let setup_file = config_dir.join("setup_complete.json");
@@ -113,8 +412,13 @@ pub async fn is_first_run(config_dir: &Path) -> Result<bool, String> {
     Ok(!setup_file.exists())
 }
 
-/// Mark setup as complete
-pub async fn complete_setup(config_dir: &Path) -> Result<(), String> {
+/// Mark setup as complete, keeping how long it took and the offline bundle
+/// it installed from, if any
+pub async fn complete_setup(
+    config_dir: &Path,
+    timings: &SetupTimings,
+    offline_bundle: Option<&Path>,
+) -> Result<(), String> {
     let setup_file = config_dir.join("setup_complete.json");
 
     // Create config directory if it doesn't exist
@@ -127,108 +431,309 @@ pub async fn complete_setup(config_dir: &Path) -> Result<(), String> {
     let data = serde_json::json!({
         "completed": true,
         "timestamp": chrono::Utc::now().to_rfc3339(),
-        "version": env!("CARGO_PKG_VERSION")
+        "version": env!("CARGO_PKG_VERSION"),
+        "timings": timings,
+        "offline_bundle": offline_bundle.map(|path| path.to_string_lossy()),
     });
 
     fs::write(setup_file, serde_json::to_string_pretty(&data).unwrap())
         .map_err(|e| format!("Failed to write setup completion: {}", e))?;
 
+    // Nothing left to resume
+    fs::remove_file(config_dir.join("setup_progress.json")).ok();
+
     Ok(())
 }
 
+/// Timings saved by `complete_setup`; None before setup finished or for
+/// setups completed by versions that didn't record them
+pub fn read_setup_timings(config_dir: &Path) -> Option<SetupTimings> {
+    let contents = fs::read_to_string(config_dir.join("setup_complete.json")).ok()?;
+    let data: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    serde_json::from_value(data.get("timings")?.clone()).ok()
+}
+
 /// Get current setup status
 pub async fn get_setup_status(config_dir: &Path) -> Result<SetupStatus, String> {
     let is_first = is_first_run(config_dir).await?;
+    if !is_first {
+        return Ok(SetupStatus {
+            is_first_run: false,
+            completed_steps: REQUIRED_SETUP_STEPS.to_vec(),
+            current_step: Some(SetupStep::Complete),
+            can_proceed: true,
+        });
+    }
 
+    let completed_steps = read_setup_progress(config_dir);
     Ok(SetupStatus {
-        is_first_run: is_first,
+        is_first_run: true,
+        current_step: Some(next_setup_step(&completed_steps)),
+        completed_steps,
         ..Default::default()
     })
 }
 
 /// Check Python installation and packages
+///
+/// The interpreter names are tried at once and the first that runs, in
+/// preference order, is used. Package versions come from one `python -c`
+/// call rather than a `pip show` per package.
 pub async fn check_python() -> Result<PythonStatus, String> {
     let python_cmds = if cfg!(target_os = "windows") {
-        vec!["python", "python3"]
+        ["python", "python3"]
     } else {
-        vec!["python3", "python"]
+        ["python3", "python"]
     };
 
-    let mut python_path = None;
-    let mut version = None;
-
-    // Find Python executable
-    for cmd in python_cmds {
-        match Command::new(cmd).arg("--version").output() {
-            Ok(output) => {
-                if output.status.success() {
-                    let ver_str = String::from_utf8_lossy(&output.stdout);
-                    version = Some(ver_str.trim().to_string());
-                    python_path = Some(cmd.to_string());
-                    break;
-                }
+    let probes = python_cmds.map(|cmd| async move {
+        run_python(Path::new(cmd), &["--version"])
+            .await
+            .filter(|output| output.status.success())
+            .map(|output| (cmd, output))
+    });
+    let found = futures_util::future::join_all(probes).await.into_iter().flatten().next();
+
+    let Some((cmd, output)) = found else {
+        return Ok(python_status(None, None, false, None));
+    };
+
+    // Python 2 prints its version to stderr
+    let version = [&output.stdout, &output.stderr]
+        .into_iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+        .find(|text| !text.is_empty());
+
+    let python = Path::new(cmd);
+    let (pip, versions) = tokio::join!(
+        run_python(python, &["-m", "pip", "--version"]),
+        python_package_versions(python),
+    );
+    let pip_available = pip.is_some_and(|output| output.status.success());
+
+    Ok(python_status(Some(cmd.to_string()), version, pip_available, versions.as_ref()))
+}
+
+/// Sort the manifest's packages into installed, missing and outdated from
+/// the versions an interpreter reported; None if it couldn't be asked
+fn python_status(
+    executable_path: Option<String>,
+    version: Option<String>,
+    pip_available: bool,
+    versions: Option<&BTreeMap<String, Option<String>>>,
+) -> PythonStatus {
+    let mut status = PythonStatus {
+        installed: executable_path.is_some(),
+        version,
+        executable_path,
+        pip_available,
+        packages_installed: Vec::new(),
+        missing_packages: Vec::new(),
+        package_versions: BTreeMap::new(),
+        outdated_packages: Vec::new(),
+    };
+
+    for package in &setup_manifest().packages {
+        let Some(installed) = versions.and_then(|v| v.get(&package.name)).cloned().flatten() else {
+            status.missing_packages.push(package.name.clone());
+            continue;
+        };
+
+        if let Some(minimum) = package.minimum_version() {
+            if compare_package_versions(&installed, minimum).is_lt() {
+                status.outdated_packages.push(OutdatedPackage {
+                    name: package.name.clone(),
+                    installed: installed.clone(),
+                    minimum: minimum.to_string(),
+                });
             }
-            _ => continue,
         }
+        status.packages_installed.push(package.name.clone());
+        status.package_versions.insert(package.name.clone(), installed);
     }
 
-    let installed = python_path.is_some();
+    status
+}
 
-    // Check pip availability
-    let pip_available = if let Some(ref cmd) = python_path {
-        Command::new(cmd)
-            .args(["-m", "pip", "--version"])
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-    } else {
-        false
+/// Compare dotted version numbers: "0.2.9" < "0.2.90" < "0.3"
+///
+/// Each part compares by its leading digits, so "3.0.0rc1" reads as 3.0.0;
+/// missing parts count as 0.
+pub fn compare_package_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|part| {
+                let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().unwrap_or(0)
+            })
+            .collect()
     };
+    let (a, b) = (parts(a), parts(b));
 
-    // Check installed packages
-    let mut packages_installed = Vec::new();
-    let mut missing_packages = Vec::new();
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|order| order.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal)
+}
 
-    if installed {
-        for package in REQUIRED_PACKAGES {
-            let installed = Command::new(python_path.as_ref().unwrap())
-                .args(["-m", "pip", "show", package])
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
+/// Seconds to wait for one interpreter to report its version or packages
+const PYTHON_PROBE_TIMEOUT_SECS: u64 = 10;
+
+/// Prints "name<TAB>version" for each named distribution, with an empty
+/// version when it isn't installed
+const PACKAGE_VERSIONS_SCRIPT: &str = "\
+import sys
+from importlib import metadata
+for name in sys.argv[1:]:
+    try:
+        print(name + '\\t' + metadata.version(name))
+    except Exception:
+        print(name + '\\t')
+";
+
+/// Find Python interpreters on PATH and in common install locations
+/// (Homebrew, pyenv, conda), one entry per real executable
+pub async fn list_python_interpreters() -> Result<Vec<PythonInterpreter>, String> {
+    let probes = dedup_interpreters(python_candidates())
+        .into_iter()
+        .map(probe_interpreter);
+
+    Ok(futures_util::future::join_all(probes)
+        .await
+        .into_iter()
+        .flatten()
+        .collect())
+}
 
-            if installed {
-                packages_installed.push(package.to_string());
-            } else {
-                missing_packages.push(package.to_string());
-            }
-        }
+/// Every executable that might be a Python, in preference order
+fn python_candidates() -> Vec<PathBuf> {
+    let names: Vec<String> = if cfg!(target_os = "windows") {
+        vec!["python.exe".to_string(), "python3.exe".to_string()]
     } else {
-        missing_packages = REQUIRED_PACKAGES.iter().map(|s| s.to_string()).collect();
+        ["python3", "python"]
+            .into_iter()
+            .map(String::from)
+            .chain((8..=13).map(|minor| format!("python3.{}", minor)))
+            .collect()
+    };
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(py) = std::env::var("VFX_PYTHON_PATH") {
+        dirs.extend(Path::new(&py).parent().map(Path::to_path_buf));
     }
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    dirs.extend(["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin", "/opt/conda/bin"].map(PathBuf::from));
+
+    if let Some(home) = dirs::home_dir() {
+        for conda in ["miniconda3", "anaconda3", "miniforge3", "mambaforge"] {
+            dirs.push(home.join(conda).join("bin"));
+        }
+        dirs.push(home.join(".pyenv/shims"));
+        if let Ok(versions) = fs::read_dir(home.join(".pyenv/versions")) {
+            dirs.extend(versions.flatten().map(|entry| entry.path().join("bin")));
+        }
+    }
+
+    dirs.iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Drop paths that resolve to an executable already listed (symlinks such
+/// as `python` -> `python3.11`), keeping the first spelling seen
+pub fn dedup_interpreters(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = std::collections::HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect()
+}
 
-    Ok(PythonStatus {
-        installed,
+/// Version number from `python --version` output, e.g. "Python 3.10.9 ::
+/// Anaconda, Inc." gives "3.10.9"
+pub fn parse_python_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .skip_while(|word| !word.eq_ignore_ascii_case("python"))
+        .nth(1)
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+/// Version and package check for one interpreter; None if it doesn't run
+async fn probe_interpreter(path: PathBuf) -> Option<PythonInterpreter> {
+    // Python 2 prints its version to stderr
+    let output = run_python(&path, &["--version"]).await?;
+    let version = parse_python_version(&format!(
+        "{} {}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ));
+
+    let missing_packages: Vec<String> = match python_package_versions(&path).await {
+        Some(versions) => versions
+            .into_iter()
+            .filter(|(_, version)| version.is_none())
+            .map(|(name, _)| name)
+            .collect(),
+        None => setup_manifest().package_names(),
+    };
+
+    Some(PythonInterpreter {
+        path: path.to_string_lossy().to_string(),
         version,
-        executable_path: python_path,
-        pip_available,
-        packages_installed,
+        packages_ok: missing_packages.is_empty(),
         missing_packages,
     })
 }
 
-/// Check system requirements
-pub fn check_system_requirements() -> Result<SystemRequirements, String> {
+/// Installed version of each required package (None if missing), or None
+/// if the interpreter couldn't be asked
+pub async fn python_package_versions(python: &Path) -> Option<BTreeMap<String, Option<String>>> {
+    let packages = setup_manifest().package_names();
+    let mut args = vec!["-c", PACKAGE_VERSIONS_SCRIPT];
+    args.extend(packages.iter().map(String::as_str));
+
+    let output = run_python(python, &args).await.filter(|o| o.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, version)| {
+                let version = Some(version.trim()).filter(|v| !v.is_empty()).map(String::from);
+                (name.trim().to_string(), version)
+            })
+            .collect(),
+    )
+}
+
+/// Run `python` with `args`, giving up after `PYTHON_PROBE_TIMEOUT_SECS`
+async fn run_python(python: &Path, args: &[&str]) -> Option<std::process::Output> {
+    let mut cmd = tokio::process::Command::new(python);
+    cmd.args(args).kill_on_drop(true);
+
+    tokio::time::timeout(std::time::Duration::from_secs(PYTHON_PROBE_TIMEOUT_SECS), cmd.output())
+        .await
+        .ok()?
+        .ok()
+}
+
+/// Check system requirements, reading free space where `model_dir` resolves
+pub fn check_system_requirements(model_dir: &str) -> Result<SystemRequirements, String> {
     // Get platform info
     let platform = std::env::consts::OS.to_string();
     let architecture = std::env::consts::ARCH.to_string();
 
-    // Check RAM (simplified - in production would use sys-info crate)
+    // Check RAM
     let ram_total_gb = get_total_ram_gb();
     let ram_sufficient = ram_total_gb >= MIN_RAM_GB;
 
     // Check disk space
-    let disk_free_gb = get_free_disk_gb()?;
+    let disk_free_gb = get_free_disk_gb(model_dir)?;
     let disk_sufficient = disk_free_gb >= (REQUIRED_DISK_SPACE / 1_000_000_000);
 
     Ok(SystemRequirements {
@@ -241,124 +746,368 @@ pub fn check_system_requirements() -> Result<SystemRequirements, String> {
     })
 }
 
-/// Get total RAM in GB
+/// Total RAM in GB
 fn get_total_ram_gb() -> u64 {
-    #[cfg(target_os = "macos")]
-    {
-        use std::process::Command;
-        let output = Command::new("sysctl")
-            .args(["hw.memsize"])
-            .output()
-            .unwrap_or_else(|_| std::process::Output {
-                status: std::process::ExitStatus::from_raw(1),
-                stdout: vec![],
-                stderr: vec![],
-            });
+    let system = System::new_with_specifics(RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()));
+    system.total_memory() / 1_000_000_000
+}
 
-        if output.status.success() {
-            let str_output = String::from_utf8_lossy(&output.stdout);
-            let bytes: u64 = str_output
-                .split(':')
-                .nth(1)
-                .and_then(|s| s.trim().parse().ok())
-                .unwrap_or(8_000_000_000);
-            bytes / 1_000_000_000
-        } else {
-            8 // Assume 8GB minimum
-        }
+/// Free space in GB on the disk models are downloaded to
+fn get_free_disk_gb(model_dir: &str) -> Result<u64, String> {
+    let model_dir = self::model_dir(model_dir);
+    free_space_bytes(&model_dir)
+        .map(|bytes| bytes / 1_000_000_000)
+        .ok_or_else(|| format!("Failed to check disk space: no disk found for {}", model_dir.display()))
+}
+
+/// Fail unless `required` bytes plus headroom fit in `available`
+pub fn check_disk_space(required: u64, available: u64) -> Result<(), String> {
+    let needed = required.saturating_add(MODEL_DISK_HEADROOM);
+    if available >= needed {
+        return Ok(());
     }
+    Err(format!(
+        "Not enough disk space: need {}, have {}",
+        format_bytes(needed),
+        format_bytes(available),
+    ))
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        // Linux implementation would go here
-        8
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        // Windows implementation would go here
-        8
-    }
-}
-
-/// Get free disk space in GB
-fn get_free_disk_gb() -> Result<u64, String> {
-    #[cfg(target_os = "macos")]
-    {
-        use std::process::Command;
-        let output = Command::new("df")
-            .arg("-H")
-            .arg(".")
-            .output()
-            .map_err(|e| format!("Failed to check disk space: {}", e))?;
-
-        if output.status.success() {
-            let str_output = String::from_utf8_lossy(&output.stdout);
-            // Parse df output
-            let lines: Vec<&str> = str_output.lines().collect();
-            if lines.len() > 1 {
-                let parts: Vec<&str> = lines[1].split_whitespace().collect();
-                if parts.len() > 3 {
-                    let free_str = parts[3].trim_end_matches('G');
-                    return free_str.parse::<u64>()
-                        .map_err(|e| format!("Failed to parse disk space: {}", e));
-                }
+/// Check there is room to write `required` bytes to `destination`
+///
+/// Run before creating the file, so a full disk fails fast instead of
+/// leaving a huge partial behind. An existing file at `destination` is about
+/// to be replaced, so its size counts as free. Skipped when free space can't
+/// be read.
+pub fn ensure_disk_space(destination: &Path, required: u64) -> Result<(), String> {
+    let Some(available) = free_space_bytes(destination) else {
+        log::warn!("Couldn't read free space for {}; skipping the check", destination.display());
+        return Ok(());
+    };
+
+    let replaced = fs::metadata(destination).map(|m| m.len()).unwrap_or(0);
+    check_disk_space(required, available.saturating_add(replaced))
+        .map_err(|e| format!("{} at {}", e, destination.parent().unwrap_or(destination).display()))
+}
+
+/// Free bytes on the volume holding `path` (or its nearest existing parent)
+///
+/// The disk mounted deepest along the path is the one it's on, which also
+/// picks the right drive letter on Windows.
+pub(crate) fn free_space_bytes(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let existing = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+
+    Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter(|disk| existing.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .map(|disk| disk.available_space())
+}
+
+/// Packages pip installs at once unless the caller asks otherwise
+///
+/// pip isn't fully safe to run in parallel (shared cache, build isolation),
+/// so this stays low.
+pub const DEFAULT_INSTALL_CONCURRENCY: usize = 2;
+
+/// Upper bound on concurrent pip processes
+pub const MAX_INSTALL_CONCURRENCY: usize = 3;
+
+/// What happened to one package during installation
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PackageStatus {
+    Installed,
+    Failed { error: String },
+    /// Not started because another package had already failed
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PackageOutcome {
+    pub package: String,
+    #[serde(flatten)]
+    pub status: PackageStatus,
+}
+
+/// Why pip couldn't install a package, as far as its output tells
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallFailure {
+    /// No release fits this Python version or platform
+    NoMatchingDistribution,
+    /// Building from source failed, usually for want of a compiler
+    BuildFailed,
+    /// The package index couldn't be reached
+    NetworkTimeout,
+    /// The OS manages this Python and pip won't change it (PEP 668)
+    ExternallyManaged,
+    Other,
+}
+
+/// A failed package install, with what to do about it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct InstallError {
+    pub package: String,
+    pub reason: InstallFailure,
+    pub message: String,
+    pub hint: Option<String>,
+    /// pip's output
+    pub details: String,
+}
+
+impl InstallError {
+    /// Classify a failed install of `package` from pip's stderr
+    pub fn from_pip_output(package: &str, output: &str) -> Self {
+        let text = output.to_lowercase();
+        let has = |signatures: &[&str]| signatures.iter().any(|s| text.contains(s));
+
+        // Checked in this order: an unreachable index also ends with "no
+        // matching distribution", and build logs mention all sorts
+        let reason = if has(&["externally-managed-environment", "externally managed"]) {
+            InstallFailure::ExternallyManaged
+        } else if has(&[
+            "read timed out",
+            "connecttimeouterror",
+            "connection timed out",
+            "temporary failure in name resolution",
+            "failed to establish a new connection",
+            "network is unreachable",
+            "max retries exceeded",
+        ]) {
+            InstallFailure::NetworkTimeout
+        } else if has(&[
+            "failed building wheel",
+            "could not build wheels",
+            "failed to build installable wheels",
+            "xcrun: error",
+            "microsoft visual c++",
+            "unable to execute 'gcc'",
+            "error: command '",
+            "cmake must be installed",
+        ]) {
+            InstallFailure::BuildFailed
+        } else if has(&["no matching distribution found", "could not find a version that satisfies"]) {
+            InstallFailure::NoMatchingDistribution
+        } else {
+            InstallFailure::Other
+        };
+
+        let (message, hint) = match reason {
+            InstallFailure::NoMatchingDistribution => (
+                format!("No release of {} is available for this Python version and platform.", package),
+                Some("Choose a newer Python (64-bit), or check the package version in the setup manifest.".to_string()),
+            ),
+            InstallFailure::BuildFailed => (
+                format!("{} had to be built from source, and the build failed.", package),
+                Some(build_tools_hint().to_string()),
+            ),
+            InstallFailure::NetworkTimeout => (
+                format!("Couldn't reach the Python package index to download {}.", package),
+                Some("Check the internet connection or proxy settings and try again, or install from an offline bundle.".to_string()),
+            ),
+            InstallFailure::ExternallyManaged => (
+                format!("This Python is managed by the operating system, so pip won't install {} into it.", package),
+                Some("Create a virtual environment (python3 -m venv ~/.vfx-bidding-venv) and choose its Python instead.".to_string()),
+            ),
+            InstallFailure::Other => {
+                let last_line = output.lines().map(str::trim).rfind(|line| !line.is_empty());
+                let message = match last_line {
+                    Some(line) => format!("Failed to install {}: {}", package, line),
+                    None => format!("Failed to install {}.", package),
+                };
+                (message, None)
             }
-        }
-        Ok(0)
+        };
+
+        Self { package: package.to_string(), reason, message, hint, details: output.to_string() }
     }
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        Ok(20)
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.hint {
+            Some(hint) => write!(f, "{} {}", self.message, hint),
+            None => write!(f, "{}", self.message),
+        }
     }
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        Ok(20)
+/// How to get a compiler for building packages on this OS
+fn build_tools_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Install the Xcode command line tools (xcode-select --install) and try again."
+    } else if cfg!(target_os = "windows") {
+        "Install the Microsoft C++ Build Tools (Desktop development with C++) and try again."
+    } else {
+        "Install a C compiler and the Python headers (e.g. build-essential and python3-dev) and try again."
     }
 }
 
 /// Install Python packages via pip
+///
+/// Up to `concurrency` packages install at once (clamped to
+/// `1..=MAX_INSTALL_CONCURRENCY`). After the first failure no new installs
+/// start, but those already running are allowed to finish. Each failure is
+/// classified from pip's output.
 pub async fn install_packages(
     python_path: &str,
+    concurrency: usize,
     progress_callback: impl Fn(String),
-) -> Result<(), String> {
+) -> Result<(), Vec<InstallError>> {
     progress_callback("Installing Python packages...".to_string());
 
-    let total_packages = REQUIRED_PACKAGES.len();
-    let mut installed = 0;
+    let requirements: Vec<String> = setup_manifest().packages.iter().map(PackageSpec::requirement).collect();
+    let requirements: Vec<&str> = requirements.iter().map(String::as_str).collect();
 
-    for package in REQUIRED_PACKAGES {
-        progress_callback(format!("Installing {}...", package));
-
-        let output = Command::new(python_path)
-            .args(["-m", "pip", "install", package])
-            .output()
-            .map_err(|e| format!("Failed to install {}: {}", package, e))?;
+    let outcomes = run_installs(&requirements, concurrency, |package| {
+        let python_path = python_path.to_string();
+        async move {
+            let output = tokio::process::Command::new(&python_path)
+                .args(["-m", "pip", "install", &package])
+                .output()
+                .await
+                .map_err(|e| e.to_string())?;
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Failed to install {}: {}", package, error));
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
         }
-
-        installed += 1;
-        let progress = ((installed as f32 / total_packages as f32) * 100.0) as u8;
-        progress_callback(format!("Progress: {}% ({}/{})", progress, installed, total_packages));
+    }, &progress_callback).await;
+
+    let failures: Vec<InstallError> = outcomes
+        .iter()
+        .filter_map(|outcome| match &outcome.status {
+            PackageStatus::Failed { error } => Some(InstallError::from_pip_output(&outcome.package, error)),
+            _ => None,
+        })
+        .collect();
+
+    if !failures.is_empty() {
+        return Err(failures);
     }
 
     progress_callback("All Python packages installed successfully!".to_string());
     Ok(())
 }
 
+/// Run `install` for each package with bounded concurrency, failing fast
+///
+/// Returns one outcome per package, in the order given.
+async fn run_installs<F, Fut>(
+    packages: &[&str],
+    concurrency: usize,
+    install: F,
+    progress_callback: &impl Fn(String),
+) -> Vec<PackageOutcome>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let total = packages.len();
+    let failed = AtomicBool::new(false);
+    let finished = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<PackageStatus>>> = Mutex::new(vec![None; total]);
+
+    // The semaphore is fair, so packages start in the order given
+    let slots = tokio::sync::Semaphore::new(concurrency.clamp(1, MAX_INSTALL_CONCURRENCY));
+
+    let mut installs = Vec::with_capacity(total);
+    for (index, package) in packages.iter().enumerate() {
+        let (install, failed, finished, outcomes, slots) = (&install, &failed, &finished, &outcomes, &slots);
+        installs.push(async move {
+            let Ok(_slot) = slots.acquire().await else {
+                return;
+            };
+
+            // Checked when the install would start, not when it was queued
+            if failed.load(Ordering::SeqCst) {
+                outcomes.lock().unwrap()[index] = Some(PackageStatus::Skipped);
+                return;
+            }
+
+            progress_callback(format!("Installing {}...", package));
+            let status = match install(package.to_string()).await {
+                Ok(()) => PackageStatus::Installed,
+                Err(error) => {
+                    failed.store(true, Ordering::SeqCst);
+                    PackageStatus::Failed { error }
+                }
+            };
+
+            let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+            let progress = ((done as f32 / total as f32) * 100.0) as u8;
+            progress_callback(format!("Progress: {}% ({}/{})", progress, done, total));
+
+            outcomes.lock().unwrap()[index] = Some(status);
+        });
+    }
+    futures_util::future::join_all(installs).await;
+
+    packages
+        .iter()
+        .zip(outcomes.into_inner().unwrap())
+        .map(|(package, status)| PackageOutcome {
+            package: package.to_string(),
+            status: status.unwrap_or(PackageStatus::Skipped),
+        })
+        .collect()
+}
+
+/// Retries for a model download cut off by a network error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadRetry {
+    /// Requests made in all before giving up, at least 1
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubles on each one after
+    pub initial_backoff: std::time::Duration,
+    pub max_backoff: std::time::Duration,
+}
+
+impl Default for DownloadRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: std::time::Duration::from_secs(2),
+            max_backoff: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl DownloadRetry {
+    /// Wait before retry number `retry`, counting from 1
+    fn backoff(&self, retry: u32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Why a download attempt stopped
+enum DownloadError {
+    /// Dropped connection, timeout or server hiccup; worth retrying
+    Transient(String),
+    Fatal(String),
+}
+
 /// Download model file
+///
+/// A network error mid-transfer doesn't lose what has arrived: the request
+/// is repeated from the current offset with a `Range` header, up to
+/// `retry.max_attempts` times with exponential backoff.
 pub async fn download_model(
     window: tauri::Window,
     source: ModelSource,
     destination: PathBuf,
+    retry: DownloadRetry,
 ) -> Result<String, String> {
     use reqwest::Client;
-    use futures_util::StreamExt;
 
     let client = Client::builder()
         .timeout(std::time::Duration::from_secs(600))
@@ -383,62 +1132,257 @@ pub async fn download_model(
         ModelSource::DirectUrl { url, filename } => (url, filename),
     };
 
-    window.emit("setup-progress", serde_json::json!({
-        "step": "DownloadModel",
-        "message": "Starting download...",
-        "percent": 0
-    })).ok();
+    let progress = |payload: SetupProgress| {
+        emit_event(&window, AppEvent::SetupProgress(payload)).ok();
+    };
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
+    progress(SetupProgress::new(SetupStep::DownloadModel, "Starting download...", 0));
 
-    if !response.status().is_success() {
-        return Err(format!("Server returned error: {}", response.status()));
-    }
+    download_with_retry(&client, &url, &destination, retry, &progress).await?;
 
-    let total_size = response.content_length().unwrap_or(MODEL_SIZE_BYTES);
-    let mut downloaded = 0u64;
-    let mut stream = response.bytes_stream();
+    progress(SetupProgress::new(SetupStep::DownloadModel, "Download complete!", 100));
 
+    Ok(destination.to_string_lossy().to_string())
+}
+
+/// Fetch `url` into `destination`, resuming after transient errors
+async fn download_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    destination: &Path,
+    retry: DownloadRetry,
+    progress: &(dyn Fn(SetupProgress) + Sync),
+) -> Result<(), String> {
     // Create destination directory
     if let Some(parent) = destination.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create directory: {}", e))?;
     }
 
-    // Create file
-    let mut file = fs::File::create(&destination)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+    // Built under the temp directory and moved into place once complete, so
+    // a half-downloaded model never sits at the destination
+    let temp = tempfiles::temp_path_for(destination);
+    if let Some(parent) = temp.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let mut transfer = Transfer {
+        downloaded: 0,
+        total_size: None,
+        started: std::time::Instant::now(),
+        rate: TransferRate::new(RATE_WINDOW_SECS),
+    };
+    let max_attempts = retry.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let error = match transfer.attempt(client, url, &temp, destination, progress).await {
+            Ok(()) => {
+                return tempfiles::persist(&temp, destination)
+                    .map_err(|e| format!("Failed to move download into place: {}", e));
+            }
+            Err(DownloadError::Fatal(e)) => {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+            Err(DownloadError::Transient(e)) => e,
+        };
+        if attempt >= max_attempts {
+            let _ = fs::remove_file(&temp);
+            return Err(format!("Download failed after {} attempts: {}", attempt, error));
+        }
+
+        let wait = retry.backoff(attempt);
+        attempt += 1;
+        log::warn!("Model download interrupted ({}); retry {} of {} in {:?}", error, attempt, max_attempts, wait);
+        let message = format!(
+            "Connection lost ({}). Retrying in {} (attempt {} of {})...",
+            error,
+            format_duration(wait.as_secs()),
+            attempt,
+            max_attempts
+        );
+        progress(SetupProgress {
+            retry_attempt: Some(attempt),
+            max_attempts: Some(max_attempts),
+            ..SetupProgress::new(SetupStep::DownloadModel, message, transfer.percent())
+        });
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Progress of one download across its attempts
+struct Transfer {
+    downloaded: u64,
+    /// Known once a server has answered
+    total_size: Option<u64>,
+    started: std::time::Instant,
+    rate: TransferRate,
+}
+
+impl Transfer {
+    fn percent(&self) -> u8 {
+        match self.total_size {
+            Some(total) if total > 0 => ((self.downloaded as f64 / total as f64) * 100.0).min(100.0) as u8,
+            _ => 0,
+        }
+    }
+
+    /// One request into `temp`, continuing from `downloaded` when there is
+    /// anything
+    async fn attempt(
+        &mut self,
+        client: &reqwest::Client,
+        url: &str,
+        temp: &Path,
+        destination: &Path,
+        progress: &(dyn Fn(SetupProgress) + Sync),
+    ) -> Result<(), DownloadError> {
+        use futures_util::StreamExt;
+        use reqwest::StatusCode;
+        use std::io::Write;
+
+        let mut request = client.get(url);
+        if self.downloaded > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", self.downloaded));
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| DownloadError::Transient(format!("Download failed: {}", e)))?;
+
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(DownloadError::Transient(format!("Server returned error: {}", status)));
+        }
+        if !status.is_success() {
+            return Err(DownloadError::Fatal(format!("Server returned error: {}", status)));
+        }
+
+        // A server that ignores the range sends the whole file again
+        let resumed = self.downloaded > 0 && status == StatusCode::PARTIAL_CONTENT;
+        if !resumed {
+            self.downloaded = 0;
+        }
+        let expected = response.content_length().map(|length| self.downloaded + length);
+
+        if self.total_size.is_none() {
+            let total = expected.unwrap_or_else(|| setup_manifest().default_model_size());
+            ensure_disk_space(destination, total).map_err(DownloadError::Fatal)?;
+            self.total_size = Some(total);
+        }
+        let total_size = self.total_size.unwrap_or_default();
+
+        let mut file = if resumed {
+            fs::OpenOptions::new().append(true).open(temp)
+        } else {
+            fs::File::create(temp)
+        }
+        .map_err(|e| DownloadError::Fatal(format!("Failed to create file: {}", e)))?;
+
+        self.rate.reset(self.started.elapsed().as_secs_f64(), self.downloaded);
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = chunk_result
+                .map_err(|e| DownloadError::Transient(format!("Download error: {}", e)))?;
+
+            file.write_all(&chunk)
+                .map_err(|e| DownloadError::Fatal(format!("Failed to write: {}", e)))?;
+
+            self.downloaded += chunk.len() as u64;
+
+            self.rate.record(self.started.elapsed().as_secs_f64(), self.downloaded);
+            let bytes_per_sec = self.rate.bytes_per_sec();
+            let eta_seconds = self.rate.eta_seconds(total_size.saturating_sub(self.downloaded));
+
+            let mut message = format!("Downloaded {} / {}", format_bytes(self.downloaded), format_bytes(total_size));
+            if let Some(speed) = bytes_per_sec {
+                message.push_str(&format!(" · {}/s", format_bytes(speed as u64)));
+            }
+            if let Some(eta) = eta_seconds {
+                message.push_str(&format!(" · {} left", format_duration(eta)));
+            }
+
+            progress(SetupProgress {
+                bytes_per_sec,
+                eta_seconds,
+                ..SetupProgress::new(SetupStep::DownloadModel, message, self.percent())
+            });
+        }
+
+        match expected {
+            Some(expected) if self.downloaded < expected => Err(DownloadError::Transient(format!(
+                "Connection closed after {} of {}",
+                format_bytes(self.downloaded),
+                format_bytes(expected)
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Seconds of samples the download speed is averaged over
+const RATE_WINDOW_SECS: f64 = 5.0;
+
+/// Rolling transfer rate over the last few seconds of (time, bytes) samples
+///
+/// Bytes are cumulative. A count lower than the last sample (a restarted
+/// transfer) starts a new window, so bytes fetched before the restart never
+/// count towards the speed.
+pub struct TransferRate {
+    window_secs: f64,
+    samples: VecDeque<(f64, u64)>,
+}
 
-    use std::io::Write;
+impl TransferRate {
+    pub fn new(window_secs: f64) -> Self {
+        Self { window_secs, samples: VecDeque::new() }
+    }
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result
-            .map_err(|e| format!("Download error: {}", e))?;
+    /// Drop all samples and start measuring from `bytes` at `at_secs`
+    pub fn reset(&mut self, at_secs: f64, bytes: u64) {
+        self.samples.clear();
+        self.samples.push_back((at_secs, bytes));
+    }
 
-        file.write_all(&chunk)
-            .map_err(|e| format!("Failed to write: {}", e))?;
+    pub fn record(&mut self, at_secs: f64, bytes: u64) {
+        if self.samples.back().is_some_and(|&(_, last)| bytes < last) {
+            self.reset(at_secs, bytes);
+            return;
+        }
+        self.samples.push_back((at_secs, bytes));
 
-        downloaded += chunk.len() as u64;
-        let percent = ((downloaded as f64 / total_size as f64) * 100.0) as u8;
+        // Keep the newest sample at or before the window start as the baseline
+        while self.samples.len() > 2 && self.samples[1].0 <= at_secs - self.window_secs {
+            self.samples.pop_front();
+        }
+    }
 
-        window.emit("setup-progress", serde_json::json!({
-            "step": "DownloadModel",
-            "message": format!("Downloaded {} / {}", format_bytes(downloaded), format_bytes(total_size)),
-            "percent": percent
-        })).ok();
+    /// None until samples span some time
+    pub fn bytes_per_sec(&self) -> Option<f64> {
+        let (&(first_at, first), &(last_at, last)) = (self.samples.front()?, self.samples.back()?);
+        let elapsed = last_at - first_at;
+        (elapsed > 0.0).then(|| (last - first) as f64 / elapsed)
     }
 
-    window.emit("setup-progress", serde_json::json!({
-        "step": "DownloadModel",
-        "message": "Download complete!",
-        "percent": 100
-    })).ok();
+    /// Seconds to fetch `remaining` bytes at the current rate; None while the
+    /// rate is unknown or zero
+    pub fn eta_seconds(&self, remaining: u64) -> Option<u64> {
+        self.bytes_per_sec()
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| (remaining as f64 / rate).ceil() as u64)
+    }
+}
 
-    Ok(destination.to_string_lossy().to_string())
+/// "45s", "3m 05s", "1h 02m"
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+    }
 }
 
 /// Verify model file integrity
@@ -452,14 +1396,96 @@ pub async fn verify_model(path: &Path) -> Result<bool, String> {
 
     // Check file size (allow 10% tolerance)
     let file_size = metadata.len();
-    let min_size = MODEL_SIZE_BYTES * 90 / 100;
-    let max_size = MODEL_SIZE_BYTES * 110 / 100;
+    let expected_size = setup_manifest().default_model_size();
+    let min_size = expected_size * 90 / 100;
+    let max_size = expected_size * 110 / 100;
 
     Ok(file_size >= min_size && file_size <= max_size)
 }
 
+/// Warn when a model of `model_bytes` is unlikely to fit in `ram_total_gb`
+///
+/// The file size stands in for the memory the weights need, plus headroom
+/// for the context cache. Returns `None` when it fits or RAM is unknown.
+pub fn model_ram_warning(model_bytes: u64, ram_total_gb: u64) -> Option<String> {
+    if ram_total_gb == 0 {
+        return None;
+    }
+
+    let needed_bytes = (model_bytes as f64 * MODEL_RAM_HEADROOM) as u64;
+    let needed_gb = needed_bytes as f64 / 1_000_000_000.0;
+    if needed_gb <= ram_total_gb as f64 {
+        return None;
+    }
+
+    Some(format!(
+        "This model is {} and needs about {:.0} GB of RAM, but this machine has {} GB. \
+         It will likely swap heavily and run very slowly; consider a smaller quantization.",
+        format_bytes(model_bytes),
+        needed_gb.ceil(),
+        ram_total_gb
+    ))
+}
+
+/// Result of checking the model file against its recorded checksum; only
+/// `Corrupt` stops the sidecar from starting
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ModelIntegrity {
+    /// No checksum recorded, so nothing was checked
+    Unverified,
+    /// The file doesn't exist; the sidecar reports that itself on load
+    Missing,
+    Verified,
+    Corrupt { expected: String, actual: String },
+}
+
+/// Compare the model at `path` with the `expected` SHA-256 (hex, any case)
+///
+/// Hashes the whole file, so call it off the main thread.
+pub fn check_model_integrity(path: &Path, expected: Option<&str>) -> Result<ModelIntegrity, String> {
+    let Some(expected) = expected.map(str::trim).filter(|e| !e.is_empty()) else {
+        return Ok(ModelIntegrity::Unverified);
+    };
+    if !path.exists() {
+        return Ok(ModelIntegrity::Missing);
+    }
+
+    let actual = sha256_file(path)?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(ModelIntegrity::Verified)
+    } else {
+        Ok(ModelIntegrity::Corrupt {
+            expected: expected.to_lowercase(),
+            actual,
+        })
+    }
+}
+
+/// Lowercase hex SHA-256 of a file, read in chunks
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
 /// Format bytes to human-readable string
-fn format_bytes(bytes: u64) -> String {
+pub fn format_bytes(bytes: u64) -> String {
     const GB: u64 = 1_000_000_000;
     const MB: u64 = 1_000_000;
 
@@ -472,15 +1498,38 @@ fn format_bytes(bytes: u64) -> String {
     }
 }
 
-/// Get default model path
-pub fn get_default_model_path() -> PathBuf {
+/// Where models go when `paths.model_dir` isn't set
+pub fn default_model_dir() -> PathBuf {
     let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("VFX-BIDDING");
     path.push("Models");
-    path.push("Floppa-12B-Gemma3-Uncensored.Q4_K_S.gguf");
     path
 }
 
+/// `model_dir` from the settings, or the default location when it's empty
+pub fn model_dir(model_dir: &str) -> PathBuf {
+    match model_dir.trim() {
+        "" => default_model_dir(),
+        dir => PathBuf::from(dir),
+    }
+}
+
+/// Get default model path: the manifest's model inside `model_dir`
+pub fn get_default_model_path(model_dir: &str) -> PathBuf {
+    self::model_dir(model_dir).join(&setup_manifest().default_model)
+}
+
+/// Create `dir` if needed and check a file can be written there
+pub fn check_dir_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .map_err(|e| format!("Can't write to {}: {}", dir.display(), e))?;
+    fs::remove_file(&probe).ok();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -493,7 +1542,474 @@ mod tests {
 
     #[test]
     fn test_system_requirements() {
-        let reqs = check_system_requirements().unwrap();
+        // A configured folder that doesn't exist yet is checked on its nearest parent
+        let configured = std::env::temp_dir().join("vfx-bidding-reqs").join("Models");
+        let reqs = check_system_requirements(configured.to_str().unwrap()).unwrap();
         println!("System requirements: {:?}", reqs);
+
+        // Read from the host, not a per-platform constant
+        assert!(reqs.ram_total_gb > 0 && reqs.ram_total_gb < 16_384, "implausible RAM: {} GB", reqs.ram_total_gb);
+        assert!(reqs.disk_free_gb > 0 && reqs.disk_free_gb < 1_000_000, "implausible free disk: {} GB", reqs.disk_free_gb);
+        assert_eq!(reqs.disk_sufficient, reqs.disk_free_gb >= REQUIRED_DISK_SPACE / 1_000_000_000);
+        assert_eq!(reqs.ram_sufficient, reqs.ram_total_gb >= MIN_RAM_GB);
+    }
+
+    #[tokio::test]
+    async fn test_step_timings_recorded_and_persisted() {
+        let mut timings = SetupTimings::default();
+        timings.record(SetupStep::SystemCheck, std::time::Duration::from_millis(40), true);
+        timings.record(SetupStep::InstallDependencies, std::time::Duration::from_secs(90), false);
+        timings.record(SetupStep::DownloadModel, std::time::Duration::from_secs(600), true);
+        // A retried step adds up rather than starting over
+        timings.record(SetupStep::InstallDependencies, std::time::Duration::from_secs(30), true);
+
+        let steps: Vec<_> = timings.steps.iter().map(|t| (t.step, t.millis, t.attempts, t.succeeded)).collect();
+        assert_eq!(steps, vec![
+            (SetupStep::SystemCheck, 40, 1, true),
+            (SetupStep::InstallDependencies, 120_000, 2, true),
+            (SetupStep::DownloadModel, 600_000, 1, true),
+        ]);
+        assert_eq!(timings.total_millis, 720_040);
+
+        let dir = std::env::temp_dir().join(format!("setup-{}", uuid::Uuid::new_v4()));
+        assert_eq!(read_setup_timings(&dir), None);
+        complete_setup(&dir, &timings, None).await.unwrap();
+        assert_eq!(read_setup_timings(&dir), Some(timings));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_setup_resumes_from_first_missing_step() {
+        let dir = std::env::temp_dir().join(format!("setup-{}", uuid::Uuid::new_v4()));
+        let status = get_setup_status(&dir).await.unwrap();
+        assert_eq!((status.completed_steps, status.current_step), (vec![], Some(SetupStep::SystemCheck)));
+
+        // Dependencies installed, then the model step failed and the app closed
+        write_setup_progress(&dir, &[SetupStep::SystemCheck, SetupStep::InstallDependencies]).unwrap();
+        let status = get_setup_status(&dir).await.unwrap();
+        assert_eq!(status.completed_steps, vec![SetupStep::SystemCheck, SetupStep::InstallDependencies]);
+        assert_eq!(status.current_step, Some(SetupStep::DownloadModel));
+        assert_eq!(missing_setup_steps(&status.completed_steps), vec![SetupStep::DownloadModel]);
+
+        // Order on disk doesn't matter, only what's missing
+        assert_eq!(next_setup_step(&[SetupStep::DownloadModel, SetupStep::SystemCheck]), SetupStep::InstallDependencies);
+        assert_eq!(next_setup_step(&REQUIRED_SETUP_STEPS), SetupStep::Complete);
+
+        complete_setup(&dir, &SetupTimings::default(), None).await.unwrap();
+        assert!(!dir.join("setup_progress.json").exists());
+        let status = get_setup_status(&dir).await.unwrap();
+        assert_eq!((status.is_first_run, status.current_step), (false, Some(SetupStep::Complete)));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_model_path_honors_model_dir() {
+        let model = &setup_manifest().default_model;
+        assert_eq!(get_default_model_path(""), default_model_dir().join(model));
+        assert_eq!(get_default_model_path("  "), default_model_dir().join(model));
+        assert!(default_model_dir().ends_with(Path::new("VFX-BIDDING").join("Models")));
+
+        let custom = Path::new("/Volumes/External/models");
+        assert_eq!(get_default_model_path("/Volumes/External/models"), custom.join(model));
+    }
+
+    #[test]
+    fn test_check_dir_writable() {
+        let dir = std::env::temp_dir().join(format!("model-dir-{}", uuid::Uuid::new_v4()));
+        assert!(check_dir_writable(&dir.join("nested")).is_ok());
+        assert_eq!(fs::read_dir(dir.join("nested")).unwrap().count(), 0);
+
+        // A file where the directory should be can't hold models
+        let file = dir.join("file");
+        fs::write(&file, b"").unwrap();
+        assert!(check_dir_writable(&file).unwrap_err().contains("Can't create"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_parse_python_version() {
+        assert_eq!(parse_python_version("Python 3.11.4\n").as_deref(), Some("3.11.4"));
+        assert_eq!(parse_python_version("Python 3.12.0rc1").as_deref(), Some("3.12.0rc1"));
+        assert_eq!(parse_python_version("Python 3.10.9 :: Anaconda, Inc.").as_deref(), Some("3.10.9"));
+        assert_eq!(parse_python_version(" Python 2.7.18\n").as_deref(), Some("2.7.18"));
+        assert_eq!(parse_python_version("pyenv: python3.9: command not found"), None);
+        assert_eq!(parse_python_version(""), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dedup_interpreters_by_real_path() {
+        let dir = std::env::temp_dir().join(format!("pythons-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("python3.11");
+        fs::write(&real, b"").unwrap();
+        std::os::unix::fs::symlink(&real, dir.join("python3")).unwrap();
+        std::os::unix::fs::symlink(dir.join("python3"), dir.join("python")).unwrap();
+        let other = dir.join("python3.12");
+        fs::write(&other, b"").unwrap();
+
+        let paths = dedup_interpreters(vec![
+            dir.join("python3"),
+            dir.join("python"),
+            real.clone(),
+            other.clone(),
+            dir.join("python3"),
+        ]);
+
+        assert_eq!(paths, vec![dir.join("python3"), other]);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_model_integrity_decides_start() {
+        const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        let path = std::env::temp_dir().join(format!("model-{}.gguf", uuid::Uuid::new_v4()));
+        fs::write(&path, b"hello").unwrap();
+
+        let verified = check_model_integrity(&path, Some(&HELLO_SHA256.to_uppercase())).unwrap();
+        assert_eq!(verified, ModelIntegrity::Verified);
+
+        let corrupt = check_model_integrity(&path, Some(&"0".repeat(64))).unwrap();
+        assert_eq!(corrupt, ModelIntegrity::Corrupt {
+            expected: "0".repeat(64),
+            actual: HELLO_SHA256.to_string(),
+        });
+
+        // Nothing recorded, or nothing to check, never blocks startup
+        assert_eq!(check_model_integrity(&path, None).unwrap(), ModelIntegrity::Unverified);
+        assert_eq!(check_model_integrity(&path, Some(" ")).unwrap(), ModelIntegrity::Unverified);
+        assert_eq!(
+            check_model_integrity(Path::new("/nonexistent/model.gguf"), Some(HELLO_SHA256)).unwrap(),
+            ModelIntegrity::Missing,
+        );
+
+        fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_install_outcomes_aggregated() {
+        use std::time::Duration;
+
+        let packages = ["slow", "broken", "later", "last"];
+        let outcomes = run_installs(&packages, 2, |package| async move {
+            match package.as_str() {
+                "slow" => {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(())
+                }
+                "broken" => {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    Err("no matching distribution".to_string())
+                }
+                _ => Ok(()),
+            }
+        }, &|_| {}).await;
+
+        let statuses: Vec<(&str, &PackageStatus)> = outcomes
+            .iter()
+            .map(|o| (o.package.as_str(), &o.status))
+            .collect();
+
+        // Every package is reported once, in order; "slow" was already
+        // running when "broken" failed, so it finishes, and the rest are skipped
+        assert_eq!(statuses, vec![
+            ("slow", &PackageStatus::Installed),
+            ("broken", &PackageStatus::Failed { error: "no matching distribution".to_string() }),
+            ("later", &PackageStatus::Skipped),
+            ("last", &PackageStatus::Skipped),
+        ]);
+    }
+
+    #[test]
+    fn test_pip_failures_are_classified() {
+        let reason = |stderr: &str| InstallError::from_pip_output("llama-cpp-python==0.2.90", stderr).reason;
+
+        assert_eq!(reason("ERROR: Could not find a version that satisfies the requirement llama-cpp-python==0.2.90 (from versions: none)\n\
+            ERROR: No matching distribution found for llama-cpp-python==0.2.90"), InstallFailure::NoMatchingDistribution);
+        assert_eq!(reason("  error: subprocess-exited-with-error\n  × Building wheel for llama-cpp-python (pyproject.toml) did not run successfully.\n\
+            xcrun: error: invalid active developer path (/Library/Developer/CommandLineTools), missing xcrun\n\
+            ERROR: Failed building wheel for llama-cpp-python"), InstallFailure::BuildFailed);
+        assert_eq!(reason("error: Microsoft Visual C++ 14.0 or greater is required."), InstallFailure::BuildFailed);
+        assert_eq!(reason("WARNING: Retrying (Retry(total=4, connect=None, read=None, redirect=None, status=None)) after connection broken by \
+            'ConnectTimeoutError(<pip._vendor.urllib3.connection.HTTPSConnection object>, 'Connection to pypi.org timed out.')'\n\
+            ERROR: Could not find a version that satisfies the requirement llama-cpp-python==0.2.90 (from versions: none)\n\
+            ERROR: No matching distribution found for llama-cpp-python==0.2.90"), InstallFailure::NetworkTimeout);
+        assert_eq!(reason("pip._vendor.urllib3.exceptions.ReadTimeoutError: HTTPSConnectionPool(host='files.pythonhosted.org', port=443): Read timed out."), InstallFailure::NetworkTimeout);
+        assert_eq!(reason("error: externally-managed-environment\n\n× This environment is externally managed"), InstallFailure::ExternallyManaged);
+
+        let other = InstallError::from_pip_output("pandas", "Collecting pandas\nERROR: Invalid requirement: 'pandas>>2'\n");
+        assert_eq!(other.reason, InstallFailure::Other);
+        assert_eq!(other.message, "Failed to install pandas: ERROR: Invalid requirement: 'pandas>>2'");
+        assert_eq!(other.hint, None);
+        assert_eq!(other.details, "Collecting pandas\nERROR: Invalid requirement: 'pandas>>2'\n");
+
+        let managed = InstallError::from_pip_output("pandas", "error: externally-managed-environment");
+        assert!(managed.to_string().ends_with("choose its Python instead."));
+    }
+
+    #[tokio::test]
+    async fn test_install_concurrency_is_bounded() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let outcomes = run_installs(REQUIRED_PACKAGES, 10, |_| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }, &|_| {}).await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), MAX_INSTALL_CONCURRENCY);
+        assert!(outcomes.iter().all(|o| o.status == PackageStatus::Installed));
+    }
+
+    #[test]
+    fn test_setup_manifest_lists() {
+        let path = std::env::temp_dir().join(format!("setup-manifest-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{
+            "packages": [
+                { "name": "pandas", "version": ">=2.0" },
+                { "name": "llama-cpp-python", "version": "==0.2.90" },
+                { "name": "openpyxl" },
+                { "name": "pyyaml", "import_name": "yaml" }
+            ],
+            "models": [
+                { "filename": "small.gguf", "size_bytes": 2000000000 },
+                { "filename": "large.gguf", "url": "https://example.com/large" }
+            ],
+            "default_model": "small.gguf"
+        }"#).unwrap();
+
+        let manifest = SetupManifest::load(&path).unwrap();
+        assert_eq!(manifest.package_names(), vec!["pandas", "llama-cpp-python", "openpyxl", "pyyaml"]);
+        let requirements: Vec<_> = manifest.packages.iter().map(PackageSpec::requirement).collect();
+        assert_eq!(requirements, vec!["pandas>=2.0", "llama-cpp-python==0.2.90", "openpyxl", "pyyaml"]);
+        let modules: Vec<_> = manifest.packages.iter().map(PackageSpec::module_name).collect();
+        assert_eq!(modules, vec!["pandas", "llama_cpp", "openpyxl", "yaml"]);
+        assert_eq!(manifest.default_model().filename, "small.gguf");
+        assert_eq!(manifest.default_model_size(), 2_000_000_000);
+
+        fs::write(&path, r#"{ "packages": [], "models": [], "default_model": "missing.gguf" }"#).unwrap();
+        assert!(SetupManifest::load(&path).unwrap_err().contains("missing.gguf"));
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_package_versions_against_minimums() {
+        use std::cmp::Ordering;
+        assert_eq!(compare_package_versions("0.2.9", "0.2.90"), Ordering::Less);
+        assert_eq!(compare_package_versions("2.0", "2.0.0"), Ordering::Equal);
+        assert_eq!(compare_package_versions("3.0.0rc1", "3.0.0"), Ordering::Equal);
+        assert_eq!(compare_package_versions("10.1", "9.12"), Ordering::Greater);
+
+        let spec = |version: &str| PackageSpec { name: "x".to_string(), version: Some(version.to_string()), import_name: None };
+        assert_eq!(spec(">=2.0, <3").minimum_version(), Some("2.0"));
+        assert_eq!(spec("<3,~=1.4").minimum_version(), Some("1.4"));
+        assert_eq!(spec("<3").minimum_version(), None);
+
+        let versions: BTreeMap<String, Option<String>> = [
+            ("openpyxl", Some("3.1.2")),
+            ("pandas", Some("1.5.3")),
+            ("chromadb", None),
+            ("llama-cpp-python", Some("0.2.90")),
+            ("pdfplumber", Some("0.11.0")),
+        ]
+        .into_iter()
+        .map(|(name, version)| (name.to_string(), version.map(String::from)))
+        .collect();
+
+        let status = python_status(Some("python3".to_string()), None, true, Some(&versions));
+        assert_eq!(status.missing_packages, vec!["chromadb", "PyPDF2"]);
+        assert_eq!(status.package_versions.get("openpyxl").map(String::as_str), Some("3.1.2"));
+        assert_eq!(status.outdated_packages, vec![OutdatedPackage {
+            name: "pandas".to_string(),
+            installed: "1.5.3".to_string(),
+            minimum: "2.0.0".to_string(),
+        }]);
+
+        let unreachable = python_status(Some("python3".to_string()), None, false, None);
+        assert_eq!(unreachable.missing_packages.len(), REQUIRED_PACKAGES.len());
+        assert!(!python_status(None, None, false, None).installed);
+    }
+
+    #[test]
+    fn test_bundled_manifest_matches_built_in() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/setup_manifest.json");
+        assert_eq!(SetupManifest::load(&path).unwrap(), SetupManifest::default());
+    }
+
+    #[test]
+    fn test_model_ram_warning_above_threshold() {
+        let warning = model_ram_warning(40_000_000_000, 16).unwrap();
+        assert!(warning.contains("40.00 GB"));
+        assert!(warning.contains("16 GB"));
+
+        // 14 GB of weights plus headroom doesn't fit in 16 GB either
+        assert!(model_ram_warning(14_000_000_000, 16).is_some());
+    }
+
+    #[test]
+    fn test_model_ram_warning_silent_below_threshold() {
+        assert_eq!(model_ram_warning(MODEL_SIZE_BYTES, 16), None);
+        assert_eq!(model_ram_warning(12_000_000_000, 16), None);
+        // Unknown RAM never warns
+        assert_eq!(model_ram_warning(40_000_000_000, 0), None);
+    }
+
+    #[test]
+    fn test_transfer_rate_and_eta() {
+        let mut rate = TransferRate::new(5.0);
+        rate.reset(0.0, 0);
+        assert_eq!(rate.bytes_per_sec(), None);
+        assert_eq!(rate.eta_seconds(1_000), None);
+
+        // 1 MB/s for ten seconds
+        for t in 1..=10 {
+            rate.record(t as f64, t * 1_000_000);
+        }
+        assert_eq!(rate.bytes_per_sec(), Some(1_000_000.0));
+        assert_eq!(rate.eta_seconds(30_000_000), Some(30));
+
+        // Only the last five seconds count, so a slowdown shows quickly
+        for t in 11..=15 {
+            rate.record(t as f64, 10_000_000 + (t - 10) * 200_000);
+        }
+        assert_eq!(rate.bytes_per_sec(), Some(200_000.0));
+        assert_eq!(rate.eta_seconds(1_000_000), Some(5));
+
+        // A stall reports a zero rate and no ETA
+        rate.record(25.0, 11_000_000);
+        assert_eq!(rate.bytes_per_sec(), Some(0.0));
+        assert_eq!(rate.eta_seconds(1_000_000), None);
+
+        // A restarted transfer doesn't inherit the old window
+        rate.record(26.0, 500_000);
+        assert_eq!(rate.bytes_per_sec(), None);
+        rate.record(28.0, 4_500_000);
+        assert_eq!(rate.bytes_per_sec(), Some(2_000_000.0));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(185), "3m 05s");
+        assert_eq!(format_duration(3720), "1h 02m");
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let gb = 1_000_000_000;
+        assert!(check_disk_space(6 * gb, 20 * gb).is_ok());
+        assert!(check_disk_space(6 * gb, 6 * gb + MODEL_DISK_HEADROOM).is_ok());
+        assert_eq!(
+            check_disk_space(6 * gb, 6 * gb).unwrap_err(),
+            "Not enough disk space: need 6.50 GB, have 6.00 GB"
+        );
+        assert_eq!(
+            check_disk_space(5_200_000_000, 3_100_000_000).unwrap_err(),
+            "Not enough disk space: need 5.70 GB, have 3.10 GB"
+        );
+    }
+
+    #[test]
+    fn test_free_space_of_a_path_not_created_yet() {
+        let missing = std::env::temp_dir().join("vfx-free-space").join("nested").join("model.gguf");
+        assert!(free_space_bytes(&missing).unwrap() > 0);
+    }
+
+    /// Drops the first connection halfway through `body`, then answers
+    /// `Range` requests; returns the URL and each request's range start
+    async fn flaky_server(body: Vec<u8>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<Option<usize>>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/model.gguf", listener.local_addr().unwrap());
+        let ranges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let Ok((mut socket, _)) = listener.accept().await else { break };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                let range = String::from_utf8_lossy(&request)
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("range: bytes=")?.trim_end_matches('-').parse().ok());
+                seen.lock().unwrap().push(range);
+
+                let start = range.unwrap_or(0);
+                let head = match range {
+                    Some(start) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        body.len() - start, start, body.len() - 1, body.len()
+                    ),
+                    None => format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()),
+                };
+                socket.write_all(head.as_bytes()).await.ok();
+
+                let end = if connection == 0 { body.len() / 2 } else { body.len() };
+                socket.write_all(&body[start..end]).await.ok();
+                socket.flush().await.ok();
+            }
+        });
+
+        (url, ranges)
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_after_dropped_connection() {
+        let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let (url, ranges) = flaky_server(body.clone()).await;
+        let dir = std::env::temp_dir().join(format!("download-retry-{}", uuid::Uuid::new_v4()));
+        let destination = dir.join("model.gguf");
+
+        let retry = DownloadRetry {
+            max_attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(10),
+            max_backoff: std::time::Duration::from_millis(50),
+        };
+        let messages = std::sync::Mutex::new(Vec::new());
+        let progress = |payload: SetupProgress| messages.lock().unwrap().push(payload);
+
+        download_with_retry(&reqwest::Client::new(), &url, &destination, retry, &progress).await.unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), body);
+        assert_eq!(ranges.lock().unwrap().as_slice(), &[None, Some(body.len() / 2)]);
+        let messages = messages.into_inner().unwrap();
+        let retries: Vec<_> = messages.iter().filter(|m| m.retry_attempt.is_some()).collect();
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].retry_attempt, Some(2));
+        assert!(retries[0].message.starts_with("Connection lost"));
+
+        // With a single attempt the first drop is final
+        let (url, _) = flaky_server(body).await;
+        let once = DownloadRetry { max_attempts: 1, ..retry };
+        let error = download_with_retry(&reqwest::Client::new(), &url, &destination, once, &|_| {}).await.unwrap_err();
+        assert!(error.starts_with("Download failed after 1 attempts"), "{}", error);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_download_backoff_doubles_up_to_cap() {
+        let retry = DownloadRetry::default();
+        let secs: Vec<_> = (1..=6).map(|n| retry.backoff(n).as_secs()).collect();
+        assert_eq!(secs, vec![2, 4, 8, 16, 30, 30]);
     }
 }
@@ -0,0 +1,221 @@
+//! Shot lists for editorial and production tracking
+//!
+//! Each shot with its sequence and frame range, as a ShotGrid import CSV,
+//! which also carries the bid hours and cost but no final price, or a plain
+//! CSV for an editor's tools. A shot's sequence is the first group it is in, or its scene
+//! number when it has none. Frame columns are left out when no shot has a
+//! frame range.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::commands::bid::{ShotData, ShotGroup};
+use crate::export_mapping::{shotgrid_preset, ExportField, ExportMapping};
+
+/// Editorial columns of the simple format
+const SIMPLE_COLUMNS: [(ExportField, &str); 6] = [
+    (ExportField::Id, "Shot"),
+    (ExportField::SceneNumber, "Sequence"),
+    (ExportField::Description, "Description"),
+    (ExportField::FrameIn, "Frame In"),
+    (ExportField::FrameOut, "Frame Out"),
+    (ExportField::FrameCount, "Frames"),
+];
+
+const FRAME_FIELDS: [ExportField; 3] = [ExportField::FrameIn, ExportField::FrameOut, ExportField::FrameCount];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShotListFormat {
+    /// Shot Code, Description, Sequence, Bid Hours, Bid Cost
+    ShotGridCsv,
+    /// Shot, sequence, description and frame range
+    SimpleCsv,
+}
+
+impl ShotListFormat {
+    /// Fields a shot needs to be listed
+    fn required(self) -> &'static [ExportField] {
+        match self {
+            ShotListFormat::ShotGridCsv => &[
+                ExportField::Id,
+                ExportField::SceneNumber,
+                ExportField::EstimatedHours,
+                ExportField::EstimatedCost,
+            ],
+            ShotListFormat::SimpleCsv => &[ExportField::Id, ExportField::Description],
+        }
+    }
+}
+
+/// A shot left out of the list
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkippedShot {
+    pub id: String,
+    /// Headers of the required columns it has no value for
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShotListReport {
+    pub path: String,
+    pub exported: usize,
+    pub skipped: Vec<SkippedShot>,
+}
+
+/// Write the shot list to `path` in `format`
+///
+/// Headers come from `names` for the fields it places, so a studio's saved
+/// export mapping can rename them; the format's own names otherwise.
+pub fn write_shot_list(
+    path: &Path,
+    shots: &[ShotData],
+    groups: &[ShotGroup],
+    format: ShotListFormat,
+    names: Option<&ExportMapping>,
+) -> Result<ShotListReport, String> {
+    let has_frames = shots.iter().any(|s| s.frame_in.is_some() || s.frame_out.is_some() || s.frame_count.is_some());
+    let columns: Vec<(ExportField, String)> = layout(format)
+        .into_iter()
+        .filter(|(field, _)| has_frames || !FRAME_FIELDS.contains(field))
+        .map(|(field, header)| {
+            let renamed = names.and_then(|mapping| mapping.columns.iter().find(|c| c.field == field));
+            (field, renamed.map_or(header, |c| c.header().to_string()))
+        })
+        .collect();
+
+    let mut writer = csv::Writer::from_path(path).map_err(|e| format!("Failed to create CSV file: {}", e))?;
+    writer
+        .write_record(columns.iter().map(|(_, header)| header))
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+    let mut exported = 0;
+    let mut skipped = Vec::new();
+    for shot in shots {
+        let sequence = sequence(shot, groups);
+        let row: Vec<String> = columns.iter().map(|(field, _)| value(shot, *field, &sequence)).collect();
+
+        let missing: Vec<String> = columns
+            .iter()
+            .zip(&row)
+            .filter(|((field, _), value)| format.required().contains(field) && value.is_empty())
+            .map(|((_, header), _)| header.clone())
+            .collect();
+        if !missing.is_empty() {
+            skipped.push(SkippedShot { id: shot.id.clone(), missing });
+            continue;
+        }
+
+        writer.write_record(&row).map_err(|e| format!("Failed to write CSV: {}", e))?;
+        exported += 1;
+    }
+    writer.flush().map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+    Ok(ShotListReport { path: path.to_string_lossy().to_string(), exported, skipped })
+}
+
+fn layout(format: ShotListFormat) -> Vec<(ExportField, String)> {
+    match format {
+        ShotListFormat::ShotGridCsv => shotgrid_preset()
+            .ordered_columns()
+            .into_iter()
+            .map(|c| (c.field, c.header().to_string()))
+            .collect(),
+        ShotListFormat::SimpleCsv => SIMPLE_COLUMNS.iter().map(|(field, header)| (*field, header.to_string())).collect(),
+    }
+}
+
+/// The first group `shot` is in, or its scene number
+fn sequence(shot: &ShotData, groups: &[ShotGroup]) -> String {
+    groups
+        .iter()
+        .find(|group| group.shot_ids.contains(&shot.id))
+        .map(|group| group.name.trim().to_string())
+        .unwrap_or_else(|| shot.scene_number.trim().to_string())
+}
+
+fn value(shot: &ShotData, field: ExportField, sequence: &str) -> String {
+    let number = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let frame = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    match field {
+        ExportField::Id => shot.id.trim().to_string(),
+        ExportField::SceneNumber => sequence.to_string(),
+        ExportField::Description => shot.description.trim().to_string(),
+        ExportField::EstimatedHours => number(shot.estimated_hours),
+        ExportField::EstimatedCost => number(shot.estimated_cost),
+        ExportField::FrameIn => frame(shot.frame_in),
+        ExportField::FrameOut => frame(shot.frame_out),
+        ExportField::FrameCount => frame(shot.frame_count),
+        // Not in either layout; a studio mapping only renames
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    fn shot(id: &str, scene: &str, description: &str, hours: Option<f64>, cost: Option<f64>) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            scene_number: scene.to_string(),
+            description: description.to_string(),
+            estimated_hours: hours,
+            estimated_cost: cost,
+            ..Default::default()
+        }
+    }
+
+    fn bid() -> (Vec<ShotData>, Vec<ShotGroup>) {
+        let shots = vec![
+            ShotData { frame_in: Some(1001), frame_out: Some(1048), frame_count: Some(48), ..shot("GH_010", "1", "Harbor wide, replace skyline", Some(80.0), Some(7600.0)) },
+            shot("GH_020", "1", "Screen insert", Some(8.0), Some(760.0)),
+            ShotData { frame_in: Some(1001), frame_out: Some(1120), frame_count: Some(120), ..shot("GH_030", "2A", "Rig removal \"hero\" crane", Some(20.5), Some(1947.5)) },
+            shot("GH_040", "", "Ship hero", Some(120.0), None),
+        ];
+        let groups = vec![ShotGroup {
+            name: "HBR".to_string(),
+            shot_ids: vec!["GH_010".to_string(), "GH_020".to_string()],
+            discount_percent: None,
+        }];
+        (shots, groups)
+    }
+
+    fn export(format: ShotListFormat, names: Option<&ExportMapping>) -> (ShotListReport, String) {
+        let (shots, groups) = bid();
+        let path = std::env::temp_dir().join(format!("shot-list-{}.csv", uuid::Uuid::new_v4()));
+        let report = write_shot_list(&path, &shots, &groups, format, names).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).ok();
+        (report, written)
+    }
+
+    #[test]
+    fn test_shotgrid_csv_matches_fixture() {
+        let (report, written) = export(ShotListFormat::ShotGridCsv, None);
+        assert_eq!(written, std::fs::read_to_string(fixture("shot_list_shotgrid.csv")).unwrap());
+
+        // No sequence from a group or scene, and no cost
+        assert_eq!(report.exported, 3);
+        assert_eq!(report.skipped, vec![SkippedShot {
+            id: "GH_040".to_string(),
+            missing: vec!["Sequence".to_string(), "Bid Cost".to_string()],
+        }]);
+    }
+
+    #[test]
+    fn test_simple_csv_matches_fixture_with_studio_names() {
+        let mut names = shotgrid_preset();
+        names.columns.retain(|c| c.field == ExportField::Id);
+        names.columns[0].header = Some("Shot Name".to_string());
+
+        let (report, written) = export(ShotListFormat::SimpleCsv, Some(&names));
+        assert_eq!(written, std::fs::read_to_string(fixture("shot_list_simple.csv")).unwrap());
+        assert_eq!((report.exported, report.skipped.len()), (4, 0));
+    }
+}
@@ -0,0 +1,283 @@
+//! Side channel for RPC values too large for one line
+//!
+//! Requests and responses travel as one JSON object per line, and a script
+//! or prompt of several megabytes makes for a line the sidecar reads slowly
+//! or not at all. Values over the threshold are written to a file instead,
+//! under `blobs/` in the request's `workspace_dir`, and replaced by
+//! `{ "$blob": "<path>", "bytes": N, "sha256": "<hex>" }`. The sidecar
+//! dereferences those and may answer with references of its own, which are
+//! inlined again before the result reaches the caller. A request's files are
+//! deleted once it completes, whether it succeeded, failed or timed out.
+//!
+//! Only a sidecar that advertises `protocol::BLOB_REFS_CAPABILITY` in
+//! `get_protocol_info` gets references; any other is sent every value inline.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use super::rpc::RpcError;
+
+/// Values whose JSON is larger than this go through a file by default
+pub const DEFAULT_BLOB_THRESHOLD: usize = 256 * 1024;
+
+/// Key marking an object as a reference to a blob file
+pub const BLOB_KEY: &str = "$blob";
+
+/// Error code for a blob that couldn't be written, read or verified
+pub const RPC_BLOB_CODE: i32 = -32011;
+
+/// Subdirectory of the workspace holding blob files
+const BLOB_DIR: &str = "blobs";
+
+/// Where blobs go and from what size
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    /// Used for requests without a `workspace_dir`
+    fallback_dir: PathBuf,
+    threshold: usize,
+}
+
+/// Blob files belonging to one request; deleted when dropped
+#[derive(Debug)]
+pub struct RequestBlobs {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl BlobStore {
+    pub fn new(fallback_dir: PathBuf, threshold: usize) -> Self {
+        Self { fallback_dir, threshold }
+    }
+
+    /// Fallback directory under the app's temp root, or the system's
+    pub fn default_dir() -> PathBuf {
+        crate::tempfiles::temp_root()
+            .map(|root| root.join("rpc_blobs"))
+            .unwrap_or_else(|| std::env::temp_dir().join("vfx-bidding-rpc-blobs"))
+    }
+
+    /// Blob directory for a request with `params`
+    fn dir_for(&self, params: &Value) -> PathBuf {
+        params.get("workspace_dir")
+            .and_then(Value::as_str)
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| Path::new(dir).join(BLOB_DIR))
+            .unwrap_or_else(|| self.fallback_dir.clone())
+    }
+
+    /// Replace the values in `params` larger than the threshold with blob
+    /// references
+    ///
+    /// The params object itself is never replaced, so every top-level key is
+    /// still there and small ones like `workspace_dir` read directly; the
+    /// value under any key, top-level ones such as `shots` included, may
+    /// become a reference.
+    pub fn externalize(&self, params: Value) -> Result<(Value, RequestBlobs), RpcError> {
+        let mut blobs = RequestBlobs { dir: self.dir_for(&params), files: Vec::new() };
+        let params = match params {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, self.externalize_value(value, &mut blobs)?)))
+                    .collect::<Result<Map<_, _>, RpcError>>()?,
+            ),
+            other => self.externalize_value(other, &mut blobs)?,
+        };
+        Ok((params, blobs))
+    }
+
+    /// Innermost values go first, so a large array of small items becomes
+    /// one file rather than many
+    fn externalize_value(&self, value: Value, blobs: &mut RequestBlobs) -> Result<Value, RpcError> {
+        let value = match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, self.externalize_value(value, blobs)?)))
+                    .collect::<Result<Map<_, _>, RpcError>>()?,
+            ),
+            Value::Array(items) => Value::Array(
+                items.into_iter()
+                    .map(|value| self.externalize_value(value, blobs))
+                    .collect::<Result<Vec<_>, RpcError>>()?,
+            ),
+            other => other,
+        };
+
+        let text = serde_json::to_vec(&value).map_err(|e| blob_error(format!("Failed to serialize blob: {}", e)))?;
+        if text.len() <= self.threshold {
+            return Ok(value);
+        }
+        blobs.write(&text)
+    }
+}
+
+impl RequestBlobs {
+    /// Write `text` to a new file and return the reference to it
+    fn write(&mut self, text: &[u8]) -> Result<Value, RpcError> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| blob_error(format!("Failed to create blob directory {}: {}", self.dir.display(), e)))?;
+        let path = self.dir.join(format!("{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, text)
+            .map_err(|e| blob_error(format!("Failed to write blob {}: {}", path.display(), e)))?;
+        self.files.push(path.clone());
+
+        Ok(serde_json::json!({
+            BLOB_KEY: path.to_string_lossy(),
+            "bytes": text.len(),
+            "sha256": sha256_hex(text),
+        }))
+    }
+
+    /// Replace the blob references in a response with the values they point
+    /// to. Files in this request's blob directory are deleted with the
+    /// request's own; others are only read.
+    pub fn inline(&mut self, value: Value) -> Result<Value, RpcError> {
+        match value {
+            Value::Object(map) if is_blob_ref(&map) => {
+                let value = self.read(&map)?;
+                self.inline(value)
+            }
+            Value::Object(map) => Ok(Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, self.inline(value)?)))
+                    .collect::<Result<Map<_, _>, RpcError>>()?,
+            )),
+            Value::Array(items) => Ok(Value::Array(
+                items.into_iter()
+                    .map(|value| self.inline(value))
+                    .collect::<Result<Vec<_>, RpcError>>()?,
+            )),
+            other => Ok(other),
+        }
+    }
+
+    fn read(&mut self, reference: &Map<String, Value>) -> Result<Value, RpcError> {
+        let path = PathBuf::from(reference[BLOB_KEY].as_str().unwrap_or_default());
+        if path.starts_with(&self.dir) && !self.files.contains(&path) {
+            self.files.push(path.clone());
+        }
+
+        let text = fs::read(&path)
+            .map_err(|e| blob_error(format!("Failed to read blob {}: {}", path.display(), e)))?;
+        if let Some(bytes) = reference.get("bytes").and_then(Value::as_u64) {
+            if bytes != text.len() as u64 {
+                return Err(blob_error(format!("Blob {} has {} bytes, expected {}", path.display(), text.len(), bytes)));
+            }
+        }
+        if let Some(sha256) = reference.get("sha256").and_then(Value::as_str) {
+            if !sha256.eq_ignore_ascii_case(&sha256_hex(&text)) {
+                return Err(blob_error(format!("Blob {} doesn't match its checksum", path.display())));
+            }
+        }
+
+        serde_json::from_slice(&text)
+            .map_err(|e| blob_error(format!("Failed to parse blob {}: {}", path.display(), e)))
+    }
+}
+
+impl Drop for RequestBlobs {
+    fn drop(&mut self) {
+        for path in &self.files {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to delete blob {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `map` is `{ "$blob": path, ... }` rather than ordinary data
+fn is_blob_ref(map: &Map<String, Value>) -> bool {
+    map.get(BLOB_KEY).is_some_and(Value::is_string)
+        && map.keys().all(|key| matches!(key.as_str(), BLOB_KEY | "bytes" | "sha256"))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn blob_error(message: String) -> RpcError {
+    RpcError { code: RPC_BLOB_CODE, message, data: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use serde_json::json;
+    use crate::sidecar::rpc::{testing::MockSidecar, RPC_TIMEOUT_CODE};
+
+    fn files_in(dir: &Path) -> usize {
+        fs::read_dir(dir).map(|entries| entries.count()).unwrap_or(0)
+    }
+
+    #[test]
+    fn test_large_values_round_trip_through_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = temp.path();
+        let fallback = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(fallback.path().to_path_buf(), 1024);
+        let script = "INT. HARBOUR - NIGHT ".repeat(200);
+        let params = json!({
+            "workspace_dir": workspace.to_string_lossy(),
+            "script": script,
+            "options": { "small": true },
+        });
+
+        let (sent, mut blobs) = store.externalize(params.clone()).unwrap();
+        assert_eq!(sent["workspace_dir"], params["workspace_dir"]);
+        assert_eq!(sent["options"], json!({ "small": true }));
+        let reference = sent["script"].as_object().unwrap();
+        assert!(is_blob_ref(reference));
+        assert!(Path::new(reference[BLOB_KEY].as_str().unwrap()).starts_with(workspace.join(BLOB_DIR)));
+
+        assert_eq!(blobs.inline(sent).unwrap(), params);
+        drop(blobs);
+        assert_eq!(files_in(&workspace.join(BLOB_DIR)), 0);
+
+        // A tampered file is refused rather than passed on
+        let (sent, mut blobs) = store.externalize(params).unwrap();
+        fs::write(&blobs.files[0], "\"short\"").unwrap();
+        assert_eq!(blobs.inline(sent).unwrap_err().code, RPC_BLOB_CODE);
+        drop(blobs);
+        assert_eq!(files_in(&workspace.join(BLOB_DIR)), 0);
+    }
+
+    #[test]
+    fn test_blobs_are_deleted_after_success() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let reply_dir = dir.to_path_buf();
+        let (sidecar, _) = MockSidecar::new(move |request| {
+            // The sidecar side: dereference the request, answer with a blob
+            let reference = request.params["text"].as_object().unwrap();
+            let text: String = serde_json::from_slice(&fs::read(reference[BLOB_KEY].as_str().unwrap()).unwrap()).unwrap();
+            let reply = serde_json::to_vec(&text.to_uppercase()).unwrap();
+            let path = reply_dir.join("reply.json");
+            fs::write(&path, &reply).unwrap();
+            Ok(json!({ "text": { BLOB_KEY: path.to_string_lossy(), "bytes": reply.len(), "sha256": sha256_hex(&reply) } }))
+        });
+        let client = sidecar.rpc_client().with_blob_store(BlobStore::new(dir.to_path_buf(), 1024));
+
+        let result = client.call("chat_command", json!({ "text": "a".repeat(4096) })).unwrap();
+        assert_eq!(result["text"], json!("A".repeat(4096)));
+        assert_eq!(files_in(dir), 0);
+    }
+
+    #[test]
+    fn test_blobs_are_deleted_after_timeout() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let sidecar = MockSidecar::scripted(|_| vec![]);
+        let client = sidecar.rpc_client().with_blob_store(BlobStore::new(dir.to_path_buf(), 1024));
+
+        let error = client
+            .call_with_timeout("bid_query", json!({ "text": "a".repeat(4096) }), Some(Duration::from_millis(50)))
+            .unwrap_err();
+        assert_eq!(error.code, RPC_TIMEOUT_CODE);
+        // The request did go out through a file
+        assert!(sidecar.requests()[0].params["text"].get(BLOB_KEY).is_some());
+        assert_eq!(files_in(dir), 0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Environment for the sidecar process
+//!
+//! The sidecar starts from an allowlist rather than everything the app
+//! inherited, so an auto-activated conda env, `PYTHONHOME` or a stray
+//! `OPENBLAS_NUM_THREADS` can't change how Python or llama.cpp behave.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use crate::commands::settings::{LlmSettings, SidecarSettings};
+
+/// Variables passed through from the app's environment
+const ALLOWED_VARS: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "TMPDIR", "TMP", "TEMP", "LANG",
+    // Windows essentials; Python can't start without SYSTEMROOT
+    "SYSTEMROOT", "SYSTEMDRIVE", "WINDIR", "COMSPEC", "PATHEXT", "USERPROFILE",
+    "APPDATA", "LOCALAPPDATA", "PROGRAMDATA",
+];
+
+/// Prefixes passed through: locale categories and our own `VFX_*` settings
+const ALLOWED_PREFIXES: &[&str] = &["LC_", "VFX_"];
+
+/// LLM parameters the sidecar reads when it loads the model
+pub const CONTEXT_SIZE_ENV: &str = "VFX_CONTEXT_SIZE";
+pub const TEMPERATURE_ENV: &str = "VFX_TEMPERATURE";
+pub const MAX_TOKENS_ENV: &str = "VFX_MAX_TOKENS";
+pub const MODEL_NAME_ENV: &str = "VFX_MODEL_NAME";
+/// Model file to load, when one was chosen after setup
+pub const MODEL_PATH_ENV: &str = "VFX_MODEL_PATH";
+
+/// Name fragments whose values are never logged
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "AUTH"];
+
+/// How to build the sidecar's environment
+#[derive(Debug, Clone, Default)]
+pub struct SidecarEnv {
+    /// Set after the allowlist, overriding it
+    pub extra: HashMap<String, String>,
+    /// Pass everything through, as before isolation
+    pub inherit: bool,
+    /// From the LLM settings; set before `extra`, so `extra` can override
+    pub llm: BTreeMap<String, String>,
+}
+
+impl SidecarEnv {
+    pub fn from_settings(settings: &SidecarSettings, llm: Option<&LlmSettings>) -> Self {
+        Self {
+            extra: settings.extra_env.clone(),
+            inherit: settings.inherit_env,
+            llm: llm.map(llm_vars).unwrap_or_default(),
+        }
+    }
+
+    /// Variables for a sidecar whose Python modules live in `resources_dir`
+    ///
+    /// `parent` is the app's environment. PYTHONPATH is exactly
+    /// `resources_dir` unless inheriting, in which case it is appended.
+    pub fn build(
+        &self,
+        parent: impl IntoIterator<Item = (String, String)>,
+        resources_dir: &Path,
+    ) -> BTreeMap<String, String> {
+        let mut env: BTreeMap<String, String> = parent
+            .into_iter()
+            .filter(|(name, _)| self.inherit || is_allowed(name))
+            .collect();
+
+        let resources = resources_dir.to_string_lossy().to_string();
+        let pythonpath = match env.get("PYTHONPATH").filter(|_| self.inherit) {
+            Some(existing) if !existing.is_empty() => {
+                let separator = if cfg!(windows) { ";" } else { ":" };
+                format!("{}{}{}", existing, separator, resources)
+            }
+            _ => resources,
+        };
+        env.insert("PYTHONPATH".to_string(), pythonpath);
+        env.insert("PYTHONUNBUFFERED".to_string(), "1".to_string());
+
+        env.extend(self.llm.iter().map(|(name, value)| (name.clone(), value.clone())));
+        env.extend(self.extra.iter().map(|(name, value)| (name.clone(), value.clone())));
+        env
+    }
+}
+
+/// Variables carrying the LLM parameters from the settings
+pub fn llm_vars(llm: &LlmSettings) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::from([
+        (CONTEXT_SIZE_ENV.to_string(), llm.context_size.to_string()),
+        (TEMPERATURE_ENV.to_string(), llm.temperature.to_string()),
+        (MAX_TOKENS_ENV.to_string(), llm.max_tokens.to_string()),
+        (MODEL_NAME_ENV.to_string(), llm.model_name.clone()),
+    ]);
+    if let Some(path) = llm.model_path.as_ref().filter(|path| !path.is_empty()) {
+        vars.insert(MODEL_PATH_ENV.to_string(), path.clone());
+    }
+    vars
+}
+
+fn is_allowed(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    ALLOWED_VARS.contains(&upper.as_str())
+        || ALLOWED_PREFIXES.iter().any(|prefix| upper.starts_with(prefix))
+}
+
+/// Whether a variable or field called `name` probably holds a secret
+pub fn is_secret_name(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// `NAME=value` pairs for the log, with secret-looking values and proxy
+/// credentials hidden
+pub fn redacted(env: &BTreeMap<String, String>) -> String {
+    env.iter()
+        .map(|(name, value)| {
+            let secret = is_secret_name(name)
+                || (name.to_ascii_uppercase().ends_with("_PROXY") && value.contains('@'));
+            if secret {
+                format!("{}=<redacted>", name)
+            } else {
+                format!("{}={}", name, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent() -> Vec<(String, String)> {
+        [
+            ("PATH", "/usr/bin"),
+            ("HOME", "/home/artist"),
+            ("LC_ALL", "de_DE.UTF-8"),
+            ("VFX_MODEL_PATH", "/models/a.gguf"),
+            ("PYTHONHOME", "/opt/conda"),
+            ("PYTHONPATH", "/opt/conda/lib"),
+            ("CONDA_PREFIX", "/opt/conda"),
+            ("OPENBLAS_NUM_THREADS", "1"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_allowlist_drops_python_and_conda_vars() {
+        let env = SidecarEnv::default().build(parent(), Path::new("/app/python_sidecar"));
+
+        assert_eq!(env.get("PATH").map(String::as_str), Some("/usr/bin"));
+        assert_eq!(env.get("LC_ALL").map(String::as_str), Some("de_DE.UTF-8"));
+        assert_eq!(env.get("VFX_MODEL_PATH").map(String::as_str), Some("/models/a.gguf"));
+        assert_eq!(env.get("PYTHONPATH").map(String::as_str), Some("/app/python_sidecar"));
+        assert_eq!(env.get("PYTHONUNBUFFERED").map(String::as_str), Some("1"));
+        for dropped in ["PYTHONHOME", "CONDA_PREFIX", "OPENBLAS_NUM_THREADS"] {
+            assert!(!env.contains_key(dropped), "{} should be dropped", dropped);
+        }
+    }
+
+    #[test]
+    fn test_extra_env_and_inherit() {
+        let sidecar_env = SidecarEnv {
+            extra: HashMap::from([
+                ("LLAMA_METAL".to_string(), "1".to_string()),
+                ("PATH".to_string(), "/custom/bin".to_string()),
+            ]),
+            inherit: false,
+            ..Default::default()
+        };
+        let env = sidecar_env.build(parent(), Path::new("/app"));
+        assert_eq!(env.get("LLAMA_METAL").map(String::as_str), Some("1"));
+        assert_eq!(env.get("PATH").map(String::as_str), Some("/custom/bin"));
+
+        let inherited = SidecarEnv { inherit: true, ..Default::default() }.build(parent(), Path::new("/app"));
+        assert_eq!(inherited.get("PYTHONHOME").map(String::as_str), Some("/opt/conda"));
+        assert!(inherited["PYTHONPATH"].starts_with("/opt/conda/lib"));
+        assert!(inherited["PYTHONPATH"].ends_with("/app"));
+    }
+
+    #[test]
+    fn test_llm_settings_become_env_vars() {
+        let mut settings = crate::commands::settings::Settings::default();
+        settings.llm.context_size = 16384;
+        settings.llm.temperature = 0.7;
+        settings.llm.max_tokens = 2048;
+        settings.llm.model_name = "other.gguf".to_string();
+        settings.llm.model_path = Some("/models/other.gguf".to_string());
+        settings.sidecar.extra_env.insert(MAX_TOKENS_ENV.to_string(), "512".to_string());
+
+        let mut parent = parent();
+        parent.push((CONTEXT_SIZE_ENV.to_string(), "2048".to_string()));
+
+        let env = SidecarEnv::from_settings(&settings.sidecar, Some(&settings.llm)).build(parent, Path::new("/app"));
+        assert_eq!(env.get(CONTEXT_SIZE_ENV).map(String::as_str), Some("16384"));
+        assert_eq!(env.get(TEMPERATURE_ENV).map(String::as_str), Some("0.7"));
+        assert_eq!(env.get(MODEL_NAME_ENV).map(String::as_str), Some("other.gguf"));
+        // The chosen model beats one inherited from the app
+        assert_eq!(env.get(MODEL_PATH_ENV).map(String::as_str), Some("/models/other.gguf"));
+        // extra_env still has the last word
+        assert_eq!(env.get(MAX_TOKENS_ENV).map(String::as_str), Some("512"));
+
+        let without = SidecarEnv::from_settings(&settings.sidecar, None).build(Vec::new(), Path::new("/app"));
+        assert!(!without.contains_key(CONTEXT_SIZE_ENV));
+
+        settings.llm.model_path = None;
+        assert!(!llm_vars(&settings.llm).contains_key(MODEL_PATH_ENV));
+    }
+
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let env = BTreeMap::from([
+            ("HF_TOKEN".to_string(), "hf_abc".to_string()),
+            ("HTTPS_PROXY".to_string(), "http://user:pw@proxy:8080".to_string()),
+            ("HTTP_PROXY".to_string(), "http://proxy:8080".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ]);
+
+        let line = redacted(&env);
+        assert!(line.contains("HF_TOKEN=<redacted>"));
+        assert!(line.contains("HTTPS_PROXY=<redacted>"));
+        assert!(line.contains("HTTP_PROXY=http://proxy:8080"));
+        assert!(line.contains("PATH=/usr/bin"));
+        assert!(!line.contains("hf_abc") && !line.contains("pw@"));
+    }
+}
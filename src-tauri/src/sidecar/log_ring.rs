@@ -0,0 +1,59 @@
+//! Recent sidecar stderr output, kept for diagnostics reports
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Lines kept by default
+pub const LOG_CAPACITY: usize = 200;
+
+/// Ring buffer of the last lines the sidecar wrote to stderr
+///
+/// Shared across restarts so the output of a crashed sidecar survives.
+#[derive(Debug)]
+pub struct LogRing {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl Default for LogRing {
+    fn default() -> Self {
+        Self::new(LOG_CAPACITY)
+    }
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Buffered lines, oldest first
+    pub fn recent(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_lines_are_dropped() {
+        let log = LogRing::new(3);
+        for i in 0..5 {
+            log.push(format!("line {}", i));
+        }
+
+        assert_eq!(log.recent(), vec!["line 2", "line 3", "line 4"]);
+    }
+}
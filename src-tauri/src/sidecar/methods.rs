@@ -0,0 +1,152 @@
+//! Methods the Python sidecar answers
+//!
+//! The contract with `rpc_server.py` lives here: commands name a method
+//! through `RpcMethod` rather than a bare string, so a typo fails to compile
+//! instead of coming back as "Method not found" at runtime. Each variant
+//! documents the params sent and the fields read from the result.
+//!
+//! Any value below the top level of params or anywhere in a result may be a
+//! blob reference `{ "$blob": path, bytes, sha256 }` standing for a value too
+//! large to send inline; see `blob`.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcMethod {
+    /// Params: `{}`. Result: `{ status: "ok" | "healthy" | ... }`
+    Health,
+    /// Params: `{ context_size, temperature, max_tokens, model_name }`.
+    /// Result is ignored
+    Configure,
+    /// Params: `{ model_path, context_size }`. Result is ignored; failure
+    /// means the model stays unloaded
+    LoadModel,
+    /// Params: `{}`. Result is ignored
+    UnloadModel,
+    /// Params: `{ path, output_path, mapping, workspace_dir, scene_hints? }`. Result:
+    /// `{ page_count?, excel_path?, unpriced_shots?, warnings? }`; shots it
+    /// couldn't price are left out of the workbook and listed in
+    /// `unpriced_shots` instead of failing the run. Shots arrive as
+    /// `shot_extracted` events while it runs, each with its `confidence?` and
+    /// `extraction_flags?`. `mapping` is an `ExportMapping` laying out the
+    /// Excel sheet; `workspace_dir` is where the bid's index and scratch
+    /// files go. `scene_hints` lists the `SceneBoundary`s found in a text
+    /// script, to chunk extraction by scene
+    ProcessScript,
+    /// Params: `{ path }`. Result: `{ shot_count, categories: [...],
+    /// page_count? }`. Parses the script and counts candidate VFX shots
+    /// without pricing them or writing a workbook, for a quick preview
+    ScanScript,
+    /// Params: `{ path, workspace_dir }`. Result: `{ summary: { total_shots,
+    /// script_name }, shots: [...] }`
+    LoadBid,
+    /// Params: `{ message, bid_context: { bid_id, script_name, shot_count,
+    /// grand_total, currency, scope?, ... } | null, read_only?, use_llm?,
+    /// workspace_dir?, apply_changes? }`. Result: `{ explanation,
+    /// action_type?, action_params?, query_result? }`. With `apply_changes`
+    /// false, edits come back as `action_type` and `action_params` for the
+    /// app to confirm and apply
+    ChatCommand,
+    /// Params: `{ query_type, params }`. Result depends on the query, e.g.
+    /// `{ total_budget, shot_count, average_cost }` or `{ shots }`
+    BidQuery,
+    /// Params: `{ template_path, output_path, placeholders, shots,
+    /// grand_total, departments, meta, mapping }`. Result: `{ excel_path }`
+    ExportBidWithTemplate,
+    /// Notification with `{ id }`; no reply. Stops the call with that id
+    Cancel,
+    /// Params: `{}`. Result: `{ architecture?, name?, parameter_count?,
+    /// quantization?, context_length? }` for the loaded model
+    ModelInfo,
+    /// Notification with `{ workspace_dir }`; no reply. Closes anything the
+    /// sidecar holds open in a bid's workspace so it can be deleted
+    ReleaseWorkspace,
+    /// Params: `{}`. Result: `{ protocol_version, methods: [...],
+    /// sidecar_version? }`; see `protocol`. Sidecars without it answer
+    /// "Method not found"
+    GetProtocolInfo,
+}
+
+impl RpcMethod {
+    pub const ALL: [RpcMethod; 14] = [
+        RpcMethod::Health,
+        RpcMethod::Configure,
+        RpcMethod::LoadModel,
+        RpcMethod::UnloadModel,
+        RpcMethod::ProcessScript,
+        RpcMethod::ScanScript,
+        RpcMethod::LoadBid,
+        RpcMethod::ChatCommand,
+        RpcMethod::BidQuery,
+        RpcMethod::ExportBidWithTemplate,
+        RpcMethod::Cancel,
+        RpcMethod::ModelInfo,
+        RpcMethod::ReleaseWorkspace,
+        RpcMethod::GetProtocolInfo,
+    ];
+
+    /// Name on the wire
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RpcMethod::Health => "health",
+            RpcMethod::Configure => "configure",
+            RpcMethod::LoadModel => "load_model",
+            RpcMethod::UnloadModel => "unload_model",
+            RpcMethod::ProcessScript => "process_script",
+            RpcMethod::ScanScript => "scan_script",
+            RpcMethod::LoadBid => "load_bid",
+            RpcMethod::ChatCommand => "chat_command",
+            RpcMethod::BidQuery => "bid_query",
+            RpcMethod::ExportBidWithTemplate => "export_bid_with_template",
+            RpcMethod::Cancel => "cancel",
+            RpcMethod::ModelInfo => "model_info",
+            RpcMethod::ReleaseWorkspace => "release_workspace",
+            RpcMethod::GetProtocolInfo => "get_protocol_info",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RpcMethod> {
+        Self::ALL.into_iter().find(|method| method.as_str() == name)
+    }
+}
+
+impl fmt::Display for RpcMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_match_python_side() {
+        // Changing any of these breaks every installed rpc_server.py
+        let names: Vec<_> = RpcMethod::ALL.iter().map(|m| m.as_str()).collect();
+        assert_eq!(names, vec![
+            "health",
+            "configure",
+            "load_model",
+            "unload_model",
+            "process_script",
+            "scan_script",
+            "load_bid",
+            "chat_command",
+            "bid_query",
+            "export_bid_with_template",
+            "cancel",
+            "model_info",
+            "release_workspace",
+            "get_protocol_info",
+        ]);
+
+        for method in RpcMethod::ALL {
+            assert_eq!(serde_json::to_value(method).unwrap(), serde_json::json!(method.as_str()));
+            assert_eq!(RpcMethod::from_name(&method.to_string()), Some(method));
+        }
+        assert_eq!(RpcMethod::from_name("all_shots"), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! Per-method latency counters for sidecar RPC calls
+//!
+//! Feeds the debug panel so slow LLM methods can be spotted without a profiler.
+//! Each call's time is split into waiting for the client and the sidecar's
+//! stdin, the round trip to Python, and decoding the result, so a slow chat
+//! can be pinned on the LLM or on our own locking. Calls over the warn
+//! threshold are logged, and one running past twice its method's p95 is
+//! reported as a `slow_operation` event while it is still in flight.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use super::process::EventSink;
+use super::rpc::ProgressEvent;
+
+/// Number of recent samples per method kept for the percentiles
+const SAMPLE_WINDOW: usize = 500;
+
+/// Calls a method needs on record before one can count as slow
+const MIN_SLOW_SAMPLES: usize = 20;
+
+/// A call running this many times its method's p95 is slow
+const SLOW_FACTOR: u32 = 2;
+
+/// Calls over this are logged at warn level unless the settings say otherwise
+pub const DEFAULT_WARN_AFTER: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct MethodSamples {
+    count: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+    queue_wait: Duration,
+    round_trip: Duration,
+    decode: Duration,
+    recent: VecDeque<Duration>,
+}
+
+/// Where the time of one call went
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallSpan {
+    /// Waiting for the client and the sidecar's stdin, until the request
+    /// was written
+    pub queue_wait: Duration,
+    /// From the request being written until its response came back
+    pub round_trip: Duration,
+    /// Reading blob files back into the result
+    pub decode: Duration,
+}
+
+impl CallSpan {
+    pub fn total(&self) -> Duration {
+        self.queue_wait + self.round_trip + self.decode
+    }
+}
+
+/// Data of the `slow_operation` event
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SlowCall {
+    pub method: String,
+    /// How long the call had been running when it was reported
+    pub elapsed_ms: f64,
+    pub p95_ms: f64,
+}
+
+/// Aggregated latency for one RPC method, in milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MethodMetrics {
+    pub method: String,
+    pub count: u64,
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    /// Mean of each part of a call's time
+    #[serde(default)]
+    pub mean_queue_wait_ms: f64,
+    #[serde(default)]
+    pub mean_round_trip_ms: f64,
+    #[serde(default)]
+    pub mean_decode_ms: f64,
+}
+
+/// Latency counters shared by every client of a sidecar
+#[derive(Default)]
+pub struct RpcMetrics {
+    methods: Mutex<HashMap<String, MethodSamples>>,
+    /// Calls over this are logged at warn level; None logs none
+    warn_after: Mutex<Option<Duration>>,
+    /// Receives `slow_operation` events
+    slow_sink: Mutex<Option<EventSink>>,
+}
+
+impl RpcMetrics {
+    /// Record one finished call, all of it as round trip
+    #[cfg(test)]
+    pub fn record(&self, method: &str, elapsed: Duration, ok: bool) {
+        self.record_span(method, CallSpan { round_trip: elapsed, ..Default::default() }, ok);
+    }
+
+    /// Record one finished call, with where its time went
+    pub fn record_span(&self, method: &str, span: CallSpan, ok: bool) {
+        let elapsed = span.total();
+        {
+            let mut methods = self.methods.lock().unwrap();
+            let samples = methods.entry(method.to_string()).or_default();
+
+            samples.count += 1;
+            if !ok {
+                samples.errors += 1;
+            }
+            samples.total += elapsed;
+            samples.max = samples.max.max(elapsed);
+            samples.queue_wait += span.queue_wait;
+            samples.round_trip += span.round_trip;
+            samples.decode += span.decode;
+
+            if samples.recent.len() == SAMPLE_WINDOW {
+                samples.recent.pop_front();
+            }
+            samples.recent.push_back(elapsed);
+        }
+
+        if self.warn_after.lock().unwrap().is_some_and(|limit| elapsed > limit) {
+            log::warn!(
+                "RPC {} took {:.0}ms: queue {:.0}ms, round trip {:.0}ms, decode {:.0}ms",
+                method, millis(elapsed), millis(span.queue_wait), millis(span.round_trip), millis(span.decode)
+            );
+        }
+    }
+
+    /// Log calls that take longer than `limit`; None turns the log off
+    pub fn set_warn_after(&self, limit: Option<Duration>) {
+        *self.warn_after.lock().unwrap() = limit;
+    }
+
+    /// Send `slow_operation` events to `sink`
+    pub fn set_slow_sink(&self, sink: EventSink) {
+        *self.slow_sink.lock().unwrap() = Some(sink);
+    }
+
+    /// How long a `method` call may run before it's reported as slow, and
+    /// the method's p95; None until the method has enough calls on record
+    pub fn slow_after(&self, method: &str) -> Option<(Duration, Duration)> {
+        let methods = self.methods.lock().unwrap();
+        let samples = methods.get(method).filter(|s| s.recent.len() >= MIN_SLOW_SAMPLES)?;
+        let mut sorted: Vec<Duration> = samples.recent.iter().copied().collect();
+        sorted.sort();
+        let p95 = percentile(&sorted, 95.0)?;
+        Some((p95 * SLOW_FACTOR, p95))
+    }
+
+    /// Tell the sink a `method` call has been running for `elapsed`
+    pub fn report_slow(&self, method: &str, elapsed: Duration, p95: Duration) {
+        let Some(sink) = self.slow_sink.lock().unwrap().clone() else {
+            return;
+        };
+        let call = SlowCall { method: method.to_string(), elapsed_ms: millis(elapsed), p95_ms: millis(p95) };
+        sink(ProgressEvent {
+            event: "slow_operation".to_string(),
+            data: serde_json::to_value(call).unwrap_or_default(),
+        });
+    }
+
+    /// Aggregates for every method seen so far, slowest p95 first
+    ///
+    /// Percentiles cover the most recent calls only; counts and totals cover
+    /// everything since the last reset.
+    pub fn snapshot(&self) -> Vec<MethodMetrics> {
+        let methods = self.methods.lock().unwrap();
+
+        let mut metrics: Vec<MethodMetrics> = methods
+            .iter()
+            .map(|(method, samples)| {
+                let mut sorted: Vec<Duration> = samples.recent.iter().copied().collect();
+                sorted.sort();
+                let mean = |total: Duration| millis(total) / samples.count.max(1) as f64;
+
+                MethodMetrics {
+                    method: method.clone(),
+                    count: samples.count,
+                    errors: samples.errors,
+                    total_ms: millis(samples.total),
+                    mean_ms: millis(samples.total) / samples.count.max(1) as f64,
+                    p50_ms: percentile(&sorted, 50.0).map(millis).unwrap_or(0.0),
+                    p95_ms: percentile(&sorted, 95.0).map(millis).unwrap_or(0.0),
+                    max_ms: millis(samples.max),
+                    mean_queue_wait_ms: mean(samples.queue_wait),
+                    mean_round_trip_ms: mean(samples.round_trip),
+                    mean_decode_ms: mean(samples.decode),
+                }
+            })
+            .collect();
+
+        metrics.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms).then_with(|| a.method.cmp(&b.method)));
+        metrics
+    }
+
+    /// Clear all counters
+    pub fn reset(&self) {
+        self.methods.lock().unwrap().clear();
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=20).map(ms).collect();
+
+        assert_eq!(percentile(&sorted, 50.0), Some(ms(10)));
+        assert_eq!(percentile(&sorted, 95.0), Some(ms(19)));
+        assert_eq!(percentile(&sorted, 100.0), Some(ms(20)));
+        assert_eq!(percentile(&sorted, 0.0), Some(ms(1)));
+        assert_eq!(percentile(&[ms(7)], 95.0), Some(ms(7)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_snapshot_aggregates_per_method() {
+        let metrics = RpcMetrics::default();
+        for n in [40, 10, 30, 20, 100] {
+            metrics.record("chat_command", ms(n), true);
+        }
+        metrics.record("chat_command", ms(50), false);
+        metrics.record("health", ms(2), true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let chat = &snapshot[0];
+        assert_eq!(chat.method, "chat_command");
+        assert_eq!(chat.count, 6);
+        assert_eq!(chat.errors, 1);
+        assert_eq!(chat.total_ms, 250.0);
+        assert!((chat.mean_ms - 250.0 / 6.0).abs() < 1e-9);
+        assert_eq!(chat.p50_ms, 30.0);
+        assert_eq!(chat.p95_ms, 100.0);
+        assert_eq!(chat.max_ms, 100.0);
+
+        assert_eq!(snapshot[1].method, "health");
+        assert_eq!(snapshot[1].p50_ms, 2.0);
+    }
+
+    #[test]
+    fn test_percentiles_use_recent_window() {
+        let metrics = RpcMetrics::default();
+        for _ in 0..SAMPLE_WINDOW {
+            metrics.record("bid_query", ms(1000), true);
+        }
+        for _ in 0..SAMPLE_WINDOW {
+            metrics.record("bid_query", ms(10), true);
+        }
+
+        let query = &metrics.snapshot()[0];
+        assert_eq!(query.count, 2 * SAMPLE_WINDOW as u64);
+        assert_eq!(query.p95_ms, 10.0);
+        assert_eq!(query.max_ms, 1000.0);
+    }
+
+    #[test]
+    fn test_spans_and_slow_threshold() {
+        let metrics = RpcMetrics::default();
+        for n in 1..=MIN_SLOW_SAMPLES as u64 {
+            assert_eq!(metrics.slow_after("chat_command"), None);
+            let span = CallSpan { queue_wait: ms(2), round_trip: ms(n * 10), decode: ms(1) };
+            metrics.record_span("chat_command", span, true);
+        }
+
+        let chat = &metrics.snapshot()[0];
+        assert_eq!(chat.mean_queue_wait_ms, 2.0);
+        assert_eq!(chat.mean_round_trip_ms, 105.0);
+        assert_eq!(chat.mean_decode_ms, 1.0);
+        assert_eq!(chat.max_ms, 203.0);
+        // p95 of 13, 23, ... 203ms is 193ms
+        assert_eq!(metrics.slow_after("chat_command"), Some((ms(386), ms(193))));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        metrics.set_slow_sink(Arc::new(move |event| seen.lock().unwrap().push(event)));
+        metrics.report_slow("chat_command", ms(400), ms(193));
+        let events = events.lock().unwrap();
+        assert_eq!(events[0].event, "slow_operation");
+        assert_eq!(events[0].data["elapsed_ms"], 400.0);
+        assert_eq!(events[0].data["p95_ms"], 193.0);
+    }
+
+    #[test]
+    fn test_reset_clears_counters() {
+        let metrics = RpcMetrics::default();
+        metrics.record("health", ms(5), true);
+        metrics.reset();
+
+        assert!(metrics.snapshot().is_empty());
+    }
+}
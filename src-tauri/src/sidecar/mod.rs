@@ -1,7 +1,25 @@
 // Python sidecar process management
+pub mod blob;
+pub mod env;
+pub mod log_ring;
+pub mod methods;
+pub mod metrics;
+pub mod pool;
 pub mod process;
+pub mod protocol;
+pub mod query_cache;
 pub mod rpc;
+pub mod smoke;
 
 // Public exports
-pub use process::{PythonSidecar, start_sidecar, stop_sidecar};
+pub use blob::BlobStore;
+pub use env::SidecarEnv;
+pub use log_ring::LogRing;
+pub use methods::RpcMethod;
+pub use metrics::{MethodMetrics, RpcMetrics};
+pub use pool::{PoolStats, RpcPool};
+pub use process::{EventSink, PythonSidecar, start_sidecar, stop_sidecar};
+pub use protocol::ProtocolInfo;
+pub use query_cache::QueryCache;
 pub use rpc::{RpcClient, AsyncRpcClient, RpcRequest, RpcResponse, RpcError, ProgressEvent};
+pub use smoke::{SmokePhase, SmokeTestResult};
@@ -0,0 +1,280 @@
+//! Worker threads for blocking RPC calls
+//!
+//! Each call blocks a thread until the sidecar answers. Running them here
+//! rather than on tokio's blocking pool keeps a burst of queries from
+//! starving the autosave and log writes that use that pool. Calls run in
+//! the order they arrive, at most `max_in_flight` at a time; once
+//! `QUEUE_HIGH_WATER` are waiting, new ones fail fast with
+//! `RPC_BACKPRESSURE_CODE`.
+
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use super::rpc::{RpcError, RPC_CANCELLED_CODE};
+
+/// Calls running at once unless the settings say otherwise
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+/// Calls that may wait for a worker before new ones are turned away
+pub const QUEUE_HIGH_WATER: usize = 50;
+
+/// Error code for a call turned away because too many are waiting
+pub const RPC_BACKPRESSURE_CODE: i32 = -32012;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    max_in_flight: usize,
+    workers: usize,
+    /// Workers waiting for a job
+    idle: usize,
+    /// Workers running one
+    busy: usize,
+    peak_depth: usize,
+    rejected: u64,
+    shut_down: bool,
+}
+
+/// Queue depth and load, for `get_rpc_metrics`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct PoolStats {
+    pub max_in_flight: usize,
+    pub in_flight: usize,
+    /// Calls waiting for a worker
+    pub queue_depth: usize,
+    pub peak_queue_depth: usize,
+    /// Calls turned away since the app started
+    pub rejected: u64,
+}
+
+/// FIFO pool of RPC worker threads, started as calls need them
+pub struct RpcPool {
+    shared: Arc<(Mutex<Queue>, Condvar)>,
+}
+
+impl Default for RpcPool {
+    fn default() -> Self {
+        let queue = Queue {
+            jobs: VecDeque::new(),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            workers: 0,
+            idle: 0,
+            busy: 0,
+            peak_depth: 0,
+            rejected: 0,
+            shut_down: false,
+        };
+        Self { shared: Arc::new((Mutex::new(queue), Condvar::new())) }
+    }
+}
+
+impl RpcPool {
+    /// Run `work` for a `method` call on a worker and wait for its result
+    ///
+    /// Fails with `RPC_BACKPRESSURE_CODE` when the queue is full, and with
+    /// `RPC_CANCELLED_CODE` when the pool shuts down before the call starts.
+    pub async fn run<T, F>(&self, method: &str, work: F) -> Result<T, RpcError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, RpcError> + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.submit(method, Box::new(move || {
+            let _ = sender.send(work());
+        }))?;
+        receiver.await.unwrap_or_else(|_| Err(cancelled_error(method)))
+    }
+
+    /// Allow `limit` calls at once, from the next call on
+    pub fn set_max_in_flight(&self, limit: usize) {
+        let (lock, wake) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+        queue.max_in_flight = limit.max(1);
+        self.spawn_workers(&mut queue);
+        // Workers over the new limit exit when they wake
+        wake.notify_all();
+    }
+
+    /// Cancel the calls still waiting and let the workers exit
+    ///
+    /// Calls already running finish within their own timeouts; nothing here
+    /// waits for them, so the app can exit straight away.
+    pub fn shutdown(&self) {
+        let (lock, wake) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+        queue.shut_down = true;
+        queue.jobs.clear();
+        wake.notify_all();
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let queue = self.shared.0.lock().unwrap();
+        PoolStats {
+            max_in_flight: queue.max_in_flight,
+            in_flight: queue.busy,
+            queue_depth: queue.jobs.len(),
+            peak_queue_depth: queue.peak_depth,
+            rejected: queue.rejected,
+        }
+    }
+
+    fn submit(&self, method: &str, job: Job) -> Result<(), RpcError> {
+        let (lock, wake) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+        if queue.shut_down {
+            return Err(cancelled_error(method));
+        }
+        if queue.jobs.len() >= QUEUE_HIGH_WATER {
+            queue.rejected += 1;
+            return Err(backpressure_error(method, queue.jobs.len()));
+        }
+
+        queue.jobs.push_back(job);
+        queue.peak_depth = queue.peak_depth.max(queue.jobs.len());
+        self.spawn_workers(&mut queue);
+        wake.notify_one();
+        Ok(())
+    }
+
+    /// Start workers for queued jobs no idle worker will pick up, up to the
+    /// limit
+    fn spawn_workers(&self, queue: &mut Queue) {
+        while queue.jobs.len() > queue.idle && queue.workers < queue.max_in_flight {
+            queue.workers += 1;
+            let shared = self.shared.clone();
+            let spawned = std::thread::Builder::new()
+                .name("rpc-worker".to_string())
+                .spawn(move || work(&shared));
+            if let Err(e) = spawned {
+                log::error!("Failed to start RPC worker: {}", e);
+                queue.workers -= 1;
+                break;
+            }
+        }
+    }
+}
+
+fn work(shared: &(Mutex<Queue>, Condvar)) {
+    let (lock, wake) = shared;
+    let mut queue = lock.lock().unwrap();
+    loop {
+        if queue.workers > queue.max_in_flight {
+            break;
+        }
+        if let Some(job) = queue.jobs.pop_front() {
+            queue.busy += 1;
+            drop(queue);
+            // A panicking call drops its sender, which its caller sees as
+            // cancelled; the worker carries on
+            let _ = std::panic::catch_unwind(AssertUnwindSafe(job));
+            queue = lock.lock().unwrap();
+            queue.busy -= 1;
+            continue;
+        }
+        if queue.shut_down {
+            break;
+        }
+        queue.idle += 1;
+        queue = wake.wait(queue).unwrap();
+        queue.idle -= 1;
+    }
+    queue.workers -= 1;
+}
+
+fn cancelled_error(method: &str) -> RpcError {
+    RpcError {
+        code: RPC_CANCELLED_CODE,
+        message: format!("RPC method '{}' was cancelled before it started", method),
+        data: None,
+    }
+}
+
+fn backpressure_error(method: &str, waiting: usize) -> RpcError {
+    RpcError {
+        code: RPC_BACKPRESSURE_CODE,
+        message: format!("RPC method '{}' was turned away: {} calls are already waiting for the sidecar", method, waiting),
+        data: Some(serde_json::json!({ "method": method, "queue_depth": waiting })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// A job that records its number and then blocks until `release` fires
+    fn gated(order: &Arc<Mutex<Vec<usize>>>, n: usize, release: &Arc<Mutex<mpsc::Receiver<()>>>) -> impl FnOnce() -> Result<usize, RpcError> + Send + 'static {
+        let (order, release) = (order.clone(), release.clone());
+        move || {
+            order.lock().unwrap().push(n);
+            let _ = release.lock().unwrap().recv_timeout(Duration::from_secs(5));
+            Ok(n)
+        }
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    #[tokio::test]
+    async fn test_calls_run_in_order_within_the_limit() {
+        let pool = Arc::new(RpcPool::default());
+        pool.set_max_in_flight(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (release, gate) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(gate));
+
+        let mut calls = Vec::new();
+        for n in 0..3 {
+            let (pool, job) = (pool.clone(), gated(&order, n, &gate));
+            calls.push(tokio::spawn(async move { pool.run("bid_query", job).await }));
+            settle().await;
+        }
+        let stats = pool.stats();
+        assert_eq!((stats.in_flight, stats.queue_depth, stats.peak_queue_depth), (1, 2, 2));
+
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        for (n, call) in calls.into_iter().enumerate() {
+            assert_eq!(call.await.unwrap().unwrap(), n);
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(pool.stats().queue_depth, 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_fails_fast_and_shutdown_cancels_waiting_calls() {
+        let pool = Arc::new(RpcPool::default());
+        pool.set_max_in_flight(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (release, gate) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(gate));
+
+        let mut calls = Vec::new();
+        for n in 0..=QUEUE_HIGH_WATER {
+            let (pool, job) = (pool.clone(), gated(&order, n, &gate));
+            calls.push(tokio::spawn(async move { pool.run("bid_query", job).await }));
+            if n == 0 {
+                settle().await;
+            }
+        }
+        settle().await;
+        assert_eq!(pool.stats().queue_depth, QUEUE_HIGH_WATER);
+
+        let error = pool.run("bid_query", || Ok(())).await.unwrap_err();
+        assert_eq!(error.code, RPC_BACKPRESSURE_CODE);
+        assert_eq!(pool.stats().rejected, 1);
+
+        pool.shutdown();
+        release.send(()).unwrap();
+        let results: Vec<_> = futures_util::future::join_all(calls).await;
+        assert_eq!(results[0].as_ref().unwrap().as_ref().unwrap(), &0);
+        assert!(results[1..].iter().all(|r| r.as_ref().unwrap().as_ref().unwrap_err().code == RPC_CANCELLED_CODE));
+        assert_eq!(pool.run("bid_query", || Ok(())).await.unwrap_err().code, RPC_CANCELLED_CODE);
+    }
+}
@@ -1,17 +1,97 @@
 use std::process::{Command, Child, Stdio};
 use std::path::{Path, PathBuf};
 use std::io::{BufRead, BufReader, Write};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+use crate::events::ModelLocked;
+use super::env::{redacted, SidecarEnv};
+use super::log_ring::LogRing;
+use super::rpc::{ProgressEvent, RpcDispatcher};
+
+/// Callback invoked for each event the sidecar writes to stderr
+pub type EventSink = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// How long a new sidecar is watched for an immediate exit
+const STARTUP_GRACE: Duration = Duration::from_millis(750);
+
+/// Stderr lines kept to explain an exit during startup
+const STARTUP_STDERR_LINES: usize = 10;
+
+/// Sent through the event sink when the model file stays locked; reaches
+/// the frontend as `model-locked`
+pub const MODEL_LOCKED_EVENT: &str = "model_locked";
+
+/// Stderr fragments meaning the model file is held by another process,
+/// typically the previous sidecar still releasing its mapping
+const LOCK_MARKERS: &[&str] = &[
+    "used by another process",
+    "sharing violation",
+    "resource busy",
+    "text file busy",
+    "resource temporarily unavailable",
+];
+
+/// Retries for a start that failed because the model file was locked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockRetry {
+    /// Starts in total, including the first
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for LockRetry {
+    fn default() -> Self {
+        Self { attempts: 4, delay: Duration::from_millis(1500) }
+    }
+}
+
+/// Whether a failed start was the model file being in use
+///
+/// Permission errors only count when they name the model, so a missing
+/// execute bit on Python isn't retried.
+pub fn is_model_lock_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    LOCK_MARKERS.iter().any(|marker| message.contains(marker))
+        || ((message.contains("permission denied") || message.contains("access is denied"))
+            && (message.contains(".gguf") || message.contains("mmap")))
+}
+
+/// Run `start` until it succeeds, fails for another reason, or the lock
+/// retries run out
+fn retry_while_locked<T>(
+    retry: LockRetry,
+    mut start: impl FnMut() -> Result<T, String>,
+    mut wait: impl FnMut(Duration),
+) -> Result<T, String> {
+    let mut attempt = 1;
+    loop {
+        match start() {
+            Err(e) if is_model_lock_error(&e) && attempt < retry.attempts => {
+                log::warn!("Model file is locked ({}); retry {} of {} in {:?}", e, attempt + 1, retry.attempts, retry.delay);
+                wait(retry.delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
 
 /// Python sidecar process manager
 /// Handles the lifecycle of the Python RPC server process
 pub struct PythonSidecar {
     child: Option<Child>,
     rpc_path: PathBuf,
+    event_sink: Option<EventSink>,
+    env: SidecarEnv,
+    log: Arc<LogRing>,
     // Keep references to stdin/stdout for RPC communication
     stdin: Option<Arc<Mutex<Box<dyn Write + Send>>>>,
-    stdout: Option<Arc<Mutex<Box<dyn BufRead + Send>>>>,
+    dispatcher: Option<Arc<RpcDispatcher>>,
+    /// Raised by an RPC client whose write found stdin closed
+    pipe_closed: Arc<AtomicBool>,
 }
 
 impl PythonSidecar {
@@ -23,6 +103,59 @@ impl PythonSidecar {
     /// # Returns
     /// Result containing PythonSidecar instance or error message
     pub fn start(script_path: &Path) -> Result<Self, String> {
+        Self::start_with_events(script_path, None, SidecarEnv::default(), Arc::default())
+    }
+
+    /// Start the Python sidecar process, forwarding events to `event_sink`
+    ///
+    /// Events arrive both as JSON lines on stderr and as JSON-RPC
+    /// notifications on stdout. The process environment is built from `env`,
+    /// and every stderr line is also kept in `log`.
+    pub fn start_with_events(
+        script_path: &Path,
+        event_sink: Option<EventSink>,
+        env: SidecarEnv,
+        log: Arc<LogRing>,
+    ) -> Result<Self, String> {
+        let python = default_python();
+
+        // The previous sidecar may still hold the model for a moment after a
+        // restart, which fails the new one's load
+        let result = retry_while_locked(
+            LockRetry::default(),
+            || Self::start_with_python(&python, script_path, event_sink.clone(), env.clone(), log.clone()),
+            thread::sleep,
+        );
+
+        if let (Err(e), Some(sink)) = (&result, &event_sink) {
+            if is_model_lock_error(e) {
+                sink(ProgressEvent {
+                    event: MODEL_LOCKED_EVENT.to_string(),
+                    data: serde_json::to_value(ModelLocked {
+                        message: "The model file is still in use by another process. \
+                                  Close other copies of the app or wait a few seconds, then restart the sidecar."
+                            .to_string(),
+                        detail: e.to_string(),
+                    })
+                    .unwrap_or_default(),
+                });
+            }
+        }
+        result
+    }
+
+    /// Start the sidecar with a specific interpreter, e.g. the one picked in
+    /// the setup wizard
+    ///
+    /// A process that exits within moments of starting counts as a failed
+    /// start, with its last stderr lines as the error.
+    pub fn start_with_python(
+        python: &str,
+        script_path: &Path,
+        event_sink: Option<EventSink>,
+        env: SidecarEnv,
+        log: Arc<LogRing>,
+    ) -> Result<Self, String> {
         let script_path = if script_path.is_absolute() {
             script_path.to_path_buf()
         } else {
@@ -37,44 +170,22 @@ impl PythonSidecar {
             return Err(format!("RPC server script not found: {}", script_path.display()));
         }
 
-        // Determine Python executable
-        // Prefer VFX_PYTHON_PATH env var, otherwise try venv, then system python
-        let python = if let Ok(py) = std::env::var("VFX_PYTHON_PATH") {
-            py
-        } else {
-            // Try to find venv Python relative to project root
-            if let Ok(cwd) = std::env::current_dir() {
-                let venv_python = cwd.join("venv/bin/python");
-                if venv_python.exists() {
-                    venv_python.to_string_lossy().to_string()
-                } else {
-                    "python3".to_string()
-                }
-            } else {
-                "python3".to_string()
-            }
-        };
-
         log::info!("Starting Python sidecar: {} {}", python, script_path.display());
 
-        // Set PYTHONPATH to include the resources directory
+        // PYTHONPATH points at the resources directory
         let resources_dir = script_path.parent()
             .unwrap_or_else(|| Path::new("."));
-        let mut cmd = Command::new(&python);
+        let vars = env.build(std::env::vars(), resources_dir);
+        log::info!("Sidecar environment: {}", redacted(&vars));
+
+        let mut cmd = Command::new(python);
         cmd.arg(&script_path)
+            .env_clear()
+            .envs(&vars)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        // Add resources directory to PYTHONPATH
-        if let Ok(mut pythonpath) = std::env::var("PYTHONPATH") {
-            pythonpath.push_str(":");
-            pythonpath.push_str(resources_dir.to_string_lossy().as_ref());
-            cmd.env("PYTHONPATH", pythonpath);
-        } else {
-            cmd.env("PYTHONPATH", resources_dir.to_string_lossy().as_ref());
-        }
-
         let mut child = cmd.spawn()
             .map_err(|e| format!("Failed to start Python sidecar: {}", e))?;
 
@@ -88,15 +199,25 @@ impl PythonSidecar {
 
         // Spawn a thread to monitor stderr for events and logging
         let stderr_reader = BufReader::new(stderr);
-        thread::spawn(move || {
+        let sink = event_sink.clone();
+        let stderr_log = log.clone();
+        let stderr_thread = thread::spawn(move || {
+            // This process's own last lines; `log` also holds earlier runs
+            let mut recent = VecDeque::with_capacity(STARTUP_STDERR_LINES);
             for line in stderr_reader.lines() {
                 match line {
                     Ok(l) => {
+                        stderr_log.push(l.clone());
+                        if recent.len() == STARTUP_STDERR_LINES {
+                            recent.pop_front();
+                        }
+                        recent.push_back(l.clone());
+
                         // Try to parse as JSON event
-                        if let Ok(event) = serde_json::from_str::<serde_json::Value>(&l) {
-                            if let Some(event_type) = event.get("event").and_then(|e| e.as_str()) {
-                                log::info!("Python sidecar event: {}", event_type);
-                                // TODO: Emit to Tauri event system
+                        if let Ok(event) = serde_json::from_str::<ProgressEvent>(&l) {
+                            log::info!("Python sidecar event: {}", event.event);
+                            if let Some(ref sink) = sink {
+                                sink(event);
                             }
                         } else {
                             // Regular log line
@@ -109,13 +230,25 @@ impl PythonSidecar {
                     }
                 }
             }
+            recent
         });
 
+        // A sidecar that can't import its modules or map the model exits at
+        // once; report that here rather than as calls that never get answers
+        if let Some(status) = wait_for_exit(&mut child, STARTUP_GRACE) {
+            let stderr: Vec<String> = stderr_thread.join().map(Vec::from).unwrap_or_default();
+            return Err(format!("Python sidecar exited during startup ({}): {}", status, stderr.join("\n")));
+        }
+
         Ok(Self {
             child: Some(child),
             rpc_path: script_path,
+            event_sink: event_sink.clone(),
+            env,
+            log,
             stdin: Some(Arc::new(Mutex::new(Box::new(stdin)))),
-            stdout: Some(Arc::new(Mutex::new(Box::new(BufReader::new(stdout))))),
+            dispatcher: Some(RpcDispatcher::spawn(Box::new(BufReader::new(stdout)), event_sink)),
+            pipe_closed: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -124,9 +257,19 @@ impl PythonSidecar {
         self.stdin.clone()
     }
 
-    /// Get stdout handle for reading JSON-RPC responses
-    pub fn stdout(&self) -> Option<Arc<Mutex<Box<dyn BufRead + Send>>>> {
-        self.stdout.clone()
+    /// Get the dispatcher that routes JSON-RPC responses read from stdout
+    pub fn dispatcher(&self) -> Option<Arc<RpcDispatcher>> {
+        self.dispatcher.clone()
+    }
+
+    pub fn pipe_closed_flag(&self) -> Arc<AtomicBool> {
+        self.pipe_closed.clone()
+    }
+
+    /// Whether a write to stdin failed because the pipe is closed, which
+    /// can happen before `is_running` notices the process is gone
+    pub fn pipe_closed(&self) -> bool {
+        self.pipe_closed.load(Ordering::SeqCst)
     }
 
     /// Check if the process is still running
@@ -148,6 +291,14 @@ impl PythonSidecar {
         }
     }
 
+    /// Exit code once the process has exited
+    ///
+    /// None while it is running, after `stop`, or when it was killed by a
+    /// signal.
+    pub fn exit_code(&mut self) -> Option<i32> {
+        self.child.as_mut()?.try_wait().ok()??.code()
+    }
+
     /// Stop the sidecar process
     pub fn stop(&mut self) -> Result<(), String> {
         if let Some(mut child) = self.child.take() {
@@ -165,20 +316,32 @@ impl PythonSidecar {
         }
 
         self.stdin = None;
-        self.stdout = None;
+        self.dispatcher = None;
 
         Ok(())
     }
 
+    /// Environment to use from the next restart
+    pub fn set_env(&mut self, env: SidecarEnv) {
+        self.env = env;
+    }
+
     /// Restart the sidecar
     pub fn restart(&mut self) -> Result<(), String> {
         self.stop()?;
-        let mut new_sidecar = Self::start(&self.rpc_path)?;
+        let mut new_sidecar = Self::start_with_events(
+            &self.rpc_path,
+            self.event_sink.clone(),
+            self.env.clone(),
+            self.log.clone(),
+        )?;
 
         // Manually move the fields
         self.child = new_sidecar.child.take();
         self.stdin = new_sidecar.stdin.take();
-        self.stdout = new_sidecar.stdout.take();
+        self.dispatcher = new_sidecar.dispatcher.take();
+        // Clients of the old process keep raising the old flag
+        self.pipe_closed = new_sidecar.pipe_closed.clone();
 
         Ok(())
     }
@@ -190,6 +353,39 @@ impl Drop for PythonSidecar {
     }
 }
 
+/// Python executable to run the sidecar with
+///
+/// Prefer VFX_PYTHON_PATH env var, otherwise try venv, then system python
+pub(crate) fn default_python() -> String {
+    if let Ok(py) = std::env::var("VFX_PYTHON_PATH") {
+        py
+    } else {
+        // Try to find venv Python relative to project root
+        if let Ok(cwd) = std::env::current_dir() {
+            let venv_python = cwd.join("venv/bin/python");
+            if venv_python.exists() {
+                venv_python.to_string_lossy().to_string()
+            } else {
+                "python3".to_string()
+            }
+        } else {
+            "python3".to_string()
+        }
+    }
+}
+
+/// Wait up to `grace` for `child` to exit; None if it is still running
+fn wait_for_exit(child: &mut Child, grace: Duration) -> Option<std::process::ExitStatus> {
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if started.elapsed() < grace => thread::sleep(Duration::from_millis(25)),
+            _ => return None,
+        }
+    }
+}
+
 /// Start sidecar (convenience function)
 pub fn start_sidecar(script_path: &Path) -> Result<PythonSidecar, String> {
     PythonSidecar::start(script_path)
@@ -199,3 +395,58 @@ pub fn start_sidecar(script_path: &Path) -> Result<PythonSidecar, String> {
 pub fn stop_sidecar(sidecar: &mut PythonSidecar) -> Result<(), String> {
     sidecar.stop()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_errors_are_recognized() {
+        assert!(is_model_lock_error("llama_model_load: failed to mmap: Resource temporarily unavailable"));
+        assert!(is_model_lock_error("The process cannot access the file because it is being used by another process"));
+        assert!(is_model_lock_error("PermissionError: [Errno 13] Permission denied: '/models/qwen.gguf'"));
+        assert!(!is_model_lock_error("PermissionError: [Errno 13] Permission denied: '/usr/bin/python3'"));
+        assert!(!is_model_lock_error("ModuleNotFoundError: No module named 'llama_cpp'"));
+    }
+
+    #[test]
+    fn test_retry_only_while_locked() {
+        let retry = LockRetry { attempts: 3, delay: Duration::from_millis(10) };
+        let locked = || Err::<(), _>("mmap failed: Device or resource busy".to_string());
+
+        // Freed on the second attempt
+        let mut results = vec![locked(), Ok(())].into_iter();
+        let mut waits = Vec::new();
+        assert!(retry_while_locked(retry, || results.next().unwrap(), |d| waits.push(d)).is_ok());
+        assert_eq!(waits, vec![Duration::from_millis(10)]);
+
+        // Still locked after every attempt
+        let mut calls = 0;
+        let error = retry_while_locked(retry, || { calls += 1; locked() }, |_| {}).unwrap_err();
+        assert_eq!(calls, 3);
+        assert!(is_model_lock_error(&error));
+
+        // Other failures are not retried
+        let mut calls = 0;
+        let error = retry_while_locked(retry, || { calls += 1; Err::<(), _>("No module named 'llama_cpp'".to_string()) }, |_| {});
+        assert_eq!((calls, error.unwrap_err().as_str()), (1, "No module named 'llama_cpp'"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_early_exit_fails_the_start() {
+        let dir = std::env::temp_dir().join(format!("sidecar-exit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("rpc_server.py");
+        std::fs::write(&script, "echo 'failed to mmap /models/a.gguf: Resource busy' >&2\nexit 3\n").unwrap();
+
+        let error = match PythonSidecar::start_with_python("/bin/sh", &script, None, SidecarEnv::default(), Arc::default()) {
+            Ok(_) => panic!("sidecar should have failed to start"),
+            Err(e) => e,
+        };
+        assert!(error.contains("exited during startup"), "{}", error);
+        assert!(error.contains("Resource busy") && is_model_lock_error(&error));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
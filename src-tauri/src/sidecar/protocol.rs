@@ -0,0 +1,148 @@
+//! Protocol version negotiation with the sidecar
+//!
+//! Right after it starts, the sidecar is asked for `get_protocol_info`.
+//! Commands check the methods it advertises before calling, so an app and a
+//! sidecar from different builds fail with `UnsupportedBySidecar` rather
+//! than a timeout or a response without a result. A sidecar that predates
+//! the handshake counts as protocol 0 answering `LEGACY_METHODS`.
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use super::methods::RpcMethod;
+use super::rpc::{AsyncRpcClient, RpcError, RPC_METHOD_NOT_FOUND_CODE};
+
+/// Major protocol version this build speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Sent through the event sink when the sidecar speaks another major
+/// version; reaches the frontend as `sidecar-incompatible`
+pub const INCOMPATIBLE_EVENT: &str = "sidecar_incompatible";
+
+/// Capability of a sidecar that resolves `$blob` references in params
+/// and may answer with its own (see `blob`)
+pub const BLOB_REFS_CAPABILITY: &str = "blob_refs";
+
+/// Methods every sidecar had before `get_protocol_info`
+const LEGACY_METHODS: [RpcMethod; 8] = [
+    RpcMethod::Health,
+    RpcMethod::Configure,
+    RpcMethod::LoadModel,
+    RpcMethod::UnloadModel,
+    RpcMethod::ProcessScript,
+    RpcMethod::LoadBid,
+    RpcMethod::ChatCommand,
+    RpcMethod::BidQuery,
+];
+
+/// Answer to `get_protocol_info`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProtocolInfo {
+    pub protocol_version: u32,
+    /// Method names the sidecar answers
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub sidecar_version: Option<String>,
+    /// Optional features beyond the methods, e.g. `BLOB_REFS_CAPABILITY`
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl ProtocolInfo {
+    /// What a sidecar without `get_protocol_info` is assumed to speak
+    pub fn legacy() -> Self {
+        Self {
+            protocol_version: 0,
+            methods: LEGACY_METHODS.iter().map(|method| method.as_str().to_string()).collect(),
+            sidecar_version: None,
+            capabilities: Vec::new(),
+        }
+    }
+
+    pub fn supports(&self, method: RpcMethod) -> bool {
+        self.methods.iter().any(|name| name == method.as_str())
+    }
+
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|name| name == capability)
+    }
+
+    /// False when the sidecar speaks another major version; legacy
+    /// sidecars are limited to their methods instead
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == 0 || self.protocol_version == PROTOCOL_VERSION
+    }
+
+    /// Upgrade guidance for an incompatible sidecar
+    pub fn mismatch_message(&self) -> String {
+        let version = self.sidecar_version.as_deref().unwrap_or("unknown");
+        if self.protocol_version > PROTOCOL_VERSION {
+            format!(
+                "The Python sidecar (version {}, protocol {}) is newer than this app (protocol {}). \
+                 Update the app, or point VFX_SIDECAR_PATH at a matching rpc_server.py.",
+                version, self.protocol_version, PROTOCOL_VERSION
+            )
+        } else {
+            format!(
+                "The Python sidecar (version {}, protocol {}) is older than this app (protocol {}). \
+                 Reinstall the sidecar from this build, or remove the sidecar path override in Settings.",
+                version, self.protocol_version, PROTOCOL_VERSION
+            )
+        }
+    }
+}
+
+/// Protocol version a sidecar needs to answer `method`
+pub fn required_version(method: RpcMethod) -> u32 {
+    if LEGACY_METHODS.contains(&method) { 0 } else { PROTOCOL_VERSION }
+}
+
+/// Ask the sidecar which protocol it speaks
+///
+/// A sidecar that doesn't know the method is a legacy one.
+pub async fn negotiate(client: &AsyncRpcClient, timeout: Duration) -> Result<ProtocolInfo, RpcError> {
+    let method = RpcMethod::GetProtocolInfo.to_string();
+    match client.call_detailed(method.clone(), json!({}), timeout).await {
+        Ok(result) => serde_json::from_value(result).map_err(|e| RpcError {
+            code: -32603,
+            message: format!("Unexpected {} result: {}", method, e),
+            data: None,
+        }),
+        Err(e) if e.code == RPC_METHOD_NOT_FOUND_CODE => Ok(ProtocolInfo::legacy()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_sidecar_gets_conservative_methods() {
+        let legacy = ProtocolInfo::legacy();
+        assert!(legacy.is_compatible());
+        assert!(!legacy.has_capability(BLOB_REFS_CAPABILITY));
+        assert!(legacy.supports(RpcMethod::ProcessScript));
+        assert!(!legacy.supports(RpcMethod::ModelInfo));
+        assert_eq!(required_version(RpcMethod::ModelInfo), PROTOCOL_VERSION);
+        assert_eq!(required_version(RpcMethod::Health), 0);
+
+        let info: ProtocolInfo = serde_json::from_value(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "methods": ["health", "model_info"],
+            "sidecar_version": "2.3.0",
+        })).unwrap();
+        assert!(info.is_compatible() && info.supports(RpcMethod::ModelInfo));
+        assert!(!info.has_capability(BLOB_REFS_CAPABILITY));
+        let with_blobs: ProtocolInfo = serde_json::from_value(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "capabilities": [BLOB_REFS_CAPABILITY],
+        })).unwrap();
+        assert!(with_blobs.has_capability(BLOB_REFS_CAPABILITY));
+
+        let newer = ProtocolInfo { protocol_version: PROTOCOL_VERSION + 1, ..info };
+        assert!(!newer.is_compatible());
+        assert!(newer.mismatch_message().contains("newer than this app"));
+    }
+}
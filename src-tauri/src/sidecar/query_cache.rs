@@ -0,0 +1,144 @@
+//! Coalescing cache for read-only sidecar queries
+//!
+//! A dashboard render can ask for the same `bid_query` several times at
+//! once. Callers with the same key share the one call in flight, and with a
+//! TTL set the answer is reused until it expires or the bid changes.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use super::rpc::RpcError;
+
+type Shared = Arc<OnceCell<Result<Value, RpcError>>>;
+
+#[derive(Default)]
+pub struct QueryCache {
+    in_flight: Mutex<HashMap<String, Shared>>,
+    results: Mutex<HashMap<String, (Instant, Value)>>,
+    /// Zero keeps no results, only coalescing calls in flight
+    ttl: Mutex<Duration>,
+    /// Bumped by `invalidate`, so a call that started before it isn't cached
+    generation: AtomicU64,
+}
+
+impl QueryCache {
+    pub fn set_ttl(&self, ttl: Duration) {
+        *self.ttl.lock().unwrap() = ttl;
+        if ttl.is_zero() {
+            self.results.lock().unwrap().clear();
+        }
+    }
+
+    /// Forget cached results, as when the bid changes
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.results.lock().unwrap().clear();
+    }
+
+    /// The cached answer for `key`, or `query`'s, shared with any identical
+    /// call already running
+    ///
+    /// Errors are shared with the callers waiting on them but not cached.
+    pub async fn get_or_query<F, Fut>(&self, key: String, query: F) -> Result<Value, RpcError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, RpcError>>,
+    {
+        if let Some(value) = self.cached(&key) {
+            return Ok(value);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let call = self.in_flight.lock().unwrap().entry(key.clone()).or_default().clone();
+        let result = call.get_or_init(query).await.clone();
+
+        // Whoever finishes first retires the call so later ones ask afresh
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&key).is_some_and(|current| Arc::ptr_eq(current, &call)) {
+            in_flight.remove(&key);
+        }
+        drop(in_flight);
+
+        if let Ok(value) = &result {
+            if !self.ttl.lock().unwrap().is_zero() && self.generation.load(Ordering::SeqCst) == generation {
+                self.results.lock().unwrap().insert(key, (Instant::now(), value.clone()));
+            }
+        }
+        result
+    }
+
+    fn cached(&self, key: &str) -> Option<Value> {
+        let ttl = *self.ttl.lock().unwrap();
+        let mut results = self.results.lock().unwrap();
+        match results.get(key) {
+            Some((stored, value)) if stored.elapsed() < ttl => Some(value.clone()),
+            Some(_) => {
+                results.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+/// Cache key for a query; `serde_json` sorts object keys, so equal params
+/// give equal keys
+pub fn query_key(query_type: &str, params: &Value) -> String {
+    format!("{}:{}", query_type, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    async fn counted(calls: &AtomicUsize, value: Value) -> Result<Value, RpcError> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(value)
+    }
+
+    #[tokio::test]
+    async fn test_identical_concurrent_queries_share_one_call() {
+        let cache = QueryCache::default();
+        let calls = AtomicUsize::new(0);
+        let key = || query_key("total_cost", &json!({ "scene": "1" }));
+
+        let (first, second) = tokio::join!(
+            cache.get_or_query(key(), || counted(&calls, json!(100))),
+            cache.get_or_query(key(), || counted(&calls, json!(100))),
+        );
+        assert_eq!((first.unwrap(), second.unwrap()), (json!(100), json!(100)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Without a TTL the next query goes to the sidecar again
+        cache.get_or_query(key(), || counted(&calls, json!(100))).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Different params are a different query
+        let other = query_key("total_cost", &json!({ "scene": "2" }));
+        cache.get_or_query(other, || counted(&calls, json!(50))).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_cache_is_invalidated() {
+        let cache = QueryCache::default();
+        cache.set_ttl(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let key = || query_key("shot_count", &json!({}));
+
+        cache.get_or_query(key(), || counted(&calls, json!(3))).await.unwrap();
+        assert_eq!(cache.get_or_query(key(), || counted(&calls, json!(4))).await.unwrap(), json!(3));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.invalidate();
+        assert_eq!(cache.get_or_query(key(), || counted(&calls, json!(4))).await.unwrap(), json!(4));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
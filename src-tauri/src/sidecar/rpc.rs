@@ -1,16 +1,28 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{BufRead, Write};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::events::ProtocolDegraded;
+use super::blob::BlobStore;
+use super::methods::RpcMethod;
+use super::metrics::{CallSpan, RpcMetrics};
+use super::pool::RpcPool;
+use super::process::EventSink;
 
 /// JSON-RPC 2.0 request
+///
+/// A request without an `id` is a notification and gets no response.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RpcRequest {
     pub jsonrpc: String,
     pub method: String,
     pub params: Value,
-    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 impl RpcRequest {
@@ -20,7 +32,7 @@ impl RpcRequest {
             jsonrpc: "2.0".to_string(),
             method,
             params,
-            id: uuid::Uuid::new_v4().to_string(),
+            id: Some(uuid::Uuid::new_v4().to_string()),
         }
     }
 
@@ -30,18 +42,41 @@ impl RpcRequest {
             jsonrpc: "2.0".to_string(),
             method,
             params,
-            id,
+            id: Some(id),
+        }
+    }
+
+    /// Create a notification (no ID, no response expected)
+    pub fn notification(method: String, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method,
+            params,
+            id: None,
         }
     }
 }
 
 /// JSON-RPC 2.0 response
+///
+/// `id` is `None` when the server couldn't determine the request ID, e.g.
+/// an error response to a malformed request carries `"id": null`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RpcResponse {
     pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error: Option<RpcError>,
-    pub id: String,
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+impl RpcResponse {
+    /// The response ID as a string, if it has one
+    pub fn id_str(&self) -> Option<&str> {
+        self.id.as_ref().and_then(|id| id.as_str())
+    }
 }
 
 /// JSON-RPC error
@@ -64,26 +99,465 @@ impl std::error::Error for RpcError {}
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProgressEvent {
     pub event: String,
+    #[serde(default)]
     pub data: Value,
 }
 
+/// A line read from the sidecar's stdout
+#[derive(Debug, Clone)]
+pub enum IncomingMessage {
+    /// Response to a request (has `result` or `error`)
+    Response(RpcResponse),
+    /// Server-initiated notification (has `method`, no `id`)
+    Notification(ProgressEvent),
+    /// Legacy progress event (`{"event": ..., "data": ...}`)
+    Event(ProgressEvent),
+}
+
+impl IncomingMessage {
+    /// Classify a line of sidecar output, or `None` if it isn't JSON-RPC
+    pub fn parse(line: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        let object = value.as_object()?;
+
+        if let Some(method) = object.get("method").and_then(|m| m.as_str()) {
+            if object.get("id").is_some_and(|id| !id.is_null()) {
+                // Server-to-client requests aren't part of our protocol
+                return None;
+            }
+
+            return Some(IncomingMessage::Notification(ProgressEvent {
+                event: method.to_string(),
+                data: object.get("params").cloned().unwrap_or(Value::Null),
+            }));
+        }
+
+        if object.contains_key("result") || object.contains_key("error") {
+            return serde_json::from_value(value).ok().map(IncomingMessage::Response);
+        }
+
+        if object.contains_key("event") {
+            return serde_json::from_value(value).ok().map(IncomingMessage::Event);
+        }
+
+        None
+    }
+}
+
+/// Message routed from stdout to the call waiting on it
+#[derive(Debug)]
+enum Routed {
+    Response(RpcResponse),
+    Chunk { seq: u64, items: Vec<Value> },
+    /// The sidecar is still working
+    Heartbeat,
+    /// Sent by `cancel` to stop the wait early
+    Cancelled,
+}
+
+/// Event the sidecar writes every few seconds during a long call
+///
+/// Heartbeats carry no request id; the Python server handles one call at a
+/// time, so each one keeps every waiting call alive.
+pub const HEARTBEAT_EVENT: &str = "heartbeat";
+
+/// Marks a line of stdout as a protocol message
+///
+/// Libraries imported by the sidecar print banners to stdout too, so the
+/// server writes this record separator before each JSON-RPC message.
+pub const RECORD_SEPARATOR: char = '\x1e';
+
+/// Largest line, or multi-line value, read from stdout; longer output is
+/// dropped rather than buffered
+const MAX_MESSAGE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Consecutive unparseable messages before the stream counts as out of sync
+const RESYNC_AFTER: usize = 5;
+
+/// Event sent to the event sink when the stream falls out of sync
+pub const PROTOCOL_DEGRADED_EVENT: &str = "sidecar_protocol_degraded";
+
+/// Splits the sidecar's stdout into messages
+///
+/// Once a line starting with `RECORD_SEPARATOR` has been seen, only marked
+/// lines are messages and everything else is noise. Before that (an older
+/// server) JSON values are joined across lines by tracking bracket depth
+/// outside strings, so a pretty-printed message is handed on once it
+/// closes; a line that is complete on its own passes straight through, as
+/// does output that isn't JSON.
+#[derive(Default)]
+struct MessageFramer {
+    partial: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// The sidecar marks its messages
+    framed: bool,
+    /// Buffered values thrown away since the last `take_dropped`
+    dropped: usize,
+}
+
+impl MessageFramer {
+    /// Feed one line of stdout; returns a complete message or other output
+    fn push(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+
+        if let Some(payload) = line.strip_prefix(RECORD_SEPARATOR) {
+            self.discard();
+            self.framed = true;
+            return Some(payload.trim().to_string());
+        }
+
+        if self.framed {
+            if !line.is_empty() {
+                log::debug!("Sidecar output: {}", line);
+            }
+            return None;
+        }
+
+        if self.partial.is_empty() {
+            if !line.starts_with(['{', '[']) {
+                return Some(line.to_string());
+            }
+
+            self.scan(line);
+            if self.depth == 0 && !self.in_string {
+                return Some(line.to_string());
+            }
+
+            // Only buffer what could be the start of a JSON value, so a stray
+            // bracket in a log line can't swallow the responses after it
+            let prefix = serde_json::from_str::<serde::de::IgnoredAny>(line);
+            if !matches!(prefix, Err(ref e) if e.is_eof()) {
+                self.reset();
+                return Some(line.to_string());
+            }
+
+            self.partial.push_str(line);
+            return None;
+        }
+
+        // A complete message on its own line means the buffered value was
+        // noise that happened to start with a bracket
+        if line.starts_with('{') && IncomingMessage::parse(line).is_some() {
+            self.discard();
+            return Some(line.to_string());
+        }
+
+        self.partial.push('\n');
+        self.partial.push_str(line);
+        self.scan(line);
+
+        if self.depth == 0 && !self.in_string {
+            let message = std::mem::take(&mut self.partial);
+            self.reset();
+            return Some(message);
+        }
+
+        if self.partial.len() > MAX_MESSAGE_BYTES {
+            self.discard();
+        }
+        None
+    }
+
+    /// Throw away a half-read value
+    fn discard(&mut self) {
+        if !self.partial.is_empty() {
+            log::warn!("Discarding {} bytes of sidecar output that never closed", self.partial.len());
+            self.partial.clear();
+            self.dropped += 1;
+        }
+        self.reset();
+    }
+
+    fn take_dropped(&mut self) -> usize {
+        std::mem::take(&mut self.dropped)
+    }
+
+    fn scan(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.depth = 0;
+        self.in_string = false;
+        self.escaped = false;
+    }
+}
+
+/// Parameters of a `chunk` notification
+#[derive(Debug, Deserialize)]
+struct ChunkParams {
+    request_id: String,
+    seq: u64,
+    items: Vec<Value>,
+}
+
+/// Reads the sidecar's stdout on a background thread and routes each message
+///
+/// Responses and `chunk` notifications go to the call waiting on that request
+/// ID, so concurrent callers never consume each other's replies. Other
+/// notifications and progress events go to the event sink.
+pub struct RpcDispatcher {
+    pending: Mutex<HashMap<String, mpsc::Sender<Routed>>>,
+    event_sink: Option<EventSink>,
+    closed: AtomicBool,
+}
+
+impl RpcDispatcher {
+    /// Start routing messages read from `stdout`
+    pub fn spawn(stdout: Box<dyn BufRead + Send>, event_sink: Option<EventSink>) -> Arc<Self> {
+        let dispatcher = Arc::new(Self {
+            pending: Mutex::new(HashMap::new()),
+            event_sink,
+            closed: AtomicBool::new(false),
+        });
+
+        let reader = dispatcher.clone();
+        thread::spawn(move || reader.run(stdout));
+
+        dispatcher
+    }
+
+    fn run(&self, mut stdout: Box<dyn BufRead + Send>) {
+        let mut line = Vec::new();
+        let mut framer = MessageFramer::default();
+        let mut unparseable = 0;
+        let mut degraded = false;
+
+        loop {
+            match read_line_capped(stdout.as_mut(), &mut line, MAX_MESSAGE_BYTES) {
+                Ok((0, _)) => break,
+                Ok((_, truncated)) => {
+                    let routed = if truncated {
+                        log::warn!("Dropping a sidecar output line longer than {} bytes", MAX_MESSAGE_BYTES);
+                        Some(false)
+                    } else {
+                        framer.push(&String::from_utf8_lossy(&line)).and_then(|message| {
+                            // Plain log lines from an older server aren't errors
+                            let expected = framer.framed || message.starts_with('{');
+                            let routed = self.dispatch_line(&message);
+                            (routed || expected).then_some(routed)
+                        })
+                    };
+
+                    unparseable += framer.take_dropped();
+                    match routed {
+                        Some(true) => {
+                            unparseable = 0;
+                            degraded = false;
+                        }
+                        Some(false) => unparseable += 1,
+                        None => {}
+                    }
+
+                    if unparseable >= RESYNC_AFTER && !degraded {
+                        degraded = true;
+                        self.resync(&mut framer, unparseable);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to read from sidecar stdout: {}", e);
+                    break;
+                }
+            }
+        }
+
+        // Dropping the senders wakes every waiting call with an error
+        self.closed.store(true, Ordering::SeqCst);
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Drop any half-read value and warn that replies may be going missing
+    fn resync(&self, framer: &mut MessageFramer, unparseable: usize) {
+        framer.discard();
+        framer.take_dropped();
+        log::warn!(
+            "Sidecar protocol degraded: {} unparseable messages in a row (framed: {})",
+            unparseable,
+            framer.framed
+        );
+
+        if let Some(ref sink) = self.event_sink {
+            sink(ProgressEvent {
+                event: PROTOCOL_DEGRADED_EVENT.to_string(),
+                data: serde_json::to_value(ProtocolDegraded { unparseable, framed: framer.framed }).unwrap_or_default(),
+            });
+        }
+    }
+
+    /// Route a message; false if it wasn't one
+    fn dispatch_line(&self, line: &str) -> bool {
+        if line.is_empty() {
+            return false;
+        }
+
+        match IncomingMessage::parse(line) {
+            Some(IncomingMessage::Response(response)) => {
+                let pending = self.pending.lock().unwrap();
+
+                let sender = match response.id_str() {
+                    Some(id) => pending.get(id),
+                    // An error without an ID can only be routed if there is
+                    // exactly one request it could belong to
+                    None if response.error.is_some() && pending.len() == 1 => pending.values().next(),
+                    None => None,
+                };
+
+                match sender {
+                    Some(sender) => {
+                        sender.send(Routed::Response(response)).ok();
+                    }
+                    None => log::warn!("Ignoring response for unrecognized request ID: {:?}", response.id),
+                }
+            }
+            Some(IncomingMessage::Notification(event)) if event.event == "chunk" => {
+                match serde_json::from_value::<ChunkParams>(event.data) {
+                    Ok(chunk) => match self.pending.lock().unwrap().get(&chunk.request_id) {
+                        Some(sender) => {
+                            sender.send(Routed::Chunk { seq: chunk.seq, items: chunk.items }).ok();
+                        }
+                        None => log::warn!("Ignoring chunk for unrecognized request ID: {}", chunk.request_id),
+                    },
+                    Err(e) => log::warn!("Malformed chunk notification: {}", e),
+                }
+            }
+            Some(IncomingMessage::Notification(event)) | Some(IncomingMessage::Event(event))
+                if event.event == HEARTBEAT_EVENT =>
+            {
+                for sender in self.pending.lock().unwrap().values() {
+                    sender.send(Routed::Heartbeat).ok();
+                }
+            }
+            Some(IncomingMessage::Notification(event)) | Some(IncomingMessage::Event(event)) => {
+                log::info!("Progress event: {}", event.event);
+                if let Some(ref sink) = self.event_sink {
+                    sink(event);
+                }
+            }
+            None => {
+                log::debug!("Unrecognized output: {}", line);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Start collecting messages for a request before it is sent
+    fn register(&self, id: &str) -> Result<mpsc::Receiver<Routed>, RpcError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(stdout_closed_error());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock().unwrap().insert(id.to_string(), sender);
+        Ok(receiver)
+    }
+
+    fn unregister(&self, id: &str) {
+        self.pending.lock().unwrap().remove(id);
+    }
+
+    /// Wake the call waiting on `id` with a cancelled error
+    fn cancel(&self, id: &str) -> bool {
+        match self.pending.lock().unwrap().get(id) {
+            Some(sender) => sender.send(Routed::Cancelled).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// Read one line into `buf`, without its newline, keeping at most `limit`
+/// bytes; returns the bytes consumed and whether the line was cut short
+fn read_line_capped(reader: &mut dyn BufRead, buf: &mut Vec<u8>, limit: usize) -> std::io::Result<(usize, bool)> {
+    buf.clear();
+    let mut consumed = 0;
+    let mut truncated = false;
+
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            break;
+        }
+
+        let (chunk, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(pos) => (&available[..pos], true),
+            None => (available, false),
+        };
+        let room = limit.saturating_sub(buf.len());
+        truncated |= chunk.len() > room;
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let used = chunk.len() + usize::from(done);
+        reader.consume(used);
+        consumed += used;
+        if done {
+            break;
+        }
+    }
+
+    Ok((consumed, truncated))
+}
+
+fn stdout_closed_error() -> RpcError {
+    RpcError {
+        code: -32603,
+        message: "Sidecar closed its output before responding".to_string(),
+        data: None,
+    }
+}
+
 /// RPC client for communicating with Python sidecar via stdin/stdout
 pub struct RpcClient {
     stdin: Arc<Mutex<Box<dyn Write + Send>>>,
-    stdout: Arc<Mutex<Box<dyn BufRead + Send>>>,
+    dispatcher: Arc<RpcDispatcher>,
     timeout: Duration,
+    heartbeat_gap: Duration,
+    metrics: Option<Arc<RpcMetrics>>,
+    /// Set once a write finds the sidecar's stdin closed
+    pipe_closed: Arc<AtomicBool>,
+    /// Moves large params and results through files
+    blobs: Option<BlobStore>,
 }
 
+/// Silence allowed between heartbeats unless the settings say otherwise
+pub const DEFAULT_HEARTBEAT_GAP: Duration = Duration::from_secs(60);
+
 impl RpcClient {
-    /// Create a new RPC client with stdin/stdout handles
+    /// Create a new RPC client writing to stdin and reading via the dispatcher
     pub fn new(
         stdin: Arc<Mutex<Box<dyn Write + Send>>>,
-        stdout: Arc<Mutex<Box<dyn BufRead + Send>>>,
+        dispatcher: Arc<RpcDispatcher>,
     ) -> Self {
         Self {
             stdin,
-            stdout,
+            dispatcher,
             timeout: Duration::from_secs(120), // Default 2 minute timeout
+            heartbeat_gap: DEFAULT_HEARTBEAT_GAP,
+            metrics: None,
+            pipe_closed: Arc::new(AtomicBool::new(false)),
+            blobs: None,
         }
     }
 
@@ -93,12 +567,70 @@ impl RpcClient {
         self
     }
 
+    /// Once a call has had a heartbeat, fail it only after `gap` without
+    /// another, however long it has run
+    pub fn with_heartbeat_gap(mut self, gap: Duration) -> Self {
+        self.heartbeat_gap = gap;
+        self
+    }
+
+    /// Record the latency of every call in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<RpcMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Raise `flag` when a write finds the pipe closed, so the owner of the
+    /// process knows to restart it
+    pub fn with_pipe_closed_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.pipe_closed = flag;
+        self
+    }
+
+    /// Pass values larger than the store's threshold through files, in
+    /// both directions
+    pub fn with_blob_store(mut self, store: BlobStore) -> Self {
+        self.blobs = Some(store);
+        self
+    }
+
     /// Send a JSON-RPC request and wait for response
     ///
-    /// This is a synchronous call that blocks until response is received
-    /// or timeout occurs.
+    /// This is a synchronous call that blocks until response is received.
+    /// Chunked replies are reassembled before returning; missing chunks fail
+    /// the call once the timeout elapses.
     pub fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
-        let request = RpcRequest::new(method.to_string(), params);
+        self.call_with_timeout(method, params, None)
+    }
+
+    /// Send a JSON-RPC request, failing if no response arrives within `limit`
+    pub fn call_with_timeout(&self, method: &str, params: Value, limit: Option<Duration>) -> Result<Value, RpcError> {
+        self.send_and_wait(RpcRequest::new(method.to_string(), params), limit, Instant::now())
+    }
+
+    /// Stop waiting for request `id` and ask the sidecar to abandon it
+    ///
+    /// The waiting call fails with `RPC_CANCELLED_CODE`. Returns whether a
+    /// call was waiting on `id`.
+    pub fn cancel(&self, id: &str) -> Result<bool, RpcError> {
+        let waiting = self.dispatcher.cancel(id);
+        self.notify(RpcMethod::Cancel.as_str(), serde_json::json!({ "id": id }))?;
+        Ok(waiting)
+    }
+
+    /// `queued` is when the caller asked for the call, for the queue wait
+    /// in the metrics
+    fn send_and_wait(&self, request: RpcRequest, limit: Option<Duration>, queued: Instant) -> Result<Value, RpcError> {
+        // Dropped on every way out of here, deleting the request's blob files
+        let (request, mut blobs) = match &self.blobs {
+            Some(store) => {
+                let (params, blobs) = store.externalize(request.params)?;
+                (RpcRequest { params, ..request }, Some(blobs))
+            }
+            None => (request, None),
+        };
+        let method = request.method.as_str();
+        let id = request.id.clone().unwrap_or_default();
 
         // Serialize request
         let request_json = serde_json::to_string(&request)
@@ -108,103 +640,165 @@ impl RpcClient {
                 data: None,
             })?;
 
-        // Send request to Python via stdin
-        {
-            let mut stdin = self.stdin.lock()
-                .map_err(|e| RpcError {
-                    code: -32603,
-                    message: format!("Failed to lock stdin: {}", e),
-                    data: None,
-                })?;
-
-            writeln!(stdin, "{}", request_json)
-                .map_err(|e| RpcError {
-                    code: -32603,
-                    message: format!("Failed to write to stdin: {}", e),
-                    data: None,
-                })?;
+        let receiver = self.dispatcher.register(&id)?;
 
-            stdin.flush()
-                .map_err(|e| RpcError {
-                    code: -32603,
-                    message: format!("Failed to flush stdin: {}", e),
-                    data: None,
-                })?;
-        }
+        // Send request to Python via stdin
+        let sent = self.write_line(&request_json);
+        let written = Instant::now();
 
-        // Read response from Python via stdout
-        let response = self.read_response(&request.id)?;
+        // Wait for the response routed from stdout
+        let result = sent.and_then(|_| self.wait_for_result(method, &id, &receiver, limit));
+        self.dispatcher.unregister(&id);
+        let answered = Instant::now();
+        let result = match blobs.as_mut() {
+            Some(blobs) => result.and_then(|value| blobs.inline(value)),
+            None => result,
+        };
 
-        // Check for errors
-        if let Some(error) = response.error {
-            return Err(error);
+        if let Some(metrics) = &self.metrics {
+            let span = CallSpan {
+                queue_wait: written.saturating_duration_since(queued),
+                round_trip: answered - written,
+                decode: answered.elapsed(),
+            };
+            metrics.record_span(method, span, result.is_ok());
         }
 
-        // Return result
-        response.result.ok_or_else(|| RpcError {
-            code: -32603,
-            message: "No result in RPC response".to_string(),
-            data: None,
-        })
+        result
     }
 
-    /// Read a response from stdout, matching the request ID
-    fn read_response(&self, expected_id: &str) -> Result<RpcResponse, RpcError> {
-        let mut stdout = self.stdout.lock()
+    fn write_line(&self, json: &str) -> Result<(), RpcError> {
+        let mut stdin = self.stdin.lock()
             .map_err(|e| RpcError {
                 code: -32603,
-                message: format!("Failed to lock stdout: {}", e),
+                message: format!("Failed to lock stdin: {}", e),
                 data: None,
             })?;
 
-        let mut line = String::new();
+        writeln!(stdin, "{}", json)
+            .map_err(|e| self.write_error("write to", e))?;
 
-        // Read lines until we find our response or timeout
-        // Note: In a real implementation, we'd want non-blocking I/O
-        // or a timeout mechanism here
-        loop {
-            line.clear();
+        stdin.flush()
+            .map_err(|e| self.write_error("flush", e))
+    }
 
-            stdout.read_line(&mut line)
-                .map_err(|e| RpcError {
-                    code: -32603,
-                    message: format!("Failed to read from stdout: {}", e),
+    /// A process that died leaves a broken pipe behind while its handle may
+    /// still report it running; waiting for a reply would only time out
+    fn write_error(&self, action: &str, error: std::io::Error) -> RpcError {
+        match error.kind() {
+            std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::WouldBlock => {
+                self.pipe_closed.store(true, Ordering::SeqCst);
+                RpcError {
+                    code: RPC_PIPE_CLOSED_CODE,
+                    message: format!("Sidecar pipe closed: {}", error),
                     data: None,
-                })?;
-
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
+                }
             }
+            _ => RpcError {
+                code: -32603,
+                message: format!("Failed to {} stdin: {}", action, error),
+                data: None,
+            },
+        }
+    }
 
-            // Try to parse as JSON-RPC response
-            if let Ok(response) = serde_json::from_str::<RpcResponse>(line) {
-                if response.id == expected_id {
-                    return Ok(response);
-                } else {
-                    log::warn!("Received response for different request ID: {}", response.id);
+    /// Wait for the response, reassembling `{"chunked": true, "total": N}` replies
+    ///
+    /// `limit` bounds the wait for the response itself; once a chunked header
+    /// arrives, the client timeout bounds the wait for the remaining chunks.
+    /// A heartbeat replaces either deadline with the heartbeat gap from now,
+    /// so a sidecar that keeps beating is never timed out, and one that
+    /// stops is caught without waiting out the whole limit.
+    fn wait_for_result(
+        &self,
+        method: &str,
+        id: &str,
+        receiver: &mpsc::Receiver<Routed>,
+        limit: Option<Duration>,
+    ) -> Result<Value, RpcError> {
+        let mut chunks: BTreeMap<u64, Vec<Value>> = BTreeMap::new();
+        let mut total: Option<u64> = None;
+        let mut deadline: Option<Instant> = limit.map(|limit| Instant::now() + limit);
+        let mut beating = false;
+
+        loop {
+            if let Some(total) = total {
+                if (0..total).all(|seq| chunks.contains_key(&seq)) {
+                    return Ok(Value::Array(chunks.into_values().flatten().collect()));
                 }
             }
 
-            // Try to parse as progress event
-            if let Ok(event) = serde_json::from_str::<ProgressEvent>(line) {
-                log::info!("Progress event: {}", event.event);
-                // TODO: Could emit to a callback channel here
-                continue;
-            }
+            let message = match deadline {
+                None => receiver.recv().map_err(|_| stdout_closed_error())?,
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    match receiver.recv_timeout(remaining) {
+                        Ok(message) => message,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            return Err(match total {
+                                Some(total) => missing_chunks_error(id, total, &chunks),
+                                None if beating => silent_error(method, self.heartbeat_gap),
+                                None => timeout_error(method, limit.unwrap_or_default()),
+                            });
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return Err(stdout_closed_error()),
+                    }
+                }
+            };
+
+            match message {
+                Routed::Response(response) => {
+                    if let Some(error) = response.error {
+                        return Err(error);
+                    }
+
+                    let result = response.result.ok_or_else(|| RpcError {
+                        code: -32603,
+                        message: "No result in RPC response".to_string(),
+                        data: None,
+                    })?;
 
-            log::debug!("Unrecognized output: {}", line);
+                    let chunked_total = result.get("chunked")
+                        .and_then(|c| c.as_bool())
+                        .filter(|chunked| *chunked)
+                        .and_then(|_| result.get("total"))
+                        .and_then(|t| t.as_u64());
+
+                    match chunked_total {
+                        Some(count) => {
+                            total = Some(count);
+                            deadline = Some(Instant::now() + self.timeout);
+                        }
+                        None => return Ok(result),
+                    }
+                }
+                Routed::Heartbeat => {
+                    beating = true;
+                    if deadline.is_some() {
+                        deadline = Some(Instant::now() + self.heartbeat_gap);
+                    }
+                }
+                Routed::Cancelled => return Err(cancelled_error(method)),
+                Routed::Chunk { seq, items } => {
+                    if total.is_some_and(|total| seq >= total) {
+                        return Err(RpcError {
+                            code: -32603,
+                            message: format!("Chunk {} is out of range for request {}", seq, id),
+                            data: None,
+                        });
+                    }
+
+                    if chunks.insert(seq, items).is_some() {
+                        log::warn!("Duplicate chunk {} for request {}", seq, id);
+                    }
+                }
+            }
         }
     }
 
     /// Send a notification (no response expected)
     pub fn notify(&self, method: &str, params: Value) -> Result<(), RpcError> {
-        let request = RpcRequest {
-            jsonrpc: "2.0".to_string(),
-            method: method.to_string(),
-            params,
-            id: "notify".to_string(), // Notifications don't need unique IDs
-        };
+        let request = RpcRequest::notification(method.to_string(), params);
 
         let request_json = serde_json::to_string(&request)
             .map_err(|e| RpcError {
@@ -213,28 +807,73 @@ impl RpcClient {
                 data: None,
             })?;
 
-        let mut stdin = self.stdin.lock()
-            .map_err(|e| RpcError {
-                code: -32603,
-                message: format!("Failed to lock stdin: {}", e),
-                data: None,
-            })?;
+        self.write_line(&request_json)
+    }
+}
 
-        writeln!(stdin, "{}", request_json)
-            .map_err(|e| RpcError {
-                code: -32603,
-                message: format!("Failed to write notification: {}", e),
-                data: None,
-            })?;
+/// Error code for a call that got no response within its timeout
+pub const RPC_TIMEOUT_CODE: i32 = -32001;
 
-        stdin.flush()
-            .map_err(|e| RpcError {
-                code: -32603,
-                message: format!("Failed to flush notification: {}", e),
-                data: None,
-            })?;
+/// Error code for a method the sidecar doesn't implement, as older
+/// `rpc_server.py` versions answer for newer methods
+pub const RPC_METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+/// Error code for a call stopped by `RpcClient::cancel`
+pub const RPC_CANCELLED_CODE: i32 = -32800;
+
+/// Error code for a call whose request couldn't be written because the
+/// sidecar's stdin is closed; the sidecar is restarted on next use
+pub const RPC_PIPE_CLOSED_CODE: i32 = -32010;
+
+fn cancelled_error(method: &str) -> RpcError {
+    RpcError {
+        code: RPC_CANCELLED_CODE,
+        message: format!("RPC method '{}' was cancelled", method),
+        data: None,
+    }
+}
+
+fn timeout_error(method: &str, limit: Duration) -> RpcError {
+    RpcError {
+        code: RPC_TIMEOUT_CODE,
+        message: format!("RPC method '{}' timed out after {}", method, duration_text(limit)),
+        data: Some(serde_json::json!({ "method": method, "timeout_ms": limit.as_millis() as u64 })),
+    }
+}
 
-        Ok(())
+/// Timeout for a call whose heartbeats stopped
+fn silent_error(method: &str, gap: Duration) -> RpcError {
+    RpcError {
+        code: RPC_TIMEOUT_CODE,
+        message: format!("RPC method '{}' stopped sending heartbeats for {}", method, duration_text(gap)),
+        data: Some(serde_json::json!({ "method": method, "heartbeat_gap_ms": gap.as_millis() as u64 })),
+    }
+}
+
+fn duration_text(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+fn missing_chunks_error(id: &str, total: u64, chunks: &BTreeMap<u64, Vec<Value>>) -> RpcError {
+    let missing: Vec<String> = (0..total)
+        .filter(|seq| !chunks.contains_key(seq))
+        .map(|seq| seq.to_string())
+        .collect();
+
+    RpcError {
+        code: -32603,
+        message: format!(
+            "Timed out waiting for chunks of request {}: received {} of {}, missing [{}]",
+            id,
+            chunks.len(),
+            total,
+            missing.join(", ")
+        ),
+        data: None,
     }
 }
 
@@ -243,44 +882,657 @@ impl crate::sidecar::process::PythonSidecar {
     /// Get RPC client for this sidecar
     pub fn rpc_client(&self) -> Option<RpcClient> {
         let stdin = self.stdin()?;
-        let stdout = self.stdout()?;
-        Some(RpcClient::new(stdin, stdout))
+        let dispatcher = self.dispatcher()?;
+        Some(RpcClient::new(stdin, dispatcher).with_pipe_closed_flag(self.pipe_closed_flag()))
     }
 }
 
 /// RPC client wrapper for async operations
 pub struct AsyncRpcClient {
     client: Arc<Mutex<RpcClient>>,
+    /// The client's metrics, for reporting calls that run long
+    metrics: Option<Arc<RpcMetrics>>,
+    /// Workers the blocking calls run on; tokio's blocking pool when None
+    pool: Option<Arc<RpcPool>>,
 }
 
 impl AsyncRpcClient {
     /// Create async wrapper around sync RPC client
     pub fn new(client: RpcClient) -> Self {
         Self {
+            metrics: client.metrics.clone(),
             client: Arc::new(Mutex::new(client)),
+            pool: None,
         }
     }
 
+    /// Run calls on `pool`'s workers
+    pub fn with_pool(mut self, pool: Arc<RpcPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
     /// Send RPC request asynchronously
-    pub async fn call(&self, method: String, params: Value) -> Result<Value, String> {
+    ///
+    /// Fails with a timeout error naming the method if no response arrives
+    /// within `timeout`; the command layer takes it from the settings policy.
+    pub async fn call(&self, method: String, params: Value, timeout: Duration) -> Result<Value, String> {
+        self.call_detailed(method, params, timeout)
+            .await
+            .map_err(|e| format!("RPC error: {}", e))
+    }
+
+    /// Send RPC request asynchronously, keeping the structured error
+    ///
+    /// Use this when the caller needs the error code or `data` payload.
+    pub async fn call_detailed(&self, method: String, params: Value, timeout: Duration) -> Result<Value, RpcError> {
+        self.spawn_call(method, params, timeout, None).await
+    }
+
+    /// Send RPC request asynchronously under `id`, so another client can
+    /// `cancel` it
+    pub async fn call_with_id(&self, method: String, params: Value, timeout: Duration, id: String) -> Result<Value, RpcError> {
+        self.spawn_call(method, params, timeout, Some(id)).await
+    }
+
+    /// A call still running at twice its method's p95 is reported to the
+    /// metrics' slow sink and then awaited as before
+    async fn spawn_call(&self, method: String, params: Value, timeout: Duration, id: Option<String>) -> Result<Value, RpcError> {
         let client = self.client.clone();
-        let method = method.clone();
+        let queued = Instant::now();
+        let slow_after = self.metrics.as_ref().and_then(|metrics| metrics.slow_after(&method));
+        let request = match id {
+            Some(id) => RpcRequest::with_id(method.clone(), params, id),
+            None => RpcRequest::new(method.clone(), params),
+        };
 
-        tokio::task::spawn_blocking(move || {
-            client.lock()
-                .map_err(|e| format!("Failed to lock client: {}", e))?
-                .call(&method, params)
-                .map_err(|e| format!("RPC error: {}", e))
-        })
-        .await
-        .map_err(|e| format!("Task join error: {}", e))?
+        let call = move || {
+            let client = client.lock()
+                .map_err(|e| RpcError {
+                    code: -32603,
+                    message: format!("Failed to lock client: {}", e),
+                    data: None,
+                })?;
+
+            client.send_and_wait(request, Some(timeout), queued)
+        };
+
+        let pool = self.pool.clone();
+        let task = async {
+            match pool {
+                Some(pool) => pool.run(&method, call).await,
+                None => tokio::task::spawn_blocking(call).await.map_err(|e| RpcError {
+                    code: -32603,
+                    message: format!("Task join error: {}", e),
+                    data: None,
+                })?,
+            }
+        };
+        tokio::pin!(task);
+
+        match (slow_after, &self.metrics) {
+            (Some((after, p95)), Some(metrics)) => tokio::select! {
+                result = &mut task => result,
+                _ = tokio::time::sleep(after) => {
+                    metrics.report_slow(&method, queued.elapsed(), p95);
+                    task.await
+                }
+            },
+            _ => task.await,
+        }
     }
 }
 
 /// AsyncRpcClient wrapper for PythonSidecar
 impl crate::sidecar::process::PythonSidecar {
-    /// Get async RPC client for this sidecar
-    pub fn async_rpc_client(&self) -> Option<AsyncRpcClient> {
-        self.rpc_client().map(AsyncRpcClient::new)
+    /// Get async RPC client for this sidecar, recording latency in `metrics`
+    /// and running calls on `pool`; large values go through `blobs` when set
+    pub fn async_rpc_client(&self, metrics: Arc<RpcMetrics>, pool: Arc<RpcPool>, heartbeat_gap: Duration, blobs: Option<BlobStore>) -> Option<AsyncRpcClient> {
+        self.rpc_client().map(|client| {
+            let client = client.with_metrics(metrics).with_heartbeat_gap(heartbeat_gap);
+            let client = match blobs {
+                Some(store) => client.with_blob_store(store),
+                None => client,
+            };
+            AsyncRpcClient::new(client).with_pool(pool)
+        })
+    }
+}
+
+/// In-memory sidecar for exercising the RPC client in tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    type Script = Box<dyn FnMut(&RpcRequest) -> Vec<String> + Send>;
+
+    /// Answers each request written to stdin with scripted stdout lines
+    pub(crate) struct MockSidecar {
+        stdin: Arc<Mutex<Box<dyn Write + Send>>>,
+        dispatcher: Arc<RpcDispatcher>,
+        requests: Arc<Mutex<Vec<RpcRequest>>>,
+        stdout: mpsc::Sender<Vec<u8>>,
+    }
+
+    impl MockSidecar {
+        /// Reply to every request with a single response built by `handler`
+        pub(crate) fn new(
+            handler: impl Fn(&RpcRequest) -> Result<Value, RpcError> + Send + 'static,
+        ) -> (Self, AsyncRpcClient) {
+            let sidecar = Self::scripted(move |request| {
+                let (result, error) = match handler(request) {
+                    Ok(value) => (Some(value), None),
+                    Err(error) => (None, Some(error)),
+                };
+                let response = RpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    result,
+                    error,
+                    id: request.id.clone().map(Value::String),
+                };
+
+                vec![serde_json::to_string(&response).unwrap()]
+            });
+
+            let client = AsyncRpcClient::new(sidecar.rpc_client());
+            (sidecar, client)
+        }
+
+        /// Write whatever lines `script` returns to stdout after each request
+        pub(crate) fn scripted(script: impl FnMut(&RpcRequest) -> Vec<String> + Send + 'static) -> Self {
+            let (sender, receiver) = mpsc::channel();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let stdin = MockStdin {
+                pending: Vec::new(),
+                script: Box::new(script),
+                requests: requests.clone(),
+                stdout: sender.clone(),
+            };
+            let stdout = BufReader::new(MockStdout {
+                receiver,
+                buffer: Vec::new(),
+            });
+
+            Self {
+                stdin: Arc::new(Mutex::new(Box::new(stdin))),
+                dispatcher: RpcDispatcher::spawn(Box::new(stdout), None),
+                requests,
+                stdout: sender,
+            }
+        }
+
+        /// Something to write stdout lines outside of a request, e.g. from
+        /// another thread
+        pub(crate) fn stdout(&self) -> mpsc::Sender<Vec<u8>> {
+            self.stdout.clone()
+        }
+
+        /// A new client sharing this sidecar's pipes, like each command gets
+        pub(crate) fn rpc_client(&self) -> RpcClient {
+            RpcClient::new(self.stdin.clone(), self.dispatcher.clone())
+        }
+
+        /// Requests received so far, in order
+        pub(crate) fn requests(&self) -> Vec<RpcRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        /// Make stdin fail like the pipe of a process that died, while
+        /// stdout stays open
+        pub(crate) fn close_stdin(&self) {
+            *self.stdin.lock().unwrap() = Box::new(ClosedStdin);
+        }
+    }
+
+    struct ClosedStdin;
+
+    impl Write for ClosedStdin {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    struct MockStdin {
+        pending: Vec<u8>,
+        script: Script,
+        requests: Arc<Mutex<Vec<RpcRequest>>>,
+        stdout: mpsc::Sender<Vec<u8>>,
+    }
+
+    impl Write for MockStdin {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+
+            while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=pos).collect();
+                let Ok(request) = serde_json::from_slice::<RpcRequest>(&line) else {
+                    continue;
+                };
+
+                self.requests.lock().unwrap().push(request.clone());
+
+                for output in (self.script)(&request) {
+                    self.stdout.send(format!("{}\n", output).into_bytes()).ok();
+                }
+            }
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Blocks like a pipe until output is written or the mock is dropped
+    struct MockStdout {
+        receiver: mpsc::Receiver<Vec<u8>>,
+        buffer: Vec<u8>,
+    }
+
+    impl Read for MockStdout {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.buffer.is_empty() {
+                match self.receiver.recv() {
+                    Ok(bytes) => self.buffer = bytes,
+                    Err(_) => return Ok(0),
+                }
+            }
+
+            let count = buf.len().min(self.buffer.len());
+            buf[..count].copy_from_slice(&self.buffer[..count]);
+            self.buffer.drain(..count);
+
+            Ok(count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reserved_error_codes_are_unique() {
+        let codes = [
+            RPC_TIMEOUT_CODE,
+            RPC_METHOD_NOT_FOUND_CODE,
+            RPC_CANCELLED_CODE,
+            RPC_PIPE_CLOSED_CODE,
+            crate::sidecar::blob::RPC_BLOB_CODE,
+            crate::sidecar::pool::RPC_BACKPRESSURE_CODE,
+        ];
+        let unique: std::collections::HashSet<i32> = codes.iter().copied().collect();
+        assert_eq!(unique.len(), codes.len(), "two reserved RPC error codes share a value: {:?}", codes);
+    }
+
+    #[test]
+    fn test_request_round_trip_keeps_id() {
+        let request = RpcRequest::with_id("load_bid".to_string(), json!({"path": "a.xlsx"}), "42".to_string());
+        let wire = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(wire["id"], "42");
+
+        let parsed: RpcRequest = serde_json::from_value(wire).unwrap();
+        assert_eq!(parsed.id.as_deref(), Some("42"));
+        assert_eq!(parsed.method, "load_bid");
+    }
+
+    #[test]
+    fn test_notification_omits_id() {
+        let notification = RpcRequest::notification("cancel".to_string(), json!({}));
+        let wire = serde_json::to_value(&notification).unwrap();
+
+        assert!(wire.get("id").is_none());
+
+        let parsed: RpcRequest = serde_json::from_value(wire).unwrap();
+        assert!(parsed.id.is_none());
+    }
+
+    #[test]
+    fn test_response_shapes_round_trip() {
+        let success = r#"{"jsonrpc":"2.0","result":{"ok":true},"id":"abc"}"#;
+        let response: RpcResponse = serde_json::from_str(success).unwrap();
+        assert_eq!(response.id_str(), Some("abc"));
+        assert_eq!(serde_json::to_value(&response).unwrap(), serde_json::from_str::<Value>(success).unwrap());
+
+        let null_id = r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error","data":null},"id":null}"#;
+        let response: RpcResponse = serde_json::from_str(null_id).unwrap();
+        assert!(response.id.is_none());
+        assert_eq!(response.error.as_ref().unwrap().code, -32700);
+
+        let numeric_id = r#"{"jsonrpc":"2.0","result":1,"id":7}"#;
+        let response: RpcResponse = serde_json::from_str(numeric_id).unwrap();
+        assert_eq!(response.id, Some(json!(7)));
+        assert_eq!(response.id_str(), None);
+    }
+
+    #[test]
+    fn test_incoming_messages_are_classified() {
+        let response = IncomingMessage::parse(r#"{"jsonrpc":"2.0","result":null,"id":"1"}"#);
+        assert!(matches!(response, Some(IncomingMessage::Response(_))));
+
+        let notification = IncomingMessage::parse(r#"{"jsonrpc":"2.0","method":"progress","params":{"percent":50}}"#);
+        match notification {
+            Some(IncomingMessage::Notification(event)) => {
+                assert_eq!(event.event, "progress");
+                assert_eq!(event.data["percent"], 50);
+            }
+            other => panic!("expected notification, got {:?}", other),
+        }
+
+        let event = IncomingMessage::parse(r#"{"event":"model_loading","data":{"percent":10}}"#);
+        assert!(matches!(event, Some(IncomingMessage::Event(_))));
+
+        assert!(IncomingMessage::parse(r#"{"jsonrpc":"2.0","method":"ask","id":"9"}"#).is_none());
+        assert!(IncomingMessage::parse("Loading model weights...").is_none());
+    }
+
+    fn chunk(request_id: &str, seq: u64, items: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "chunk",
+            "params": { "request_id": request_id, "seq": seq, "items": items }
+        }).to_string()
+    }
+
+    fn chunked_header(request_id: &str, total: u64) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "result": { "chunked": true, "total": total },
+            "id": request_id
+        }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_chunks_for_two_requests() {
+        // Hold the first request's reply until the second arrives, then emit
+        // both replies with their chunks interleaved and out of order
+        let mut first_id = None;
+        let sidecar = testing::MockSidecar::scripted(move |request| {
+            let id = request.id.clone().unwrap();
+            let Some(first) = first_id.clone() else {
+                first_id = Some(id);
+                return vec![];
+            };
+
+            vec![
+                chunked_header(&first, 2),
+                chunk(&id, 1, json!(["b2"])),
+                chunk(&first, 1, json!(["a2", "a3"])),
+                "{\"event\": \"progress\", \"data\": {}}".to_string(),
+                chunked_header(&id, 2),
+                chunk(&first, 0, json!(["a1"])),
+                chunk(&id, 0, json!(["b1"])),
+            ]
+        });
+
+        let first = AsyncRpcClient::new(sidecar.rpc_client());
+        let second = AsyncRpcClient::new(sidecar.rpc_client());
+
+        let first_call = tokio::spawn(async move {
+            first.call("all_shots".to_string(), json!({}), Duration::from_secs(5)).await
+        });
+        while sidecar.requests().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let second_result = second.call("all_shots".to_string(), json!({}), Duration::from_secs(5)).await.unwrap();
+        let first_result = first_call.await.unwrap().unwrap();
+
+        assert_eq!(first_result, json!(["a1", "a2", "a3"]));
+        assert_eq!(second_result, json!(["b1", "b2"]));
+    }
+
+    #[test]
+    fn test_missing_chunks_time_out() {
+        let sidecar = testing::MockSidecar::scripted(|request| {
+            let id = request.id.clone().unwrap();
+            vec![chunked_header(&id, 3), chunk(&id, 2, json!([3])), chunk(&id, 0, json!([1]))]
+        });
+
+        let client = sidecar.rpc_client().with_timeout(Duration::from_millis(100));
+        let error = client.call("all_shots", json!({})).unwrap_err();
+
+        assert!(error.message.contains("received 2 of 3"), "{}", error.message);
+        assert!(error.message.contains("missing [1]"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_framer_joins_multi_line_json() {
+        let mut framer = MessageFramer::default();
+        let compact = json!({ "jsonrpc": "2.0", "result": { "text": "a } \" [" }, "id": "1" }).to_string();
+        assert_eq!(framer.push(&format!("{}\n", compact)).as_deref(), Some(compact.as_str()));
+
+        let pretty = serde_json::to_string_pretty(&json!({
+            "jsonrpc": "2.0",
+            "result": { "shots": [{ "id": "1", "notes": "{ unbalanced [" }] },
+            "id": "2"
+        })).unwrap();
+        let lines: Vec<_> = pretty.lines().collect();
+        for line in &lines[..lines.len() - 1] {
+            assert_eq!(framer.push(line), None);
+        }
+        let joined = framer.push(lines[lines.len() - 1]).unwrap();
+        let parsed: Value = serde_json::from_str(&joined).unwrap();
+        assert_eq!(parsed["result"]["shots"][0]["notes"], "{ unbalanced [");
+
+        // Output that isn't JSON passes straight through
+        assert_eq!(framer.push("[INFO] loading {model").as_deref(), Some("[INFO] loading {model"));
+        assert_eq!(framer.push(&compact).as_deref(), Some(compact.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_framed_stream_ignores_unmarked_output() {
+        let sidecar = testing::MockSidecar::scripted(|request| {
+            let id = request.id.clone().unwrap();
+            let marked = |value: Value| format!("{}{}", RECORD_SEPARATOR, value);
+            vec![
+                "Anonymized telemetry enabled. See https://docs.trychroma.com".to_string(),
+                marked(json!({ "event": "progress", "data": {} })),
+                "{\"telemetry\": {\"session\": ".to_string(),
+                json!({ "jsonrpc": "2.0", "result": "spoofed", "id": id }).to_string(),
+                "llama.cpp build: 3496 (b0bd1d8)".to_string(),
+                marked(json!({ "jsonrpc": "2.0", "result": "real", "id": id })),
+            ]
+        });
+        let client = AsyncRpcClient::new(sidecar.rpc_client());
+
+        let result = client.call("health".to_string(), json!({}), Duration::from_secs(5)).await;
+        assert_eq!(result.unwrap(), json!("real"));
+    }
+
+    #[test]
+    fn test_legacy_framer_recovers_from_unclosed_noise() {
+        let mut framer = MessageFramer::default();
+        let response = json!({ "jsonrpc": "2.0", "result": true, "id": "1" }).to_string();
+
+        assert_eq!(framer.push("{\"banner\": \"chromadb\","), None);
+        assert_eq!(framer.push(&response).as_deref(), Some(response.as_str()));
+        assert_eq!(framer.take_dropped(), 1);
+        assert!(!framer.framed);
+
+        // A marked line switches to strict framing for good
+        let marked = format!("{}{}\n", RECORD_SEPARATOR, response);
+        assert_eq!(framer.push(&marked).as_deref(), Some(response.as_str()));
+        assert_eq!(framer.push(&response), None);
+    }
+
+    #[test]
+    fn test_unparseable_run_emits_degraded_event() {
+        let garbage = format!("{}{{\"truncated\": \n", RECORD_SEPARATOR);
+        let valid = format!("{}{}\n", RECORD_SEPARATOR, json!({ "event": "progress", "data": {} }));
+        let stdout = garbage.repeat(RESYNC_AFTER) + &valid + &garbage.repeat(RESYNC_AFTER);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = events.clone();
+        let sink: EventSink = Arc::new(move |event: ProgressEvent| sink_events.lock().unwrap().push(event));
+        let dispatcher = RpcDispatcher::spawn(Box::new(std::io::Cursor::new(stdout.into_bytes())), Some(sink));
+
+        while !dispatcher.closed.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let events = events.lock().unwrap();
+        let names: Vec<_> = events.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(names, vec![PROTOCOL_DEGRADED_EVENT, "progress", PROTOCOL_DEGRADED_EVENT]);
+        assert_eq!(events[0].data, json!({ "unparseable": RESYNC_AFTER, "framed": true }));
+    }
+
+    #[test]
+    fn test_long_lines_are_cut_at_the_limit() {
+        let text = format!("short\n{}\nafter", "x".repeat(50));
+        let mut reader = std::io::Cursor::new(text.into_bytes());
+        let mut line = Vec::new();
+
+        assert_eq!(read_line_capped(&mut reader, &mut line, 10).unwrap(), (6, false));
+        assert_eq!(line, b"short");
+        assert_eq!(read_line_capped(&mut reader, &mut line, 10).unwrap(), (51, true));
+        assert_eq!(line, "x".repeat(10).as_bytes());
+        assert_eq!(read_line_capped(&mut reader, &mut line, 10).unwrap(), (5, false));
+        assert_eq!(line, b"after");
+        assert_eq!(read_line_capped(&mut reader, &mut line, 10).unwrap().0, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pretty_printed_response_is_routed() {
+        let sidecar = testing::MockSidecar::scripted(|request| {
+            let id = request.id.clone().unwrap();
+            vec![
+                "{\"event\": \"progress\", \"data\": {}}".to_string(),
+                serde_json::to_string_pretty(&json!({
+                    "jsonrpc": "2.0",
+                    "result": { "shots": ["a", "b"] },
+                    "id": id
+                })).unwrap(),
+            ]
+        });
+        let client = AsyncRpcClient::new(sidecar.rpc_client());
+
+        let result = client.call("all_shots".to_string(), json!({}), Duration::from_secs(5)).await;
+        assert_eq!(result.unwrap(), json!({ "shots": ["a", "b"] }));
+    }
+
+    #[tokio::test]
+    async fn test_call_returns_matching_response() {
+        let (_sidecar, client) = testing::MockSidecar::new(|_| Ok(json!("done")));
+
+        assert_eq!(client.call("ping".to_string(), json!({}), Duration::from_secs(5)).await.unwrap(), json!("done"));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_names_method_and_limit() {
+        let sidecar = testing::MockSidecar::scripted(|_| vec![]);
+        let client = AsyncRpcClient::new(sidecar.rpc_client());
+
+        let error = client.call_detailed("bid_query".to_string(), json!({}), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code, RPC_TIMEOUT_CODE);
+        assert_eq!(error.message, "RPC method 'bid_query' timed out after 50ms");
+    }
+
+    #[test]
+    fn test_write_to_closed_pipe_fails_fast() {
+        let sidecar = testing::MockSidecar::scripted(|_| vec![]);
+        let flag = Arc::new(AtomicBool::new(false));
+        let client = sidecar.rpc_client().with_pipe_closed_flag(flag.clone());
+        sidecar.close_stdin();
+
+        // Fails at once rather than waiting out the timeout for a reply
+        let started = Instant::now();
+        let error = client.call_with_timeout("chat_command", json!({}), Some(Duration::from_secs(30))).unwrap_err();
+        assert_eq!(error.code, RPC_PIPE_CLOSED_CODE);
+        assert!(error.message.starts_with("Sidecar pipe closed"), "{}", error.message);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_late_response_within_limit_succeeds() {
+        let sidecar = testing::MockSidecar::scripted(|request| {
+            let id = request.id.clone().unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            vec![json!({ "jsonrpc": "2.0", "result": "slow", "id": id }).to_string()]
+        });
+        let client = AsyncRpcClient::new(sidecar.rpc_client());
+
+        let result = client.call("process_script".to_string(), json!({}), Duration::from_secs(5)).await;
+        assert_eq!(result.unwrap(), json!("slow"));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_keep_long_call_alive() {
+        let sidecar = testing::MockSidecar::scripted(|_| vec![]);
+        let client = AsyncRpcClient::new(sidecar.rpc_client().with_heartbeat_gap(Duration::from_millis(150)));
+        let limit = Duration::from_millis(100);
+
+        // Beats for three times the limit, then answers
+        let stdout = sidecar.stdout();
+        std::thread::spawn(move || {
+            for _ in 0..10 {
+                std::thread::sleep(Duration::from_millis(30));
+                stdout.send(format!("{}\n", json!({ "event": HEARTBEAT_EVENT })).into_bytes()).ok();
+            }
+            let response = json!({ "jsonrpc": "2.0", "result": "thought hard", "id": "long" });
+            stdout.send(format!("{}\n", response).into_bytes()).ok();
+        });
+
+        let started = Instant::now();
+        let result = client.call_with_id("chat_command".to_string(), json!({}), limit, "long".to_string()).await;
+        assert_eq!(result.unwrap(), json!("thought hard"));
+        assert!(started.elapsed() > limit * 2);
+
+        // Beats that stop fail the call after the gap, not the limit
+        let stdout = sidecar.stdout();
+        std::thread::spawn(move || {
+            for _ in 0..3 {
+                std::thread::sleep(Duration::from_millis(30));
+                stdout.send(format!("{}\n", json!({ "jsonrpc": "2.0", "method": HEARTBEAT_EVENT })).into_bytes()).ok();
+            }
+        });
+
+        let error = client.call_with_id("chat_command".to_string(), json!({}), limit, "hung".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, RPC_TIMEOUT_CODE);
+        assert_eq!(error.message, "RPC method 'chat_command' stopped sending heartbeats for 150ms");
+    }
+
+    #[tokio::test]
+    async fn test_long_call_is_reported_while_running() {
+        let (_sidecar, client) = testing::MockSidecar::new(|request| {
+            if request.params["slow"] == json!(true) {
+                std::thread::sleep(Duration::from_millis(150));
+            }
+            Ok(json!("ok"))
+        });
+        let metrics = Arc::new(RpcMetrics::default());
+        for _ in 0..20 {
+            metrics.record("bid_query", Duration::from_millis(20), true);
+        }
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        metrics.set_slow_sink(Arc::new(move |event| seen.lock().unwrap().push(event)));
+
+        let client = AsyncRpcClient { metrics: Some(metrics.clone()), ..client };
+        let timeout = Duration::from_secs(5);
+        client.call("bid_query".to_string(), json!({}), timeout).await.unwrap();
+        assert!(events.lock().unwrap().is_empty());
+
+        client.call("bid_query".to_string(), json!({ "slow": true }), timeout).await.unwrap();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "slow_operation");
+        assert_eq!(events[0].data["method"], "bid_query");
+        assert_eq!(events[0].data["p95_ms"], 20.0);
     }
 }
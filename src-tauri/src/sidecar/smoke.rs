@@ -0,0 +1,211 @@
+//! End-to-end check of the sidecar for the setup wizard
+//!
+//! Python, the packages and the model file can all look fine while the
+//! sidecar still fails to start or answer, so setup only finishes once a
+//! real sidecar has been launched and has handled a couple of calls.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use super::{LogRing, PythonSidecar, RpcClient, RpcError, RpcMethod, SidecarEnv};
+
+/// How long the sidecar gets to import its modules and answer
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Limit for each call once the sidecar is up
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stderr lines returned with a failure
+pub const STDERR_TAIL_LINES: usize = 20;
+
+/// Message sent to `chat_command`; answered without the model
+const SMOKE_MESSAGE: &str = "help";
+
+/// Stage of the smoke test, in the order they run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmokePhase {
+    /// Spawning the Python process
+    Launch,
+    /// Waiting for the first answer
+    Handshake,
+    Health,
+    Chat,
+}
+
+/// How long a phase took
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PhaseTiming {
+    pub phase: SmokePhase,
+    pub millis: u64,
+}
+
+/// Outcome of `run_smoke_test`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SmokeTestResult {
+    pub passed: bool,
+    /// Phases that completed, in order
+    pub timings: Vec<PhaseTiming>,
+    pub failed_phase: Option<SmokePhase>,
+    pub error: Option<String>,
+    /// Set when the sidecar exited on its own
+    pub exit_code: Option<i32>,
+    /// Last lines the sidecar wrote to stderr, oldest first
+    pub stderr_tail: Vec<String>,
+}
+
+/// Launch the sidecar with `python`, check it answers, then stop it
+///
+/// Blocks until the test finishes; run it off the async runtime.
+pub fn run_smoke_test(python: &str, script_path: &Path, env: SidecarEnv) -> SmokeTestResult {
+    let log = Arc::new(LogRing::default());
+    let started = Instant::now();
+
+    let mut sidecar = match PythonSidecar::start_with_python(python, script_path, None, env, log.clone()) {
+        Ok(sidecar) => sidecar,
+        Err(e) => {
+            return SmokeTestResult {
+                passed: false,
+                timings: Vec::new(),
+                failed_phase: Some(SmokePhase::Launch),
+                error: Some(e),
+                exit_code: None,
+                stderr_tail: Vec::new(),
+            };
+        }
+    };
+    let launch = PhaseTiming { phase: SmokePhase::Launch, millis: started.elapsed().as_millis() as u64 };
+
+    let outcome = match sidecar.rpc_client() {
+        Some(client) => run_phases(&client),
+        None => PhasesOutcome {
+            timings: Vec::new(),
+            failure: Some((SmokePhase::Handshake, "Sidecar pipes are not open".to_string())),
+        },
+    };
+
+    // Give a crashing process a moment to report its exit code
+    let exit_code = match outcome.failure {
+        Some(_) => {
+            std::thread::sleep(Duration::from_millis(200));
+            sidecar.exit_code()
+        }
+        None => None,
+    };
+    let _ = sidecar.stop();
+
+    let mut timings = vec![launch];
+    timings.extend(outcome.timings);
+    let (failed_phase, error) = outcome.failure.unzip();
+
+    SmokeTestResult {
+        passed: failed_phase.is_none(),
+        timings,
+        failed_phase,
+        error,
+        exit_code,
+        stderr_tail: tail(log.recent(), STDERR_TAIL_LINES),
+    }
+}
+
+struct PhasesOutcome {
+    timings: Vec<PhaseTiming>,
+    failure: Option<(SmokePhase, String)>,
+}
+
+/// Handshake, health and chat against a started sidecar
+///
+/// The handshake is the first `health` answer; requests written before
+/// Python is listening wait in the pipe, so it gets the longer timeout.
+fn run_phases(client: &RpcClient) -> PhasesOutcome {
+    let steps: [(SmokePhase, RpcMethod, Value, Duration); 3] = [
+        (SmokePhase::Handshake, RpcMethod::Health, json!({}), HANDSHAKE_TIMEOUT),
+        (SmokePhase::Health, RpcMethod::Health, json!({}), CALL_TIMEOUT),
+        (SmokePhase::Chat, RpcMethod::ChatCommand, json!({
+            "message": SMOKE_MESSAGE,
+            "bid_context": null,
+            "use_llm": false,
+        }), CALL_TIMEOUT),
+    ];
+
+    let mut timings = Vec::new();
+    for (phase, method, params, limit) in steps {
+        let started = Instant::now();
+        let result = client.call_with_timeout(method.as_str(), params, Some(limit))
+            .and_then(|value| check_reply(phase, &value));
+        if let Err(e) = result {
+            return PhasesOutcome { timings, failure: Some((phase, e.to_string())) };
+        }
+        timings.push(PhaseTiming { phase, millis: started.elapsed().as_millis() as u64 });
+    }
+
+    PhasesOutcome { timings, failure: None }
+}
+
+/// A health reply that reports a problem fails the phase
+fn check_reply(phase: SmokePhase, value: &Value) -> Result<(), RpcError> {
+    let status = value.get("status").and_then(Value::as_str);
+    match status {
+        Some(status) if phase != SmokePhase::Chat && !matches!(status, "ok" | "healthy") => Err(RpcError {
+            code: -32603,
+            message: format!("Sidecar reported status '{}'", status),
+            data: Some(value.clone()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn tail(mut lines: Vec<String>, count: usize) -> Vec<String> {
+    let skip = lines.len().saturating_sub(count);
+    lines.drain(..skip);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::rpc::testing::MockSidecar;
+
+    #[test]
+    fn test_phases_stop_at_first_failure() {
+        let (sidecar, _) = MockSidecar::new(|request| match request.method.as_str() {
+            "health" => Ok(json!({ "status": "ok" })),
+            _ => Err(RpcError { code: -32601, message: "Method not found".to_string(), data: None }),
+        });
+
+        let outcome = run_phases(&sidecar.rpc_client());
+        let phases: Vec<_> = outcome.timings.iter().map(|t| t.phase).collect();
+        assert_eq!(phases, vec![SmokePhase::Handshake, SmokePhase::Health]);
+        let (phase, error) = outcome.failure.unwrap();
+        assert_eq!(phase, SmokePhase::Chat);
+        assert!(error.contains("Method not found"));
+
+        let methods: Vec<_> = sidecar.requests().into_iter().map(|r| r.method).collect();
+        assert_eq!(methods, vec!["health", "health", "chat_command"]);
+        assert_eq!(sidecar.requests()[2].params["use_llm"], json!(false));
+    }
+
+    #[test]
+    fn test_unhealthy_status_fails_handshake() {
+        let (sidecar, _) = MockSidecar::new(|_| Ok(json!({ "status": "error", "detail": "llama_cpp missing" })));
+
+        let outcome = run_phases(&sidecar.rpc_client());
+        assert!(outcome.timings.is_empty());
+        assert_eq!(outcome.failure.unwrap().0, SmokePhase::Handshake);
+
+        let (passing, _) = MockSidecar::new(|_| Ok(json!({ "status": "ok", "explanation": "Commands: ..." })));
+        assert!(run_phases(&passing.rpc_client()).failure.is_none());
+
+        assert_eq!(tail((0..30).map(|i| i.to_string()).collect(), 3), vec!["27", "28", "29"]);
+    }
+
+    #[test]
+    fn test_missing_script_fails_launch() {
+        let result = run_smoke_test("python3", Path::new("/nonexistent/rpc_server.py"), SidecarEnv::default());
+        assert!(!result.passed);
+        assert_eq!(result.failed_phase, Some(SmokePhase::Launch));
+        assert!(result.error.unwrap().contains("not found"));
+    }
+}
@@ -0,0 +1,190 @@
+//! Noticing when the bid's file is changed outside the app
+//!
+//! People open a generated workbook in Excel, tweak it, and keep editing in
+//! the app; without a check the two drift apart silently. The file a bid was
+//! loaded from or last exported to is fingerprinted, and compared again when
+//! the window regains focus and before anything is written over it.
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::setup_wizard::sha256_file;
+
+/// Files up to this size are also compared by content
+pub const HASH_LIMIT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// What the file looked like when the app last read or wrote it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SourceFingerprint {
+    pub path: String,
+    /// RFC 3339; None where the platform has no modification time
+    pub modified_at: Option<String>,
+    pub size: u64,
+    /// Only for files up to `HASH_LIMIT_BYTES`
+    pub sha256: Option<String>,
+}
+
+/// Result of comparing the file on disk with its fingerprint
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SourceStatus {
+    /// The bid wasn't loaded from or exported to a file
+    Untracked,
+    InSync,
+    /// The file was changed since the app last read or wrote it
+    Modified {
+        path: String,
+        recorded_modified_at: Option<String>,
+        current_modified_at: Option<String>,
+        recorded_size: u64,
+        current_size: u64,
+    },
+    /// The file was deleted or moved
+    Missing { path: String, recorded_modified_at: Option<String> },
+}
+
+impl SourceStatus {
+    pub fn is_diverged(&self) -> bool {
+        matches!(self, SourceStatus::Modified { .. } | SourceStatus::Missing { .. })
+    }
+}
+
+/// The tracked file and what the user chose to do about changes to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceTracking {
+    pub fingerprint: SourceFingerprint,
+    /// The user chose to write over the changed file on the next export
+    pub overwrite_confirmed: bool,
+    /// Last divergence reported to the frontend, so focus checks don't
+    /// repeat it
+    pub reported: Option<SourceStatus>,
+}
+
+impl SourceTracking {
+    pub fn new(fingerprint: SourceFingerprint) -> Self {
+        Self { fingerprint, overwrite_confirmed: false, reported: None }
+    }
+}
+
+pub fn fingerprint(path: &Path) -> Result<SourceFingerprint, String> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let sha256 = match metadata.len() <= HASH_LIMIT_BYTES {
+        true => Some(sha256_file(path)?),
+        false => None,
+    };
+
+    Ok(SourceFingerprint {
+        path: path.to_string_lossy().to_string(),
+        modified_at: modified_at(&metadata),
+        size: metadata.len(),
+        sha256,
+    })
+}
+
+fn modified_at(metadata: &std::fs::Metadata) -> Option<String> {
+    metadata
+        .modified()
+        .ok()
+        .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+}
+
+/// Compare the file at `recorded.path` with `recorded`
+///
+/// Small files are compared by content, so saving without changes (or a
+/// copy that kept the data) doesn't count; larger ones by time and size.
+pub fn check(recorded: &SourceFingerprint) -> SourceStatus {
+    let path = Path::new(&recorded.path);
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return SourceStatus::Missing {
+            path: recorded.path.clone(),
+            recorded_modified_at: recorded.modified_at.clone(),
+        };
+    };
+
+    let current_modified_at = modified_at(&metadata);
+    let unchanged = match &recorded.sha256 {
+        Some(hash) if metadata.len() == recorded.size => sha256_file(path).is_ok_and(|current| &current == hash),
+        Some(_) => false,
+        None => metadata.len() == recorded.size && current_modified_at == recorded.modified_at,
+    };
+
+    if unchanged {
+        return SourceStatus::InSync;
+    }
+    SourceStatus::Modified {
+        path: recorded.path.clone(),
+        recorded_modified_at: recorded.modified_at.clone(),
+        current_modified_at,
+        recorded_size: recorded.size,
+        current_size: metadata.len(),
+    }
+}
+
+/// First free `<stem>_v<N>.<ext>` after `path`
+///
+/// `bid.xlsx` becomes `bid_v2.xlsx`, and `bid_v2.xlsx` becomes `bid_v3.xlsx`.
+pub fn next_version_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let (base, current) = match stem.rsplit_once("_v") {
+        Some((base, number)) if !base.is_empty() => match number.parse::<u32>() {
+            Ok(number) => (base.to_string(), number),
+            Err(_) => (stem.clone(), 1),
+        },
+        _ => (stem.clone(), 1),
+    };
+
+    (current + 1..)
+        .map(|version| path.with_file_name(format!("{}_v{}{}", base, version, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_detects_edits_and_deletion() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("bid.xlsx");
+        std::fs::write(&path, b"shots v1").unwrap();
+
+        let recorded = fingerprint(&path).unwrap();
+        assert!(recorded.sha256.is_some());
+        assert_eq!(check(&recorded), SourceStatus::InSync);
+
+        // Rewriting the same bytes is not a change
+        std::fs::write(&path, b"shots v1").unwrap();
+        assert_eq!(check(&recorded), SourceStatus::InSync);
+
+        // Same size, different content
+        std::fs::write(&path, b"shots v2").unwrap();
+        let status = check(&recorded);
+        assert!(matches!(status, SourceStatus::Modified { current_size: 8, .. }));
+        assert!(status.is_diverged());
+
+        std::fs::rename(&path, dir.join("moved.xlsx")).unwrap();
+        assert!(matches!(check(&recorded), SourceStatus::Missing { .. }));
+
+        // Large files fall back to time and size
+        let large = SourceFingerprint { sha256: None, ..fingerprint(&dir.join("moved.xlsx")).unwrap() };
+        assert_eq!(check(&large), SourceStatus::InSync);
+        assert!(check(&SourceFingerprint { size: 1, ..large }).is_diverged());
+    }
+
+    #[test]
+    fn test_next_version_path() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        assert_eq!(next_version_path(&dir.join("bid.xlsx")), dir.join("bid_v2.xlsx"));
+        assert_eq!(next_version_path(&dir.join("bid_v2.xlsx")), dir.join("bid_v3.xlsx"));
+        assert_eq!(next_version_path(&dir.join("my_venue.csv")), dir.join("my_venue_v2.csv"));
+
+        std::fs::write(dir.join("bid_v2.xlsx"), b"").unwrap();
+        assert_eq!(next_version_path(&dir.join("bid.xlsx")), dir.join("bid_v3.xlsx"));
+    }
+}
@@ -0,0 +1,299 @@
+//! Per-shot audit trail of edits
+//!
+//! When a bid changes hands people ask who changed a shot's hours and when.
+//! Every mutation is diffed field by field against the shots as they were
+//! after the previous one, and each changed field becomes an entry. Entries
+//! are appended to `audit/<bid-id>.jsonl`; once that passes
+//! `ROTATE_BYTES` it moves to `<bid-id>.1.jsonl`, replacing the older one.
+//! Project files carry the trail too, so it survives the bid changing hands.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::attachments::sanitize;
+use crate::commands::bid::ShotData;
+
+/// Log size at which it's rotated
+pub const ROTATE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Entries kept in memory when the trail isn't written to disk
+const MEMORY_LIMIT: usize = 5000;
+
+/// Fields not audited: the position changes for every shot on a reorder
+const SKIPPED_FIELDS: [&str; 1] = ["sort_index"];
+
+/// One changed field of one shot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub shot_id: String,
+    /// Field name as in `ShotData`, or `shot` when the shot was added or removed
+    pub field: String,
+    pub old_value: Value,
+    pub new_value: Value,
+    /// OS user the app ran as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<String>,
+}
+
+/// Shots as of the last mutation plus where entries go
+#[derive(Default)]
+pub struct AuditLog {
+    dir: Option<PathBuf>,
+    /// Shots as they were after the last recorded mutation
+    base: HashMap<String, ShotData>,
+    /// Recent entries of the current bid, for when there's no `dir`
+    recent: VecDeque<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Write entries under `dir` from now on
+    pub fn enable(&mut self, dir: PathBuf) {
+        self.dir = Some(dir);
+    }
+
+    /// Take `shots` as the new starting point without recording anything,
+    /// as when a different bid is loaded
+    pub fn reset(&mut self, shots: &[ShotData]) {
+        self.base = shots.iter().map(|s| (s.id.clone(), s.clone())).collect();
+        self.recent.clear();
+    }
+
+    /// Record how `shots` differ from the last mutation, updating the base.
+    /// With `only` set, just that shot is compared.
+    pub fn record(&mut self, bid_id: &str, shots: &[ShotData], only: Option<&str>) -> Result<(), String> {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let changed_by = current_user();
+        let mut entries = Vec::new();
+
+        for shot in shots.iter().filter(|s| only.is_none_or(|id| s.id == id)) {
+            let changes = match self.base.get(&shot.id) {
+                Some(before) if before == shot => continue,
+                Some(before) => diff_shot(before, shot),
+                None => vec![("shot".to_string(), Value::from("absent"), Value::from("present"))],
+            };
+            entries.extend(changes.into_iter().map(|(field, old_value, new_value)| AuditEntry {
+                timestamp: timestamp.clone(),
+                shot_id: shot.id.clone(),
+                field,
+                old_value,
+                new_value,
+                changed_by: changed_by.clone(),
+            }));
+            self.base.insert(shot.id.clone(), shot.clone());
+        }
+
+        if only.is_none() {
+            let removed: Vec<String> = self.base.keys()
+                .filter(|id| !shots.iter().any(|s| &s.id == *id))
+                .cloned()
+                .collect();
+            for id in removed {
+                self.base.remove(&id);
+                entries.push(AuditEntry {
+                    timestamp: timestamp.clone(),
+                    shot_id: id,
+                    field: "shot".to_string(),
+                    old_value: Value::from("present"),
+                    new_value: Value::from("absent"),
+                    changed_by: changed_by.clone(),
+                });
+            }
+        }
+
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.recent.extend(entries.iter().cloned());
+        let overflow = self.recent.len().saturating_sub(MEMORY_LIMIT);
+        self.recent.drain(..overflow);
+
+        match &self.dir {
+            Some(dir) => append(dir, bid_id, &entries),
+            None => Ok(()),
+        }
+    }
+
+    /// Entries for `shot_id`, oldest first
+    pub fn history(&self, bid_id: &str, shot_id: &str) -> Result<Vec<AuditEntry>, String> {
+        Ok(self.trail(bid_id)?.into_iter().filter(|e| e.shot_id == shot_id).collect())
+    }
+
+    /// Every entry kept for `bid_id`, oldest first
+    pub fn trail(&self, bid_id: &str) -> Result<Vec<AuditEntry>, String> {
+        match &self.dir {
+            Some(dir) => read(dir, bid_id),
+            None => Ok(self.recent.iter().cloned().collect()),
+        }
+    }
+
+    /// Take the trail saved with a project as `bid_id`'s, unless the one
+    /// kept here is at least as long, as when the project was saved here
+    pub fn import(&mut self, bid_id: &str, entries: Vec<AuditEntry>) -> Result<(), String> {
+        if entries.len() <= self.trail(bid_id)?.len() {
+            return Ok(());
+        }
+
+        match &self.dir {
+            Some(dir) => {
+                for path in [rotated_path(dir, bid_id), log_path(dir, bid_id)] {
+                    match fs::remove_file(&path) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(format!("Failed to replace audit log {}: {}", path.display(), e));
+                        }
+                        _ => {}
+                    }
+                }
+                append(dir, bid_id, &entries)
+            }
+            None => {
+                self.recent = entries.into();
+                let overflow = self.recent.len().saturating_sub(MEMORY_LIMIT);
+                self.recent.drain(..overflow);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Fields that differ between two versions of a shot, as (field, old, new)
+pub fn diff_shot(before: &ShotData, after: &ShotData) -> Vec<(String, Value, Value)> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) = (serde_json::to_value(before), serde_json::to_value(after)) else {
+        return Vec::new();
+    };
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|field| !SKIPPED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field).cloned().unwrap_or(Value::Null);
+            let new = after.get(field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| (field.clone(), old, new))
+        })
+        .collect()
+}
+
+/// OS user the app runs as
+pub(crate) fn current_user() -> Option<String> {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok()
+}
+
+fn log_path(dir: &Path, bid_id: &str) -> PathBuf {
+    dir.join(format!("{}.jsonl", sanitize(bid_id)))
+}
+
+fn rotated_path(dir: &Path, bid_id: &str) -> PathBuf {
+    dir.join(format!("{}.1.jsonl", sanitize(bid_id)))
+}
+
+fn append(dir: &Path, bid_id: &str, entries: &[AuditEntry]) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create audit directory: {}", e))?;
+    let path = log_path(dir, bid_id);
+    if fs::metadata(&path).is_ok_and(|m| m.len() >= ROTATE_BYTES) {
+        fs::rename(&path, rotated_path(dir, bid_id))
+            .map_err(|e| format!("Failed to rotate audit log: {}", e))?;
+    }
+
+    let mut lines = String::new();
+    for entry in entries {
+        let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(|e| format!("Failed to write audit log {}: {}", path.display(), e))
+}
+
+/// Every entry kept for `bid_id`, rotated file first; unreadable lines are skipped
+fn read(dir: &Path, bid_id: &str) -> Result<Vec<AuditEntry>, String> {
+    let mut entries = Vec::new();
+    for path in [rotated_path(dir, bid_id), log_path(dir, bid_id)] {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to read audit log {}: {}", path.display(), e)),
+        };
+        entries.extend(contents.lines().filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok()));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, hours: f64) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            estimated_hours: Some(hours),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_persist_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        let mut log = AuditLog::default();
+        log.enable(dir.clone());
+        log.reset(&[shot("SH010", 10.0)]);
+
+        log.record("bid", &[shot("SH010", 12.0), shot("SH020", 4.0)], None).unwrap();
+        log.record("bid", &[shot("SH020", 4.0)], None).unwrap();
+
+        let history = log.history("bid", "SH010").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].old_value.clone(), history[0].new_value.clone()), (Value::from(10.0), Value::from(12.0)));
+        assert_eq!((history[1].field.as_str(), history[1].new_value.clone()), ("shot", Value::from("absent")));
+        assert_eq!(log.history("bid", "SH020").unwrap()[0].new_value, Value::from("present"));
+
+        // A full log moves aside and is still read back
+        fs::write(log_path(&dir, "bid"), "x".repeat(ROTATE_BYTES as usize)).unwrap();
+        log.record("bid", &[shot("SH020", 5.0)], Some("SH020")).unwrap();
+        assert!(rotated_path(&dir, "bid").exists());
+        assert_eq!(log.history("bid", "SH020").unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_project_trail_is_imported_once() {
+        let dir = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        let mut saved = AuditLog::default();
+        saved.reset(&[shot("SH010", 10.0)]);
+        saved.record("bid", &[shot("SH010", 12.0)], None).unwrap();
+        saved.record("bid", &[shot("SH010", 14.0)], None).unwrap();
+        let trail = saved.trail("bid").unwrap();
+
+        let mut log = AuditLog::default();
+        log.enable(dir.clone());
+        log.import("bid", trail.clone()).unwrap();
+        assert_eq!(log.history("bid", "SH010").unwrap(), trail);
+
+        // A longer trail kept here wins over an older copy of the project
+        log.reset(&[shot("SH010", 14.0)]);
+        log.record("bid", &[shot("SH010", 16.0)], None).unwrap();
+        log.import("bid", trail).unwrap();
+        assert_eq!(log.trail("bid").unwrap().len(), 3);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_reorder_is_not_audited() {
+        let mut moved = shot("SH010", 10.0);
+        moved.sort_index = 2048;
+        assert!(diff_shot(&shot("SH010", 10.0), &moved).is_empty());
+    }
+}
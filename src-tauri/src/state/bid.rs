@@ -1,38 +1,2778 @@
-use crate::commands::bid::ShotData;
-use std::sync::Mutex;
+use crate::adjustments::{self, AdjustmentReport, AdjustmentScope, LumpAdjustment, SplitMethod};
+use crate::assumptions::{self, AssumptionItem};
+use crate::budget::{self, BudgetFitReport, BudgetStrategy};
+use crate::commands::bid::{normalize_tags, total_final_price, validate_tasks, ArchivedShot, BidMeta, BidRevision, BidStatus, RestoredShot, ShotData, ShotGroup, TagCount, TaskLine, UNASSIGNED_DEPARTMENT};
+use crate::error::AppError;
+use crate::estimation::{self, EstimateStrategy, EstimatedBy, HourRange};
+use crate::source_sync::{self, SourceFingerprint, SourceStatus, SourceTracking};
+use crate::scene_order::compare_scene_numbers;
+use crate::tempfiles;
+use super::audit::{AuditEntry, AuditLog};
+use super::journal::{self, Journal, JournalEntry, JournalOp, JournalStats};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Delay before a mutated bid is written to the autosave file
+pub(crate) const AUTOSAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Spacing between consecutive `sort_index` values
+const SORT_INDEX_GAP: u32 = 1024;
+
+/// Shot orders kept for `undo_shot_order`
+const ORDER_HISTORY_LIMIT: usize = 50;
+
+/// Deletes and restores kept for `undo_archive_change`
+const ARCHIVE_HISTORY_LIMIT: usize = 50;
+
+/// Bid contents written to `bid_autosave.json`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BidAutosave {
+    pub saved_at: String,
+    pub shots: Vec<ShotData>,
+    pub groups: Vec<ShotGroup>,
+    #[serde(default)]
+    pub meta: BidMeta,
+    #[serde(default)]
+    pub bid_id: Option<String>,
+    #[serde(default)]
+    pub revisions: Vec<BidRevision>,
+    #[serde(default)]
+    pub archived: Vec<ArchivedShot>,
+    /// Journal revision the snapshot includes
+    #[serde(default)]
+    pub revision: u64,
+}
+
+/// What `abort_extraction` puts back
+struct PreExtraction {
+    shots: Vec<ShotData>,
+    groups: Vec<ShotGroup>,
+    meta: BidMeta,
+    revisions: Vec<BidRevision>,
+    archived: Vec<ArchivedShot>,
+    bid_id: Option<String>,
+    access: ReadOnlyStatus,
+    source: Option<SourceTracking>,
+    /// `BidState.edits` when the run began
+    edits: u64,
+}
+
+/// Part of the bid a mutation touched, which decides what gets journaled
+enum Change<'a> {
+    Shot(&'a str),
+    Shots,
+    Groups,
+    Bid,
+}
+
+/// A delete or restore that undo and redo can reverse
+enum ArchiveChange {
+    /// Shot `id` went to the archive
+    Deleted { id: String },
+    /// Shot `id` came back from the archive
+    Restored { id: String, deleted_at: String, reason: Option<String> },
+}
+
+/// Outcome of replacing provisional shots with the final shot list
+///
+/// Sent to the frontend as the `shots-reconciled` event.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct ShotsReconciled {
+    /// Provisional shots that are in the final list
+    pub confirmed: Vec<String>,
+    /// Shots in the final list that were never delivered provisionally
+    pub added: Vec<String>,
+    /// Provisional shots dropped because the final list doesn't have them
+    pub removed: Vec<String>,
+}
+
+impl ShotsReconciled {
+    pub fn is_empty(&self) -> bool {
+        self.confirmed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Whether the open bid can be edited
+///
+/// Sent to the frontend as the `bid-readonly-changed` event.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub struct ReadOnlyStatus {
+    /// Edits are refused
+    pub read_only: bool,
+    /// The project file is saved to open read-only; only `unlock` clears it
+    pub project_read_only: bool,
+    /// Changed since it was opened or saved
+    pub dirty: bool,
+}
 
 /// Global bid state
 #[derive(Default)]
 pub struct BidState {
     shots: Mutex<Vec<ShotData>>,
+    meta: Mutex<BidMeta>,
+    groups: Mutex<Vec<ShotGroup>>,
+    /// Earlier versions kept by `revise`, oldest first
+    revisions: Mutex<Vec<BidRevision>>,
+    bid_id: Mutex<Option<String>>,
+    /// Ids delivered by `shot_extracted` while a script is being processed
+    extraction: Mutex<Option<HashSet<String>>>,
+    /// The bid as it stood before the script run, put back if the run fails
+    pre_extraction: Mutex<Option<PreExtraction>>,
+    autosave_file: Mutex<Option<PathBuf>>,
+    autosave_generation: Arc<AtomicU64>,
+    journal: Mutex<Option<Journal>>,
+    /// Bumped by every journaled mutation
+    revision: AtomicU64,
+    /// Bumped by every mutation, journaled or not
+    edits: AtomicU64,
+    /// Shot ids in their order before each reorder, newest last
+    order_history: Mutex<Vec<Vec<String>>>,
+    access: Mutex<ReadOnlyStatus>,
+    /// File the bid was last loaded from or exported to
+    source: Mutex<Option<SourceTracking>>,
+    audit: Mutex<AuditLog>,
+    /// Deleted shots, oldest first
+    archived: Mutex<Vec<ArchivedShot>>,
+    archive_undo: Mutex<Vec<ArchiveChange>>,
+    archive_redo: Mutex<Vec<ArchiveChange>>,
+    /// Called after every change to the bid
+    change_listener: Mutex<Option<ChangeListener>>,
 }
 
+/// Told when a bid changes, e.g. to drop cached query answers
+pub type ChangeListener = Arc<dyn Fn() + Send + Sync>;
+
 impl BidState {
     pub fn get_shots(&self) -> Vec<ShotData> {
         self.shots.lock().unwrap().clone()
     }
 
-    pub fn set_shots(&self, shots: Vec<ShotData>) {
+    /// Get a page of shots in bid order along with the total shot count
+    ///
+    /// An offset past the end yields an empty page rather than an error.
+    pub fn get_shots_page(&self, offset: usize, limit: usize) -> (Vec<ShotData>, usize) {
+        let shots = self.shots.lock().unwrap();
+        let total = shots.len();
+        let start = offset.min(total);
+        let end = start.saturating_add(limit).min(total);
+
+        (shots[start..end].to_vec(), total)
+    }
+
+    /// Replace the shots with a newly loaded bid, which gets a new bid id
+    /// and starts as a first draft
+    ///
+    /// A read-only bid is refused; only `replace_bid` changes the lock.
+    pub fn set_shots(&self, mut shots: Vec<ShotData>) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        *self.source.lock().unwrap() = None;
+        renumber(&mut shots);
+        *self.shots.lock().unwrap() = shots;
+        *self.bid_id.lock().unwrap() = None;
+        self.revisions.lock().unwrap().clear();
+        self.order_history.lock().unwrap().clear();
+        self.archived.lock().unwrap().clear();
+        self.clear_archive_history();
+
+        let mut meta = self.meta.lock().unwrap();
+        meta.status = BidStatus::Draft;
+        meta.valid_until = None;
+        meta.version = 1;
+        drop(meta);
+
+        self.record(Change::Bid);
+        Ok(())
+    }
+
+    /// Replace the archive ahead of the `replace_bid` that journals it
+    pub fn load_archive(&self, archived: Vec<ArchivedShot>) {
+        *self.archived.lock().unwrap() = archived;
+    }
+
+    /// Replace the whole bid, as when opening a project file
+    ///
+    /// The bid starts unchanged, locked as `access` says. The archive is
+    /// whatever `load_archive` last set.
+    pub fn replace_bid(
+        &self,
+        bid_id: String,
+        shots: Vec<ShotData>,
+        groups: Vec<ShotGroup>,
+        meta: BidMeta,
+        revisions: Vec<BidRevision>,
+        access: ReadOnlyStatus,
+    ) {
+        let mut shots = shots;
+        restore_order(&mut shots);
         *self.shots.lock().unwrap() = shots;
+        *self.groups.lock().unwrap() = groups;
+        *self.meta.lock().unwrap() = meta;
+        *self.revisions.lock().unwrap() = revisions;
+        *self.bid_id.lock().unwrap() = Some(bid_id);
+        self.order_history.lock().unwrap().clear();
+        self.clear_archive_history();
+        *self.source.lock().unwrap() = None;
+        self.record(Change::Bid);
+
+        *self.access.lock().unwrap() = ReadOnlyStatus { dirty: false, ..access };
+    }
+
+    /// Remember the file the bid was just loaded from or exported to
+    pub fn track_source(&self, fingerprint: SourceFingerprint) {
+        *self.source.lock().unwrap() = Some(SourceTracking::new(fingerprint));
+    }
+
+    pub fn source_file(&self) -> Option<SourceFingerprint> {
+        self.source.lock().unwrap().as_ref().map(|tracking| tracking.fingerprint.clone())
+    }
+
+    /// Compare the tracked file with what's on disk
+    ///
+    /// The second value is true when the divergence differs from the one
+    /// last returned, so a focus check reports each change once.
+    pub fn check_source(&self) -> (SourceStatus, bool) {
+        let Some(fingerprint) = self.source_file() else {
+            return (SourceStatus::Untracked, false);
+        };
+        let status = source_sync::check(&fingerprint);
+
+        let mut source = self.source.lock().unwrap();
+        let Some(tracking) = source.as_mut().filter(|t| t.fingerprint == fingerprint) else {
+            // Replaced while the file was being read
+            return (status, false);
+        };
+        let reported = match status.is_diverged() {
+            true => tracking.reported.replace(status.clone()).as_ref() != Some(&status),
+            false => {
+                tracking.reported = None;
+                tracking.overwrite_confirmed = false;
+                false
+            }
+        };
+        (status, reported)
+    }
+
+    /// Allow the next export to write over the changed file
+    pub fn confirm_overwrite(&self) -> Result<(), AppError> {
+        match self.source.lock().unwrap().as_mut() {
+            Some(tracking) => {
+                tracking.overwrite_confirmed = true;
+                Ok(())
+            }
+            None => Err(AppError::validation("The bid wasn't loaded from a file")),
+        }
+    }
+
+    /// Refuse to write to `output_path` if it's the tracked file and it
+    /// changed on disk, unless the user confirmed the overwrite
+    pub fn ensure_source_not_overwritten(&self, output_path: &Path) -> Result<(), AppError> {
+        let Some(fingerprint) = self.source_file() else {
+            return Ok(());
+        };
+        if !same_file(Path::new(&fingerprint.path), output_path) {
+            return Ok(());
+        }
+        if self.source.lock().unwrap().as_ref().is_some_and(|t| t.overwrite_confirmed) {
+            return Ok(());
+        }
+
+        match self.check_source().0 {
+            SourceStatus::Modified { path, .. } => Err(AppError::source_diverged(format!(
+                "{} was changed outside the app since it was loaded. Reload it, confirm the overwrite, or export a new version",
+                path
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn read_only_status(&self) -> ReadOnlyStatus {
+        *self.access.lock().unwrap()
+    }
+
+    /// Error unless the bid can be edited
+    pub fn ensure_writable(&self) -> Result<(), AppError> {
+        let access = self.access.lock().unwrap();
+        if !access.read_only {
+            return Ok(());
+        }
+
+        Err(AppError::read_only(if access.project_read_only {
+            "This project opens read-only. Unlock it to make changes."
+        } else {
+            "This bid is open read-only. Turn read-only off to make changes."
+        }))
+    }
+
+    /// Lock or unlock editing
+    ///
+    /// With `persist` the lock is also saved with the project, marking the
+    /// bid as changed. A project saved read-only can only be opened up with
+    /// `unlock`.
+    pub fn set_read_only(&self, enabled: bool, persist: bool) -> Result<ReadOnlyStatus, AppError> {
+        let mut access = self.access.lock().unwrap();
+        if !enabled && access.project_read_only {
+            return Err(AppError::read_only("This project opens read-only. Unlock it to make changes."));
+        }
+
+        access.read_only = enabled;
+        if enabled && persist && !access.project_read_only {
+            access.project_read_only = true;
+            access.dirty = true;
+        }
+        Ok(*access)
+    }
+
+    /// Make the bid editable and clear the project's read-only flag
+    pub fn unlock(&self) -> ReadOnlyStatus {
+        let mut access = self.access.lock().unwrap();
+        if access.project_read_only {
+            access.dirty = true;
+        }
+        access.read_only = false;
+        access.project_read_only = false;
+        *access
+    }
+
+    /// The bid was written to a project file
+    pub fn mark_saved(&self) {
+        self.access.lock().unwrap().dirty = false;
+    }
+
+    /// Start a script run: empty the table and accept provisional shots
+    ///
+    /// The bid is set aside rather than dropped, and nothing is autosaved
+    /// or journaled, until the final shot list replaces it; a failed or
+    /// cancelled run gets it back through `abort_extraction`.
+    pub fn begin_extraction(&self) {
+        let mut shots = self.shots.lock().unwrap();
+        *self.pre_extraction.lock().unwrap() = Some(PreExtraction {
+            shots: std::mem::take(&mut *shots),
+            groups: self.get_groups(),
+            meta: self.get_meta(),
+            revisions: self.get_revisions(),
+            archived: self.get_archived_shots(),
+            bid_id: self.bid_id.lock().unwrap().clone(),
+            access: *self.access.lock().unwrap(),
+            source: self.source.lock().unwrap().clone(),
+            edits: self.edits.load(Ordering::SeqCst),
+        });
+        *self.extraction.lock().unwrap() = Some(HashSet::new());
+    }
+
+    /// Whether a script run is in progress
+    pub fn is_extracting(&self) -> bool {
+        self.extraction.lock().unwrap().is_some()
+    }
+
+    /// Add or refresh a shot delivered mid-run, returning it as stored
+    ///
+    /// Returns `None` (and changes nothing) when no run is in progress, when
+    /// the same shot was already delivered, or when the final shot list has
+    /// already replaced it, so late or repeated events are harmless.
+    pub fn add_provisional_shot(&self, mut shot: ShotData) -> Option<ShotData> {
+        let mut extraction = self.extraction.lock().unwrap();
+        let delivered = extraction.as_mut()?;
+
+        shot.provisional = true;
+        shot.sync_frame_count();
+
+        let mut shots = self.shots.lock().unwrap();
+        match shots.iter_mut().find(|s| s.id == shot.id) {
+            Some(existing) => {
+                shot.sort_index = existing.sort_index;
+                if !existing.provisional || *existing == shot {
+                    return None;
+                }
+                *existing = shot.clone();
+                Some(shot)
+            }
+            None => {
+                delivered.insert(shot.id.clone());
+                push_last(&mut shots, shot);
+                shots.last().cloned()
+            }
+        }
+    }
+
+    /// End a successful script run, dropping provisional shots the final
+    /// list lacks
+    ///
+    /// Call after the final shot list has been stored.
+    pub fn finish_extraction(&self) -> ShotsReconciled {
+        *self.pre_extraction.lock().unwrap() = None;
+        let Some(delivered) = self.extraction.lock().unwrap().take() else {
+            return ShotsReconciled::default();
+        };
+
+        let mut shots = self.shots.lock().unwrap();
+        let mut reconciled = ShotsReconciled::default();
+
+        for shot in shots.iter() {
+            match (shot.provisional, delivered.contains(&shot.id)) {
+                (true, _) => reconciled.removed.push(shot.id.clone()),
+                (false, true) => reconciled.confirmed.push(shot.id.clone()),
+                (false, false) => reconciled.added.push(shot.id.clone()),
+            }
+        }
+        shots.retain(|s| !s.provisional);
+        drop(shots);
+
+        if !reconciled.removed.is_empty() {
+            self.record(Change::Shots);
+        }
+        reconciled
+    }
+
+    /// End a failed or cancelled script run, putting back the bid it replaced
+    ///
+    /// Reports every provisional shot as removed. The autosave and journal
+    /// are rewritten only if something was recorded during the run.
+    pub fn abort_extraction(&self) -> ShotsReconciled {
+        *self.extraction.lock().unwrap() = None;
+        let Some(previous) = self.pre_extraction.lock().unwrap().take() else {
+            return ShotsReconciled::default();
+        };
+
+        let mut shots = self.shots.lock().unwrap();
+        let removed = shots.iter().filter(|s| s.provisional).map(|s| s.id.clone()).collect();
+        *shots = previous.shots;
+        drop(shots);
+        *self.groups.lock().unwrap() = previous.groups;
+        *self.meta.lock().unwrap() = previous.meta;
+        *self.revisions.lock().unwrap() = previous.revisions;
+        *self.archived.lock().unwrap() = previous.archived;
+        *self.bid_id.lock().unwrap() = previous.bid_id;
+        *self.access.lock().unwrap() = previous.access;
+        *self.source.lock().unwrap() = previous.source;
+
+        if self.edits.load(Ordering::SeqCst) != previous.edits {
+            self.record(Change::Bid);
+            self.access.lock().unwrap().dirty = previous.access.dirty;
+        }
+        ShotsReconciled { removed, ..Default::default() }
+    }
+
+    /// Stable id of the current bid, used to locate its attachments
+    pub fn bid_id(&self) -> String {
+        self.bid_id
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone()
+    }
+
+    /// Give a newly loaded bid the id its sidecar workspace was created under
+    pub fn assign_bid_id(&self, id: String) {
+        *self.bid_id.lock().unwrap() = Some(id);
     }
 
-    pub fn update_shot(&self, id: String, updates: ShotData) -> Result<ShotData, String> {
+    /// Apply a change to one shot and return what the closure produced
+    pub fn modify_shot<R>(&self, id: &str, change: impl FnOnce(&mut ShotData) -> Result<R, AppError>) -> Result<R, AppError> {
+        self.ensure_writable()?;
+        let mut shots = self.shots.lock().unwrap();
+        let shot = shots
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id)))?;
+
+        let result = change(shot)?;
+        drop(shots);
+
+        self.record(Change::Shot(id));
+        Ok(result)
+    }
+
+    pub fn update_shot(&self, id: String, updates: ShotData) -> Result<ShotData, AppError> {
+        self.ensure_writable()?;
         let mut shots = self.shots.lock().unwrap();
 
         let index = shots
             .iter()
             .position(|s| s.id == id)
-            .ok_or_else(|| format!("Shot {} not found", id))?;
+            .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id)))?;
+
+        // The id argument names the shot being edited. A body carrying
+        // another shot's id would overwrite that shot's row; any other
+        // stray id, e.g. a blank one from a form, is corrected.
+        if updates.id != id {
+            if shots.iter().any(|s| s.id == updates.id) {
+                return Err(AppError::validation(format!(
+                    "Update for shot {} carries the id of shot {}; ids can't be changed by an edit",
+                    id, updates.id
+                )));
+            }
+            log::warn!("Update for shot {} carried id '{}'; keeping {}", id, updates.id, id);
+        }
+        let mut updates = ShotData { id: id.clone(), ..updates };
+        updates.sync_frame_count();
+
+        // Attachments are only changed through attach/remove, position
+        // through reorder/move, tasks through set_shot_tasks, review
+        // state by extraction and mark_reviewed, hour provenance by
+        // estimate_missing_hours and adjustments by apply/remove_adjustment,
+        // so an edit from a form that doesn't know about them can't drop or scramble them
+        updates.attachments = shots[index].attachments.clone();
+        updates.sort_index = shots[index].sort_index;
+        updates.tasks = shots[index].tasks.clone();
+        updates.tags = shots[index].tags.clone();
+        updates.confidence = shots[index].confidence;
+        updates.extraction_flags = shots[index].extraction_flags.clone();
+        updates.review = shots[index].review.clone();
+        updates.adjustment = shots[index].adjustment;
+        // Hours typed in by hand are no longer the table's
+        updates.estimated_by = shots[index].estimated_by
+            .filter(|_| updates.estimated_hours == shots[index].estimated_hours);
+
+        // Hours and cost of an itemized shot come from its tasks
+        if !updates.tasks.is_empty() {
+            updates.recalculate_pricing();
+        }
 
         shots[index] = updates.clone();
+        drop(shots);
+
+        self.record(Change::Shot(&id));
         Ok(updates)
     }
 
-    pub fn add_shot(&self, shot: ShotData) {
-        self.shots.lock().unwrap().push(shot);
+    /// Estimate hours for shots that have a duration but no hours yet
+    ///
+    /// `hours_per_second` is keyed by complexity tier (case-insensitive).
+    /// Returns the number of shots updated.
+    pub fn estimate_hours_from_duration(&self, hours_per_second: &HashMap<String, f64>) -> usize {
+        let mut shots = self.shots.lock().unwrap();
+        let mut updated = 0;
+
+        for shot in shots.iter_mut().filter(|s| s.estimated_hours.is_none()) {
+            let Some(seconds) = shot.duration_seconds() else {
+                continue;
+            };
+
+            let factor = hours_per_second
+                .iter()
+                .find(|(tier, _)| tier.eq_ignore_ascii_case(&shot.complexity))
+                .map(|(_, factor)| *factor);
+
+            if let Some(factor) = factor {
+                shot.estimated_hours = Some(seconds * factor);
+                shot.recalculate_pricing();
+                updated += 1;
+            }
+        }
+        drop(shots);
+
+        if updated > 0 {
+            self.record(Change::Shots);
+        }
+        updated
     }
 
-    pub fn clear(&self) {
-        self.shots.lock().unwrap().clear();
+    /// Fill blank hours from `ranges`, marking those shots as estimated by
+    /// the table; shots with tasks or without a matching range are skipped.
+    /// Returns the number of shots filled.
+    pub fn estimate_missing_hours(&self, ranges: &[HourRange], strategy: EstimateStrategy) -> usize {
+        let mut shots = self.shots.lock().unwrap();
+        let mut filled = 0;
+
+        for shot in shots.iter_mut().filter(|s| s.estimated_hours.is_none() && s.tasks.is_empty()) {
+            let Some(range) = estimation::find_range(ranges, shot) else {
+                continue;
+            };
+            shot.estimated_hours = Some(range.hours(strategy));
+            shot.estimated_by = Some(EstimatedBy::Table);
+            shot.recalculate_pricing();
+            filled += 1;
+        }
+        drop(shots);
+
+        if filled > 0 {
+            self.record(Change::Shots);
+        }
+        filled
+    }
+
+    /// Scale shot prices by `percent` (e.g. 20.0 = 20% more), optionally within
+    /// one scene and among `shot_ids`
+    ///
+    /// Returns the number of shots repriced.
+    pub fn adjust_prices(&self, scene: Option<&str>, shot_ids: Option<&[String]>, percent: f64) -> usize {
+        let factor = 1.0 + percent / 100.0;
+        let updated = self.update_matching(scene, shot_ids, |shot| scale_price(shot, factor));
+
+        if updated > 0 {
+            self.record(Change::Shots);
+        }
+        updated
+    }
+
+    /// The shots `adjust_prices` would change, before and after, leaving the bid as is
+    pub fn preview_adjust_prices(&self, scene: Option<&str>, shot_ids: Option<&[String]>, percent: f64) -> Vec<(ShotData, ShotData)> {
+        let factor = 1.0 + percent / 100.0;
+        self.preview_matching(scene, shot_ids, |shot| scale_price(shot, factor))
+    }
+
+    /// Fit the bid's total to `target`, or only report the fit with `dry_run`
+    pub fn fit_to_budget(
+        &self,
+        target: f64,
+        strategy: &BudgetStrategy,
+        min_hours: &HashMap<String, f64>,
+        dry_run: bool,
+    ) -> Result<BudgetFitReport, AppError> {
+        if !dry_run {
+            self.ensure_writable()?;
+        }
+        let mut shots = self.shots.lock().unwrap();
+        let (fitted, mut report) = budget::plan_budget_fit(&shots, target, strategy, min_hours)?;
+        report.dry_run = dry_run;
+
+        if !dry_run {
+            *shots = fitted;
+            drop(shots);
+            self.record(Change::Shots);
+        }
+        Ok(report)
+    }
+
+    /// Spread a lump sum over the shots in `scope`, or only report the split with `dry_run`
+    pub fn apply_adjustment(
+        &self,
+        amount: f64,
+        scope: AdjustmentScope,
+        method: SplitMethod,
+        label: Option<String>,
+        dry_run: bool,
+    ) -> Result<AdjustmentReport, AppError> {
+        if !dry_run {
+            self.ensure_writable()?;
+        }
+        let adjustment = LumpAdjustment {
+            id: uuid::Uuid::new_v4().to_string(),
+            label: label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()),
+            amount,
+            scope,
+            method,
+            shares: Vec::new(),
+            applied_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        let mut shots = self.shots.lock().unwrap();
+        let groups = self.groups.lock().unwrap();
+        let (adjusted, mut report) = adjustments::plan_adjustment(&shots, &groups, adjustment)?;
+        drop(groups);
+        report.dry_run = dry_run;
+
+        if !dry_run {
+            *shots = adjusted;
+            self.meta.lock().unwrap().adjustments.push(report.adjustment.clone());
+            drop(shots);
+            self.record(Change::Bid);
+        }
+        Ok(report)
+    }
+
+    /// Undo adjustment `id`, including on shots deleted since
+    pub fn remove_adjustment(&self, id: &str) -> Result<LumpAdjustment, AppError> {
+        self.ensure_writable()?;
+        let mut shots = self.shots.lock().unwrap();
+        let mut meta = self.meta.lock().unwrap();
+        let index = meta
+            .adjustments
+            .iter()
+            .position(|a| a.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Adjustment {} not found", id)))?;
+        let removed = meta.adjustments.remove(index);
+        drop(meta);
+
+        let mut archived = self.archived.lock().unwrap();
+        let all_shots = shots.iter_mut().chain(archived.iter_mut().map(|a| &mut a.shot));
+        adjustments::reverse_adjustment(all_shots, &removed);
+        drop(archived);
+        drop(shots);
+
+        self.record(Change::Bid);
+        Ok(removed)
+    }
+
+    /// Set the complexity tier of every shot, optionally within one scene and
+    /// among `shot_ids`
+    pub fn set_complexity(&self, scene: Option<&str>, shot_ids: Option<&[String]>, complexity: &str) -> usize {
+        let updated = self.update_matching(scene, shot_ids, |shot| {
+            shot.complexity = complexity.to_string();
+        });
+
+        if updated > 0 {
+            self.record(Change::Shots);
+        }
+        updated
+    }
+
+    /// The shots `set_complexity` would change, before and after, leaving the bid as is
+    pub fn preview_set_complexity(&self, scene: Option<&str>, shot_ids: Option<&[String]>, complexity: &str) -> Vec<(ShotData, ShotData)> {
+        self.preview_matching(scene, shot_ids, |shot| shot.complexity = complexity.to_string())
+    }
+
+    fn update_matching(&self, scene: Option<&str>, shot_ids: Option<&[String]>, mut update: impl FnMut(&mut ShotData)) -> usize {
+        let mut shots = self.shots.lock().unwrap();
+        let mut updated = 0;
+
+        for shot in shots.iter_mut().filter(|shot| in_scene(shot, scene) && in_scope(shot, shot_ids)) {
+            update(shot);
+            updated += 1;
+        }
+
+        updated
+    }
+
+    fn preview_matching(&self, scene: Option<&str>, shot_ids: Option<&[String]>, update: impl Fn(&mut ShotData)) -> Vec<(ShotData, ShotData)> {
+        self.shots.lock().unwrap()
+            .iter()
+            .filter(|shot| in_scene(shot, scene) && in_scope(shot, shot_ids))
+            .map(|shot| {
+                let mut after = shot.clone();
+                update(&mut after);
+                (shot.clone(), after)
+            })
+            .collect()
+    }
+
+    /// Changes whenever the bid does; lets a staged edit notice it went stale
+    pub fn edit_count(&self) -> u64 {
+        self.edits.load(Ordering::SeqCst)
+    }
+
+    /// Add a new shot, inheriting the bid's contingency and overhead defaults
+    pub fn add_shot(&self, mut shot: ShotData) {
+        let meta = self.get_meta();
+        shot.contingency_percent = meta.default_contingency;
+        shot.overhead_percent = meta.default_overhead;
+        shot.recalculate_pricing();
+
+        let id = shot.id.clone();
+        let renumbered = push_last(&mut self.shots.lock().unwrap(), shot);
+        self.record(if renumbered { Change::Shots } else { Change::Shot(&id) });
+    }
+
+    /// Replace a shot's task breakdown and reprice it as one change
+    ///
+    /// An empty list returns the shot to flat hours, keeping the last total.
+    pub fn set_shot_tasks(&self, id: &str, tasks: Vec<TaskLine>) -> Result<ShotData, AppError> {
+        self.set_tasks(&[id.to_string()], &tasks)
+            .map(|mut shots| shots.remove(0))
+    }
+
+    /// Add normalized `tags` to each listed shot, skipping ones it already has
+    ///
+    /// Nothing changes unless every id exists, and the whole batch is one
+    /// journal entry. Returns the updated shots in the order given.
+    pub fn add_tags(&self, ids: &[String], tags: &[String]) -> Result<Vec<ShotData>, AppError> {
+        let tags = normalize_tags(tags)?;
+        self.retag(ids, &tags, |shot, tag| {
+            if !shot.tags.contains(tag) {
+                shot.tags.push(tag.clone());
+            }
+        })
+    }
+
+    /// Remove normalized `tags` from each listed shot, on the same terms as `add_tags`
+    pub fn remove_tags(&self, ids: &[String], tags: &[String]) -> Result<Vec<ShotData>, AppError> {
+        let tags = normalize_tags(tags)?;
+        self.retag(ids, &tags, |shot, tag| shot.tags.retain(|t| t != tag))
+    }
+
+    fn retag(&self, ids: &[String], tags: &[String], apply: impl Fn(&mut ShotData, &String)) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        if ids.is_empty() {
+            return Err(AppError::validation("No shots given"));
+        }
+        if tags.is_empty() {
+            return Err(AppError::validation("No tags given"));
+        }
+
+        let mut shots = self.shots.lock().unwrap();
+        if let Some(missing) = ids.iter().find(|id| !shots.iter().any(|s| &s.id == *id)) {
+            return Err(AppError::not_found(format!("Shot {} not found", missing)));
+        }
+
+        let mut updated = Vec::with_capacity(ids.len());
+        for id in ids {
+            let shot = shots.iter_mut().find(|s| &s.id == id).unwrap();
+            for tag in tags {
+                apply(shot, tag);
+            }
+            updated.push(shot.clone());
+        }
+        drop(shots);
+
+        match ids {
+            [id] => self.record(Change::Shot(id)),
+            _ => self.record(Change::Shots),
+        }
+        Ok(updated)
+    }
+
+    /// Tags in use with their shot counts, most used first
+    pub fn all_tags(&self) -> Vec<TagCount> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for shot in self.shots.lock().unwrap().iter() {
+            for tag in &shot.tags {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts.into_iter().map(|(tag, count)| TagCount { tag, count }).collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        tags
+    }
+
+    /// Give each listed shot a copy of `tasks`
+    ///
+    /// Nothing changes unless every id exists, and the whole batch is one
+    /// journal entry. Returns the updated shots in the order given.
+    pub fn set_tasks(&self, ids: &[String], tasks: &[TaskLine]) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        validate_tasks(tasks)?;
+        if ids.is_empty() {
+            return Err(AppError::validation("No shots given"));
+        }
+
+        let mut shots = self.shots.lock().unwrap();
+        if let Some(missing) = ids.iter().find(|id| !shots.iter().any(|s| &s.id == *id)) {
+            return Err(AppError::not_found(format!("Shot {} not found", missing)));
+        }
+
+        let mut updated = Vec::with_capacity(ids.len());
+        for id in ids {
+            let shot = shots.iter_mut().find(|s| &s.id == id).unwrap();
+            shot.tasks = tasks.to_vec();
+            shot.estimated_by = None;
+            shot.recalculate_pricing();
+            updated.push(shot.clone());
+        }
+        drop(shots);
+
+        match ids {
+            [id] => self.record(Change::Shot(id)),
+            _ => self.record(Change::Shots),
+        }
+        Ok(updated)
+    }
+
+    /// Put the shots in the order of `ordered_ids`, which must list every
+    /// shot exactly once
+    pub fn reorder_shots(&self, ordered_ids: &[String]) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        let mut shots = self.shots.lock().unwrap();
+
+        let existing: HashSet<&str> = shots.iter().map(|s| s.id.as_str()).collect();
+        let mut seen = HashSet::new();
+        let mut problems = Vec::new();
+
+        let missing: Vec<&str> = shots
+            .iter()
+            .map(|s| s.id.as_str())
+            .filter(|id| !ordered_ids.iter().any(|o| o == id))
+            .collect();
+        let unknown: Vec<&str> = ordered_ids
+            .iter()
+            .map(String::as_str)
+            .filter(|id| !existing.contains(id))
+            .collect();
+        let repeated: Vec<&str> = ordered_ids
+            .iter()
+            .map(String::as_str)
+            .filter(|id| existing.contains(id) && !seen.insert(*id))
+            .collect();
+
+        if !missing.is_empty() {
+            problems.push(format!("missing {}", missing.join(", ")));
+        }
+        if !unknown.is_empty() {
+            problems.push(format!("unknown {}", unknown.join(", ")));
+        }
+        if !repeated.is_empty() {
+            problems.push(format!("repeated {}", repeated.join(", ")));
+        }
+        if !problems.is_empty() {
+            return Err(AppError::validation(format!(
+                "Shot order doesn't match the bid: {}",
+                problems.join("; ")
+            )));
+        }
+
+        self.push_order_history(&shots);
+        let mut by_id: HashMap<String, ShotData> = shots.drain(..).map(|s| (s.id.clone(), s)).collect();
+        shots.extend(ordered_ids.iter().filter_map(|id| by_id.remove(id)));
+        renumber(&mut shots);
+
+        let result = shots.clone();
+        drop(shots);
+
+        self.record(Change::Shots);
+        Ok(result)
+    }
+
+    /// Move one shot in front of `before_id`, or to the end when `None`
+    ///
+    /// Only the moved shot gets a new index unless its neighbours have run
+    /// out of room between them.
+    pub fn move_shot(&self, id: &str, before_id: Option<&str>) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        if before_id == Some(id) {
+            return Err(AppError::validation(format!("Shot {} cannot be moved before itself", id)));
+        }
+
+        let mut shots = self.shots.lock().unwrap();
+        let from = shots
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id)))?;
+        if let Some(before_id) = before_id {
+            if !shots.iter().any(|s| s.id == before_id) {
+                return Err(AppError::not_found(format!("Shot {} not found", before_id)));
+            }
+        }
+
+        self.push_order_history(&shots);
+        let mut shot = shots.remove(from);
+        let to = match before_id {
+            Some(before_id) => shots.iter().position(|s| s.id == before_id).unwrap(),
+            None => shots.len(),
+        };
+
+        let prev = if to == 0 { 0 } else { shots[to - 1].sort_index };
+        let next = shots.get(to).map_or(prev.saturating_add(2 * SORT_INDEX_GAP), |s| s.sort_index);
+        let renumbered = next.saturating_sub(prev) < 2;
+
+        shot.sort_index = prev + (next - prev) / 2;
+        shots.insert(to, shot);
+        if renumbered {
+            renumber(&mut shots);
+        }
+
+        let result = shots.clone();
+        drop(shots);
+
+        self.record(if renumbered { Change::Shots } else { Change::Shot(id) });
+        Ok(result)
+    }
+
+    /// Put the shots back in their order before the last reorder or move
+    ///
+    /// Shots added since go last; shots removed since are skipped.
+    pub fn undo_shot_order(&self) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        let previous = self.order_history
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| AppError::not_found("No shot reorder to undo"))?;
+
+        let mut shots = self.shots.lock().unwrap();
+        let position = |id: &str| previous.iter().position(|p| p == id).unwrap_or(usize::MAX);
+        shots.sort_by_key(|s| position(&s.id));
+        renumber(&mut shots);
+
+        let result = shots.clone();
+        drop(shots);
+
+        self.record(Change::Shots);
+        Ok(result)
+    }
+
+    fn push_order_history(&self, shots: &[ShotData]) {
+        let mut history = self.order_history.lock().unwrap();
+        if history.len() == ORDER_HISTORY_LIMIT {
+            history.remove(0);
+        }
+        history.push(shots.iter().map(|s| s.id.clone()).collect());
+    }
+
+    pub fn get_archived_shots(&self) -> Vec<ArchivedShot> {
+        self.archived.lock().unwrap().clone()
+    }
+
+    /// Move a shot to the archive, taking it out of its groups
+    ///
+    /// Archived shots are out of totals, queries and exports. Undo with
+    /// `undo_archive_change`.
+    pub fn delete_shot(&self, id: &str, reason: Option<String>) -> Result<ArchivedShot, AppError> {
+        self.ensure_writable()?;
+        let archived = self.archive(id, reason, chrono::Utc::now().to_rfc3339())?;
+        self.push_archive_change(ArchiveChange::Deleted { id: id.to_string() });
+        Ok(archived)
+    }
+
+    /// Put the last shot archived as `id` back at its old position and in
+    /// those of its groups that still exist
+    ///
+    /// If a shot has since taken the id, the restored one gets a suffixed id
+    /// and a warning says so.
+    pub fn restore_shot(&self, id: &str) -> Result<RestoredShot, AppError> {
+        self.ensure_writable()?;
+        let (restored, archived) = self.unarchive(id)?;
+        self.push_archive_change(ArchiveChange::Restored {
+            id: restored.shot.id.clone(),
+            deleted_at: archived.deleted_at,
+            reason: archived.reason,
+        });
+        Ok(restored)
+    }
+
+    /// Drop archived shots deleted more than `older_than_days` ago
+    ///
+    /// Returns how many went. Purging clears the archive undo history.
+    pub fn purge_archived(&self, older_than_days: u32) -> Result<usize, AppError> {
+        self.ensure_writable()?;
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(older_than_days));
+
+        let mut archived = self.archived.lock().unwrap();
+        let before = archived.len();
+        archived.retain(|a| {
+            chrono::DateTime::parse_from_rfc3339(&a.deleted_at)
+                .map(|deleted_at| deleted_at >= cutoff)
+                .unwrap_or(true)
+        });
+        let purged = before - archived.len();
+        drop(archived);
+
+        if purged > 0 {
+            self.clear_archive_history();
+            self.record(Change::Bid);
+        }
+        Ok(purged)
+    }
+
+    /// Reverse the last delete or restore
+    pub fn undo_archive_change(&self) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        let change = self.archive_undo
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| AppError::not_found("No delete or restore to undo"))?;
+
+        let inverse = self.invert(change)?;
+        self.archive_redo.lock().unwrap().push(inverse);
+        Ok(self.get_shots())
+    }
+
+    /// Repeat the last delete or restore undone
+    pub fn redo_archive_change(&self) -> Result<Vec<ShotData>, AppError> {
+        self.ensure_writable()?;
+        let change = self.archive_redo
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| AppError::not_found("No delete or restore to redo"))?;
+
+        let inverse = self.invert(change)?;
+        self.archive_undo.lock().unwrap().push(inverse);
+        Ok(self.get_shots())
+    }
+
+    /// Apply the opposite of `change`, returning the change that reverses it
+    fn invert(&self, change: ArchiveChange) -> Result<ArchiveChange, AppError> {
+        match change {
+            ArchiveChange::Deleted { id } => {
+                let (restored, archived) = self.unarchive(&id)?;
+                Ok(ArchiveChange::Restored {
+                    id: restored.shot.id,
+                    deleted_at: archived.deleted_at,
+                    reason: archived.reason,
+                })
+            }
+            ArchiveChange::Restored { id, deleted_at, reason } => {
+                self.archive(&id, reason, deleted_at)?;
+                Ok(ArchiveChange::Deleted { id })
+            }
+        }
+    }
+
+    fn archive(&self, id: &str, reason: Option<String>, deleted_at: String) -> Result<ArchivedShot, AppError> {
+        let mut shots = self.shots.lock().unwrap();
+        let index = shots
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Shot {} not found", id)))?;
+        let shot = shots.remove(index);
+        drop(shots);
+
+        let mut groups = Vec::new();
+        for group in self.groups.lock().unwrap().iter_mut() {
+            if let Some(at) = group.shot_ids.iter().position(|s| s == id) {
+                group.shot_ids.remove(at);
+                groups.push(group.name.clone());
+            }
+        }
+
+        let archived = ArchivedShot { shot, deleted_at, reason, groups };
+        self.archived.lock().unwrap().push(archived.clone());
+        self.record(Change::Bid);
+        Ok(archived)
+    }
+
+    /// Take the last shot archived as `id` out of the archive and back into
+    /// the bid, returning it with its archive entry
+    fn unarchive(&self, id: &str) -> Result<(RestoredShot, ArchivedShot), AppError> {
+        let mut archive = self.archived.lock().unwrap();
+        let index = archive
+            .iter()
+            .rposition(|a| a.shot.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Shot {} is not archived", id)))?;
+        let archived = archive.remove(index);
+        drop(archive);
+
+        let mut shot = archived.shot.clone();
+        let mut shots = self.shots.lock().unwrap();
+        let warning = shots.iter().any(|s| s.id == shot.id).then(|| {
+            let free = unused_id(&shots, &shot.id);
+            let warning = format!("Shot {} already exists, so the restored shot is {}", shot.id, free);
+            shot.id = free;
+            warning
+        });
+        let restored_id = shot.id.clone();
+        insert_by_index(&mut shots, shot);
+        let shot = shots.iter().find(|s| s.id == restored_id).cloned().unwrap();
+        drop(shots);
+
+        for group in self.groups.lock().unwrap().iter_mut() {
+            if archived.groups.contains(&group.name) && !group.shot_ids.contains(&shot.id) {
+                group.shot_ids.push(shot.id.clone());
+            }
+        }
+
+        self.record(Change::Bid);
+        Ok((RestoredShot { shot, warning }, archived))
+    }
+
+    fn push_archive_change(&self, change: ArchiveChange) {
+        let mut undo = self.archive_undo.lock().unwrap();
+        if undo.len() == ARCHIVE_HISTORY_LIMIT {
+            undo.remove(0);
+        }
+        undo.push(change);
+        self.archive_redo.lock().unwrap().clear();
+    }
+
+    fn clear_archive_history(&self) {
+        self.archive_undo.lock().unwrap().clear();
+        self.archive_redo.lock().unwrap().clear();
+    }
+
+    /// Merge `merged_ids` into the primary shot and remove them from the bid
+    pub fn merge_shots(&self, primary_id: &str, merged_ids: &[String]) -> Result<ShotData, AppError> {
+        self.ensure_writable()?;
+        if merged_ids.iter().any(|id| id == primary_id) {
+            return Err(AppError::validation(format!("Shot {} cannot be merged into itself", primary_id)));
+        }
+
+        let mut shots = self.shots.lock().unwrap();
+
+        for id in std::iter::once(primary_id).chain(merged_ids.iter().map(|s| s.as_str())) {
+            if !shots.iter().any(|s| s.id == id) {
+                return Err(AppError::not_found(format!("Shot {} not found", id)));
+            }
+        }
+
+        let merged: Vec<ShotData> = shots
+            .iter()
+            .filter(|s| merged_ids.contains(&s.id))
+            .cloned()
+            .collect();
+        shots.retain(|s| !merged_ids.contains(&s.id));
+
+        let primary = shots.iter_mut().find(|s| s.id == primary_id).unwrap();
+
+        // Once any shot has a task breakdown the merged shot keeps one, with
+        // flat hours as an unassigned line
+        let hours_before = primary.estimated_hours;
+        let itemized = !primary.tasks.is_empty() || merged.iter().any(|s| !s.tasks.is_empty());
+        if itemized && primary.tasks.is_empty() {
+            primary.tasks = unassigned_task(primary);
+        }
+
+        for shot in &merged {
+            for vfx_type in &shot.vfx_types {
+                if !primary.vfx_types.contains(vfx_type) {
+                    primary.vfx_types.push(vfx_type.clone());
+                }
+            }
+            for tag in &shot.tags {
+                if !primary.tags.contains(tag) {
+                    primary.tags.push(tag.clone());
+                }
+            }
+
+            if itemized {
+                let tasks = if shot.tasks.is_empty() { unassigned_task(shot) } else { shot.tasks.clone() };
+                primary.tasks.extend(tasks);
+            } else if let Some(hours) = shot.estimated_hours {
+                primary.estimated_hours = Some(primary.estimated_hours.unwrap_or(0.0) + hours);
+            }
+        }
+
+        primary.recalculate_pricing();
+        // Summed hours are no longer the estimate the primary shot had
+        if primary.estimated_hours != hours_before {
+            primary.estimated_by = None;
+        }
+        let result = primary.clone();
+        drop(shots);
+
+        // Point groups at the primary shot instead of the merged ones
+        for group in self.groups.lock().unwrap().iter_mut() {
+            let had_merged = group.shot_ids.iter().any(|id| merged_ids.contains(id));
+            group.shot_ids.retain(|id| !merged_ids.contains(id));
+
+            if had_merged && !group.shot_ids.iter().any(|id| id == primary_id) {
+                group.shot_ids.push(primary_id.to_string());
+            }
+        }
+
+        self.record(Change::Bid);
+        Ok(result)
+    }
+
+    /// Add or replace the group of that name; its shots are kept in scene order
+    pub fn add_group(&self, mut group: ShotGroup) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        let shots = self.shots.lock().unwrap();
+
+        if let Some(missing) = group.shot_ids.iter().find(|id| !shots.iter().any(|s| &s.id == *id)) {
+            return Err(AppError::not_found(format!("Shot {} not found", missing)));
+        }
+
+        let scene_of = |id: &str| shots.iter().find(|s| s.id == id).map(|s| s.scene_number.as_str()).unwrap_or_default();
+        group.shot_ids.sort_by(|a, b| {
+            compare_scene_numbers(scene_of(a), scene_of(b)).then_with(|| compare_scene_numbers(a, b))
+        });
+
+        let mut groups = self.groups.lock().unwrap();
+        groups.retain(|g| g.name != group.name);
+        groups.push(group);
+        drop(groups);
+        drop(shots);
+
+        self.record(Change::Groups);
+        Ok(())
+    }
+
+    pub fn get_groups(&self) -> Vec<ShotGroup> {
+        self.groups.lock().unwrap().clone()
+    }
+
+    pub fn get_meta(&self) -> BidMeta {
+        self.meta.lock().unwrap().clone()
+    }
+
+    /// Replace the bid metadata, moving shots on the old defaults to the new ones
+    ///
+    /// Status, version and adjustments are kept; they change only through
+    /// their own methods. Returns the number of shots whose pricing changed.
+    pub fn set_meta(&self, mut meta: BidMeta) -> usize {
+        let mut shots = self.shots.lock().unwrap();
+        let mut current = self.meta.lock().unwrap();
+        let mut affected = 0;
+
+        meta.status = current.status;
+        meta.version = current.version;
+        meta.adjustments = current.adjustments.clone();
+
+        for shot in shots.iter_mut() {
+            let mut changed = false;
+
+            if shot.contingency_percent == current.default_contingency
+                && shot.contingency_percent != meta.default_contingency
+            {
+                shot.contingency_percent = meta.default_contingency;
+                changed = true;
+            }
+
+            if shot.overhead_percent == current.default_overhead
+                && shot.overhead_percent != meta.default_overhead
+            {
+                shot.overhead_percent = meta.default_overhead;
+                changed = true;
+            }
+
+            if changed {
+                shot.recalculate_pricing();
+                affected += 1;
+            }
+        }
+
+        *current = meta;
+        drop(current);
+        drop(shots);
+
+        self.record(Change::Bid);
+        affected
+    }
+
+    /// Add `items` after the bid's assumptions; returns the whole list
+    pub fn add_assumptions(&self, items: Vec<AssumptionItem>) -> Result<Vec<AssumptionItem>, AppError> {
+        let items = items
+            .into_iter()
+            .map(|mut item| {
+                item.text = assumptions::validate_text(&item.text).map_err(AppError::validation)?;
+                Ok(item)
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        self.modify_assumptions(|list| {
+            list.extend(items);
+            Ok(())
+        })
+    }
+
+    /// Replace the assumption with `item`'s id
+    pub fn update_assumption(&self, mut item: AssumptionItem) -> Result<Vec<AssumptionItem>, AppError> {
+        item.text = assumptions::validate_text(&item.text).map_err(AppError::validation)?;
+        self.modify_assumptions(|list| {
+            let existing = list.iter_mut()
+                .find(|a| a.id == item.id)
+                .ok_or_else(|| AppError::not_found(format!("Assumption {} not found", item.id)))?;
+            *existing = item;
+            Ok(())
+        })
+    }
+
+    pub fn remove_assumption(&self, id: &str) -> Result<Vec<AssumptionItem>, AppError> {
+        self.modify_assumptions(|list| {
+            let before = list.len();
+            list.retain(|a| a.id != id);
+            if list.len() == before {
+                return Err(AppError::not_found(format!("Assumption {} not found", id)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Put the assumptions in the order of `ids`, which must list each once
+    pub fn reorder_assumptions(&self, ids: &[String]) -> Result<Vec<AssumptionItem>, AppError> {
+        self.modify_assumptions(|list| {
+            *list = assumptions::reorder(list, ids).map_err(AppError::validation)?;
+            Ok(())
+        })
+    }
+
+    fn modify_assumptions(&self, change: impl FnOnce(&mut Vec<AssumptionItem>) -> Result<(), AppError>) -> Result<Vec<AssumptionItem>, AppError> {
+        self.ensure_writable()?;
+        let mut meta = self.meta.lock().unwrap();
+        change(&mut meta.assumptions)?;
+        let updated = meta.assumptions.clone();
+        drop(meta);
+
+        self.record(Change::Bid);
+        Ok(updated)
+    }
+
+    /// Move the bid to `status` if the workflow allows it
+    pub fn set_status(&self, status: BidStatus) -> Result<BidMeta, AppError> {
+        self.ensure_writable()?;
+        let mut meta = self.meta.lock().unwrap();
+
+        if !meta.status.can_become(status) {
+            let hint = if meta.status == BidStatus::Approved { "; revise the bid to change it" } else { "" };
+            return Err(AppError::validation(format!(
+                "A {:?} bid cannot be marked {:?}{}",
+                meta.status, status, hint
+            )));
+        }
+
+        meta.status = status;
+        let updated = meta.clone();
+        drop(meta);
+
+        self.record(Change::Bid);
+        Ok(updated)
+    }
+
+    /// Keep the bid as it stands and reopen it as the next version's draft
+    pub fn revise(&self) -> Result<BidMeta, AppError> {
+        self.ensure_writable()?;
+        // Shots before meta, the lock order `set_meta` uses
+        let shots = self.get_shots();
+        let groups = self.get_groups();
+        let mut meta = self.meta.lock().unwrap();
+
+        if meta.status == BidStatus::Draft {
+            return Err(AppError::validation(format!("Version {} is still a draft", meta.version)));
+        }
+
+        self.revisions.lock().unwrap().push(BidRevision {
+            revised_at: chrono::Utc::now().to_rfc3339(),
+            meta: meta.clone(),
+            shots,
+            groups,
+        });
+
+        meta.version += 1;
+        meta.status = BidStatus::Draft;
+        let updated = meta.clone();
+        drop(meta);
+
+        self.record(Change::Bid);
+        Ok(updated)
+    }
+
+    pub fn get_revisions(&self) -> Vec<BidRevision> {
+        self.revisions.lock().unwrap().clone()
+    }
+
+    /// A kept revision, or the bid as it stands for the current version
+    pub fn version(&self, version: u32) -> Option<BidRevision> {
+        let meta = self.get_meta();
+
+        if version == meta.version {
+            return Some(BidRevision {
+                revised_at: chrono::Utc::now().to_rfc3339(),
+                meta,
+                shots: self.get_shots(),
+                groups: self.get_groups(),
+            });
+        }
+
+        self.revisions.lock().unwrap().iter().find(|r| r.meta.version == version).cloned()
+    }
+
+    /// Sum of final prices with the bid's presentation rounding
+    pub fn grand_total(&self) -> f64 {
+        let rounding = self.meta.lock().unwrap().rounding;
+        total_final_price(&self.shots.lock().unwrap(), rounding)
+    }
+
+    /// Empty the bid to start a new one
+    ///
+    /// Shots, groups, the archive and the undo histories go, and the saved versions too
+    /// unless `keep_revisions` is set. Meta keeps only the pricing defaults
+    /// (markups, currency, rounding); client, project, assumptions and
+    /// adjustments go and it returns to a first draft. With nothing left to
+    /// recover, the autosave and journal are removed and the bid counts as
+    /// unchanged. A read-only bid is refused.
+    pub fn clear(&self, keep_revisions: bool) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        self.shots.lock().unwrap().clear();
+        self.groups.lock().unwrap().clear();
+        self.order_history.lock().unwrap().clear();
+        self.archived.lock().unwrap().clear();
+        self.clear_archive_history();
+        *self.extraction.lock().unwrap() = None;
+        *self.pre_extraction.lock().unwrap() = None;
+        *self.bid_id.lock().unwrap() = None;
+        if !keep_revisions {
+            self.revisions.lock().unwrap().clear();
+        }
+
+        let mut meta = self.meta.lock().unwrap();
+        let version = if keep_revisions { meta.version } else { 1 };
+        *meta = BidMeta {
+            default_contingency: meta.default_contingency,
+            default_overhead: meta.default_overhead,
+            currency: std::mem::take(&mut meta.currency),
+            rounding: meta.rounding,
+            version,
+            ..BidMeta::default()
+        };
+        drop(meta);
+
+        *self.access.lock().unwrap() = ReadOnlyStatus::default();
+        *self.source.lock().unwrap() = None;
+        self.audit.lock().unwrap().reset(&[]);
+        self.edits.fetch_add(1, Ordering::SeqCst);
+        self.notify_change();
+        self.clear_autosave().map_err(AppError::io)
+    }
+
+    /// Call `listener` after every edit, load, clear or restore
+    pub fn on_change(&self, listener: ChangeListener) {
+        *self.change_listener.lock().unwrap() = Some(listener);
+    }
+
+    fn notify_change(&self) {
+        let listener = self.change_listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener();
+        }
+    }
+
+    /// Write the bid to `path` (debounced) after every mutation
+    pub fn enable_autosave(&self, path: PathBuf) {
+        *self.autosave_file.lock().unwrap() = Some(path);
+    }
+
+    /// Journal every mutation to `dir` so edits since the last autosave
+    /// survive a crash
+    pub fn enable_journal(&self, dir: PathBuf) -> Result<(), String> {
+        *self.journal.lock().unwrap() = Some(Journal::open(dir)?);
+        Ok(())
+    }
+
+    /// Write the per-shot audit trail to `dir`
+    pub fn enable_audit(&self, dir: PathBuf) {
+        self.audit.lock().unwrap().enable(dir);
+    }
+
+    /// Every audited change to shot `id` in this bid, oldest first
+    pub fn shot_history(&self, id: &str) -> Result<Vec<AuditEntry>, String> {
+        let bid_id = self.bid_id();
+        self.audit.lock().unwrap().history(&bid_id, id)
+    }
+
+    /// The whole audit trail of this bid, for saving with the project
+    pub fn audit_trail(&self) -> Result<Vec<AuditEntry>, String> {
+        let bid_id = self.bid_id();
+        self.audit.lock().unwrap().trail(&bid_id)
+    }
+
+    /// Adopt the audit trail a project was saved with
+    pub fn import_audit(&self, entries: Vec<AuditEntry>) -> Result<(), String> {
+        let bid_id = self.bid_id();
+        self.audit.lock().unwrap().import(&bid_id, entries)
+    }
+
+    pub fn journal_stats(&self) -> JournalStats {
+        match self.journal.lock().unwrap().as_ref() {
+            Some(journal) => journal.stats(self.revision.load(Ordering::SeqCst)),
+            None => JournalStats::default(),
+        }
+    }
+
+    /// Wait for queued journal entries to reach the disk
+    pub fn flush_journal(&self) {
+        if let Some(journal) = self.journal.lock().unwrap().as_ref() {
+            journal.flush();
+        }
+    }
+
+    /// Read the autosave with the journal replayed on top, without applying it
+    pub fn read_autosave(&self) -> Result<Option<BidAutosave>, String> {
+        let path = self.autosave_file.lock().unwrap().clone();
+        let snapshot = match path.filter(|path| path.exists()) {
+            Some(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read autosaved bid: {}", e))?;
+
+                Some(serde_json::from_str(&contents)
+                    .map_err(|e| format!("Failed to parse autosaved bid: {}", e))?)
+            }
+            None => None,
+        };
+
+        let mut recovered = match self.journal.lock().unwrap().as_ref() {
+            Some(journal) => journal::recover(snapshot, journal.dir())?,
+            None => snapshot,
+        };
+
+        // A journaled move updates a shot in place; put it back in position
+        if let Some(autosave) = recovered.as_mut() {
+            restore_order(&mut autosave.shots);
+        }
+        Ok(recovered)
+    }
+
+    /// Replace the bid with the recovered copy, if one exists, and compact
+    /// the journal into a fresh autosave
+    ///
+    /// The recovered bid counts as unsaved. A read-only bid is refused.
+    pub fn restore_autosave(&self) -> Result<Option<BidAutosave>, String> {
+        self.ensure_writable()?;
+        let Some(autosave) = self.read_autosave()? else {
+            return Ok(None);
+        };
+
+        let journal = self.journal.lock().unwrap();
+        *self.shots.lock().unwrap() = autosave.shots.clone();
+        *self.groups.lock().unwrap() = autosave.groups.clone();
+        *self.meta.lock().unwrap() = autosave.meta.clone();
+        *self.revisions.lock().unwrap() = autosave.revisions.clone();
+        *self.archived.lock().unwrap() = autosave.archived.clone();
+        *self.bid_id.lock().unwrap() = autosave.bid_id.clone();
+        self.revision.store(autosave.revision, Ordering::SeqCst);
+        self.order_history.lock().unwrap().clear();
+        self.clear_archive_history();
+        self.audit.lock().unwrap().reset(&autosave.shots);
+
+        if let (Some(journal), Some(path)) = (journal.as_ref(), self.autosave_file.lock().unwrap().clone()) {
+            journal.compact(self.snapshot(), path);
+            if let Some(bid_id) = &autosave.bid_id {
+                journal.prune(bid_id);
+            }
+        }
+        drop(journal);
+        self.access.lock().unwrap().dirty = true;
+        self.notify_change();
+
+        Ok(Some(autosave))
+    }
+
+    /// Delete the autosave file and journal and cancel any pending write
+    pub fn clear_autosave(&self) -> Result<(), String> {
+        self.autosave_generation.fetch_add(1, Ordering::SeqCst);
+
+        if let Some(journal) = self.journal.lock().unwrap().as_ref() {
+            journal.clear();
+        }
+
+        if let Some(path) = self.autosave_file.lock().unwrap().as_ref() {
+            if path.exists() {
+                std::fs::remove_file(path)
+                    .map_err(|e| format!("Failed to remove autosaved bid: {}", e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Journal what a mutation touched, then schedule the autosave
+    fn record(&self, change: Change) {
+        self.access.lock().unwrap().dirty = true;
+        self.edits.fetch_add(1, Ordering::SeqCst);
+        self.audit(&change);
+        self.append_journal(change);
+        self.schedule_autosave();
+        self.notify_change();
+    }
+
+    fn audit(&self, change: &Change) {
+        let result = match change {
+            Change::Shot(id) => {
+                let shot: Vec<ShotData> = self.shots.lock().unwrap().iter().filter(|s| s.id == *id).cloned().collect();
+                self.audit.lock().unwrap().record(&self.bid_id(), &shot, Some(id))
+            }
+            Change::Shots => {
+                let shots = self.get_shots();
+                self.audit.lock().unwrap().record(&self.bid_id(), &shots, None)
+            }
+            Change::Groups => Ok(()),
+            Change::Bid => {
+                self.audit.lock().unwrap().reset(&self.get_shots());
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            log::warn!("Failed to record shot audit: {}", e);
+        }
+    }
+
+    fn append_journal(&self, change: Change) {
+        // Holding the journal lock while reading state keeps entries in
+        // revision order when mutations race
+        let journal = self.journal.lock().unwrap();
+        let Some(journal) = journal.as_ref() else {
+            return;
+        };
+
+        let bid_id = self.bid_id();
+        let change = if journal.is_current(&bid_id) { change } else { Change::Bid };
+
+        let op = match change {
+            Change::Shot(id) => match self.shots.lock().unwrap().iter().find(|s| s.id == id) {
+                Some(shot) => JournalOp::UpsertShot(Box::new(shot.clone())),
+                None => return,
+            },
+            Change::Shots => JournalOp::SetShots(self.get_shots()),
+            Change::Groups => JournalOp::SetGroups(self.get_groups()),
+            Change::Bid => JournalOp::Reset {
+                shots: self.get_shots(),
+                groups: self.get_groups(),
+                meta: Box::new(self.get_meta()),
+                revisions: self.get_revisions(),
+                archived: self.get_archived_shots(),
+            },
+        };
+
+        let entry = JournalEntry {
+            revision: self.revision.fetch_add(1, Ordering::SeqCst) + 1,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            op,
+        };
+        journal.append(&bid_id, &entry);
+
+        if journal.should_compact() {
+            match self.autosave_file.lock().unwrap().clone() {
+                Some(path) => journal.compact(self.snapshot(), path),
+                None => log::warn!("Bid journal is over its size limit but autosave is off"),
+            }
+        }
+    }
+
+    /// The bid as an autosave at the current revision
+    fn snapshot(&self) -> BidAutosave {
+        // Read the revision first: the state may run ahead of it, never behind
+        let revision = self.revision.load(Ordering::SeqCst);
+
+        BidAutosave {
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            shots: self.get_shots(),
+            groups: self.get_groups(),
+            meta: self.get_meta(),
+            revisions: self.get_revisions(),
+            archived: self.get_archived_shots(),
+            bid_id: Some(self.bid_id()),
+            revision,
+        }
+    }
+
+    /// Snapshot the bid now and write it once mutations settle
+    fn schedule_autosave(&self) {
+        let Some(path) = self.autosave_file.lock().unwrap().clone() else {
+            return;
+        };
+
+        let autosave = self.snapshot();
+        let generation = self.autosave_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.autosave_generation.clone();
+
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(AUTOSAVE_DEBOUNCE).await;
+
+            if current.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            if let Err(e) = write_autosave(&path, &autosave) {
+                log::warn!("Failed to autosave bid: {}", e);
+            }
+        });
+    }
+}
+
+/// Give the shots evenly spaced indices in their current order
+fn renumber(shots: &mut [ShotData]) {
+    for (i, shot) in shots.iter_mut().enumerate() {
+        shot.sort_index = (i as u32 + 1).saturating_mul(SORT_INDEX_GAP);
+    }
+}
+
+/// Whether the shot is in `scene`, or any shot without one
+fn in_scene(shot: &ShotData, scene: Option<&str>) -> bool {
+    scene.is_none_or(|scene| shot.scene_number.trim().eq_ignore_ascii_case(scene))
+}
+
+/// Whether the shot is one of `shot_ids`, or any shot without them
+fn in_scope(shot: &ShotData, shot_ids: Option<&[String]>) -> bool {
+    shot_ids.is_none_or(|ids| ids.contains(&shot.id))
+}
+
+/// Scale a shot's rates by `factor` and reprice it; shots priced without a
+/// rate have their cost and price scaled directly
+fn scale_price(shot: &mut ShotData, factor: f64) {
+    if let Some(rate) = shot.rate_per_hour {
+        shot.rate_per_hour = Some(rate * factor);
+        for task in &mut shot.tasks {
+            task.rate_override = task.rate_override.map(|r| r * factor);
+        }
+        shot.recalculate_pricing();
+    } else {
+        shot.estimated_cost = shot.estimated_cost.map(|c| c * factor);
+        shot.final_price = shot.final_price.map(|p| p * factor);
+    }
+}
+
+/// Whether two paths name the same file, even if it doesn't exist yet
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Sort shots read from disk by index
+///
+/// Bids saved before shots had an index are all 0 and keep their stored
+/// order; any duplicates are respaced.
+fn restore_order(shots: &mut [ShotData]) {
+    shots.sort_by_key(|s| s.sort_index);
+    if shots.windows(2).any(|w| w[0].sort_index == w[1].sort_index) || shots.first().is_some_and(|s| s.sort_index == 0) {
+        renumber(shots);
+    }
+}
+
+/// Insert `shot` where its `sort_index` places it, giving it a free index
+/// between its neighbours or renumbering when there is no room
+fn insert_by_index(shots: &mut Vec<ShotData>, mut shot: ShotData) {
+    let at = shots.partition_point(|s| s.sort_index <= shot.sort_index);
+    let prev = if at == 0 { 0 } else { shots[at - 1].sort_index };
+    let next = shots.get(at).map_or(prev.saturating_add(2 * SORT_INDEX_GAP), |s| s.sort_index);
+
+    if shot.sort_index > prev && shot.sort_index < next {
+        shots.insert(at, shot);
+    } else if next.saturating_sub(prev) < 2 {
+        shots.insert(at, shot);
+        renumber(shots);
+    } else {
+        shot.sort_index = prev + (next - prev) / 2;
+        shots.insert(at, shot);
+    }
+}
+
+/// `id` with a `-restored` suffix no shot in `shots` has
+fn unused_id(shots: &[ShotData], id: &str) -> String {
+    let taken = |candidate: &str| shots.iter().any(|s| s.id == candidate);
+    let mut candidate = format!("{}-restored", id);
+    let mut n = 2;
+    while taken(&candidate) {
+        candidate = format!("{}-restored-{}", id, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// A shot's flat hours as a single task line, or none without hours
+fn unassigned_task(shot: &ShotData) -> Vec<TaskLine> {
+    shot.estimated_hours
+        .map(|hours| TaskLine {
+            department: UNASSIGNED_DEPARTMENT.to_string(),
+            hours,
+            ..Default::default()
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Append `shot` after the last one, returning whether every shot had to be
+/// renumbered to make room
+fn push_last(shots: &mut Vec<ShotData>, mut shot: ShotData) -> bool {
+    let last = shots.last().map_or(0, |s| s.sort_index);
+    match last.checked_add(SORT_INDEX_GAP) {
+        Some(index) => {
+            shot.sort_index = index;
+            shots.push(shot);
+            false
+        }
+        None => {
+            shots.push(shot);
+            renumber(shots);
+            true
+        }
+    }
+}
+
+/// Write `autosave` to `path` through a temporary file, so a crash mid-write
+/// leaves the previous copy intact
+pub(super) fn write_autosave(path: &Path, autosave: &BidAutosave) -> Result<(), String> {
+    let json = serde_json::to_string(autosave).map_err(|e| e.to_string())?;
+    tempfiles::write_atomic(path, json.as_bytes()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::{ShotFilter, MAX_TAG_LENGTH};
+
+    fn shot(id: &str) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            scene_number: "1".to_string(),
+            description: format!("Shot {}", id),
+            complexity: "Simple".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn state_with(count: usize) -> BidState {
+        let state = BidState::default();
+        state.set_shots((0..count).map(|i| shot(&i.to_string())).collect()).unwrap();
+        state
+    }
+
+    #[test]
+    fn test_tags_are_normalized_and_counted() {
+        let state = state_with(3);
+        let ids = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let updated = state.add_tags(&ids(&["1", "0"]), &ids(&["  Awaiting-Plate ", "awaiting-plate", "phase-2", ""])).unwrap();
+        assert_eq!(updated.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), vec!["1", "0"]);
+        assert_eq!(updated[0].tags, vec!["awaiting-plate", "phase-2"]);
+        state.add_tags(&ids(&["1"]), &ids(&["PHASE-2", "needs-client-review"])).unwrap();
+        assert_eq!(state.get_shots()[1].tags, vec!["awaiting-plate", "phase-2", "needs-client-review"]);
+
+        assert_eq!(state.all_tags(), vec![
+            TagCount { tag: "awaiting-plate".to_string(), count: 2 },
+            TagCount { tag: "phase-2".to_string(), count: 2 },
+            TagCount { tag: "needs-client-review".to_string(), count: 1 },
+        ]);
+
+        // Edits from the shot form don't drop tags
+        let edit = ShotData { description: "new".to_string(), ..shot("0") };
+        assert_eq!(state.update_shot("0".to_string(), edit).unwrap().tags, vec!["awaiting-plate", "phase-2"]);
+
+        state.remove_tags(&ids(&["0"]), &ids(&["Phase-2"])).unwrap();
+        assert_eq!(state.get_shots()[0].tags, vec!["awaiting-plate"]);
+
+        let filter = ShotFilter { tags_any: ids(&["phase-2", "missing"]), tags_all: ids(&["awaiting-plate"]) };
+        let matched: Vec<_> = state.get_shots().into_iter().filter(|s| filter.matches(s)).map(|s| s.id).collect();
+        assert_eq!(matched, vec!["1"]);
+
+        let long = "x".repeat(MAX_TAG_LENGTH + 1);
+        assert!(state.add_tags(&ids(&["2"]), &[long]).is_err());
+        assert!(matches!(state.add_tags(&ids(&["9"]), &ids(&["a"])), Err(AppError::NotFound { .. })));
+        assert!(state.add_tags(&ids(&["2"]), &ids(&[" "])).is_err());
+        assert!(state.get_shots()[2].tags.is_empty());
+    }
+
+    #[test]
+    fn test_table_estimates_until_edited_by_hand() {
+        let state = BidState::default();
+        state.set_shots(vec![
+            ShotData { complexity: "Medium".to_string(), vfx_types: vec!["greenscreen".to_string()], rate_per_hour: Some(100.0), ..shot("0") },
+            ShotData { complexity: "High".to_string(), rate_per_hour: Some(100.0), ..shot("1") },
+            ShotData { complexity: "Medium".to_string(), estimated_hours: Some(5.0), ..shot("2") },
+            shot("3"),
+        ]).unwrap();
+
+        let filled = state.estimate_missing_hours(&estimation::default_hour_ranges(), EstimateStrategy::Low);
+        assert_eq!(filled, 2);
+        let shots = state.get_shots();
+        assert_eq!((shots[0].estimated_hours, shots[0].final_price, shots[0].estimated_by), (Some(24.0), Some(2400.0), Some(EstimatedBy::Table)));
+        assert_eq!((shots[1].estimated_hours, shots[1].estimated_by), (Some(40.0), Some(EstimatedBy::Table)));
+        assert_eq!((shots[2].estimated_hours, shots[2].estimated_by), (Some(5.0), None));
+        // "Simple" has no range
+        assert_eq!(shots[3].estimated_hours, None);
+
+        // Editing something else keeps the provenance, editing the hours drops it
+        let renamed = ShotData { description: "Sky".to_string(), estimated_by: None, ..shots[0].clone() };
+        assert_eq!(state.update_shot("0".to_string(), renamed).unwrap().estimated_by, Some(EstimatedBy::Table));
+        let retimed = ShotData { estimated_hours: Some(30.0), ..shots[1].clone() };
+        assert_eq!(state.update_shot("1".to_string(), retimed).unwrap().estimated_by, None);
+    }
+
+    #[test]
+    fn test_task_edits_are_atomic() {
+        let state = state_with(3);
+        let tasks = vec![
+            TaskLine { department: "roto".to_string(), hours: 8.0, ..Default::default() },
+            TaskLine { department: "comp".to_string(), hours: 16.0, rate_override: Some(150.0), ..Default::default() },
+        ];
+
+        // One unknown id leaves every shot untouched
+        let ids = vec!["0".to_string(), "missing".to_string()];
+        assert!(state.set_tasks(&ids, &tasks).is_err());
+        assert!(state.get_shots().iter().all(|s| s.tasks.is_empty()));
+
+        let ids = vec!["0".to_string(), "2".to_string()];
+        let updated = state.set_tasks(&ids, &tasks).unwrap();
+        assert_eq!(updated.len(), 2);
+        assert!(updated.iter().all(|s| s.estimated_hours == Some(24.0)));
+
+        // The flat fields can't be edited while the shot has tasks
+        let edit = ShotData { estimated_hours: Some(2.0), rate_per_hour: Some(100.0), ..shot("0") };
+        let edited = state.update_shot("0".to_string(), edit).unwrap();
+        assert_eq!(edited.tasks, tasks);
+        assert_eq!(edited.estimated_hours, Some(24.0));
+        assert_eq!(edited.estimated_cost, Some(800.0 + 2400.0));
+
+        // Clearing the tasks keeps the last total as flat hours
+        let flat = state.set_shot_tasks("0", Vec::new()).unwrap();
+        assert!(flat.tasks.is_empty());
+        assert_eq!(flat.estimated_hours, Some(24.0));
+    }
+
+    #[test]
+    fn test_merge_keeps_task_breakdown() {
+        let state = BidState::default();
+        let itemized = ShotData {
+            tasks: vec![TaskLine { department: "comp".to_string(), hours: 16.0, ..Default::default() }],
+            rate_per_hour: Some(100.0),
+            ..shot("A")
+        };
+        let flat = ShotData { estimated_hours: Some(4.0), ..shot("B") };
+        state.set_shots(vec![itemized, flat]).unwrap();
+        state.set_shot_tasks("A", state.get_shots()[0].tasks.clone()).unwrap();
+
+        let merged = state.merge_shots("A", &["B".to_string()]).unwrap();
+        let departments: Vec<_> = merged.tasks.iter().map(|t| (t.department.as_str(), t.hours)).collect();
+        assert_eq!(departments, vec![("comp", 16.0), (UNASSIGNED_DEPARTMENT, 4.0)]);
+        assert_eq!(merged.estimated_hours, Some(20.0));
+    }
+
+    #[test]
+    fn test_shots_page_first_page() {
+        let state = state_with(10);
+        let (page, total) = state.get_shots_page(0, 4);
+
+        assert_eq!(total, 10);
+        let ids: Vec<_> = page.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_shots_page_last_partial_page() {
+        let state = state_with(10);
+        let (page, total) = state.get_shots_page(8, 4);
+
+        assert_eq!(total, 10);
+        let ids: Vec<_> = page.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["8", "9"]);
+    }
+
+    #[test]
+    fn test_shots_page_out_of_range_offset() {
+        let state = state_with(10);
+        let (page, total) = state.get_shots_page(25, 4);
+
+        assert_eq!(total, 10);
+        assert!(page.is_empty());
+
+        let (page, _) = state.get_shots_page(0, usize::MAX);
+        assert_eq!(page.len(), 10);
+    }
+
+    #[test]
+    fn test_update_shot_keeps_frame_count_in_sync() {
+        let state = state_with(1);
+        let mut updates = shot("0");
+        updates.frame_in = Some(1001);
+        updates.frame_out = Some(1100);
+        updates.frame_count = Some(5);
+
+        let updated = state.update_shot("0".to_string(), updates).unwrap();
+
+        assert_eq!(updated.frame_count, Some(100));
+        assert_eq!(state.get_shots()[0].frame_count, Some(100));
+    }
+
+    #[test]
+    fn test_update_shot_keeps_the_id_it_was_called_with() {
+        let state = state_with(2);
+        let mut updates = shot("0");
+        updates.description = "Matching".to_string();
+        assert_eq!(state.update_shot("0".to_string(), updates).unwrap().id, "0");
+
+        // A stray id is corrected rather than renaming the shot
+        let mut updates = shot("");
+        updates.description = "Blank id".to_string();
+        let updated = state.update_shot("0".to_string(), updates).unwrap();
+        assert_eq!((updated.id.as_str(), updated.description.as_str()), ("0", "Blank id"));
+        let ids: Vec<String> = state.get_shots().iter().map(|s| s.id.clone()).collect();
+        assert_eq!(ids, ["0", "1"]);
+
+        // Another shot's id would leave two shots "1"
+        let mut updates = shot("1");
+        updates.description = "Collision".to_string();
+        let error = state.update_shot("0".to_string(), updates).unwrap_err();
+        assert!(matches!(error, AppError::Validation { .. }));
+        assert!(error.to_string().contains("shot 1"));
+        let shots = state.get_shots();
+        assert_eq!((shots[0].description.as_str(), shots[1].description.as_str()), ("Blank id", "Shot 1"));
+    }
+
+    #[test]
+    fn test_update_shot_audits_each_changed_field() {
+        let state = state_with(2);
+        let mut updates = shot("0");
+        updates.estimated_hours = Some(45.0);
+        updates.complexity = "Hero".to_string();
+        state.update_shot("0".to_string(), updates).unwrap();
+
+        let history = state.shot_history("0").unwrap();
+        let changes: Vec<_> = history.iter().map(|e| (e.field.as_str(), e.old_value.clone(), e.new_value.clone())).collect();
+        assert_eq!(changes, vec![
+            ("complexity", serde_json::json!("Simple"), serde_json::json!("Hero")),
+            ("estimated_hours", serde_json::Value::Null, serde_json::json!(45.0)),
+        ]);
+        assert!(state.shot_history("1").unwrap().is_empty());
+
+        // Loading a different bid starts a fresh trail
+        state.set_shots(vec![shot("0")]).unwrap();
+        assert!(state.shot_history("0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_estimate_hours_from_duration() {
+        let state = BidState::default();
+        let mut timed = shot("timed");
+        timed.frame_count = Some(48);
+        timed.rate_per_hour = Some(100.0);
+        timed.contingency_percent = 10.0;
+
+        let mut already_estimated = shot("estimated");
+        already_estimated.frame_count = Some(48);
+        already_estimated.estimated_hours = Some(3.0);
+
+        state.set_shots(vec![timed, already_estimated, shot("untimed")]).unwrap();
+
+        let factors = HashMap::from([("simple".to_string(), 5.0)]);
+        assert_eq!(state.estimate_hours_from_duration(&factors), 1);
+
+        let shots = state.get_shots();
+        assert_eq!(shots[0].estimated_hours, Some(10.0));
+        assert_eq!(shots[0].estimated_cost, Some(1000.0));
+        assert!((shots[0].final_price.unwrap() - 1100.0).abs() < 1e-9);
+        assert_eq!(shots[1].estimated_hours, Some(3.0));
+        assert_eq!(shots[2].estimated_hours, None);
+    }
+
+    #[test]
+    fn test_assumptions_are_edited_in_place() {
+        use crate::assumptions::AssumptionCategory;
+
+        let state = BidState::default();
+        let list = state.add_assumptions(vec![
+            AssumptionItem::new("Plates delivered as EXR.", AssumptionCategory::Assumption),
+            AssumptionItem::new(" Stereo conversion. ", AssumptionCategory::Exclusion),
+        ]).unwrap();
+        assert_eq!(list[1].text, "Stereo conversion.");
+        assert!(state.read_only_status().dirty);
+
+        let mut internal = list[0].clone();
+        internal.included_in_export = false;
+        state.update_assumption(internal).unwrap();
+        let reordered = state.reorder_assumptions(&[list[1].id.clone(), list[0].id.clone()]).unwrap();
+        assert_eq!(reordered[0].category, AssumptionCategory::Exclusion);
+        assert!(!reordered[1].included_in_export);
+
+        assert!(state.add_assumptions(vec![AssumptionItem::new("  ", AssumptionCategory::Note)]).is_err());
+        assert_eq!(state.remove_assumption(&list[0].id).unwrap().len(), 1);
+        assert!(matches!(state.remove_assumption(&list[0].id), Err(AppError::NotFound { .. })));
+
+        state.set_read_only(true, false).unwrap();
+        assert!(state.remove_assumption(&list[1].id).is_err());
+        assert_eq!(state.get_meta().assumptions.len(), 1);
+    }
+
+    #[test]
+    fn test_meta_change_moves_shots_on_old_default() {
+        let state = BidState::default();
+        let defaults = state.get_meta();
+
+        let mut on_default = shot("default");
+        on_default.contingency_percent = defaults.default_contingency;
+        on_default.overhead_percent = defaults.default_overhead;
+        on_default.estimated_hours = Some(10.0);
+        on_default.rate_per_hour = Some(100.0);
+
+        let mut overridden = shot("overridden");
+        overridden.contingency_percent = 40.0;
+        overridden.overhead_percent = defaults.default_overhead;
+
+        state.set_shots(vec![on_default, overridden]).unwrap();
+
+        let affected = state.set_meta(BidMeta {
+            default_contingency: 20.0,
+            default_overhead: 0.0,
+            ..defaults
+        });
+
+        let shots = state.get_shots();
+        assert_eq!(affected, 2);
+        assert_eq!(shots[0].contingency_percent, 20.0);
+        assert_eq!(shots[0].overhead_percent, 0.0);
+        assert!((shots[0].final_price.unwrap() - 1200.0).abs() < 1e-9);
+        assert_eq!(shots[1].contingency_percent, 40.0);
+        assert_eq!(shots[1].overhead_percent, 0.0);
+        assert!((state.grand_total() - 1200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_added_shot_inherits_defaults() {
+        let state = BidState::default();
+        state.add_shot(shot("new"));
+
+        let meta = state.get_meta();
+        let added = &state.get_shots()[0];
+        assert_eq!(added.contingency_percent, meta.default_contingency);
+        assert_eq!(added.overhead_percent, meta.default_overhead);
+    }
+
+    fn merge_fixture() -> BidState {
+        let state = BidState::default();
+
+        let mut primary = shot("p");
+        primary.vfx_types = vec!["Comp".to_string(), "Roto".to_string()];
+        primary.estimated_hours = Some(4.0);
+        primary.estimated_by = Some(EstimatedBy::Table);
+        primary.rate_per_hour = Some(100.0);
+
+        let mut first = shot("a");
+        first.vfx_types = vec!["Roto".to_string(), "CG".to_string()];
+        first.estimated_hours = Some(6.0);
+
+        let mut second = shot("b");
+        second.vfx_types = vec!["FX".to_string()];
+
+        state.set_shots(vec![primary, first, second, shot("other")]).unwrap();
+        state
+    }
+
+    #[test]
+    fn test_merge_unions_vfx_types_and_sums_hours() {
+        let state = merge_fixture();
+
+        let merged = state
+            .merge_shots("p", &["a".to_string(), "b".to_string()])
+            .unwrap();
+
+        assert_eq!(merged.vfx_types, vec!["Comp", "Roto", "CG", "FX"]);
+        assert_eq!(merged.estimated_hours, Some(10.0));
+        assert_eq!(merged.estimated_cost, Some(1000.0));
+        assert_eq!(merged.estimated_by, None);
+
+        let ids: Vec<_> = state.get_shots().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["p", "other"]);
+    }
+
+    #[test]
+    fn test_merge_fixes_group_membership() {
+        let state = merge_fixture();
+        state.add_group(ShotGroup {
+            name: "merged only".to_string(),
+            shot_ids: vec!["a".to_string(), "other".to_string()],
+            discount_percent: None,
+        }).unwrap();
+        state.add_group(ShotGroup {
+            name: "with primary".to_string(),
+            shot_ids: vec!["p".to_string(), "b".to_string()],
+            discount_percent: None,
+        }).unwrap();
+
+        state.merge_shots("p", &["a".to_string(), "b".to_string()]).unwrap();
+
+        let groups = state.get_groups();
+        assert_eq!(groups[0].shot_ids, vec!["other", "p"]);
+        assert_eq!(groups[1].shot_ids, vec!["p"]);
+    }
+
+    #[test]
+    fn test_group_shots_follow_scene_order() {
+        let state = BidState::default();
+        let in_scene = |id: &str, scene: &str| ShotData { scene_number: scene.to_string(), ..shot(id) };
+        state.set_shots(vec![in_scene("c", "10"), in_scene("a", "2"), in_scene("b", "1B"), in_scene("d", "2")]).unwrap();
+
+        state.add_group(ShotGroup {
+            name: "Act one".to_string(),
+            shot_ids: vec!["c".to_string(), "d".to_string(), "a".to_string(), "b".to_string()],
+            discount_percent: None,
+        }).unwrap();
+
+        assert_eq!(state.get_groups()[0].shot_ids, vec!["b", "a", "d", "c"]);
+    }
+
+    #[test]
+    fn test_merge_rejects_missing_or_self_merge() {
+        let state = merge_fixture();
+
+        assert!(state.merge_shots("p", &["p".to_string()]).is_err());
+        assert!(state.merge_shots("p", &["missing".to_string()]).is_err());
+        assert!(state.merge_shots("missing", &["a".to_string()]).is_err());
+        assert_eq!(state.get_shots().len(), 4);
+    }
+
+    fn autosave_path() -> PathBuf {
+        std::env::temp_dir().join(format!("bid_autosave-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_mutation_writes_autosave() {
+        let path = autosave_path();
+        let state = BidState::default();
+        state.enable_autosave(path.clone());
+
+        state.set_shots(vec![shot("a"), shot("b")]).unwrap();
+        state.update_shot("b".to_string(), shot("b")).unwrap();
+        assert!(!path.exists(), "autosave should be debounced");
+
+        tokio::time::sleep(AUTOSAVE_DEBOUNCE * 3).await;
+
+        let saved = state.read_autosave().unwrap().unwrap();
+        assert_eq!(saved.shots.len(), 2);
+
+        state.clear_autosave().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_autosave_restore_round_trip() {
+        let path = autosave_path();
+        let original = merge_fixture();
+        original.enable_autosave(path.clone());
+        original.add_group(ShotGroup {
+            name: "roto".to_string(),
+            shot_ids: vec!["a".to_string()],
+            discount_percent: Some(5.0),
+        }).unwrap();
+
+        tokio::time::sleep(AUTOSAVE_DEBOUNCE * 3).await;
+
+        let restored = BidState::default();
+        restored.enable_autosave(path.clone());
+        restored.restore_autosave().unwrap().unwrap();
+
+        let ids: Vec<_> = restored.get_shots().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["p", "a", "b", "other"]);
+        assert_eq!(restored.get_shots()[1].estimated_hours, Some(6.0));
+        assert_eq!(restored.get_groups()[0].name, "roto");
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_shot_without_frame_fields_deserializes() {
+        let json = serde_json::json!({
+            "id": "1",
+            "scene_number": "1",
+            "description": "Legacy shot",
+            "vfx_types": [],
+            "complexity": "Simple",
+            "estimated_hours": null,
+            "rate_per_hour": null,
+            "estimated_cost": null,
+            "contingency_percent": 0.0,
+            "overhead_percent": 0.0,
+            "final_price": null
+        });
+
+        let shot: ShotData = serde_json::from_value(json).unwrap();
+        assert_eq!(shot.frame_count, None);
+        assert_eq!(shot.fps, None);
+    }
+
+    #[test]
+    fn test_provisional_shots_reconciled_with_final_list() {
+        let state = state_with(2);
+        state.begin_extraction();
+        assert!(state.get_shots().is_empty());
+
+        assert!(state.add_provisional_shot(shot("SH010")).is_some());
+        assert!(state.add_provisional_shot(shot("SH020")).is_some());
+        assert!(state.get_shots().iter().all(|s| s.provisional));
+
+        // The final list drops SH020 and adds SH030
+        state.set_shots(vec![shot("SH010"), shot("SH030")]).unwrap();
+        let reconciled = state.finish_extraction();
+
+        assert_eq!(reconciled, ShotsReconciled {
+            confirmed: vec!["SH010".to_string()],
+            added: vec!["SH030".to_string()],
+            removed: vec![],
+        });
+        let ids: Vec<_> = state.get_shots().iter().map(|s| s.id.clone()).collect();
+        assert_eq!(ids, vec!["SH010", "SH030"]);
+        assert!(state.get_shots().iter().all(|s| !s.provisional));
+    }
+
+    #[test]
+    fn test_duplicate_and_late_shot_events_ignored() {
+        let state = BidState::default();
+
+        // No run in progress
+        assert!(state.add_provisional_shot(shot("SH010")).is_none());
+
+        state.begin_extraction();
+        assert!(state.add_provisional_shot(shot("SH010")).is_some());
+        assert!(state.add_provisional_shot(shot("SH010")).is_none());
+
+        // A corrected copy replaces the provisional row in place
+        let mut corrected = shot("SH010");
+        corrected.description = "Corrected".to_string();
+        assert!(state.add_provisional_shot(corrected).is_some());
+        assert_eq!(state.get_shots().len(), 1);
+        assert_eq!(state.get_shots()[0].description, "Corrected");
+
+        // An event arriving after the final list must not overwrite it
+        state.set_shots(vec![shot("SH010")]).unwrap();
+        let late = ShotData { description: "Late".to_string(), ..shot("SH010") };
+        assert!(state.add_provisional_shot(late).is_none());
+        assert!(!state.get_shots()[0].provisional);
+
+        state.finish_extraction();
+        assert!(state.add_provisional_shot(shot("SH040")).is_none());
+        assert_eq!(state.get_shots().len(), 1);
+    }
+
+    #[test]
+    fn test_every_change_reaches_the_listener() {
+        let state = state_with(2);
+        let changes = Arc::new(AtomicU64::new(0));
+        let counter = changes.clone();
+        state.on_change(Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        let count = || changes.load(Ordering::SeqCst);
+
+        state.update_shot("0".to_string(), shot("0")).unwrap();
+        assert_eq!(count(), 1);
+        state.set_shots(vec![shot("SH010")]).unwrap();
+        assert_eq!(count(), 2);
+        state.replace_bid("loaded".to_string(), vec![shot("SH020")], Vec::new(), BidMeta::default(), Vec::new(), ReadOnlyStatus::default());
+        assert_eq!(count(), 3);
+        state.clear(false).unwrap();
+        assert_eq!(count(), 4);
+    }
+
+    #[test]
+    fn test_failed_run_removes_provisional_shots() {
+        let state = BidState::default();
+        state.begin_extraction();
+        state.add_provisional_shot(shot("SH010"));
+
+        let reconciled = state.abort_extraction();
+
+        assert_eq!(reconciled.removed, vec!["SH010"]);
+        assert!(state.get_shots().is_empty());
+    }
+
+    #[test]
+    fn test_failed_run_puts_the_open_bid_back() {
+        let state = state_with(2);
+        state.set_meta(BidMeta { client_name: Some("Studio".to_string()), ..state.get_meta() });
+        let bid_id = state.bid_id();
+        let edits = state.edits.load(Ordering::SeqCst);
+
+        state.begin_extraction();
+        assert!(state.get_shots().is_empty());
+        state.add_provisional_shot(shot("SH010"));
+        // Nothing about the run reaches the autosave or journal
+        assert_eq!(state.edits.load(Ordering::SeqCst), edits);
+
+        let reconciled = state.abort_extraction();
+        assert_eq!(reconciled.removed, vec!["SH010"]);
+        let ids: Vec<_> = state.get_shots().iter().map(|s| s.id.clone()).collect();
+        assert_eq!(ids, vec!["0", "1"]);
+        assert_eq!(state.get_meta().client_name.as_deref(), Some("Studio"));
+        assert_eq!(state.bid_id(), bid_id);
+        assert!(state.add_provisional_shot(shot("SH020")).is_none());
+    }
+
+    #[test]
+    fn test_fit_to_budget_dry_run_leaves_bid_unchanged() {
+        let state = BidState::default();
+        let mut priced = shot("SH010");
+        priced.estimated_hours = Some(100.0);
+        priced.rate_per_hour = Some(100.0);
+        priced.recalculate_pricing();
+        state.set_shots(vec![priced]).unwrap();
+
+        let preview = state.fit_to_budget(5000.0, &BudgetStrategy::Uniform, &HashMap::new(), true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(state.grand_total(), 10000.0);
+
+        let applied = state.fit_to_budget(5000.0, &BudgetStrategy::Uniform, &HashMap::new(), false).unwrap();
+        assert_eq!(applied.new_total, preview.new_total);
+        assert_eq!(state.grand_total(), 5000.0);
+    }
+
+    #[test]
+    fn test_lump_adjustment_survives_edits_and_comes_back_out() {
+        let state = BidState::default();
+        let shots: Vec<ShotData> = ["SH010", "SH020", "SH030"]
+            .iter()
+            .map(|id| {
+                let mut priced = shot(id);
+                priced.estimated_hours = Some(10.0);
+                priced.rate_per_hour = Some(100.0);
+                priced.contingency_percent = 15.0;
+                priced.overhead_percent = 10.0;
+                priced.recalculate_pricing();
+                priced
+            })
+            .collect();
+        state.set_shots(shots.clone()).unwrap();
+        let cents = |state: &BidState| -> Vec<i64> {
+            state.get_shots().iter().map(|s| adjustments::to_cents(s.final_price.unwrap())).collect()
+        };
+
+        let preview = state.apply_adjustment(-100.0, AdjustmentScope::AllShots, SplitMethod::Even, None, true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(cents(&state), [125_000, 125_000, 125_000]);
+        assert!(state.get_meta().adjustments.is_empty());
+
+        let report = state
+            .apply_adjustment(-100.0, AdjustmentScope::AllShots, SplitMethod::Even, Some("Negotiated discount".to_string()), false)
+            .unwrap();
+        assert_eq!(cents(&state), [121_666, 121_667, 121_667]);
+        assert_eq!(state.get_meta().adjustments.len(), 1);
+
+        // Form edits and markup changes reprice on top of the adjustment
+        state.update_shot("SH010".to_string(), shots[0].clone()).unwrap();
+        state.set_meta(BidMeta { default_contingency: 20.0, adjustments: Vec::new(), ..state.get_meta() });
+        assert_eq!(state.get_meta().adjustments.len(), 1);
+        assert_eq!(cents(&state), [126_666, 126_667, 126_667]);
+
+        state.delete_shot("SH030", None).unwrap();
+        state.remove_adjustment(&report.adjustment.id).unwrap();
+        state.restore_shot("SH030").unwrap();
+        assert_eq!(cents(&state), [130_000, 130_000, 130_000]);
+        assert!(state.get_shots().iter().all(|s| s.adjustment == 0.0));
+        assert!(state.get_meta().adjustments.is_empty());
+        assert!(matches!(state.remove_adjustment(&report.adjustment.id), Err(AppError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_revise_keeps_approved_version() {
+        let state = state_with(2);
+        assert!(state.set_status(BidStatus::Approved).is_err());
+        state.set_status(BidStatus::Sent).unwrap();
+        state.set_status(BidStatus::Approved).unwrap();
+
+        // Approved bids reopen only through revise, and meta edits can't skip it
+        assert!(state.set_status(BidStatus::Draft).is_err());
+        state.set_meta(BidMeta { status: BidStatus::Draft, version: 9, ..state.get_meta() });
+        assert_eq!(state.get_meta().status, BidStatus::Approved);
+
+        let meta = state.revise().unwrap();
+        assert_eq!((meta.status, meta.version), (BidStatus::Draft, 2));
+        assert!(state.revise().is_err());
+
+        state.add_shot(shot("new"));
+        let approved = state.version(1).unwrap();
+        assert_eq!(approved.meta.status, BidStatus::Approved);
+        assert_eq!(approved.shots.len(), 2);
+        assert_eq!(state.version(2).unwrap().shots.len(), 3);
+        assert!(state.version(3).is_none());
+    }
+
+    fn ids(shots: &[ShotData]) -> Vec<&str> {
+        shots.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_move_shot_renumbers_only_the_moved_shot() {
+        let state = state_with(4);
+        let before: Vec<u32> = state.get_shots().iter().map(|s| s.sort_index).collect();
+        assert_eq!(before, vec![1024, 2048, 3072, 4096]);
+
+        let shots = state.move_shot("3", Some("1")).unwrap();
+        assert_eq!(ids(&shots), vec!["0", "3", "1", "2"]);
+        assert_eq!(shots[1].sort_index, 1536);
+        assert_eq!([shots[0].sort_index, shots[2].sort_index, shots[3].sort_index], [1024, 2048, 3072]);
+
+        assert_eq!(ids(&state.move_shot("0", None).unwrap()), vec!["3", "1", "2", "0"]);
+        assert_eq!(ids(&state.move_shot("2", Some("3")).unwrap()), vec!["2", "3", "1", "0"]);
+
+        // Edits from the shot form keep the position
+        let edited = ShotData { sort_index: 0, ..shot("1") };
+        state.update_shot("1".to_string(), edited).unwrap();
+        assert_eq!(ids(&state.get_shots()), vec!["2", "3", "1", "0"]);
+
+        assert!(state.move_shot("1", Some("1")).is_err());
+        assert!(state.move_shot("9", None).is_err());
+        assert!(state.move_shot("1", Some("9")).is_err());
+    }
+
+    #[test]
+    fn test_move_shot_respaces_when_gap_runs_out() {
+        let state = state_with(3);
+        // Repeatedly moving to the front halves the gap until it runs out
+        for i in 0..12 {
+            let id = if i % 2 == 0 { "2" } else { "1" };
+            state.move_shot(id, Some(&state.get_shots()[0].id.clone())).unwrap();
+        }
+
+        let shots = state.get_shots();
+        assert!(shots.windows(2).all(|w| w[0].sort_index < w[1].sort_index));
+        assert_eq!(ids(&shots), vec!["1", "2", "0"]);
+    }
+
+    #[test]
+    fn test_reorder_validates_permutation_and_undoes() {
+        let state = state_with(3);
+        let order = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let error = state.reorder_shots(&order(&["2", "2", "7"])).unwrap_err();
+        assert_eq!(error, AppError::validation("Shot order doesn't match the bid: missing 0, 1; unknown 7; repeated 2"));
+        assert_eq!(ids(&state.get_shots()), vec!["0", "1", "2"]);
+
+        assert_eq!(ids(&state.reorder_shots(&order(&["2", "0", "1"])).unwrap()), vec!["2", "0", "1"]);
+        state.move_shot("1", Some("2")).unwrap();
+        state.add_shot(shot("new"));
+
+        assert_eq!(ids(&state.undo_shot_order().unwrap()), vec!["2", "0", "1", "new"]);
+        assert_eq!(ids(&state.undo_shot_order().unwrap()), vec!["0", "1", "2", "new"]);
+        assert!(state.undo_shot_order().is_err());
+    }
+
+    #[test]
+    fn test_loaded_shots_follow_sort_index() {
+        let state = BidState::default();
+        let indexed = |id: &str, sort_index| ShotData { sort_index, ..shot(id) };
+
+        state.replace_bid("bid".to_string(), vec![indexed("b", 3000), indexed("a", 1500), indexed("c", 4000)], Vec::new(), BidMeta::default(), Vec::new(), ReadOnlyStatus::default());
+        assert_eq!(ids(&state.get_shots()), vec!["a", "b", "c"]);
+
+        // Bids saved before shots had an index keep their stored order
+        state.replace_bid("bid".to_string(), vec![shot("z"), shot("y")], Vec::new(), BidMeta::default(), Vec::new(), ReadOnlyStatus::default());
+        let shots = state.get_shots();
+        assert_eq!(ids(&shots), vec!["z", "y"]);
+        assert_eq!(shots[1].sort_index, 2 * SORT_INDEX_GAP);
+    }
+
+    #[test]
+    fn test_read_only_refuses_edits_untouched() {
+        let state = state_with(2);
+        let before = state.get_shots();
+        state.set_read_only(true, false).unwrap();
+
+        let edit = ShotData { description: "nudged".to_string(), ..shot("0") };
+        assert!(matches!(state.update_shot("0".to_string(), edit.clone()), Err(AppError::ReadOnly { .. })));
+        assert!(matches!(state.modify_shot("0", |s| { s.estimated_hours = Some(1.0); Ok(()) }), Err(AppError::ReadOnly { .. })));
+        assert!(state.move_shot("1", Some("0")).is_err());
+        assert!(state.set_status(BidStatus::Sent).is_err());
+        assert_eq!(state.get_shots(), before);
+
+        // Previews still work
+        let report = state.fit_to_budget(1000.0, &BudgetStrategy::Uniform, &HashMap::new(), true);
+        assert!(!matches!(report, Err(AppError::ReadOnly { .. })));
+
+        state.set_read_only(false, false).unwrap();
+        assert!(state.update_shot("0".to_string(), edit).is_ok());
+    }
+
+    #[test]
+    fn test_project_read_only_needs_unlock() {
+        let state = BidState::default();
+        let access = ReadOnlyStatus { read_only: true, project_read_only: true, dirty: false };
+        state.replace_bid("bid".to_string(), vec![shot("a")], Vec::new(), BidMeta::default(), Vec::new(), access);
+        assert_eq!(state.read_only_status(), access);
+
+        let error = state.set_read_only(false, false).unwrap_err();
+        assert!(error.message().contains("Unlock"));
+        assert!(state.ensure_writable().is_err());
+
+        let unlocked = state.unlock();
+        assert_eq!(unlocked, ReadOnlyStatus { read_only: false, project_read_only: false, dirty: true });
+        assert!(state.ensure_writable().is_ok());
+
+        // Locking for the project marks it changed until it is saved
+        state.mark_saved();
+        let locked = state.set_read_only(true, true).unwrap();
+        assert!(locked.project_read_only && locked.dirty);
+
+        // Loading over a locked bid is refused and leaves the lock alone
+        assert!(matches!(state.set_shots(vec![shot("b")]), Err(AppError::ReadOnly { .. })));
+        assert!(state.restore_autosave().is_err());
+        assert_eq!(state.get_shots()[0].id, "a");
+        assert!(state.read_only_status().read_only);
+
+        // Opening a project is what changes it
+        state.replace_bid("next".to_string(), vec![shot("b")], Vec::new(), BidMeta::default(), Vec::new(), ReadOnlyStatus::default());
+        assert!(!state.read_only_status().read_only);
+    }
+
+    #[test]
+    fn test_clear_empties_bid() {
+        let state = state_with(3);
+        state.add_group(ShotGroup { name: "Opening".to_string(), shot_ids: vec!["0".to_string()], discount_percent: None }).unwrap();
+        state.set_status(BidStatus::Sent).unwrap();
+        state.set_status(BidStatus::Approved).unwrap();
+        state.revise().unwrap();
+        assert!(state.read_only_status().dirty);
+
+        state.clear(true).unwrap();
+        assert!(state.get_shots().is_empty() && state.get_groups().is_empty());
+        assert!(!state.read_only_status().dirty);
+        assert_eq!(state.get_meta().status, BidStatus::Draft);
+        assert_eq!(state.get_meta().version, 2);
+        assert!(!state.get_revisions().is_empty());
+
+        state.clear(false).unwrap();
+        assert_eq!(state.get_meta().version, 1);
+        assert!(state.get_revisions().is_empty());
+    }
+
+    #[test]
+    fn test_clear_keeps_only_pricing_defaults() {
+        let state = BidState::default();
+        let adjustment = LumpAdjustment {
+            id: "adj".to_string(),
+            label: None,
+            amount: -100.0,
+            scope: AdjustmentScope::AllShots,
+            method: SplitMethod::Even,
+            shares: Vec::new(),
+            applied_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let meta = BidMeta {
+            default_contingency: 20.0,
+            currency: "EUR".to_string(),
+            client_name: Some("Client".to_string()),
+            project_name: Some("Project".to_string()),
+            adjustments: vec![adjustment],
+            ..BidMeta::default()
+        };
+        state.replace_bid("bid".to_string(), vec![shot("a")], Vec::new(), meta, Vec::new(), ReadOnlyStatus::default());
+
+        state.clear(false).unwrap();
+        let meta = state.get_meta();
+        assert_eq!((meta.default_contingency, meta.currency.as_str()), (20.0, "EUR"));
+        assert!(meta.client_name.is_none() && meta.project_name.is_none());
+        assert!(meta.adjustments.is_empty());
+
+        state.set_read_only(true, false).unwrap();
+        assert!(matches!(state.clear(false), Err(AppError::ReadOnly { .. })));
+    }
+
+    #[test]
+    fn test_changed_source_blocks_overwrite() {
+        let dir = std::env::temp_dir().join(format!("bid-source-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bid.xlsx");
+        std::fs::write(&path, b"v1").unwrap();
+
+        let state = state_with(2);
+        state.track_source(source_sync::fingerprint(&path).unwrap());
+        assert_eq!(state.check_source(), (SourceStatus::InSync, false));
+
+        std::fs::write(&path, b"v2").unwrap();
+        let (status, newly_diverged) = state.check_source();
+        assert!(matches!(status, SourceStatus::Modified { .. }) && newly_diverged);
+        // Reported once per change
+        assert!(!state.check_source().1);
+
+        let error = state.ensure_source_not_overwritten(&path).unwrap_err();
+        assert!(matches!(error, AppError::SourceDiverged { .. }));
+        assert!(state.ensure_source_not_overwritten(&dir.join("bid_v2.xlsx")).is_ok());
+
+        state.confirm_overwrite().unwrap();
+        assert!(state.ensure_source_not_overwritten(&path).is_ok());
+
+        // Loading another bid forgets the file
+        state.set_shots(vec![shot("1")]).unwrap();
+        assert_eq!(state.check_source(), (SourceStatus::Untracked, false));
+        assert!(state.confirm_overwrite().is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_deleted_shot_restores_into_place_and_groups() {
+        let state = state_with(3);
+        state.add_group(ShotGroup { name: "Sky".to_string(), shot_ids: vec!["1".to_string(), "2".to_string()], discount_percent: None }).unwrap();
+        let ids = |state: &BidState| state.get_shots().iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+
+        state.delete_shot("1", Some("Cut in edit".to_string())).unwrap();
+        assert_eq!(ids(&state), vec!["0", "2"]);
+        assert_eq!(state.get_groups()[0].shot_ids, vec!["2"]);
+        let archived = state.get_archived_shots();
+        assert_eq!(archived[0].reason.as_deref(), Some("Cut in edit"));
+        assert_eq!(archived[0].groups, vec!["Sky"]);
+
+        // Undo and redo the delete
+        state.undo_archive_change().unwrap();
+        assert_eq!(ids(&state), vec!["0", "1", "2"]);
+        assert!(state.get_archived_shots().is_empty());
+        state.redo_archive_change().unwrap();
+        assert_eq!(ids(&state), vec!["0", "2"]);
+
+        // Its id is taken, so it comes back under another
+        state.add_shot(shot("1"));
+        let restored = state.restore_shot("1").unwrap();
+        assert_eq!(restored.shot.id, "1-restored");
+        assert!(restored.warning.is_some());
+        assert_eq!(ids(&state), vec!["0", "1-restored", "2", "1"]);
+        assert!(state.get_groups()[0].shot_ids.contains(&"1-restored".to_string()));
+
+        state.undo_archive_change().unwrap();
+        assert_eq!(state.get_archived_shots()[0].shot.id, "1-restored");
+        assert_eq!(state.purge_archived(0).unwrap(), 1);
+        assert!(state.get_archived_shots().is_empty());
+        assert!(state.redo_archive_change().is_err());
     }
 }
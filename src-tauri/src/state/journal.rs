@@ -0,0 +1,551 @@
+//! Write-ahead journal for bid mutations
+//!
+//! The debounced autosave lags the last edit by half a second, so every
+//! mutation also appends one JSON line to `journal/<bid-id>.log`. Lines are
+//! written by a background thread fed through a bounded channel, so callers
+//! never wait on the disk. Recovery replays the journal on top of the
+//! autosave; compaction writes a fresh autosave and truncates the journal.
+//! Switching bids leaves the previous bid's journal in place until the bid
+//! is saved, discarded or recovered, so a crashed bid survives a new one
+//! being opened before it is restored.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::commands::bid::{ArchivedShot, BidMeta, BidRevision, ShotData, ShotGroup};
+use super::bid::{write_autosave, BidAutosave};
+
+/// Journal size that triggers compaction
+pub const COMPACT_THRESHOLD_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Messages queued for the writer before a mutation has to wait for it
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// One journal line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub revision: u64,
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub op: JournalOp,
+}
+
+/// The touched part of the bid as it was after a mutation
+///
+/// Entries carry resulting values rather than the edit, so replaying one
+/// that the snapshot already contains is harmless.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", content = "payload", rename_all = "snake_case")]
+pub enum JournalOp {
+    /// Insert or replace one shot
+    UpsertShot(Box<ShotData>),
+    SetShots(Vec<ShotData>),
+    SetGroups(Vec<ShotGroup>),
+    /// The whole bid; always the first entry for a bid
+    Reset {
+        shots: Vec<ShotData>,
+        groups: Vec<ShotGroup>,
+        meta: Box<BidMeta>,
+        #[serde(default)]
+        revisions: Vec<BidRevision>,
+        #[serde(default)]
+        archived: Vec<ArchivedShot>,
+    },
+}
+
+impl JournalOp {
+    fn apply(self, bid: &mut BidAutosave) {
+        match self {
+            JournalOp::UpsertShot(shot) => match bid.shots.iter_mut().find(|s| s.id == shot.id) {
+                Some(existing) => *existing = *shot,
+                None => bid.shots.push(*shot),
+            },
+            JournalOp::SetShots(shots) => bid.shots = shots,
+            JournalOp::SetGroups(groups) => bid.groups = groups,
+            JournalOp::Reset { shots, groups, meta, revisions, archived } => {
+                bid.shots = shots;
+                bid.groups = groups;
+                bid.meta = *meta;
+                bid.revisions = revisions;
+                bid.archived = archived;
+            }
+        }
+    }
+}
+
+/// Journal health for the diagnostics panel
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct JournalStats {
+    pub enabled: bool,
+    pub bid_id: Option<String>,
+    pub path: Option<String>,
+    pub size_bytes: u64,
+    pub entries: u64,
+    /// Revision of the latest mutation
+    pub revision: u64,
+    pub compactions: u64,
+    pub last_compacted_at: Option<String>,
+    pub write_errors: u64,
+    pub threshold_bytes: u64,
+}
+
+enum Message {
+    Append { bid_id: String, line: String },
+    Compact { snapshot: Box<BidAutosave>, autosave_path: PathBuf },
+    Clear,
+    Prune { keep: String },
+    Flush(SyncSender<()>),
+}
+
+/// Updated by the writer thread, read for stats
+#[derive(Default)]
+struct Counters {
+    bid_id: Mutex<Option<String>>,
+    bytes: AtomicU64,
+    entries: AtomicU64,
+    compactions: AtomicU64,
+    write_errors: AtomicU64,
+    last_compacted_at: Mutex<Option<String>>,
+    /// Set when a compaction is queued so only one is in flight
+    compacting: AtomicBool,
+}
+
+/// Handle to the journal writer thread
+pub struct Journal {
+    dir: PathBuf,
+    sender: SyncSender<Message>,
+    counters: Arc<Counters>,
+    /// Bid the last queued entry belongs to
+    queued_bid: Mutex<Option<String>>,
+}
+
+impl Journal {
+    /// Start a writer for journals in `dir`
+    pub fn open(dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let counters = Arc::new(Counters::default());
+        let writer = Writer { dir: dir.clone(), current: None, counters: counters.clone() };
+
+        std::thread::Builder::new()
+            .name("bid-journal".to_string())
+            .spawn(move || writer.run(receiver))
+            .map_err(|e| format!("Failed to start journal writer: {}", e))?;
+
+        Ok(Self { dir, sender, counters, queued_bid: Mutex::new(None) })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the last queued entry belongs to `bid_id`; if not, the next
+    /// entry starts a new journal and must be a full `Reset`
+    pub fn is_current(&self, bid_id: &str) -> bool {
+        self.queued_bid.lock().unwrap().as_deref() == Some(bid_id)
+    }
+
+    pub fn append(&self, bid_id: &str, entry: &JournalEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("Failed to serialize journal entry: {}", e);
+                return;
+            }
+        };
+
+        *self.queued_bid.lock().unwrap() = Some(bid_id.to_string());
+        self.send(Message::Append { bid_id: bid_id.to_string(), line });
+    }
+
+    /// Whether the journal has outgrown the threshold; claims the compaction
+    /// so the caller must follow up with `compact`
+    pub fn should_compact(&self) -> bool {
+        self.counters.bytes.load(Ordering::SeqCst) >= COMPACT_THRESHOLD_BYTES
+            && !self.counters.compacting.swap(true, Ordering::SeqCst)
+    }
+
+    /// Write `snapshot` as the autosave, then truncate its bid's journal
+    pub fn compact(&self, snapshot: BidAutosave, autosave_path: PathBuf) {
+        self.counters.compacting.store(true, Ordering::SeqCst);
+        *self.queued_bid.lock().unwrap() = snapshot.bid_id.clone();
+        self.send(Message::Compact { snapshot: Box::new(snapshot), autosave_path });
+    }
+
+    /// Delete every journal, as when the bid has been exported
+    pub fn clear(&self) {
+        *self.queued_bid.lock().unwrap() = None;
+        self.send(Message::Clear);
+    }
+
+    /// Delete every journal but `bid_id`'s, as once a bid has been recovered
+    pub fn prune(&self, bid_id: &str) {
+        self.send(Message::Prune { keep: bid_id.to_string() });
+    }
+
+    /// Block until everything queued so far is on disk
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::sync_channel(1);
+        self.send(Message::Flush(done));
+        wait.recv().ok();
+    }
+
+    pub fn stats(&self, revision: u64) -> JournalStats {
+        let bid_id = self.counters.bid_id.lock().unwrap().clone();
+
+        JournalStats {
+            enabled: true,
+            path: bid_id.as_ref().map(|id| journal_path(&self.dir, id).to_string_lossy().to_string()),
+            bid_id,
+            size_bytes: self.counters.bytes.load(Ordering::SeqCst),
+            entries: self.counters.entries.load(Ordering::SeqCst),
+            revision,
+            compactions: self.counters.compactions.load(Ordering::SeqCst),
+            last_compacted_at: self.counters.last_compacted_at.lock().unwrap().clone(),
+            write_errors: self.counters.write_errors.load(Ordering::SeqCst),
+            threshold_bytes: COMPACT_THRESHOLD_BYTES,
+        }
+    }
+
+    fn send(&self, message: Message) {
+        match self.sender.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(message)) => {
+                // Dropping an entry would make replay skip an edit, so wait
+                log::warn!("Bid journal writer is falling behind");
+                self.sender.send(message).ok();
+            }
+            Err(TrySendError::Disconnected(_)) => log::warn!("Bid journal writer has stopped"),
+        }
+    }
+}
+
+struct Writer {
+    dir: PathBuf,
+    current: Option<(String, File)>,
+    counters: Arc<Counters>,
+}
+
+impl Writer {
+    fn run(mut self, receiver: Receiver<Message>) {
+        for message in receiver {
+            let result = match message {
+                Message::Append { bid_id, line } => self.append(&bid_id, &line),
+                Message::Compact { snapshot, autosave_path } => {
+                    let result = self.compact(&snapshot, &autosave_path);
+                    self.counters.compacting.store(false, Ordering::SeqCst);
+                    result
+                }
+                Message::Clear => self.clear(),
+                Message::Prune { keep } => remove_journals(&self.dir, Some(&keep)),
+                Message::Flush(done) => {
+                    done.send(()).ok();
+                    Ok(())
+                }
+            };
+
+            if let Err(e) = result {
+                self.counters.write_errors.fetch_add(1, Ordering::SeqCst);
+                log::warn!("Bid journal write failed: {}", e);
+            }
+        }
+    }
+
+    fn append(&mut self, bid_id: &str, line: &str) -> Result<(), String> {
+        let file = self.file_for(bid_id)?;
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| e.to_string())?;
+
+        self.counters.bytes.fetch_add(line.len() as u64 + 1, Ordering::SeqCst);
+        self.counters.entries.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn compact(&mut self, snapshot: &BidAutosave, autosave_path: &Path) -> Result<(), String> {
+        write_autosave(autosave_path, snapshot)?;
+
+        if let Some(bid_id) = &snapshot.bid_id {
+            self.file_for(bid_id)?.set_len(0).map_err(|e| e.to_string())?;
+        }
+        self.counters.bytes.store(0, Ordering::SeqCst);
+        self.counters.entries.store(0, Ordering::SeqCst);
+        self.counters.compactions.fetch_add(1, Ordering::SeqCst);
+        *self.counters.last_compacted_at.lock().unwrap() = Some(chrono::Utc::now().to_rfc3339());
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.current = None;
+        *self.counters.bid_id.lock().unwrap() = None;
+        self.counters.bytes.store(0, Ordering::SeqCst);
+        self.counters.entries.store(0, Ordering::SeqCst);
+        remove_journals(&self.dir, None)
+    }
+
+    /// Open the journal for `bid_id`; other bids' journals are left alone
+    fn file_for(&mut self, bid_id: &str) -> Result<&mut File, String> {
+        if self.current.as_ref().map(|(id, _)| id.as_str()) != Some(bid_id) {
+            let path = journal_path(&self.dir, bid_id);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .read(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+            // Cut a torn line left by a crash, or new entries would land after it
+            let contents = fs::read(&path).map_err(|e| e.to_string())?;
+            let complete = contents.iter().rposition(|b| *b == b'\n').map(|i| i + 1).unwrap_or(0);
+            if complete < contents.len() {
+                file.set_len(complete as u64).map_err(|e| e.to_string())?;
+            }
+
+            let entries = contents[..complete].iter().filter(|b| **b == b'\n').count();
+            self.counters.bytes.store(complete as u64, Ordering::SeqCst);
+            self.counters.entries.store(entries as u64, Ordering::SeqCst);
+            *self.counters.bid_id.lock().unwrap() = Some(bid_id.to_string());
+            self.current = Some((bid_id.to_string(), file));
+        }
+
+        Ok(&mut self.current.as_mut().unwrap().1)
+    }
+}
+
+fn journal_path(dir: &Path, bid_id: &str) -> PathBuf {
+    dir.join(format!("{}.log", bid_id))
+}
+
+fn journal_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn remove_journals(dir: &Path, keep: Option<&str>) -> Result<(), String> {
+    for path in journal_files(dir) {
+        if keep.is_some_and(|id| path == journal_path(dir, id)) {
+            continue;
+        }
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Entries in the journal at `path`, up to the last complete one
+///
+/// A crash mid-write leaves a torn final line; reading stops there.
+pub fn read_entries(path: &Path) -> Result<Vec<JournalEntry>, String> {
+    let contents = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut entries = Vec::new();
+
+    for line in contents.split(|b| *b == b'\n').filter(|line| !line.is_empty()) {
+        match serde_json::from_slice(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                log::warn!("Journal {} ends in a torn entry: {}", path.display(), e);
+                break;
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Apply the entries newer than `bid`'s revision, in order
+pub fn replay(mut bid: BidAutosave, entries: Vec<JournalEntry>) -> BidAutosave {
+    let since = bid.revision;
+
+    for entry in entries.into_iter().filter(|e| e.revision > since) {
+        entry.op.apply(&mut bid);
+        bid.revision = entry.revision;
+        bid.saved_at = entry.timestamp;
+    }
+    bid
+}
+
+/// Rebuild the bid from the autosave snapshot and the journal in `dir`
+///
+/// The snapshot's own journal is used when there is one, otherwise the
+/// most recently written.
+pub fn recover(snapshot: Option<BidAutosave>, dir: &Path) -> Result<Option<BidAutosave>, String> {
+    let mut files = journal_files(dir);
+    let own = snapshot.as_ref().and_then(|s| s.bid_id.as_ref()).map(|id| journal_path(dir, id));
+    let path = match own.filter(|path| files.contains(path)) {
+        Some(path) => path,
+        None => {
+            files.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+            let Some(path) = files.pop() else {
+                return Ok(snapshot);
+            };
+            path
+        }
+    };
+    let bid_id = path.file_stem().map(|s| s.to_string_lossy().to_string());
+    let entries = read_entries(&path)?;
+
+    match snapshot {
+        Some(snapshot) if snapshot.bid_id == bid_id => Ok(Some(replay(snapshot, entries))),
+        // A journal for another bid only counts if it holds the whole bid
+        snapshot => match entries.first() {
+            Some(JournalEntry { op: JournalOp::Reset { .. }, .. }) => {
+                let empty = BidAutosave {
+                    saved_at: String::new(),
+                    shots: Vec::new(),
+                    groups: Vec::new(),
+                    meta: BidMeta::default(),
+                    revisions: Vec::new(),
+                    archived: Vec::new(),
+                    bid_id,
+                    revision: 0,
+                };
+                Ok(Some(replay(empty, entries)))
+            }
+            _ => Ok(snapshot),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(id: &str, hours: f64) -> ShotData {
+        ShotData {
+            id: id.to_string(),
+            scene_number: "1".to_string(),
+            estimated_hours: Some(hours),
+            ..Default::default()
+        }
+    }
+
+    fn entry(revision: u64, op: JournalOp) -> String {
+        let entry = JournalEntry { revision, timestamp: format!("t{}", revision), op };
+        serde_json::to_string(&entry).unwrap()
+    }
+
+    fn reset(shots: Vec<ShotData>) -> JournalOp {
+        JournalOp::Reset { shots, groups: Vec::new(), meta: Box::default(), revisions: Vec::new(), archived: Vec::new() }
+    }
+
+    #[test]
+    fn test_replay_stops_at_torn_final_line() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let complete = [
+            entry(1, reset(vec![shot("1", 10.0), shot("2", 20.0)])),
+            entry(2, JournalOp::UpsertShot(Box::new(shot("2", 25.0)))),
+            entry(3, JournalOp::UpsertShot(Box::new(shot("3", 5.0)))),
+        ];
+        let torn = entry(4, JournalOp::SetShots(Vec::new()));
+        let contents = format!("{}\n{}", complete.join("\n"), &torn[..torn.len() / 2]);
+        fs::write(journal_path(dir, "bid-a"), contents).unwrap();
+
+        let entries = read_entries(&journal_path(dir, "bid-a")).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let bid = recover(None, dir).unwrap().unwrap();
+        let hours: Vec<_> = bid.shots.iter().map(|s| (s.id.as_str(), s.estimated_hours)).collect();
+        assert_eq!(hours, vec![("1", Some(10.0)), ("2", Some(25.0)), ("3", Some(5.0))]);
+        assert_eq!(bid.revision, 3);
+        assert_eq!(bid.bid_id.as_deref(), Some("bid-a"));
+    }
+
+    #[test]
+    fn test_replay_skips_entries_in_snapshot() {
+        let snapshot = BidAutosave {
+            saved_at: "t2".to_string(),
+            shots: vec![shot("1", 12.0)],
+            groups: Vec::new(),
+            meta: BidMeta::default(),
+            revisions: Vec::new(),
+            archived: Vec::new(),
+            bid_id: Some("bid-a".to_string()),
+            revision: 2,
+        };
+        let entries = [
+            entry(2, JournalOp::SetShots(Vec::new())),
+            entry(3, JournalOp::UpsertShot(Box::new(shot("1", 14.0)))),
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let bid = replay(snapshot, entries);
+        assert_eq!(bid.shots.len(), 1);
+        assert_eq!(bid.shots[0].estimated_hours, Some(14.0));
+        assert_eq!(bid.saved_at, "t3");
+    }
+
+    #[test]
+    fn test_crashed_journal_survives_another_bid() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(journal_path(dir, "bid-a"), entry(3, JournalOp::UpsertShot(Box::new(shot("1", 9.0)))) + "\n").unwrap();
+        let journal = Journal::open(dir.to_path_buf()).unwrap();
+
+        let other: JournalEntry = serde_json::from_str(&entry(1, reset(vec![shot("x", 1.0)]))).unwrap();
+        journal.append("bid-b", &other);
+        journal.flush();
+
+        let snapshot = BidAutosave {
+            saved_at: "t2".to_string(),
+            shots: vec![shot("1", 4.0)],
+            groups: Vec::new(),
+            meta: BidMeta::default(),
+            revisions: Vec::new(),
+            archived: Vec::new(),
+            bid_id: Some("bid-a".to_string()),
+            revision: 2,
+        };
+        let bid = recover(Some(snapshot), dir).unwrap().unwrap();
+        assert_eq!(bid.shots[0].estimated_hours, Some(9.0));
+    }
+
+    #[test]
+    fn test_writer_appends_and_compacts() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let autosave_path = dir.join("bid_autosave.json");
+        let journal = Journal::open(dir.join("journal")).unwrap();
+
+        let first: JournalEntry = serde_json::from_str(&entry(1, reset(vec![shot("1", 1.0)]))).unwrap();
+        let second: JournalEntry = serde_json::from_str(&entry(2, JournalOp::UpsertShot(Box::new(shot("2", 2.0))))).unwrap();
+        journal.append("bid-a", &first);
+        journal.append("bid-a", &second);
+        journal.flush();
+
+        let stats = journal.stats(2);
+        assert_eq!(stats.entries, 2);
+        assert!(stats.size_bytes > 0);
+        assert_eq!(recover(None, journal.dir()).unwrap().unwrap().shots.len(), 2);
+
+        // A new bid leaves the old journal until it is pruned
+        journal.append("bid-b", &first);
+        journal.flush();
+        assert_eq!(journal_files(journal.dir()).len(), 2);
+        journal.prune("bid-b");
+        journal.flush();
+        assert_eq!(journal_files(journal.dir()), vec![journal_path(journal.dir(), "bid-b")]);
+
+        let snapshot = replay(recover(None, journal.dir()).unwrap().unwrap(), Vec::new());
+        journal.compact(snapshot, autosave_path.clone());
+        journal.flush();
+
+        let stats = journal.stats(1);
+        assert_eq!((stats.entries, stats.size_bytes, stats.compactions), (0, 0, 1));
+        let saved: BidAutosave = serde_json::from_str(&fs::read_to_string(&autosave_path).unwrap()).unwrap();
+        assert_eq!(saved.revision, 1);
+        assert_eq!(saved.bid_id.as_deref(), Some("bid-b"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Most recent command failure in each area of the app
+//!
+//! Error toasts vanish; the error panel reads these instead. Commands run
+//! their bodies through `track`/`track_async`, which keep the error when the
+//! body fails and pass the result through unchanged.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Area of the app a command belongs to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorDomain {
+    Setup,
+    Script,
+    Chat,
+    Bid,
+    Sidecar,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LastError {
+    pub domain: ErrorDomain,
+    pub command: String,
+    pub message: String,
+    /// RFC 3339
+    pub at: String,
+}
+
+#[derive(Default)]
+pub struct LastErrorState {
+    errors: Mutex<HashMap<ErrorDomain, LastError>>,
+}
+
+impl LastErrorState {
+    /// Run a `command` body, keeping its error if it fails
+    pub fn track<T, E: Display>(&self, domain: ErrorDomain, command: &str, body: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let result = body();
+        if let Err(e) = &result {
+            self.record(domain, command, e);
+        }
+        result
+    }
+
+    /// As `track`, for async commands
+    pub async fn track_async<T, E: Display>(&self, domain: ErrorDomain, command: &str, body: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let result = body.await;
+        if let Err(e) = &result {
+            self.record(domain, command, e);
+        }
+        result
+    }
+
+    pub fn record(&self, domain: ErrorDomain, command: &str, error: &impl Display) {
+        let error = LastError {
+            domain,
+            command: command.to_string(),
+            message: error.to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.errors.lock().unwrap().insert(domain, error);
+    }
+
+    /// One error per domain, newest first
+    pub fn get(&self) -> Vec<LastError> {
+        let mut errors: Vec<LastError> = self.errors.lock().unwrap().values().cloned().collect();
+        errors.sort_by(|a, b| b.at.cmp(&a.at));
+        errors
+    }
+
+    /// Forget the error in `domain`, or in every domain when None
+    pub fn clear(&self, domain: Option<ErrorDomain>) {
+        let mut errors = self.errors.lock().unwrap();
+        match domain {
+            Some(domain) => {
+                errors.remove(&domain);
+            }
+            None => errors.clear(),
+        }
+    }
+}
+
+/// Define a command whose failures are kept for the error panel
+///
+/// Wraps a `#[tauri::command]` function returning a `Result`: it gains a
+/// `LastErrorState` argument and its body runs through `track` (or
+/// `track_async`) under `ErrorDomain::$domain`, named after the function.
+///
+/// ```ignore
+/// tracked_command! { Bid;
+/// #[tauri::command]
+/// pub fn get_shot(id: String, state: WindowBid<'_>) -> Result<ShotData, AppError> {
+///     ...
+/// }
+/// }
+/// ```
+macro_rules! tracked_command {
+    ($domain:ident; $(#[$attr:meta])* $vis:vis async fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block) => {
+        $(#[$attr])*
+        $vis async fn $name($($arg: $ty,)* last_errors: tauri::State<'_, $crate::state::LastErrorState>) -> $ret {
+            last_errors.track_async($crate::state::ErrorDomain::$domain, stringify!($name), async $body).await
+        }
+    };
+    ($domain:ident; $(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block) => {
+        $(#[$attr])*
+        $vis fn $name($($arg: $ty,)* last_errors: tauri::State<'_, $crate::state::LastErrorState>) -> $ret {
+            last_errors.track($crate::state::ErrorDomain::$domain, stringify!($name), || $body)
+        }
+    };
+}
+pub(crate) use tracked_command;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::script::open_bid_file_internal;
+    use crate::error::AppError;
+    use crate::state::SessionState;
+
+    #[test]
+    fn test_failing_command_records_its_error() {
+        let errors = LastErrorState::default();
+        let session_state = SessionState::default();
+
+        let result = errors.track(ErrorDomain::Script, "open_bid_file", || {
+            open_bid_file_internal(None, &session_state, |_| Ok(()))
+        });
+        assert!(result.is_err());
+
+        let failed = errors.track(ErrorDomain::Setup, "save_settings", || Err::<(), _>("Disk full".to_string()));
+        assert_eq!(failed.unwrap_err(), "Disk full");
+
+        // A later success leaves the error for the panel
+        errors.track(ErrorDomain::Script, "export_bid_csv", || Ok::<_, AppError>(())).unwrap();
+
+        let recorded = errors.get();
+        assert_eq!(recorded.len(), 2);
+        let script = recorded.iter().find(|e| e.domain == ErrorDomain::Script).unwrap();
+        assert_eq!((script.command.as_str(), script.message.as_str()), ("open_bid_file", "No bid has been generated yet"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&script.at).is_ok());
+
+        errors.clear(Some(ErrorDomain::Script));
+        assert_eq!(errors.get().iter().map(|e| e.domain).collect::<Vec<_>>(), vec![ErrorDomain::Setup]);
+        errors.clear(None);
+        assert!(errors.get().is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 // Global state management
+pub mod audit;
 pub mod bid;
+pub mod journal;
+pub mod last_error;
 pub mod session;
 pub mod sidecar;
+pub mod workspaces;
 
 pub use bid::BidState;
+pub use last_error::{ErrorDomain, LastErrorState};
 pub use session::SessionState;
-pub use sidecar::SidecarState;
+pub use sidecar::{ModelState, SidecarState};
+pub use workspaces::{WindowBid, WorkspaceRegistry};
@@ -1,27 +1,230 @@
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::commands::chat::{ChatMessage, PendingAction};
 use crate::commands::settings::Settings;
 
+/// Delay before a changed session is written to disk
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// Maximum number of chat messages kept for restoring
+const MAX_CHAT_HISTORY: usize = 200;
+
+/// Session data persisted to `config_dir/session.json`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(default)]
+pub struct SessionSnapshot {
+    pub last_bid_path: Option<String>,
+    /// Workbook the last `process_script` run wrote
+    pub last_generated_bid: Option<String>,
+    pub current_script: Option<String>,
+    pub last_output_dir: Option<String>,
+    pub chat_panel_open: bool,
+    pub selected_shot_id: Option<String>,
+    pub settings: Option<Settings>,
+    pub chat_history: Vec<ChatMessage>,
+}
+
+impl SessionSnapshot {
+    /// Whether the user opted in to restoring this session on launch
+    pub fn restore_enabled(&self) -> bool {
+        self.settings.as_ref().map(|s| s.ui.restore_session).unwrap_or(false)
+    }
+}
+
 /// User session state
 #[derive(Default)]
 pub struct SessionState {
-    settings: Mutex<Option<Settings>>,
-    current_script: Mutex<Option<String>>,
+    snapshot: Arc<Mutex<SessionSnapshot>>,
+    session_file: Arc<Mutex<Option<PathBuf>>>,
+    generation: Arc<AtomicU64>,
+    /// Chat edits waiting for the user to confirm; not persisted
+    pending_actions: Arc<Mutex<Vec<PendingAction>>>,
+    /// Session loaded at launch that the user hasn't restored or dismissed
+    pending_restore: Arc<Mutex<Option<SessionSnapshot>>>,
 }
 
 impl SessionState {
     pub fn set_settings(&self, settings: Settings) {
-        *self.settings.lock().unwrap() = Some(settings);
+        self.update(|s| s.settings = Some(settings));
     }
 
     pub fn get_settings(&self) -> Option<Settings> {
-        self.settings.lock().unwrap().clone()
+        self.snapshot.lock().unwrap().settings.clone()
     }
 
     pub fn set_current_script(&self, path: String) {
-        *self.current_script.lock().unwrap() = Some(path);
+        self.update(|s| s.current_script = Some(path));
     }
 
     pub fn get_current_script(&self) -> Option<String> {
-        self.current_script.lock().unwrap().clone()
+        self.snapshot.lock().unwrap().current_script.clone()
+    }
+
+    pub fn snapshot(&self) -> SessionSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Apply a change to the session and schedule a debounced save
+    pub fn update(&self, change: impl FnOnce(&mut SessionSnapshot)) {
+        change(&mut self.snapshot.lock().unwrap());
+        self.schedule_save();
+    }
+
+    /// Record a chat message so the conversation can be rehydrated later
+    pub fn push_chat_message(&self, message: ChatMessage) {
+        self.update(|s| {
+            s.chat_history.push(message);
+            let overflow = s.chat_history.len().saturating_sub(MAX_CHAT_HISTORY);
+            s.chat_history.drain(..overflow);
+        });
+    }
+
+    /// Hold a chat edit until it's confirmed or rejected, dropping any
+    /// that have expired
+    pub fn add_pending_action(&self, action: PendingAction) {
+        let mut pending = self.pending_actions.lock().unwrap();
+        let now = std::time::Instant::now();
+        pending.retain(|a| !a.is_expired(now));
+        pending.push(action);
+    }
+
+    /// Remove and return the pending chat edit with `id`
+    pub fn take_pending_action(&self, id: &str) -> Option<PendingAction> {
+        let mut pending = self.pending_actions.lock().unwrap();
+        let index = pending.iter().position(|a| a.id == id)?;
+        Some(pending.remove(index))
+    }
+
+    /// Session waiting to be offered for restore, if any
+    ///
+    /// Held until `dismiss_restore`, so a frontend that starts listening
+    /// after launch can still ask for it.
+    pub fn pending_restore(&self) -> Option<SessionSnapshot> {
+        self.pending_restore.lock().unwrap().clone()
+    }
+
+    /// Withdraw the restore offer once it has been taken up or declined
+    pub fn dismiss_restore(&self) {
+        self.pending_restore.lock().unwrap().take();
+    }
+
+    /// Load the saved session from the config directory
+    ///
+    /// Later saves are written back to the same location. Returns `None` when
+    /// no session has been saved yet. A session with restore enabled is also
+    /// kept as the pending restore offer.
+    pub fn load(&self, config_dir: &Path) -> Result<Option<SessionSnapshot>, String> {
+        let session_file = config_dir.join("session.json");
+        *self.session_file.lock().unwrap() = Some(session_file.clone());
+
+        if !session_file.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(&session_file)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        let snapshot: SessionSnapshot = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse session file: {}", e))?;
+
+        *self.snapshot.lock().unwrap() = snapshot.clone();
+        *self.pending_restore.lock().unwrap() = Some(snapshot.clone()).filter(SessionSnapshot::restore_enabled);
+        Ok(Some(snapshot))
+    }
+
+    /// Write the session to disk immediately
+    pub fn save(&self) -> Result<(), String> {
+        save_snapshot(&self.snapshot, &self.session_file)
+    }
+
+    /// Save after a short delay, collapsing bursts of changes into one write
+    fn schedule_save(&self) {
+        if self.session_file.lock().unwrap().is_none() {
+            return;
+        }
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let snapshot = self.snapshot.clone();
+        let session_file = self.session_file.clone();
+
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(SAVE_DEBOUNCE).await;
+
+            if current.load(Ordering::SeqCst) == generation {
+                if let Err(e) = save_snapshot(&snapshot, &session_file) {
+                    log::warn!("Failed to save session: {}", e);
+                }
+            }
+        });
+    }
+}
+
+fn save_snapshot(
+    snapshot: &Mutex<SessionSnapshot>,
+    session_file: &Mutex<Option<PathBuf>>,
+) -> Result<(), String> {
+    let Some(path) = session_file.lock().unwrap().clone() else {
+        return Ok(());
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(&*snapshot.lock().unwrap())
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_session(name: &str, restore_session: bool) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vfx-session-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut settings = Settings::default();
+        settings.ui.restore_session = restore_session;
+        let snapshot = SessionSnapshot {
+            last_bid_path: Some("/bids/show.xlsx".to_string()),
+            settings: Some(settings),
+            ..Default::default()
+        };
+        std::fs::write(dir.join("session.json"), serde_json::to_string(&snapshot).unwrap()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_restore_offer_waits_for_the_frontend() {
+        let dir = saved_session("offer", true);
+        let state = SessionState::default();
+        assert!(state.pending_restore().is_none());
+
+        state.load(&dir).unwrap();
+        // Asking twice still finds it, as a reloaded window would
+        assert_eq!(state.pending_restore().unwrap().last_bid_path.as_deref(), Some("/bids/show.xlsx"));
+        assert!(state.pending_restore().is_some());
+
+        state.dismiss_restore();
+        assert!(state.pending_restore().is_none());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_no_restore_offer_when_disabled() {
+        let dir = saved_session("disabled", false);
+        let state = SessionState::default();
+
+        assert!(state.load(&dir).unwrap().is_some());
+        assert!(state.pending_restore().is_none());
+        std::fs::remove_dir_all(dir).ok();
     }
 }
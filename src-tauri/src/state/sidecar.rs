@@ -1,71 +1,591 @@
-use std::sync::Mutex;
-use crate::sidecar::PythonSidecar;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::commands::settings::{LlmSettings, SidecarSettings};
+use crate::error::AppError;
+use crate::events::SidecarIncompatible;
+use crate::gguf::ModelInfo;
+use crate::model_health::ModelUnavailable;
+use crate::sidecar::protocol::{self, ProtocolInfo};
+use crate::sidecar::{AsyncRpcClient, BlobStore, EventSink, LogRing, MethodMetrics, PoolStats, ProgressEvent, PythonSidecar, QueryCache, RpcMethod, RpcMetrics, RpcPool, SidecarEnv};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::path::{Path, PathBuf};
+use serde_json::json;
+use crate::workspace;
+use std::time::Duration;
+
+/// How often the health monitor refreshes the cached running state
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Lifecycle of the LLM model inside the sidecar
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, JsonSchema)]
+#[serde(tag = "state")]
+pub enum ModelState {
+    #[default]
+    NotLoaded,
+    Loading,
+    Loaded { name: String, ctx: usize },
+}
 
 /// Global Python sidecar state
 #[derive(Default)]
 pub struct SidecarState {
+    /// Held only briefly; a sidecar being started or stopped is taken out
     sidecar: Mutex<Option<PythonSidecar>>,
+    /// Held across starts, stops and restarts, which wait on the process,
+    /// so they don't overlap and calls don't queue behind them
+    lifecycle: Mutex<()>,
+    event_sink: Mutex<Option<EventSink>>,
+    model_state: Mutex<ModelState>,
+    settings: Mutex<SidecarSettings>,
+    /// LLM parameters exported to the sidecar when it starts
+    llm: Mutex<Option<LlmSettings>>,
+    rpc_metrics: Arc<RpcMetrics>,
+    /// Workers the blocking RPC calls run on
+    rpc_pool: Arc<RpcPool>,
+    log: Arc<LogRing>,
+    /// Request ids of cancellable calls in flight, by operation
+    in_flight: Mutex<HashMap<RpcMethod, String>>,
+    /// Answer to `get_model_info`, kept until the sidecar restarts
+    model_info: Mutex<Option<ModelInfo>>,
+    /// Why the model can't be opened; no automatic restarts while set
+    model_unavailable: Mutex<Option<ModelUnavailable>>,
+    /// Workspace of the bid the sidecar last worked on
+    workspace: Mutex<Option<PathBuf>>,
+    /// Cached so status polls don't wait on the sidecar lock
+    running: AtomicBool,
+    /// Running with the model loaded
+    ready: AtomicBool,
+    /// What the sidecar answered to `get_protocol_info`; None until asked
+    protocol: Mutex<Option<ProtocolInfo>>,
+    /// Set by an automatic restart until the new process has answered the
+    /// handshake; `client_for` refuses calls meanwhile
+    handshake_pending: AtomicBool,
+    /// `bid_query` answers shared between identical calls
+    queries: QueryCache,
 }
 
 impl SidecarState {
-    /// Initialize and start the Python sidecar
-    pub fn start(&self, rpc_script_path: PathBuf) -> Result<(), String> {
-        let mut guard = self.sidecar.lock()
+    /// Initialize and start the Python sidecar with the user's LLM settings
+    ///
+    /// Blocks while the new process is watched through startup; async
+    /// callers run it with `spawn_blocking`.
+    pub fn start(&self, rpc_script_path: PathBuf, llm: &LlmSettings) -> Result<(), String> {
+        let _lifecycle = self.lifecycle.lock()
             .map_err(|e| format!("Failed to lock sidecar state: {}", e))?;
+        self.set_llm_settings(llm.clone());
 
         // Stop existing sidecar if running
-        if let Some(ref mut existing) = *guard {
+        let existing = self.sidecar.lock().unwrap().take();
+        self.set_running(false);
+        *self.protocol.lock().unwrap() = None;
+        self.handshake_pending.store(false, Ordering::SeqCst);
+        self.queries.invalidate();
+        if let Some(mut existing) = existing {
             let _ = existing.stop();
         }
 
         // Start new sidecar
-        let new_sidecar = PythonSidecar::start(&rpc_script_path)?;
-        *guard = Some(new_sidecar);
+        let event_sink = self.event_sink.lock().unwrap().clone();
+        let new_sidecar = PythonSidecar::start_with_events(&rpc_script_path, event_sink, self.env(), self.log.clone())?;
+        *self.sidecar.lock().unwrap() = Some(new_sidecar);
+        self.set_running(true);
+        self.set_model_state(ModelState::NotLoaded);
+        self.set_model_info(None);
+        self.reset_metrics_on_restart();
 
         Ok(())
     }
 
+    /// Forward sidecar events to `sink` for sidecars started after this call,
+    /// along with `slow_operation` reports for calls running long
+    pub fn set_event_sink(&self, sink: EventSink) {
+        self.rpc_metrics.set_slow_sink(sink.clone());
+        *self.event_sink.lock().unwrap() = Some(sink);
+    }
+
     /// Stop the Python sidecar
     pub fn stop(&self) -> Result<(), String> {
-        let mut guard = self.sidecar.lock()
+        let _lifecycle = self.lifecycle.lock()
             .map_err(|e| format!("Failed to lock sidecar state: {}", e))?;
 
-        if let Some(ref mut sidecar) = *guard {
+        let sidecar = self.sidecar.lock().unwrap().take();
+        self.set_running(false);
+        self.handshake_pending.store(false, Ordering::SeqCst);
+        if let Some(mut sidecar) = sidecar {
             sidecar.stop()?;
         }
 
-        *guard = None;
+        *self.protocol.lock().unwrap() = None;
+        self.queries.invalidate();
+        self.set_model_state(ModelState::NotLoaded);
+        self.set_model_info(None);
         Ok(())
     }
 
     /// Get the RPC client if sidecar is running
-    pub fn rpc_client(&self) -> Option<crate::sidecar::AsyncRpcClient> {
+    ///
+    /// None for a sidecar on another major protocol version, and for one
+    /// whose pipe closed until `check_health` has restarted it.
+    pub fn rpc_client(&self) -> Option<AsyncRpcClient> {
+        if self.protocol.lock().unwrap().as_ref().is_some_and(|info| !info.is_compatible()) {
+            return None;
+        }
         let guard = self.sidecar.lock()
             .ok()?;
+        if guard.as_ref()?.pipe_closed() {
+            return None;
+        }
 
-        guard.as_ref()?.async_rpc_client()
+        // Blob references only go to a sidecar that said it resolves them
+        let blob_refs = self.protocol.lock().unwrap().as_ref()
+            .is_some_and(|info| info.has_capability(protocol::BLOB_REFS_CAPABILITY));
+        let (heartbeat_gap, blobs) = {
+            let settings = self.settings.lock().unwrap();
+            let blobs = blob_refs.then(|| BlobStore::new(BlobStore::default_dir(), settings.blob_threshold_bytes));
+            (settings.heartbeat_gap(), blobs)
+        };
+        guard.as_ref()?.async_rpc_client(self.rpc_metrics.clone(), self.rpc_pool.clone(), heartbeat_gap, blobs)
     }
 
-    /// Check if sidecar is running
-    pub fn is_running(&self) -> bool {
-        if let Ok(mut guard) = self.sidecar.lock() {
-            if let Some(ref mut sidecar) = *guard {
-                return sidecar.is_running();
+    /// RPC client for a call to `method`
+    ///
+    /// Fails with `UnsupportedBySidecar` when the sidecar doesn't advertise
+    /// the method, rather than letting the call time out.
+    pub fn client_for(&self, method: RpcMethod) -> Result<AsyncRpcClient, AppError> {
+        if !self.is_running() || self.handshake_pending() {
+            return Err(AppError::sidecar_unavailable());
+        }
+        self.require(method)?;
+        self.rpc_client().ok_or_else(AppError::sidecar_unavailable)
+    }
+
+    /// Error unless the sidecar can answer `method`
+    ///
+    /// Calls go ahead before the handshake has been made.
+    pub fn require(&self, method: RpcMethod) -> Result<(), AppError> {
+        let protocol = self.protocol.lock().unwrap();
+        let Some(info) = protocol.as_ref() else {
+            return Ok(());
+        };
+        if !info.is_compatible() {
+            return Err(AppError::sidecar_incompatible(info.mismatch_message()));
+        }
+        if !info.supports(method) {
+            return Err(AppError::unsupported_by_sidecar(
+                method.as_str(),
+                info.sidecar_version.clone(),
+                protocol::required_version(method),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Ask a newly started sidecar which protocol it speaks
+    ///
+    /// An incompatible one raises `sidecar_incompatible` through the event
+    /// sink and gets no further calls. If the handshake fails, calls stay
+    /// ungated.
+    pub async fn negotiate_protocol(&self) {
+        let Some(client) = self.rpc_client() else {
+            return;
+        };
+        let info = match protocol::negotiate(&client, self.rpc_timeout(RpcMethod::GetProtocolInfo)).await {
+            Ok(info) => info,
+            Err(e) => {
+                log::warn!("Sidecar protocol handshake failed: {}", e.message);
+                return;
+            }
+        };
+        self.handshake_pending.store(false, Ordering::SeqCst);
+        log::info!(
+            "Sidecar speaks protocol {} (version {})",
+            info.protocol_version,
+            info.sidecar_version.as_deref().unwrap_or("unknown")
+        );
+
+        if !info.is_compatible() {
+            log::error!("{}", info.mismatch_message());
+            let sink = self.event_sink.lock().unwrap().clone();
+            if let Some(sink) = sink {
+                sink(ProgressEvent {
+                    event: protocol::INCOMPATIBLE_EVENT.to_string(),
+                    data: serde_json::to_value(SidecarIncompatible {
+                        protocol_version: info.protocol_version,
+                        app_protocol_version: protocol::PROTOCOL_VERSION,
+                        sidecar_version: info.sidecar_version.clone(),
+                        message: info.mismatch_message(),
+                    })
+                    .unwrap_or_default(),
+                });
             }
         }
-        false
+        *self.protocol.lock().unwrap() = Some(info);
+    }
+
+    pub fn protocol_info(&self) -> Option<ProtocolInfo> {
+        self.protocol.lock().unwrap().clone()
+    }
+
+    /// Whether an automatic restart still waits for `negotiate_protocol`
+    pub fn handshake_pending(&self) -> bool {
+        self.handshake_pending.load(Ordering::SeqCst)
+    }
+
+    /// Restart a sidecar whose stdin was found closed by a call that failed
+    /// with `RPC_PIPE_CLOSED_CODE`; returns whether it is running again
+    ///
+    /// The new process may be another version, so calls are held until the
+    /// health monitor has made the handshake.
+    ///
+    /// Left stopped while the model is unavailable: a sidecar that exits
+    /// because it can't load the model would only exit again.
+    fn restart_after_pipe_closed(&self) -> bool {
+        if self.model_unavailable().is_some() {
+            if self.running.load(Ordering::SeqCst) {
+                log::warn!("Sidecar pipe closed while the model is unavailable; not restarting");
+            }
+            self.set_running(false);
+            return false;
+        }
+
+        log::warn!("Sidecar pipe closed; restarting the sidecar");
+        self.handshake_pending.store(true, Ordering::SeqCst);
+        match self.restart() {
+            Ok(()) => self.is_running(),
+            Err(e) => {
+                log::error!("Failed to restart sidecar after its pipe closed: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Pick the request id for the next `operation` call so `cancel_call`
+    /// can find it
+    pub fn track_call(&self, operation: RpcMethod) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.in_flight.lock().unwrap().insert(operation, id.clone());
+        id
+    }
+
+    /// Forget a finished call, unless a newer one has replaced it
+    pub fn untrack_call(&self, operation: RpcMethod, id: &str) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&operation).is_some_and(|current| current == id) {
+            in_flight.remove(&operation);
+        }
+    }
+
+    /// Cancel the in-flight `operation` call; false when there was none
+    pub fn cancel_call(&self, operation: RpcMethod) -> Result<bool, AppError> {
+        let Some(id) = self.in_flight.lock().unwrap().remove(&operation) else {
+            return Ok(false);
+        };
+
+        let guard = self.sidecar.lock().unwrap();
+        let client = guard.as_ref()
+            .and_then(|sidecar| sidecar.rpc_client())
+            .ok_or_else(AppError::sidecar_unavailable)?;
+
+        Ok(client.cancel(&id)?)
+    }
+
+    /// Whether the sidecar is running, as of the last transition or
+    /// `check_health`
+    ///
+    /// Doesn't wait on calls holding the sidecar lock, so it is cheap
+    /// enough for frequent status polls.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Whether the sidecar is running with the model loaded
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    /// Reconcile the cached state with the process, for the health monitor
+    ///
+    /// A sidecar whose pipe broke under an earlier call is restarted, on the
+    /// monitor's thread. Skipped while the sidecar lock is taken or the
+    /// sidecar is being started or stopped; the next check catches up.
+    pub fn check_health(&self) {
+        let Ok(lifecycle) = self.lifecycle.try_lock() else {
+            return;
+        };
+        let Ok(mut guard) = self.sidecar.try_lock() else {
+            return;
+        };
+        let (pipe_closed, running) = match guard.as_mut() {
+            Some(sidecar) => (sidecar.pipe_closed(), sidecar.is_running()),
+            None => (false, false),
+        };
+        drop(guard);
+        drop(lifecycle);
+        let running = if pipe_closed { self.restart_after_pipe_closed() } else { running };
+
+        let was_running = self.running.swap(running, Ordering::SeqCst);
+        if was_running && !running {
+            log::warn!("Sidecar is no longer running");
+            self.set_model_state(ModelState::NotLoaded);
+            self.set_model_info(None);
+        }
+        self.update_ready();
+    }
+
+    fn set_running(&self, running: bool) {
+        self.running.store(running, Ordering::SeqCst);
+        self.update_ready();
+    }
+
+    fn update_ready(&self) {
+        let loaded = matches!(*self.model_state.lock().unwrap(), ModelState::Loaded { .. });
+        self.ready.store(self.is_running() && loaded, Ordering::SeqCst);
     }
 
     /// Restart the sidecar
+    ///
+    /// Blocks while the new process is watched through startup; async
+    /// callers run it with `spawn_blocking`. Calls made meanwhile find no
+    /// sidecar rather than waiting on the lock.
     pub fn restart(&self) -> Result<(), String> {
-        let mut guard = self.sidecar.lock()
+        let _lifecycle = self.lifecycle.lock()
             .map_err(|e| format!("Failed to lock sidecar state: {}", e))?;
 
-        if let Some(ref mut sidecar) = *guard {
-            sidecar.restart()?;
+        *self.protocol.lock().unwrap() = None;
+        self.queries.invalidate();
+        let taken = self.sidecar.lock().unwrap().take();
+        if let Some(mut sidecar) = taken {
+            sidecar.set_env(self.env());
+            let result = sidecar.restart();
+            *self.sidecar.lock().unwrap() = Some(sidecar);
+            if let Err(e) = result {
+                self.set_running(false);
+                return Err(e);
+            }
+            self.set_running(true);
         }
 
+        self.set_model_state(ModelState::NotLoaded);
+        self.set_model_info(None);
+        self.reset_metrics_on_restart();
         Ok(())
     }
+
+    /// Clear the latency counters after a restart if the settings ask for it
+    fn reset_metrics_on_restart(&self) {
+        if self.settings.lock().unwrap().reset_metrics_on_restart {
+            self.rpc_metrics.reset();
+        }
+    }
+
+    /// Apply the per-method timeout policy from the settings
+    ///
+    /// Environment changes take effect the next time the sidecar starts.
+    pub fn set_rpc_timeouts(&self, settings: SidecarSettings) {
+        self.rpc_metrics.set_warn_after(settings.slow_call_warn());
+        self.queries.set_ttl(settings.query_cache_ttl());
+        self.rpc_pool.set_max_in_flight(settings.max_in_flight_rpcs);
+        *self.settings.lock().unwrap() = settings;
+    }
+
+    /// Timeout the command layer should pass when calling `method`
+    pub fn rpc_timeout(&self, method: RpcMethod) -> Duration {
+        self.settings.lock().unwrap().timeout_for(method.as_str())
+    }
+
+    /// LLM parameters for the next start or restart
+    pub fn set_llm_settings(&self, llm: LlmSettings) {
+        *self.llm.lock().unwrap() = Some(llm);
+    }
+
+    /// Environment for a sidecar started now
+    pub fn env(&self) -> SidecarEnv {
+        SidecarEnv::from_settings(&self.settings.lock().unwrap(), self.llm.lock().unwrap().as_ref())
+    }
+
+    /// Cache that identical `bid_query` calls share
+    pub fn queries(&self) -> &QueryCache {
+        &self.queries
+    }
+
+    /// Latency aggregates for calls made through `rpc_client`
+    pub fn rpc_metrics(&self) -> Vec<MethodMetrics> {
+        self.rpc_metrics.snapshot()
+    }
+
+    pub fn reset_rpc_metrics(&self) {
+        self.rpc_metrics.reset();
+    }
+
+    /// Queue depth and load of the RPC workers
+    pub fn rpc_pool_stats(&self) -> PoolStats {
+        self.rpc_pool.stats()
+    }
+
+    /// Cancel RPC calls still waiting for a worker, as the app exits
+    pub fn shutdown_rpc_pool(&self) {
+        self.rpc_pool.shutdown();
+    }
+
+    /// Last lines the sidecar wrote to stderr, including before a restart
+    pub fn recent_log(&self) -> Vec<String> {
+        self.log.recent()
+    }
+
+    pub fn model_state(&self) -> ModelState {
+        self.model_state.lock().unwrap().clone()
+    }
+
+    pub fn set_model_state(&self, state: ModelState) {
+        *self.model_state.lock().unwrap() = state;
+        self.update_ready();
+    }
+
+    /// Workspace the sidecar should use for `bid_id`, created if needed
+    ///
+    /// Switching from another bid's workspace tells the sidecar to let go of
+    /// the old one. None before the workspace root is known or when the
+    /// directory can't be created; the sidecar then falls back to its own.
+    pub fn use_workspace(&self, bid_id: &str) -> Option<PathBuf> {
+        let dir = match workspace::prepare(workspace::root()?, bid_id) {
+            Ok(dir) => dir,
+            Err(e) => {
+                log::warn!("{}", e);
+                return None;
+            }
+        };
+
+        let previous = self.workspace.lock().unwrap().replace(dir.clone());
+        if let Some(previous) = previous.filter(|previous| *previous != dir) {
+            self.release_workspace(&previous);
+        }
+        Some(dir)
+    }
+
+    pub fn active_workspace(&self) -> Option<PathBuf> {
+        self.workspace.lock().unwrap().clone()
+    }
+
+    /// Ask the sidecar to close files in `dir`; Windows won't delete a
+    /// directory with open handles
+    pub fn release_workspace(&self, dir: &Path) {
+        let mut active = self.workspace.lock().unwrap();
+        if active.as_deref() == Some(dir) {
+            *active = None;
+        }
+        drop(active);
+
+        let guard = self.sidecar.lock().unwrap();
+        let Some(client) = guard.as_ref().and_then(|sidecar| sidecar.rpc_client()) else {
+            return;
+        };
+        let params = json!({ "workspace_dir": dir.to_string_lossy() });
+        if let Err(e) = client.notify(RpcMethod::ReleaseWorkspace.as_str(), params) {
+            log::warn!("Failed to release workspace {}: {}", dir.display(), e.message);
+        }
+    }
+
+    pub fn model_info(&self) -> Option<ModelInfo> {
+        self.model_info.lock().unwrap().clone()
+    }
+
+    pub fn set_model_info(&self, info: Option<ModelInfo>) {
+        *self.model_info.lock().unwrap() = info;
+    }
+
+    pub fn model_unavailable(&self) -> Option<ModelUnavailable> {
+        self.model_unavailable.lock().unwrap().clone()
+    }
+
+    /// Record why the model can't be opened, or None once it can; returns
+    /// the previous value
+    pub fn set_model_unavailable(&self, unavailable: Option<ModelUnavailable>) -> Option<ModelUnavailable> {
+        std::mem::replace(&mut *self.model_unavailable.lock().unwrap(), unavailable)
+    }
+
+    /// Error unless the model is loaded and ready for LLM work
+    pub fn ensure_model_loaded(&self) -> Result<(), AppError> {
+        match self.model_state() {
+            ModelState::Loaded { .. } => Ok(()),
+            ModelState::Loading => Err(AppError::not_ready(
+                "The model is still loading. Please wait for it to finish and try again."
+            )),
+            ModelState::NotLoaded => Err(AppError::not_ready(
+                "The model is not loaded. Use 'Preload model' to load it, then try again."
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::settings::Settings;
+
+    fn script(body: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("sidecar-state-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rpc_server.py");
+        std::fs::write(&path, body).unwrap();
+        (dir, path)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cached_flags_follow_start_stop_and_exit() {
+        let (dir, path) = script("import sys\nsys.stdin.read()\n");
+        let state = SidecarState::default();
+        assert!(!state.is_running() && !state.is_ready());
+
+        state.start(path, &Settings::default().llm).unwrap();
+        assert!(state.is_running());
+        assert!(!state.is_ready());
+        state.set_model_state(ModelState::Loaded { name: "model".to_string(), ctx: 4096 });
+        assert!(state.is_ready());
+
+        state.stop().unwrap();
+        assert!(!state.is_running() && !state.is_ready());
+        state.check_health();
+        assert!(!state.is_running());
+
+        // An exit between transitions is picked up by the health check
+        let (exit_dir, exit_path) = script("import time\ntime.sleep(1)\n");
+        state.start(exit_path, &Settings::default().llm).unwrap();
+        state.set_model_state(ModelState::Loaded { name: "model".to_string(), ctx: 4096 });
+        assert!(state.is_ready());
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(state.is_running());
+        state.check_health();
+        assert!(!state.is_running() && !state.is_ready());
+        assert_eq!(state.model_state(), ModelState::NotLoaded);
+
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::remove_dir_all(exit_dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_closed_pipe_is_restarted_by_the_health_check() {
+        let (dir, path) = script("import sys\nsys.stdin.read()\n");
+        let state = SidecarState::default();
+        state.start(path, &Settings::default().llm).unwrap();
+        assert!(state.rpc_client().is_some());
+
+        // Calls don't restart it themselves, so none waits on the startup
+        state.sidecar.lock().unwrap().as_ref().unwrap().pipe_closed_flag().store(true, Ordering::SeqCst);
+        assert!(state.rpc_client().is_none());
+
+        state.check_health();
+        assert!(state.is_running());
+        assert!(state.rpc_client().is_some());
+
+        // Gated calls wait for the new process's handshake
+        assert!(state.handshake_pending());
+        assert!(matches!(state.client_for(RpcMethod::Health), Err(AppError::SidecarUnavailable { .. })));
+
+        state.stop().unwrap();
+        std::fs::remove_dir_all(dir).ok();
+    }
 }
@@ -0,0 +1,385 @@
+//! Bids open in windows besides the main one, and in project tabs
+//!
+//! The main window works on the managed `BidState`. Every window opened by
+//! `open_bid_in_new_window` gets a `BidState` of its own, kept here under the
+//! window's label, which doubles as the context id. Commands take `WindowBid`
+//! rather than `State<BidState>` so they act on the bid of the window that
+//! called them; settings, the session and the sidecar stay shared.
+//!
+//! A window can also hold project tabs, each a context of its own. Bid
+//! commands act on the window's active tab, or on the tab named by a
+//! `projectId` argument, which any command taking `WindowBid` accepts.
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use serde_json::Value;
+use tauri::ipc::{CommandArg, CommandItem, InvokeBody, InvokeError};
+use tauri::{Manager, Runtime, State};
+use crate::error::AppError;
+use crate::state::bid::ChangeListener;
+use crate::state::BidState;
+
+/// Label of the window declared in `tauri.conf.json`
+pub const MAIN_WINDOW_LABEL: &str = "main";
+
+/// Labels of bid windows start with this, followed by a uuid
+pub const WINDOW_LABEL_PREFIX: &str = "bid-";
+
+/// Ids of project tabs start with this, followed by a uuid
+pub const PROJECT_ID_PREFIX: &str = "project-";
+
+/// Command argument naming the project tab to act on
+const PROJECT_ID_ARG: &str = "projectId";
+
+/// Bid contexts of the extra windows and project tabs, by id
+#[derive(Default)]
+pub struct WorkspaceRegistry {
+    contexts: Mutex<HashMap<String, Arc<BidState>>>,
+    /// Where new contexts write their audit trail
+    audit_dir: Mutex<Option<PathBuf>>,
+    /// Where new contexts keep their autosave and journal, one directory
+    /// per context id
+    recovery_dir: Mutex<Option<PathBuf>>,
+    /// Project tabs in the order they were created, with their window
+    tabs: Mutex<Vec<(String, String)>>,
+    /// Tab each window shows; windows without one show their own bid
+    active: Mutex<HashMap<String, String>>,
+    /// Given to every new context
+    change_listener: Mutex<Option<ChangeListener>>,
+}
+
+impl WorkspaceRegistry {
+    /// Give contexts created from now on an audit trail under `dir`
+    pub fn enable_audit(&self, dir: PathBuf) {
+        *self.audit_dir.lock().unwrap() = Some(dir);
+    }
+
+    /// Call `listener` after every change to a context created from now on
+    pub fn on_change(&self, listener: ChangeListener) {
+        *self.change_listener.lock().unwrap() = Some(listener);
+    }
+
+    /// Autosave and journal contexts created from now on under `dir`, so a
+    /// crash doesn't lose the work in other windows and tabs
+    pub fn enable_recovery(&self, dir: PathBuf) {
+        *self.recovery_dir.lock().unwrap() = Some(dir);
+    }
+
+    /// Reopen, as tabs of `window`, the contexts a crash left behind
+    ///
+    /// Each comes back under its old id with its autosave and journal
+    /// replayed, and counts as unsaved. Returns the ids reopened.
+    pub fn recover_tabs(&self, window: &str) -> Vec<String> {
+        let Some(root) = self.recovery_dir.lock().unwrap().clone() else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(&root) else {
+            return Vec::new();
+        };
+
+        let mut leftovers: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|id| id.starts_with(PROJECT_ID_PREFIX) || id.starts_with(WINDOW_LABEL_PREFIX))
+            .filter(|id| self.get(id).is_none())
+            .collect();
+        leftovers.sort();
+
+        let mut recovered = Vec::new();
+        for id in leftovers {
+            let (id, bid) = self.insert(id);
+            match bid.restore_autosave() {
+                Ok(Some(_)) => {
+                    self.tabs.lock().unwrap().push((id.clone(), window.to_string()));
+                    recovered.push(id);
+                }
+                Ok(None) => {
+                    self.remove(&id);
+                }
+                Err(e) => {
+                    // Left on disk for another try
+                    log::warn!("Failed to recover bid context {}: {}", id, e);
+                    self.contexts.lock().unwrap().remove(&id);
+                }
+            }
+        }
+        recovered
+    }
+
+    /// Create an empty context; returns its id, to be used as the window label
+    pub fn create(&self) -> (String, Arc<BidState>) {
+        self.insert(format!("{}{}", WINDOW_LABEL_PREFIX, uuid::Uuid::new_v4()))
+    }
+
+    /// Create an empty project tab in `window` and make it the active one
+    pub fn create_tab(&self, window: &str) -> (String, Arc<BidState>) {
+        let (id, bid) = self.insert(format!("{}{}", PROJECT_ID_PREFIX, uuid::Uuid::new_v4()));
+        self.tabs.lock().unwrap().push((id.clone(), window.to_string()));
+        self.active.lock().unwrap().insert(window.to_string(), id.clone());
+        (id, bid)
+    }
+
+    fn insert(&self, id: String) -> (String, Arc<BidState>) {
+        let bid = Arc::new(BidState::default());
+        if let Some(dir) = self.audit_dir.lock().unwrap().clone() {
+            bid.enable_audit(dir);
+        }
+        if let Some(listener) = self.change_listener.lock().unwrap().clone() {
+            bid.on_change(listener);
+        }
+        if let Some(root) = self.recovery_dir.lock().unwrap().clone() {
+            let dir = root.join(&id);
+            bid.enable_autosave(dir.join("bid_autosave.json"));
+            if let Err(e) = bid.enable_journal(dir.join("journal")) {
+                log::warn!("Failed to start the journal for bid context {}: {}", id, e);
+            }
+        }
+        self.contexts.lock().unwrap().insert(id.clone(), bid.clone());
+        (id, bid)
+    }
+
+    /// Project tabs of `window`, oldest first; its own bid isn't one
+    pub fn tabs(&self, window: &str) -> Vec<String> {
+        self.tabs.lock().unwrap()
+            .iter()
+            .filter(|(_, owner)| owner == window)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Show `id` in `window`: one of its tabs, or the window's own label to
+    /// go back to its own bid
+    pub fn set_active(&self, window: &str, id: &str) -> Result<(), String> {
+        if id == window {
+            self.active.lock().unwrap().remove(window);
+            return Ok(());
+        }
+        if !self.tabs(window).iter().any(|tab| tab == id) {
+            return Err(format!("No project '{}' in this window", id));
+        }
+        self.active.lock().unwrap().insert(window.to_string(), id.to_string());
+        Ok(())
+    }
+
+    /// Tab `window` shows, if it isn't showing its own bid
+    pub fn active(&self, window: &str) -> Option<String> {
+        self.active.lock().unwrap().get(window).cloned()
+    }
+
+    /// Window that shows context `id`: its window for a tab, else `id` itself
+    pub fn window_of(&self, id: &str) -> String {
+        self.tabs.lock().unwrap()
+            .iter()
+            .find(|(tab, _)| tab == id)
+            .map_or_else(|| id.to_string(), |(_, window)| window.clone())
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<BidState>> {
+        self.contexts.lock().unwrap().get(id).cloned()
+    }
+
+    /// Drop context `id`; a window closed on its active tab goes back to
+    /// its own bid
+    ///
+    /// Contexts are removed once saved or discarded, so their autosave and
+    /// journal go with them.
+    pub fn remove(&self, id: &str) -> Option<Arc<BidState>> {
+        self.tabs.lock().unwrap().retain(|(tab, _)| tab != id);
+        self.active.lock().unwrap().retain(|window, tab| tab != id && window != id);
+        let bid = self.contexts.lock().unwrap().remove(id)?;
+        if let Some(root) = self.recovery_dir.lock().unwrap().clone() {
+            if let Err(e) = bid.clear_autosave() {
+                log::warn!("Failed to clear the autosave of bid context {}: {}", id, e);
+            }
+            let dir = root.join(id);
+            if dir.exists() {
+                if let Err(e) = std::fs::remove_dir_all(&dir) {
+                    log::warn!("Failed to remove {}: {}", dir.display(), e);
+                }
+            }
+        }
+        Some(bid)
+    }
+
+    /// Every context with its id, ordered by id
+    pub fn contexts(&self) -> Vec<(String, Arc<BidState>)> {
+        let mut contexts: Vec<_> = self.contexts.lock().unwrap()
+            .iter()
+            .map(|(id, bid)| (id.clone(), bid.clone()))
+            .collect();
+        contexts.sort_by(|a, b| a.0.cmp(&b.0));
+        contexts
+    }
+
+    /// Id of the context that has `bid_id` loaded
+    pub fn find_bid(&self, bid_id: &str) -> Option<String> {
+        self.contexts()
+            .into_iter()
+            .find(|(_, bid)| bid.bid_id() == bid_id)
+            .map(|(id, _)| id)
+    }
+}
+
+/// The bid of one window: the managed state for the main window, else the
+/// window's context from the registry
+pub enum WindowBid<'r> {
+    Main(State<'r, BidState>),
+    Window(Arc<BidState>),
+}
+
+impl WindowBid<'_> {
+    /// Whether this is the main window's bid, the one the session remembers
+    pub fn is_main(&self) -> bool {
+        matches!(self, WindowBid::Main(_))
+    }
+}
+
+impl Deref for WindowBid<'_> {
+    type Target = BidState;
+
+    fn deref(&self) -> &BidState {
+        match self {
+            WindowBid::Main(state) => state.inner(),
+            WindowBid::Window(bid) => bid,
+        }
+    }
+}
+
+/// Bid the window labelled `label` shows: its active tab or its own bid;
+/// unknown labels get the main bid
+pub fn window_bid<'a, R: Runtime>(manager: &'a impl Manager<R>, label: &str) -> Option<WindowBid<'a>> {
+    let context = manager.try_state::<WorkspaceRegistry>().and_then(|registry| {
+        let id = registry.active(label).unwrap_or_else(|| label.to_string());
+        registry.get(&id)
+    });
+    match context {
+        Some(bid) => Some(WindowBid::Window(bid)),
+        None => manager.try_state::<BidState>().map(WindowBid::Main),
+    }
+}
+
+/// Bid of the context `id`, where `main` is the main window's
+pub fn context_bid<'a, R: Runtime>(manager: &'a impl Manager<R>, id: &str) -> Result<WindowBid<'a>, AppError> {
+    if id == MAIN_WINDOW_LABEL {
+        return Ok(WindowBid::Main(manager.state()));
+    }
+    manager.state::<WorkspaceRegistry>()
+        .get(id)
+        .map(WindowBid::Window)
+        .ok_or_else(|| AppError::not_found(format!("No bid window '{}'", id)))
+}
+
+/// Label of the window that has `bid_id` loaded, the main one included
+pub fn window_with_bid<R: Runtime>(manager: &impl Manager<R>, bid_id: &str) -> Option<String> {
+    if manager.state::<BidState>().bid_id() == bid_id {
+        return Some(MAIN_WINDOW_LABEL.to_string());
+    }
+    manager.state::<WorkspaceRegistry>().find_bid(bid_id)
+}
+
+impl<'r, 'de: 'r, R: Runtime> CommandArg<'de, R> for WindowBid<'r> {
+    /// Resolves the bid from a `projectId` argument, or else from the label
+    /// of the calling window
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        let webview = command.message.webview_ref();
+        let project_id = match command.message.payload() {
+            InvokeBody::Json(args) => args.get(PROJECT_ID_ARG).and_then(Value::as_str),
+            InvokeBody::Raw(_) => None,
+        };
+        if let Some(id) = project_id {
+            return context_bid(webview, id).map_err(|e| InvokeError(serde_json::to_value(e).unwrap_or_default()));
+        }
+        window_bid(webview, webview.label()).ok_or_else(|| {
+            InvokeError::from(format!("bid state not managed for field `{}` on command `{}`", command.key, command.name))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bid::ShotData;
+
+    #[test]
+    fn test_contexts_are_independent() {
+        let registry = WorkspaceRegistry::default();
+        let (first, first_bid) = registry.create();
+        let (second, second_bid) = registry.create();
+        assert!(first.starts_with(WINDOW_LABEL_PREFIX));
+        assert_ne!(first, second);
+
+        first_bid.set_shots(vec![ShotData { id: "SH010".to_string(), ..Default::default() }]).unwrap();
+        assert_eq!(registry.get(&first).unwrap().get_shots().len(), 1);
+        assert!(second_bid.get_shots().is_empty());
+
+        assert_eq!(registry.find_bid(&first_bid.bid_id()), Some(first.clone()));
+        assert_eq!(registry.find_bid("missing"), None);
+
+        assert!(registry.remove(&first).is_some());
+        assert!(registry.get(&first).is_none());
+        assert_eq!(registry.contexts().len(), 1);
+    }
+
+    #[test]
+    fn test_project_tabs_are_independent_and_switchable() {
+        let registry = WorkspaceRegistry::default();
+        let (first, first_bid) = registry.create_tab(MAIN_WINDOW_LABEL);
+        let (second, second_bid) = registry.create_tab(MAIN_WINDOW_LABEL);
+        assert!(first.starts_with(PROJECT_ID_PREFIX));
+        assert_eq!(registry.tabs(MAIN_WINDOW_LABEL), vec![first.clone(), second.clone()]);
+        assert_eq!(registry.active(MAIN_WINDOW_LABEL), Some(second.clone()));
+
+        first_bid.set_shots(vec![ShotData { id: "SH010".to_string(), ..Default::default() }]).unwrap();
+        second_bid.set_shots(vec![
+            ShotData { id: "AB010".to_string(), ..Default::default() },
+            ShotData { id: "AB020".to_string(), ..Default::default() },
+        ]).unwrap();
+        first_bid.update_shot("SH010".to_string(), ShotData { id: "SH010".to_string(), description: "Sky".to_string(), ..Default::default() }).unwrap();
+        assert_eq!(registry.get(&first).unwrap().get_shots()[0].description, "Sky");
+        assert_eq!(registry.get(&second).unwrap().get_shots().len(), 2);
+        assert!(second_bid.get_shots().iter().all(|s| s.description.is_empty()));
+
+        registry.set_active(MAIN_WINDOW_LABEL, &first).unwrap();
+        assert_eq!(registry.active(MAIN_WINDOW_LABEL), Some(first.clone()));
+        assert_eq!(registry.window_of(&first), MAIN_WINDOW_LABEL);
+        assert!(registry.set_active("bid-other", &first).is_err());
+
+        // Closing the active tab goes back to the window's own bid
+        registry.remove(&first);
+        assert_eq!(registry.active(MAIN_WINDOW_LABEL), None);
+        assert_eq!(registry.tabs(MAIN_WINDOW_LABEL), vec![second.clone()]);
+        registry.set_active(MAIN_WINDOW_LABEL, &second).unwrap();
+        registry.set_active(MAIN_WINDOW_LABEL, MAIN_WINDOW_LABEL).unwrap();
+        assert_eq!(registry.active(MAIN_WINDOW_LABEL), None);
+    }
+
+    #[tokio::test]
+    async fn test_tabs_left_by_a_crash_are_recovered() {
+        let dir = std::env::temp_dir().join(format!("contexts-{}", uuid::Uuid::new_v4()));
+        let crashed = WorkspaceRegistry::default();
+        crashed.enable_recovery(dir.clone());
+        let (lost, lost_bid) = crashed.create_tab(MAIN_WINDOW_LABEL);
+        lost_bid.set_shots(vec![ShotData { id: "SH010".to_string(), ..Default::default() }]).unwrap();
+        let (closed, closed_bid) = crashed.create_tab(MAIN_WINDOW_LABEL);
+        closed_bid.set_shots(vec![ShotData { id: "AB010".to_string(), ..Default::default() }]).unwrap();
+        lost_bid.flush_journal();
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+
+        // A tab closed normally leaves nothing to recover
+        crashed.remove(&closed);
+        assert!(!dir.join(&closed).exists());
+
+        let relaunched = WorkspaceRegistry::default();
+        relaunched.enable_recovery(dir.clone());
+        assert_eq!(relaunched.recover_tabs(MAIN_WINDOW_LABEL), vec![lost.clone()]);
+        assert_eq!(relaunched.tabs(MAIN_WINDOW_LABEL), vec![lost.clone()]);
+        let recovered = relaunched.get(&lost).unwrap();
+        assert_eq!(recovered.get_shots()[0].id, "SH010");
+        assert!(recovered.read_only_status().dirty);
+
+        relaunched.remove(&lost);
+        assert!(!dir.join(&lost).exists());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
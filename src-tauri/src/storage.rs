@@ -0,0 +1,169 @@
+//! Disk space used by the app, for the storage panel in settings
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use crate::commands::model::resolve_model_path;
+use crate::commands::settings::Settings;
+
+/// Where the app keeps files
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    /// Temp files and anything else under `app_cache_dir()`
+    Cache,
+    Attachments,
+    /// The autosaved bid and its edit journal
+    Autosaves,
+    Models,
+    Logs,
+    /// The sidecar's per-bid indexes and scratch files
+    Workspaces,
+}
+
+impl StorageCategory {
+    pub const ALL: [StorageCategory; 6] = [
+        StorageCategory::Cache,
+        StorageCategory::Attachments,
+        StorageCategory::Autosaves,
+        StorageCategory::Models,
+        StorageCategory::Logs,
+        StorageCategory::Workspaces,
+    ];
+
+    /// Attachments and models are part of bids and setup, not scratch space;
+    /// workspaces are deleted one at a time with `delete_workspace`
+    pub fn clearable(self) -> bool {
+        matches!(self, StorageCategory::Cache | StorageCategory::Autosaves | StorageCategory::Logs)
+    }
+}
+
+/// Bytes used by one category
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub bytes: u64,
+    pub paths: Vec<String>,
+    pub clearable: bool,
+}
+
+/// Result of `get_storage_usage`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StorageUsage {
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+}
+
+/// Files and directories that make up `category`
+pub fn category_paths(app: &tauri::AppHandle, settings: &Settings, category: StorageCategory) -> Vec<PathBuf> {
+    let resolver = app.path();
+    match category {
+        StorageCategory::Cache => resolver.app_cache_dir().into_iter().collect(),
+        StorageCategory::Attachments => resolver.app_data_dir().map(|d| d.join("attachments")).into_iter().collect(),
+        StorageCategory::Autosaves => resolver
+            .app_config_dir()
+            .map(|d| d.join("bid_autosave.json"))
+            .into_iter()
+            .chain(resolver.app_data_dir().map(|d| d.join("journal")))
+            .collect(),
+        StorageCategory::Models => resolve_model_path(settings).parent().map(Path::to_path_buf).into_iter().collect(),
+        StorageCategory::Logs => resolver.app_log_dir().into_iter().collect(),
+        StorageCategory::Workspaces => resolver.app_data_dir().map(|d| d.join("workspaces")).into_iter().collect(),
+    }
+}
+
+/// Usage of every category
+pub fn storage_usage(app: &tauri::AppHandle, settings: &Settings) -> StorageUsage {
+    let categories: Vec<CategoryUsage> = StorageCategory::ALL
+        .into_iter()
+        .map(|category| {
+            let paths = category_paths(app, settings, category);
+            CategoryUsage {
+                category,
+                bytes: paths.iter().map(|p| disk_usage(p)).sum(),
+                paths: paths.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                clearable: category.clearable(),
+            }
+        })
+        .collect();
+
+    StorageUsage {
+        total_bytes: categories.iter().map(|c| c.bytes).sum(),
+        categories,
+    }
+}
+
+/// Size of a file, or of everything under a directory; missing paths are 0
+///
+/// Symlinks are not followed, so a model linked in from elsewhere isn't
+/// counted twice.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Delete the contents of `path`, keeping a directory itself in place
+///
+/// Returns the bytes freed; files that can't be removed are skipped.
+pub fn clear_path(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return match std::fs::remove_file(path) {
+            Ok(()) => metadata.len(),
+            Err(e) => {
+                log::warn!("Failed to remove {}: {}", path.display(), e);
+                0
+            }
+        };
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let child = entry.path();
+            let freed = clear_path(&child);
+            if child.is_dir() {
+                let _ = std::fs::remove_dir(&child);
+            }
+            freed
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_and_clear_keep_root() {
+        let dir = std::env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("tmp/nested")).unwrap();
+        std::fs::write(dir.join("a.bin"), [0u8; 100]).unwrap();
+        std::fs::write(dir.join("tmp/nested/b.bin"), [0u8; 50]).unwrap();
+
+        assert_eq!(disk_usage(&dir), 150);
+        assert_eq!(disk_usage(&dir.join("a.bin")), 100);
+        assert_eq!(disk_usage(&dir.join("missing")), 0);
+
+        assert_eq!(clear_path(&dir), 150);
+        assert!(dir.is_dir());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        assert!(StorageCategory::Cache.clearable() && !StorageCategory::Models.clearable());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
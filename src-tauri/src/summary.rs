@@ -0,0 +1,356 @@
+//! Quick-look bid summary for pasting into an email
+//!
+//! Rendered as Markdown, or as HTML with inline styles and no external
+//! assets so it pastes cleanly into Outlook. Both formats come from the same
+//! sections. Only client-facing fields are read: tags, task notes, review
+//! state and extraction flags never appear.
+
+use serde::{Deserialize, Serialize};
+use crate::assumptions::{exported_items, AssumptionCategory};
+use crate::commands::bid::{total_final_price, BidMeta, ShotData};
+use crate::commands::script::scene_summaries;
+use crate::commands::settings::LocaleSettings;
+use crate::format::{format_currency, format_number};
+
+const FONT: &str = "font-family:Arial,Helvetica,sans-serif;font-size:14px;color:#222222;";
+const CELL: &str = "border:1px solid #cccccc;padding:4px 8px;";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryFormat {
+    Markdown,
+    Html,
+}
+
+/// Sections to include
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SummaryOptions {
+    pub totals: bool,
+    pub complexity: bool,
+    /// Most expensive shots to list; 0 leaves the section out
+    pub top_shots: usize,
+    pub scenes: bool,
+    /// The bid's assumptions, exclusions and notes marked for export
+    pub assumptions: bool,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        Self { totals: true, complexity: true, top_shots: 5, scenes: true, assumptions: true }
+    }
+}
+
+enum Block {
+    Heading(String),
+    Fields(Vec<(&'static str, String)>),
+    /// Headers, rows, and which columns are numbers
+    Table(Vec<&'static str>, Vec<Vec<String>>, Vec<bool>),
+    List(Vec<String>),
+}
+
+/// Render the summary of `shots` in `format`
+pub fn render_summary(
+    shots: &[ShotData],
+    meta: &BidMeta,
+    locale: &LocaleSettings,
+    format: SummaryFormat,
+    options: &SummaryOptions,
+) -> String {
+    let blocks = summary_blocks(shots, meta, locale, options);
+    match format {
+        SummaryFormat::Markdown => markdown(&title(meta), &blocks),
+        SummaryFormat::Html => html(&title(meta), &blocks),
+    }
+}
+
+fn title(meta: &BidMeta) -> String {
+    match &meta.project_name {
+        Some(project) => format!("{} – Bid summary", project),
+        None => "Bid summary".to_string(),
+    }
+}
+
+fn summary_blocks(shots: &[ShotData], meta: &BidMeta, locale: &LocaleSettings, options: &SummaryOptions) -> Vec<Block> {
+    let money = |value: f64| format_currency(value, &meta.currency, locale);
+    let price = |shot: &ShotData| shot.final_price.map(|p| money(meta.rounding.apply(p))).unwrap_or_default();
+    let mut blocks = Vec::new();
+
+    if options.totals {
+        let grand_total = total_final_price(shots, meta.rounding);
+        let hours: f64 = shots.iter().filter_map(|s| s.estimated_hours).sum();
+        let mut fields = Vec::new();
+        if let Some(client) = &meta.client_name {
+            fields.push(("Client", client.clone()));
+        }
+        fields.push(("Version", meta.version.to_string()));
+        fields.push(("Shots", shots.len().to_string()));
+        fields.push(("Estimated hours", format_number(hours, 1, locale)));
+        if !meta.adjustments.is_empty() {
+            let adjusted: f64 = meta.adjustments.iter().map(|a| a.amount).sum();
+            fields.push(("Before adjustments", money(grand_total - adjusted)));
+            for adjustment in &meta.adjustments {
+                fields.push(("Adjustment", format!("{} ({})", money(adjustment.amount), adjustment.display_label())));
+            }
+        }
+        fields.push(("Grand total", money(grand_total)));
+        if !shots.is_empty() {
+            fields.push(("Average per shot", money(grand_total / shots.len() as f64)));
+        }
+        if let Some(date) = meta.valid_until {
+            fields.push(("Valid until", date.format("%Y-%m-%d").to_string()));
+        }
+        blocks.push(Block::Heading("Totals".to_string()));
+        blocks.push(Block::Fields(fields));
+    }
+
+    if options.complexity && !shots.is_empty() {
+        let mut levels: Vec<(String, Vec<ShotData>)> = Vec::new();
+        for shot in shots {
+            let level = match shot.complexity.trim() {
+                "" => "Unspecified",
+                level => level,
+            };
+            match levels.iter_mut().find(|(name, _)| name == level) {
+                Some((_, members)) => members.push(shot.clone()),
+                None => levels.push((level.to_string(), vec![shot.clone()])),
+            }
+        }
+        let mut rows: Vec<(f64, Vec<String>)> = levels
+            .into_iter()
+            .map(|(level, members)| {
+                let total = total_final_price(&members, meta.rounding);
+                (total, vec![level, members.len().to_string(), money(total)])
+            })
+            .collect();
+        rows.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1[0].cmp(&b.1[0])));
+
+        blocks.push(Block::Heading("By complexity".to_string()));
+        blocks.push(Block::Table(
+            vec!["Complexity", "Shots", "Total"],
+            rows.into_iter().map(|(_, row)| row).collect(),
+            vec![false, true, true],
+        ));
+    }
+
+    if options.top_shots > 0 && !shots.is_empty() {
+        let mut ranked: Vec<&ShotData> = shots.iter().collect();
+        ranked.sort_by(|a, b| {
+            b.final_price.unwrap_or(0.0).total_cmp(&a.final_price.unwrap_or(0.0)).then_with(|| a.id.cmp(&b.id))
+        });
+        let rows = ranked
+            .into_iter()
+            .take(options.top_shots)
+            .map(|shot| vec![
+                shot.id.clone(),
+                shot.scene_number.clone(),
+                shot.description.clone(),
+                shot.complexity.clone(),
+                price(shot),
+            ])
+            .collect();
+
+        blocks.push(Block::Heading(format!("Top {} shots by price", options.top_shots.min(shots.len()))));
+        blocks.push(Block::Table(
+            vec!["Shot", "Scene", "Description", "Complexity", "Price"],
+            rows,
+            vec![false, false, false, false, true],
+        ));
+    }
+
+    if options.scenes && !shots.is_empty() {
+        let rows = scene_summaries(shots)
+            .into_iter()
+            .map(|scene| {
+                let members: Vec<ShotData> = shots
+                    .iter()
+                    .filter(|s| s.scene_number.trim() == scene.scene_number)
+                    .cloned()
+                    .collect();
+                vec![scene.scene_number, scene.shot_count.to_string(), money(total_final_price(&members, meta.rounding))]
+            })
+            .collect();
+
+        blocks.push(Block::Heading("By scene".to_string()));
+        blocks.push(Block::Table(vec!["Scene", "Shots", "Subtotal"], rows, vec![false, true, true]));
+    }
+
+    if options.assumptions {
+        let exported = exported_items(&meta.assumptions);
+        for category in AssumptionCategory::ALL {
+            let items: Vec<String> = exported.iter().filter(|a| a.category == category).map(|a| a.text.clone()).collect();
+            if !items.is_empty() {
+                blocks.push(Block::Heading(category.heading().to_string()));
+                blocks.push(Block::List(items));
+            }
+        }
+    }
+
+    blocks
+}
+
+fn markdown(title: &str, blocks: &[Block]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace(['\r', '\n'], " ");
+    let mut out = format!("# {}\n", title);
+
+    for block in blocks {
+        out.push('\n');
+        match block {
+            Block::Heading(text) => out.push_str(&format!("## {}\n", text)),
+            Block::Fields(fields) => {
+                for (label, value) in fields {
+                    out.push_str(&format!("- **{}:** {}\n", label, value));
+                }
+            }
+            Block::Table(headers, rows, numeric) => {
+                out.push_str(&format!("| {} |\n", headers.join(" | ")));
+                let rule: Vec<&str> = numeric.iter().map(|n| if *n { "---:" } else { "---" }).collect();
+                out.push_str(&format!("| {} |\n", rule.join(" | ")));
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+                    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+            }
+            Block::List(items) => {
+                for item in items {
+                    out.push_str(&format!("- {}\n", item.replace(['\r', '\n'], " ")));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn html(title: &str, blocks: &[Block]) -> String {
+    let mut out = format!("<div style=\"{}\">\n", FONT);
+    out.push_str(&format!("<h1 style=\"font-size:20px;margin:0 0 12px 0;\">{}</h1>\n", escape(title)));
+
+    for block in blocks {
+        match block {
+            Block::Heading(text) => {
+                out.push_str(&format!("<h2 style=\"font-size:16px;margin:16px 0 8px 0;\">{}</h2>\n", escape(text)));
+            }
+            Block::Fields(fields) => {
+                out.push_str("<table style=\"border-collapse:collapse;\">\n");
+                for (label, value) in fields {
+                    out.push_str(&format!(
+                        "<tr><td style=\"padding:2px 12px 2px 0;font-weight:bold;\">{}</td><td style=\"padding:2px 0;\">{}</td></tr>\n",
+                        escape(label),
+                        escape(value)
+                    ));
+                }
+                out.push_str("</table>\n");
+            }
+            Block::Table(headers, rows, numeric) => {
+                let align = |i: usize| if numeric[i] { "text-align:right;" } else { "text-align:left;" };
+                out.push_str("<table style=\"border-collapse:collapse;\">\n<tr>");
+                for (i, header) in headers.iter().enumerate() {
+                    out.push_str(&format!("<th style=\"{}{}background:#f2f2f2;\">{}</th>", CELL, align(i), escape(header)));
+                }
+                out.push_str("</tr>\n");
+                for row in rows {
+                    out.push_str("<tr>");
+                    for (i, value) in row.iter().enumerate() {
+                        out.push_str(&format!("<td style=\"{}{}\">{}</td>", CELL, align(i), escape(value)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+            Block::List(items) => {
+                out.push_str("<ul style=\"margin:0 0 8px 0;padding-left:20px;\">\n");
+                for item in items {
+                    out.push_str(&format!("<li>{}</li>\n", escape(item)));
+                }
+                out.push_str("</ul>\n");
+            }
+        }
+    }
+    out.push_str("</div>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::adjustments::{AdjustmentScope, LumpAdjustment, SplitMethod};
+    use crate::commands::bid::PriceRounding;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    fn fixture_bid() -> (Vec<ShotData>, BidMeta) {
+        let bid: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(fixture("summary_bid.json")).unwrap()).unwrap();
+        (
+            serde_json::from_value(bid["shots"].clone()).unwrap(),
+            serde_json::from_value(bid["meta"].clone()).unwrap(),
+        )
+    }
+
+    fn assert_snapshot(rendered: &str, name: &str) {
+        let expected = std::fs::read_to_string(fixture(name)).unwrap();
+        assert_eq!(rendered, expected, "{} no longer matches; update the fixture if the change is intended", name);
+    }
+
+    #[test]
+    fn test_markdown_matches_snapshot() {
+        let (shots, meta) = fixture_bid();
+        let rendered = render_summary(&shots, &meta, &LocaleSettings::for_locale("en-US"), SummaryFormat::Markdown, &SummaryOptions::default());
+        assert_snapshot(&rendered, "summary_bid.md");
+    }
+
+    #[test]
+    fn test_html_matches_snapshot_and_is_self_contained() {
+        let (shots, meta) = fixture_bid();
+        let options = SummaryOptions { top_shots: 3, ..Default::default() };
+        let rendered = render_summary(&shots, &meta, &LocaleSettings::for_locale("de-DE"), SummaryFormat::Html, &options);
+        assert_snapshot(&rendered, "summary_bid.html");
+
+        assert!(!rendered.contains("<link") && !rendered.contains("<img") && !rendered.contains("<style"));
+        assert!(!rendered.contains("class="));
+    }
+
+    #[test]
+    fn test_internal_fields_never_appear() {
+        let (shots, meta) = fixture_bid();
+        for format in [SummaryFormat::Markdown, SummaryFormat::Html] {
+            let rendered = render_summary(&shots, &meta, &LocaleSettings::for_locale("en-US"), format, &SummaryOptions::default());
+            assert!(!rendered.contains("awaiting-plate"), "tag leaked");
+            assert!(!rendered.contains("Client keeps changing"), "task note leaked");
+            assert!(!rendered.contains("Margin is thin"), "internal assumption leaked");
+        }
+    }
+
+    #[test]
+    fn test_adjustments_get_their_own_lines() {
+        let (shots, mut meta) = fixture_bid();
+        let (shots, report) = crate::adjustments::plan_adjustment(&shots, &[], LumpAdjustment {
+            id: "adj-1".to_string(),
+            label: Some("Negotiated discount".to_string()),
+            amount: -1000.0,
+            scope: AdjustmentScope::AllShots,
+            method: SplitMethod::ProRata,
+            shares: Vec::new(),
+            applied_at: "2026-10-18T10:00:00Z".to_string(),
+        })
+        .unwrap();
+        meta.adjustments.push(report.adjustment);
+        meta.rounding = PriceRounding::None;
+
+        let rendered = render_summary(&shots, &meta, &LocaleSettings::for_locale("en-US"), SummaryFormat::Markdown, &SummaryOptions::default());
+        let money = |value: f64| format_currency(value, &meta.currency, &LocaleSettings::for_locale("en-US"));
+        assert!(rendered.contains(&format!("- **Before adjustments:** {}\n", money(report.previous_total))));
+        assert!(rendered.contains(&format!("- **Adjustment:** {} (Negotiated discount)\n", money(-1000.0))));
+        assert!(rendered.contains(&format!("- **Grand total:** {}\n", money(report.previous_total - 1000.0))));
+    }
+}
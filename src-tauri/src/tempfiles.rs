@@ -0,0 +1,226 @@
+//! Temporary files for downloads, autosaves and restored attachments
+//!
+//! Writing straight to the destination leaves a truncated file behind when
+//! the app dies mid-write, and on sandboxed macOS builds some destinations
+//! can't hold scratch files at all. Everything is written under
+//! `app_cache_dir()/tmp` instead, synced, and then moved into place.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+/// Temp files older than this are left over from a crash
+pub const ORPHAN_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const TEMP_EXTENSION: &str = "tmp";
+
+static TEMP_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` for temp files and clear out orphans left there
+///
+/// Called once at startup; later calls only clean up.
+pub fn init(dir: PathBuf) -> io::Result<usize> {
+    fs::create_dir_all(&dir)?;
+    let root = TEMP_ROOT.get_or_init(|| dir);
+    Ok(clean_orphans(root, ORPHAN_AGE))
+}
+
+/// Directory temp files go in, once `init` has run
+pub fn temp_root() -> Option<&'static Path> {
+    TEMP_ROOT.get().map(PathBuf::as_path)
+}
+
+/// A fresh path for building `destination`
+///
+/// Lives under the temp root, or next to the destination before `init`.
+pub fn temp_path_for(destination: &Path) -> PathBuf {
+    let dir = temp_root()
+        .map(Path::to_path_buf)
+        .or_else(|| destination.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    unique_path(&dir, destination)
+}
+
+fn unique_path(dir: &Path, destination: &Path) -> PathBuf {
+    let name = destination
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    dir.join(format!(".{}.{}.{}", name, uuid::Uuid::new_v4().simple(), TEMP_EXTENSION))
+}
+
+/// Write `bytes` to `destination` through a temp file
+///
+/// A crash mid-write leaves any previous copy intact.
+pub fn write_atomic(destination: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp = temp_path_for(destination);
+    if let Some(parent) = temp.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| file.write_all(bytes))
+        .and_then(|_| persist(&temp, destination));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Sync `temp` and move it to `destination`, replacing what is there
+pub fn persist(temp: &Path, destination: &Path) -> io::Result<()> {
+    persist_with(temp, destination, |from, to| fs::rename(from, to))
+}
+
+/// `persist` with the rename swapped out, so tests can fake another volume
+fn persist_with(
+    temp: &Path,
+    destination: &Path,
+    rename: impl Fn(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    fs::File::open(temp)?.sync_all()?;
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match rename(temp, destination) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_across(temp, destination, &rename)?,
+        Err(e) => return Err(e),
+    }
+
+    sync_dir(destination);
+    Ok(())
+}
+
+/// Copy to a temp file beside `destination`, then rename on that volume
+fn copy_across(
+    temp: &Path,
+    destination: &Path,
+    rename: &impl Fn(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let local = unique_path(destination.parent().unwrap_or(Path::new("")), destination);
+
+    let result = fs::copy(temp, &local)
+        .and_then(|_| fs::File::open(&local)?.sync_all())
+        .and_then(|_| rename(&local, destination));
+    if result.is_err() {
+        let _ = fs::remove_file(&local);
+        return result;
+    }
+
+    fs::remove_file(temp)
+}
+
+/// Make the rename itself durable; not every platform can open a directory
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let _ = fs::File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Remove temp files in `dir` not touched for `max_age`
+pub fn clean_orphans(dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+
+    entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == TEMP_EXTENSION))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() >= max_age)
+        })
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tempfiles-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("tmp")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_persist_replaces_destination() {
+        let dir = fixture();
+        let destination = dir.join("out").join("bid_autosave.json");
+        fs::create_dir_all(destination.parent().unwrap()).unwrap();
+        fs::write(&destination, "old").unwrap();
+
+        let temp = unique_path(&dir.join("tmp"), &destination);
+        fs::write(&temp, "new").unwrap();
+        persist(&temp, &destination).unwrap();
+
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "new");
+        assert!(!temp.exists());
+
+        // Before init the temp file sits next to the destination
+        let sibling = temp_path_for(&destination);
+        assert_eq!(sibling.parent(), destination.parent());
+        assert_ne!(sibling, temp_path_for(&destination));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_cross_device_rename_copies() {
+        let dir = fixture();
+        let destination = dir.join("models").join("model.gguf");
+        let temp = unique_path(&dir.join("tmp"), &destination);
+        fs::write(&temp, b"GGUF weights").unwrap();
+
+        // Renames out of tmp fail the way they do across volumes
+        let tmp_dir = dir.join("tmp");
+        let rename = |from: &Path, to: &Path| {
+            if from.starts_with(&tmp_dir) {
+                Err(io::Error::from(io::ErrorKind::CrossesDevices))
+            } else {
+                fs::rename(from, to)
+            }
+        };
+        persist_with(&temp, &destination, rename).unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), b"GGUF weights");
+        assert!(!temp.exists());
+        let leftovers: Vec<_> = fs::read_dir(dir.join("models")).unwrap().flatten().collect();
+        assert_eq!(leftovers.len(), 1);
+
+        // Other errors are passed on and leave the temp file for cleanup
+        let again = unique_path(&tmp_dir, &destination);
+        fs::write(&again, b"x").unwrap();
+        let denied = persist_with(&again, &destination, |_, _| Err(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert_eq!(denied.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert!(again.exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_clean_orphans_by_age() {
+        let dir = fixture();
+        let tmp = dir.join("tmp");
+        let stale = unique_path(&tmp, Path::new("model.gguf"));
+        fs::write(&stale, b"partial").unwrap();
+        fs::write(tmp.join("notes.txt"), b"keep").unwrap();
+
+        assert_eq!(clean_orphans(&tmp, ORPHAN_AGE), 0);
+        assert_eq!(clean_orphans(&tmp, Duration::ZERO), 1);
+        assert!(!stale.exists() && tmp.join("notes.txt").exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
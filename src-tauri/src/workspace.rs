@@ -0,0 +1,226 @@
+//! Per-bid working directories for the Python sidecar
+//!
+//! The sidecar's vector index, parsed scripts and default Excel output used
+//! to land in its own working directory, so two projects overwrote each
+//! other's index. Each bid now gets `app_data_dir()/workspaces/<bid-id>/`,
+//! passed to the sidecar as `workspace_dir`. A marker file records when the
+//! workspace was last used, which is what pruning goes by; another records
+//! the workbook the bid was loaded from, so reloading it keeps the bid id.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::attachments::sanitize;
+use crate::storage;
+
+/// Holds the RFC 3339 time the workspace was last used
+const LAST_USED_FILE: &str = ".last_used";
+
+/// Holds the path of the workbook the bid was last loaded from
+const SOURCE_FILE: &str = ".source";
+
+static WORKSPACES_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// A bid's workspace as listed in settings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct WorkspaceInfo {
+    /// Directory name; the bid id, made safe for the file system
+    pub id: String,
+    pub path: String,
+    pub bytes: u64,
+    pub last_used: Option<String>,
+}
+
+/// Workspaces unused for longer than the retention period, sent as the
+/// `workspaces-prune-pending` event so the user can confirm before they
+/// are deleted
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PrunePlan {
+    pub retention_days: u32,
+    pub workspaces: Vec<WorkspaceInfo>,
+    pub total_bytes: u64,
+}
+
+/// Keep workspaces under `root`; called once at startup
+pub fn init(root: PathBuf) {
+    let _ = WORKSPACES_ROOT.set(root);
+}
+
+/// Directory holding every workspace, once `init` has run
+pub fn root() -> Option<&'static Path> {
+    WORKSPACES_ROOT.get().map(PathBuf::as_path)
+}
+
+pub fn workspace_dir(root: &Path, bid_id: &str) -> PathBuf {
+    root.join(sanitize(bid_id))
+}
+
+/// Create the workspace for `bid_id` if needed and mark it used now
+pub fn prepare(root: &Path, bid_id: &str) -> Result<PathBuf, String> {
+    let dir = workspace_dir(root, bid_id);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create workspace {}: {}", dir.display(), e))?;
+    fs::write(dir.join(LAST_USED_FILE), chrono::Utc::now().to_rfc3339())
+        .map_err(|e| format!("Failed to mark workspace {} as used: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Note that the bid in `dir` was loaded from `workbook`
+pub fn record_source(dir: &Path, workbook: &Path) -> Result<(), String> {
+    fs::write(dir.join(SOURCE_FILE), workbook.to_string_lossy().as_bytes())
+        .map_err(|e| format!("Failed to record the workbook of workspace {}: {}", dir.display(), e))
+}
+
+/// Id of the bid `workbook` was last loaded as, if any workspace recorded it
+///
+/// Workspaces are named after their bid ids, which are UUIDs and so come
+/// through `sanitize` unchanged.
+pub fn bid_for_source(root: &Path, workbook: &Path) -> Option<String> {
+    list(root).into_iter().map(|workspace| workspace.id).find(|id| {
+        fs::read_to_string(root.join(id).join(SOURCE_FILE)).is_ok_and(|source| Path::new(&source) == workbook)
+    })
+}
+
+/// Every workspace under `root`, most recently used first
+pub fn list(root: &Path) -> Vec<WorkspaceInfo> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut workspaces: Vec<WorkspaceInfo> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let path = entry.path();
+            WorkspaceInfo {
+                id: entry.file_name().to_string_lossy().to_string(),
+                bytes: storage::disk_usage(&path),
+                last_used: last_used(&path).map(|time| time.to_rfc3339()),
+                path: path.to_string_lossy().to_string(),
+            }
+        })
+        .collect();
+
+    workspaces.sort_by(|a, b| b.last_used.cmp(&a.last_used).then_with(|| a.id.cmp(&b.id)));
+    workspaces
+}
+
+/// When the workspace was last used: the marker file, else the directory's
+/// modification time
+fn last_used(dir: &Path) -> Option<chrono::DateTime<chrono::Utc>> {
+    fs::read_to_string(dir.join(LAST_USED_FILE))
+        .ok()
+        .and_then(|text| chrono::DateTime::parse_from_rfc3339(text.trim()).ok())
+        .map(|time| time.with_timezone(&chrono::Utc))
+        .or_else(|| fs::metadata(dir).and_then(|m| m.modified()).ok().map(chrono::DateTime::from))
+}
+
+/// Workspaces not used in the last `retention_days`, except `keep`
+///
+/// A retention of 0 turns pruning off.
+pub fn stale(root: &Path, retention_days: u32, now: chrono::DateTime<chrono::Utc>, keep: Option<&Path>) -> Vec<WorkspaceInfo> {
+    if retention_days == 0 {
+        return Vec::new();
+    }
+    let cutoff = now - chrono::Duration::days(retention_days as i64);
+
+    list(root)
+        .into_iter()
+        .filter(|workspace| keep.is_none_or(|keep| Path::new(&workspace.path) != keep))
+        .filter(|workspace| last_used(Path::new(&workspace.path)).is_none_or(|used| used < cutoff))
+        .collect()
+}
+
+pub fn prune_plan(root: &Path, retention_days: u32, keep: Option<&Path>) -> PrunePlan {
+    let workspaces = stale(root, retention_days, chrono::Utc::now(), keep);
+    PrunePlan {
+        retention_days,
+        total_bytes: workspaces.iter().map(|w| w.bytes).sum(),
+        workspaces,
+    }
+}
+
+/// Delete the workspace called `id`; returns the bytes freed
+pub fn delete(root: &Path, id: &str) -> Result<u64, String> {
+    let dir = root.join(id);
+    if id.is_empty() || sanitize(id) != id || !dir.is_dir() {
+        return Err(format!("Workspace '{}' not found", id));
+    }
+
+    let bytes = storage::disk_usage(&dir);
+    fs::remove_dir_all(&dir)
+        .map_err(|e| format!("Failed to delete workspace '{}': {}", id, e))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("workspaces-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_prepare_list_and_delete() {
+        let root = temp_root();
+        let dir = prepare(&root, "bid/1").unwrap();
+        assert_eq!(dir, root.join("bid_1"));
+        fs::write(dir.join("index.bin"), vec![0u8; 100]).unwrap();
+        prepare(&root, "bid-2").unwrap();
+
+        let workspaces = list(&root);
+        assert_eq!(workspaces.len(), 2);
+        let first = workspaces.iter().find(|w| w.id == "bid_1").unwrap();
+        assert!(first.bytes >= 100);
+        assert!(first.last_used.is_some());
+
+        assert!(delete(&root, "../bid-2").is_err());
+        assert!(delete(&root, "missing").is_err());
+        assert!(delete(&root, "bid_1").unwrap() >= 100);
+        assert_eq!(list(&root).len(), 1);
+
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_reloaded_workbook_finds_its_bid() {
+        let root = temp_root();
+        let workbook = root.join("bid.xlsx");
+        let older = prepare(&root, "older").unwrap();
+        record_source(&older, &workbook).unwrap();
+        fs::write(older.join(LAST_USED_FILE), "2020-01-01T00:00:00Z").unwrap();
+        let newer = prepare(&root, "newer").unwrap();
+        record_source(&newer, &workbook).unwrap();
+
+        assert_eq!(bid_for_source(&root, &workbook).as_deref(), Some("newer"));
+        assert_eq!(bid_for_source(&root, &root.join("other.xlsx")), None);
+
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_stale_workspaces() {
+        let root = temp_root();
+        let old = prepare(&root, "old").unwrap();
+        let active = prepare(&root, "active").unwrap();
+        let recent = prepare(&root, "recent").unwrap();
+        let long_ago = (chrono::Utc::now() - chrono::Duration::days(120)).to_rfc3339();
+        fs::write(old.join(LAST_USED_FILE), &long_ago).unwrap();
+        fs::write(active.join(LAST_USED_FILE), &long_ago).unwrap();
+
+        let now = chrono::Utc::now();
+        let ids = |list: Vec<WorkspaceInfo>| list.into_iter().map(|w| w.id).collect::<Vec<_>>();
+        assert_eq!(ids(stale(&root, 90, now, Some(&active))), vec!["old"]);
+        assert_eq!(ids(stale(&root, 180, now, None)), Vec::<String>::new());
+        assert!(stale(&root, 0, now, None).is_empty());
+
+        let plan = prune_plan(&root, 90, Some(&recent));
+        assert_eq!(plan.workspaces.len(), 2);
+        assert_eq!(plan.total_bytes, plan.workspaces.iter().map(|w| w.bytes).sum::<u64>());
+
+        fs::remove_dir_all(root).ok();
+    }
+}
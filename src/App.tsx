@@ -7,6 +7,8 @@ import ChatWindow from './components/Chat/ChatWindow';
 import QuickGenerateView from './components/QuickGenerate/QuickGenerateView';
 import LiveSyncView from './components/LiveSync/LiveSyncView';
 import SetupWizard from './components/Setup/SetupWizard';
+import { useBidStore } from './stores/bidStore';
+import { scriptService, settingsService } from './services/tauri';
 import './App.css';
 
 /**
@@ -17,6 +19,7 @@ function App() {
   const [activeMode, setActiveMode] = useState<AppMode>('chat');
   const [isLoading, setIsLoading] = useState(true);
   const [showSetupWizard, setShowSetupWizard] = useState(false);
+  const [modelError, setModelError] = useState<string | null>(null);
 
   useEffect(() => {
     checkSetupStatus();
@@ -45,10 +48,48 @@ function App() {
       console.log('Script processing complete');
     });
 
+    // Fill the shot table as the sidecar extracts shots
+    const unlistenShotExtracted = scriptService.onShotExtracted((shot) => {
+      useBidStore.getState().upsertShot(shot);
+    });
+
+    const unlistenShotsReconciled = scriptService.onShotsReconciled(({ removed }) => {
+      useBidStore.getState().removeShots(removed);
+    });
+
     const unlistenCommandExecuting = listen('command-executing', (event) => {
       console.log(`Executing: ${event.payload}`);
     });
 
+    // The model failed its checksum, so the sidecar was not started
+    const unlistenModelCorrupt = listen<{ message: string }>('model-corrupt', (event) => {
+      setModelError(event.payload.message);
+    });
+
+    // The model file was still held by another process after several tries
+    const unlistenModelLocked = listen<{ message: string }>('model-locked', (event) => {
+      setModelError(event.payload.message);
+    });
+
+    // A model load was refused because too little memory is free
+    const unlistenInsufficientMemory = settingsService.onInsufficientMemory((event) => {
+      const alternatives = event.smaller_models.length > 0
+        ? ` Models that would fit: ${event.smaller_models.join(', ')}.`
+        : '';
+      setModelError(
+        `The model needs about ${event.needed_gb.toFixed(1)} GB of free memory, but only ` +
+        `${event.available_gb.toFixed(1)} GB is available. ${event.suggestion}${alternatives}`
+      );
+    });
+
+    // The sidecar's output stopped parsing, usually library noise on stdout
+    const unlistenProtocolDegraded = settingsService.onProtocolDegraded(() => {
+      setModelError(
+        'The Python sidecar is writing output the app cannot read, so some replies may be lost. ' +
+        'Restarting the sidecar usually clears this.'
+      );
+    });
+
     // Listen for setup completion
     const unlistenSetupComplete = listen('setup-complete', () => {
       setShowSetupWizard(false);
@@ -57,7 +98,13 @@ function App() {
     return () => {
       unlistenScriptStart.then((u) => u());
       unlistenScriptComplete.then((u) => u());
+      unlistenShotExtracted.then((u) => u());
+      unlistenShotsReconciled.then((u) => u());
       unlistenCommandExecuting.then((u) => u());
+      unlistenModelCorrupt.then((u) => u());
+      unlistenModelLocked.then((u) => u());
+      unlistenInsufficientMemory.then((u) => u());
+      unlistenProtocolDegraded.then((u) => u());
       unlistenSetupComplete.then((u) => u());
     };
   };
@@ -90,6 +137,11 @@ function App() {
 
   return (
     <AppLayout>
+      {modelError && (
+        <div className="bg-red-900/50 border-b border-red-700 text-red-200 px-4 py-2 text-sm">
+          {modelError}
+        </div>
+      )}
       <ModeSelector activeMode={activeMode} onModeChange={setActiveMode} />
       {renderContent()}
     </AppLayout>
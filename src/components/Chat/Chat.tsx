@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { errorMessage } from "../../services/tauri";
 import "./Chat.css";
 
 interface ChatMessage {
@@ -41,7 +42,7 @@ export default function Chat() {
         ...prev,
         {
           role: "system",
-          content: `Error: ${error}`,
+          content: `Error: ${errorMessage(error)}`,
           timestamp: Date.now(),
         },
       ]);
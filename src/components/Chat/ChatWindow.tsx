@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
 import { useChatStore } from '../../stores/chatStore';
 import { useBidStore } from '../../stores/bidStore';
-import { chatService } from '../../services/tauri';
+import { chatService, errorMessage } from '../../services/tauri';
 
 /**
  * Chat Window Component
@@ -59,7 +59,7 @@ export default function ChatWindow() {
       console.error('Failed to send message:', error);
       addMessage({
         role: 'system',
-        content: `Error: ${error instanceof Error ? error.message : 'Failed to send message'}`,
+        content: `Error: ${errorMessage(error)}`,
         timestamp: Date.now(),
       });
       setLoading(false);
@@ -1,7 +1,7 @@
 import { useState, useCallback } from 'react';
 import { open } from '@tauri-apps/plugin-dialog';
 import { useBidStore } from '../../stores/bidStore';
-import { scriptService } from '../../services/tauri';
+import { scriptService, errorMessage } from '../../services/tauri';
 
 /**
  * Quick Generate View Component
@@ -102,7 +102,7 @@ export default function QuickGenerateView() {
       }, 1000);
     } catch (error) {
       console.error('Failed to generate bid:', error);
-      alert(`Failed to generate bid: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      alert(`Failed to generate bid: ${errorMessage(error)}`);
       setIsProcessing(false);
     }
   };
@@ -11,6 +11,18 @@ interface SetupStatus {
   system?: SystemRequirements;
   model_configured: boolean;
   model_path?: string;
+  /** Bundle found in ~/VFX-BIDDING/offline_bundle/ */
+  offline_bundle?: string | null;
+  /** Steps done so far, including before the wizard was last closed */
+  completed_steps: SetupStep[];
+  /** Where to resume */
+  current_step: SetupStep;
+}
+
+interface OfflineInstall {
+  bundle_path: string;
+  packages: string[];
+  model_path: string;
 }
 
 interface PythonStatus {
@@ -20,6 +32,10 @@ interface PythonStatus {
   pip_available: boolean;
   packages_installed: string[];
   missing_packages: string[];
+  /** Installed version of each package in packages_installed */
+  package_versions?: Record<string, string>;
+  /** Installed below the minimum version */
+  outdated_packages?: { name: string; installed: string; minimum: string }[];
 }
 
 interface SystemRequirements {
@@ -32,13 +48,60 @@ interface SystemRequirements {
 }
 
 interface SetupProgress {
-  step: string;
+  step: SetupStep;
   message: string;
   percent: number;
+  /** Set during model download once the speed is known */
+  bytes_per_sec?: number | null;
+  eta_seconds?: number | null;
+  /** Set while a dropped download waits to resume */
+  retry_attempt?: number;
+  max_attempts?: number;
+  /** Set while installing from an offline bundle */
+  offline?: boolean;
+  /** Set when package installs failed */
+  errors?: InstallError[];
+}
+
+type InstallFailure = 'no_matching_distribution' | 'build_failed' | 'network_timeout' | 'externally_managed' | 'other';
+
+interface InstallError {
+  package: string;
+  reason: InstallFailure;
+  message: string;
+  hint: string | null;
+  /** pip's output */
+  details: string;
+}
+
+type SmokePhase = 'launch' | 'handshake' | 'health' | 'chat';
+
+interface SmokeTestResult {
+  passed: boolean;
+  timings: Array<{ phase: SmokePhase; millis: number }>;
+  failed_phase: SmokePhase | null;
+  error: string | null;
+  exit_code: number | null;
+  stderr_tail: string[];
+}
+
+type SetupStep = 'Welcome' | 'SystemCheck' | 'InstallDependencies' | 'DownloadModel' | 'Verify' | 'Complete';
+
+interface SetupTimings {
+  /** Every attempt at a step added together */
+  steps: Array<{ step: SetupStep; millis: number; attempts: number; succeeded: boolean }>;
+  total_millis: number;
 }
 
 type WizardStep = 'welcome' | 'system-check' | 'dependencies' | 'model' | 'complete';
 
+const RESUME_STEPS: Partial<Record<SetupStep, WizardStep>> = {
+  SystemCheck: 'system-check',
+  InstallDependencies: 'dependencies',
+  DownloadModel: 'model',
+  Complete: 'complete',
+};
+
 interface ModelDownloadInstructions {
   methods: Array<{
     name: string;
@@ -59,7 +122,12 @@ export default function SetupWizard() {
   const [error, setError] = useState<string | null>(null);
   const [modelInstructions, setModelInstructions] = useState<ModelDownloadInstructions | null>(null);
   const [selectedModelPath, setSelectedModelPath] = useState<string>('');
+  const [ramWarning, setRamWarning] = useState<string | null>(null);
   const [downloadUrl, setDownloadUrl] = useState<string>('');
+  const [smokeTest, setSmokeTest] = useState<SmokeTestResult | null>(null);
+  const [verifying, setVerifying] = useState(false);
+  const [timings, setTimings] = useState<SetupTimings | null>(null);
+  const [installErrors, setInstallErrors] = useState<InstallError[]>([]);
 
   useEffect(() => {
     checkInitialStatus();
@@ -79,6 +147,9 @@ export default function SetupWizard() {
       if (!status.is_first_run) {
         // Setup already complete, could redirect to main app
         setCurrentStep('complete');
+      } else if (status.completed_steps.length > 0) {
+        // Pick up where an earlier run of the wizard stopped
+        setCurrentStep(RESUME_STEPS[status.current_step] ?? 'welcome');
       }
 
       setLoading(false);
@@ -92,6 +163,9 @@ export default function SetupWizard() {
   const setupProgressListener = async () => {
     const unlisten = await listen<SetupProgress>('setup-progress', (event) => {
       setProgress(event.payload);
+      if (event.payload.errors) {
+        setInstallErrors(event.payload.errors);
+      }
     });
     return unlisten;
   };
@@ -138,6 +212,7 @@ export default function SetupWizard() {
   const handleInstallDependencies = async () => {
     try {
       setError(null);
+      setInstallErrors([]);
       const pythonPath = setupStatus?.python?.executable_path || 'python3';
       await invoke('install_python_dependencies', { pythonPath });
       setCurrentStep('model');
@@ -146,6 +221,20 @@ export default function SetupWizard() {
     }
   };
 
+  const handleInstallOfflineBundle = async () => {
+    try {
+      setError(null);
+      setInstallErrors([]);
+      const result: OfflineInstall = await invoke('install_from_offline_bundle', {
+        path: setupStatus?.offline_bundle ?? null
+      });
+      setSelectedModelPath(result.model_path);
+      setCurrentStep('complete');
+    } catch (err) {
+      setError(err as string);
+    }
+  };
+
   const handleSelectLocalFile = async () => {
     try {
       const path = await open({
@@ -158,7 +247,8 @@ export default function SetupWizard() {
 
       if (path && typeof path === 'string') {
         setSelectedModelPath(path);
-        await invoke('select_local_model', { path });
+        const selection = await invoke<{ path: string; ram_warning: string | null }>('select_local_model', { path });
+        setRamWarning(selection.ram_warning);
       }
     } catch (err) {
       console.error('Failed to select file:', err);
@@ -212,12 +302,24 @@ export default function SetupWizard() {
     }
   };
 
-  const handleCompleteSetup = async () => {
+  const handleCompleteSetup = async (skipSmokeTest = false) => {
+    setError(null);
     try {
-      await invoke('complete_setup_process');
+      if (!skipSmokeTest && !smokeTest?.passed) {
+        setVerifying(true);
+        const result: SmokeTestResult = await invoke('run_setup_smoke_test');
+        setSmokeTest(result);
+        if (!result.passed) {
+          return;
+        }
+      }
+      await invoke('complete_setup_process', { skipSmokeTest });
+      setTimings(await invoke<SetupTimings>('get_setup_timings'));
       // Could trigger app restart or redirect to main app
     } catch (err) {
       setError(err as string);
+    } finally {
+      setVerifying(false);
     }
   };
 
@@ -284,7 +386,20 @@ export default function SetupWizard() {
               </svg>
               <div>
                 <h3 className="text-red-400 font-medium mb-1">Error</h3>
-                <p className="text-red-300 text-sm">{error}</p>
+                {installErrors.length > 0 ? (
+                  installErrors.map((failure) => (
+                    <div key={failure.package} className="mb-2">
+                      <p className="text-red-300 text-sm">{failure.message}</p>
+                      {failure.hint && <p className="text-red-200 text-sm">{failure.hint}</p>}
+                      <details className="mt-1">
+                        <summary className="text-xs text-gray-400 cursor-pointer">pip output</summary>
+                        <pre className="bg-gray-900 rounded p-2 text-xs text-gray-400 overflow-x-auto max-h-48">{failure.details}</pre>
+                      </details>
+                    </div>
+                  ))
+                ) : (
+                  <p className="text-red-300 text-sm">{error}</p>
+                )}
               </div>
             </div>
           </div>
@@ -308,12 +423,26 @@ export default function SetupWizard() {
               {renderNextButton()}
             </div>
           )}
+          {currentStep === 'complete' && smokeTest && !smokeTest.passed && (
+            <button
+              onClick={() => handleCompleteSetup(true)}
+              disabled={verifying}
+              className="px-6 py-2 bg-gray-700 hover:bg-gray-600 rounded-lg transition-colors"
+            >
+              Finish Without Check
+            </button>
+          )}
           {currentStep === 'complete' && (
             <button
-              onClick={handleCompleteSetup}
-              className="px-8 py-3 bg-gradient-to-r from-blue-500 to-purple-500 hover:from-blue-600 hover:to-purple-600 rounded-lg font-semibold transition-all"
+              onClick={() => handleCompleteSetup()}
+              disabled={verifying}
+              className="ml-auto px-8 py-3 bg-gradient-to-r from-blue-500 to-purple-500 hover:from-blue-600 hover:to-purple-600 rounded-lg font-semibold transition-all"
             >
-              Start Using VFX Bidding AI
+              {verifying
+                ? 'Checking the Python sidecar...'
+                : smokeTest && !smokeTest.passed
+                  ? 'Run Check Again'
+                  : 'Start Using VFX Bidding AI'}
             </button>
           )}
         </div>
@@ -328,22 +457,31 @@ export default function SetupWizard() {
       case 'system-check':
         return <SystemCheckStep status={setupStatus} />;
       case 'dependencies':
-        return <DependenciesStep status={setupStatus} />;
+        return (
+          <>
+            <OfflineBundleNotice bundlePath={setupStatus?.offline_bundle} onInstall={handleInstallOfflineBundle} />
+            <DependenciesStep status={setupStatus} />
+          </>
+        );
       case 'model':
         return (
-          <ModelStep
-            instructions={modelInstructions}
-            selectedPath={selectedModelPath}
-            downloadUrl={downloadUrl}
-            onDownloadUrlChange={setDownloadUrl}
-            onSelectFile={handleSelectLocalFile}
-            onUseLocalFile={handleUseLocalFile}
-            onDownloadFromUrl={handleDownloadFromUrl}
-            onSkip={handleSkipModel}
-          />
+          <>
+            <OfflineBundleNotice bundlePath={setupStatus?.offline_bundle} onInstall={handleInstallOfflineBundle} />
+            <ModelStep
+              instructions={modelInstructions}
+              selectedPath={selectedModelPath}
+              ramWarning={ramWarning}
+              downloadUrl={downloadUrl}
+              onDownloadUrlChange={setDownloadUrl}
+              onSelectFile={handleSelectLocalFile}
+              onUseLocalFile={handleUseLocalFile}
+              onDownloadFromUrl={handleDownloadFromUrl}
+              onSkip={handleSkipModel}
+            />
+          </>
         );
       case 'complete':
-        return <CompleteStep />;
+        return <CompleteStep smokeTest={smokeTest} timings={timings} />;
       default:
         return null;
     }
@@ -527,6 +665,7 @@ function DependenciesStep({ status }: { status: SetupStatus | null }) {
 interface ModelStepProps {
   instructions: ModelDownloadInstructions | null;
   selectedPath: string;
+  ramWarning: string | null;
   downloadUrl: string;
   onDownloadUrlChange: (url: string) => void;
   onSelectFile: () => void;
@@ -538,6 +677,7 @@ interface ModelStepProps {
 function ModelStep({
   instructions,
   selectedPath,
+  ramWarning,
   downloadUrl,
   onDownloadUrlChange,
   onSelectFile,
@@ -613,6 +753,12 @@ function ModelStep({
             </div>
           )}
 
+          {ramWarning && (
+            <div className="p-4 bg-yellow-900/30 border border-yellow-700 rounded-lg">
+              <p className="text-yellow-300 text-sm">{ramWarning}</p>
+            </div>
+          )}
+
           <button
             onClick={onUseLocalFile}
             disabled={!selectedPath}
@@ -696,7 +842,27 @@ function ModelStep({
   );
 }
 
-function CompleteStep() {
+const SETUP_STEP_LABELS: Record<SetupStep, string> = {
+  Welcome: 'Welcome',
+  SystemCheck: 'System check',
+  InstallDependencies: 'Python packages',
+  DownloadModel: 'Model',
+  Verify: 'Sidecar check',
+  Complete: 'Finish',
+};
+
+function formatMillis(millis: number): string {
+  const seconds = Math.round(millis / 1000);
+  if (seconds < 60) return `${seconds}s`;
+  const minutes = Math.floor(seconds / 60);
+  return seconds % 60 ? `${minutes}m ${seconds % 60}s` : `${minutes}m`;
+}
+
+function CompleteStep({ smokeTest, timings }: { smokeTest: SmokeTestResult | null; timings: SetupTimings | null }) {
+  if (smokeTest && !smokeTest.passed) {
+    return <SmokeTestFailure result={smokeTest} />;
+  }
+
   return (
     <div className="bg-gray-800 rounded-xl p-8 text-center">
       <div className="w-20 h-20 bg-green-500 rounded-full flex items-center justify-center mx-auto mb-6">
@@ -711,6 +877,18 @@ function CompleteStep() {
         VFX Bidding AI is now ready to use. You can start analyzing scripts and generating bids.
       </p>
 
+      {timings && timings.steps.length > 0 && (
+        <div className="max-w-sm mx-auto mb-8 text-sm text-gray-400">
+          <div className="mb-2">Setup took {formatMillis(timings.total_millis)}</div>
+          {timings.steps.map((timing) => (
+            <div key={timing.step} className="flex justify-between">
+              <span>{SETUP_STEP_LABELS[timing.step]}{timing.attempts > 1 && ` (${timing.attempts} tries)`}</span>
+              <span>{formatMillis(timing.millis)}</span>
+            </div>
+          ))}
+        </div>
+      )}
+
       <div className="grid grid-cols-3 gap-4 max-w-lg mx-auto">
         <FeatureCard icon="📄" title="Upload Script" description="Import your screenplay" />
         <FeatureCard icon="🤖" title="AI Analysis" description="Auto-detect VFX shots" />
@@ -720,6 +898,59 @@ function CompleteStep() {
   );
 }
 
+const SMOKE_PHASE_LABELS: Record<SmokePhase, string> = {
+  launch: 'Starting Python',
+  handshake: 'Waiting for the sidecar to respond',
+  health: 'Health check',
+  chat: 'Test command',
+};
+
+function SmokeTestFailure({ result }: { result: SmokeTestResult }) {
+  return (
+    <div className="bg-gray-800 rounded-xl p-8">
+      <h2 className="text-2xl font-bold mb-4">The Python sidecar didn't start correctly</h2>
+
+      <p className="text-gray-300 mb-4">
+        Failed at: <span className="text-red-400 font-medium">
+          {result.failed_phase ? SMOKE_PHASE_LABELS[result.failed_phase] : 'Unknown'}
+        </span>
+        {result.exit_code !== null && <> (exit code {result.exit_code})</>}
+      </p>
+
+      {result.error && <p className="text-red-300 text-sm mb-4">{result.error}</p>}
+
+      {result.stderr_tail.length > 0 && (
+        <pre className="bg-gray-900 rounded-lg p-4 text-xs text-gray-400 overflow-x-auto max-h-64">
+          {result.stderr_tail.join('\n')}
+        </pre>
+      )}
+    </div>
+  );
+}
+
+function OfflineBundleNotice({ bundlePath, onInstall }: { bundlePath?: string | null; onInstall: () => void }) {
+  if (!bundlePath) {
+    return null;
+  }
+
+  return (
+    <div className="flex items-center justify-between p-4 mb-4 bg-green-900/30 border border-green-700 rounded-lg">
+      <div>
+        <h4 className="font-semibold text-green-400 mb-1">Offline bundle found</h4>
+        <p className="text-sm text-gray-300">
+          Install the Python packages and the AI model from <span className="font-mono">{bundlePath}</span> without a network connection.
+        </p>
+      </div>
+      <button
+        onClick={onInstall}
+        className="ml-4 px-4 py-2 bg-green-600 hover:bg-green-700 rounded-lg text-sm font-semibold whitespace-nowrap transition-colors"
+      >
+        Install from Bundle
+      </button>
+    </div>
+  );
+}
+
 function RequirementItem({
   label,
   met,
@@ -1,5 +1,5 @@
 import { useState } from 'react';
-import { bidService } from '../services/tauri';
+import { bidService, errorMessage } from '../services/tauri';
 import { useBidStore } from '../stores/bidStore';
 import type { Shot } from '../stores/bidStore';
 
@@ -9,7 +9,7 @@ export interface UseBidResult {
   error: string | null;
   refresh: () => Promise<void>;
   updateShot: (id: string, updates: Partial<Shot>) => Promise<void>;
-  deleteShot: (id: string) => Promise<void>;
+  deleteShot: (id: string, reason?: string) => Promise<void>;
   addShot: (shot: Shot) => Promise<void>;
 }
 
@@ -29,8 +29,7 @@ export function useBid(): UseBidResult {
       const allShots = await bidService.getAllShots();
       setShots(allShots);
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
     } finally {
       setIsLoading(false);
     }
@@ -50,24 +49,22 @@ export function useBid(): UseBidResult {
       await bidService.updateShot(id, updatedShot);
       updateStoreShot(id, updates);
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
       throw err;
     } finally {
       setIsLoading(false);
     }
   };
 
-  const deleteShot = async (id: string) => {
+  const deleteShot = async (id: string, reason?: string) => {
     setIsLoading(true);
     setError(null);
 
     try {
-      // TODO: Add delete command to backend
+      await bidService.deleteShot(id, reason);
       deleteStoreShot(id);
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
       throw err;
     } finally {
       setIsLoading(false);
@@ -82,8 +79,7 @@ export function useBid(): UseBidResult {
       // TODO: Add add command to backend
       addStoreShot(shot);
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
       throw err;
     } finally {
       setIsLoading(false);
@@ -1,5 +1,5 @@
 import { useState } from 'react';
-import { chatService } from '../services/tauri';
+import { chatService, errorMessage } from '../services/tauri';
 
 export interface UseLlmResult {
   isLoading: boolean;
@@ -23,8 +23,7 @@ export function useLlm(): UseLlmResult {
       const response = await chatService.sendMessage(message);
       return response;
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
       throw err;
     } finally {
       setIsLoading(false);
@@ -39,8 +38,7 @@ export function useLlm(): UseLlmResult {
       const response = await chatService.executeCommand({ command, args });
       return response;
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(errorMessage(err));
       throw err;
     } finally {
       setIsLoading(false);
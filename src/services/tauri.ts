@@ -1,7 +1,51 @@
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
-import type { Shot } from '../stores/bidStore';
-import type { Settings } from '../stores/settingsStore';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+import type { Shot, TaskLine } from '../stores/bidStore';
+import type { Settings, SidecarSettings } from '../stores/settingsStore';
+
+// Error returned by bid, chat and script commands
+export type AppErrorKind =
+  | 'sidecar_unavailable'
+  | 'not_ready'
+  | 'rpc_failed'
+  | 'not_found'
+  | 'validation'
+  | 'io'
+  | 'insufficient_memory'
+  | 'no_model'
+  | 'model_unavailable'
+  | 'read_only'
+  | 'unsupported_by_sidecar'
+  | 'sidecar_incompatible'
+  | 'backpressure';
+
+export interface AppError {
+  kind: AppErrorKind;
+  message: string;
+  code?: number;
+  needed_gb?: number;
+  available_gb?: number;
+  /** For unsupported_by_sidecar: the RPC method and the protocol it needs */
+  method?: string;
+  sidecar_version?: string | null;
+  required?: number;
+  /** For model_unavailable: why the model can't be opened */
+  reason?: ModelUnavailableReason;
+  path?: string;
+}
+
+export const isAppError = (err: unknown): err is AppError =>
+  typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+
+/**
+ * Readable message for anything a command can reject with
+ */
+export const errorMessage = (err: unknown): string => {
+  if (isAppError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  return String(err);
+};
 
 // Script Analysis Types
 export interface ScriptAnalysis {
@@ -11,15 +55,368 @@ export interface ScriptAnalysis {
     total_shots: number;
     vfx_categories: string[];
   };
+  /** Per-scene breakdown in natural scene order */
+  scenes?: SceneSummary[];
+  // 'rust-fallback' bids were read without Python and cannot be re-exported yet
+  source?: 'sidecar' | 'rust-fallback';
+  /** Excel bid written by processScript */
+  output_path?: string | null;
+  /** Scenes found in a TXT/Markdown script before extraction; null for PDFs */
+  detected_scenes?: number | null;
+  /** Shots the sidecar couldn't price; also in shots, flagged 'unpriced' */
+  unpriced_shots?: Shot[];
+  /** Problems that didn't stop the run */
+  warnings?: string[];
+  /** Reopened from an earlier run of the same script; see script-result-cached */
+  cached?: boolean;
+}
+
+/** Payload of script-result-cached: the earlier run that was reopened */
+export interface CachedScriptResult {
+  /** SHA-256 of the script's contents */
+  hash: string;
+  script_path: string;
+  excel_path: string;
+  shot_count: number;
+  processed_at: string;
+}
+
+export interface SceneSummary {
+  scene_number: string;
+  shot_count: number;
+  /** Sum of the scene's final prices, unrounded */
+  subtotal: number;
+  departments?: DepartmentTotal[];
+  /** Sum of the scene's frame counts, for shots that have one */
+  total_frames?: number;
+}
+
+/** Task hours and cost before markups for one department */
+export interface DepartmentTotal {
+  department: string;
+  hours: number;
+  cost: number;
+}
+
+/** CSV layout: one row per shot, or per task with optional shot subtotals */
+export type CsvRows = 'shots' | 'tasks' | 'tasks_with_subtotals';
+
+export type SummaryFormat = 'markdown' | 'html';
+
+export type ShotListFormat = 'shot_grid_csv' | 'simple_csv';
+
+export interface ShotListReport {
+  path: string;
+  exported: number;
+  /** Shots left out, with the required columns they had no value for */
+  skipped: { id: string; missing: string[] }[];
+}
+
+/** Sections of the bid summary; all are on by default */
+export interface SummaryOptions {
+  totals?: boolean;
+  complexity?: boolean;
+  /** Most expensive shots to list, 0 for none (default 5) */
+  top_shots?: number;
+  scenes?: boolean;
+  assumptions?: boolean;
+}
+
+export type ExportField =
+  | 'id' | 'scene_number' | 'description' | 'vfx_types' | 'complexity'
+  | 'estimated_hours' | 'rate_per_hour' | 'estimated_cost'
+  | 'contingency_percent' | 'overhead_percent' | 'final_price'
+  | 'frame_in' | 'frame_out' | 'frame_count' | 'fps' | 'tags';
+
+export interface ExportMapping {
+  name: string;
+  sheet_name: string;
+  // 1-based
+  header_row: number;
+  // Fields without a column are left out
+  columns: { field: ExportField; column: string; header?: string | null }[];
+  subtotals: 'none' | 'above_group' | 'below_group';
+}
+
+export interface ExportMappingList {
+  active: string;
+  builtin: ExportMapping[];
+  saved: ExportMapping[];
+}
+
+/** 'manual' is the bid's own (drag-and-drop) order */
+export type ShotOrder = 'manual' | 'scene' | 'id';
+
+/** Presentation rounding for final prices in totals and exports */
+export type PriceRounding = 'none' | 'nearest_dollar' | 'nearest10' | 'nearest100' | 'nearest500';
+
+export type BidStatus = 'draft' | 'sent' | 'revised' | 'approved' | 'expired';
+
+export interface BidMeta {
+  default_contingency: number;
+  default_overhead: number;
+  currency: string;
+  client_name: string | null;
+  project_name: string | null;
+  rounding?: PriceRounding;
+  /** Changed only through setBidStatus and reviseBid */
+  status?: BidStatus;
+  /** Last day the quote is valid, as YYYY-MM-DD */
+  valid_until?: string | null;
+  version?: number;
+  /** Assumptions, exclusions and notes, in display order */
+  assumptions?: AssumptionItem[];
+  /** Changed only through applyLumpAdjustment and removeAdjustment */
+  adjustments?: LumpAdjustment[];
+}
+
+export type AssumptionCategory = 'assumption' | 'exclusion' | 'note';
+
+export interface AssumptionItem {
+  id: string;
+  text: string;
+  category: AssumptionCategory;
+  /** Shown to the client in the Excel export and the summary */
+  included_in_export: boolean;
+}
+
+/** Boilerplate item in the assumption library */
+export interface LibraryItem {
+  /** Empty when saving a new item */
+  id: string;
+  text: string;
+  category: AssumptionCategory;
+}
+
+export interface ShotChange {
+  shot_id: string;
+  fields: string[];
+  old_price: number | null;
+  new_price: number | null;
+}
+
+export interface BidComparison {
+  base_version: number;
+  target_version: number;
+  added: string[];
+  removed: string[];
+  changed: ShotChange[];
+  base_total: number;
+  target_total: number;
+  difference: number;
+}
+
+export interface ShotFilter {
+  tags_any?: string[];
+  tags_all?: string[];
+}
+
+export interface ArchivedShot {
+  shot: Shot;
+  deleted_at: string;
+  reason: string | null;
+  groups: string[];
+}
+
+export interface RestoredShot {
+  shot: Shot;
+  warning: string | null;
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+export interface ShotAuditEntry {
+  timestamp: string;
+  shot_id: string;
+  /** ShotData field, or 'shot' when the shot was added or removed */
+  field: string;
+  old_value: unknown;
+  new_value: unknown;
+  changed_by?: string;
+}
+
+// Whether edits to the loaded bid are refused
+// A window and the bid open in it; context_id is what compareBids takes
+export interface BidWindow {
+  context_id: string;
+  bid_id: string;
+  project_name: string | null;
+  shot_count: number;
+  dirty: boolean;
+}
+
+/**
+ * A project tab of this window; bid commands accept its id as `projectId`
+ * and otherwise act on the active one
+ */
+export interface ProjectTab {
+  project_id: string;
+  bid_id: string;
+  project_name: string | null;
+  shot_count: number;
+  dirty: boolean;
+  active: boolean;
+}
+
+export interface ReadOnlyStatus {
+  read_only: boolean;
+  // Saved in the project file; needs unlockBid to clear
+  project_read_only: boolean;
+  dirty: boolean;
+}
+
+// The loaded bid's file compared with what's on disk
+export type SourceStatus =
+  | { status: 'untracked' }
+  | { status: 'in_sync' }
+  | {
+      status: 'modified';
+      path: string;
+      recorded_modified_at: string | null;
+      current_modified_at: string | null;
+      recorded_size: number;
+      current_size: number;
+    }
+  | { status: 'missing'; path: string; recorded_modified_at: string | null };
+
+export interface BidMetaUpdate {
+  meta: BidMeta;
+  affected_shots: number;
+  grand_total: number;
 }
 
 // Chat Types
+export interface BidTotals {
+  shot_count: number;
+  total_hours: number;
+  total_cost: number;
+  /** Sum of rounded final prices when rounding is active */
+  grand_total: number;
+  precise_grand_total: number;
+  rounding: PriceRounding;
+  currency: string;
+  status: BidStatus;
+  valid_until: string | null;
+  version: number;
+  expired: boolean;
+  /** Empty when no shot has tasks */
+  departments: DepartmentTotal[];
+  /** Sum of the frame counts of shots that have one */
+  total_frames: number;
+  /** How much of the bid rests on table-estimated rather than entered hours */
+  estimate_share: {
+    table_hours: number;
+    manual_hours: number;
+    table_total: number;
+    manual_total: number;
+  };
+  /** Lump-sum adjustments, already in grand_total */
+  adjustments: { id: string; label: string; amount: number }[];
+  display: {
+    total_hours: string;
+    total_cost: string;
+    grand_total: string;
+  };
+}
+
+export type EstimateStrategy = 'midpoint' | 'low' | 'high';
+
+export interface MissingHoursEstimate {
+  filled: number;
+  grand_total: number;
+}
+
+export type BudgetStrategy =
+  | { type: 'uniform' }
+  | { type: 'above_complexity'; threshold: string }
+  | { type: 'markups_only' };
+
+export interface ShotBudgetDelta {
+  shot_id: string;
+  old_hours: number | null;
+  new_hours: number | null;
+  old_price: number;
+  new_price: number;
+}
+
+export interface BudgetFitReport {
+  target: number;
+  previous_total: number;
+  new_total: number;
+  scale_factor: number;
+  deltas: ShotBudgetDelta[];
+  floored: string[];
+  excluded: string[];
+  dry_run: boolean;
+}
+
+export type AdjustmentScope =
+  | { type: 'all_shots' }
+  | { type: 'group'; name: string }
+  | { type: 'shot_ids'; ids: string[] };
+
+export type SplitMethod = 'pro_rata' | 'even';
+
+export interface LumpAdjustment {
+  id: string;
+  label: string | null;
+  /** Negative for a discount */
+  amount: number;
+  scope: AdjustmentScope;
+  method: SplitMethod;
+  /** What each shot took, in whole cents */
+  shares: { shot_id: string; amount: number }[];
+  applied_at: string;
+}
+
+export interface AdjustmentReport {
+  adjustment: LumpAdjustment;
+  previous_total: number;
+  new_total: number;
+  deltas: { shot_id: string; old_price: number; new_price: number }[];
+  /** Locked or unpriced shots in scope */
+  excluded: string[];
+  dry_run: boolean;
+}
+
+export interface ShotsReconciled {
+  confirmed: string[];
+  added: string[];
+  removed: string[];
+}
+
 export interface ChatMessage {
   role: string;
   content: string;
   timestamp: number;
 }
 
+export interface PendingShotChange {
+  shot_id: string;
+  scene_number: string;
+  complexity_before: string;
+  complexity_after: string;
+  price_before: number | null;
+  price_after: number | null;
+}
+
+/** A chat edit waiting for the user to confirm or reject it */
+export interface PendingChatAction {
+  id: string;
+  description: string;
+  affected_shot_ids: string[];
+  preview_diff: {
+    shots: PendingShotChange[];
+    total_before: number;
+    total_after: number;
+  };
+  /** RFC 3339 */
+  expires_at: string;
+}
+
 // Command Types
 export interface CommandRequest {
   command: string;
@@ -29,12 +426,86 @@ export interface CommandRequest {
 /**
  * Script Analysis Service
  */
+export interface ProcessingRun {
+  started_at: string;
+  script_hash: string;
+  page_count: number | null;
+  word_count: number | null;
+  shot_count: number;
+  /** Milliseconds per stage, e.g. process_script and load_bid */
+  stages: Record<string, number>;
+  total_millis: number;
+  model: string;
+  app_version: string;
+  success: boolean;
+  error: string | null;
+}
+
+export interface ScriptScan {
+  shot_count: number;
+  categories: string[];
+  page_count: number | null;
+  estimate: ProcessingEstimate;
+}
+
+export interface ProcessingEstimate {
+  size: {
+    page_count: number | null;
+    word_count: number | null;
+    words_estimated: boolean;
+  };
+  runs_used: number;
+  expected_secs: number | null;
+  low_secs: number | null;
+  high_secs: number | null;
+  confidence: 'unknown' | 'low' | 'medium' | 'high';
+}
+
 export const scriptService = {
   /**
-   * Process a script file and extract VFX shots
+   * Process a script file and extract VFX shots; the bid goes in outputDir,
+   * else the output folder setting. A script processed before reopens its
+   * earlier bid unless force is set.
+   */
+  processScript: async (filePath: string, outputDir?: string, force?: boolean): Promise<ScriptAnalysis> => {
+    return await invoke('process_script', { filePath, outputDir, force });
+  },
+
+  /**
+   * Stop a running processScript; resolves false when nothing was running.
+   * The processScript call then rejects with an rpc_failed error.
+   */
+  cancelProcessing: async (): Promise<boolean> => {
+    return await invoke('cancel_script_processing');
+  },
+
+  /**
+   * Script processing was cancelled; the payload is the script path
+   */
+  onProcessingCancelled: (callback: (filePath: string) => void) => {
+    return listen<string>('script-processing-cancelled', (event) => callback(event.payload));
+  },
+
+  /**
+   * Past processing runs, newest first (50 unless limit says otherwise)
+   */
+  getProcessingHistory: async (limit?: number): Promise<ProcessingRun[]> => {
+    return await invoke('get_processing_history', { limit });
+  },
+
+  /**
+   * Expected processing time for a script, from past runs' throughput
+   */
+  estimateProcessingTime: async (scriptPath: string): Promise<ProcessingEstimate> => {
+    return await invoke('estimate_processing_time', { scriptPath });
+  },
+
+  /**
+   * Count a script's candidate VFX shots without pricing them, with the
+   * expected time of a full run
    */
-  processScript: async (filePath: string): Promise<ScriptAnalysis> => {
-    return await invoke('process_script', { filePath });
+  scanScript: async (path: string): Promise<ScriptScan> => {
+    return await invoke('scan_script', { path });
   },
 
   /**
@@ -44,6 +515,14 @@ export const scriptService = {
     return await invoke('load_bid', { filePath });
   },
 
+  /**
+   * Open a bid workbook in the system's default app, by default the one
+   * processScript last generated; returns the path opened
+   */
+  openBidFile: async (path?: string): Promise<string> => {
+    return await invoke('open_bid_file', { path });
+  },
+
   /**
    * Export bid to Excel
    */
@@ -51,6 +530,90 @@ export const scriptService = {
     return await invoke('export_bid', { outputPath });
   },
 
+  /**
+   * Export the shot list as CSV, one row per shot unless rows says otherwise;
+   * tags and archived shots are left out unless includeTags/includeArchived are set
+   */
+  exportBidCsv: async (outputPath: string, rows?: CsvRows, includeTags?: boolean, includeArchived?: boolean): Promise<string> => {
+    return await invoke('export_bid_csv', { outputPath, rows, includeTags, includeArchived });
+  },
+
+  /**
+   * Export the shot list for editorial or a ShotGrid import; mapping names an
+   * export mapping whose headers rename the columns
+   */
+  exportShotList: async (format: ShotListFormat, outputPath: string, mapping?: string): Promise<ShotListReport> => {
+    return await invoke('export_shot_list', { format, outputPath, mapping });
+  },
+
+  /**
+   * Render a Markdown or self-contained HTML summary of the bid for email,
+   * also writing it to outputPath when given
+   */
+  generateBidSummary: async (format: SummaryFormat, options?: SummaryOptions, outputPath?: string): Promise<string> => {
+    return await invoke('generate_bid_summary', { format, options, outputPath });
+  },
+
+  /**
+   * Built-in and saved export column layouts
+   */
+  listExportMappings: async (): Promise<ExportMappingList> => {
+    return await invoke('list_export_mappings');
+  },
+
+  /**
+   * Save a studio layout; id, description and price must be mapped
+   */
+  saveExportMapping: async (mapping: ExportMapping): Promise<ExportMappingList> => {
+    return await invoke('save_export_mapping', { mapping });
+  },
+
+  /**
+   * Use a layout for Excel and CSV exports
+   */
+  setActiveExportMapping: async (name: string): Promise<ExportMapping> => {
+    return await invoke('set_active_export_mapping', { name });
+  },
+
+  /**
+   * Import shots from CSV; locale (e.g. "de-DE") declares the file's number format
+   */
+  importBidCsv: async (filePath: string, locale?: string): Promise<Shot[]> => {
+    return await invoke('import_bid_csv', { filePath, locale });
+  },
+
+  /**
+   * Compare the bid's file with disk; call on window focus
+   */
+  checkSourceSync: async (): Promise<SourceStatus> => {
+    return await invoke('check_source_sync');
+  },
+
+  /**
+   * Load the bid's file again, dropping in-app edits
+   */
+  reloadFromDisk: async (): Promise<Shot[]> => {
+    return await invoke('reload_from_disk');
+  },
+
+  /**
+   * Let the next export write over the externally changed file
+   */
+  overwriteOnExport: async (): Promise<void> => {
+    return await invoke('overwrite_on_export');
+  },
+
+  /**
+   * Export beside the bid's file as name_vN; Excel bids need a template
+   */
+  exportAsNewVersion: async (templatePath?: string, includeTags?: boolean): Promise<string> => {
+    return await invoke('export_as_new_version', { templatePath, includeTags });
+  },
+
+  onSourceFileDiverged: (callback: (status: SourceStatus) => void) => {
+    return listen('source-file-diverged', (event) => callback(event.payload as SourceStatus));
+  },
+
   /**
    * Listen for script processing events
    */
@@ -61,6 +624,20 @@ export const scriptService = {
   onScriptProcessingComplete: (callback: (analysis: ScriptAnalysis) => void) => {
     return listen('script-processing-complete', (event) => callback(event.payload as ScriptAnalysis));
   },
+
+  /**
+   * Listen for shots streamed while a script is processing (provisional)
+   */
+  onShotExtracted: (callback: (shot: Shot) => void) => {
+    return listen('shot-extracted', (event) => callback(event.payload as Shot));
+  },
+
+  /**
+   * Listen for the final shot list replacing the provisional shots
+   */
+  onShotsReconciled: (callback: (changes: ShotsReconciled) => void) => {
+    return listen('shots-reconciled', (event) => callback(event.payload as ShotsReconciled));
+  },
 };
 
 /**
@@ -68,10 +645,33 @@ export const scriptService = {
  */
 export const chatService = {
   /**
-   * Send a message to the LLM
+   * Send a message to the LLM, optionally about just `shotIds`
+   */
+  sendMessage: async (message: string, shotIds?: string[]): Promise<string> => {
+    const reply = await invoke<{ content: string; handled_by: 'local' | 'sidecar' }>('send_message', { message, shotIds });
+    return reply.content;
+  },
+
+  /**
+   * Apply a pending chat edit; fails with kind 'stale_action' once it has
+   * expired or the bid changed underneath it
+   */
+  confirmChatAction: async (id: string): Promise<string> => {
+    return await invoke('confirm_chat_action', { id });
+  },
+
+  /**
+   * Discard a pending chat edit
+   */
+  rejectChatAction: async (id: string): Promise<void> => {
+    return await invoke('reject_chat_action', { id });
+  },
+
+  /**
+   * Listen for chat edits held for confirmation
    */
-  sendMessage: async (message: string): Promise<string> => {
-    return await invoke('send_message', { message });
+  onChatActionPending: (callback: (action: PendingChatAction) => void) => {
+    return listen('chat-action-pending', (event) => callback(event.payload as PendingChatAction));
   },
 
   /**
@@ -114,6 +714,27 @@ export const bidService = {
     return await invoke('update_shot', { id, updates });
   },
 
+  /**
+   * Audited changes to a shot, one entry per changed field, oldest first
+   */
+  getShotHistory: async (id: string): Promise<ShotAuditEntry[]> => {
+    return await invoke('get_shot_history', { id });
+  },
+
+  /**
+   * Shots to double-check, least confident and most flagged first
+   */
+  getReviewQueue: async (): Promise<Shot[]> => {
+    return await invoke('get_review_queue');
+  },
+
+  /**
+   * Mark a shot as checked, taking it out of the review queue
+   */
+  markReviewed: async (shotId: string): Promise<Shot> => {
+    return await invoke('mark_reviewed', { shotId });
+  },
+
   /**
    * Group shots for batch operations
    */
@@ -128,35 +749,868 @@ export const bidService = {
   },
 
   /**
-   * Get all shots
+   * Get all shots, in bid order unless another order is asked for
    */
-  getAllShots: async (): Promise<Shot[]> => {
-    return await invoke('get_all_shots');
+  getAllShots: async (order?: ShotOrder): Promise<Shot[]> => {
+    return await invoke('get_all_shots', { order });
   },
-};
 
-/**
- * Settings Service
- */
-export const settingsService = {
   /**
-   * Get current settings
+   * Shots with any of tagsAny and all of tagsAll, in bid order
    */
-  getSettings: async (): Promise<Settings> => {
-    return await invoke('get_settings');
+  queryShots: async (filter: ShotFilter): Promise<Shot[]> => {
+    return await invoke('query_shots', { filter: { tags_any: filter.tags_any ?? [], tags_all: filter.tags_all ?? [] } });
   },
 
   /**
-   * Update settings
+   * Add workflow tags to shots; tags are trimmed and lowercased
    */
-  updateSettings: async (settings: Settings): Promise<void> => {
-    await invoke('update_settings', { settings });
+  addTags: async (shotIds: string[], tags: string[]): Promise<Shot[]> => {
+    return await invoke('add_tags', { shotIds, tags });
   },
 
   /**
-   * Test LLM connection
+   * Remove tags from shots
    */
-  testLlmConnection: async (settings: Settings): Promise<string> => {
-    return await invoke('test_llm_connection', { settings });
+  removeTags: async (shotIds: string[], tags: string[]): Promise<Shot[]> => {
+    return await invoke('remove_tags', { shotIds, tags });
+  },
+
+  /**
+   * Every tag in the bid with its shot count, most used first
+   */
+  getAllTags: async (): Promise<TagCount[]> => {
+    return await invoke('get_all_tags');
+  },
+
+  /**
+   * Put the shots in the given order; every shot id must appear once
+   */
+  reorderShots: async (orderedIds: string[]): Promise<Shot[]> => {
+    return await invoke('reorder_shots', { orderedIds });
+  },
+
+  /**
+   * Move a shot in front of another, or to the end without beforeId
+   */
+  moveShot: async (id: string, beforeId?: string): Promise<Shot[]> => {
+    return await invoke('move_shot', { id, beforeId });
+  },
+
+  /**
+   * Restore the order from before the last reorder or move
+   */
+  undoShotOrder: async (): Promise<Shot[]> => {
+    return await invoke('undo_shot_order');
+  },
+
+  /**
+   * Move a shot to the archive, out of totals and exports
+   */
+  deleteShot: async (id: string, reason?: string): Promise<ArchivedShot> => {
+    return await invoke('delete_shot', { id, reason });
+  },
+
+  getArchivedShots: async (): Promise<ArchivedShot[]> => {
+    return await invoke('get_archived_shots');
+  },
+
+  /**
+   * Bring an archived shot back; a reused id gets a suffix and a warning
+   */
+  restoreShot: async (id: string): Promise<RestoredShot> => {
+    return await invoke('restore_shot', { id });
+  },
+
+  /**
+   * Permanently drop archived shots older than the given days; returns how many
+   */
+  purgeArchived: async (olderThanDays: number): Promise<number> => {
+    return await invoke('purge_archived', { olderThanDays });
+  },
+
+  undoArchiveChange: async (): Promise<Shot[]> => {
+    return await invoke('undo_archive_change');
+  },
+
+  redoArchiveChange: async (): Promise<Shot[]> => {
+    return await invoke('redo_archive_change');
+  },
+
+  /**
+   * Replace a shot's department breakdown; an empty list returns it to flat hours
+   */
+  setShotTasks: async (id: string, tasks: TaskLine[]): Promise<Shot> => {
+    return await invoke('set_shot_tasks', { id, tasks });
+  },
+
+  /**
+   * Give shots the breakdown of a task template from the pricing settings
+   */
+  applyTaskTemplate: async (shotIds: string[], templateName: string): Promise<Shot[]> => {
+    return await invoke('apply_task_template', { shotIds, templateName });
+  },
+
+  /**
+   * Get bid-level defaults, currency and price rounding
+   */
+  getBidMeta: async (): Promise<BidMeta> => {
+    return await invoke('get_bid_meta');
+  },
+
+  /**
+   * Update bid-level defaults; shots on the old defaults follow the new ones
+   */
+  updateBidMeta: async (meta: BidMeta): Promise<BidMetaUpdate> => {
+    return await invoke('update_bid_meta', { meta });
+  },
+
+  /**
+   * Move the bid through its lifecycle; illegal transitions are rejected
+   */
+  setBidStatus: async (status: BidStatus): Promise<BidMeta> => {
+    return await invoke('set_bid_status', { status });
+  },
+
+  /**
+   * Reopen a sent or approved bid as the next draft version, keeping a copy
+   */
+  reviseBid: async (): Promise<BidMeta> => {
+    return await invoke('revise_bid');
+  },
+
+  /**
+   * Add an assumption, exclusion or note to the end of the list
+   */
+  addAssumption: async (text: string, category: AssumptionCategory, includedInExport?: boolean): Promise<AssumptionItem[]> => {
+    return await invoke('add_assumption', { text, category, includedInExport });
+  },
+
+  updateAssumption: async (item: AssumptionItem): Promise<AssumptionItem[]> => {
+    return await invoke('update_assumption', { item });
+  },
+
+  removeAssumption: async (id: string): Promise<AssumptionItem[]> => {
+    return await invoke('remove_assumption', { id });
+  },
+
+  /**
+   * Reorder the list; `ids` must name every item once
+   */
+  reorderAssumptions: async (ids: string[]): Promise<AssumptionItem[]> => {
+    return await invoke('reorder_assumptions', { ids });
+  },
+
+  getAssumptionLibrary: async (): Promise<LibraryItem[]> => {
+    return await invoke('get_assumption_library');
+  },
+
+  saveLibraryAssumption: async (item: LibraryItem): Promise<LibraryItem[]> => {
+    return await invoke('save_library_assumption', { item });
+  },
+
+  deleteLibraryAssumption: async (id: string): Promise<LibraryItem[]> => {
+    return await invoke('delete_library_assumption', { id });
+  },
+
+  /**
+   * Add library items to the bid, as new items in the order given
+   */
+  insertLibraryAssumptions: async (ids: string[]): Promise<AssumptionItem[]> => {
+    return await invoke('insert_library_assumptions', { ids });
+  },
+
+  /**
+   * Diff two bid versions, or the bids of two windows; versions default to
+   * the current ones and contexts to this window
+   */
+  compareBids: async (
+    baseVersion?: number,
+    targetVersion?: number,
+    baseContext?: string,
+    targetContext?: string,
+  ): Promise<BidComparison> => {
+    return await invoke('compare_bids', { baseVersion, targetVersion, baseContext, targetContext });
+  },
+
+  /**
+   * Open a project file in a new window; returns its context id
+   */
+  openBidInNewWindow: async (path: string): Promise<string> => {
+    return await invoke('open_bid_in_new_window', { path });
+  },
+
+  /**
+   * Every window with its bid, the main one first
+   */
+  listBidWindows: async (): Promise<BidWindow[]> => {
+    return await invoke('list_bid_windows');
+  },
+
+  /**
+   * Close this bid window; fails on unsaved changes, in the bid or any of
+   * its project tabs, unless discardChanges
+   */
+  closeBidWindow: async (discardChanges: boolean): Promise<void> => {
+    return await invoke('close_bid_window', { discardChanges });
+  },
+
+  /**
+   * Listen for this window being closed with unsaved changes; the status
+   * is the window's own bid's, with the ids of any unsaved project tabs
+   */
+  onWindowCloseRequested: (callback: (status: ReadOnlyStatus & { dirty_projects: string[] }) => void) => {
+    return getCurrentWebviewWindow().listen('bid-window-close-requested', (event) => callback(event.payload as ReadOnlyStatus & { dirty_projects: string[] }));
+  },
+
+  /**
+   * Open an empty project tab in this window and make it active
+   */
+  createProject: async (): Promise<string> => {
+    return await invoke('create_project');
+  },
+
+  /**
+   * Show another project; this window's label goes back to its own bid
+   */
+  switchProject: async (projectId: string): Promise<void> => {
+    return await invoke('switch_project', { projectId });
+  },
+
+  /**
+   * Close a project tab; fails on unsaved changes unless discardChanges
+   */
+  closeProject: async (projectId: string, discardChanges: boolean): Promise<void> => {
+    return await invoke('close_project', { projectId, discardChanges });
+  },
+
+  /**
+   * This window's own bid and its project tabs
+   */
+  listProjects: async (): Promise<ProjectTab[]> => {
+    return await invoke('list_projects');
+  },
+
+  /**
+   * Listen for the active project of this window changing
+   */
+  onActiveProjectChanged: (callback: (projectId: string) => void) => {
+    return getCurrentWebviewWindow().listen('active-project-changed', (event) => callback(event.payload as string));
+  },
+
+  /**
+   * Listen for a loaded bid whose quote has passed its valid-until date
+   */
+  onBidExpired: (callback: (info: { project_name: string | null; status: BidStatus; valid_until: string }) => void) => {
+    return listen('bid-expired', (event) => callback(event.payload as { project_name: string | null; status: BidStatus; valid_until: string }));
+  },
+
+  /**
+   * Whether the loaded bid is read-only
+   */
+  getBidReadonly: async (): Promise<ReadOnlyStatus> => {
+    return await invoke('get_bid_readonly');
+  },
+
+  /**
+   * Turn read-only on or off; with persist the flag is saved with the project
+   */
+  setBidReadonly: async (enabled: boolean, persist?: boolean): Promise<ReadOnlyStatus> => {
+    return await invoke('set_bid_readonly', { enabled, persist });
+  },
+
+  /**
+   * Clear read-only, including a flag saved in the project file
+   */
+  unlockBid: async (): Promise<ReadOnlyStatus> => {
+    return await invoke('unlock_bid');
+  },
+
+  /**
+   * Open a project file without allowing edits
+   */
+  openProjectReadonly: async (path: string): Promise<{ bid_id: string; meta: BidMeta; shots: Shot[]; read_only: boolean }> => {
+    return await invoke('open_project_readonly', { path });
+  },
+
+  /**
+   * Listen for read-only being switched or a project loading locked
+   */
+  onReadonlyChanged: (callback: (status: ReadOnlyStatus) => void) => {
+    return listen('bid-readonly-changed', (event) => callback(event.payload as ReadOnlyStatus));
+  },
+
+  /**
+   * Remove every shot and group; the settings and model stay loaded
+   */
+  clearBid: async (confirm: boolean, keepRevisions?: boolean): Promise<BidTotals> => {
+    return await invoke('clear_bid', { confirm, keepRevisions });
+  },
+
+  /**
+   * Listen for the bid being cleared
+   */
+  onBidChanged: (callback: (totals: BidTotals) => void) => {
+    return listen('bid-changed', (event) => callback(event.payload as BidTotals));
+  },
+
+  /**
+   * Get bid totals with display strings in the user's locale
+   */
+  getBidTotals: async (): Promise<BidTotals> => {
+    return await invoke('get_bid_totals');
+  },
+
+  /**
+   * Fit the bid total to a budget; with dryRun only the report is returned
+   */
+  fitToBudget: async (
+    target: number,
+    strategy: BudgetStrategy,
+    dryRun = false,
+  ): Promise<BudgetFitReport> => {
+    return await invoke('fit_to_budget', { target, strategy, dryRun });
+  },
+
+  /**
+   * Spread a lump sum (negative for a discount) over shots, to the cent;
+   * with dryRun only the report is returned
+   */
+  applyLumpAdjustment: async (
+    amount: number,
+    scope: AdjustmentScope,
+    method: SplitMethod = 'pro_rata',
+    label: string | null = null,
+    dryRun = false,
+  ): Promise<AdjustmentReport> => {
+    return await invoke('apply_lump_adjustment', { amount, scope, method, label, dryRun });
+  },
+
+  /**
+   * Undo a lump-sum adjustment
+   */
+  removeAdjustment: async (id: string): Promise<LumpAdjustment> => {
+    return await invoke('remove_adjustment', { id });
+  },
+
+  /**
+   * Fill blank hours from the hour ranges in the pricing settings
+   */
+  estimateMissingHours: async (strategy: EstimateStrategy = 'midpoint'): Promise<MissingHoursEstimate> => {
+    return await invoke('estimate_missing_hours', { strategy });
+  },
+
+  /**
+   * Attach a reference image to a shot, returning the stored path
+   */
+  attachReference: async (shotId: string, filePath: string): Promise<string> => {
+    return await invoke('attach_reference', { shotId, filePath });
+  },
+
+  /**
+   * Remove a reference image from a shot
+   */
+  removeReference: async (shotId: string, attachmentId: string): Promise<void> => {
+    await invoke('remove_reference', { shotId, attachmentId });
+  },
+
+  /**
+   * Get a URL the webview can load for a reference image
+   */
+  getReferenceUrl: async (shotId: string, attachmentId: string): Promise<string> => {
+    const path = await invoke<string>('get_reference_path', { shotId, attachmentId });
+    return convertFileSrc(path);
+  },
+};
+
+export interface PythonInterpreter {
+  path: string;
+  version: string | null;
+  packages_ok: boolean;
+  missing_packages: string[];
+}
+
+/**
+ * Settings Service
+ */
+export type StorageCategory = 'cache' | 'attachments' | 'autosaves' | 'models' | 'logs' | 'workspaces';
+
+// A bid's sidecar working directory
+export interface WorkspaceInfo {
+  id: string;
+  path: string;
+  bytes: number;
+  last_used: string | null;
+}
+
+export interface WorkspacePrunePlan {
+  retention_days: number;
+  workspaces: WorkspaceInfo[];
+  total_bytes: number;
+}
+
+export interface CategoryUsage {
+  category: StorageCategory;
+  bytes: number;
+  paths: string[];
+  clearable: boolean;
+}
+
+export interface StorageUsage {
+  categories: CategoryUsage[];
+  total_bytes: number;
+}
+
+export const settingsService = {
+  /**
+   * Get current settings
+   */
+  getSettings: async (): Promise<Settings> => {
+    return await invoke('get_settings');
+  },
+
+  /**
+   * Update settings
+   *
+   * Changed LLM parameters are sent to the running sidecar unless
+   * `configureSidecar` is false; they always apply from the next start.
+   */
+  updateSettings: async (settings: Settings, configureSidecar?: boolean): Promise<void> => {
+    await invoke('update_settings', { settings, configureSidecar });
+  },
+
+  /**
+   * Get per-method RPC timeouts
+   */
+  getSidecarSettings: async (): Promise<SidecarSettings> => {
+    return await invoke('get_sidecar_settings');
+  },
+
+  /**
+   * Update per-method RPC timeouts (each 1 s to 4 h)
+   */
+  updateSidecarSettings: async (sidecar: SidecarSettings): Promise<SidecarSettings> => {
+    return await invoke('update_sidecar_settings', { sidecar });
+  },
+
+  /**
+   * Disk space used by the cache, attachments, autosaves, models and logs
+   */
+  getStorageUsage: async (): Promise<StorageUsage> => {
+    return await invoke('get_storage_usage');
+  },
+
+  /**
+   * Free the space used by cache, autosaves or logs
+   */
+  clearCache: async (categories: StorageCategory[]): Promise<StorageUsage> => {
+    return await invoke('clear_cache', { categories });
+  },
+
+  /**
+   * Per-bid sidecar workspaces, most recently used first
+   */
+  listWorkspaces: async (): Promise<WorkspaceInfo[]> => {
+    return await invoke('list_workspaces');
+  },
+
+  /**
+   * Delete one workspace; returns the bytes freed
+   */
+  deleteWorkspace: async (id: string): Promise<number> => {
+    return await invoke('delete_workspace', { id });
+  },
+
+  /**
+   * Delete the confirmed workspaces from a prune offer; returns the bytes freed
+   */
+  pruneWorkspaces: async (ids: string[]): Promise<number> => {
+    return await invoke('prune_workspaces', { ids });
+  },
+
+  onWorkspacesPrunePending: (callback: (plan: WorkspacePrunePlan) => void) => {
+    return listen<WorkspacePrunePlan>('workspaces-prune-pending', (event) => callback(event.payload));
+  },
+
+  /**
+   * Point the app at a different GGUF model and reload the sidecar
+   */
+  setModelPath: async (path: string): Promise<string> => {
+    return await invoke('set_model_path', { path });
+  },
+
+  /**
+   * Look for the model on known paths, the model folder and mounted drives
+   */
+  rescanForModel: async (): Promise<FoundModel[]> => {
+    return await invoke('rescan_for_model');
+  },
+
+  /**
+   * Switch to a copy found by rescanForModel and reload the sidecar
+   */
+  setActiveModel: async (path: string): Promise<string> => {
+    return await invoke('set_active_model', { path });
+  },
+
+  /**
+   * Available memory against what the configured model needs
+   */
+  getMemoryStatus: async (): Promise<MemoryStatus> => {
+    return await invoke('get_memory_status');
+  },
+
+  /**
+   * Architecture, size, quant and context of the model, from the sidecar or
+   * the GGUF header
+   */
+  getModelInfo: async (): Promise<ModelInfo> => {
+    return await invoke('get_model_info');
+  },
+
+  /**
+   * Whether the sidecar is running and ready, and the protocol it speaks
+   */
+  getSidecarStatus: async (): Promise<SidecarStatus> => {
+    return await invoke('get_sidecar_status');
+  },
+
+  /**
+   * Which features work without a model or a running sidecar
+   */
+  getCapabilities: async (): Promise<Capabilities> => {
+    return await invoke('get_capabilities');
+  },
+
+  /**
+   * A model load was refused for lack of free memory
+   */
+  onInsufficientMemory: (callback: (event: InsufficientMemoryEvent) => void) => {
+    return listen<InsufficientMemoryEvent>('insufficient-memory', (event) => callback(event.payload));
+  },
+
+  /**
+   * The model that loaded before can't be opened: drive unplugged, file
+   * moved, or no permission
+   */
+  onModelUnavailable: (callback: (event: ModelUnavailable) => void) => {
+    return listen<ModelUnavailable>('model-unavailable', (event) => callback(event.payload));
+  },
+
+  /**
+   * The sidecar's stdout stopped parsing as JSON-RPC; replies may be lost
+   */
+  onProtocolDegraded: (callback: (event: ProtocolDegradedEvent) => void) => {
+    return listen<ProtocolDegradedEvent>('sidecar-protocol-degraded', (event) => callback(event.payload));
+  },
+
+  /**
+   * The sidecar speaks another major protocol version; no calls are made to it
+   */
+  onSidecarIncompatible: (callback: (event: SidecarIncompatibleEvent) => void) => {
+    return listen<SidecarIncompatibleEvent>('sidecar-incompatible', (event) => callback(event.payload));
+  },
+
+  /**
+   * Python interpreters found on this machine, for the interpreter chooser
+   */
+  listPythonInterpreters: async (): Promise<PythonInterpreter[]> => {
+    return await invoke('list_python_interpreters');
+  },
+
+  /**
+   * Test LLM connection
+   */
+  testLlmConnection: async (settings: Settings): Promise<string> => {
+    return await invoke('test_llm_connection', { settings });
+  },
+};
+
+export interface ModelInfo {
+  architecture: string | null;
+  name: string | null;
+  parameter_count: number | null;
+  quantization: string | null;
+  context_length: number | null;
+  source: 'sidecar' | 'gguf_header';
+}
+
+export interface MemoryStatus {
+  model: string;
+  needed_gb: number;
+  available_gb: number | null;
+  total_gb: number | null;
+  sufficient: boolean;
+  guard_enabled: boolean;
+}
+
+export interface Capabilities {
+  model_configured: boolean;
+  sidecar_running: boolean;
+  load_bids: boolean;
+  edit_bids: boolean;
+  export_excel: boolean;
+  process_script: boolean;
+  chat: boolean;
+  reason: string | null;
+}
+
+export interface ProtocolInfo {
+  protocol_version: number;
+  methods: string[];
+  sidecar_version: string | null;
+  /** e.g. 'blob_refs' when large values may go through files */
+  capabilities: string[];
+}
+
+export interface SidecarStatus {
+  running: boolean;
+  /** Running with the model loaded */
+  ready: boolean;
+  model_state: { state: 'NotLoaded' | 'Loading' } | { state: 'Loaded'; name: string; ctx: number };
+  /** Null until the sidecar has been asked */
+  protocol: ProtocolInfo | null;
+  /** Set while the model can't be opened */
+  model_unavailable: ModelUnavailable | null;
+}
+
+export type ModelUnavailableReason = 'volume_unmounted' | 'deleted' | 'permission_denied' | 'unreadable';
+
+export interface ModelUnavailable {
+  path: string;
+  reason: ModelUnavailableReason;
+  /** Mount point of the missing drive */
+  volume: string | null;
+  message: string;
+}
+
+export interface FoundModel {
+  path: string;
+  size_bytes: number;
+  known_good: boolean;
+}
+
+export interface SidecarIncompatibleEvent {
+  protocol_version: number;
+  app_protocol_version: number;
+  sidecar_version: string | null;
+  message: string;
+}
+
+export interface ProtocolDegradedEvent {
+  unparseable: number;
+  framed: boolean;
+}
+
+export interface InsufficientMemoryEvent {
+  model: string;
+  needed_gb: number;
+  available_gb: number;
+  /** Catalog models that would fit, smallest first */
+  smaller_models: string[];
+  suggestion: string;
+}
+
+export interface MethodMetrics {
+  method: string;
+  count: number;
+  errors: number;
+  total_ms: number;
+  mean_ms: number;
+  p50_ms: number;
+  p95_ms: number;
+  max_ms: number;
+  /** Waiting for the client and the sidecar's stdin */
+  mean_queue_wait_ms: number;
+  /** From the request going out until the response came back */
+  mean_round_trip_ms: number;
+  /** Reading large values back from blob files */
+  mean_decode_ms: number;
+}
+
+/** Load on the workers RPC calls run on */
+export interface PoolStats {
+  max_in_flight: number;
+  in_flight: number;
+  /** Calls waiting for a worker */
+  queue_depth: number;
+  peak_queue_depth: number;
+  /** Calls turned away because the queue was full */
+  rejected: number;
+}
+
+export interface RpcMetricsReport {
+  /** Slowest p95 first */
+  methods: MethodMetrics[];
+  pool: PoolStats;
+}
+
+/** A call running past twice its method's usual p95 */
+export interface SlowOperation {
+  method: string;
+  elapsed_ms: number;
+  p95_ms: number;
+}
+
+export interface JournalStats {
+  enabled: boolean;
+  bid_id: string | null;
+  path: string | null;
+  size_bytes: number;
+  entries: number;
+  revision: number;
+  compactions: number;
+  last_compacted_at: string | null;
+  write_errors: number;
+  threshold_bytes: number;
+}
+
+export type SelfTestStatus = 'pass' | 'fail' | 'skip';
+
+export type SettingsScreen = 'llm' | 'paths' | 'setup' | 'storage';
+
+export interface SelfTestCheck {
+  id: string;
+  status: SelfTestStatus;
+  message: string;
+  details: string[];
+  duration_ms: number;
+  remediation: { screen: SettingsScreen; hint: string } | null;
+}
+
+export type ErrorDomain = 'setup' | 'script' | 'chat' | 'bid' | 'sidecar';
+
+/** Most recent failure of a command in a domain */
+export interface LastError {
+  domain: ErrorDomain;
+  command: string;
+  message: string;
+  /** RFC 3339 */
+  at: string;
+}
+
+export interface EventSchema {
+  name: string;
+  description: string;
+  /** JSON Schema (draft 7) of the payload */
+  payload: Record<string, unknown>;
+  /** Raised by the sidecar and forwarded */
+  forwarded: boolean;
+}
+
+export interface SelfTestReport {
+  started_at: string;
+  duration_ms: number;
+  passed: boolean;
+  checks: SelfTestCheck[];
+}
+
+/**
+ * Sidecar diagnostics
+ */
+export const debugService = {
+  /**
+   * Per-method RPC latency, slowest p95 first, and the call queue
+   */
+  getRpcMetrics: async (): Promise<RpcMetricsReport> => {
+    return await invoke('get_rpc_metrics');
+  },
+
+  /**
+   * Clear the RPC latency counters
+   */
+  resetRpcMetrics: async (): Promise<void> => {
+    await invoke('reset_rpc_metrics');
+  },
+
+  /**
+   * A call is taking longer than usual; it's still running
+   */
+  onSlowOperation: (callback: (event: SlowOperation) => void) => {
+    return listen<SlowOperation>('slow-operation', (event) => callback(event.payload));
+  },
+
+  /**
+   * Size and compaction history of the bid's crash journal
+   */
+  getJournalStats: async (): Promise<JournalStats> => {
+    return await invoke('get_journal_stats');
+  },
+
+  /**
+   * Environment report to attach to an issue, secrets redacted; also saved
+   * to outputPath when given
+   */
+  generateDiagnosticsReport: async (outputPath?: string, includeSelfTest?: boolean): Promise<Record<string, unknown>> => {
+    return await invoke('generate_diagnostics_report', { outputPath, includeSelfTest });
+  },
+
+  /**
+   * Pass/fail/skip checklist of every integration point
+   */
+  runSelfTest: async (): Promise<SelfTestReport> => {
+    return await invoke('run_self_test');
+  },
+
+  /**
+   * The last error in each domain, newest first, for the error panel
+   */
+  getLastErrors: async (): Promise<LastError[]> => {
+    return await invoke('get_last_errors');
+  },
+
+  /**
+   * Clear the error panel, or only domain's entry
+   */
+  clearLastErrors: async (domain?: ErrorDomain): Promise<void> => {
+    await invoke('clear_last_errors', { domain });
+  },
+
+  /**
+   * Every backend event with a JSON Schema of its payload
+   */
+  getEventSchema: async (): Promise<EventSchema[]> => {
+    return await invoke('get_event_schema');
+  },
+};
+
+export interface PaletteArg {
+  name: string;
+  kind: 'string' | 'path' | 'number' | 'boolean' | 'object';
+  required: boolean;
+  description: string;
+}
+
+export interface PaletteCommand {
+  id: string;
+  title: string;
+  description: string;
+  args: PaletteArg[];
+  available: boolean;
+  unavailable_reason: string | null;
+}
+
+export interface PaletteResult {
+  ok: boolean;
+  message: string;
+  data: unknown;
+}
+
+/**
+ * Command palette (Cmd+K)
+ */
+export const paletteService = {
+  /**
+   * All palette actions; unavailable ones carry the reason to show
+   */
+  getCommandCatalog: async (): Promise<PaletteCommand[]> => {
+    return await invoke('get_command_catalog');
+  },
+
+  /**
+   * Run a palette action; failures resolve with ok: false
+   */
+  invoke: async (id: string, args: Record<string, unknown> | null = null): Promise<PaletteResult> => {
+    return await invoke('invoke_palette_command', { id, args });
   },
 };
@@ -1,5 +1,22 @@
 import { create } from 'zustand';
 
+export interface Attachment {
+  id: string;
+  filename: string;
+  width: number;
+  height: number;
+  added_at: string;
+}
+
+/** One department's share of a shot's work */
+export interface TaskLine {
+  department: string;
+  hours: number;
+  /** Used instead of the shot's rate_per_hour */
+  rate_override?: number | null;
+  note?: string;
+}
+
 export interface Shot {
   id: string;
   scene_number: string;
@@ -12,6 +29,29 @@ export interface Shot {
   contingency_percent: number;
   overhead_percent: number;
   final_price?: number;
+  frame_in?: number;
+  frame_out?: number;
+  frame_count?: number;
+  fps?: number;
+  attachments?: Attachment[];
+  provisional?: boolean;
+  price_locked?: boolean;
+  /** Position in the bid; change it with reorderShots/moveShot */
+  sort_index?: number;
+  /** When non-empty, hours and cost are derived from these and read-only */
+  tasks?: TaskLine[];
+  /** Workflow tags, lowercase; change them with addTags/removeTags */
+  tags?: string[];
+  /** How sure extraction was of this shot, 0 to 1 */
+  confidence?: number | null;
+  /** Reasons to double-check the shot, e.g. "duplicate_description" */
+  extraction_flags?: string[];
+  /** Set by markReviewed; takes the shot out of the review queue */
+  review?: { reviewed_by: string | null; reviewed_at: string } | null;
+  /** 'table' when estimateMissingHours filled the hours; cleared on a manual edit */
+  estimated_by?: 'table' | null;
+  /** Share of the bid's lump-sum adjustments, already in final_price */
+  adjustment?: number;
 }
 
 interface BidState {
@@ -21,6 +61,8 @@ interface BidState {
   updateShot: (id: string, updates: Partial<Shot>) => void;
   deleteShot: (id: string) => void;
   addShot: (shot: Shot) => void;
+  upsertShot: (shot: Shot) => void;
+  removeShots: (ids: string[]) => void;
   setCurrentScript: (path: string | null) => void;
   clear: () => void;
 }
@@ -48,6 +90,18 @@ export const useBidStore = create<BidState>((set) => ({
       shots: [...state.shots, shot],
     })),
 
+  upsertShot: (shot) =>
+    set((state) => ({
+      shots: state.shots.some((s) => s.id === shot.id)
+        ? state.shots.map((s) => (s.id === shot.id ? shot : s))
+        : [...state.shots, shot],
+    })),
+
+  removeShots: (ids) =>
+    set((state) => ({
+      shots: state.shots.filter((shot) => !ids.includes(shot.id)),
+    })),
+
   setCurrentScript: (path) => set({ currentScript: path }),
 
   clear: () => set({ shots: [], currentScript: null }),
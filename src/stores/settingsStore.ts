@@ -6,6 +6,11 @@ export interface LlmSettings {
   context_size: number;
   temperature: number;
   max_tokens: number;
+  model_path?: string | null;
+  model_sha256?: string | null;
+  skip_memory_check?: boolean;
+  /** Paths the model last loaded from, newest first */
+  known_model_paths?: string[];
 }
 
 export interface PathSettings {
@@ -13,18 +18,61 @@ export interface PathSettings {
   scripts_dir: string;
   templates_dir: string;
   output_dir: string;
+  // Where setup puts models; empty for ~/VFX-BIDDING/Models
+  model_dir: string;
 }
 
 export interface UiSettings {
   theme: 'light' | 'dark';
   auto_save: boolean;
   show_console: boolean;
+  chat_overlap?: 'reject' | 'queue';
+  /** Apply single-shot complexity changes from chat without confirming */
+  chat_auto_apply_safe_actions?: boolean;
+  /** Keep a local history of processing runs for time estimates */
+  record_processing_history?: boolean;
+}
+
+export interface SidecarSettings {
+  /** RPC timeout in seconds, keyed by method name */
+  rpc_timeouts: Record<string, number>;
+  default_rpc_timeout: number;
+  /** Extra variables for the sidecar process, e.g. proxies or LLAMA_METAL=1 */
+  extra_env?: Record<string, string>;
+  /** Pass the app's whole environment to the sidecar (debugging only) */
+  inherit_env?: boolean;
+  /** Days a bid workspace may sit unused before pruning is offered; 0 keeps them */
+  workspace_retention_days?: number;
+  /** RPC values larger than this many bytes go through files (min 4096) */
+  blob_threshold_bytes?: number;
+  /** rpc_server.py to run instead of the bundled one (VFX_SIDECAR_PATH wins) */
+  script_path?: string | null;
+  /** Calls slower than this are logged with where their time went; 0 logs none */
+  slow_call_warn_ms?: number;
+  /** Clear the RPC latency counters whenever the sidecar restarts */
+  reset_metrics_on_restart?: boolean;
+  /** Reuse bid_query answers this long until the bid changes; 0 only shares concurrent ones */
+  query_cache_ms?: number;
+  /** RPC calls running at once; more wait their turn (default 4) */
+  max_in_flight_rpcs?: number;
+}
+
+export interface LocaleSettings {
+  /** BCP 47 tag such as "en-US" or "de-DE" */
+  locale: string;
+  currency_symbol_position: 'before' | 'after';
+  thousands_separator: string;
+  decimal_separator: string;
+  /** Write CSV numbers with the separators above instead of plain 1234.56 */
+  export_locale_numbers?: boolean;
 }
 
 export interface Settings {
   llm: LlmSettings;
   paths: PathSettings;
   ui: UiSettings;
+  sidecar?: SidecarSettings;
+  locale?: LocaleSettings;
 }
 
 interface SettingsState {
@@ -47,6 +95,7 @@ const defaultSettings: Settings = {
     scripts_dir: '',
     templates_dir: '',
     output_dir: '',
+    model_dir: '',
   },
   ui: {
     theme: 'dark',